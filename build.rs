@@ -1,4 +1,4 @@
-use std::{path::Path, process::Command};
+use std::{fs, path::Path, process::Command};
 
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
@@ -10,6 +10,16 @@ fn commit_info() {
     if !Path::new(".git").exists() {
         return;
     }
+
+    // Embed the new commit when the checked out branch or commit changes
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    if let Some(head) = fs::read_to_string(".git/HEAD")
+        .ok()
+        .and_then(|head| head.strip_prefix("ref: ").map(|r| r.trim().to_string()))
+    {
+        println!("cargo:rerun-if-changed=.git/{head}");
+    }
+
     let output = match Command::new("git")
         .arg("log")
         .arg("-1")
@@ -0,0 +1,29 @@
+#!/bin/bash
+set -euo pipefail
+
+function stop_minio() {
+    echo stopping minio container
+    docker stop minio-test &> /dev/null || true
+    docker rm minio-test &> /dev/null || true
+}
+
+trap 'stop_minio' EXIT
+
+stop_minio
+
+echo starting minio container
+docker run -d --name minio-test -e MINIO_ROOT_USER=minioadmin -e MINIO_ROOT_PASSWORD=minioadmin -p 9000:9000 minio/minio server /data
+
+while ! curl -sf http://localhost:9000/minio/health/ready; do
+    echo waiting for minio to accept connections
+    sleep 1
+done
+
+echo creating bucket
+docker run --rm --network host --entrypoint sh minio/mc -c \
+    "mc alias set local http://localhost:9000 minioadmin minioadmin && mc mb --ignore-existing local/warg-test"
+
+echo running tests
+AWS_ACCESS_KEY_ID=minioadmin AWS_SECRET_ACCESS_KEY=minioadmin \
+    WARG_S3_ENDPOINT=http://localhost:9000 WARG_S3_BUCKET=warg-test \
+    cargo test --features s3 --test s3 $@
@@ -0,0 +1,342 @@
+//! Types relating to the administration API.
+
+use super::webhook::WebhookPayload;
+use serde::{Deserialize, Serialize, Serializer};
+use std::{
+    borrow::Cow,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+use warg_crypto::{hash::AnyHash, signing::KeyID};
+use warg_protocol::registry::{LogId, PackageName, RecordId, RegistryLen};
+
+/// Represents a mutating operation recorded in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditOperation {
+    /// A package record was submitted for publishing.
+    RecordSubmitted,
+    /// Content for a package record was uploaded.
+    ContentUploaded,
+    /// A package record was processed by the transparency service.
+    RecordProcessed,
+    /// A new checkpoint was issued by the transparency service.
+    CheckpointIssued,
+    /// A package was suppressed by the operator.
+    PackageSuppressed,
+    /// The suppression of a package was lifted by the operator.
+    PackageUnsuppressed,
+    /// A record signed by an operator key was published to the operator log.
+    OperatorRecordPublished,
+    /// A pending package record was rejected by the operator.
+    RecordRejected,
+}
+
+/// Represents the outcome of an audited operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditOutcome {
+    /// The operation succeeded.
+    Success,
+    /// The operation failed or was rejected.
+    Failure,
+}
+
+/// Represents an entry in the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// When the operation occurred, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The operation that was performed.
+    pub operation: AuditOperation,
+    /// The outcome of the operation.
+    pub outcome: AuditOutcome,
+    /// The reason for a failed outcome or for rejecting a record.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// The log the operation applied to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_id: Option<LogId>,
+    /// The name of the package the operation applied to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<PackageName>,
+    /// The record the operation applied to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_id: Option<RecordId>,
+    /// The id of the key that signed the record.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<KeyID>,
+    /// The digest of the content the operation applied to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<AnyHash>,
+    /// The log length of an issued checkpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_length: Option<RegistryLen>,
+    /// The address of the client that made the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// The identifier of the request that performed the operation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl AuditEntry {
+    /// Creates a new successful audit entry for the given operation timestamped now.
+    pub fn new(operation: AuditOperation) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            operation,
+            outcome: AuditOutcome::Success,
+            reason: None,
+            log_id: None,
+            package: None,
+            record_id: None,
+            key_id: None,
+            content: None,
+            log_length: None,
+            source: None,
+            request_id: None,
+        }
+    }
+
+    /// Marks the entry as failed with the given reason.
+    pub fn failed(mut self, reason: impl ToString) -> Self {
+        self.outcome = AuditOutcome::Failure;
+        self.reason = Some(reason.to_string());
+        self
+    }
+}
+
+/// Represents the query parameters of a get audit entries request.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntriesQuery {
+    /// Only return entries for the given package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<PackageName>,
+    /// Only return entries for records signed by the given key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<KeyID>,
+    /// The maximum number of entries to return.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+/// Represents a response to a get audit entries request.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntriesResponse {
+    /// The most recent matching entries, oldest first.
+    pub entries: Vec<AuditEntry>,
+}
+
+/// Represents a response to a get submission queue request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionQueueResponse {
+    /// The maximum number of records that may be waiting to be processed.
+    pub capacity: u64,
+    /// The number of records currently waiting to be processed.
+    pub depth: u64,
+    /// The number of records submitted for processing since the server started.
+    pub submitted: u64,
+    /// The number of submissions rejected because the queue was full since
+    /// the server started.
+    pub rejected: u64,
+}
+
+/// Represents a request to suppress a package.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuppressPackageRequest {
+    /// The reason the package is suppressed, reported to clients requesting it.
+    pub reason: String,
+}
+
+/// Represents a response to a suppress or unsuppress package request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuppressionResponse {
+    /// The identifier of the operator record that was published.
+    pub record_id: RecordId,
+}
+
+/// Represents a request to reject a pending package record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectRecordRequest {
+    /// The reason the record is rejected, reported to clients waiting on it.
+    pub reason: String,
+}
+
+/// Represents a response to a reject package record request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectRecordResponse {
+    /// The identifier of the record that was rejected.
+    pub record_id: RecordId,
+}
+
+/// Represents a request to prune rejected records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneRecordsRequest {
+    /// Records rejected before this time, in seconds since the Unix epoch,
+    /// are pruned.
+    pub before: u64,
+}
+
+/// Represents a response to a prune rejected records request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneRecordsResponse {
+    /// The number of records that were removed.
+    pub removed: u64,
+}
+
+/// Represents a request to compact published records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactRecordsRequest {
+    /// The number of most recent published records of each package log to
+    /// keep in full.
+    pub keep_last: u64,
+}
+
+/// Represents a response to a compact published records request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactRecordsResponse {
+    /// The number of records that were compacted.
+    pub compacted: u64,
+}
+
+/// Represents a response to a collect unreferenced content request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentGcResponse {
+    /// The number of content files that were removed.
+    pub removed: u64,
+}
+
+/// Represents the status of a webhook delivery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WebhookDeliveryStatus {
+    /// The delivery is being attempted.
+    Pending,
+    /// The endpoint accepted the delivery.
+    Delivered,
+    /// Every attempt of the delivery failed.
+    Failed,
+}
+
+/// Represents a delivery of a webhook payload to an endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDelivery {
+    /// The identifier of the delivery.
+    pub id: String,
+    /// The URL of the endpoint.
+    pub url: String,
+    /// The status of the delivery.
+    pub status: WebhookDeliveryStatus,
+    /// The number of times the delivery was attempted.
+    pub attempts: u32,
+    /// When the delivery was created, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The error of the last failed attempt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// The delivered payload.
+    pub payload: WebhookPayload,
+}
+
+/// Represents the query parameters of a get webhook deliveries request.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveriesQuery {
+    /// Only return deliveries with the given status.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<WebhookDeliveryStatus>,
+    /// The maximum number of deliveries to return.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+/// Represents a response to a get webhook deliveries request.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveriesResponse {
+    /// The most recent matching deliveries, oldest first.
+    pub deliveries: Vec<WebhookDelivery>,
+}
+
+/// Represents an administration API error.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum AdminError {
+    /// The request was not authorized.
+    #[error("the request is not authorized")]
+    Unauthorized,
+    /// An error with a message occurred.
+    #[error("{message}")]
+    Message {
+        /// The HTTP status code.
+        status: u16,
+        /// The error message
+        message: String,
+    },
+}
+
+impl AdminError {
+    /// Returns the HTTP status code of the error.
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::Unauthorized => 401,
+            Self::Message { status, .. } => *status,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged, rename_all = "camelCase")]
+enum RawError<'a> {
+    Message { status: u16, message: Cow<'a, str> },
+}
+
+impl Serialize for AdminError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Unauthorized => RawError::Message {
+                status: self.status(),
+                message: Cow::Owned(self.to_string()),
+            }
+            .serialize(serializer),
+            Self::Message { status, message } => RawError::Message {
+                status: *status,
+                message: Cow::Borrowed(message),
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AdminError {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match RawError::deserialize(deserializer)? {
+            RawError::Message { status: 401, .. } => Ok(Self::Unauthorized),
+            RawError::Message { status, message } => Ok(Self::Message {
+                status,
+                message: message.into_owned(),
+            }),
+        }
+    }
+}
@@ -7,8 +7,8 @@ use std::borrow::Cow;
 use thiserror::Error;
 use warg_crypto::hash::AnyHash;
 use warg_protocol::{
-    registry::{LogId, PackageName, RegistryLen},
-    PublishedProtoEnvelopeBody,
+    registry::{LogId, PackageName, RegistryLen, TimestampedCheckpoint},
+    PublishedProtoEnvelopeBody, SerdeEnvelope,
 };
 
 /// Wraps the PublishedProtoEnvelopeBody with a fetch token.
@@ -31,6 +31,12 @@ pub struct FetchLogsRequest<'a> {
     /// The limit for the number of operator and package records to fetch.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub limit: Option<u16>,
+    /// The limit for the total number of records to fetch across all logs.
+    ///
+    /// Logs that do not fit in the limit are omitted from the response and
+    /// `more` is set so they may be fetched with a subsequent request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_limit: Option<u32>,
     /// The last known operator record fetch token.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub operator: Option<Cow<'a, str>>,
@@ -54,6 +60,87 @@ pub struct FetchLogsResponse {
     pub packages: IndexMap<LogId, Vec<PublishedRecord>>,
 }
 
+/// The media type of a streamed fetch logs response.
+///
+/// A fetch logs request accepting this media type is answered with
+/// newline-delimited JSON, each line being a [`FetchLogsStreamItem`].
+pub const FETCH_LOGS_STREAM_MEDIA_TYPE: &str = "application/x-ndjson";
+
+/// Represents a line of a streamed fetch logs response.
+///
+/// The records of the operator log are followed by the records of each
+/// requested package log, in request order; the response ends with either
+/// an `end` or an `error` line.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FetchLogsStreamItem {
+    /// A record of the operator log.
+    Operator {
+        /// The operator record.
+        record: PublishedRecord,
+    },
+    /// A record of a package log.
+    #[serde(rename_all = "camelCase")]
+    Package {
+        /// The package log the record belongs to.
+        log_id: LogId,
+        /// The package record.
+        record: PublishedRecord,
+    },
+    /// The end of the response.
+    End {
+        /// Whether there are more records to fetch.
+        more: bool,
+    },
+    /// An error that occurred after the response started.
+    Error {
+        /// The error.
+        error: FetchError,
+    },
+}
+
+/// Represents the query parameters of a fetch checkpoint request.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchCheckpointQuery {
+    /// The identifier of the last known checkpoint.
+    ///
+    /// If set, the request waits for a checkpoint with a different
+    /// identifier to be issued before responding.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<AnyHash>,
+    /// The number of seconds to wait for a new checkpoint (defaults to 30).
+    ///
+    /// The latest checkpoint is returned when the timeout elapses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+/// Represents the query parameters of a list checkpoints request.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointsQuery {
+    /// The identifier of the checkpoint after which to list checkpoints.
+    ///
+    /// If not set, checkpoints are listed from the first checkpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<AnyHash>,
+    /// The maximum number of checkpoints to list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+/// Represents a list checkpoints response.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointsResponse {
+    /// The checkpoints, in the order they were issued.
+    pub checkpoints: Vec<SerdeEnvelope<TimestampedCheckpoint>>,
+    /// Whether there are more checkpoints to list.
+    #[serde(default)]
+    pub more: bool,
+}
+
 /// Represents a fetch package names request.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -79,6 +166,9 @@ pub enum FetchError {
     /// The provided checkpoint was not found.
     #[error("checkpoint log length `{0}` was not found")]
     CheckpointNotFound(RegistryLen),
+    /// The checkpoint with the provided identifier was not found.
+    #[error("checkpoint `{0}` was not found")]
+    CheckpointIdNotFound(AnyHash),
     /// The provided log was not found.
     #[error("log `{0}` was not found")]
     LogNotFound(LogId),
@@ -99,7 +189,10 @@ impl FetchError {
     /// Returns the HTTP status code of the error.
     pub fn status(&self) -> u16 {
         match self {
-            Self::CheckpointNotFound(_) | Self::LogNotFound(_) | Self::FetchTokenNotFound(_) => 404,
+            Self::CheckpointNotFound(_)
+            | Self::CheckpointIdNotFound(_)
+            | Self::LogNotFound(_)
+            | Self::FetchTokenNotFound(_) => 404,
             Self::Message { status, .. } => *status,
         }
     }
@@ -109,6 +202,7 @@ impl FetchError {
 #[serde(rename_all = "camelCase")]
 enum EntityType {
     LogLength,
+    Checkpoint,
     Log,
     FetchToken,
 }
@@ -147,6 +241,12 @@ impl Serialize for FetchError {
                 id: *log_length,
             }
             .serialize(serializer),
+            Self::CheckpointIdNotFound(checkpoint_id) => RawError::NotFound {
+                status: Status::<404>,
+                ty: EntityType::Checkpoint,
+                id: Cow::Borrowed(checkpoint_id),
+            }
+            .serialize(serializer),
             Self::LogNotFound(log_id) => RawError::NotFound {
                 status: Status::<404>,
                 ty: EntityType::Log,
@@ -183,6 +283,14 @@ impl<'de> Deserialize<'de> for FetchError {
                         })?
                         .into(),
                 )),
+                EntityType::Checkpoint => Ok(Self::CheckpointIdNotFound(
+                    id.parse::<AnyHash>().map_err(|_| {
+                        serde::de::Error::invalid_value(
+                            Unexpected::Str(&id),
+                            &"a valid checkpoint id",
+                        )
+                    })?,
+                )),
                 EntityType::FetchToken => Ok(Self::FetchTokenNotFound(id.into_owned())),
                 _ => Err(serde::de::Error::invalid_value(
                     Unexpected::Str(&id),
@@ -1,15 +1,24 @@
 //! Types representing v1 of the Warg REST API.
 
+pub mod admin;
 pub mod content;
 pub mod fetch;
 pub mod ledger;
 pub mod monitor;
+pub mod operator;
 pub mod package;
 pub mod paths;
 pub mod proof;
+pub mod registry;
+pub mod webhook;
 
 use serde::{Deserialize, Serialize};
 
+/// The version of the Warg REST API described by this module.
+///
+/// The registry serves the API under a path of the same name.
+pub const API_VERSION: &str = "v1";
+
 /// The HTTP request and response header name that specifies the registry domain whose data is the
 /// subject of the request. This header is only expected to be used if referring to a different
 /// registry than the host registry.
@@ -17,6 +26,12 @@ pub const REGISTRY_HEADER_NAME: &str = "warg-registry";
 /// The HTTP response header name that specifies that the client should
 /// try another registry
 pub const REGISTRY_HINT_HEADER_NAME: &str = "warg-registry-hint";
+/// The HTTP request header name that specifies a key identifying a record
+/// submission, allowing the submission to be safely retried.
+pub const IDEMPOTENCY_KEY_HEADER_NAME: &str = "idempotency-key";
+/// The HTTP request and response header name that specifies the ID of a
+/// request; the registry assigns an ID to requests without one.
+pub const REQUEST_ID_HEADER_NAME: &str = "x-request-id";
 
 /// Represents the supported kinds of content sources.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -0,0 +1,112 @@
+//! Types relating to the operator API.
+
+use crate::Status;
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Cow;
+use thiserror::Error;
+use warg_protocol::{
+    registry::{RecordId, RegistryIndex},
+    ProtoEnvelopeBody,
+};
+
+/// Represents a request to publish a record to the operator log.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishOperatorRecordRequest<'a> {
+    /// The operator record being published.
+    pub record: Cow<'a, ProtoEnvelopeBody>,
+}
+
+/// Represents a response to a publish operator record request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatorRecordResponse {
+    /// The identifier of the published record.
+    pub record_id: RecordId,
+    /// The index of the record in the registry log.
+    pub registry_index: RegistryIndex,
+}
+
+/// Represents an operator API error.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum OperatorError {
+    /// The record does not follow the current head of the operator log.
+    #[error("the record does not follow the head of the operator log; fetch the operator log and try again")]
+    HeadMismatch,
+    /// The record was rejected by the operator log validator.
+    #[error("{0}")]
+    Rejection(String),
+    /// An error with a message occurred.
+    #[error("{message}")]
+    Message {
+        /// The HTTP status code.
+        status: u16,
+        /// The error message
+        message: String,
+    },
+}
+
+impl OperatorError {
+    /// Returns the HTTP status code of the error.
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::HeadMismatch => 409,
+            Self::Rejection(_) => 422,
+            Self::Message { status, .. } => *status,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged, rename_all = "camelCase")]
+enum RawError<'a> {
+    HeadMismatch {
+        status: Status<409>,
+    },
+    Rejection {
+        status: Status<422>,
+        message: Cow<'a, str>,
+    },
+    Message {
+        status: u16,
+        message: Cow<'a, str>,
+    },
+}
+
+impl Serialize for OperatorError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::HeadMismatch => RawError::HeadMismatch {
+                status: Status::<409>,
+            }
+            .serialize(serializer),
+            Self::Rejection(message) => RawError::Rejection {
+                status: Status::<422>,
+                message: Cow::Borrowed(message),
+            }
+            .serialize(serializer),
+            Self::Message { status, message } => RawError::Message {
+                status: *status,
+                message: Cow::Borrowed(message),
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OperatorError {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match RawError::deserialize(deserializer)? {
+            RawError::HeadMismatch { .. } => Ok(Self::HeadMismatch),
+            RawError::Rejection { status: _, message } => Ok(Self::Rejection(message.into_owned())),
+            RawError::Message { status, message } => Ok(Self::Message {
+                status,
+                message: message.into_owned(),
+            }),
+        }
+    }
+}
@@ -6,10 +6,11 @@ use indexmap::IndexMap;
 use serde::{de::Unexpected, Deserialize, Serialize, Serializer};
 use std::borrow::Cow;
 use thiserror::Error;
-use warg_crypto::hash::AnyHash;
+use warg_crypto::{hash::AnyHash, signing::KeyID};
 use warg_protocol::{
-    registry::{LogId, PackageName, RecordId, RegistryIndex},
-    ProtoEnvelopeBody,
+    package::{Permission, ReleaseMetadata},
+    registry::{LogId, PackageName, RecordId, RegistryIndex, RegistryLen, TimestampedCheckpoint},
+    ProtoEnvelopeBody, SerdeEnvelope, Version,
 };
 
 /// Represents the supported kinds of content upload endpoints.
@@ -62,6 +63,11 @@ pub struct PackageRecord {
     /// The current state of the package.
     #[serde(flatten)]
     pub state: PackageRecordState,
+    /// The status of the package log the record belongs to.
+    ///
+    /// This is only provided while the record is processing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_status: Option<PackageLogStatus>,
 }
 
 impl PackageRecord {
@@ -76,12 +82,41 @@ impl PackageRecord {
     }
 }
 
+/// Represents a response to a request for the content still missing from a
+/// package record.
+///
+/// Content the registry already has, such as content uploaded for another
+/// package, is never reported as missing; the content is empty once the
+/// record is no longer sourcing content.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingContentResponse {
+    /// The identifier of the package record.
+    pub record_id: RecordId,
+    /// The content the record is missing, keyed by digest.
+    pub missing_content: IndexMap<AnyHash, MissingContent>,
+}
+
+/// Represents the progress of a package log toward the next checkpoint.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageLogStatus {
+    /// The identifier of the latest validated record in the log.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head: Option<RecordId>,
+    /// The number of validated records in the log not yet included in a checkpoint.
+    pub pending_records: u64,
+    /// The timestamp of the latest checkpoint, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint_timestamp: Option<u64>,
+}
+
 /// Represents a package record in one of the following states:
 /// * `sourcing` - The record is sourcing content.
 /// * `processing` - The record is being processed.
 /// * `rejected` - The record was rejected.
 /// * `published` - The record was published to the log.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(tag = "state", rename_all = "camelCase")]
 #[allow(clippy::large_enum_variant)]
 pub enum PackageRecordState {
@@ -108,6 +143,362 @@ pub enum PackageRecordState {
     },
 }
 
+/// Represents a query of the package records API.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageRecordsQuery {
+    /// Only return records after the given record.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<RecordId>,
+    /// The maximum number of records to return.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+/// Represents an entry of a package record in a package's record history.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[allow(clippy::large_enum_variant)]
+pub enum PackageRecordEntry {
+    /// The package log was initialized.
+    #[serde(rename_all = "camelCase")]
+    Init {
+        /// The hash algorithm of the package log.
+        hash_algorithm: String,
+        /// The id of the initial key of the package log.
+        key_id: KeyID,
+    },
+    /// Permissions were granted to a key.
+    #[serde(rename_all = "camelCase")]
+    Grant {
+        /// The id of the key granted the permissions.
+        key_id: KeyID,
+        /// The permissions granted.
+        permissions: Vec<Permission>,
+    },
+    /// Permissions were revoked from a key.
+    #[serde(rename_all = "camelCase")]
+    Revoke {
+        /// The id of the key the permissions were revoked from.
+        key_id: KeyID,
+        /// The permissions revoked.
+        permissions: Vec<Permission>,
+    },
+    /// A key was revoked.
+    #[serde(rename_all = "camelCase")]
+    RevokeKey {
+        /// The id of the revoked key.
+        key_id: KeyID,
+        /// The time the key is revoked as of, in seconds since the Unix epoch.
+        since: u64,
+    },
+    /// A version of the package was released.
+    #[serde(rename_all = "camelCase")]
+    Release {
+        /// The released version.
+        version: Version,
+        /// The digest of the released content.
+        content: AnyHash,
+        /// The digests of the release's artifacts other than its component,
+        /// by name.
+        #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+        artifacts: IndexMap<String, AnyHash>,
+        /// Whether the release has since been yanked.
+        #[serde(default)]
+        yanked: bool,
+        /// The metadata included with the release.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metadata: Option<ReleaseMetadata>,
+    },
+    /// A release of the package was yanked.
+    #[serde(rename_all = "camelCase")]
+    Yank {
+        /// The yanked version.
+        version: Version,
+    },
+    /// An entry not known to the registry API.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Represents a published record in a package's record history.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageRecordSummary {
+    /// The identifier of the package record.
+    pub record_id: RecordId,
+    /// The current state of the record.
+    #[serde(flatten)]
+    pub state: PackageRecordState,
+    /// The log length of the first checkpoint that includes the record.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint_log_length: Option<RegistryLen>,
+    /// The timestamp of the record, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The id of the key that signed the record.
+    pub key_id: KeyID,
+    /// The entries of the record.
+    pub entries: Vec<PackageRecordEntry>,
+}
+
+/// Represents a response to a package records request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageRecordsResponse {
+    /// The records of the requested page, in log order.
+    pub records: Vec<PackageRecordSummary>,
+    /// The record to request the next page since.
+    ///
+    /// This is `None` if there are no more records in the package log.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<RecordId>,
+}
+
+/// Represents a dependency of a package release on another package.
+///
+/// Dependencies are derived by the registry from the imports of the
+/// released component.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageDependency {
+    /// The name of the package being depended on.
+    pub name: PackageName,
+    /// The component import the dependency was derived from.
+    pub import: String,
+}
+
+/// Represents a response to a package dependencies request.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageDependenciesResponse {
+    /// The version of the package the dependencies are for.
+    pub version: Version,
+    /// The dependencies of the package release.
+    pub dependencies: Vec<PackageDependency>,
+}
+
+/// Represents a package release that depends on another package.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageDependent {
+    /// The name of the dependent package.
+    pub name: PackageName,
+    /// The version of the dependent package release.
+    pub version: Version,
+}
+
+/// Represents a response to a package dependents (reverse dependencies) request.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageDependentsResponse {
+    /// The package releases that depend on the package.
+    pub dependents: Vec<PackageDependent>,
+}
+
+/// Represents a response to a package release resolution request.
+///
+/// The release is selected by the registry from its own validation of the
+/// package log. Clients that verify the log should treat the response only
+/// as a hint and validate the package log themselves before trusting it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvePackageResponse {
+    /// The version of the selected release.
+    pub version: Version,
+    /// The content digest of the selected release.
+    pub content: AnyHash,
+    /// The id of the record that published the release.
+    pub record_id: RecordId,
+    /// The index of the record in the registry log.
+    pub registry_index: RegistryIndex,
+    /// The latest checkpoint that includes the record.
+    ///
+    /// This is `None` if the record has not yet been included in a checkpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<SerdeEnvelope<TimestampedCheckpoint>>,
+    /// A reminder that the resolution has not been verified by the client.
+    pub note: String,
+}
+
+/// Represents the number of downloads of a package or release.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadCounts {
+    /// The number of downloads since statistics were first collected.
+    pub total: u64,
+    /// The number of downloads in the last day.
+    pub last_day: u64,
+    /// The number of downloads in the last 7 days.
+    pub last_week: u64,
+    /// The number of downloads in the last 30 days.
+    pub last_month: u64,
+}
+
+impl std::ops::AddAssign for DownloadCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.total += other.total;
+        self.last_day += other.last_day;
+        self.last_week += other.last_week;
+        self.last_month += other.last_month;
+    }
+}
+
+/// Represents a response to a package download statistics request.
+///
+/// Downloads are counted by the registry when content is served or a release
+/// is resolved; counts are flushed periodically, so recent downloads may not
+/// be reported yet.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageStatsResponse {
+    /// The name of the package.
+    pub name: PackageName,
+    /// The downloads of all releases of the package.
+    pub downloads: DownloadCounts,
+    /// The downloads of each release of the package.
+    pub versions: IndexMap<Version, DownloadCounts>,
+}
+
+/// Represents a query of the list packages API.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListPackagesQuery {
+    /// The number of packages to skip.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    /// The maximum number of packages to return.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+/// Represents a package published to the registry.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageSummary {
+    /// The name of the package.
+    pub name: PackageName,
+    /// The identifier of the package log.
+    pub log_id: LogId,
+    /// The number of validated records in the package log.
+    pub record_count: u64,
+    /// The latest version of the package that has not been yanked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest_version: Option<Version>,
+    /// The metadata included with the latest version of the package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ReleaseMetadata>,
+    /// The timestamp of the latest validated record, in seconds since the Unix epoch.
+    pub updated: u64,
+}
+
+/// Represents a response to a list packages request.
+///
+/// Packages are listed in the order they were first published.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListPackagesResponse {
+    /// The packages of the requested page.
+    pub packages: Vec<PackageSummary>,
+}
+
+/// The note included in every [`ResolvePackageResponse`].
+pub const RESOLVE_PACKAGE_NOTE: &str = "this resolution is computed by the registry and is not \
+    verified; clients performing full verification must validate the package log themselves";
+
+/// Represents the `Content-Range` header of a resumable content upload request.
+///
+/// The registry responds to an upload that has not yet received all of the
+/// content with `202 Accepted` and a `Range` header of the bytes received so
+/// far (see [`received_range`]); an upload that does not start at the end of
+/// the received bytes is answered with `416 Range Not Satisfiable` and the
+/// same header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UploadContentRange {
+    /// Requests the bytes received without uploading any (`bytes */<total>`).
+    Status {
+        /// The length of the content.
+        total: u64,
+    },
+    /// Uploads the inclusive range of bytes of the content (`bytes <start>-<end>/<total>`).
+    Bytes {
+        /// The offset of the first byte uploaded.
+        start: u64,
+        /// The offset of the last byte uploaded.
+        end: u64,
+        /// The length of the content.
+        total: u64,
+    },
+}
+
+impl UploadContentRange {
+    /// Gets the length of the content being uploaded.
+    pub fn total(&self) -> u64 {
+        match self {
+            Self::Status { total } | Self::Bytes { total, .. } => *total,
+        }
+    }
+}
+
+impl std::fmt::Display for UploadContentRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Status { total } => write!(f, "bytes */{total}"),
+            Self::Bytes { start, end, total } => write!(f, "bytes {start}-{end}/{total}"),
+        }
+    }
+}
+
+impl std::str::FromStr for UploadContentRange {
+    type Err = InvalidContentRange;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidContentRange(s.to_string());
+        let (range, total) = s
+            .strip_prefix("bytes ")
+            .and_then(|r| r.split_once('/'))
+            .ok_or_else(invalid)?;
+        let total = total.parse().map_err(|_| invalid())?;
+
+        if range == "*" {
+            return Ok(Self::Status { total });
+        }
+
+        let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+        let start = start.parse().map_err(|_| invalid())?;
+        let end = end.parse().map_err(|_| invalid())?;
+        if start > end || end >= total {
+            return Err(invalid());
+        }
+
+        Ok(Self::Bytes { start, end, total })
+    }
+}
+
+/// The error returned when an upload content range fails to parse.
+#[derive(Debug, Error)]
+#[error("invalid content range `{0}`")]
+pub struct InvalidContentRange(String);
+
+/// Formats the `Range` header value for the given number of received bytes.
+///
+/// Returns `None` if no bytes have been received.
+pub fn received_range(received: u64) -> Option<String> {
+    received
+        .checked_sub(1)
+        .map(|last| format!("bytes=0-{last}"))
+}
+
+/// Parses a `Range` header value of received bytes.
+///
+/// Returns the number of bytes received, or `None` if the value is invalid.
+pub fn parse_received_range(value: &str) -> Option<u64> {
+    value
+        .strip_prefix("bytes=0-")?
+        .parse::<u64>()
+        .ok()?
+        .checked_add(1)
+}
+
 /// Represents a package API error.
 #[non_exhaustive]
 #[derive(Debug, Error)]
@@ -142,6 +533,15 @@ pub enum PackageError {
     /// The package was rejected by the registry.
     #[error("the package was rejected by the registry: {0}")]
     Rejection(String),
+    /// The package was suppressed by the registry operator.
+    #[error("the package was suppressed by the registry operator: {0}")]
+    Suppressed(String),
+    /// Too many records were submitted by the signing key.
+    #[error("too many records were submitted; retry after {retry_after} second(s)")]
+    TooManyRequests {
+        /// The number of seconds to wait before retrying.
+        retry_after: u64,
+    },
     /// An error with a message occurred.
     #[error("{message}")]
     Message {
@@ -164,7 +564,9 @@ impl PackageError {
             | Self::NamespaceConflict(_)
             | Self::PackageNameConflict(_) => 409,
             Self::RecordNotSourcing => 405,
+            Self::Suppressed(_) => 410,
             Self::Rejection(_) => 422,
+            Self::TooManyRequests { .. } => 429,
             Self::NotSupported(_) => 501,
             Self::Message { status, .. } => *status,
         }
@@ -215,6 +617,15 @@ where
         status: Status<501>,
         message: Cow<'a, str>,
     },
+    Suppressed {
+        status: Status<410>,
+        reason: Cow<'a, str>,
+    },
+    TooManyRequests {
+        status: Status<429>,
+        #[serde(rename = "retryAfter")]
+        retry_after: u64,
+    },
     Message {
         status: u16,
         message: Cow<'a, str>,
@@ -279,6 +690,16 @@ impl Serialize for PackageError {
                 message: Cow::Borrowed(message),
             }
             .serialize(serializer),
+            Self::Suppressed(reason) => RawError::Suppressed::<()> {
+                status: Status::<410>,
+                reason: Cow::Borrowed(reason),
+            }
+            .serialize(serializer),
+            Self::TooManyRequests { retry_after } => RawError::TooManyRequests::<()> {
+                status: Status::<429>,
+                retry_after: *retry_after,
+            }
+            .serialize(serializer),
             Self::Message { status, message } => RawError::Message::<()> {
                 status: *status,
                 message: Cow::Borrowed(message),
@@ -337,6 +758,11 @@ impl<'de> Deserialize<'de> for PackageError {
             RawError::NotSupported { status: _, message } => {
                 Ok(Self::NotSupported(message.into_owned()))
             }
+            RawError::Suppressed { status: _, reason } => Ok(Self::Suppressed(reason.into_owned())),
+            RawError::TooManyRequests {
+                status: _,
+                retry_after,
+            } => Ok(Self::TooManyRequests { retry_after }),
             RawError::Message { status, message } => Ok(Self::Message {
                 status,
                 message: message.into_owned(),
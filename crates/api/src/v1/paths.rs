@@ -1,7 +1,7 @@
 //! The paths of the Warg REST API.
 
 use warg_crypto::hash::AnyHash;
-use warg_protocol::registry::{LogId, RecordId};
+use warg_protocol::registry::{LogId, PackageName, RecordId};
 
 /// The path of the "fetch logs" API.
 pub fn fetch_logs() -> &'static str {
@@ -13,16 +13,91 @@ pub fn fetch_checkpoint() -> &'static str {
     "v1/fetch/checkpoint"
 }
 
+/// The path of the "fetch checkpoint" API for a specific checkpoint.
+pub fn fetch_checkpoint_by_id(checkpoint_id: &AnyHash) -> String {
+    format!("v1/fetch/checkpoint/{checkpoint_id}")
+}
+
+/// The path of the "list checkpoints" API.
+pub fn checkpoints() -> &'static str {
+    "v1/checkpoints"
+}
+
 /// The path of the "fetch package names" API.
 pub fn fetch_package_names() -> &'static str {
     "v1/fetch/names"
 }
 
+/// The path of the "get registry metadata" API.
+pub fn registry_metadata() -> &'static str {
+    "v1/registry"
+}
+
 /// The path of the get ledger sources.
 pub fn ledger_sources() -> &'static str {
     "v1/ledger"
 }
 
+/// The path of the "get audit entries" administration API.
+pub fn admin_audit() -> &'static str {
+    "v1/admin/audit"
+}
+
+/// The path of the "get submission queue" administration API.
+pub fn admin_queue() -> &'static str {
+    "v1/admin/queue"
+}
+
+/// The path of the "collect unreferenced content" administration API.
+pub fn admin_content_gc() -> &'static str {
+    "v1/admin/content/gc"
+}
+
+/// The path of the "prune rejected records" administration API.
+pub fn admin_records_prune() -> &'static str {
+    "v1/admin/records/prune"
+}
+
+/// The path of the "compact published records" administration API.
+pub fn admin_records_compact() -> &'static str {
+    "v1/admin/records/compact"
+}
+
+/// The path of the "reject package record" administration API.
+pub fn admin_record_reject(log_id: &LogId, record_id: &RecordId) -> String {
+    format!("v1/admin/records/{log_id}/{record_id}/reject")
+}
+
+/// The path of the "suppress package" and "unsuppress package" administration APIs.
+pub fn admin_suppression(name: &PackageName) -> String {
+    format!("v1/admin/suppressions/{name}")
+}
+
+/// The path of the "get webhook deliveries" administration API.
+pub fn admin_webhook_deliveries() -> &'static str {
+    "v1/admin/webhooks/deliveries"
+}
+
+/// The path of the "retry webhook delivery" administration API.
+pub fn admin_webhook_delivery_retry(id: &str) -> String {
+    format!("v1/admin/webhooks/deliveries/{id}/retry")
+}
+
+/// The path of the "publish operator record" API.
+pub fn publish_operator_record() -> &'static str {
+    "v1/operator/record"
+}
+
+/// The path of the "list packages" API.
+pub fn list_packages() -> &'static str {
+    "v1/packages"
+}
+
+/// The path of the "package records" API.
+pub fn package_records(name: &PackageName) -> String {
+    format!("v1/packages/{name}/records")
+}
+
 /// The path of the "publish package record" API.
 pub fn publish_package_record(log_id: &LogId) -> String {
     format!("v1/package/{log_id}/record")
@@ -38,11 +113,41 @@ pub fn package_record(log_id: &LogId, record_id: &RecordId) -> String {
     format!("v1/package/{log_id}/record/{record_id}")
 }
 
+/// The path for the content still missing from a package record.
+pub fn package_record_missing_content(log_id: &LogId, record_id: &RecordId) -> String {
+    format!("v1/package/{log_id}/record/{record_id}/missing")
+}
+
+/// The path for the dependencies of a package release.
+pub fn package_dependencies(log_id: &LogId) -> String {
+    format!("v1/package/{log_id}/dependencies")
+}
+
+/// The path for the dependents (reverse dependencies) of a package.
+pub fn package_dependents(log_id: &LogId) -> String {
+    format!("v1/package/{log_id}/dependents")
+}
+
+/// The path for resolving a version requirement of a package to a release.
+pub fn package_resolve(name: &PackageName) -> String {
+    format!("v1/package/{name}/resolve")
+}
+
+/// The path for the download statistics of a package.
+pub fn package_stats(name: &PackageName) -> String {
+    format!("v1/package/{name}/stats")
+}
+
 /// The path for proving checkpoint consistency.
 pub fn prove_consistency() -> &'static str {
     "v1/proof/consistency"
 }
 
+/// The path for proving the consistency between two checkpoints by their log roots.
+pub fn prove_checkpoint_consistency() -> &'static str {
+    "v1/proofs/consistency"
+}
+
 /// The path for proving checkpoint inclusion.
 pub fn prove_inclusion() -> &'static str {
     "v1/proof/inclusion"
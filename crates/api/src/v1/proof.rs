@@ -28,6 +28,17 @@ pub struct ConsistencyResponse {
     pub proof: Vec<u8>,
 }
 
+/// Represents the query parameters of a request to prove the consistency
+/// between two checkpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointConsistencyQuery {
+    /// The log root of the earlier checkpoint.
+    pub from: AnyHash,
+    /// The log root of the later checkpoint.
+    pub to: AnyHash,
+}
+
 /// Represents an inclusion proof request.
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -0,0 +1,84 @@
+//! Types relating to the registry metadata API.
+
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Cow;
+use thiserror::Error;
+
+/// Represents the metadata of a registry.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryMetadata {
+    /// The limits on package records submitted to the registry.
+    #[serde(default)]
+    pub record_limits: RecordLimits,
+}
+
+/// Represents the limits on package records submitted to a registry.
+///
+/// Records exceeding a limit are rejected; clients may split the entries of
+/// a publish across multiple records to stay within the limits.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordLimits {
+    /// The maximum size, in bytes, of a serialized record envelope.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_record_size: Option<u64>,
+    /// The maximum number of entries in a record.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_record_entries: Option<u32>,
+}
+
+/// Represents a registry metadata API error.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum RegistryError {
+    /// An error with a message occurred.
+    #[error("{message}")]
+    Message {
+        /// The HTTP status code.
+        status: u16,
+        /// The error message
+        message: String,
+    },
+}
+
+impl RegistryError {
+    /// Returns the HTTP status code of the error.
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::Message { status, .. } => *status,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged, rename_all = "camelCase")]
+enum RawError<'a> {
+    Message { status: u16, message: Cow<'a, str> },
+}
+
+impl Serialize for RegistryError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Message { status, message } => RawError::Message {
+                status: *status,
+                message: Cow::Borrowed(message),
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RegistryError {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match RawError::deserialize(deserializer)? {
+            RawError::Message { status, message } => Ok(Self::Message {
+                status,
+                message: message.into_owned(),
+            }),
+        }
+    }
+}
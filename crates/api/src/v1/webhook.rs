@@ -0,0 +1,56 @@
+//! Types relating to webhook notifications of published package records.
+
+use serde::{Deserialize, Serialize};
+use warg_crypto::hash::AnyHash;
+use warg_protocol::{
+    registry::{LogId, PackageName, RecordId, RegistryIndex},
+    Version,
+};
+
+/// The HTTP request header name of the signature of a webhook payload.
+///
+/// The value is `sha256=` followed by the hex-encoded HMAC-SHA256 of the
+/// request body, keyed by the secret configured for the webhook.
+pub const WEBHOOK_SIGNATURE_HEADER_NAME: &str = "warg-webhook-signature";
+/// The HTTP request header name of the identifier of a webhook delivery.
+///
+/// Every attempt of a delivery has the same identifier.
+pub const WEBHOOK_DELIVERY_HEADER_NAME: &str = "warg-webhook-delivery";
+
+/// Represents the payload of a webhook notifying that a package record was
+/// published.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload {
+    /// The name of the package.
+    pub package: PackageName,
+    /// The log of the package.
+    pub log_id: LogId,
+    /// The identifier of the published record.
+    pub record_id: RecordId,
+    /// The index of the record in the registry log.
+    pub registry_index: RegistryIndex,
+    /// The identifier of the checkpoint the record was delivered for, which
+    /// includes the record.
+    pub checkpoint: AnyHash,
+    /// The version entries of the record.
+    pub entries: Vec<WebhookEntry>,
+}
+
+/// Represents a version entry of a published package record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum WebhookEntry {
+    /// A version of the package was released.
+    Release {
+        /// The released version.
+        version: Version,
+        /// The digest of the released content.
+        content: AnyHash,
+    },
+    /// A version of the package was yanked.
+    Yank {
+        /// The yanked version.
+        version: Version,
+    },
+}
@@ -0,0 +1,160 @@
+//! Verifies a Warg registry package from a web page.
+//!
+//! The package log is fetched with the browser's `fetch()` and verified by
+//! the client core against the registry's latest checkpoint.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use warg_api::v1::{
+    fetch::{FetchLogsRequest, FetchLogsResponse},
+    paths,
+    proof::{ConsistencyRequest, ConsistencyResponse, InclusionRequest, InclusionResponse},
+};
+use warg_client::core::{self, MemoryStateStorage, Transport, Verifier};
+use warg_protocol::{
+    registry::{PackageName, TimestampedCheckpoint},
+    SerdeEnvelope, VersionReq,
+};
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Request, RequestInit, Response};
+
+/// A transport that sends requests with the browser's `fetch()`.
+struct FetchTransport {
+    url: String,
+}
+
+impl FetchTransport {
+    async fn send<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<String>,
+    ) -> Result<R> {
+        let init = RequestInit::new();
+        init.set_method(method);
+        if let Some(body) = &body {
+            init.set_body(&JsValue::from_str(body));
+        }
+
+        let url = format!("{url}/{path}", url = self.url);
+        let request = Request::new_with_str_and_init(&url, &init).map_err(js_error)?;
+        if body.is_some() {
+            request
+                .headers()
+                .set("content-type", "application/json")
+                .map_err(js_error)?;
+        }
+
+        let window = web_sys::window().context("not running in a browser window")?;
+        let response: Response = JsFuture::from(window.fetch_with_request(&request))
+            .await
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(js_error)?;
+        if !response.ok() {
+            bail!(
+                "`{url}` returned status {status}",
+                status = response.status()
+            );
+        }
+
+        let text = JsFuture::from(response.text().map_err(js_error)?)
+            .await
+            .map_err(js_error)?
+            .as_string()
+            .unwrap_or_default();
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    async fn post<T: Serialize, R: DeserializeOwned>(&self, path: &str, body: &T) -> Result<R> {
+        self.send("POST", path, Some(serde_json::to_string(body)?))
+            .await
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Transport for FetchTransport {
+    async fn latest_checkpoint(&self) -> Result<SerdeEnvelope<TimestampedCheckpoint>> {
+        self.send("GET", paths::fetch_checkpoint(), None).await
+    }
+
+    async fn fetch_logs(&self, request: FetchLogsRequest<'_>) -> Result<FetchLogsResponse> {
+        self.post(paths::fetch_logs(), &request).await
+    }
+
+    async fn prove_inclusion(&self, request: InclusionRequest) -> Result<InclusionResponse> {
+        self.post(paths::prove_inclusion(), &request).await
+    }
+
+    async fn prove_consistency(&self, request: ConsistencyRequest) -> Result<ConsistencyResponse> {
+        self.post(paths::prove_consistency(), &request).await
+    }
+}
+
+/// The result of verifying a package.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    package: String,
+    checkpoint_log_length: Option<usize>,
+    head: Option<String>,
+    latest: Option<String>,
+    releases: Vec<ReleaseReport>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReleaseReport {
+    version: String,
+    content: Option<String>,
+    yanked: bool,
+}
+
+fn js_error(e: JsValue) -> anyhow::Error {
+    anyhow!("{e:?}")
+}
+
+/// Fetches the log of the given package from the registry at the given URL,
+/// verifies it, and returns a JSON report of its releases.
+///
+/// Verification failures are returned as errors.
+#[wasm_bindgen(js_name = verifyPackage)]
+pub async fn verify_package(registry_url: String, package: String) -> Result<String, JsValue> {
+    verify(registry_url, package)
+        .await
+        .map_err(|e| JsValue::from_str(&format!("{e:#}")))
+}
+
+async fn verify(registry_url: String, package: String) -> Result<String> {
+    let name = PackageName::new(package)?;
+    let verifier = Verifier::new(
+        FetchTransport {
+            url: registry_url.trim_end_matches('/').to_string(),
+        },
+        MemoryStateStorage::default(),
+    );
+
+    let info = verifier
+        .update(std::slice::from_ref(&name))
+        .await?
+        .remove(0);
+    let report = Report {
+        package: name.to_string(),
+        checkpoint_log_length: info.checkpoint.as_ref().map(|c| c.log_length),
+        head: info.state.head().as_ref().map(|h| h.digest.to_string()),
+        latest: core::resolve_release(&info.state, &VersionReq::STAR, false, false)
+            .map(|r| r.version.to_string()),
+        releases: info
+            .state
+            .releases()
+            .map(|r| ReleaseReport {
+                version: r.version.to_string(),
+                content: r.content().map(ToString::to_string),
+                yanked: r.yanked(),
+            })
+            .collect(),
+    };
+
+    Ok(serde_json::to_string_pretty(&report)?)
+}
@@ -2,42 +2,62 @@
 
 use anyhow::{anyhow, Result};
 use bytes::Bytes;
-use futures_util::{Stream, TryStreamExt};
+use futures_util::{stream, Stream, StreamExt, TryStreamExt};
 use indexmap::IndexMap;
+use rand_core::{OsRng, RngCore};
 use reqwest::{
-    header::{HeaderMap, HeaderName, HeaderValue},
-    Body, IntoUrl, Method, RequestBuilder, Response, StatusCode,
+    header::{
+        HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_RANGE, CONTENT_TYPE, RANGE, RETRY_AFTER,
+    },
+    Body, IntoUrl, Method, Request, RequestBuilder, Response, StatusCode,
 };
 use secrecy::{ExposeSecret, Secret};
 use serde::de::DeserializeOwned;
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    ops::{Bound, RangeBounds},
+    time::Duration,
+};
 use thiserror::Error;
 use warg_api::v1::{
     content::{ContentError, ContentSourcesResponse},
     fetch::{
-        FetchError, FetchLogsRequest, FetchLogsResponse, FetchPackageNamesRequest,
-        FetchPackageNamesResponse,
+        CheckpointsQuery, CheckpointsResponse, FetchCheckpointQuery, FetchError, FetchLogsRequest,
+        FetchLogsResponse, FetchLogsStreamItem, FetchPackageNamesRequest,
+        FetchPackageNamesResponse, FETCH_LOGS_STREAM_MEDIA_TYPE,
     },
     ledger::{LedgerError, LedgerSourcesResponse},
     monitor::{CheckpointVerificationResponse, MonitorError},
-    package::{ContentSource, PackageError, PackageRecord, PublishRecordRequest},
+    operator::{OperatorError, OperatorRecordResponse, PublishOperatorRecordRequest},
+    package::{
+        parse_received_range, ContentSource, ListPackagesQuery, ListPackagesResponse,
+        MissingContentResponse, PackageDependenciesResponse, PackageDependentsResponse,
+        PackageError, PackageRecord, PackageRecordsQuery, PackageRecordsResponse,
+        PackageStatsResponse, PublishRecordRequest, ResolvePackageResponse, UploadContentRange,
+    },
     paths,
     proof::{
-        ConsistencyRequest, ConsistencyResponse, InclusionRequest, InclusionResponse, ProofError,
+        CheckpointConsistencyQuery, ConsistencyRequest, ConsistencyResponse, InclusionRequest,
+        InclusionResponse, ProofError,
     },
-    REGISTRY_HEADER_NAME, REGISTRY_HINT_HEADER_NAME,
+    registry::{RegistryError, RegistryMetadata},
+    IDEMPOTENCY_KEY_HEADER_NAME, REGISTRY_HEADER_NAME, REGISTRY_HINT_HEADER_NAME,
 };
-use warg_crypto::hash::{AnyHash, HashError, Sha256};
+use warg_crypto::hash::{AnyHash, HashAlgorithm, HashError};
 use warg_protocol::{
-    registry::{Checkpoint, LogId, LogLeaf, MapLeaf, RecordId, TimestampedCheckpoint},
-    SerdeEnvelope,
+    registry::{Checkpoint, LogId, LogLeaf, PackageName, RecordId, TimestampedCheckpoint},
+    SerdeEnvelope, Version, VersionReq,
 };
-use warg_transparency::{
-    log::{ConsistencyProofError, InclusionProofError, LogProofBundle, ProofBundle},
-    map::MapProofBundle,
+
+use crate::{
+    core::{self, CoreError},
+    registry_url::RegistryUrl,
+    retry::RetryPolicy,
+    storage::RegistryDomain,
 };
+/// The time to wait for the registry to respond to a record submission.
+const SUBMISSION_TIMEOUT: Duration = Duration::from_secs(30);
 
-use crate::{registry_url::RegistryUrl, storage::RegistryDomain};
 /// Represents an error that occurred while communicating with the registry.
 #[derive(Debug, Error)]
 pub enum ClientError {
@@ -59,6 +79,12 @@ pub enum ClientError {
     /// An error was returned from the ledger API.
     #[error(transparent)]
     Ledger(#[from] LedgerError),
+    /// An error was returned from the operator API.
+    #[error(transparent)]
+    Operator(#[from] OperatorError),
+    /// An error was returned from the registry metadata API.
+    #[error(transparent)]
+    Registry(#[from] RegistryError),
     /// An error occurred while communicating with the registry.
     #[error("failed to send request to registry server: {0}")]
     Communication(#[from] reqwest::Error),
@@ -70,25 +96,15 @@ pub enum ClientError {
         /// The error message.
         message: String,
     },
-    /// The provided root for a consistency proof was incorrect.
-    #[error(
-        "the client failed to prove consistency: found root `{found}` but was given root `{root}`"
-    )]
-    IncorrectConsistencyProof {
-        /// The provided root.
-        root: AnyHash,
-        /// The found root.
-        found: AnyHash,
-    },
+    /// A proof provided by the registry failed verification.
+    #[error("the registry provided a proof that failed verification: {0}")]
+    InvalidProof(ProofError),
     /// A hash returned from the server was incorrect.
     #[error("the server returned an invalid hash: {0}")]
     Hash(#[from] HashError),
-    /// The client failed a consistency proof.
-    #[error("the client failed a consistency proof: {0}")]
-    ConsistencyProof(#[from] ConsistencyProofError),
-    /// The client failed an inclusion proof.
-    #[error("the client failed an inclusion proof: {0}")]
-    InclusionProof(#[from] InclusionProofError),
+    /// The registry uses a hash algorithm the client does not support.
+    #[error("the registry uses unsupported hash algorithm `{0}`")]
+    UnsupportedHashAlgorithm(HashAlgorithm),
     /// The record was not published.
     #[error("record `{0}` has not been published")]
     RecordNotPublished(RecordId),
@@ -98,6 +114,18 @@ pub enum ClientError {
     /// All sources for the given content digest returned an error response.
     #[error("all sources for content digest `{0}` returned an error response")]
     AllSourcesFailed(AnyHash),
+    /// No source for the given content digest succeeded and at least one was
+    /// temporarily unavailable.
+    #[error("the sources for content digest `{0}` are temporarily unavailable")]
+    ContentUnavailable(AnyHash),
+    /// A request failed with a transient error on every attempt.
+    #[error("{source} (gave up after {attempts} attempts)")]
+    RetriesExhausted {
+        /// The number of times the request was attempted.
+        attempts: u32,
+        /// The error of the last attempt.
+        source: Box<ClientError>,
+    },
     /// Invalid upload HTTP method.
     #[error("server returned an invalid HTTP method `{0}`")]
     InvalidHttpMethod(String),
@@ -112,6 +140,91 @@ pub enum ClientError {
     Other(#[from] anyhow::Error),
 }
 
+impl From<CoreError> for ClientError {
+    fn from(e: CoreError) -> Self {
+        match e {
+            CoreError::InvalidProof(e) => Self::InvalidProof(e),
+            CoreError::UnsupportedHashAlgorithm(algorithm) => {
+                Self::UnsupportedHashAlgorithm(algorithm)
+            }
+            e => Self::Other(e.into()),
+        }
+    }
+}
+
+impl ClientError {
+    /// Determines if the error is transient, such that the request may
+    /// succeed if retried.
+    ///
+    /// A request that already exhausted its retries is not transient.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Communication(_) | Self::ContentUnavailable(_) => true,
+            Self::UnexpectedResponse { status, .. } => is_transient_status(*status),
+            Self::Content(e) => StatusCode::from_u16(e.status()).map_or(false, is_transient_status),
+            _ => false,
+        }
+    }
+}
+
+/// Gets the value of the `Range` header requesting the given range of bytes.
+///
+/// Returns `None` if the range covers the entire content.
+fn range_header(range: impl RangeBounds<u64>) -> Option<String> {
+    let start = match range.start_bound() {
+        Bound::Included(start) => *start,
+        Bound::Excluded(start) => start.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(end) => Some(*end),
+        Bound::Excluded(end) => Some(end.saturating_sub(1)),
+        Bound::Unbounded => None,
+    };
+
+    match end {
+        Some(end) => Some(format!("bytes={start}-{end}")),
+        None if start > 0 => Some(format!("bytes={start}-")),
+        None => None,
+    }
+}
+
+/// Determines if a response status indicates the registry may respond
+/// successfully if the request is retried.
+fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Determines if a response status indicates the registry, or a proxy in
+/// front of it, is temporarily unable to handle a request.
+///
+/// Other server errors are unlikely to go away on their own, so requests
+/// failing with them are not retried.
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+            | StatusCode::TOO_MANY_REQUESTS
+    )
+}
+
+/// Determines if a request failed before the registry could respond to it.
+fn is_retryable_error(e: &reqwest::Error) -> bool {
+    e.is_timeout() || e.is_connect() || e.is_request()
+}
+
+/// Gets the delay requested by the `Retry-After` header of a response.
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+}
+
 async fn deserialize<T: DeserializeOwned>(response: Response) -> Result<T, ClientError> {
     let status = response.status();
     match response.headers().get("content-type") {
@@ -186,13 +299,24 @@ impl WithAuth for RequestBuilder {
     }
 }
 
+/// Represents the progress of a resumable content upload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadProgress {
+    /// The registry received all of the content.
+    Complete,
+    /// The registry received the given number of bytes of the content.
+    Received(u64),
+}
+
 /// Represents a Warg API client for communicating with
 /// a Warg registry server.
+#[derive(Clone)]
 pub struct Client {
     url: RegistryUrl,
     client: reqwest::Client,
     warg_registry_header: Option<RegistryDomain>,
     auth_token: Option<Secret<String>>,
+    retry: RetryPolicy,
 }
 
 impl Client {
@@ -204,9 +328,26 @@ impl Client {
             client: reqwest::Client::new(),
             warg_registry_header: None,
             auth_token,
+            retry: RetryPolicy::default(),
         })
     }
 
+    /// Sets the policy for retrying requests that fail with a transient
+    /// error.
+    ///
+    /// Requests are only retried if they are safe to repeat: requests with an
+    /// idempotent method, queries sent with `POST`, and record submissions,
+    /// which carry an idempotency key.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Gets the policy for retrying requests that fail with a transient error.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+    }
+
     /// Gets auth token
     pub fn auth_token(&self) -> &Option<Secret<String>> {
         &self.auth_token
@@ -223,12 +364,87 @@ impl Client {
         let url = self.url.join(paths::fetch_checkpoint());
         tracing::debug!("getting latest checkpoint at `{url}`");
         into_result::<_, FetchError>(
-            self.client
-                .get(url)
-                .warg_header(self.get_warg_registry())?
-                .auth(self.auth_token())
-                .send()
-                .await?,
+            self.send::<FetchError>(
+                self.client
+                    .get(url)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Waits for the registry to issue a checkpoint other than the one with
+    /// the given identifier, returning the latest checkpoint.
+    ///
+    /// The registry responds with its latest checkpoint once a new one is
+    /// issued or after `timeout`, whichever comes first.
+    pub async fn wait_for_checkpoint(
+        &self,
+        since: &AnyHash,
+        timeout: Duration,
+    ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, ClientError> {
+        let url = self.url.join(paths::fetch_checkpoint());
+        tracing::debug!("waiting for a checkpoint after `{since}` at `{url}`");
+        into_result::<_, FetchError>(
+            self.send::<FetchError>(
+                self.client
+                    .get(url)
+                    .query(&FetchCheckpointQuery {
+                        since: Some(since.clone()),
+                        timeout: Some(timeout.as_secs()),
+                    })
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Gets the checkpoint with the given identifier from the registry.
+    pub async fn fetch_checkpoint(
+        &self,
+        checkpoint_id: &AnyHash,
+    ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, ClientError> {
+        let url = self.url.join(&paths::fetch_checkpoint_by_id(checkpoint_id));
+        tracing::debug!("getting checkpoint `{checkpoint_id}` at `{url}`");
+        into_result::<_, FetchError>(
+            self.send::<FetchError>(
+                self.client
+                    .get(url)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Lists the checkpoints of the registry in the order they were issued.
+    ///
+    /// If `since` is given, only checkpoints issued after the checkpoint with
+    /// that identifier are listed.
+    pub async fn list_checkpoints(
+        &self,
+        since: Option<&AnyHash>,
+        limit: Option<u16>,
+    ) -> Result<CheckpointsResponse, ClientError> {
+        let url = self.url.join(paths::checkpoints());
+        tracing::debug!("listing checkpoints at `{url}`");
+        into_result::<_, FetchError>(
+            self.send::<FetchError>(
+                self.client
+                    .get(url)
+                    .query(&CheckpointsQuery {
+                        since: since.cloned(),
+                        limit,
+                    })
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
         )
         .await
     }
@@ -244,12 +460,13 @@ impl Client {
             let registry_header = HeaderName::try_from(REGISTRY_HEADER_NAME).unwrap();
             let header_val = HeaderValue::try_from(reg).unwrap();
             let res: SerdeEnvelope<TimestampedCheckpoint> = into_result::<_, FetchError>(
-                self.client
-                    .get(url)
-                    .header(registry_header, header_val)
-                    .auth(self.auth_token())
-                    .send()
-                    .await?,
+                self.send::<FetchError>(
+                    self.client
+                        .get(url)
+                        .header(registry_header, header_val)
+                        .auth(self.auth_token()),
+                )
+                .await?,
             )
             .await?;
             timestamps.insert(reg.clone(), res);
@@ -266,12 +483,14 @@ impl Client {
         tracing::debug!("verifying checkpoint at `{url}`");
 
         let response = self
-            .client
-            .post(url)
-            .json(&request)
-            .warg_header(self.get_warg_registry())?
-            .auth(self.auth_token())
-            .send()
+            .send_with_retry::<MonitorError>(
+                self.client
+                    .post(url)
+                    .json(&request)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+                self.retry,
+            )
             .await?;
         into_result::<_, MonitorError>(response).await
     }
@@ -284,23 +503,133 @@ impl Client {
         let url = self.url.join(paths::fetch_logs());
         tracing::debug!("fetching logs at `{url}`");
         let response = self
-            .client
-            .post(&url)
-            .json(&request)
-            .warg_header(self.get_warg_registry())?
-            .auth(self.auth_token())
-            .send()
+            .send_with_retry::<FetchError>(
+                self.client
+                    .post(&url)
+                    .json(&request)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+                self.retry,
+            )
             .await?;
 
         let header = response.headers().get(REGISTRY_HINT_HEADER_NAME).cloned();
         into_result::<_, FetchError>(response)
             .await
-            .map_err(|err| match err {
-                ClientError::Fetch(FetchError::LogNotFound(log_id)) if header.is_some() => {
-                    ClientError::LogNotFoundWithHint(log_id, header.unwrap())
+            .map_err(|err| Self::with_log_hint(err, header))
+    }
+
+    /// Fetches package log entries from the registry as a stream.
+    ///
+    /// The registry is asked to stream the records as newline-delimited JSON,
+    /// so that each record may be processed as it arrives instead of the
+    /// whole response being buffered. A registry that does not stream
+    /// responses answers with a single response, whose records are yielded in
+    /// the same order.
+    ///
+    /// The stream ends with a [`FetchLogsStreamItem::End`] item; an error
+    /// line sent by the registry is yielded as an error.
+    pub async fn fetch_logs_stream(
+        &self,
+        request: FetchLogsRequest<'_>,
+    ) -> Result<impl Stream<Item = Result<FetchLogsStreamItem, ClientError>>, ClientError> {
+        let url = self.url.join(paths::fetch_logs());
+        tracing::debug!("streaming logs at `{url}`");
+        let response = self
+            .send_with_retry::<FetchError>(
+                self.client
+                    .post(&url)
+                    .header(ACCEPT, FETCH_LOGS_STREAM_MEDIA_TYPE)
+                    .json(&request)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+                self.retry,
+            )
+            .await?;
+
+        let streamed = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map_or(false, |value| {
+                value.starts_with(FETCH_LOGS_STREAM_MEDIA_TYPE)
+            });
+        if !response.status().is_success() || !streamed {
+            let header = response.headers().get(REGISTRY_HINT_HEADER_NAME).cloned();
+            let response = into_result::<FetchLogsResponse, FetchError>(response)
+                .await
+                .map_err(|err| Self::with_log_hint(err, header))?;
+
+            let end = FetchLogsStreamItem::End {
+                more: response.more,
+            };
+            let items = response
+                .operator
+                .into_iter()
+                .map(|record| FetchLogsStreamItem::Operator { record })
+                .chain(response.packages.into_iter().flat_map(|(log_id, records)| {
+                    records
+                        .into_iter()
+                        .map(move |record| FetchLogsStreamItem::Package {
+                            log_id: log_id.clone(),
+                            record,
+                        })
+                }))
+                .chain([end]);
+            return Ok(stream::iter(items.map(Ok)).left_stream());
+        }
+
+        let status = response.status();
+        let lines = stream::try_unfold(
+            (response.bytes_stream(), Vec::new()),
+            |(mut chunks, mut buffer)| async move {
+                loop {
+                    if let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                        let line = buffer.drain(..=end).collect::<Vec<_>>();
+                        return Ok(Some((line, (chunks, buffer))));
+                    }
+
+                    match chunks.try_next().await? {
+                        Some(chunk) => buffer.extend_from_slice(&chunk),
+                        None if buffer.is_empty() => return Ok(None),
+                        None => {
+                            let line = std::mem::take(&mut buffer);
+                            return Ok(Some((line, (chunks, buffer))));
+                        }
+                    }
                 }
-                _ => err,
+            },
+        );
+
+        Ok(lines
+            .try_filter(|line| {
+                futures_util::future::ready(!line.iter().all(u8::is_ascii_whitespace))
             })
+            .map_err(ClientError::Communication)
+            .and_then(move |line| {
+                futures_util::future::ready(
+                    match serde_json::from_slice::<FetchLogsStreamItem>(&line) {
+                        Ok(FetchLogsStreamItem::Error { error }) => Err(error.into()),
+                        Ok(item) => Ok(item),
+                        Err(e) => Err(ClientError::UnexpectedResponse {
+                            status,
+                            message: format!("invalid line in streamed response: {e}"),
+                        }),
+                    },
+                )
+            })
+            .right_stream())
+    }
+
+    /// Replaces a log not found error with one including the registry hint
+    /// header, if the response had one.
+    fn with_log_hint(err: ClientError, header: Option<HeaderValue>) -> ClientError {
+        match (err, header) {
+            (ClientError::Fetch(FetchError::LogNotFound(log_id)), Some(header)) => {
+                ClientError::LogNotFoundWithHint(log_id, header)
+            }
+            (err, _) => err,
+        }
     }
 
     /// Fetches package names from the registry.
@@ -312,12 +641,14 @@ impl Client {
         tracing::debug!("fetching package names at `{url}`");
 
         let response = self
-            .client
-            .post(url)
-            .warg_header(self.get_warg_registry())?
-            .auth(self.auth_token())
-            .json(&request)
-            .send()
+            .send_with_retry::<FetchError>(
+                self.client
+                    .post(url)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token())
+                    .json(&request),
+                self.retry,
+            )
             .await?;
         into_result::<_, FetchError>(response).await
     }
@@ -328,17 +659,53 @@ impl Client {
         tracing::debug!("getting ledger sources at `{url}`");
 
         into_result::<_, LedgerError>(
-            self.client
-                .get(url)
-                .warg_header(self.get_warg_registry())?
-                .auth(self.auth_token())
-                .send()
-                .await?,
+            self.send::<LedgerError>(
+                self.client
+                    .get(url)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Gets the metadata of the registry.
+    ///
+    /// Registries that do not serve the registry metadata API impose no
+    /// record limits, so a `404 Not Found` response results in the default
+    /// metadata.
+    pub async fn registry_metadata(&self) -> Result<RegistryMetadata, ClientError> {
+        let url = self.url.join(paths::registry_metadata());
+        tracing::debug!("getting registry metadata at `{url}`");
+
+        match into_result::<_, RegistryError>(
+            self.send::<RegistryError>(
+                self.client
+                    .get(url)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
         )
         .await
+        {
+            Err(ClientError::Registry(e)) if e.status() == StatusCode::NOT_FOUND.as_u16() => {
+                Ok(RegistryMetadata::default())
+            }
+            res => res,
+        }
     }
 
     /// Publish a new record to a package log.
+    ///
+    /// The submission is sent with a random idempotency key and retried with
+    /// the same key if it times out, fails to connect, or the registry is
+    /// temporarily unavailable; the registry returns the outcome of the
+    /// original submission for retries it has already received.
+    ///
+    /// Resubmitting a record the registry already has is also safe without
+    /// the original key: the registry responds with the existing record.
     pub async fn publish_package_record(
         &self,
         log_id: &LogId,
@@ -350,15 +717,45 @@ impl Client {
             name = request.package_name
         );
 
-        let response = self
-            .client
-            .post(url)
-            .json(&request)
-            .warg_header(self.get_warg_registry())?
-            .auth(self.auth_token())
-            .send()
-            .await?;
-        into_result::<_, PackageError>(response).await
+        let mut key = [0u8; 16];
+        OsRng.fill_bytes(&mut key);
+
+        into_result::<_, PackageError>(
+            self.send_with_retry::<PackageError>(
+                self.client
+                    .post(&url)
+                    .json(&request)
+                    .header(IDEMPOTENCY_KEY_HEADER_NAME, hex::encode(key))
+                    .timeout(SUBMISSION_TIMEOUT)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+                self.retry,
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Publishes a record to the operator log of the registry.
+    pub async fn publish_operator_record(
+        &self,
+        request: PublishOperatorRecordRequest<'_>,
+    ) -> Result<OperatorRecordResponse, ClientError> {
+        let url = self.url.join(paths::publish_operator_record());
+        tracing::debug!("appending record to the operator log at `{url}`");
+
+        into_result::<_, OperatorError>(
+            self.send::<OperatorError>(
+                self.client
+                    .post(url)
+                    .json(&request)
+                    .timeout(SUBMISSION_TIMEOUT)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
+        )
+        .await
     }
 
     /// Gets a package record from the registry.
@@ -371,12 +768,176 @@ impl Client {
         tracing::debug!("getting record `{record_id}` for package `{log_id}` at `{url}`");
 
         into_result::<_, PackageError>(
-            self.client
-                .get(url)
-                .warg_header(self.get_warg_registry())?
-                .auth(self.auth_token())
-                .send()
-                .await?,
+            self.send::<PackageError>(
+                self.client
+                    .get(url)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Gets the content still missing from a package record.
+    ///
+    /// Content the registry already has is not reported as missing.
+    pub async fn get_missing_content(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+    ) -> Result<MissingContentResponse, ClientError> {
+        let url = self
+            .url
+            .join(&paths::package_record_missing_content(log_id, record_id));
+        tracing::debug!(
+            "getting missing content of record `{record_id}` for package `{log_id}` at `{url}`"
+        );
+
+        into_result::<_, PackageError>(
+            self.send::<PackageError>(
+                self.client
+                    .get(url)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Lists a page of the packages published to the registry.
+    pub async fn list_packages(
+        &self,
+        query: &ListPackagesQuery,
+    ) -> Result<ListPackagesResponse, ClientError> {
+        let url = self.url.join(paths::list_packages());
+        tracing::debug!("listing packages at `{url}`");
+
+        into_result::<_, PackageError>(
+            self.send::<PackageError>(
+                self.client
+                    .get(url)
+                    .query(query)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Gets a page of the record history of a package from the registry.
+    pub async fn package_records(
+        &self,
+        name: &PackageName,
+        query: &PackageRecordsQuery,
+    ) -> Result<PackageRecordsResponse, ClientError> {
+        let url = self.url.join(&paths::package_records(name));
+        tracing::debug!("getting records of package `{name}` at `{url}`");
+
+        into_result::<_, PackageError>(
+            self.send::<PackageError>(
+                self.client
+                    .get(url)
+                    .query(query)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Gets the dependencies of a package release from the registry.
+    pub async fn package_dependencies(
+        &self,
+        log_id: &LogId,
+        version: &Version,
+    ) -> Result<PackageDependenciesResponse, ClientError> {
+        let url = self.url.join(&paths::package_dependencies(log_id));
+        tracing::debug!(
+            "getting dependencies of version `{version}` of package `{log_id}` at `{url}`"
+        );
+
+        into_result::<_, PackageError>(
+            self.send::<PackageError>(
+                self.client
+                    .get(url)
+                    .query(&[("version", version.to_string())])
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Gets the package releases that depend on a package from the registry.
+    pub async fn package_dependents(
+        &self,
+        log_id: &LogId,
+    ) -> Result<PackageDependentsResponse, ClientError> {
+        let url = self.url.join(&paths::package_dependents(log_id));
+        tracing::debug!("getting dependents of package `{log_id}` at `{url}`");
+
+        into_result::<_, PackageError>(
+            self.send::<PackageError>(
+                self.client
+                    .get(url)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Asks the registry to resolve a version requirement of a package to a release.
+    ///
+    /// The registry's answer is not verified; it must only be used as a hint
+    /// and never in place of validating the package log.
+    pub async fn resolve_package(
+        &self,
+        name: &PackageName,
+        req: &VersionReq,
+        include_prerelease: bool,
+    ) -> Result<ResolvePackageResponse, ClientError> {
+        let url = self.url.join(&paths::package_resolve(name));
+        tracing::debug!("resolving `{req}` of package `{name}` at `{url}`");
+
+        into_result::<_, PackageError>(
+            self.send::<PackageError>(
+                self.client
+                    .get(url)
+                    .query(&[
+                        ("req", req.to_string()),
+                        ("include_prerelease", include_prerelease.to_string()),
+                    ])
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Gets the download statistics of a package collected by the registry.
+    pub async fn package_stats(
+        &self,
+        name: &PackageName,
+    ) -> Result<PackageStatsResponse, ClientError> {
+        let url = self.url.join(&paths::package_stats(name));
+        tracing::debug!("getting download statistics of package `{name}` at `{url}`");
+
+        into_result::<_, PackageError>(
+            self.send::<PackageError>(
+                self.client
+                    .get(url)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
         )
         .await
     }
@@ -390,12 +951,13 @@ impl Client {
         tracing::debug!("getting content sources for digest `{digest}` at `{url}`");
 
         into_result::<_, ContentError>(
-            self.client
-                .get(url)
-                .warg_header(self.get_warg_registry())?
-                .auth(self.auth_token())
-                .send()
-                .await?,
+            self.send::<ContentError>(
+                self.client
+                    .get(url)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+            )
+            .await?,
         )
         .await
     }
@@ -405,7 +967,28 @@ impl Client {
         &self,
         digest: &AnyHash,
     ) -> Result<impl Stream<Item = Result<Bytes>>, ClientError> {
-        tracing::debug!("requesting content download for digest `{digest}`");
+        self.download_content_range(digest, ..).await
+    }
+
+    /// Downloads the given range of bytes of the content associated with a
+    /// given record.
+    ///
+    /// A range other than the entire content is only requested from sources
+    /// that accept range requests; a source responding with anything but the
+    /// requested range is skipped.
+    pub async fn download_content_range(
+        &self,
+        digest: &AnyHash,
+        range: impl RangeBounds<u64>,
+    ) -> Result<impl Stream<Item = Result<Bytes>>, ClientError> {
+        let range = range_header(range);
+        tracing::debug!(
+            "requesting content download for digest `{digest}`{range}",
+            range = range
+                .as_deref()
+                .map(|range| format!(" ({range})"))
+                .unwrap_or_default()
+        );
 
         let ContentSourcesResponse { content_sources } = self.content_sources(digest).await?;
 
@@ -413,23 +996,44 @@ impl Client {
             .get(digest)
             .ok_or(ClientError::AllSourcesFailed(digest.clone()))?;
 
+        let mut unavailable = false;
         for source in sources {
-            let ContentSource::HttpGet { url, .. } = source;
+            let ContentSource::HttpGet {
+                url, accept_ranges, ..
+            } = source;
+
+            let mut request = self.client.get(url);
+            if let Some(range) = &range {
+                if !accept_ranges {
+                    tracing::debug!("content source `{url}` does not accept range requests");
+                    continue;
+                }
+
+                request = request.header(RANGE, range);
+            }
 
             tracing::debug!("downloading content `{digest}` from `{url}`");
 
-            let response = self.client.get(url).send().await?;
-            if !response.status().is_success() {
-                tracing::debug!(
-                    "failed to download content `{digest}` from `{url}`: {status}",
-                    status = response.status()
-                );
+            let response = request.send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                tracing::debug!("failed to download content `{digest}` from `{url}`: {status}");
+                unavailable |= is_transient_status(status);
+                continue;
+            }
+
+            if range.is_some() && status != StatusCode::PARTIAL_CONTENT {
+                tracing::debug!("content source `{url}` ignored the requested range");
                 continue;
             }
 
             return Ok(response.bytes_stream().map_err(|e| anyhow!(e)));
         }
 
+        if unavailable {
+            return Err(ClientError::ContentUnavailable(digest.clone()));
+        }
+
         Err(ClientError::AllSourcesFailed(digest.clone()))
     }
 
@@ -454,17 +1058,19 @@ impl Client {
         tracing::debug!("proving checkpoint inclusion at `{url}`");
 
         let response = into_result::<InclusionResponse, ProofError>(
-            self.client
-                .post(url)
-                .json(&request)
-                .warg_header(self.get_warg_registry())?
-                .auth(self.auth_token())
-                .send()
-                .await?,
+            self.send_with_retry::<ProofError>(
+                self.client
+                    .post(url)
+                    .json(&request)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+                self.retry,
+            )
+            .await?,
         )
         .await?;
 
-        Self::validate_inclusion_response(response, checkpoint, leafs)
+        core::verify_inclusion(&response, checkpoint, leafs).map_err(Into::into)
     }
 
     /// Proves consistency between two log roots.
@@ -476,61 +1082,115 @@ impl Client {
     ) -> Result<(), ClientError> {
         let url = self.url.join(paths::prove_consistency());
         let response = into_result::<ConsistencyResponse, ProofError>(
-            self.client
-                .post(url)
-                .json(&request)
-                .warg_header(self.get_warg_registry())?
-                .auth(self.auth_token())
-                .send()
-                .await?,
+            self.send_with_retry::<ProofError>(
+                self.client
+                    .post(url)
+                    .json(&request)
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+                self.retry,
+            )
+            .await?,
         )
         .await?;
 
-        let proof = ProofBundle::<Sha256, LogLeaf>::decode(&response.proof).unwrap();
-        let (log_data, consistencies, inclusions) = proof.unbundle();
-        if !inclusions.is_empty() {
-            return Err(ClientError::Proof(ProofError::BundleFailure(
-                "expected no inclusion proofs".into(),
-            )));
-        }
-
-        if consistencies.len() != 1 {
-            return Err(ClientError::Proof(ProofError::BundleFailure(
-                "expected exactly one consistency proof".into(),
-            )));
-        }
+        core::verify_consistency(&response.proof, &from_log_root, &to_log_root).map_err(Into::into)
+    }
 
-        let (from, to) = consistencies
-            .first()
-            .unwrap()
-            .evaluate(&log_data)
-            .map(|(from, to)| (AnyHash::from(from), AnyHash::from(to)))?;
+    /// Proves consistency between two checkpoints issued by the registry,
+    /// identified by their log roots.
+    ///
+    /// Unlike `prove_log_consistency`, neither checkpoint need be the latest.
+    pub async fn prove_checkpoint_consistency(
+        &self,
+        from_log_root: &AnyHash,
+        to_log_root: &AnyHash,
+    ) -> Result<(), ClientError> {
+        let url = self.url.join(paths::prove_checkpoint_consistency());
+        let response = into_result::<ConsistencyResponse, ProofError>(
+            self.send_with_retry::<ProofError>(
+                self.client
+                    .get(url)
+                    .query(&CheckpointConsistencyQuery {
+                        from: from_log_root.clone(),
+                        to: to_log_root.clone(),
+                    })
+                    .warg_header(self.get_warg_registry())?
+                    .auth(self.auth_token()),
+                self.retry,
+            )
+            .await?,
+        )
+        .await?;
 
-        if from_log_root.as_ref() != &from {
-            return Err(ClientError::IncorrectConsistencyProof {
-                root: from_log_root.into_owned(),
-                found: from,
-            });
-        }
+        core::verify_consistency(&response.proof, from_log_root, to_log_root).map_err(Into::into)
+    }
 
-        if to_log_root.as_ref() != &to {
-            return Err(ClientError::IncorrectConsistencyProof {
-                root: to_log_root.into_owned(),
-                found: to,
-            });
+    /// Uploads package content to the registry.
+    pub async fn upload_content(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &IndexMap<String, String>,
+        content: impl Into<Body>,
+    ) -> Result<(), ClientError> {
+        let response = self
+            .upload_request(method, url, headers)?
+            .body(content)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(ClientError::Package(
+                deserialize::<PackageError>(response).await?,
+            ));
         }
 
         Ok(())
     }
 
-    /// Uploads package content to the registry.
-    pub async fn upload_content(
+    /// Uploads a range of package content to the registry.
+    ///
+    /// Uploading a [`UploadContentRange::Status`] range sends no content and
+    /// reports the content the registry has received so far.
+    pub async fn upload_content_range(
         &self,
         method: &str,
         url: &str,
         headers: &IndexMap<String, String>,
+        range: UploadContentRange,
         content: impl Into<Body>,
-    ) -> Result<(), ClientError> {
+    ) -> Result<UploadProgress, ClientError> {
+        let response = self
+            .upload_request(method, url, headers)?
+            .header(CONTENT_RANGE, range.to_string())
+            .body(content)
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::ACCEPTED | StatusCode::RANGE_NOT_SATISFIABLE => {
+                Ok(UploadProgress::Received(
+                    response
+                        .headers()
+                        .get(RANGE)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(parse_received_range)
+                        .unwrap_or(0),
+                ))
+            }
+            status if status.is_success() => Ok(UploadProgress::Complete),
+            _ => Err(ClientError::Package(
+                deserialize::<PackageError>(response).await?,
+            )),
+        }
+    }
+
+    fn upload_request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &IndexMap<String, String>,
+    ) -> Result<RequestBuilder, ClientError> {
         // Upload URLs may be relative to the registry URL.
         let url = self.url.join(url);
 
@@ -556,60 +1216,116 @@ impl Client {
 
         tracing::debug!("uploading content to `{url}`");
 
-        let response = self
-            .client
-            .request(method, url)
-            .headers(headers)
-            .body(content)
-            .send()
-            .await?;
-        if !response.status().is_success() {
-            return Err(ClientError::Package(
-                deserialize::<PackageError>(response).await?,
-            ));
-        }
+        Ok(self.client.request(method, url).headers(headers))
+    }
 
-        Ok(())
+    /// Sends a request, retrying it with the client's retry policy while it
+    /// fails with a transient error.
+    ///
+    /// Only requests with an idempotent method are retried; use
+    /// [`Self::send_with_retry`] for other requests that are safe to repeat.
+    async fn send<E>(&self, request: RequestBuilder) -> Result<Response, ClientError>
+    where
+        E: DeserializeOwned + Into<ClientError>,
+    {
+        let request = request.build()?;
+        let policy = if request.method().is_idempotent() {
+            self.retry
+        } else {
+            RetryPolicy::never()
+        };
+
+        self.execute::<E>(request, policy).await
     }
 
-    fn validate_inclusion_response(
-        response: InclusionResponse,
-        checkpoint: &Checkpoint,
-        leafs: &[LogLeaf],
-    ) -> Result<(), ClientError> {
-        let log_proof_bundle: LogProofBundle<Sha256, LogLeaf> =
-            LogProofBundle::decode(response.log.as_slice())?;
-        let (log_data, _, log_inclusions) = log_proof_bundle.unbundle();
-        for (leaf, proof) in leafs.iter().zip(log_inclusions.iter()) {
-            let found = proof.evaluate_value(&log_data, leaf)?;
-            let root = checkpoint.log_root.clone().try_into()?;
-            if found != root {
-                return Err(ClientError::Proof(ProofError::IncorrectProof {
-                    root: checkpoint.log_root.clone(),
-                    found: found.into(),
-                }));
+    /// Sends a request that is safe to repeat, retrying it with the given
+    /// policy while it fails with a transient error.
+    async fn send_with_retry<E>(
+        &self,
+        request: RequestBuilder,
+        policy: RetryPolicy,
+    ) -> Result<Response, ClientError>
+    where
+        E: DeserializeOwned + Into<ClientError>,
+    {
+        self.execute::<E>(request.build()?, policy).await
+    }
+
+    async fn execute<E>(
+        &self,
+        request: Request,
+        policy: RetryPolicy,
+    ) -> Result<Response, ClientError>
+    where
+        E: DeserializeOwned + Into<ClientError>,
+    {
+        let mut attempts = 1;
+        loop {
+            // Requests with a streaming body cannot be repeated
+            let attempt = match request.try_clone() {
+                Some(attempt) if policy.should_retry(attempts) => attempt,
+                _ => return Self::finish::<E>(self.client.execute(request).await, attempts).await,
+            };
+
+            let res = self.client.execute(attempt).await;
+            let delay = match &res {
+                Err(e) if is_retryable_error(e) => policy.delay(attempts),
+                Ok(response) if is_retryable_status(response.status()) => {
+                    match retry_after(response) {
+                        // Waiting longer than the policy allows is not worth it
+                        Some(delay) if delay > policy.max_delay() => {
+                            return Self::finish::<E>(res, attempts).await
+                        }
+                        Some(delay) => delay,
+                        None => policy.delay(attempts),
+                    }
+                }
+                _ => return Self::finish::<E>(res, attempts).await,
+            };
+
+            match &res {
+                Ok(response) => tracing::debug!(
+                    "request to `{url}` failed with status {status} (attempt {attempts}), retrying in {delay:?}",
+                    url = request.url(),
+                    status = response.status()
+                ),
+                Err(e) => tracing::debug!(
+                    "request to `{url}` failed (attempt {attempts}), retrying in {delay:?}: {e}",
+                    url = request.url()
+                ),
             }
+
+            tokio::time::sleep(delay).await;
+            attempts += 1;
         }
+    }
 
-        let map_proof_bundle: MapProofBundle<Sha256, LogId, MapLeaf> =
-            MapProofBundle::decode(response.map.as_slice())?;
-        let map_inclusions = map_proof_bundle.unbundle();
-        for (leaf, proof) in leafs.iter().zip(map_inclusions.iter()) {
-            let found = proof.evaluate(
-                &leaf.log_id,
-                &MapLeaf {
-                    record_id: leaf.record_id.clone(),
-                },
-            );
-            let root = checkpoint.map_root.clone().try_into()?;
-            if found != root {
-                return Err(ClientError::Proof(ProofError::IncorrectProof {
-                    root: checkpoint.map_root.clone(),
-                    found: found.into(),
-                }));
+    /// Completes the last attempt of a request.
+    ///
+    /// If a request that was retried failed with a transient error again, the
+    /// error is returned along with the number of attempts; otherwise the
+    /// response is returned for the caller to handle.
+    async fn finish<E>(
+        res: reqwest::Result<Response>,
+        attempts: u32,
+    ) -> Result<Response, ClientError>
+    where
+        E: DeserializeOwned + Into<ClientError>,
+    {
+        let source = match res {
+            Ok(response) if attempts > 1 && is_retryable_status(response.status()) => {
+                match deserialize::<E>(response).await {
+                    Ok(e) => e.into(),
+                    Err(e) => e,
+                }
             }
-        }
+            Err(e) if attempts > 1 && is_retryable_error(&e) => ClientError::Communication(e),
+            res => return Ok(res?),
+        };
 
-        Ok(())
+        Err(ClientError::RetriesExhausted {
+            attempts,
+            source: Box::new(source),
+        })
     }
 }
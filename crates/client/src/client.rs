@@ -0,0 +1,3578 @@
+//! The client for Warg registries.
+
+use crate::api::{self, UploadProgress};
+use crate::config::{
+    self, Config, StaleCheckpointPolicy, StoragePaths, DEFAULT_CHECKPOINT_GRACE_PERIOD,
+};
+use crate::core::{self, CoreError, ValidatedLogs};
+use crate::depsolve::{Bundler, LockListBuilder};
+use crate::lockfile::{LockFile, LockedPackage};
+use crate::retry::RetryPolicy;
+use crate::signer::Signer;
+use crate::storage::PackageInfo;
+use crate::storage::{
+    ContentStorage, FileSystemContentStorage, FileSystemNamespaceMapStorage, LocalRegistryStorage,
+    NamespaceMapStorage, OperatorInfo, PublishEntry, PublishInfo, RegistryDomain,
+    RegistryStateEntry, RegistryStorage,
+};
+use crate::trust::TrustPolicy;
+use crate::version_util::{
+    kindless_name, locked_package, locked_release, resolve_release, versioned_package, Import,
+    ImportKind,
+};
+use crate::RegistryUrl;
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use indexmap::{IndexMap, IndexSet};
+use itertools::Itertools;
+use reqwest::header::HeaderValue;
+use reqwest::{Body, IntoUrl};
+use secrecy::Secret;
+use semver::{Version, VersionReq};
+use std::collections::HashMap;
+use std::fs;
+use std::io::SeekFrom;
+use std::str::FromStr;
+use std::{
+    borrow::Cow,
+    fmt,
+    ops::RangeBounds,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+use warg_api::v1::{
+    fetch::{FetchError, FetchLogsRequest, FetchLogsStreamItem, PublishedRecord},
+    operator::PublishOperatorRecordRequest,
+    package::{
+        MissingContent, PackageDependency, PackageDependent, PackageError, PackageLogStatus,
+        PackageRecord, PackageRecordState, PackageRecordSummary, PackageRecordsQuery,
+        PublishRecordRequest, UploadContentRange, UploadEndpoint,
+    },
+    proof::{ConsistencyRequest, InclusionRequest, ProofError},
+    registry::RecordLimits,
+};
+use warg_crypto::hash::HashAlgorithm;
+use warg_crypto::{hash::AnyHash, signing, Signable};
+use warg_protocol::package::ReleaseState;
+use warg_protocol::{
+    operator, package,
+    registry::{
+        Checkpoint, LogId, PackageName, RecordId, RegistryIndex, RegistryLen, TimestampedCheckpoint,
+    },
+    ProtoEnvelope, PublishedProtoEnvelope, SerdeEnvelope,
+};
+use wasm_compose::graph::{CompositionGraph, EncodeOptions, ExportIndex, InstanceId};
+
+/// The number of requests made to upload content before giving up.
+const MAX_UPLOAD_ATTEMPTS: u32 = 5;
+
+/// The amount of time to wait between checks for a record split from a
+/// publish to be published.
+const SPLIT_RECORD_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long the registry is asked to wait for a new checkpoint per request.
+const CHECKPOINT_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A client for a Warg registry.
+pub struct Client<R, C, N>
+where
+    R: RegistryStorage,
+    C: ContentStorage,
+    N: NamespaceMapStorage,
+{
+    registry: R,
+    content: C,
+    namespace_map: N,
+    api: api::Client,
+    home: api::Client,
+    namespace_registries: IndexMap<String, api::Client>,
+    routed: Option<RegistryDomain>,
+    stale_checkpoint: StaleCheckpointPolicy,
+    checkpoint_grace_period: Duration,
+    offline: bool,
+    include_revoked: bool,
+    allow_stale: bool,
+    trust_policy: TrustPolicy,
+    strict_trust: bool,
+    skip_prevalidation: bool,
+    backoff_polling: bool,
+    clock: Arc<dyn Fn() -> SystemTime + Send + Sync>,
+}
+
+impl<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage> Client<R, C, N> {
+    /// Creates a new client for the given URL, registry storage, and
+    /// content storage.
+    pub fn new(
+        url: impl IntoUrl,
+        registry: R,
+        content: C,
+        namespace_map: N,
+        auth_token: Option<Secret<String>>,
+    ) -> ClientResult<Self> {
+        let api = api::Client::new(url, auth_token)?;
+        Ok(Self {
+            registry,
+            content,
+            namespace_map,
+            home: api.clone(),
+            api,
+            namespace_registries: IndexMap::new(),
+            routed: None,
+            stale_checkpoint: StaleCheckpointPolicy::default(),
+            checkpoint_grace_period: DEFAULT_CHECKPOINT_GRACE_PERIOD,
+            offline: false,
+            include_revoked: false,
+            allow_stale: false,
+            trust_policy: TrustPolicy::default(),
+            strict_trust: false,
+            skip_prevalidation: false,
+            backoff_polling: false,
+            clock: Arc::new(SystemTime::now),
+        })
+    }
+
+    /// Sets how the client treats a registry whose latest checkpoint is older
+    /// than the registry's declared maximum checkpoint interval plus the
+    /// given grace period.
+    pub fn with_stale_checkpoint_policy(
+        mut self,
+        policy: StaleCheckpointPolicy,
+        grace_period: Duration,
+    ) -> Self {
+        self.stale_checkpoint = policy;
+        self.checkpoint_grace_period = grace_period;
+        self
+    }
+
+    /// Sets whether the client is in offline mode.
+    ///
+    /// An offline client never contacts the registry: package logs are
+    /// served as last validated in client storage and content is only
+    /// available if it was previously downloaded.
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Sets whether resolving a version requirement may select a release by
+    /// a key that was revoked as of the release.
+    ///
+    /// Such releases are not trusted and are skipped by default.
+    pub fn with_include_revoked(mut self, include_revoked: bool) -> Self {
+        self.include_revoked = include_revoked;
+        self
+    }
+
+    /// Sets whether the client uses the registry's state even if the latest
+    /// checkpoint has expired.
+    ///
+    /// A registry that stops issuing checkpoints could otherwise freeze the
+    /// client on stale state, so an expired checkpoint is an error by
+    /// default.
+    pub fn with_allow_stale(mut self, allow_stale: bool) -> Self {
+        self.allow_stale = allow_stale;
+        self
+    }
+
+    /// Sets the policy of the keys trusted to sign the records of packages.
+    ///
+    /// New records of a package with pinned keys that are signed by any other
+    /// key are reported with a warning when the package log is fetched.
+    pub fn with_trust_policy(mut self, policy: TrustPolicy) -> Self {
+        self.trust_policy = policy;
+        self
+    }
+
+    /// Sets whether a package record signed by a key that is not trusted by
+    /// the trust policy fails the fetch of the package log instead of being
+    /// reported with a warning.
+    pub fn with_strict_trust(mut self, strict_trust: bool) -> Self {
+        self.strict_trust = strict_trust;
+        self
+    }
+
+    /// Sets whether records are published without first being validated
+    /// against the package log in client storage.
+    ///
+    /// By default, a record the registry would reject is caught before it
+    /// is signed; skipping this is useful when client storage is known to
+    /// be out of date.
+    pub fn with_skip_prevalidation(mut self, skip: bool) -> Self {
+        self.skip_prevalidation = skip;
+        self
+    }
+
+    /// Sets whether waiting for a publish backs off between checks of the
+    /// record with the client's retry policy.
+    ///
+    /// By default, the record is checked at the fixed interval given to
+    /// `wait_for_publish`; with backoff, that interval is the delay before
+    /// the second check and later delays grow up to the policy's maximum
+    /// delay, reducing the load on the registry while waiting for a record
+    /// that takes long to publish.
+    pub fn with_backoff_polling(mut self, backoff: bool) -> Self {
+        self.backoff_polling = backoff;
+        self
+    }
+
+    /// Sets the clock the client checks checkpoints against.
+    ///
+    /// The system clock is used by default.
+    pub fn with_clock(mut self, clock: impl Fn() -> SystemTime + Send + Sync + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sets the policy for retrying requests to the registry that fail with a
+    /// transient error.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.api = self.api.with_retry_policy(policy);
+        self.home = self.home.clone().with_retry_policy(policy);
+        for api in self.namespace_registries.values_mut() {
+            *api = api.clone().with_retry_policy(policy);
+        }
+        self
+    }
+
+    /// Routes packages in namespaces matching the given pattern to the
+    /// registry at the given URL instead of the home registry.
+    ///
+    /// See [`Config::namespace_registries`] for the syntax of patterns. A
+    /// route replaces any existing route for the same pattern.
+    ///
+    /// The route takes effect when [`Client::refresh_namespace`] is called
+    /// for a matching namespace; client storage for the registry is kept
+    /// separately from the home registry's.
+    pub fn with_namespace_registry(
+        mut self,
+        pattern: impl Into<String>,
+        url: impl IntoUrl,
+        auth_token: Option<Secret<String>>,
+    ) -> ClientResult<Self> {
+        let api = api::Client::new(url, auth_token)?.with_retry_policy(self.home.retry_policy());
+        self.namespace_registries.insert(pattern.into(), api);
+        Ok(self)
+    }
+
+    /// Routes the namespaces of the given client configuration to their
+    /// registries.
+    fn with_config_namespace_registries(mut self, config: &Config) -> ClientResult<Self> {
+        for (pattern, registry) in &config.namespace_registries {
+            self = self.with_namespace_registry(
+                pattern.clone(),
+                config.resolve_registry(registry),
+                None,
+            )?;
+        }
+
+        Ok(self)
+    }
+
+    /// Determines if the client is in offline mode.
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Gets the URL of the registry the client is using.
+    ///
+    /// This is the home registry unless the namespace the client was last
+    /// refreshed for is routed to another registry.
+    pub fn url(&self) -> &RegistryUrl {
+        self.api.url()
+    }
+
+    /// Gets the URL of the registry the namespace the client was last
+    /// refreshed for is routed to, if it is not served by the home registry.
+    pub fn routed_registry(&self) -> Option<&RegistryUrl> {
+        self.routed.as_ref().map(|_| self.api.url())
+    }
+
+    /// Gets the registry storage used by the client.
+    pub fn registry(&self) -> &R {
+        &self.registry
+    }
+
+    /// Gets the content storage used by the client.
+    pub fn content(&self) -> &C {
+        &self.content
+    }
+
+    /// Gets the namespace map
+    pub fn namespace_map(&self) -> &N {
+        &self.namespace_map
+    }
+
+    /// Stores namespace mapping in local storage
+    pub async fn store_namespace(
+        &self,
+        namespace: String,
+        registry_domain: RegistryDomain,
+    ) -> Result<()> {
+        self.namespace_map
+            .store_namespace(namespace, registry_domain)
+            .await?;
+        Ok(())
+    }
+
+    /// Resets the namespace map
+    pub async fn reset_namespaces(&self) -> Result<()> {
+        self.namespace_map.reset_namespaces().await?;
+        Ok(())
+    }
+
+    /// Reset client storage for the registry.
+    pub async fn reset_registry(&self, all_registries: bool) -> ClientResult<()> {
+        tracing::info!("resetting registry local state");
+        self.registry
+            .reset(all_registries)
+            .await
+            .or(Err(ClientError::ResettingRegistryLocalStateFailed))
+    }
+
+    /// Clear client content cache.
+    pub async fn clear_content_cache(&self) -> ClientResult<()> {
+        tracing::info!("removing content cache");
+        self.content
+            .clear()
+            .await
+            .or(Err(ClientError::ClearContentCacheFailed))
+    }
+
+    /// Lists the local data that [`Client::reset_registry`] removes.
+    pub async fn registry_state(
+        &self,
+        all_registries: bool,
+    ) -> ClientResult<Vec<RegistryStateEntry>> {
+        Ok(self.registry.list_state(all_registries).await?)
+    }
+
+    /// Lists the cached content that is not referenced by any release known
+    /// to client storage, along with the size of each in bytes.
+    ///
+    /// Content of the releases of every registry in client storage and of
+    /// the pending publishes for the registry is considered referenced.
+    pub async fn unreferenced_content(&self) -> ClientResult<Vec<(AnyHash, u64)>> {
+        let mut referenced = IndexSet::new();
+        for packages in self.registry.load_all_packages().await?.values() {
+            for package in packages {
+                referenced.extend(
+                    package
+                        .state
+                        .releases()
+                        .flat_map(|r| r.artifacts().map(|(_, content)| content.clone())),
+                );
+            }
+        }
+
+        for publish in self.registry.load_publishes().await? {
+            for entry in publish.entries {
+                if let PublishEntry::Release {
+                    content, artifacts, ..
+                } = entry
+                {
+                    referenced.insert(content);
+                    referenced.extend(artifacts.into_values());
+                }
+            }
+        }
+
+        Ok(self
+            .content
+            .list_content()
+            .await?
+            .into_iter()
+            .filter(|(digest, _)| !referenced.contains(digest))
+            .collect())
+    }
+
+    /// Removes the cached content that is not referenced by any release
+    /// known to client storage.
+    ///
+    /// Returns the content that was removed along with the size of each in
+    /// bytes.
+    pub async fn prune_content(&self) -> ClientResult<Vec<(AnyHash, u64)>> {
+        tracing::info!("removing unreferenced content");
+        let mut removed = Vec::new();
+        for (digest, size) in self.unreferenced_content().await? {
+            if self.content.remove_content(&digest).await? {
+                removed.push((digest, size));
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Check operator log for namespace mapping
+    ///
+    /// Namespaces routed to another registry with
+    /// [`Client::with_namespace_registry`] are served by that registry
+    /// directly.
+    pub async fn refresh_namespace(&mut self, namespace: &str) -> ClientResult<()> {
+        if let Some(api) = config::match_namespace(&self.namespace_registries, namespace) {
+            tracing::debug!(
+                "namespace `{namespace}` is routed to registry `{url}`",
+                url = api.url()
+            );
+            self.api = api.clone();
+            self.routed = Some(RegistryDomain::from_str(&api.url().safe_label())?);
+            if !self.offline {
+                self.update_checkpoint(&self.api.latest_checkpoint().await?, vec![])
+                    .await?;
+            }
+            return Ok(());
+        }
+
+        if self.routed.take().is_some() {
+            self.api = self.home.clone();
+        }
+
+        // With namespace routes configured, the operator log is kept up to
+        // date to tell whether the home registry serves the namespace
+        if !self.offline {
+            self.update_checkpoint_with_operator(
+                &self.api.latest_checkpoint().await?,
+                vec![],
+                !self.namespace_registries.is_empty(),
+            )
+            .await?;
+        }
+        let operator = self.registry().load_operator(&None).await?;
+        let operator_log_maps_namespace = if let Some(op) = &operator {
+            let namespace_state = op.state.namespace_state(namespace);
+            if let Ok(Some(nm)) = namespace_state {
+                if let warg_protocol::operator::NamespaceState::Imported { registry } = nm {
+                    self.api
+                        .set_warg_registry(Some(RegistryDomain::from_str(registry)?));
+                }
+                true
+            } else {
+                false
+            }
+        } else {
+            false
+        };
+        if !operator_log_maps_namespace {
+            let map = self.namespace_map().load_namespace_map().await?;
+            let mapped = map.as_ref().and_then(|map| map.get(namespace));
+            if let Some(nm) = mapped {
+                self.api
+                    .set_warg_registry(Some(RegistryDomain::from_str(nm)?));
+            } else {
+                if map.is_some() {
+                    self.api.set_warg_registry(None);
+                }
+
+                // With namespace routes configured, a namespace the home
+                // registry does not serve must be routed explicitly
+                if operator.is_some() && !self.namespace_registries.is_empty() {
+                    return Err(ClientError::NamespaceNotRouted {
+                        namespace: namespace.to_string(),
+                        registry: self.home.url().clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the registry serving the given package according to the
+    /// namespace routes, along with the domain its client storage is keyed
+    /// by.
+    ///
+    /// Unlike [`Client::refresh_namespace`], the client is not changed and
+    /// the registry is not contacted.
+    pub(crate) async fn package_registry(
+        &self,
+        name: &PackageName,
+    ) -> ClientResult<(RegistryUrl, Option<RegistryDomain>)> {
+        let namespace = name.namespace();
+        if let Some(api) = config::match_namespace(&self.namespace_registries, namespace) {
+            let domain = RegistryDomain::from_str(&api.url().safe_label())?;
+            return Ok((api.url().clone(), Some(domain)));
+        }
+
+        // Other namespaces are served by the home registry, or by the
+        // registry it imports the namespace from
+        let operator = self.registry.load_operator(&None).await?;
+        let domain = match operator
+            .as_ref()
+            .map(|op| op.state.namespace_state(namespace))
+        {
+            Some(Ok(Some(operator::NamespaceState::Imported { registry }))) => {
+                Some(registry.clone())
+            }
+            Some(Ok(Some(_))) => None,
+            _ => self
+                .namespace_map
+                .load_namespace_map()
+                .await?
+                .and_then(|mut map| map.shift_remove(namespace)),
+        };
+
+        match domain {
+            Some(domain) => Ok((
+                RegistryUrl::new(&domain)?,
+                Some(RegistryDomain::from_str(&domain)?),
+            )),
+            None => Ok((self.home.url().clone(), None)),
+        }
+    }
+
+    /// Loads a package from the client storage of the registry serving it
+    /// according to the namespace routes.
+    pub(crate) async fn load_routed_package(
+        &self,
+        name: &PackageName,
+    ) -> ClientResult<Option<PackageInfo>> {
+        let (_, domain) = self.package_registry(name).await?;
+        Ok(self.registry.load_package(&domain, name).await?)
+    }
+
+    /// Gets the domain of the registry the client is using, if it is not the
+    /// home registry.
+    ///
+    /// Client storage for the registry is keyed by the domain. For a
+    /// namespace routed to another registry, this is the label of the
+    /// registry's URL; otherwise, it is the value of the `warg-registry`
+    /// header sent to the home registry.
+    pub fn get_warg_registry(&self) -> &Option<RegistryDomain> {
+        if self.routed.is_some() {
+            return &self.routed;
+        }
+
+        self.api.get_warg_registry()
+    }
+
+    /// Builds the list of packages to lock for a component
+    async fn lock_list(&self, info: &PackageInfo) -> ClientResult<IndexSet<Import>> {
+        let mut builder = LockListBuilder::default();
+        builder.build_list(self, info).await?;
+        let top = Import {
+            name: format!("{}:{}", info.name.namespace(), info.name.name()),
+            req: VersionReq::STAR,
+            kind: ImportKind::Unlocked,
+        };
+        builder.lock_list.insert(top);
+        Ok(builder.lock_list)
+    }
+
+    /// Resolves the packages of a locked component into a lock file
+    ///
+    /// Each package is recorded with the registry it was resolved from
+    /// according to the namespace routes.
+    pub async fn lock_file(&self, info: &PackageInfo) -> ClientResult<LockFile> {
+        let mut packages = Vec::new();
+        for package in self.lock_list(info).await? {
+            let id = PackageName::new(package.name)?;
+            let (registry, domain) = self.package_registry(&id).await?;
+            let registry = registry.to_string();
+            let info = self.registry().load_package(&domain, &id).await?;
+            if let Some(inf) = info {
+                if let Some(r) = locked_release(&inf.state, &package.req) {
+                    if let ReleaseState::Released { content, .. } = &r.state {
+                        packages.push(LockedPackage {
+                            name: id,
+                            version: r.version.clone(),
+                            content: content.clone(),
+                            registry: registry.clone(),
+                            checkpoint: inf
+                                .checkpoint
+                                .as_ref()
+                                .map(|c| AnyHash::of(c.log_root.algorithm(), c)),
+                            license: r.metadata.as_ref().and_then(|m| m.license.clone()),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(LockFile::new(packages))
+    }
+
+    /// Locks component
+    pub async fn lock_component(&self, info: &PackageInfo) -> ClientResult<Vec<u8>> {
+        let mut composer = CompositionGraph::new();
+        let mut handled = IndexMap::<String, InstanceId>::new();
+        for package in self.lock_list(info).await? {
+            let name = package.name.clone();
+            let version = package.req;
+            let id = PackageName::new(name)?;
+            let info = self
+                .registry()
+                .load_package(self.get_warg_registry(), &id)
+                .await?;
+            if let Some(inf) = info {
+                if let Some(r) = locked_release(&inf.state, &version) {
+                    let state = &r.state;
+                    if let ReleaseState::Released { content, .. } = state {
+                        let locked_package = locked_package(&package.name, r, content);
+                        let path = self.content().content_location(content);
+                        if let Some(p) = path {
+                            let bytes = fs::read(&p).map_err(|_| ClientError::ContentNotFound {
+                                digest: content.clone(),
+                            })?;
+
+                            let read_digest =
+                                AnyHash::from_str(&format!("sha256:{}", sha256::digest(bytes)))
+                                    .unwrap();
+                            if content != &read_digest {
+                                return Err(ClientError::IncorrectContent {
+                                    digest: read_digest,
+                                    expected: content.clone(),
+                                });
+                            }
+                            let component =
+                                wasm_compose::graph::Component::from_file(&locked_package, p)?;
+                            let component_id = if let Some((id, _)) =
+                                composer.get_component_by_name(&locked_package)
+                            {
+                                id
+                            } else {
+                                composer.add_component(component)?
+                            };
+                            let instance_id = composer.instantiate(component_id)?;
+                            let added = composer.get_component(component_id);
+                            handled.insert(versioned_package(&package.name, version), instance_id);
+                            let mut args = Vec::new();
+                            if let Some(added) = added {
+                                for (index, name, _) in added.imports() {
+                                    let iid = handled.get(kindless_name(name));
+                                    if let Some(arg) = iid {
+                                        args.push((arg, index));
+                                    }
+                                }
+                            }
+                            for arg in args {
+                                composer.connect(
+                                    *arg.0,
+                                    None::<ExportIndex>,
+                                    instance_id,
+                                    arg.1,
+                                )?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        let final_name = &format!("{}:{}", info.name.namespace(), &info.name.name());
+        let id = handled.get(final_name);
+        let options = EncodeOptions {
+            export: id.copied(),
+            ..Default::default()
+        };
+        let locked = composer.encode(options)?;
+        fs::write("./locked.wasm", locked.as_slice()).map_err(|e| ClientError::Other(e.into()))?;
+        Ok(locked)
+    }
+
+    /// Bundles component
+    pub async fn bundle_component(&self, info: &PackageInfo) -> ClientResult<Vec<u8>> {
+        let mut bundler = Bundler::new(self);
+        let path = PathBuf::from("./locked.wasm");
+        let locked = if !path.is_file() {
+            self.lock_component(info).await?
+        } else {
+            fs::read("./locked.wasm").map_err(|e| ClientError::Other(e.into()))?
+        };
+        let bundled = bundler.parse(&locked).await?;
+        fs::write("./bundled.wasm", bundled.as_slice())
+            .map_err(|e| ClientError::Other(e.into()))?;
+        Ok(bundled.as_slice().to_vec())
+    }
+
+    /// Submits the publish information in client storage.
+    ///
+    /// If there's no publishing information in client storage, an error is returned.
+    ///
+    /// Returns the identifier of the record that was published.
+    ///
+    /// Use `wait_for_publish` to wait for the record to transition to the `published` state.
+    pub async fn publish(&self, signer: &dyn Signer) -> ClientResult<RecordId> {
+        let info = self
+            .registry
+            .load_publish()
+            .await?
+            .ok_or(ClientError::NotPublishing)?;
+
+        let res = self.publish_with_info(signer, info).await;
+        self.registry.store_publish(None).await?;
+        res
+    }
+
+    /// Submits the provided publish information.
+    ///
+    /// Any publish information in client storage is ignored.
+    ///
+    /// If the entries would exceed the record limits advertised by the
+    /// registry, they are split across multiple records; each record but the
+    /// last is waited on until published before the next is submitted.
+    ///
+    /// Returns the identifier of the last record that was published.
+    ///
+    /// Use `wait_for_publish` to wait for the record to transition to the `published` state.
+    pub async fn publish_with_info(
+        &self,
+        signer: &dyn Signer,
+        info: PublishInfo,
+    ) -> ClientResult<RecordId> {
+        // TODO: this seems wrong to record the current time client-side
+        // How can we guarantee that the timestamps are monotonic?
+        // Should incrementing timestamps even be a requirement?
+        self.publish_with_info_at(signer, info, SystemTime::now())
+            .await
+    }
+
+    /// Submits the provided publish information with a record of the given
+    /// timestamp.
+    ///
+    /// This is used to submit the record previewed with `build_record`: given
+    /// the same timestamp and head, the submitted record has the previewed
+    /// identifier.
+    ///
+    /// If a head is not specified and the package is not being initialized,
+    /// the package log is first updated to find its latest head.
+    ///
+    /// See `publish_with_info` for how the record is submitted.
+    pub async fn publish_with_info_at(
+        &self,
+        signer: &dyn Signer,
+        mut info: PublishInfo,
+        timestamp: SystemTime,
+    ) -> ClientResult<RecordId> {
+        self.ensure_online()?;
+
+        tracing::info!(
+            "publishing {new}package `{name}`",
+            name = info.name,
+            new = if info.initializing() { "new " } else { "" }
+        );
+        tracing::debug!("entries: {:?}", info.entries);
+
+        if info.entries.is_empty() {
+            return Err(ClientError::NothingToPublish { name: info.name });
+        }
+
+        // If we're not initializing the package and a head was not explicitly specified,
+        // use the latest head.
+        if !info.initializing() && info.head.is_none() {
+            let mut package = self
+                .registry
+                .load_package(self.get_warg_registry(), &info.name)
+                .await?
+                .unwrap_or_else(|| PackageInfo::new(info.name.clone()));
+            self.update_checkpoint(&self.api.latest_checkpoint().await?, [&mut package])
+                .await?;
+            info.head = package.state.head().as_ref().map(|h| h.digest.clone());
+        }
+
+        let key = signer.public_key();
+        let mut record = new_record(&key, &info, self.hash_algorithm().await?, timestamp)?;
+        self.prevalidate_record(&key, &info.name, &record).await?;
+
+        let limits = self.api.registry_metadata().await?.record_limits;
+        loop {
+            let rest = split_record(&key, &limits, &info.name, &mut record)?;
+            let record_id = self.publish_record(signer, &info.name, record).await?;
+            if rest.is_empty() {
+                return Ok(record_id);
+            }
+
+            // The next record can only be verified once this one is in the log
+            tracing::info!(
+                "waiting for record `{record_id}` before publishing the remaining {len} entries of package `{name}`",
+                len = rest.len(),
+                name = info.name
+            );
+            let result = self
+                .wait_for_publish(&info.name, &record_id, SPLIT_RECORD_POLL_INTERVAL)
+                .await?;
+            if let Some(reason) = result.reason() {
+                return Err(ClientError::PublishRejected {
+                    name: info.name,
+                    record_id,
+                    reason,
+                });
+            }
+
+            record = package::PackageRecord {
+                prev: Some(record_id),
+                version: package::PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: rest,
+            };
+        }
+    }
+
+    /// Rebases a pending publish onto the latest head of its package log.
+    ///
+    /// This is used when a record built from the publish was rejected because
+    /// another record was published to the package in the meantime. The
+    /// package log is refreshed from the registry and the entries are checked
+    /// against it; a release of a version that has since been released fails
+    /// with [`ClientError::ReleaseConflict`] instead of being resubmitted.
+    ///
+    /// Returns the publish information with the latest head as its head.
+    pub async fn rebase_publish(&self, info: &PublishInfo) -> ClientResult<PublishInfo> {
+        self.ensure_online()?;
+
+        let mut package = self
+            .registry
+            .load_package(self.get_warg_registry(), &info.name)
+            .await?
+            .unwrap_or_else(|| PackageInfo::new(info.name.clone()));
+        self.update_checkpoint(&self.api.latest_checkpoint().await?, [&mut package])
+            .await?;
+
+        let head = package.state.head().as_ref().map(|h| h.digest.clone());
+        for entry in &info.entries {
+            match entry {
+                PublishEntry::Init if head.is_some() => {
+                    return Err(ClientError::CannotInitializePackage {
+                        name: info.name.clone(),
+                    })
+                }
+                PublishEntry::Release { version, .. }
+                    if package.state.release(version).is_some() =>
+                {
+                    return Err(ClientError::ReleaseConflict {
+                        name: info.name.clone(),
+                        version: version.clone(),
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        tracing::info!(
+            "rebasing publish of package `{name}` onto record `{head}`",
+            name = info.name,
+            head = head.as_ref().map(ToString::to_string).unwrap_or_default()
+        );
+
+        Ok(PublishInfo {
+            head,
+            ..info.clone()
+        })
+    }
+
+    /// Builds the package record that would be submitted for the provided
+    /// publish information with the given timestamp, without signing or
+    /// submitting it.
+    ///
+    /// Only client storage is used: if a head is not specified and the
+    /// package is not being initialized, the last known head of the package
+    /// log is used, and the record is hashed with the algorithm of the last
+    /// known checkpoint. The registry is not contacted.
+    ///
+    /// Submitting the record with `publish_with_info_at` and the same
+    /// timestamp yields a record with the same identifier, provided the
+    /// package log has not changed in the meantime.
+    ///
+    /// `key` is the public key of the key that will sign the record.
+    pub async fn build_record(
+        &self,
+        key: &signing::PublicKey,
+        info: &PublishInfo,
+        timestamp: SystemTime,
+    ) -> ClientResult<package::PackageRecord> {
+        let mut info = info.clone();
+        if !info.initializing() && info.head.is_none() {
+            info.head = self
+                .registry
+                .load_package(self.get_warg_registry(), &info.name)
+                .await?
+                .and_then(|package| package.state.head().as_ref().map(|h| h.digest.clone()));
+        }
+
+        let hash_algorithm = self
+            .registry
+            .load_checkpoint(self.get_warg_registry())
+            .await?
+            .ok_or(ClientError::NoStoredCheckpoint)?
+            .as_ref()
+            .checkpoint
+            .log_root
+            .algorithm();
+
+        new_record(key, &info, hash_algorithm, timestamp)
+    }
+
+    // Validates a record to be signed with the given key against the package
+    // log in client storage, failing with the reason the registry would
+    // reject the record for.
+    //
+    // The record is not validated if it does not follow the stored head of
+    // the package log, or if the package log is not stored and the record
+    // does not initialize it, as the stored log is then out of date.
+    async fn prevalidate_record(
+        &self,
+        key: &signing::PublicKey,
+        name: &PackageName,
+        record: &package::PackageRecord,
+    ) -> ClientResult<()> {
+        if self.skip_prevalidation {
+            return Ok(());
+        }
+
+        let state = match self
+            .registry
+            .load_package(self.get_warg_registry(), name)
+            .await?
+        {
+            Some(package) => package.state,
+            // A package not in client storage can only be checked if the
+            // record initializes it
+            None if matches!(
+                record.entries.first(),
+                Some(package::PackageEntry::Init { .. })
+            ) =>
+            {
+                Default::default()
+            }
+            None => {
+                tracing::debug!(
+                    "not validating the record for package `{name}` as its log is not stored"
+                );
+                return Ok(());
+            }
+        };
+        if state.head().as_ref().map(|head| &head.digest) != record.prev.as_ref() {
+            tracing::debug!(
+                "not validating the record for package `{name}` as its stored log is out of date"
+            );
+            return Ok(());
+        }
+
+        state
+            .validate_unsigned(&key.fingerprint(), record)
+            .map_err(|inner| ClientError::PrevalidationFailed {
+                name: name.clone(),
+                inner,
+            })?;
+        Ok(())
+    }
+
+    /// Signs and submits a package record built with `build_record`.
+    ///
+    /// The signature is not verified before the record is submitted, so a
+    /// record signed incorrectly by the signer is rejected by the registry.
+    ///
+    /// Returns the identifier of the record that was published.
+    ///
+    /// Use `wait_for_publish` to wait for the record to transition to the `published` state.
+    pub async fn publish_record(
+        &self,
+        signer: &dyn Signer,
+        name: &PackageName,
+        record: package::PackageRecord,
+    ) -> ClientResult<RecordId> {
+        self.ensure_online()?;
+
+        let hash_algorithm = self.hash_algorithm().await?;
+        let signature = signer.sign(&record.signing_payload()).await.map_err(|e| {
+            ClientError::SigningFailed {
+                name: name.clone(),
+                source: e,
+            }
+        })?;
+        let record =
+            ProtoEnvelope::from_signature(record, signer.public_key().fingerprint(), signature);
+        let log_id = LogId::package_log_for(hash_algorithm, name);
+        let record = self
+            .api
+            .publish_package_record(
+                &log_id,
+                PublishRecordRequest {
+                    package_name: Cow::Borrowed(name),
+                    record: Cow::Owned(record.into()),
+                    content_sources: Default::default(),
+                },
+            )
+            .await
+            .map_err(|e| {
+                ClientError::translate_log_not_found(e, |id| {
+                    if id == &log_id {
+                        Some(name.clone())
+                    } else {
+                        None
+                    }
+                })
+            })?;
+
+        // Ask for the content that is still missing just before uploading, so
+        // content the registry already has is never uploaded again
+        if record.missing_content().next().is_none() {
+            return Ok(record.record_id);
+        }
+
+        let missing: IndexMap<_, _> = match self
+            .api
+            .get_missing_content(&log_id, &record.record_id)
+            .await
+        {
+            Ok(missing) => missing.missing_content,
+            // Fall back to the content reported when the record was published
+            // for registries that do not support the query
+            Err(e) => {
+                tracing::debug!(
+                    "failed to get missing content of record `{record_id}`: {e}",
+                    record_id = record.record_id
+                );
+                record
+                    .missing_content()
+                    .map(|(digest, missing)| (digest.clone(), missing.clone()))
+                    .collect()
+            }
+        };
+
+        // TODO: parallelize this
+        for (digest, MissingContent { upload }) in &missing {
+            // Upload the missing content, if the registry supports it
+            let Some(UploadEndpoint::Http {
+                method,
+                url,
+                headers,
+            }) = upload.first()
+            else {
+                continue;
+            };
+
+            self.upload_content(method, url, headers, digest)
+                .await
+                .map_err(|e| match e {
+                    ClientError::Api(api::ClientError::Package(PackageError::Rejection(
+                        reason,
+                    ))) => ClientError::PublishRejected {
+                        name: name.clone(),
+                        record_id: record.record_id.clone(),
+                        reason,
+                    },
+                    e => e,
+                })?;
+        }
+
+        Ok(record.record_id)
+    }
+
+    /// Uploads content to the given upload endpoint.
+    ///
+    /// Content stored on disk is uploaded in ranges, so an interrupted upload
+    /// resumes from the last byte the registry received; this includes uploads
+    /// interrupted by a previous publish of the same content.
+    async fn upload_content(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &IndexMap<String, String>,
+        digest: &AnyHash,
+    ) -> ClientResult<()> {
+        let content_not_found = || ClientError::ContentNotFound {
+            digest: digest.clone(),
+        };
+
+        let Some(path) = self.content.content_location(digest) else {
+            let content = self
+                .content
+                .load_content(digest)
+                .await?
+                .ok_or_else(content_not_found)?;
+            self.api
+                .upload_content(method, url, headers, Body::wrap_stream(content))
+                .await?;
+            return Ok(());
+        };
+
+        let total = tokio::fs::metadata(&path)
+            .await
+            .map_err(|_| content_not_found())?
+            .len();
+
+        // The registry is first asked how much of the content it already has
+        let mut received = None;
+        for attempts in 1..=MAX_UPLOAD_ATTEMPTS {
+            let progress = match received {
+                None => {
+                    self.api
+                        .upload_content_range(
+                            method,
+                            url,
+                            headers,
+                            UploadContentRange::Status { total },
+                            Body::from(Vec::new()),
+                        )
+                        .await
+                }
+                Some(start) => {
+                    let mut file = tokio::fs::File::open(&path)
+                        .await
+                        .map_err(|_| content_not_found())?;
+                    file.seek(SeekFrom::Start(start)).await.with_context(|| {
+                        format!("failed to seek `{path}`", path = path.display())
+                    })?;
+
+                    self.api
+                        .upload_content_range(
+                            method,
+                            url,
+                            headers,
+                            UploadContentRange::Bytes {
+                                start,
+                                end: total - 1,
+                                total,
+                            },
+                            Body::wrap_stream(ReaderStream::new(file.take(total - start))),
+                        )
+                        .await
+                }
+            };
+
+            received = match progress {
+                Ok(UploadProgress::Complete) => return Ok(()),
+                Ok(UploadProgress::Received(received)) => Some(received),
+                Err(e) if e.is_transient() => {
+                    tracing::warn!("upload of content `{digest}` was interrupted: {e}");
+                    tokio::time::sleep(self.api.retry_policy().delay(attempts)).await;
+                    None
+                }
+                Err(e) => return Err(e.into()),
+            };
+        }
+
+        Err(ClientError::Other(anyhow!(
+            "failed to upload content `{digest}` after {MAX_UPLOAD_ATTEMPTS} attempts"
+        )))
+    }
+
+    /// Waits for a package record to be published or rejected.
+    ///
+    /// The record is checked every `interval`, unless the client backs off
+    /// between checks (see `with_backoff_polling`). A check that fails with a
+    /// transient error is retried with the client's retry policy.
+    ///
+    /// Returns the outcome of the publish; a rejected record is not an error.
+    pub async fn wait_for_publish(
+        &self,
+        package: &PackageName,
+        record_id: &RecordId,
+        interval: Duration,
+    ) -> ClientResult<PublishResult> {
+        self.wait_for_publish_with_progress(package, record_id, interval, |_| {})
+            .await
+    }
+
+    /// Waits for a package record to be published or rejected, reporting the
+    /// status of the package log while the record is processing.
+    ///
+    /// The record is checked every `interval`, unless the client backs off
+    /// between checks (see `with_backoff_polling`). A check that fails with a
+    /// transient error is retried with the client's retry policy.
+    ///
+    /// The `progress` callback is invoked after each check for which the
+    /// registry reported the status of the package log.
+    ///
+    /// Returns the outcome of the publish; a rejected record is not an error.
+    pub async fn wait_for_publish_with_progress(
+        &self,
+        package: &PackageName,
+        record_id: &RecordId,
+        interval: Duration,
+        mut progress: impl FnMut(&PackageLogStatus),
+    ) -> ClientResult<PublishResult> {
+        self.ensure_online()?;
+
+        let policy = self.api.retry_policy();
+        let backoff = policy
+            .with_base_delay(interval)
+            .with_max_delay(policy.max_delay().max(interval));
+
+        let log_id = LogId::package_log_for(self.hash_algorithm().await?, package);
+        let mut checks = 1;
+        loop {
+            let record = self.get_package_record(package, &log_id, record_id).await?;
+            if let Some(result) = PublishResult::from_state(record.state) {
+                return Ok(result);
+            }
+
+            if let Some(status) = &record.log_status {
+                progress(status);
+            }
+
+            let delay = if self.backoff_polling {
+                backoff.delay(checks)
+            } else {
+                interval
+            };
+            tokio::time::sleep(delay).await;
+            checks = checks.saturating_add(1);
+        }
+    }
+
+    /// Checks whether a package record has been published or rejected.
+    ///
+    /// Returns `Ok(None)` if the record is still processing.
+    pub async fn check_publish(
+        &self,
+        package: &PackageName,
+        record_id: &RecordId,
+    ) -> ClientResult<Option<PublishResult>> {
+        self.ensure_online()?;
+
+        let log_id = LogId::package_log_for(self.hash_algorithm().await?, package);
+        Ok(PublishResult::from_state(
+            self.get_package_record(package, &log_id, record_id)
+                .await?
+                .state,
+        ))
+    }
+
+    /// Checks if a version of a package has already been released with the given content.
+    ///
+    /// The package log is always refreshed from the registry before checking,
+    /// so stale client storage is never trusted.
+    ///
+    /// Returns `true` if the version was released with the same content and
+    /// `false` if the package or version does not exist.
+    ///
+    /// An error is returned if the version was released with different content
+    /// or has been yanked.
+    pub async fn release_exists(
+        &self,
+        package: &PackageName,
+        version: &Version,
+        content: &AnyHash,
+    ) -> ClientResult<bool> {
+        self.ensure_online()?;
+
+        let mut info = self
+            .registry
+            .load_package(self.get_warg_registry(), package)
+            .await?
+            .unwrap_or_else(|| PackageInfo::new(package.clone()));
+
+        match self
+            .update_checkpoint(&self.api.latest_checkpoint().await?, [&mut info])
+            .await
+        {
+            Ok(_) => {}
+            Err(
+                ClientError::PackageDoesNotExist { .. }
+                | ClientError::PackageDoesNotExistWithHint { .. },
+            ) => return Ok(false),
+            Err(e) => return Err(e),
+        }
+
+        let Some(release) = info.state.release(version) else {
+            return Ok(false);
+        };
+
+        match release.content() {
+            Some(existing) if existing == content => Ok(true),
+            Some(existing) => Err(ClientError::ReleaseContentMismatch {
+                name: package.clone(),
+                version: Box::new(version.clone()),
+                existing: existing.clone(),
+                content: content.clone(),
+            }),
+            None => Err(ClientError::ReleaseYanked {
+                name: package.clone(),
+                version: version.clone(),
+            }),
+        }
+    }
+
+    /// Updates every package log in every client registry storage to the latest registry checkpoint.
+    pub async fn update_all(&mut self) -> ClientResult<()> {
+        if self.offline {
+            tracing::info!("client is offline; using package logs in client storage");
+            return Ok(());
+        }
+
+        let packages = self.registry.load_all_packages().await?;
+        let checkpoints = self.api.latest_checkpoints(packages.keys()).await?;
+        self.update_checkpoints(checkpoints, packages).await?;
+        Ok(())
+    }
+
+    /// Updates every package log in client storage to the latest registry checkpoint.
+    pub async fn update(&self) -> ClientResult<()> {
+        if self.offline {
+            tracing::info!("client is offline; using package logs in client storage");
+            return Ok(());
+        }
+
+        tracing::info!("updating all packages to latest checkpoint");
+
+        let mut updating = self.registry.load_packages().await?;
+
+        self.update_checkpoint(&self.api.latest_checkpoint().await?, &mut updating)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Waits for the registry to issue a checkpoint other than the one with
+    /// the given identifier, then updates all packages in client storage to it.
+    ///
+    /// If `since` is `None`, the latest checkpoint is used without waiting.
+    ///
+    /// Returns the checkpoint the packages were updated to.
+    pub async fn wait_for_checkpoint(
+        &self,
+        since: Option<&AnyHash>,
+    ) -> ClientResult<SerdeEnvelope<TimestampedCheckpoint>> {
+        self.ensure_online()?;
+
+        let checkpoint = match since {
+            Some(since) => loop {
+                let checkpoint = self
+                    .api
+                    .wait_for_checkpoint(since, CHECKPOINT_WAIT_TIMEOUT)
+                    .await?;
+                if &AnyHash::of(since.algorithm(), &checkpoint.as_ref().checkpoint) != since {
+                    break checkpoint;
+                }
+            },
+            None => self.api.latest_checkpoint().await?,
+        };
+
+        let mut updating = self.registry.load_packages().await?;
+        self.update_checkpoint(&checkpoint, &mut updating).await?;
+
+        Ok(checkpoint)
+    }
+
+    /// Waits for the registry to sign a checkpoint with the given key, such as
+    /// after the operator key is rotated to it, then updates all packages in
+    /// client storage to that checkpoint.
+    ///
+    /// An error is returned if the registry signs a checkpoint with another
+    /// key once the operator log permits the given key to sign checkpoints.
+    ///
+    /// Returns the first checkpoint signed with the key.
+    pub async fn wait_for_operator_key(
+        &self,
+        key_id: &signing::KeyID,
+    ) -> ClientResult<SerdeEnvelope<TimestampedCheckpoint>> {
+        let algorithm = self.hash_algorithm().await?;
+        let mut checkpoint = self.wait_for_checkpoint(None).await?;
+        loop {
+            if checkpoint.key_id() == key_id {
+                return Ok(checkpoint);
+            }
+
+            // The registry should have switched keys if the operator log as of
+            // the checkpoint permits the key to sign it
+            let mut operator = self
+                .registry
+                .load_operator(self.get_warg_registry())
+                .await?
+                .unwrap_or_default();
+            self.fetch_logs_at(&checkpoint, &mut operator, &mut IndexMap::new())
+                .await?;
+            if operator
+                .state
+                .key_has_permission_to_sign_checkpoints(key_id, checkpoint.as_ref().timestamp)
+            {
+                return Err(ClientError::OperatorKeyNotInUse {
+                    key_id: key_id.clone(),
+                    found: checkpoint.key_id().clone(),
+                });
+            }
+
+            let since = AnyHash::of(algorithm, &checkpoint.as_ref().checkpoint);
+            checkpoint = self.wait_for_checkpoint(Some(&since)).await?;
+        }
+    }
+
+    /// Resolves a version requirement to a release of a package in client
+    /// storage.
+    ///
+    /// The package is not fetched from the registry; use [`Client::upsert`]
+    /// to update it first.
+    ///
+    /// See [`version_util::resolve_release`] for how a release is selected.
+    pub async fn resolve_version(
+        &self,
+        package: &PackageName,
+        requirement: &VersionReq,
+        include_yanked: bool,
+    ) -> ClientResult<Option<package::Release>> {
+        self.check_stored_checkpoint_expiry().await?;
+
+        Ok(self
+            .registry
+            .resolve_version(
+                self.get_warg_registry(),
+                package,
+                requirement,
+                include_yanked,
+                self.include_revoked,
+            )
+            .await?)
+    }
+
+    /// Inserts or updates the logs of the specified packages in client storage to
+    /// the latest registry checkpoint.
+    pub async fn upsert<'a, I>(&self, packages: I) -> Result<(), ClientError>
+    where
+        I: IntoIterator<Item = &'a PackageName>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        tracing::info!("updating specific packages to latest checkpoint");
+
+        let packages = packages.into_iter();
+        let mut updating = Vec::with_capacity(packages.len());
+        for package in packages {
+            match self
+                .registry
+                .load_package(self.get_warg_registry(), package)
+                .await?
+            {
+                Some(info) => updating.push(info),
+                None => {
+                    self.ensure_online()?;
+                    updating.push(PackageInfo::new(package.clone()));
+                }
+            }
+        }
+
+        if self.offline {
+            tracing::info!("client is offline; using package logs in client storage");
+            return Ok(());
+        }
+
+        self.update_checkpoint(&self.api.latest_checkpoint().await?, &mut updating)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Updates the operator log in client storage to the latest registry
+    /// checkpoint.
+    ///
+    /// Returns the operator records that were not previously in client storage.
+    pub async fn fetch_operator(
+        &self,
+    ) -> ClientResult<Vec<PublishedProtoEnvelope<operator::OperatorRecord>>> {
+        if self.offline {
+            tracing::info!("client is offline; using operator log in client storage");
+            return Ok(Vec::new());
+        }
+
+        let ts_checkpoint = self.api.latest_checkpoint().await?;
+        self.check_checkpoint_expiry(ts_checkpoint.as_ref())?;
+        let mut operator = self
+            .registry
+            .load_operator(self.get_warg_registry())
+            .await?
+            .unwrap_or_default();
+
+        let records = self
+            .fetch_logs_at(&ts_checkpoint, &mut operator, &mut IndexMap::new())
+            .await?
+            .operator;
+        self.check_checkpoint_freshness(&operator.state, &ts_checkpoint)?;
+
+        self.registry
+            .store_operator(self.get_warg_registry(), operator)
+            .await?;
+
+        Ok(records)
+    }
+
+    /// Fetches the given package logs up to the latest checkpoint.
+    ///
+    /// Packages not yet in client storage start being tracked. The package
+    /// logs are fetched together first; should that fail, each package log
+    /// is fetched on its own so that a failure to update one package does
+    /// not prevent updating the others.
+    ///
+    /// Returns the outcome of fetching each package log.
+    pub async fn fetch_packages<'a>(
+        &self,
+        packages: impl IntoIterator<Item = &'a PackageName>,
+    ) -> ClientResult<Vec<PackageFetch>> {
+        self.ensure_online()?;
+
+        let mut updating = Vec::new();
+        for name in packages {
+            updating.push(self.load_or_new_package(name).await?);
+        }
+        if updating.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ts_checkpoint = self.api.latest_checkpoint().await?;
+        let algorithm = ts_checkpoint.as_ref().checkpoint.log_root.algorithm();
+        let new_records = |counts: &IndexMap<LogId, usize>, name: &PackageName| {
+            counts
+                .get(&LogId::package_log_for(algorithm, name))
+                .copied()
+                .unwrap_or(0)
+        };
+
+        let batched = self
+            .update_checkpoint(&ts_checkpoint, updating.iter_mut())
+            .await;
+        let counts = match batched {
+            Ok(counts) => counts,
+            Err(e) if updating.len() == 1 => {
+                let info = updating.pop().unwrap();
+                return Ok(vec![self.failed_fetch(info.name, e).await?]);
+            }
+            Err(e) => {
+                tracing::warn!("failed to fetch package logs together, fetching each log: {e}");
+
+                let mut fetches = Vec::with_capacity(updating.len());
+                for info in updating {
+                    let mut info = self.load_or_new_package(&info.name).await?;
+                    fetches.push(
+                        match self.update_checkpoint(&ts_checkpoint, [&mut info]).await {
+                            Ok(counts) => PackageFetch {
+                                new_records: Ok(new_records(&counts, &info.name)),
+                                head: info.state.head().as_ref().map(|h| h.digest.clone()),
+                                name: info.name,
+                            },
+                            Err(e) => self.failed_fetch(info.name, e).await?,
+                        },
+                    );
+                }
+                return Ok(fetches);
+            }
+        };
+
+        Ok(updating
+            .into_iter()
+            .map(|info| PackageFetch {
+                head: info.state.head().as_ref().map(|h| h.digest.clone()),
+                new_records: Ok(new_records(&counts, &info.name)),
+                name: info.name,
+            })
+            .collect())
+    }
+
+    /// Fetches the operator log and every package log in client storage up
+    /// to the latest checkpoint.
+    ///
+    /// See [`Client::fetch_packages`] for how failures to update individual
+    /// package logs are reported.
+    ///
+    /// Returns the new operator records and the outcome of fetching each
+    /// package log.
+    pub async fn fetch_all(
+        &self,
+    ) -> ClientResult<(
+        Vec<PublishedProtoEnvelope<operator::OperatorRecord>>,
+        Vec<PackageFetch>,
+    )> {
+        self.ensure_online()?;
+
+        let operator = self.fetch_operator().await?;
+        let names = self
+            .registry
+            .load_packages()
+            .await?
+            .into_iter()
+            .map(|info| info.name)
+            .collect::<Vec<_>>();
+        let packages = self.fetch_packages(&names).await?;
+        Ok((operator, packages))
+    }
+
+    /// Records a failure to fetch the given package log, reporting the head of
+    /// the log as it remains in client storage.
+    async fn failed_fetch(
+        &self,
+        name: PackageName,
+        error: ClientError,
+    ) -> ClientResult<PackageFetch> {
+        let info = self.load_or_new_package(&name).await?;
+        Ok(PackageFetch {
+            name,
+            new_records: Err(error),
+            head: info.state.head().as_ref().map(|h| h.digest.clone()),
+        })
+    }
+
+    /// Loads the given package from client storage, or creates a new package
+    /// to track if it is not yet in storage.
+    async fn load_or_new_package(&self, name: &PackageName) -> ClientResult<PackageInfo> {
+        Ok(self
+            .registry
+            .load_package(self.get_warg_registry(), name)
+            .await?
+            .unwrap_or_else(|| PackageInfo::new(name.clone())))
+    }
+
+    /// Signs and publishes an operator record with the given entries.
+    ///
+    /// The operator log is fetched first so that the record follows the head
+    /// of the log as of the latest checkpoint.
+    ///
+    /// Returns the identifier of the published record.
+    pub async fn publish_operator_record(
+        &self,
+        signing_key: &signing::PrivateKey,
+        entries: Vec<operator::OperatorEntry>,
+    ) -> ClientResult<RecordId> {
+        self.ensure_online()?;
+        self.fetch_operator().await?;
+        let operator = self
+            .registry
+            .load_operator(self.get_warg_registry())
+            .await?
+            .unwrap_or_default();
+        let head = operator
+            .state
+            .head()
+            .as_ref()
+            .ok_or(ClientError::NoOperatorRecords)?;
+
+        let record = operator::OperatorRecord {
+            prev: Some(head.digest.clone()),
+            version: operator::OPERATOR_RECORD_VERSION,
+            timestamp: SystemTime::now().max(head.timestamp),
+            entries,
+        };
+        let record =
+            ProtoEnvelope::signed_contents(signing_key, record).map_err(anyhow::Error::from)?;
+
+        let response = self
+            .api
+            .publish_operator_record(PublishOperatorRecordRequest {
+                record: Cow::Owned(record.into()),
+            })
+            .await?;
+
+        Ok(response.record_id)
+    }
+
+    /// Downloads the latest version of a package into client storage that
+    /// satisfies the given version requirement.
+    ///
+    /// If the requested package log is not present in client storage, it
+    /// will be fetched from the registry first.
+    ///
+    /// An error is returned if the package does not exist.
+    ///
+    /// If a version satisfying the requirement does not exist, `None` is
+    /// returned.
+    ///
+    /// Returns the path within client storage of the package contents for
+    /// the resolved version.
+    pub async fn download(
+        &self,
+        name: &PackageName,
+        requirement: &VersionReq,
+    ) -> Result<Option<PackageDownload>, ClientError> {
+        match self.resolve_download(name, requirement).await? {
+            Some((version, digest)) => {
+                let path = self.download_content(&digest).await?;
+                Ok(Some(PackageDownload {
+                    version,
+                    digest,
+                    path,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Resolves the latest version of a package that satisfies the given
+    /// version requirement without downloading its content.
+    ///
+    /// If the requested package log is not present in client storage, it
+    /// will be fetched from the registry first.
+    ///
+    /// An error is returned if the package does not exist.
+    ///
+    /// Returns the version and content digest of the resolved release, or
+    /// `None` if a version satisfying the requirement does not exist.
+    pub async fn resolve_download(
+        &self,
+        name: &PackageName,
+        requirement: &VersionReq,
+    ) -> ClientResult<Option<(Version, AnyHash)>> {
+        self.resolve_artifact_download(name, requirement, package::COMPONENT_ARTIFACT)
+            .await
+    }
+
+    /// Resolves the latest version of a package that satisfies the given
+    /// version requirement, like [`Client::resolve_download`], returning the
+    /// content digest of the release's artifact with the given name.
+    ///
+    /// An error is returned if the resolved release has no such artifact.
+    pub async fn resolve_artifact_download(
+        &self,
+        name: &PackageName,
+        requirement: &VersionReq,
+        artifact: &str,
+    ) -> ClientResult<Option<(Version, AnyHash)>> {
+        tracing::info!("downloading package `{name}` with requirement `{requirement}`");
+        let info = self.fetch_package(name).await?;
+        release_content(&info, requirement, artifact, self.include_revoked)
+    }
+
+    /// Verifies that the given file is the content of a package release,
+    /// as recorded in the registry's transparency log.
+    ///
+    /// The operator log and the package log are fetched and validated up to
+    /// the registry's latest checkpoint without using or updating client
+    /// storage; the checkpoint's signature is verified against the operator
+    /// log and the log heads are proven included in the checkpoint.
+    ///
+    /// A check that depends on a check that failed is skipped.
+    ///
+    /// An error is returned if the file could not be read or the registry
+    /// could not be contacted for the latest checkpoint.
+    pub async fn verify_release(
+        &self,
+        name: &PackageName,
+        version: &Version,
+        path: &Path,
+    ) -> ClientResult<ReleaseVerification> {
+        self.ensure_online()?;
+
+        let ts_checkpoint = self.api.latest_checkpoint().await?;
+        self.check_checkpoint_expiry(ts_checkpoint.as_ref())?;
+        let checkpoint = &ts_checkpoint.as_ref().checkpoint;
+        let mut verification = ReleaseVerification {
+            log_length: checkpoint.log_length,
+            digest: None,
+            checks: Vec::new(),
+        };
+
+        let mut operator = OperatorInfo::default();
+        let mut package = PackageInfo::new(name.clone());
+        let mut packages = IndexMap::from([(
+            LogId::package_log_for(checkpoint.log_root.algorithm(), name),
+            &mut package,
+        )]);
+        let validated = self
+            .validate_logs_at(&ts_checkpoint, &mut operator, &mut packages)
+            .await
+            .map(|_| ());
+        let valid = validated.is_ok();
+        verification.record(VerificationCheck::PackageLog, Some(validated));
+        if !valid {
+            verification.skip([
+                VerificationCheck::ReleaseContent,
+                VerificationCheck::CheckpointSignature,
+                VerificationCheck::InclusionProof,
+            ]);
+            return Ok(verification);
+        }
+
+        // The file is hashed with the algorithm of the released content
+        let released = match packages[0].state.release(version) {
+            Some(release) => match release.content() {
+                Some(content) => Ok(content.clone()),
+                None => Err(ClientError::Other(anyhow!(
+                    "version `{version}` of package `{name}` has been yanked"
+                ))),
+            },
+            None => Err(ClientError::PackageVersionDoesNotExist {
+                version: version.clone(),
+                name: name.clone(),
+            }),
+        };
+        let content = match released {
+            Ok(expected) => {
+                let digest = hash_file(path, expected.algorithm()).await?;
+                verification.digest = Some(digest.clone());
+                if digest == expected {
+                    Ok(())
+                } else {
+                    Err(ClientError::ContentDoesNotMatchRelease {
+                        name: name.clone(),
+                        version: Box::new(version.clone()),
+                        digest,
+                        expected,
+                    })
+                }
+            }
+            Err(e) => Err(e),
+        };
+        verification.record(VerificationCheck::ReleaseContent, Some(content));
+
+        let signed = core::verify_checkpoint_signature(&operator.state, &ts_checkpoint)
+            .map_err(ClientError::from);
+        let trusted = signed.is_ok();
+        verification.record(VerificationCheck::CheckpointSignature, Some(signed));
+
+        // Proving inclusion in a checkpoint that is not trusted proves nothing
+        let included = if trusted {
+            Some(self.prove_log_heads(checkpoint, &operator, &packages).await)
+        } else {
+            None
+        };
+        verification.record(VerificationCheck::InclusionProof, included);
+
+        Ok(verification)
+    }
+
+    /// Downloads the specified version of a package into client storage.
+    ///
+    /// If the requested package log is not present in client storage, it
+    /// will be fetched from the registry first.
+    ///
+    /// An error is returned if the package does not exist.
+    ///
+    /// Returns the path within client storage of the package contents for
+    /// the specified version.
+    pub async fn download_exact(
+        &self,
+        package: &PackageName,
+        version: &Version,
+    ) -> Result<PackageDownload, ClientError> {
+        tracing::info!("downloading version {version} of package `{package}`");
+        let info = self.fetch_package(package).await?;
+
+        let release =
+            info.state
+                .release(version)
+                .ok_or_else(|| ClientError::PackageVersionDoesNotExist {
+                    version: version.clone(),
+                    name: package.clone(),
+                })?;
+
+        let digest = release
+            .content()
+            .ok_or_else(|| ClientError::PackageVersionDoesNotExist {
+                version: version.clone(),
+                name: package.clone(),
+            })?;
+
+        Ok(PackageDownload {
+            version: version.clone(),
+            digest: digest.clone(),
+            path: self.download_content(digest).await?,
+        })
+    }
+
+    /// Gets the state of a package as of a historical checkpoint.
+    ///
+    /// The operator and package logs are fetched only up to the checkpoint
+    /// and validated into new log states; the checkpoint's signature and the
+    /// inclusion of the log heads in the checkpoint are verified.
+    ///
+    /// The package state in client storage is neither used nor modified.
+    pub async fn package_at_checkpoint(
+        &self,
+        name: &PackageName,
+        checkpoint_id: &AnyHash,
+    ) -> ClientResult<PackageInfo> {
+        tracing::info!("fetching package `{name}` as of checkpoint `{checkpoint_id}`");
+        let (_, info) = self.fetch_package_at(name, checkpoint_id).await?;
+        Ok(info)
+    }
+
+    /// Downloads the latest version of a package satisfying the requirement
+    /// as of a historical checkpoint.
+    ///
+    /// The version is resolved from the package state as of the checkpoint;
+    /// see [`Client::package_at_checkpoint`].
+    ///
+    /// If a version satisfying the requirement did not exist as of the
+    /// checkpoint, `None` is returned.
+    pub async fn download_at_checkpoint(
+        &self,
+        name: &PackageName,
+        requirement: &VersionReq,
+        checkpoint_id: &AnyHash,
+    ) -> ClientResult<Option<PackageDownload>> {
+        match self
+            .resolve_download_at_checkpoint(name, requirement, checkpoint_id)
+            .await?
+        {
+            Some((version, digest)) => {
+                let path = self.download_content(&digest).await?;
+                Ok(Some(PackageDownload {
+                    version,
+                    digest,
+                    path,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Resolves the latest version of a package satisfying the requirement
+    /// as of a historical checkpoint without downloading its content.
+    ///
+    /// See [`Client::download_at_checkpoint`].
+    pub async fn resolve_download_at_checkpoint(
+        &self,
+        name: &PackageName,
+        requirement: &VersionReq,
+        checkpoint_id: &AnyHash,
+    ) -> ClientResult<Option<(Version, AnyHash)>> {
+        self.resolve_artifact_download_at_checkpoint(
+            name,
+            requirement,
+            checkpoint_id,
+            package::COMPONENT_ARTIFACT,
+        )
+        .await
+    }
+
+    /// Resolves the latest version of a package satisfying the requirement
+    /// as of a historical checkpoint, returning the content digest of the
+    /// release's artifact with the given name.
+    ///
+    /// See [`Client::resolve_artifact_download`].
+    pub async fn resolve_artifact_download_at_checkpoint(
+        &self,
+        name: &PackageName,
+        requirement: &VersionReq,
+        checkpoint_id: &AnyHash,
+        artifact: &str,
+    ) -> ClientResult<Option<(Version, AnyHash)>> {
+        tracing::info!(
+            "downloading package `{name}` with requirement `{requirement}` as of checkpoint `{checkpoint_id}`"
+        );
+        let (operator, info) = self.fetch_package_at(name, checkpoint_id).await?;
+
+        if let Some(reason) = operator
+            .state
+            .package_suppression(&LogId::package_log_for(checkpoint_id.algorithm(), name))
+        {
+            return Err(ClientError::PackageSuppressed {
+                name: name.clone(),
+                reason: reason.to_string(),
+            });
+        }
+
+        release_content(&info, requirement, artifact, self.include_revoked)
+    }
+
+    /// Gets the dependencies of a package release as analyzed by the registry.
+    ///
+    /// The dependencies are derived by the registry from the imports of the
+    /// released component; they are not verified by the client.
+    pub async fn remote_dependencies(
+        &self,
+        package: &PackageName,
+        version: &Version,
+    ) -> ClientResult<Vec<PackageDependency>> {
+        self.ensure_online()?;
+
+        let log_id = LogId::package_log_for(self.hash_algorithm().await?, package);
+        self.api
+            .package_dependencies(&log_id, version)
+            .await
+            .map(|res| res.dependencies)
+            .map_err(|e| {
+                ClientError::translate_log_not_found(e, |id| {
+                    if id == &log_id {
+                        Some(package.clone())
+                    } else {
+                        None
+                    }
+                })
+            })
+    }
+
+    /// Gets the package releases that depend on a package as analyzed by the registry.
+    ///
+    /// The package does not need to exist in the registry.
+    pub async fn remote_dependents(
+        &self,
+        package: &PackageName,
+    ) -> ClientResult<Vec<PackageDependent>> {
+        self.ensure_online()?;
+
+        Ok(self
+            .api
+            .package_dependents(&LogId::package_log_for(
+                self.hash_algorithm().await?,
+                package,
+            ))
+            .await?
+            .dependents)
+    }
+
+    /// Gets the published record history of a package as summarized by the registry.
+    ///
+    /// The summaries are decoded by the registry; they are not verified by the client.
+    pub async fn remote_record_history(
+        &self,
+        package: &PackageName,
+    ) -> ClientResult<Vec<PackageRecordSummary>> {
+        self.ensure_online()?;
+
+        let log_id = LogId::package_log_for(self.hash_algorithm().await?, package);
+        let mut records = Vec::new();
+        let mut query = PackageRecordsQuery::default();
+        loop {
+            let page = self
+                .api
+                .package_records(package, &query)
+                .await
+                .map_err(|e| {
+                    ClientError::translate_log_not_found(e, |id| {
+                        if id == &log_id {
+                            Some(package.clone())
+                        } else {
+                            None
+                        }
+                    })
+                })?;
+            records.extend(page.records);
+
+            match page.next {
+                Some(next) => query.since = Some(next),
+                None => return Ok(records),
+            }
+        }
+    }
+
+    /// Update checkpoint for list of packages
+    ///
+    /// Returns the number of new records validated for each package log that
+    /// was updated.
+    async fn update_checkpoint<'a>(
+        &self,
+        ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
+        packages: impl IntoIterator<Item = &mut PackageInfo>,
+    ) -> Result<IndexMap<LogId, usize>, ClientError> {
+        self.update_checkpoint_with_operator(ts_checkpoint, packages, false)
+            .await
+    }
+
+    /// Update checkpoint for list of packages, also updating the operator
+    /// log when `operator` is set even if no package log needs updating.
+    ///
+    /// Returns the number of new records validated for each package log that
+    /// was updated.
+    async fn update_checkpoint_with_operator<'a>(
+        &self,
+        ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
+        packages: impl IntoIterator<Item = &mut PackageInfo>,
+        operator: bool,
+    ) -> Result<IndexMap<LogId, usize>, ClientError> {
+        let checkpoint = &ts_checkpoint.as_ref().checkpoint;
+        tracing::info!(
+            "updating to checkpoint log length `{}`",
+            checkpoint.log_length
+        );
+        self.check_checkpoint_expiry(ts_checkpoint.as_ref())?;
+
+        // Refuse a checkpoint that does not follow the one previously
+        // received before fetching or storing anything
+        let from = self
+            .registry
+            .load_checkpoint(self.get_warg_registry())
+            .await?;
+        let prove_consistency = match &from {
+            Some(from) => core::check_checkpoint_succession(from.as_ref(), ts_checkpoint.as_ref())?,
+            None => false,
+        };
+        let operator_outdated = operator
+            && from
+                .as_ref()
+                .map_or(true, |from| from.as_ref().checkpoint != *checkpoint);
+
+        let mut operator = self
+            .registry
+            .load_operator(self.get_warg_registry())
+            .await?
+            .unwrap_or_default();
+
+        // Map package names to package logs that need to be updated
+        let mut packages = packages
+            .into_iter()
+            .filter_map(|p| match &p.checkpoint {
+                // Don't bother updating if the package is already at the specified checkpoint
+                Some(c) if c == checkpoint => None,
+                _ => Some((
+                    LogId::package_log_for(checkpoint.log_root.algorithm(), &p.name),
+                    p,
+                )),
+            })
+            .inspect(|(_, p)| tracing::info!("package `{name}` will be updated", name = p.name))
+            .collect::<IndexMap<_, _>>();
+        if packages.is_empty() && !operator_outdated {
+            self.check_checkpoint_freshness(&operator.state, ts_checkpoint)?;
+
+            // The registry re-signs its latest checkpoint before it expires;
+            // keep the re-signed checkpoint so stored state does not expire
+            if let Some(from) = &from {
+                if from.as_ref().checkpoint == *checkpoint
+                    && from.as_ref() != ts_checkpoint.as_ref()
+                {
+                    core::verify_checkpoint_signature(&operator.state, ts_checkpoint)?;
+                    self.registry
+                        .store_checkpoint(self.get_warg_registry(), ts_checkpoint)
+                        .await?;
+                }
+            }
+
+            return Ok(IndexMap::new());
+        }
+
+        let validated = self
+            .fetch_logs_at(ts_checkpoint, &mut operator, &mut packages)
+            .await?;
+
+        self.check_checkpoint_freshness(&operator.state, ts_checkpoint)?;
+
+        if let (true, Some(from)) = (prove_consistency, &from) {
+            self.api
+                .prove_log_consistency(
+                    ConsistencyRequest {
+                        from: from.as_ref().checkpoint.log_length,
+                        to: checkpoint.log_length,
+                    },
+                    Cow::Borrowed(&from.as_ref().checkpoint.log_root),
+                    Cow::Borrowed(&checkpoint.log_root),
+                )
+                .await
+                .map_err(ClientError::translate_invalid_proof)?;
+        }
+
+        // Every proof has been verified; only now is the new state persisted
+        self.registry
+            .store_operator(self.get_warg_registry(), operator)
+            .await?;
+
+        for package in packages.values_mut() {
+            package.checkpoint = Some(checkpoint.clone());
+            self.registry
+                .store_package(self.get_warg_registry(), package)
+                .await?;
+        }
+
+        self.registry
+            .store_checkpoint(self.get_warg_registry(), ts_checkpoint)
+            .await?;
+
+        Ok(validated.packages)
+    }
+
+    /// Fetches the operator log and the given package logs up to the given
+    /// checkpoint, validating the records into the given states.
+    ///
+    /// The checkpoint's signature and the inclusion of the log heads in the
+    /// checkpoint are verified; nothing is persisted to storage.
+    ///
+    /// Returns the records that were validated.
+    async fn fetch_logs_at(
+        &self,
+        ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
+        operator: &mut OperatorInfo,
+        packages: &mut IndexMap<LogId, &mut PackageInfo>,
+    ) -> Result<ValidatedLogs, ClientError> {
+        let validated = self
+            .validate_logs_at(ts_checkpoint, operator, packages)
+            .await?;
+        core::verify_checkpoint_signature(&operator.state, ts_checkpoint)?;
+        self.prove_log_heads(&ts_checkpoint.as_ref().checkpoint, operator, packages)
+            .await?;
+        Ok(validated)
+    }
+
+    /// Fetches the operator log and the given package logs up to the given
+    /// checkpoint, validating the records into the given states.
+    ///
+    /// Neither the checkpoint nor the inclusion of the records in it is
+    /// verified.
+    ///
+    /// Returns the records that were validated.
+    async fn validate_logs_at(
+        &self,
+        ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
+        operator: &mut OperatorInfo,
+        packages: &mut IndexMap<LogId, &mut PackageInfo>,
+    ) -> Result<ValidatedLogs, ClientError> {
+        let checkpoint = &ts_checkpoint.as_ref().checkpoint;
+        let mut validated = ValidatedLogs::new(packages.keys());
+
+        let mut last_known = packages
+            .iter()
+            .map(|(id, p)| (id.clone(), p.head_fetch_token.clone()))
+            .collect::<IndexMap<_, _>>();
+
+        loop {
+            let items = self
+                .api
+                .fetch_logs_stream(FetchLogsRequest {
+                    log_length: checkpoint.log_length,
+                    operator: operator
+                        .head_fetch_token
+                        .as_ref()
+                        .map(|t| Cow::Borrowed(t.as_str())),
+                    limit: None,
+                    total_limit: None,
+                    packages: Cow::Borrowed(&last_known),
+                })
+                .await
+                .map_err(|e| {
+                    ClientError::translate_log_not_found(e, |id| {
+                        packages.get(id).map(|p| p.name.clone())
+                    })
+                })?;
+            futures_util::pin_mut!(items);
+
+            // Each record is validated as it arrives, so only the states are
+            // held in memory regardless of the number of records fetched
+            let mut more = None;
+            while let Some(item) = items.try_next().await.map_err(|e| {
+                ClientError::translate_log_not_found(e, |id| {
+                    packages.get(id).map(|p| p.name.clone())
+                })
+            })? {
+                match item {
+                    FetchLogsStreamItem::Operator { record } => {
+                        core::validate_fetched_operator_record(record, operator, &mut validated)?;
+                    }
+                    FetchLogsStreamItem::Package { log_id, record } => {
+                        self.check_trusted_publisher(
+                            checkpoint.log_root.algorithm(),
+                            &log_id,
+                            &record,
+                            packages,
+                        )?;
+                        core::validate_fetched_package_record(
+                            &log_id,
+                            record,
+                            packages,
+                            &mut validated,
+                        )?;
+                    }
+                    FetchLogsStreamItem::End { more: m } => {
+                        more = Some(m);
+                        break;
+                    }
+                    FetchLogsStreamItem::Error { error } => {
+                        return Err(api::ClientError::Fetch(error).into())
+                    }
+                }
+            }
+
+            let Some(more) = more else {
+                return Err(ClientError::Other(anyhow!(
+                    "the registry ended the fetch logs response unexpectedly"
+                )));
+            };
+
+            if !more {
+                break;
+            }
+
+            // Update the last known record fetch token for each package log
+            for (id, fetch_token) in last_known.iter_mut() {
+                *fetch_token = packages[id].head_fetch_token.clone();
+            }
+        }
+
+        // At this point, no package log should be empty
+        for package in packages.values() {
+            core::check_package_log_not_empty(package)?;
+        }
+
+        Ok(validated)
+    }
+
+    /// Checks that a new package record of the given log is signed by a key
+    /// the trust policy trusts for its package.
+    ///
+    /// An untrusted record is only an error if trust is strict.
+    fn check_trusted_publisher(
+        &self,
+        algorithm: HashAlgorithm,
+        log_id: &LogId,
+        record: &PublishedRecord,
+        packages: &IndexMap<LogId, &mut PackageInfo>,
+    ) -> Result<(), ClientError> {
+        let Some(package) = packages.get(log_id) else {
+            return Ok(());
+        };
+        let Some(trusted) = self.trust_policy.trusted_keys(&package.name) else {
+            return Ok(());
+        };
+
+        let record: PublishedProtoEnvelope<package::PackageRecord> =
+            record.envelope.clone().try_into()?;
+        let key_id = record.envelope.key_id();
+        if trusted.contains(key_id)
+            || package
+                .head_registry_index
+                .map_or(false, |head| record.registry_index <= head)
+        {
+            return Ok(());
+        }
+
+        let err = ClientError::UntrustedPublisher {
+            name: package.name.clone(),
+            record_id: RecordId::package_record_for(algorithm, &record.envelope),
+            key_id: key_id.clone(),
+        };
+        if self.strict_trust {
+            return Err(err);
+        }
+
+        tracing::warn!("{err}");
+        Ok(())
+    }
+
+    /// Proves the inclusion of the heads of the operator log and the given
+    /// package logs in the given checkpoint.
+    async fn prove_log_heads(
+        &self,
+        checkpoint: &Checkpoint,
+        operator: &OperatorInfo,
+        packages: &IndexMap<LogId, &mut PackageInfo>,
+    ) -> Result<(), ClientError> {
+        // Prove inclusion for the current log heads
+        let (leaf_indices, leafs) = core::log_heads(
+            checkpoint,
+            operator,
+            packages.iter().map(|(id, p)| (id, &**p)),
+        )?;
+
+        if !leafs.is_empty() {
+            self.api
+                .prove_inclusion(
+                    InclusionRequest {
+                        log_length: checkpoint.log_length,
+                        leafs: leaf_indices,
+                    },
+                    checkpoint,
+                    &leafs,
+                )
+                .await
+                .map_err(ClientError::translate_invalid_proof)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns an error if the client is in offline mode.
+    fn ensure_online(&self) -> ClientResult<()> {
+        if self.offline {
+            return Err(ClientError::Offline);
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the checkpoint is no older than the maximum checkpoint
+    /// interval declared by the operator, plus the configured grace period.
+    fn check_checkpoint_freshness(
+        &self,
+        operator: &operator::LogState,
+        ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
+    ) -> Result<(), ClientError> {
+        let max_interval = match operator.max_checkpoint_interval() {
+            Some(interval) => interval,
+            None => return Ok(()),
+        };
+
+        let now = (self.clock)()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let age = now.saturating_sub(Duration::from_secs(ts_checkpoint.as_ref().timestamp));
+        if age <= max_interval + self.checkpoint_grace_period {
+            return Ok(());
+        }
+
+        let err = ClientError::StaleCheckpoint {
+            age: age.as_secs(),
+            max_interval: max_interval.as_secs(),
+        };
+        match self.stale_checkpoint {
+            StaleCheckpointPolicy::Warn => {
+                tracing::warn!("{err}");
+                Ok(())
+            }
+            StaleCheckpointPolicy::Fail => Err(err),
+        }
+    }
+
+    /// Checks that the given checkpoint has not expired.
+    ///
+    /// An expired checkpoint is only used if the client allows stale state.
+    fn check_checkpoint_expiry(&self, ts_checkpoint: &TimestampedCheckpoint) -> ClientResult<()> {
+        if !ts_checkpoint.is_expired_at((self.clock)()) {
+            return Ok(());
+        }
+
+        let err = ClientError::StaleRegistry {
+            expires: ts_checkpoint.expires.unwrap_or_default(),
+        };
+        if self.allow_stale {
+            tracing::warn!("{err}");
+            return Ok(());
+        }
+
+        Err(err)
+    }
+
+    /// Checks that the checkpoint in client storage, if any, has not expired.
+    async fn check_stored_checkpoint_expiry(&self) -> ClientResult<()> {
+        match self
+            .registry
+            .load_checkpoint(self.get_warg_registry())
+            .await?
+        {
+            Some(ts_checkpoint) => self.check_checkpoint_expiry(ts_checkpoint.as_ref()),
+            None => Ok(()),
+        }
+    }
+
+    async fn update_checkpoints(
+        &mut self,
+        ts_checkpoints: IndexMap<std::string::String, SerdeEnvelope<TimestampedCheckpoint>>,
+        mut packages: IndexMap<String, Vec<PackageInfo>>,
+    ) -> Result<(), ClientError> {
+        for (name, ts_checkpoint) in ts_checkpoints {
+            if self.url().safe_label() != name {
+                self.api
+                    .set_warg_registry(Some(RegistryDomain::from_str(&name)?));
+            } else {
+                self.api.set_warg_registry(None)
+            }
+            let mut packages = packages.get_mut(&name.clone());
+            if let Some(pkgs) = &mut packages {
+                self.update_checkpoint(&ts_checkpoint, pkgs.as_mut_slice())
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the hash algorithm of the registry's logs, as used by the most
+    /// recent checkpoint known to the client.
+    pub async fn hash_algorithm(&self) -> Result<HashAlgorithm, ClientError> {
+        let ts_checkpoint = match self
+            .registry
+            .load_checkpoint(self.get_warg_registry())
+            .await?
+        {
+            Some(ts_checkpoint) => ts_checkpoint,
+            None => {
+                self.ensure_online()?;
+                self.api.latest_checkpoint().await?
+            }
+        };
+
+        Ok(ts_checkpoint.as_ref().checkpoint.log_root.algorithm())
+    }
+
+    async fn fetch_package(&self, name: &PackageName) -> Result<PackageInfo, ClientError> {
+        let info = match self
+            .registry
+            .load_package(self.get_warg_registry(), name)
+            .await?
+        {
+            Some(info) => {
+                tracing::info!("log for package `{name}` already exists in storage");
+                self.check_stored_checkpoint_expiry().await?;
+                info
+            }
+            None => {
+                self.ensure_online()?;
+
+                let mut info = PackageInfo::new(name.clone());
+                self.update_checkpoint(&self.api.latest_checkpoint().await?, [&mut info])
+                    .await?;
+
+                info
+            }
+        };
+
+        // The log of a suppressed package is still served for auditing, but
+        // its use is refused with the operator's reason
+        if let Some(operator) = self
+            .registry
+            .load_operator(self.get_warg_registry())
+            .await?
+        {
+            if let Some(reason) = operator
+                .state
+                .package_suppression(&LogId::package_log_for(self.hash_algorithm().await?, name))
+            {
+                return Err(ClientError::PackageSuppressed {
+                    name: name.clone(),
+                    reason: reason.to_string(),
+                });
+            }
+        }
+
+        Ok(info)
+    }
+
+    async fn fetch_package_at(
+        &self,
+        name: &PackageName,
+        checkpoint_id: &AnyHash,
+    ) -> ClientResult<(OperatorInfo, PackageInfo)> {
+        self.ensure_online()?;
+
+        let ts_checkpoint =
+            self.api
+                .fetch_checkpoint(checkpoint_id)
+                .await
+                .map_err(|e| match e {
+                    api::ClientError::Fetch(FetchError::CheckpointIdNotFound(_)) => {
+                        ClientError::CheckpointNotFound {
+                            checkpoint_id: checkpoint_id.clone(),
+                        }
+                    }
+                    e => ClientError::Api(e),
+                })?;
+
+        let checkpoint = &ts_checkpoint.as_ref().checkpoint;
+        let found = AnyHash::of(checkpoint_id.algorithm(), checkpoint);
+        if &found != checkpoint_id {
+            return Err(ClientError::CheckpointMismatch {
+                expected: checkpoint_id.clone(),
+                found,
+            });
+        }
+
+        let mut operator = OperatorInfo::default();
+        let mut info = PackageInfo::new(name.clone());
+        let mut packages = IndexMap::from([(
+            LogId::package_log_for(checkpoint_id.algorithm(), name),
+            &mut info,
+        )]);
+        self.fetch_logs_at(&ts_checkpoint, &mut operator, &mut packages)
+            .await
+            .map_err(|e| match e {
+                ClientError::PackageLogEmpty { name } => ClientError::PackageNotInCheckpoint {
+                    name,
+                    checkpoint_id: checkpoint_id.clone(),
+                },
+                e => e,
+            })?;
+
+        info.checkpoint = Some(checkpoint.clone());
+        Ok((operator, info))
+    }
+
+    async fn get_package_record(
+        &self,
+        package: &PackageName,
+        log_id: &LogId,
+        record_id: &RecordId,
+    ) -> ClientResult<PackageRecord> {
+        let record = self
+            .api
+            .get_package_record(log_id, record_id)
+            .await
+            .map_err(|e| {
+                ClientError::translate_log_not_found(e, |id| {
+                    if id == log_id {
+                        Some(package.clone())
+                    } else {
+                        None
+                    }
+                })
+            })?;
+        Ok(record)
+    }
+
+    /// Downloads the content for the specified digests into client storage,
+    /// with up to `parallelism` downloads in progress at once.
+    ///
+    /// Each download is verified against its digest as it completes; downloads
+    /// failing with a transient error are retried with exponential backoff.
+    /// Content that already exists in client storage is not downloaded again.
+    ///
+    /// Returns the result of each download in the order of the digests.
+    pub async fn download_contents(
+        &self,
+        digests: impl IntoIterator<Item = AnyHash>,
+        parallelism: usize,
+    ) -> IndexMap<AnyHash, Result<PathBuf, ClientError>> {
+        let digests: IndexSet<AnyHash> = digests.into_iter().collect();
+        let mut completed: HashMap<_, _> = futures_util::stream::iter(digests.iter())
+            .map(|digest| async move { (digest, self.download_content_with_retry(digest).await) })
+            .buffer_unordered(parallelism.max(1))
+            .collect()
+            .await;
+
+        digests
+            .iter()
+            .map(|digest| {
+                let res = completed
+                    .remove(digest)
+                    .expect("every digest is downloaded");
+                (digest.clone(), res)
+            })
+            .collect()
+    }
+
+    async fn download_content_with_retry(&self, digest: &AnyHash) -> Result<PathBuf, ClientError> {
+        let policy = self.api.retry_policy();
+        let mut attempt = 1;
+        loop {
+            match self.download_content(digest).await {
+                Err(e) if policy.should_retry(attempt) && e.is_transient() => {
+                    let delay = policy.delay(attempt);
+                    tracing::debug!(
+                        "failed to download content `{digest}` (attempt {attempt}), retrying in {delay:?}: {e}"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Downloads the content for the specified digest into client storage.
+    ///
+    /// If the content already exists in client storage, the existing path
+    /// is returned.
+    ///
+    /// A download that was interrupted is resumed from the bytes already
+    /// stored, falling back to downloading the content again if its sources
+    /// do not accept range requests or the resumed content does not match
+    /// the digest.
+    pub async fn download_content(&self, digest: &AnyHash) -> Result<PathBuf, ClientError> {
+        if let Some(path) = self.content.content_location(digest) {
+            tracing::info!("content for digest `{digest}` already exists in storage");
+            return Ok(path);
+        }
+
+        self.ensure_online()?;
+
+        let mut offset = self.content.partial_content_len(digest).await?;
+        loop {
+            if offset > 0 {
+                tracing::info!("resuming download of content `{digest}` from byte {offset}");
+            }
+
+            let stream = match self.api.download_content_range(digest, offset..).await {
+                Err(e) if offset > 0 && !e.is_transient() => {
+                    tracing::debug!(
+                        "failed to resume download of content `{digest}`, downloading it again: {e}"
+                    );
+                    offset = 0;
+                    self.api.download_content_range(digest, offset..).await?
+                }
+                res => res?,
+            };
+
+            let hash = self
+                .content
+                .store_partial_content(Box::pin(stream), digest, offset)
+                .await?;
+            if hash == *digest {
+                break;
+            }
+
+            if offset == 0 {
+                return Err(ClientError::IncorrectContent {
+                    digest: hash,
+                    expected: digest.clone(),
+                });
+            }
+
+            tracing::warn!(
+                "resumed download of content `{digest}` has digest `{hash}`, downloading it again"
+            );
+            offset = 0;
+        }
+
+        self.content
+            .content_location(digest)
+            .ok_or_else(|| ClientError::ContentNotFound {
+                digest: digest.clone(),
+            })
+    }
+
+    /// Downloads the given range of bytes of the content for the specified
+    /// digest.
+    ///
+    /// The bytes are neither verified against the digest nor stored in
+    /// client storage; use `download_content` to download the entire content.
+    pub async fn download_range(
+        &self,
+        digest: &AnyHash,
+        range: impl RangeBounds<u64>,
+    ) -> Result<impl Stream<Item = Result<Bytes>>, ClientError> {
+        self.ensure_online()?;
+        Ok(self.api.download_content_range(digest, range).await?)
+    }
+
+    /// Extracts the WIT of the component with the specified digest from
+    /// client storage.
+    ///
+    /// The content must have been downloaded into client storage first.
+    ///
+    /// Returns the WIT package of the component printed as text.
+    pub fn extract_wit(&self, digest: &AnyHash) -> Result<String, ClientError> {
+        let bytes = self
+            .content
+            .content_location(digest)
+            .and_then(|path| fs::read(path).ok())
+            .ok_or_else(|| ClientError::ContentNotFound {
+                digest: digest.clone(),
+            })?;
+
+        if !wasmparser::Parser::is_component(&bytes) {
+            return Err(ClientError::ContentNotAComponent {
+                digest: digest.clone(),
+            });
+        }
+
+        let decoding_failed = |e: anyhow::Error| ClientError::ComponentDecodingFailed {
+            digest: digest.clone(),
+            inner: e,
+        };
+        let decoded = wit_component::decode(&bytes).map_err(decoding_failed)?;
+        wit_component::WitPrinter::default()
+            .print(decoded.resolve(), decoded.package())
+            .map_err(decoding_failed)
+    }
+}
+/// A Warg registry client that uses the local file system to store
+/// package logs and content.
+///
+/// Registry information is stored as files or in a database, as selected by
+/// the client configuration.
+pub type FileSystemClient =
+    Client<LocalRegistryStorage, FileSystemContentStorage, FileSystemNamespaceMapStorage>;
+
+/// A result of an attempt to lock client storage.
+pub enum StorageLockResult<T> {
+    /// The storage lock was acquired.
+    Acquired(T),
+    /// The storage lock was not acquired for the specified directory.
+    NotAcquired(PathBuf),
+}
+
+impl FileSystemClient {
+    /// Attempts to create a client for the given registry URL.
+    ///
+    /// If the URL is `None`, the home registry URL is used; if there is no home registry
+    /// URL, an error is returned.
+    ///
+    /// If a lock cannot be acquired for a storage directory, then
+    /// `NewClientResult::Blocked` is returned with the path to the
+    /// directory that could not be locked.
+    pub fn try_new_with_config(
+        url: Option<&str>,
+        config: &Config,
+        auth_token: Option<Secret<String>>,
+    ) -> Result<StorageLockResult<Self>, ClientError> {
+        let StoragePaths {
+            registry_url: url,
+            registries_dir,
+            content_dir,
+            namespace_map_path,
+        } = config.storage_paths_for_url(url)?;
+
+        let (packages, content, namespace_map) = match (
+            LocalRegistryStorage::try_lock(config.storage, registries_dir.clone())?,
+            FileSystemContentStorage::try_lock(content_dir.clone())?
+                .map(|content| content.with_fallback_dirs(config.content_fallback_dirs())),
+            FileSystemNamespaceMapStorage::new(namespace_map_path.clone()),
+        ) {
+            (Some(packages), Some(content), namespace_map) => (packages, content, namespace_map),
+            (None, _, _) => return Ok(StorageLockResult::NotAcquired(registries_dir)),
+            (_, None, _) => return Ok(StorageLockResult::NotAcquired(content_dir)),
+        };
+
+        Ok(StorageLockResult::Acquired(
+            Self::new(url.into_url(), packages, content, namespace_map, auth_token)?
+                .with_stale_checkpoint_policy(
+                    config.stale_checkpoint,
+                    config.checkpoint_grace_period(),
+                )
+                .with_offline(config.offline)
+                .with_retry_policy(config.retry_policy())
+                .with_trust_policy(TrustPolicy::from_file(config.trust_policy_path()?)?)
+                .with_config_namespace_registries(config)?,
+        ))
+    }
+
+    /// Creates a client for the given registry URL.
+    ///
+    /// If the URL is `None`, the home registry URL is used; if there is no home registry
+    /// URL, an error is returned.
+    ///
+    /// This method blocks if storage locks cannot be acquired.
+    pub fn new_with_config(
+        url: Option<&str>,
+        config: &Config,
+        auth_token: Option<Secret<String>>,
+    ) -> Result<Self, ClientError> {
+        let StoragePaths {
+            registry_url,
+            registries_dir,
+            content_dir,
+            namespace_map_path,
+        } = config.storage_paths_for_url(url)?;
+        Self::new(
+            registry_url.into_url(),
+            LocalRegistryStorage::lock(config.storage, registries_dir)?,
+            FileSystemContentStorage::lock(content_dir)?
+                .with_fallback_dirs(config.content_fallback_dirs()),
+            FileSystemNamespaceMapStorage::new(namespace_map_path),
+            auth_token,
+        )
+        .and_then(|client| {
+            client
+                .with_stale_checkpoint_policy(
+                    config.stale_checkpoint,
+                    config.checkpoint_grace_period(),
+                )
+                .with_offline(config.offline)
+                .with_retry_policy(config.retry_policy())
+                .with_trust_policy(TrustPolicy::from_file(config.trust_policy_path()?)?)
+                .with_config_namespace_registries(config)
+        })
+    }
+}
+
+/// Represents information about a downloaded package.
+#[derive(Debug, Clone)]
+pub struct PackageDownload {
+    /// The package version that was downloaded.
+    pub version: Version,
+    /// The digest of the package contents.
+    pub digest: AnyHash,
+    /// The path to the downloaded package contents.
+    pub path: PathBuf,
+}
+
+/// The outcome of fetching a package log with [`Client::fetch_packages`].
+#[derive(Debug)]
+pub struct PackageFetch {
+    /// The name of the package.
+    pub name: PackageName,
+    /// The number of new records fetched, or the error that prevented the
+    /// package log from being updated.
+    pub new_records: ClientResult<usize>,
+    /// The head of the package log in client storage after the fetch.
+    pub head: Option<RecordId>,
+}
+
+/// Gets the version and content digest of the latest release satisfying the
+/// given requirement.
+fn release_content(
+    info: &PackageInfo,
+    requirement: &VersionReq,
+    artifact: &str,
+    include_revoked: bool,
+) -> ClientResult<Option<(Version, AnyHash)>> {
+    match resolve_release(&info.state, requirement, false, include_revoked) {
+        Some(release) => {
+            let digest = release.artifact(artifact).ok_or_else(|| {
+                ClientError::ReleaseArtifactDoesNotExist {
+                    name: info.name.clone(),
+                    version: Box::new(release.version.clone()),
+                    artifact: artifact.to_string(),
+                }
+            })?;
+            Ok(Some((release.version.clone(), digest.clone())))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Builds the package record for the given publish information, checking
+/// that the publish initializes the package exactly when it has no head.
+fn new_record(
+    key: &signing::PublicKey,
+    info: &PublishInfo,
+    hash_algorithm: HashAlgorithm,
+    timestamp: SystemTime,
+) -> ClientResult<package::PackageRecord> {
+    if info.entries.is_empty() {
+        return Err(ClientError::NothingToPublish {
+            name: info.name.clone(),
+        });
+    }
+
+    match (info.initializing(), info.head.is_some()) {
+        (true, true) => Err(ClientError::CannotInitializePackage {
+            name: info.name.clone(),
+        }),
+        (false, false) => Err(ClientError::MustInitializePackage {
+            name: info.name.clone(),
+        }),
+        _ => Ok(info.build_record(key, hash_algorithm, timestamp)),
+    }
+}
+
+/// Splits the entries of a record that exceed the given record limits.
+///
+/// The record keeps as many leading entries as fit within the limits; the
+/// remaining entries are returned to be published in subsequent records.
+fn split_record(
+    key: &signing::PublicKey,
+    limits: &RecordLimits,
+    name: &PackageName,
+    record: &mut package::PackageRecord,
+) -> ClientResult<Vec<package::PackageEntry>> {
+    let mut len = record.entries.len();
+    if let Some(max) = limits.max_record_entries {
+        len = len.min((max as usize).max(1));
+    }
+
+    if let Some(max_size) = limits.max_record_size {
+        let key_id = key.fingerprint();
+        let algorithm = key.signature_algorithm();
+        let size = |len: usize| -> ClientResult<u64> {
+            let record = package::PackageRecord {
+                entries: record.entries[..len].to_vec(),
+                ..record.clone()
+            };
+            Ok(ProtoEnvelope::max_protobuf_len(&record, &key_id, &algorithm) as u64)
+        };
+
+        // Find the most entries that fit, knowing that size grows with entries
+        if size(len)? > max_size {
+            let (mut fits, mut exceeds) = (0, len);
+            while exceeds - fits > 1 {
+                let mid = (fits + exceeds) / 2;
+                if size(mid)? > max_size {
+                    exceeds = mid;
+                } else {
+                    fits = mid;
+                }
+            }
+
+            if fits == 0 {
+                return Err(ClientError::RecordTooLarge {
+                    name: name.clone(),
+                    size: size(1)?,
+                    max_size,
+                });
+            }
+
+            len = fits;
+        }
+    }
+
+    Ok(record.entries.split_off(len))
+}
+
+/// Represents the outcome of publishing a package record.
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublishResult {
+    /// The record was published.
+    Published {
+        /// The index of the record in the registry log.
+        registry_index: RegistryIndex,
+    },
+    /// The record was rejected by the registry.
+    Rejected {
+        /// The reason the registry gave for rejecting the record.
+        reason: String,
+    },
+    /// The record is waiting on content that was never uploaded.
+    ///
+    /// The registry does not process the record until the content is provided.
+    MissingContent {
+        /// The digests of the missing content.
+        digests: Vec<AnyHash>,
+    },
+}
+
+impl PublishResult {
+    /// Gets the result for the given record state.
+    ///
+    /// Returns `None` if the record is still processing.
+    fn from_state(state: PackageRecordState) -> Option<Self> {
+        match state {
+            PackageRecordState::Sourcing { missing_content } => Some(Self::MissingContent {
+                digests: missing_content.into_keys().collect(),
+            }),
+            PackageRecordState::Processing => None,
+            PackageRecordState::Rejected { reason } => Some(Self::Rejected { reason }),
+            PackageRecordState::Published { registry_index } => {
+                Some(Self::Published { registry_index })
+            }
+        }
+    }
+
+    /// Determines if the record was published.
+    pub fn is_published(&self) -> bool {
+        matches!(self, Self::Published { .. })
+    }
+
+    /// Determines if the record was rejected because its previous record was
+    /// no longer the head of the package log.
+    ///
+    /// Such a record may be rebased with `Client::rebase_publish` and
+    /// submitted again.
+    pub fn is_stale_head(&self) -> bool {
+        let stale = package::ValidationError::RecordHashDoesNotMatch.to_string();
+        matches!(self, Self::Rejected { reason } if reason.ends_with(&stale))
+    }
+
+    /// Gets the reason the record was not published.
+    ///
+    /// Returns `None` if the record was published.
+    pub fn reason(&self) -> Option<String> {
+        match self {
+            Self::Published { .. } => None,
+            Self::Rejected { reason } => Some(reason.clone()),
+            Self::MissingContent { digests } => Some(format!(
+                "needed content {digests} but it was not provided",
+                digests = digests.iter().map(|d| format!("`{d}`")).join(", ")
+            )),
+        }
+    }
+}
+
+/// Represents a check performed when verifying a package release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationCheck {
+    /// The operator and package logs were fetched and their records
+    /// validated.
+    PackageLog,
+    /// The package log releases the version with the content of the file.
+    ReleaseContent,
+    /// The checkpoint is signed by a key the operator log authorizes to sign
+    /// checkpoints.
+    CheckpointSignature,
+    /// The heads of the logs are proven included in the checkpoint.
+    InclusionProof,
+}
+
+impl fmt::Display for VerificationCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PackageLog => write!(f, "package log"),
+            Self::ReleaseContent => write!(f, "release content"),
+            Self::CheckpointSignature => write!(f, "checkpoint signature"),
+            Self::InclusionProof => write!(f, "inclusion proof"),
+        }
+    }
+}
+
+/// Represents the outcome of a check performed when verifying a package
+/// release.
+#[derive(Debug)]
+pub enum VerificationOutcome {
+    /// The check passed.
+    Passed,
+    /// The check failed.
+    Failed(ClientError),
+    /// The check was skipped because a check it depends on failed.
+    Skipped,
+}
+
+/// Represents the result of verifying a package release.
+#[derive(Debug)]
+pub struct ReleaseVerification {
+    /// The log length of the checkpoint the release was verified against.
+    pub log_length: RegistryLen,
+    /// The digest of the file, if the released content was found.
+    pub digest: Option<AnyHash>,
+    /// The outcome of each check, in the order they were performed.
+    pub checks: Vec<(VerificationCheck, VerificationOutcome)>,
+}
+
+impl ReleaseVerification {
+    /// Determines if every check passed.
+    pub fn passed(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|(_, outcome)| matches!(outcome, VerificationOutcome::Passed))
+    }
+
+    fn record(&mut self, check: VerificationCheck, result: Option<ClientResult<()>>) {
+        let outcome = match result {
+            Some(Ok(())) => VerificationOutcome::Passed,
+            Some(Err(e)) => VerificationOutcome::Failed(e),
+            None => VerificationOutcome::Skipped,
+        };
+        self.checks.push((check, outcome));
+    }
+
+    fn skip(&mut self, checks: impl IntoIterator<Item = VerificationCheck>) {
+        for check in checks {
+            self.record(check, None);
+        }
+    }
+}
+
+/// Hashes the file at the given path with the given algorithm.
+async fn hash_file(path: &Path, algorithm: HashAlgorithm) -> Result<AnyHash> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .with_context(|| format!("failed to open `{path}`", path = path.display()))?;
+    let mut hasher = algorithm.hasher();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file
+            .read(&mut buf)
+            .await
+            .with_context(|| format!("failed to read `{path}`", path = path.display()))?;
+        if n == 0 {
+            return Ok(hasher.finalize());
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+/// Represents an error returned by Warg registry clients.
+#[derive(Debug, Error)]
+pub enum ClientError {
+    /// No home registry registry server URL is configured.
+    #[error("no home registry registry server URL is configured")]
+    NoHomeRegistryUrl,
+
+    /// Reset registry local state.
+    #[error("reset registry state failed")]
+    ResettingRegistryLocalStateFailed,
+
+    /// Clearing content local cache.
+    #[error("clear content cache failed")]
+    ClearContentCacheFailed,
+
+    /// The operation requires contacting the registry, but the client is in
+    /// offline mode.
+    #[error("the operation requires contacting the registry, but the client is in offline mode")]
+    Offline,
+
+    /// Checkpoint signature failed verification
+    #[error("invalid checkpoint signature")]
+    InvalidCheckpointSignature,
+
+    /// A transparency proof provided by the registry failed verification.
+    ///
+    /// Nothing fetched alongside the proof is stored.
+    #[error("failed to verify a proof provided by the registry: {0}")]
+    ProofVerificationFailed(ProofError),
+
+    /// The latest checkpoint is older than the maximum checkpoint interval
+    /// declared by the registry.
+    #[error("the registry's latest checkpoint was issued {age} second(s) ago, but the registry declares a checkpoint at least every {max_interval} second(s); the registry may be stale or withholding updates")]
+    StaleCheckpoint {
+        /// The age of the latest checkpoint, in seconds.
+        age: u64,
+        /// The maximum checkpoint interval declared by the registry, in seconds.
+        max_interval: u64,
+    },
+
+    /// The checkpoint was signed by a key that the operator log does not
+    /// authorize to sign checkpoints.
+    #[error("the checkpoint was signed by key `{key_id}`, which is not authorized by the operator log to sign checkpoints")]
+    UntrustedCheckpoint {
+        /// The signature key ID.
+        key_id: signing::KeyID,
+    },
+
+    /// The registry signed a checkpoint with another key after the operator
+    /// log permitted the expected key to sign checkpoints.
+    #[error("the registry signed a checkpoint with key `{found}` instead of key `{key_id}`; check that the registry is configured with the key")]
+    OperatorKeyNotInUse {
+        /// The key ID expected to sign checkpoints.
+        key_id: signing::KeyID,
+        /// The key ID that signed the checkpoint.
+        found: signing::KeyID,
+    },
+
+    /// The server did not provide operator records.
+    #[error("the server did not provide any operator records")]
+    NoOperatorRecords,
+
+    /// The operator failed validation.
+    #[error("operator failed validation: {inner}")]
+    OperatorValidationFailed {
+        /// The validation error.
+        inner: operator::ValidationError,
+    },
+
+    /// The package already exists and cannot be initialized.
+    #[error("package `{name}` already exists and cannot be initialized")]
+    CannotInitializePackage {
+        /// The package name that already exists.
+        name: PackageName,
+    },
+
+    /// The package must be initialized before publishing.
+    #[error("package `{name}` must be initialized before publishing")]
+    MustInitializePackage {
+        /// The name of the package that must be initialized.
+        name: PackageName,
+    },
+
+    /// There is no publish operation in progress.
+    #[error("there is no publish operation in progress")]
+    NotPublishing,
+
+    /// The namespace is neither served by the home registry nor routed to
+    /// another registry.
+    #[error("namespace `{namespace}` is not served by registry `{registry}` and is not routed to another registry")]
+    NamespaceNotRouted {
+        /// The namespace that has no route.
+        namespace: String,
+        /// The URL of the home registry.
+        registry: RegistryUrl,
+    },
+
+    /// No checkpoint of the registry is stored to build a record against.
+    #[error("no checkpoint of the registry is stored; update the client before building a record")]
+    NoStoredCheckpoint,
+
+    /// The package has no records to publish.
+    #[error("package `{name}` has no records to publish")]
+    NothingToPublish {
+        /// The package that has no publish operations.
+        name: PackageName,
+    },
+
+    /// The package does not exist.
+    #[error("package `{name}` does not exist")]
+    PackageDoesNotExist {
+        /// The missing package.
+        name: PackageName,
+    },
+
+    /// The package does not exist with hint.
+    #[error("package `{name}` does not exist")]
+    PackageDoesNotExistWithHint {
+        /// The missing package.
+        name: PackageName,
+        /// The registry hint
+        hint: HeaderValue,
+    },
+
+    /// The package was suppressed by the registry operator.
+    #[error("package `{name}` was suppressed by the registry operator: {reason}")]
+    PackageSuppressed {
+        /// The suppressed package.
+        name: PackageName,
+        /// The reason given by the operator.
+        reason: String,
+    },
+
+    /// The package version does not exist.
+    #[error("version `{version}` of package `{name}` does not exist")]
+    PackageVersionDoesNotExist {
+        /// The missing version of the package.
+        version: Version,
+        /// The package with the missing version.
+        name: PackageName,
+    },
+
+    /// The package release does not have the requested artifact.
+    #[error("version `{version}` of package `{name}` does not have a `{artifact}` artifact")]
+    ReleaseArtifactDoesNotExist {
+        /// The package that was released.
+        name: PackageName,
+        /// The version that was released.
+        version: Box<Version>,
+        /// The name of the missing artifact.
+        artifact: String,
+    },
+
+    /// The package version was already released with different content.
+    #[error("version `{version}` of package `{name}` already exists with content `{existing}` instead of `{content}`")]
+    ReleaseContentMismatch {
+        /// The package that was released.
+        name: PackageName,
+        /// The version that was released.
+        version: Box<Version>,
+        /// The digest of the released content.
+        existing: AnyHash,
+        /// The digest of the content being released.
+        content: AnyHash,
+    },
+
+    /// The package version was released by another record after a publish
+    /// releasing it was started.
+    #[error("version `{version}` of package `{name}` was released by another publish since this publish was started")]
+    ReleaseConflict {
+        /// The package being published.
+        name: PackageName,
+        /// The version that was already released.
+        version: Version,
+    },
+
+    /// The package version was yanked and cannot be released again.
+    #[error(
+        "version `{version}` of package `{name}` has been yanked and cannot be released again"
+    )]
+    ReleaseYanked {
+        /// The package that was released.
+        name: PackageName,
+        /// The version that was yanked.
+        version: Version,
+    },
+
+    /// The package failed validation.
+    #[error("package `{name}` failed validation: {inner}")]
+    PackageValidationFailed {
+        /// The package that failed validation.
+        name: PackageName,
+        /// The validation error.
+        inner: package::ValidationError,
+    },
+
+    /// Content was not found during a publish operation.
+    #[error("content with digest `{digest}` was not found in client storage")]
+    ContentNotFound {
+        /// The digest of the missing content.
+        digest: AnyHash,
+    },
+
+    /// Content digest was different than expected.
+    #[error("content with digest `{digest}` was not found expected `{expected}`")]
+    IncorrectContent {
+        /// The digest of the missing content.
+        digest: AnyHash,
+        /// The expected
+        expected: AnyHash,
+    },
+
+    /// The package log is empty and cannot be validated.
+    #[error("package log is empty and cannot be validated")]
+    PackageLogEmpty {
+        /// The package with an empty package log.
+        name: PackageName,
+    },
+
+    /// A single entry of a publish exceeds the registry's maximum record size.
+    #[error("a record for package `{name}` with a single entry is {size} bytes, exceeding the registry's maximum record size of {max_size} bytes")]
+    RecordTooLarge {
+        /// The package being published.
+        name: PackageName,
+        /// The size of the record, in bytes.
+        size: u64,
+        /// The maximum record size of the registry, in bytes.
+        max_size: u64,
+    },
+
+    /// A file does not have the content of a package release.
+    #[error("the file has digest `{digest}`, but version `{version}` of package `{name}` was released with content `{expected}`")]
+    ContentDoesNotMatchRelease {
+        /// The package that was released.
+        name: PackageName,
+        /// The version that was released.
+        version: Box<Version>,
+        /// The digest of the file.
+        digest: AnyHash,
+        /// The digest of the released content.
+        expected: AnyHash,
+    },
+
+    /// A record could not be signed.
+    #[error("failed to sign a record for package `{name}`: {source:#}")]
+    SigningFailed {
+        /// The package being published.
+        name: PackageName,
+        /// The signing error.
+        source: anyhow::Error,
+    },
+
+    /// A record failed validation against the package log in client storage
+    /// before it was signed.
+    ///
+    /// The registry would reject the record for the same reason.
+    #[error("the publishing of package `{name}` was rejected by a local pre-check due to: the package record was invalid: {inner}")]
+    PrevalidationFailed {
+        /// The package being published.
+        name: PackageName,
+        /// The validation error.
+        inner: package::ValidationError,
+    },
+
+    /// A publish operation was rejected.
+    #[error("the publishing of package `{name}` was rejected due to: {reason}")]
+    PublishRejected {
+        /// The package that was rejected.
+        name: PackageName,
+        /// The record identifier for the record that was rejected.
+        record_id: RecordId,
+        /// The reason it was rejected.
+        reason: String,
+    },
+
+    /// The registry provided a latest checkpoint with a log length or
+    /// timestamp less than that of a previously provided checkpoint.
+    #[error("registry rewinded checkpoints; latest checkpoint at log length `{to_log_length}` (timestamp {to_timestamp}) is older than previously received checkpoint at log length `{from_log_length}` (timestamp {from_timestamp})")]
+    CheckpointRegression {
+        /// The previously received checkpoint log length.
+        from_log_length: RegistryLen,
+        /// The previously received checkpoint timestamp.
+        from_timestamp: u64,
+        /// The latest checkpoint log length.
+        to_log_length: RegistryLen,
+        /// The latest checkpoint timestamp.
+        to_timestamp: u64,
+    },
+
+    /// A package record was signed by a key that the trust policy does not
+    /// trust for the package.
+    #[error("record `{record_id}` of package `{name}` was signed by key `{key_id}`, which is not trusted for the package")]
+    UntrustedPublisher {
+        /// The name of the package.
+        name: PackageName,
+        /// The identifier of the record.
+        record_id: RecordId,
+        /// The ID of the key that signed the record.
+        key_id: signing::KeyID,
+    },
+
+    /// The registry's latest checkpoint has expired.
+    #[error("the registry's latest checkpoint expired at {expires} seconds since the Unix epoch; the registry may be frozen or withholding updates")]
+    StaleRegistry {
+        /// The time the checkpoint expired, in seconds since the Unix epoch.
+        expires: u64,
+    },
+
+    /// The registry provided a checkpoint with a different `log_root` and
+    /// `map_root` than a previously provided checkpoint.
+    #[error("registry provided a new checkpoint with the same log length `{log_length}` as previously fetched but different log root or map root")]
+    CheckpointChangedLogRootOrMapRoot {
+        /// The checkpoint log length.
+        log_length: RegistryLen,
+    },
+
+    /// The requested checkpoint was not found in the registry.
+    #[error("checkpoint `{checkpoint_id}` was not found in the registry")]
+    CheckpointNotFound {
+        /// The identifier of the requested checkpoint.
+        checkpoint_id: AnyHash,
+    },
+
+    /// The registry provided a checkpoint other than the one requested.
+    #[error("registry provided checkpoint `{found}` when checkpoint `{expected}` was requested")]
+    CheckpointMismatch {
+        /// The identifier of the requested checkpoint.
+        expected: AnyHash,
+        /// The identifier of the provided checkpoint.
+        found: AnyHash,
+    },
+
+    /// The package log has no records as of the requested checkpoint.
+    #[error("package `{name}` has no records as of checkpoint `{checkpoint_id}`")]
+    PackageNotInCheckpoint {
+        /// The name of the package.
+        name: PackageName,
+        /// The identifier of the requested checkpoint.
+        checkpoint_id: AnyHash,
+    },
+
+    /// The content is not a WebAssembly component.
+    #[error("content with digest `{digest}` is not a component")]
+    ContentNotAComponent {
+        /// The digest of the content.
+        digest: AnyHash,
+    },
+
+    /// The WIT of a component could not be decoded.
+    #[error("failed to decode the WIT of component with digest `{digest}`: {inner:#}")]
+    ComponentDecodingFailed {
+        /// The digest of the component.
+        digest: AnyHash,
+        /// The decoding error.
+        inner: anyhow::Error,
+    },
+
+    /// An error occurred during an API operation.
+    #[error(transparent)]
+    Api(#[from] api::ClientError),
+
+    /// An error occurred while performing a client operation.
+    #[error("{0:?}")]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<CoreError> for ClientError {
+    fn from(e: CoreError) -> Self {
+        match e {
+            CoreError::InvalidCheckpointSignature => Self::InvalidCheckpointSignature,
+            CoreError::UntrustedCheckpoint { key_id } => Self::UntrustedCheckpoint { key_id },
+            CoreError::CheckpointRegression {
+                from_log_length,
+                from_timestamp,
+                to_log_length,
+                to_timestamp,
+            } => Self::CheckpointRegression {
+                from_log_length,
+                from_timestamp,
+                to_log_length,
+                to_timestamp,
+            },
+            CoreError::CheckpointChangedLogRootOrMapRoot { log_length } => {
+                Self::CheckpointChangedLogRootOrMapRoot { log_length }
+            }
+            CoreError::NoOperatorRecords => Self::NoOperatorRecords,
+            CoreError::OperatorValidationFailed { inner } => {
+                Self::OperatorValidationFailed { inner }
+            }
+            CoreError::PackageValidationFailed { name, inner } => {
+                Self::PackageValidationFailed { name, inner }
+            }
+            CoreError::PackageLogEmpty { name } => Self::PackageLogEmpty { name },
+            CoreError::InvalidProof(e) => Self::ProofVerificationFailed(e),
+            CoreError::UnsupportedHashAlgorithm(algorithm) => {
+                Self::Api(api::ClientError::UnsupportedHashAlgorithm(algorithm))
+            }
+            CoreError::Other(e) => Self::Other(e),
+            e => Self::Other(e.into()),
+        }
+    }
+}
+
+impl ClientError {
+    /// Determines if the error is transient, such that the operation may
+    /// succeed if retried.
+    ///
+    /// Besides transient API errors, this includes content downloads that
+    /// failed while streaming the content.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Api(e) => e.is_transient(),
+            Self::Other(e) => e
+                .chain()
+                .any(|e| e.downcast_ref::<reqwest::Error>().is_some()),
+            _ => false,
+        }
+    }
+
+    fn translate_invalid_proof(e: api::ClientError) -> Self {
+        match e {
+            api::ClientError::InvalidProof(e) => Self::ProofVerificationFailed(e),
+            e => Self::Api(e),
+        }
+    }
+
+    fn translate_log_not_found(
+        e: api::ClientError,
+        lookup: impl Fn(&LogId) -> Option<PackageName>,
+    ) -> Self {
+        match &e {
+            api::ClientError::Fetch(FetchError::LogNotFound(id))
+            | api::ClientError::Package(PackageError::LogNotFound(id)) => {
+                if let Some(name) = lookup(id) {
+                    return Self::PackageDoesNotExist { name };
+                }
+            }
+            api::ClientError::LogNotFoundWithHint(log_id, hint) => {
+                if let Some(name) = lookup(log_id) {
+                    return Self::PackageDoesNotExistWithHint {
+                        name,
+                        hint: hint.clone(),
+                    };
+                }
+            }
+            _ => {}
+        }
+
+        Self::Api(e)
+    }
+}
+
+/// Represents the result of a client operation.
+pub type ClientResult<T> = Result<T, ClientError>;
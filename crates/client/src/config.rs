@@ -1,8 +1,8 @@
 //! Module for client configuration.
 
-use crate::{ClientError, RegistryUrl};
+use crate::{retry::RetryPolicy, storage::RegistryStorageKind, ClientError, RegistryUrl};
 use anyhow::{anyhow, Context, Result};
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 use normpath::PathExt;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
@@ -10,12 +10,21 @@ use std::{
     env::current_dir,
     fs::{self, File},
     path::{Component, Path, PathBuf},
+    time::Duration,
 };
 
 static CACHE_DIR: Lazy<Option<PathBuf>> = Lazy::new(dirs::cache_dir);
 static CONFIG_DIR: Lazy<Option<PathBuf>> = Lazy::new(dirs::config_dir);
 static CONFIG_FILE_NAME: &str = "warg-config.json";
 
+/// The environment variable listing read-only content directories, which
+/// takes precedence over [`Config::content_fallback_dirs`].
+pub const CONTENT_PATH_VAR: &str = "WARG_CONTENT_PATH";
+
+/// The default grace period allowed beyond a registry's declared maximum
+/// checkpoint interval.
+pub const DEFAULT_CHECKPOINT_GRACE_PERIOD: Duration = Duration::from_secs(5 * 60);
+
 fn find_warg_config(cwd: &Path) -> Option<PathBuf> {
     let mut current = Some(cwd);
 
@@ -72,6 +81,18 @@ pub struct StoragePaths {
     pub namespace_map_path: PathBuf,
 }
 
+/// Determines how a client treats a registry whose latest checkpoint is
+/// older than the registry's declared maximum checkpoint interval.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StaleCheckpointPolicy {
+    /// Log a warning and continue.
+    #[default]
+    Warn,
+    /// Fail the operation with an error.
+    Fail,
+}
+
 /// Represents the Warg client configuration.
 #[derive(Default, Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -79,6 +100,20 @@ pub struct Config {
     /// The home Warg registry server URL.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub home_url: Option<String>,
+    /// Named registries that may be selected by alias instead of by URL.
+    ///
+    /// Maps each alias to the URL of the registry.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub registries: IndexMap<String, String>,
+    /// Registries that serve packages of specific namespaces instead of the
+    /// home registry.
+    ///
+    /// Maps each namespace pattern to the URL or alias of the registry. A
+    /// pattern is either a namespace, such as `internal`, or a prefix of
+    /// package names ending in `*`, such as `internal:*` or `acme-*`; the
+    /// longest matching pattern is used.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub namespace_registries: IndexMap<String, String>,
 
     /// The path to the top-level directory where per-registry information is stored.
     ///
@@ -98,6 +133,17 @@ pub struct Config {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub content_dir: Option<PathBuf>,
 
+    /// Read-only directories to look up package content in, in order, when
+    /// the content is not in the content directory.
+    ///
+    /// These paths are expected to be relative to the configuration file.
+    ///
+    /// Content found in these directories is verified against its digest
+    /// before use. The `WARG_CONTENT_PATH` environment variable, if set,
+    /// takes precedence over this list.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub content_fallback_dirs: Vec<PathBuf>,
+
     /// The path to the directory where namespace map is stored.
     ///
     /// This path is expected to be relative to the configuration file.
@@ -107,13 +153,58 @@ pub struct Config {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub namespace_map_path: Option<PathBuf>,
 
+    /// The path to the trust policy file pinning the keys trusted to sign
+    /// the records of packages.
+    ///
+    /// This path is expected to be relative to the configuration file.
+    ///
+    /// If `None`, the default of `$CONFIG_DIR/warg/trust.json` is used, where
+    /// `$CONFIG_DIR` is the platform-specific configuration directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trust_policy_path: Option<PathBuf>,
+
     /// List of creds availabe in keyring
     #[serde(default, skip_serializing_if = "IndexSet::is_empty")]
     pub keys: IndexSet<String>,
+    /// The name of the signing key in the keyring to use for registries
+    /// without a signing key of their own.
+    ///
+    /// If `None`, the key named `default` is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_key: Option<String>,
 
     /// Whether or not an auth key should be retreived from keyring
     #[serde(default)]
     pub keyring_auth: bool,
+
+    /// How to treat a registry whose latest checkpoint is stale.
+    #[serde(default)]
+    pub stale_checkpoint: StaleCheckpointPolicy,
+
+    /// The number of seconds a checkpoint may be older than the registry's
+    /// declared maximum checkpoint interval before it is considered stale.
+    ///
+    /// If `None`, a default of 5 minutes is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint_grace_period: Option<u64>,
+
+    /// Whether the client is in offline mode.
+    ///
+    /// An offline client never contacts the registry and serves package logs
+    /// and content from client storage only.
+    #[serde(default)]
+    pub offline: bool,
+
+    /// How requests to the registry that fail with a transient error, such
+    /// as a `502 Bad Gateway` response, are retried.
+    ///
+    /// If `None`, the default retry policy is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+
+    /// The kind of storage used for registry information.
+    #[serde(default)]
+    pub storage: RegistryStorageKind,
 }
 
 impl Config {
@@ -134,6 +225,12 @@ impl Config {
         if let Some(parent) = path.parent() {
             config.registries_dir = config.registries_dir.map(|p| parent.join(p));
             config.content_dir = config.content_dir.map(|p| parent.join(p));
+            config.trust_policy_path = config.trust_policy_path.map(|p| parent.join(p));
+            config.content_fallback_dirs = config
+                .content_fallback_dirs
+                .into_iter()
+                .map(|p| parent.join(p))
+                .collect();
         }
 
         Ok(config)
@@ -175,6 +272,8 @@ impl Config {
 
         let config = Config {
             home_url: self.home_url.clone(),
+            registries: self.registries.clone(),
+            namespace_registries: self.namespace_registries.clone(),
             registries_dir: self.registries_dir.as_ref().map(|p| {
                 let p = normalize_path(parent.join(p).as_path());
                 assert!(p.is_absolute());
@@ -185,13 +284,33 @@ impl Config {
                 assert!(p.is_absolute());
                 pathdiff::diff_paths(&p, &parent).unwrap()
             }),
+            content_fallback_dirs: self
+                .content_fallback_dirs
+                .iter()
+                .map(|p| {
+                    let p = normalize_path(parent.join(p).as_path());
+                    assert!(p.is_absolute());
+                    pathdiff::diff_paths(&p, &parent).unwrap()
+                })
+                .collect(),
             namespace_map_path: self.namespace_map_path.as_ref().map(|p| {
                 let p = normalize_path(parent.join(p).as_path());
                 assert!(p.is_absolute());
                 pathdiff::diff_paths(&p, &parent).unwrap()
             }),
+            trust_policy_path: self.trust_policy_path.as_ref().map(|p| {
+                let p = normalize_path(parent.join(p).as_path());
+                assert!(p.is_absolute());
+                pathdiff::diff_paths(&p, &parent).unwrap()
+            }),
             keys: self.keys.clone(),
+            default_key: self.default_key.clone(),
             keyring_auth: self.keyring_auth,
+            stale_checkpoint: self.stale_checkpoint,
+            checkpoint_grace_period: self.checkpoint_grace_period,
+            offline: self.offline,
+            retry: self.retry,
+            storage: self.storage,
         };
 
         serde_json::to_writer_pretty(
@@ -214,11 +333,7 @@ impl Config {
     ///
     /// Returns `Ok(None)` if no configuration file was found.
     pub fn from_default_file() -> Result<Option<Self>> {
-        if let Some(path) = find_warg_config(&std::env::current_dir()?) {
-            return Ok(Some(Self::from_file(path)?));
-        }
-
-        let path = Self::default_config_path()?;
+        let path = Self::default_file_path()?;
         if path.is_file() {
             return Ok(Some(Self::from_file(path)?));
         }
@@ -226,6 +341,19 @@ impl Config {
         Ok(None)
     }
 
+    /// Gets the path of the configuration file loaded by [`Config::from_default_file`].
+    ///
+    /// This is the first `warg-config.json` found in the current directory
+    /// or its parents, otherwise `$CONFIG_DIR/warg/config.json`, which may
+    /// not exist.
+    pub fn default_file_path() -> Result<PathBuf> {
+        if let Some(path) = find_warg_config(&std::env::current_dir()?) {
+            return Ok(path);
+        }
+
+        Self::default_config_path()
+    }
+
     /// Gets the path to the default configuration file.
     ///
     /// The default configuration file is `$CONFIG_DIR/warg/config.json`,
@@ -264,6 +392,20 @@ impl Config {
             })
     }
 
+    /// Gets the read-only directories to look up package content in when the
+    /// content is not in the content directory.
+    ///
+    /// The directories listed in the `WARG_CONTENT_PATH` environment
+    /// variable are used if it is set, otherwise the configured directories.
+    pub fn content_fallback_dirs(&self) -> Vec<PathBuf> {
+        match std::env::var_os(CONTENT_PATH_VAR) {
+            Some(paths) => std::env::split_paths(&paths)
+                .filter(|p| !p.as_os_str().is_empty())
+                .collect(),
+            None => self.content_fallback_dirs.clone(),
+        }
+    }
+
     /// Gets the path to the directory where namespace mapping is stored.
     pub fn namespace_map_path(&self) -> Result<PathBuf> {
         self.namespace_map_path
@@ -278,12 +420,52 @@ impl Config {
             })
     }
 
+    /// Gets the path to the trust policy file.
+    pub fn trust_policy_path(&self) -> Result<PathBuf> {
+        self.trust_policy_path
+            .as_ref()
+            .cloned()
+            .map(Ok)
+            .unwrap_or_else(|| {
+                CONFIG_DIR
+                    .as_ref()
+                    .map(|p| p.join("warg/trust.json"))
+                    .ok_or_else(|| {
+                        anyhow!("failed to determine operating system configuration directory")
+                    })
+            })
+    }
+
+    /// Gets the grace period allowed beyond a registry's declared maximum
+    /// checkpoint interval.
+    pub fn checkpoint_grace_period(&self) -> Duration {
+        self.checkpoint_grace_period
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_CHECKPOINT_GRACE_PERIOD)
+    }
+
+    /// Gets the policy for retrying requests that fail with a transient error.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry.unwrap_or_default()
+    }
+
+    /// Resolves a registry alias to the URL of the registry.
+    ///
+    /// Returns the given value unchanged if it is not a configured alias.
+    pub fn resolve_registry<'a>(&'a self, registry: &'a str) -> &'a str {
+        self.registries
+            .get(registry)
+            .map(String::as_str)
+            .unwrap_or(registry)
+    }
+
     pub(crate) fn storage_paths_for_url(
         &self,
         url: Option<&str>,
     ) -> Result<StoragePaths, ClientError> {
         let registry_url = RegistryUrl::new(
-            url.or(self.home_url.as_deref())
+            url.map(|url| self.resolve_registry(url))
+                .or(self.home_url.as_deref())
                 .ok_or(ClientError::NoHomeRegistryUrl)?,
         )?;
 
@@ -299,3 +481,21 @@ impl Config {
         })
     }
 }
+
+/// Finds the value of the longest namespace pattern matching the given
+/// namespace.
+///
+/// See [`Config::namespace_registries`] for the syntax of patterns.
+pub(crate) fn match_namespace<'a, T>(
+    patterns: &'a IndexMap<String, T>,
+    namespace: &str,
+) -> Option<&'a T> {
+    patterns
+        .iter()
+        .filter(|(pattern, _)| match pattern.strip_suffix('*') {
+            Some(prefix) => format!("{namespace}:").starts_with(prefix),
+            None => pattern.as_str() == namespace,
+        })
+        .max_by_key(|(pattern, _)| pattern.len())
+        .map(|(_, value)| value)
+}
@@ -0,0 +1,420 @@
+//! The network-independent core of the client.
+//!
+//! The core validates log records, verifies checkpoints and transparency
+//! proofs, and resolves package versions. It depends on neither an async
+//! runtime nor the file system, so it compiles to `wasm32-unknown-unknown`;
+//! storage and HTTP are provided by the implementations of the
+//! [`StateStorage`] and [`Transport`] traits given to a [`Verifier`].
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use warg_api::v1::{
+    fetch::{FetchLogsResponse, PublishedRecord},
+    proof::ProofError,
+};
+use warg_crypto::{hash::HashAlgorithm, signing, Encode, Signable};
+use warg_protocol::{
+    operator,
+    package::{self, LogState, Release},
+    registry::{
+        Checkpoint, LogId, LogLeaf, PackageName, RegistryIndex, RegistryLen, TimestampedCheckpoint,
+    },
+    PublishedProtoEnvelope, SerdeEnvelope, VersionReq,
+};
+
+use indexmap::IndexMap;
+
+mod proof;
+mod verifier;
+
+pub use proof::*;
+pub use verifier::*;
+
+/// Represents information about a registry operator.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatorInfo {
+    /// The current operator log state
+    #[serde(default)]
+    pub state: operator::LogState,
+    /// The registry log index of the most recent record
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head_registry_index: Option<RegistryIndex>,
+    /// The fetch token for the most recent record
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head_fetch_token: Option<String>,
+}
+
+/// Represents information about a registry package.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageInfo {
+    /// The package name to publish.
+    pub name: PackageName,
+    /// The last known checkpoint of the package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<Checkpoint>,
+    /// The current package log state
+    #[serde(default)]
+    pub state: package::LogState,
+    /// The registry log index of the most recent record
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head_registry_index: Option<RegistryIndex>,
+    /// The fetch token for the most recent record
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head_fetch_token: Option<String>,
+}
+
+impl PackageInfo {
+    /// Creates a new package info for the given package name.
+    pub fn new(name: impl Into<PackageName>) -> Self {
+        Self {
+            name: name.into(),
+            checkpoint: None,
+            state: package::LogState::default(),
+            head_registry_index: None,
+            head_fetch_token: None,
+        }
+    }
+}
+
+/// The records validated while fetching logs up to a checkpoint.
+#[derive(Debug, Default)]
+pub struct ValidatedLogs {
+    /// The new operator records.
+    pub operator: Vec<PublishedProtoEnvelope<operator::OperatorRecord>>,
+    /// The number of new records for each package log.
+    pub packages: IndexMap<LogId, usize>,
+}
+
+impl ValidatedLogs {
+    /// Creates an empty set of validated records for the given package logs.
+    pub fn new<'a>(packages: impl IntoIterator<Item = &'a LogId>) -> Self {
+        Self {
+            operator: Vec::new(),
+            packages: packages.into_iter().map(|id| (id.clone(), 0)).collect(),
+        }
+    }
+}
+
+/// Represents an error of the client core.
+#[derive(Debug, Error)]
+pub enum CoreError {
+    /// Checkpoint signature failed verification
+    #[error("invalid checkpoint signature")]
+    InvalidCheckpointSignature,
+
+    /// The checkpoint was signed by a key that the operator log does not
+    /// authorize to sign checkpoints.
+    #[error("the checkpoint was signed by key `{key_id}`, which is not authorized by the operator log to sign checkpoints")]
+    UntrustedCheckpoint {
+        /// The signature key ID.
+        key_id: signing::KeyID,
+    },
+
+    /// The registry provided a latest checkpoint with a log length or
+    /// timestamp less than that of a previously provided checkpoint.
+    #[error("registry rewinded checkpoints; latest checkpoint at log length `{to_log_length}` (timestamp {to_timestamp}) is older than previously received checkpoint at log length `{from_log_length}` (timestamp {from_timestamp})")]
+    CheckpointRegression {
+        /// The previously received checkpoint log length.
+        from_log_length: RegistryLen,
+        /// The previously received checkpoint timestamp.
+        from_timestamp: u64,
+        /// The latest checkpoint log length.
+        to_log_length: RegistryLen,
+        /// The latest checkpoint timestamp.
+        to_timestamp: u64,
+    },
+
+    /// The registry provided a checkpoint with a different `log_root` and
+    /// `map_root` than a previously provided checkpoint.
+    #[error("registry provided a new checkpoint with the same log length `{log_length}` as previously fetched but different log root or map root")]
+    CheckpointChangedLogRootOrMapRoot {
+        /// The checkpoint log length.
+        log_length: RegistryLen,
+    },
+
+    /// The server did not provide operator records.
+    #[error("the server did not provide any operator records")]
+    NoOperatorRecords,
+
+    /// The operator failed validation.
+    #[error("operator failed validation: {inner}")]
+    OperatorValidationFailed {
+        /// The validation error.
+        inner: operator::ValidationError,
+    },
+
+    /// The package failed validation.
+    #[error("package `{name}` failed validation: {inner}")]
+    PackageValidationFailed {
+        /// The package that failed validation.
+        name: PackageName,
+        /// The validation error.
+        inner: package::ValidationError,
+    },
+
+    /// The package log is empty and cannot be validated.
+    #[error("package log is empty and cannot be validated")]
+    PackageLogEmpty {
+        /// The package with an empty package log.
+        name: PackageName,
+    },
+
+    /// The registry returned records of a log that was not requested.
+    #[error("received records for unknown package log `{0}`")]
+    UnknownPackageLog(LogId),
+
+    /// A transparency proof provided by the registry failed verification.
+    #[error("failed to verify a proof provided by the registry: {0}")]
+    InvalidProof(ProofError),
+
+    /// The registry uses a hash algorithm the client does not support.
+    #[error("the registry uses unsupported hash algorithm `{0}`")]
+    UnsupportedHashAlgorithm(HashAlgorithm),
+
+    /// A request made through the transport failed.
+    #[error("failed to send request to registry server: {0:#}")]
+    Transport(anyhow::Error),
+
+    /// The state storage failed.
+    #[error("failed to access client state: {0:#}")]
+    Storage(anyhow::Error),
+
+    /// A record envelope returned by the registry could not be decoded.
+    #[error("{0:#}")]
+    Other(#[from] anyhow::Error),
+}
+
+/// Validates the records of a fetch logs response into the given states.
+///
+/// Records the states already include are skipped; the validated records
+/// are added to `validated`.
+pub fn validate_fetched_logs(
+    response: FetchLogsResponse,
+    operator: &mut OperatorInfo,
+    packages: &mut IndexMap<LogId, &mut PackageInfo>,
+    validated: &mut ValidatedLogs,
+) -> Result<(), CoreError> {
+    for record in response.operator {
+        validate_fetched_operator_record(record, operator, validated)?;
+    }
+
+    for (log_id, records) in response.packages {
+        for record in records {
+            validate_fetched_package_record(&log_id, record, packages, validated)?;
+        }
+
+        // At this point, the package log should not be empty
+        check_package_log_not_empty(
+            packages
+                .get(&log_id)
+                .ok_or_else(|| CoreError::UnknownPackageLog(log_id.clone()))?,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Validates a fetched operator record into the given operator state.
+///
+/// A record that was already validated is skipped.
+pub fn validate_fetched_operator_record(
+    record: PublishedRecord,
+    operator: &mut OperatorInfo,
+    validated: &mut ValidatedLogs,
+) -> Result<(), CoreError> {
+    let proto_envelope: PublishedProtoEnvelope<operator::OperatorRecord> =
+        record.envelope.try_into()?;
+
+    // skip over records that has already seen
+    if operator.head_registry_index.is_none()
+        || proto_envelope.registry_index > operator.head_registry_index.unwrap()
+    {
+        let state = std::mem::take(&mut operator.state);
+        operator.state = state
+            .validate(&proto_envelope.envelope)
+            .map_err(|inner| CoreError::OperatorValidationFailed { inner })?;
+        operator.head_registry_index = Some(proto_envelope.registry_index);
+        operator.head_fetch_token = Some(record.fetch_token);
+        validated.operator.push(proto_envelope);
+    }
+
+    Ok(())
+}
+
+/// Validates a fetched record of the given package log into the state of
+/// the package.
+///
+/// A record that was already validated is skipped.
+pub fn validate_fetched_package_record(
+    log_id: &LogId,
+    record: PublishedRecord,
+    packages: &mut IndexMap<LogId, &mut PackageInfo>,
+    validated: &mut ValidatedLogs,
+) -> Result<(), CoreError> {
+    let package = packages
+        .get_mut(log_id)
+        .ok_or_else(|| CoreError::UnknownPackageLog(log_id.clone()))?;
+    let proto_envelope: PublishedProtoEnvelope<package::PackageRecord> =
+        record.envelope.try_into()?;
+
+    // skip over records that has already seen
+    if package.head_registry_index.is_none()
+        || proto_envelope.registry_index > package.head_registry_index.unwrap()
+    {
+        let state = std::mem::take(&mut package.state);
+        package.state = state.validate(&proto_envelope.envelope).map_err(|inner| {
+            CoreError::PackageValidationFailed {
+                name: package.name.clone(),
+                inner,
+            }
+        })?;
+        package.head_registry_index = Some(proto_envelope.registry_index);
+        package.head_fetch_token = Some(record.fetch_token);
+        *validated.packages.entry(log_id.clone()).or_default() += 1;
+    }
+
+    Ok(())
+}
+
+/// Checks that a fetched package log has at least one record.
+pub fn check_package_log_not_empty(package: &PackageInfo) -> Result<(), CoreError> {
+    if package.state.head().is_none() {
+        return Err(CoreError::PackageLogEmpty {
+            name: package.name.clone(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Verifies the checkpoint is signed by a key the given operator log
+/// state authorizes to sign checkpoints.
+pub fn verify_checkpoint_signature(
+    operator: &operator::LogState,
+    ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
+) -> Result<(), CoreError> {
+    let key_id = ts_checkpoint.key_id();
+    let key = operator
+        .public_key(key_id)
+        .filter(|_| {
+            operator
+                .key_has_permission_to_sign_checkpoints(key_id, ts_checkpoint.as_ref().timestamp)
+        })
+        .ok_or_else(|| CoreError::UntrustedCheckpoint {
+            key_id: key_id.clone(),
+        })?;
+    TimestampedCheckpoint::verify(
+        key,
+        &ts_checkpoint.as_ref().encode(),
+        ts_checkpoint.signature(),
+    )
+    .or(Err(CoreError::InvalidCheckpointSignature))
+}
+
+/// Checks that a checkpoint may follow the checkpoint previously received.
+///
+/// A checkpoint may not be older than the previous checkpoint, nor change
+/// the roots of a checkpoint of the same log length.
+///
+/// Returns `true` if the log grew, so the consistency of the logs of the two
+/// checkpoints must be proven.
+pub fn check_checkpoint_succession(
+    from: &TimestampedCheckpoint,
+    to: &TimestampedCheckpoint,
+) -> Result<bool, CoreError> {
+    if to.checkpoint.log_length < from.checkpoint.log_length || to.timestamp < from.timestamp {
+        return Err(CoreError::CheckpointRegression {
+            from_log_length: from.checkpoint.log_length,
+            from_timestamp: from.timestamp,
+            to_log_length: to.checkpoint.log_length,
+            to_timestamp: to.timestamp,
+        });
+    }
+
+    if to.checkpoint.log_length > from.checkpoint.log_length {
+        return Ok(true);
+    }
+
+    if from.checkpoint.log_root != to.checkpoint.log_root
+        || from.checkpoint.map_root != to.checkpoint.map_root
+    {
+        return Err(CoreError::CheckpointChangedLogRootOrMapRoot {
+            log_length: from.checkpoint.log_length,
+        });
+    }
+
+    Ok(false)
+}
+
+/// Gets the registry indexes and leafs of the heads of the operator log and
+/// the given package logs, to prove their inclusion in the given checkpoint.
+pub fn log_heads<'a>(
+    checkpoint: &Checkpoint,
+    operator: &OperatorInfo,
+    packages: impl IntoIterator<Item = (&'a LogId, &'a PackageInfo)>,
+) -> Result<(Vec<RegistryIndex>, Vec<LogLeaf>), CoreError> {
+    let mut leaf_indices = Vec::new();
+    let mut leafs = Vec::new();
+
+    // operator record inclusion
+    match (operator.head_registry_index, operator.state.head()) {
+        (Some(index), Some(head)) => {
+            leaf_indices.push(index);
+            leafs.push(LogLeaf {
+                log_id: LogId::operator_log_for(checkpoint.log_root.algorithm()),
+                record_id: head.digest.clone(),
+            });
+        }
+        _ => return Err(CoreError::NoOperatorRecords),
+    }
+
+    // package records inclusion
+    for (log_id, package) in packages {
+        match (package.head_registry_index, package.state.head()) {
+            (Some(index), Some(head)) => {
+                leaf_indices.push(index);
+                leafs.push(LogLeaf {
+                    log_id: log_id.clone(),
+                    record_id: head.digest.clone(),
+                });
+            }
+            _ => {
+                return Err(CoreError::PackageLogEmpty {
+                    name: package.name.clone(),
+                })
+            }
+        }
+    }
+
+    Ok((leaf_indices, leafs))
+}
+
+/// Resolves a version requirement to a release of a package log.
+///
+/// The highest matching version is selected, except that a prerelease is
+/// only selected when no stable version matches. Yanked releases are skipped
+/// unless `include_yanked` is set, and releases by a key revoked as of their
+/// release are skipped unless `include_revoked` is set.
+pub fn resolve_release<'a>(
+    state: &'a LogState,
+    req: &VersionReq,
+    include_yanked: bool,
+    include_revoked: bool,
+) -> Option<&'a Release> {
+    state
+        .releases()
+        .filter(|r| {
+            (include_yanked || !r.yanked())
+                && (include_revoked || state.is_trusted(r))
+                && req.matches(&r.version)
+        })
+        .max_by(|a, b| {
+            a.version
+                .pre
+                .is_empty()
+                .cmp(&b.version.pre.is_empty())
+                .then_with(|| a.version.cmp(&b.version))
+        })
+}
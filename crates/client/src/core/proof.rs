@@ -0,0 +1,148 @@
+use super::CoreError;
+use warg_api::v1::proof::{InclusionResponse, ProofError};
+use warg_crypto::hash::{AnyHash, Hash, HashAlgorithm, HashError, Sha256, Sha512, SupportedDigest};
+use warg_protocol::registry::{Checkpoint, LogId, LogLeaf, MapLeaf};
+use warg_transparency::{
+    log::{LogProofBundle, ProofBundle},
+    map::MapProofBundle,
+};
+
+/// Verifies that the given leafs are included in the log and map of the
+/// given checkpoint, as proven by an inclusion response.
+///
+/// Every leaf must be proven.
+pub fn verify_inclusion(
+    response: &InclusionResponse,
+    checkpoint: &Checkpoint,
+    leafs: &[LogLeaf],
+) -> Result<(), CoreError> {
+    match checkpoint.log_root.algorithm() {
+        HashAlgorithm::Sha256 => verify_inclusion_proofs::<Sha256>(response, checkpoint, leafs),
+        HashAlgorithm::Sha512 => verify_inclusion_proofs::<Sha512>(response, checkpoint, leafs),
+        algorithm => return Err(CoreError::UnsupportedHashAlgorithm(algorithm)),
+    }
+    .map_err(CoreError::InvalidProof)
+}
+
+fn verify_inclusion_proofs<D: SupportedDigest>(
+    response: &InclusionResponse,
+    checkpoint: &Checkpoint,
+    leafs: &[LogLeaf],
+) -> Result<(), ProofError> {
+    let bundle_failure = |e: anyhow::Error| ProofError::BundleFailure(format!("{e:#}"));
+    let log_root: Hash<D> = checkpoint
+        .log_root
+        .clone()
+        .try_into()
+        .map_err(|e: HashError| ProofError::BundleFailure(e.to_string()))?;
+    let map_root: Hash<D> = checkpoint
+        .map_root
+        .clone()
+        .try_into()
+        .map_err(|e: HashError| ProofError::BundleFailure(e.to_string()))?;
+
+    // Every leaf must be proven; a bundle with fewer proofs proves nothing
+    // about the remaining leafs
+    let log_proof_bundle: LogProofBundle<D, LogLeaf> =
+        LogProofBundle::decode(response.log.as_slice()).map_err(bundle_failure)?;
+    let (log_data, _, log_inclusions) = log_proof_bundle.unbundle();
+    if log_inclusions.len() != leafs.len() {
+        return Err(ProofError::BundleFailure(format!(
+            "expected {expected} log inclusion proof(s) but found {found}",
+            expected = leafs.len(),
+            found = log_inclusions.len()
+        )));
+    }
+
+    for (leaf, proof) in leafs.iter().zip(log_inclusions.iter()) {
+        let found = proof
+            .evaluate_value(&log_data, leaf)
+            .map_err(|e| ProofError::BundleFailure(e.to_string()))?;
+        if found != log_root {
+            return Err(ProofError::IncorrectProof {
+                root: checkpoint.log_root.clone(),
+                found: found.into(),
+            });
+        }
+    }
+
+    let map_proof_bundle: MapProofBundle<D, LogId, MapLeaf> =
+        MapProofBundle::decode(response.map.as_slice()).map_err(bundle_failure)?;
+    let map_inclusions = map_proof_bundle.unbundle();
+    if map_inclusions.len() != leafs.len() {
+        return Err(ProofError::BundleFailure(format!(
+            "expected {expected} map inclusion proof(s) but found {found}",
+            expected = leafs.len(),
+            found = map_inclusions.len()
+        )));
+    }
+
+    for (leaf, proof) in leafs.iter().zip(map_inclusions.iter()) {
+        let found = proof.evaluate(
+            &leaf.log_id,
+            &MapLeaf {
+                record_id: leaf.record_id.clone(),
+            },
+        );
+        if found != map_root {
+            return Err(ProofError::IncorrectProof {
+                root: checkpoint.map_root.clone(),
+                found: found.into(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Verifies that the log with the first root is consistent with the log with
+/// the second root, as proven by the given consistency proof bundle.
+pub fn verify_consistency(
+    proof: &[u8],
+    from_log_root: &AnyHash,
+    to_log_root: &AnyHash,
+) -> Result<(), CoreError> {
+    let (from, to) = match from_log_root.algorithm() {
+        HashAlgorithm::Sha256 => evaluate_consistency::<Sha256>(proof),
+        HashAlgorithm::Sha512 => evaluate_consistency::<Sha512>(proof),
+        algorithm => return Err(CoreError::UnsupportedHashAlgorithm(algorithm)),
+    }
+    .map_err(CoreError::InvalidProof)?;
+
+    for (root, found) in [(from_log_root, from), (to_log_root, to)] {
+        if root != &found {
+            return Err(CoreError::InvalidProof(ProofError::IncorrectProof {
+                root: root.clone(),
+                found,
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+fn evaluate_consistency<D: SupportedDigest>(
+    proof: &[u8],
+) -> Result<(AnyHash, AnyHash), ProofError> {
+    let proof = ProofBundle::<D, LogLeaf>::decode(proof)
+        .map_err(|e| ProofError::BundleFailure(format!("{e:#}")))?;
+    let (log_data, consistencies, inclusions) = proof.unbundle();
+    if !inclusions.is_empty() {
+        return Err(ProofError::BundleFailure(
+            "expected no inclusion proofs".into(),
+        ));
+    }
+
+    if consistencies.len() != 1 {
+        return Err(ProofError::BundleFailure(
+            "expected exactly one consistency proof".into(),
+        ));
+    }
+
+    consistencies
+        .first()
+        .unwrap()
+        .evaluate(&log_data)
+        .map(|(from, to)| (AnyHash::from(from), AnyHash::from(to)))
+        .map_err(|e| ProofError::BundleFailure(e.to_string()))
+}
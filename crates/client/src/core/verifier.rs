@@ -0,0 +1,270 @@
+use super::{
+    check_checkpoint_succession, log_heads, validate_fetched_logs, verify_checkpoint_signature,
+    verify_consistency, verify_inclusion, CoreError, OperatorInfo, PackageInfo, ValidatedLogs,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use indexmap::IndexMap;
+use std::{borrow::Cow, sync::Mutex};
+use warg_api::v1::{
+    fetch::{FetchLogsRequest, FetchLogsResponse},
+    proof::{ConsistencyRequest, ConsistencyResponse, InclusionRequest, InclusionResponse},
+};
+use warg_protocol::{
+    registry::{LogId, PackageName, TimestampedCheckpoint},
+    SerdeEnvelope,
+};
+
+/// Sends the requests of a [`Verifier`] to a registry.
+///
+/// On `wasm32` targets the returned futures need not be `Send`, so the
+/// trait may be implemented with the browser's `fetch()`.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait Transport {
+    /// Gets the latest checkpoint of the registry.
+    async fn latest_checkpoint(&self) -> Result<SerdeEnvelope<TimestampedCheckpoint>>;
+
+    /// Fetches log records.
+    async fn fetch_logs(&self, request: FetchLogsRequest<'_>) -> Result<FetchLogsResponse>;
+
+    /// Gets the proof of the inclusion of log leafs in a checkpoint.
+    async fn prove_inclusion(&self, request: InclusionRequest) -> Result<InclusionResponse>;
+
+    /// Gets the proof of the consistency between two log lengths.
+    async fn prove_consistency(&self, request: ConsistencyRequest) -> Result<ConsistencyResponse>;
+}
+
+/// Stores the state of a [`Verifier`].
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait StateStorage {
+    /// Loads the most recent checkpoint.
+    async fn load_checkpoint(&self) -> Result<Option<SerdeEnvelope<TimestampedCheckpoint>>>;
+
+    /// Stores the most recent checkpoint.
+    async fn store_checkpoint(
+        &self,
+        ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
+    ) -> Result<()>;
+
+    /// Loads the operator information.
+    ///
+    /// Returns `Ok(None)` if the information is not present.
+    async fn load_operator(&self) -> Result<Option<OperatorInfo>>;
+
+    /// Stores the operator information.
+    async fn store_operator(&self, operator: OperatorInfo) -> Result<()>;
+
+    /// Loads the information of the given package.
+    ///
+    /// Returns `Ok(None)` if the information is not present.
+    async fn load_package(&self, name: &PackageName) -> Result<Option<PackageInfo>>;
+
+    /// Stores the information of a package.
+    async fn store_package(&self, info: &PackageInfo) -> Result<()>;
+}
+
+/// A [`StateStorage`] that keeps the state in memory.
+#[derive(Default)]
+pub struct MemoryStateStorage {
+    checkpoint: Mutex<Option<SerdeEnvelope<TimestampedCheckpoint>>>,
+    operator: Mutex<Option<OperatorInfo>>,
+    packages: Mutex<IndexMap<PackageName, PackageInfo>>,
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl StateStorage for MemoryStateStorage {
+    async fn load_checkpoint(&self) -> Result<Option<SerdeEnvelope<TimestampedCheckpoint>>> {
+        Ok(self.checkpoint.lock().unwrap().clone())
+    }
+
+    async fn store_checkpoint(
+        &self,
+        ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
+    ) -> Result<()> {
+        *self.checkpoint.lock().unwrap() = Some(ts_checkpoint.clone());
+        Ok(())
+    }
+
+    async fn load_operator(&self) -> Result<Option<OperatorInfo>> {
+        Ok(self.operator.lock().unwrap().clone())
+    }
+
+    async fn store_operator(&self, operator: OperatorInfo) -> Result<()> {
+        *self.operator.lock().unwrap() = Some(operator);
+        Ok(())
+    }
+
+    async fn load_package(&self, name: &PackageName) -> Result<Option<PackageInfo>> {
+        Ok(self.packages.lock().unwrap().get(name).cloned())
+    }
+
+    async fn store_package(&self, info: &PackageInfo) -> Result<()> {
+        self.packages
+            .lock()
+            .unwrap()
+            .insert(info.name.clone(), info.clone());
+        Ok(())
+    }
+}
+
+/// Fetches package logs and verifies them against the registry's
+/// checkpoints.
+pub struct Verifier<T, S> {
+    transport: T,
+    storage: S,
+}
+
+impl<T: Transport, S: StateStorage> Verifier<T, S> {
+    /// Creates a new verifier with the given transport and state storage.
+    pub fn new(transport: T, storage: S) -> Self {
+        Self { transport, storage }
+    }
+
+    /// Gets the transport of the verifier.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Gets the state storage of the verifier.
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// Updates the given packages to the registry's latest checkpoint.
+    ///
+    /// The new records are validated, the checkpoint's signature is verified,
+    /// and the inclusion of the log heads in the checkpoint and the
+    /// consistency of the checkpoint with the previously stored one are
+    /// proven. The state is only stored once everything is verified.
+    ///
+    /// Returns the updated package information.
+    pub async fn update(&self, names: &[PackageName]) -> Result<Vec<PackageInfo>, CoreError> {
+        let ts_checkpoint = self
+            .transport
+            .latest_checkpoint()
+            .await
+            .map_err(CoreError::Transport)?;
+        let checkpoint = &ts_checkpoint.as_ref().checkpoint;
+
+        // Refuse a checkpoint that does not follow the one previously
+        // received before fetching anything
+        let from = self
+            .storage
+            .load_checkpoint()
+            .await
+            .map_err(CoreError::Storage)?;
+        let prove_consistency = match &from {
+            Some(from) => check_checkpoint_succession(from.as_ref(), ts_checkpoint.as_ref())?,
+            None => false,
+        };
+
+        let mut operator = self
+            .storage
+            .load_operator()
+            .await
+            .map_err(CoreError::Storage)?
+            .unwrap_or_default();
+        let mut infos = Vec::with_capacity(names.len());
+        for name in names {
+            infos.push(
+                self.storage
+                    .load_package(name)
+                    .await
+                    .map_err(CoreError::Storage)?
+                    .unwrap_or_else(|| PackageInfo::new(name.clone())),
+            );
+        }
+
+        let mut packages = infos
+            .iter_mut()
+            .map(|p| {
+                (
+                    LogId::package_log_for(checkpoint.log_root.algorithm(), &p.name),
+                    p,
+                )
+            })
+            .collect::<IndexMap<_, _>>();
+        let mut validated = ValidatedLogs::new(packages.keys());
+        let mut last_known = packages
+            .iter()
+            .map(|(id, p)| (id.clone(), p.head_fetch_token.clone()))
+            .collect::<IndexMap<_, _>>();
+
+        loop {
+            let response = self
+                .transport
+                .fetch_logs(FetchLogsRequest {
+                    log_length: checkpoint.log_length,
+                    operator: operator.head_fetch_token.as_deref().map(Cow::Borrowed),
+                    limit: None,
+                    total_limit: None,
+                    packages: Cow::Borrowed(&last_known),
+                })
+                .await
+                .map_err(CoreError::Transport)?;
+            let more = response.more;
+            validate_fetched_logs(response, &mut operator, &mut packages, &mut validated)?;
+            if !more {
+                break;
+            }
+
+            // Update the last known record fetch token for each package log
+            for (id, fetch_token) in last_known.iter_mut() {
+                *fetch_token = packages[id].head_fetch_token.clone();
+            }
+        }
+
+        verify_checkpoint_signature(&operator.state, &ts_checkpoint)?;
+
+        let (leaf_indices, leafs) = log_heads(
+            checkpoint,
+            &operator,
+            packages.iter().map(|(id, p)| (id, &**p)),
+        )?;
+        let response = self
+            .transport
+            .prove_inclusion(InclusionRequest {
+                log_length: checkpoint.log_length,
+                leafs: leaf_indices,
+            })
+            .await
+            .map_err(CoreError::Transport)?;
+        verify_inclusion(&response, checkpoint, &leafs)?;
+
+        if let (true, Some(from)) = (prove_consistency, &from) {
+            let from = &from.as_ref().checkpoint;
+            let response = self
+                .transport
+                .prove_consistency(ConsistencyRequest {
+                    from: from.log_length,
+                    to: checkpoint.log_length,
+                })
+                .await
+                .map_err(CoreError::Transport)?;
+            verify_consistency(&response.proof, &from.log_root, &checkpoint.log_root)?;
+        }
+
+        // Every proof has been verified; only now is the new state stored
+        self.storage
+            .store_operator(operator)
+            .await
+            .map_err(CoreError::Storage)?;
+        for package in packages.values_mut() {
+            package.checkpoint = Some(checkpoint.clone());
+            self.storage
+                .store_package(package)
+                .await
+                .map_err(CoreError::Storage)?;
+        }
+        self.storage
+            .store_checkpoint(&ts_checkpoint)
+            .await
+            .map_err(CoreError::Storage)?;
+
+        drop(packages);
+        Ok(infos)
+    }
+}
@@ -13,7 +13,6 @@ use wasmparser::{Chunk, ComponentImportSectionReader, Parser, Payload};
 use super::Client;
 use crate::storage::{ContentStorage, NamespaceMapStorage, PackageInfo, RegistryStorage};
 use crate::version_util::{DependencyImportParser, Import, ImportKind};
-/// Import Kinds found in components
 
 /// Creates list of dependenies for locking components
 pub struct LockListBuilder {
@@ -45,6 +44,7 @@ impl LockListBuilder {
     }
 
     #[async_recursion]
+    #[allow(clippy::multiple_bound_locations)]
     async fn parse_package<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage>(
         &mut self,
         client: &Client<R, C, N>,
@@ -101,11 +101,21 @@ impl LockListBuilder {
             match import.kind {
                 ImportKind::Locked(_) | ImportKind::Unlocked => {
                     let id = PackageName::new(import.name.clone())?;
-                    if let Some(info) = client
-                        .registry()
-                        .load_package(client.api.get_warg_registry(), &id)
-                        .await?
-                    {
+                    let info = match client.load_routed_package(&id).await? {
+                        Some(info) => Some(info),
+                        None => {
+                            // The client only downloads from the registry it
+                            // was last refreshed for
+                            let (registry, domain) = client.package_registry(&id).await?;
+                            if &domain != client.get_warg_registry() {
+                                bail!("package `{id}` is served by registry `{registry}`; download it before locking");
+                            }
+
+                            client.download(&id, &VersionReq::STAR).await?;
+                            client.load_routed_package(&id).await?
+                        }
+                    };
+                    if let Some(info) = info {
                         let release = info.state.releases().last();
                         if let Some(r) = release {
                             if let Some(bytes) = self.release_bytes(r, client)? {
@@ -113,21 +123,6 @@ impl LockListBuilder {
                             }
                         }
                         self.lock_list.insert(import);
-                    } else {
-                        client.download(&id, &VersionReq::STAR).await?;
-                        if let Some(info) = client
-                            .registry()
-                            .load_package(client.api.get_warg_registry(), &id)
-                            .await?
-                        {
-                            let release = info.state.releases().last();
-                            if let Some(r) = release {
-                                if let Some(bytes) = self.release_bytes(r, client)? {
-                                    self.parse_package(client, &bytes).await?;
-                                }
-                            }
-                            self.lock_list.insert(import);
-                        }
                     }
                 }
                 ImportKind::Interface(_) => {}
@@ -142,7 +137,7 @@ impl LockListBuilder {
         client: &Client<R, C, N>,
     ) -> Result<Option<Vec<u8>>> {
         let state = &release.state;
-        if let ReleaseState::Released { content } = state {
+        if let ReleaseState::Released { content, .. } = state {
             let path = client.content().content_location(content);
             if let Some(p) = path {
                 return Ok(Some(fs::read(p)?));
@@ -153,6 +148,7 @@ impl LockListBuilder {
 
     /// List of deps for building
     #[async_recursion]
+    #[allow(clippy::multiple_bound_locations)]
     pub async fn build_list<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage>(
         &mut self,
         client: &Client<R, C, N>,
@@ -161,7 +157,7 @@ impl LockListBuilder {
         let release = info.state.releases().last();
         if let Some(r) = release {
             let state = &r.state;
-            if let ReleaseState::Released { content } = state {
+            if let ReleaseState::Released { content, .. } = state {
                 let path = client.content().content_location(content);
                 if let Some(p) = path {
                     let bytes = fs::read(p)?;
@@ -210,32 +206,17 @@ where
             let parsed_imp = dep_parser.parse()?;
             if !parsed_imp.name.contains('/') {
                 let pkg_id = PackageName::new(parsed_imp.name)?;
-                if let Some(info) = self
+                let release = self
                     .client
-                    .registry()
-                    .load_package(self.client.api.get_warg_registry(), &pkg_id)
-                    .await?
-                {
-                    let release = if parsed_imp.req != VersionReq::STAR {
-                        info.state
-                            .releases()
-                            .filter(|r| parsed_imp.req.matches(&r.version))
-                            .last()
-                    } else {
-                        info.state.releases().last()
-                    };
-                    if let Some(r) = release {
-                        let release_state = &r.state;
-                        if let ReleaseState::Released { content } = release_state {
-                            let path = self.client.content().content_location(content);
-                            if let Some(p) = path {
-                                let bytes = fs::read(p)?;
-                                component.section(&RawSection {
-                                    id: ComponentSectionId::Component.into(),
-                                    data: &bytes,
-                                });
-                            }
-                        }
+                    .resolve_version(&pkg_id, &parsed_imp.req, false)
+                    .await?;
+                if let Some(ReleaseState::Released { content, .. }) = release.map(|r| r.state) {
+                    if let Some(p) = self.client.content().content_location(&content) {
+                        let bytes = fs::read(p)?;
+                        component.section(&RawSection {
+                            id: ComponentSectionId::Component.into(),
+                            data: &bytes,
+                        });
                     }
                 }
             } else if let wasmparser::ComponentTypeRef::Instance(i) = imp.ty {
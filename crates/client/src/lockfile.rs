@@ -0,0 +1,72 @@
+//! A module for the lock file describing a resolved set of packages.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+use warg_crypto::hash::AnyHash;
+use warg_protocol::{registry::PackageName, Version};
+
+/// The default file name of a lock file.
+pub const LOCK_FILE_NAME: &str = "warg.lock";
+
+/// The current version of the lock file format.
+pub const LOCK_FILE_VERSION: u32 = 1;
+
+/// Represents the set of packages resolved when locking a component.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockFile {
+    /// The version of the lock file format.
+    pub version: u32,
+    /// The locked packages.
+    #[serde(default)]
+    pub packages: Vec<LockedPackage>,
+}
+
+impl LockFile {
+    /// Creates a new lock file for the given packages.
+    pub fn new(packages: Vec<LockedPackage>) -> Self {
+        Self {
+            version: LOCK_FILE_VERSION,
+            packages,
+        }
+    }
+
+    /// Reads a lock file from the given file path.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read lock file `{path}`", path = path.display()))?;
+
+        serde_json::from_str(&contents)
+            .with_context(|| format!("failed to deserialize file `{path}`", path = path.display()))
+    }
+
+    /// Writes the lock file to the given file path.
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(path, contents)
+            .with_context(|| format!("failed to write lock file `{path}`", path = path.display()))
+    }
+}
+
+/// Represents a single locked package release.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockedPackage {
+    /// The name of the package.
+    pub name: PackageName,
+    /// The locked version of the package.
+    pub version: Version,
+    /// The digest of the release content.
+    pub content: AnyHash,
+    /// The URL of the registry the package was resolved from.
+    pub registry: String,
+    /// The hash of the checkpoint the package was resolved at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<AnyHash>,
+    /// The license of the package, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+}
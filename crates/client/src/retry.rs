@@ -0,0 +1,139 @@
+//! A module for retrying requests that fail with a transient error.
+
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// The default number of times a request is attempted.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 4;
+
+/// The default delay before the first retry of a request.
+pub const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(200);
+
+/// The default maximum delay before a retry of a request.
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(10);
+
+/// A policy for retrying requests to a registry that fail with a transient
+/// error, such as a connection failure or a `502 Bad Gateway` response.
+///
+/// Retries are delayed with exponential backoff: the base delay is doubled
+/// after each retry, up to the maximum delay. With jitter, each delay is
+/// randomized to between half and all of the backoff, so that clients failing
+/// at the same time do not retry in lockstep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryPolicy {
+    /// The number of times a request is attempted, including the first
+    /// attempt.
+    pub max_attempts: u32,
+    /// The delay before the first retry, in milliseconds.
+    pub base_delay_ms: u64,
+    /// The maximum delay before a retry, in milliseconds.
+    pub max_delay_ms: u64,
+    /// Whether the delays before retries are randomized.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay_ms: DEFAULT_BASE_DELAY.as_millis() as u64,
+            max_delay_ms: DEFAULT_MAX_DELAY.as_millis() as u64,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a policy that never retries a request.
+    pub fn never() -> Self {
+        Self::default().with_max_attempts(1)
+    }
+
+    /// Sets the number of times a request is attempted.
+    ///
+    /// A request is always attempted at least once.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn with_base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay_ms = delay.as_millis().try_into().unwrap_or(u64::MAX);
+        self
+    }
+
+    /// Sets the maximum delay before a retry.
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay_ms = delay.as_millis().try_into().unwrap_or(u64::MAX);
+        self
+    }
+
+    /// Sets whether the delays before retries are randomized.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Gets the maximum delay before a retry.
+    pub fn max_delay(&self) -> Duration {
+        Duration::from_millis(self.max_delay_ms)
+    }
+
+    /// Determines if a request is attempted again after the given number of
+    /// attempts.
+    pub fn should_retry(&self, attempts: u32) -> bool {
+        attempts < self.max_attempts
+    }
+
+    /// Gets the delay before attempting a request again after the given
+    /// number of attempts.
+    pub fn delay(&self, attempts: u32) -> Duration {
+        let backoff = self
+            .base_delay_ms
+            .saturating_mul(2u64.saturating_pow(attempts.saturating_sub(1)))
+            .min(self.max_delay_ms);
+
+        if !self.jitter || backoff == 0 {
+            return Duration::from_millis(backoff);
+        }
+
+        let half = backoff / 2;
+        Duration::from_millis(backoff - half + OsRng.next_u64() % (half + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_backs_off_exponentially() {
+        let policy = RetryPolicy::default()
+            .with_base_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(1000))
+            .with_jitter(false);
+
+        let delays = (1..=6).map(|a| policy.delay(a)).collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1000, 1000].map(Duration::from_millis)
+        );
+        assert_eq!(policy.delay(u32::MAX), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn it_jitters_delays() {
+        let policy = RetryPolicy::default().with_base_delay(Duration::from_millis(100));
+
+        for attempts in 1..=3 {
+            let backoff = Duration::from_millis(100 * 2u64.pow(attempts - 1));
+            for _ in 0..100 {
+                let delay = policy.delay(attempts);
+                assert!(delay >= backoff / 2 && delay <= backoff, "{delay:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! A module for generating software bills of materials from lock files.
+
+use crate::lockfile::{LockFile, LockedPackage};
+use anyhow::{bail, Error, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::{fmt, str::FromStr, time::SystemTime};
+use warg_crypto::hash::{AnyHash, HashAlgorithm};
+
+pub mod cyclonedx;
+pub mod spdx;
+
+/// The name of the tool recorded as the creator of generated documents.
+const TOOL_NAME: &str = "warg";
+
+/// The version of the tool recorded as the creator of generated documents.
+const TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The supported software bill of materials formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SbomFormat {
+    /// A CycloneDX 1.5 JSON document.
+    CycloneDx,
+    /// An SPDX 2.3 JSON document.
+    Spdx,
+}
+
+impl fmt::Display for SbomFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CycloneDx => write!(f, "cyclonedx"),
+            Self::Spdx => write!(f, "spdx"),
+        }
+    }
+}
+
+impl FromStr for SbomFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "cyclonedx" => Ok(Self::CycloneDx),
+            "spdx" => Ok(Self::Spdx),
+            _ => bail!("unsupported SBOM format `{s}`: expected `cyclonedx` or `spdx`"),
+        }
+    }
+}
+
+/// Generates a software bill of materials for the given lock file.
+///
+/// The `name` is used to identify the document and `created` is recorded
+/// as its creation time.
+///
+/// Returns the serialized JSON document.
+pub fn generate(
+    lock: &LockFile,
+    format: SbomFormat,
+    name: &str,
+    created: SystemTime,
+) -> Result<String> {
+    Ok(match format {
+        SbomFormat::CycloneDx => {
+            serde_json::to_string_pretty(&cyclonedx::Bom::new(lock, name, created))?
+        }
+        SbomFormat::Spdx => {
+            serde_json::to_string_pretty(&spdx::Document::new(lock, name, created)?)?
+        }
+    })
+}
+
+/// Formats a timestamp as an RFC 3339 UTC date-time.
+fn timestamp(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Gets the hex-encoded digest of a package's content.
+fn content_digest(package: &LockedPackage) -> String {
+    hex(package.content.bytes())
+}
+
+/// Gets the hex-encoded digest identifying a lock file.
+fn lock_digest(lock: &LockFile) -> Result<String> {
+    let bytes = serde_json::to_vec(lock)?;
+    Ok(hex(HashAlgorithm::Sha256.digest(&bytes).bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Gets the checkpoint of a package as a string, if present.
+fn checkpoint(package: &LockedPackage) -> Option<String> {
+    package.checkpoint.as_ref().map(AnyHash::to_string)
+}
@@ -0,0 +1,201 @@
+//! A serde model of a CycloneDX 1.5 JSON document.
+
+use super::{checkpoint, content_digest, timestamp, TOOL_NAME, TOOL_VERSION};
+use crate::lockfile::{LockFile, LockedPackage};
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+use warg_crypto::hash::HashAlgorithm;
+
+/// The CycloneDX specification version of generated documents.
+pub const SPEC_VERSION: &str = "1.5";
+
+/// The name of the property recording the checkpoint a package was resolved at.
+pub const CHECKPOINT_PROPERTY: &str = "warg:checkpoint";
+
+/// Represents a CycloneDX bill of materials.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bom {
+    /// The format of the document; always `CycloneDX`.
+    pub bom_format: String,
+    /// The CycloneDX specification version.
+    pub spec_version: String,
+    /// The version of the bill of materials.
+    pub version: u32,
+    /// The metadata of the bill of materials.
+    pub metadata: Metadata,
+    /// The components in the bill of materials.
+    pub components: Vec<Component>,
+}
+
+impl Bom {
+    /// Creates a bill of materials for the given lock file.
+    pub fn new(lock: &LockFile, name: &str, created: SystemTime) -> Self {
+        Self {
+            bom_format: "CycloneDX".to_string(),
+            spec_version: SPEC_VERSION.to_string(),
+            version: 1,
+            metadata: Metadata {
+                timestamp: timestamp(created),
+                tools: Tools {
+                    components: vec![Tool {
+                        ty: "application".to_string(),
+                        name: TOOL_NAME.to_string(),
+                        version: TOOL_VERSION.to_string(),
+                    }],
+                },
+                component: (!name.is_empty()).then(|| Tool {
+                    ty: "application".to_string(),
+                    name: name.to_string(),
+                    version: String::new(),
+                }),
+            },
+            components: lock.packages.iter().map(Component::new).collect(),
+        }
+    }
+}
+
+/// Represents the metadata of a bill of materials.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metadata {
+    /// When the bill of materials was created.
+    pub timestamp: String,
+    /// The tools used to create the bill of materials.
+    pub tools: Tools,
+    /// The component the bill of materials describes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub component: Option<Tool>,
+}
+
+/// Represents the tools used to create a bill of materials.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tools {
+    /// The tool components.
+    pub components: Vec<Tool>,
+}
+
+/// Represents a tool or described component in the metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tool {
+    /// The type of the component.
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// The name of the component.
+    pub name: String,
+    /// The version of the component.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub version: String,
+}
+
+/// Represents a component in the bill of materials.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Component {
+    /// The type of the component; always `library`.
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// The reference of the component within the document.
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: String,
+    /// The name of the component.
+    pub name: String,
+    /// The version of the component.
+    pub version: String,
+    /// The hashes of the component content.
+    pub hashes: Vec<Hash>,
+    /// The licenses of the component.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub licenses: Vec<License>,
+    /// The external references of the component.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub external_references: Vec<ExternalReference>,
+    /// The additional properties of the component.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<Property>,
+}
+
+impl Component {
+    fn new(package: &LockedPackage) -> Self {
+        Self {
+            ty: "library".to_string(),
+            bom_ref: format!(
+                "{name}@{version}",
+                name = package.name,
+                version = package.version
+            ),
+            name: package.name.to_string(),
+            version: package.version.to_string(),
+            hashes: vec![Hash {
+                alg: match package.content.algorithm() {
+                    HashAlgorithm::Sha256 => "SHA-256".to_string(),
+                    HashAlgorithm::Sha512 => "SHA-512".to_string(),
+                    algorithm => algorithm.to_string().to_uppercase(),
+                },
+                content: content_digest(package),
+            }],
+            licenses: package
+                .license
+                .iter()
+                .map(|name| License {
+                    license: LicenseName { name: name.clone() },
+                })
+                .collect(),
+            external_references: vec![ExternalReference {
+                ty: "distribution".to_string(),
+                url: package.registry.clone(),
+            }],
+            properties: checkpoint(package)
+                .map(|value| Property {
+                    name: CHECKPOINT_PROPERTY.to_string(),
+                    value,
+                })
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+/// Represents a hash of a component.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hash {
+    /// The hash algorithm.
+    pub alg: String,
+    /// The hex-encoded hash value.
+    pub content: String,
+}
+
+/// Represents a license of a component.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct License {
+    /// The license.
+    pub license: LicenseName,
+}
+
+/// Represents a license identified by name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LicenseName {
+    /// The name of the license.
+    pub name: String,
+}
+
+/// Represents an external reference of a component.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalReference {
+    /// The type of the reference.
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// The URL of the reference.
+    pub url: String,
+}
+
+/// Represents a name-value property of a component.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Property {
+    /// The name of the property.
+    pub name: String,
+    /// The value of the property.
+    pub value: String,
+}
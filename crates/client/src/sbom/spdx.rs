@@ -0,0 +1,152 @@
+//! A serde model of an SPDX 2.3 JSON document.
+
+use super::{checkpoint, content_digest, lock_digest, timestamp, TOOL_NAME, TOOL_VERSION};
+use crate::lockfile::{LockFile, LockedPackage};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+use warg_crypto::hash::HashAlgorithm;
+
+/// The SPDX specification version of generated documents.
+pub const SPEC_VERSION: &str = "SPDX-2.3";
+
+/// The value used by SPDX when no assertion is made about a field.
+pub const NOASSERTION: &str = "NOASSERTION";
+
+/// Represents an SPDX document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Document {
+    /// The SPDX specification version.
+    pub spdx_version: String,
+    /// The license of the document data; always `CC0-1.0`.
+    pub data_license: String,
+    /// The identifier of the document.
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    /// The name of the document.
+    pub name: String,
+    /// The unique namespace URI of the document.
+    pub document_namespace: String,
+    /// Information about the creation of the document.
+    pub creation_info: CreationInfo,
+    /// The packages described by the document.
+    pub packages: Vec<Package>,
+}
+
+impl Document {
+    /// Creates an SPDX document for the given lock file.
+    pub fn new(lock: &LockFile, name: &str, created: SystemTime) -> Result<Self> {
+        Ok(Self {
+            spdx_version: SPEC_VERSION.to_string(),
+            data_license: "CC0-1.0".to_string(),
+            spdx_id: "SPDXRef-DOCUMENT".to_string(),
+            name: name.to_string(),
+            document_namespace: format!(
+                "https://spdx.org/spdxdocs/{name}-{digest}",
+                name = identifier(name),
+                digest = lock_digest(lock)?
+            ),
+            creation_info: CreationInfo {
+                created: timestamp(created),
+                creators: vec![format!("Tool: {TOOL_NAME}-{TOOL_VERSION}")],
+            },
+            packages: lock.packages.iter().map(Package::new).collect(),
+        })
+    }
+}
+
+/// Represents the creation information of an SPDX document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreationInfo {
+    /// When the document was created.
+    pub created: String,
+    /// The creators of the document.
+    pub creators: Vec<String>,
+}
+
+/// Represents a package in an SPDX document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Package {
+    /// The identifier of the package within the document.
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    /// The name of the package.
+    pub name: String,
+    /// The version of the package.
+    pub version_info: String,
+    /// Where the package can be downloaded from.
+    pub download_location: String,
+    /// Whether the files of the package were analyzed; always `false`.
+    pub files_analyzed: bool,
+    /// The checksums of the package content.
+    pub checksums: Vec<Checksum>,
+    /// The license concluded for the package.
+    pub license_concluded: String,
+    /// The license declared by the package.
+    pub license_declared: String,
+    /// The copyright text of the package.
+    pub copyright_text: String,
+    /// A comment about the package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+impl Package {
+    fn new(package: &LockedPackage) -> Self {
+        let license = package
+            .license
+            .clone()
+            .unwrap_or_else(|| NOASSERTION.to_string());
+
+        Self {
+            spdx_id: format!(
+                "SPDXRef-Package-{name}-{version}",
+                name = identifier(package.name.as_ref()),
+                version = identifier(&package.version.to_string())
+            ),
+            name: package.name.to_string(),
+            version_info: package.version.to_string(),
+            download_location: package.registry.clone(),
+            files_analyzed: false,
+            checksums: vec![Checksum {
+                algorithm: match package.content.algorithm() {
+                    HashAlgorithm::Sha256 => "SHA256".to_string(),
+                    HashAlgorithm::Sha512 => "SHA512".to_string(),
+                    algorithm => algorithm.to_string().to_uppercase(),
+                },
+                checksum_value: content_digest(package),
+            }],
+            license_concluded: license.clone(),
+            license_declared: license,
+            copyright_text: NOASSERTION.to_string(),
+            comment: checkpoint(package)
+                .map(|checkpoint| format!("resolved at registry checkpoint {checkpoint}")),
+        }
+    }
+}
+
+/// Represents a checksum of a package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checksum {
+    /// The checksum algorithm.
+    pub algorithm: String,
+    /// The hex-encoded checksum value.
+    pub checksum_value: String,
+}
+
+/// Converts a string into one containing only characters valid in an SPDX identifier.
+fn identifier(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
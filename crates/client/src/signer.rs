@@ -0,0 +1,128 @@
+//! A module for signing records with keys the client may not hold.
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use std::process::Stdio;
+use tokio::{io::AsyncWriteExt, process::Command};
+use warg_crypto::signing::{PrivateKey, PublicKey, Signature};
+
+/// Signs package records on behalf of the client.
+///
+/// Implement this trait to sign records with keys that cannot be loaded into
+/// the client, such as keys held by a KMS or a hardware security module.
+#[async_trait]
+pub trait Signer: Send + Sync {
+    /// Gets the public key of the signing key.
+    ///
+    /// The key is used to initialize packages and to identify the key that
+    /// signed a record.
+    fn public_key(&self) -> PublicKey;
+
+    /// Signs the given message with the signing key.
+    async fn sign(&self, message: &[u8]) -> Result<Signature>;
+}
+
+#[async_trait]
+impl Signer for PrivateKey {
+    fn public_key(&self) -> PublicKey {
+        PrivateKey::public_key(self)
+    }
+
+    async fn sign(&self, message: &[u8]) -> Result<Signature> {
+        Ok(PrivateKey::sign(self, message)?)
+    }
+}
+
+/// A signer that runs a command to sign records.
+///
+/// The command is run with the system shell. The message to sign is written
+/// to its stdin, and it is expected to print the signature in
+/// `<algorithm>:<base64>` form on the first line of its stdout. It may print
+/// the ID of the signing key on the second line, which must match the ID of
+/// the signer's public key.
+pub struct CommandSigner {
+    command: String,
+    public_key: PublicKey,
+}
+
+impl CommandSigner {
+    /// Creates a new signer that runs the given command to sign messages with
+    /// the key with the given public key.
+    pub fn new(command: impl Into<String>, public_key: PublicKey) -> Self {
+        Self {
+            command: command.into(),
+            public_key,
+        }
+    }
+
+    fn shell(&self) -> Command {
+        let mut command = if cfg!(windows) {
+            let mut command = Command::new("cmd");
+            command.arg("/C");
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.arg("-c");
+            command
+        };
+        command.arg(&self.command);
+        command
+    }
+}
+
+#[async_trait]
+impl Signer for CommandSigner {
+    fn public_key(&self) -> PublicKey {
+        self.public_key.clone()
+    }
+
+    async fn sign(&self, message: &[u8]) -> Result<Signature> {
+        let command = &self.command;
+        let mut child = self
+            .shell()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to run signing command `{command}`"))?;
+
+        let mut stdin = child.stdin.take().expect("stdin should be piped");
+        stdin
+            .write_all(message)
+            .await
+            .with_context(|| format!("failed to write to signing command `{command}`"))?;
+        drop(stdin);
+
+        let output = child
+            .wait_with_output()
+            .await
+            .with_context(|| format!("failed to run signing command `{command}`"))?;
+        if !output.status.success() {
+            bail!(
+                "signing command `{command}` failed ({status}): {stderr}",
+                status = output.status,
+                stderr = String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let stdout = String::from_utf8(output.stdout)
+            .with_context(|| format!("signing command `{command}` printed invalid UTF-8"))?;
+        let mut lines = stdout.lines().map(str::trim).filter(|l| !l.is_empty());
+        let signature = lines
+            .next()
+            .ok_or_else(|| anyhow!("signing command `{command}` did not print a signature"))?
+            .parse::<Signature>()
+            .with_context(|| format!("signing command `{command}` printed an invalid signature"))?;
+
+        if let Some(key_id) = lines.next() {
+            let expected = self.public_key.fingerprint();
+            if key_id != expected.to_string() {
+                bail!(
+                    "signing command `{command}` signed with key `{key_id}`, but key `{expected}` was expected"
+                );
+            }
+        }
+
+        Ok(signature)
+    }
+}
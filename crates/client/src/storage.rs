@@ -1,29 +1,39 @@
 //! A module for client storage implementations.
 
-use anyhow::{Error, Result};
+use anyhow::{Context, Error, Result};
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures_util::Stream;
 use indexmap::IndexMap;
 use reqwest::header::HeaderValue;
 use serde::{Deserialize, Serialize};
-use std::{path::PathBuf, pin::Pin, str::FromStr, time::SystemTime};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    pin::Pin,
+    str::FromStr,
+    time::SystemTime,
+};
 use warg_crypto::{
     hash::{AnyHash, HashAlgorithm},
-    signing::{self, KeyID, PublicKey},
+    signing::{KeyID, PublicKey},
 };
 use warg_protocol::{
-    operator,
     package::{self, PackageRecord, Permission, PACKAGE_RECORD_VERSION},
-    registry::{Checkpoint, PackageName, RecordId, RegistryIndex, TimestampedCheckpoint},
-    ProtoEnvelope, SerdeEnvelope, Version,
+    registry::{PackageName, RecordId, TimestampedCheckpoint},
+    SerdeEnvelope, Version, VersionReq,
 };
 
+use crate::core::resolve_release;
+pub use crate::core::{OperatorInfo, PackageInfo};
+
 mod fs;
+mod sqlite;
 pub use fs::*;
+pub use sqlite::*;
 
 /// Registry domain used for warg header values
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct RegistryDomain(String);
 
 // impl From<String> for RegistryDomain {
@@ -40,9 +50,9 @@ impl FromStr for RegistryDomain {
     }
 }
 
-impl ToString for RegistryDomain {
-    fn to_string(&self) -> String {
-        self.0.clone()
+impl std::fmt::Display for RegistryDomain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
     }
 }
 
@@ -81,6 +91,9 @@ pub trait RegistryStorage: Send + Sync {
     /// Reset registry local data
     async fn reset(&self, all_registries: bool) -> Result<()>;
 
+    /// Lists the local data that [`RegistryStorage::reset`] removes.
+    async fn list_state(&self, all_registries: bool) -> Result<Vec<RegistryStateEntry>>;
+
     // /// Directory where all registries are stored
     // fn registries_dir(&self) -> PathBuf;
     /// Loads most recent checkpoint
@@ -126,6 +139,27 @@ pub trait RegistryStorage: Send + Sync {
         package: &PackageName,
     ) -> Result<Option<PackageInfo>>;
 
+    /// Resolves a version requirement to a release of a package in the storage.
+    ///
+    /// See [`resolve_release`] for how a release is selected.
+    ///
+    /// Returns `Ok(None)` if the package is not present or no release matches.
+    async fn resolve_version(
+        &self,
+        namespace_registry: &Option<RegistryDomain>,
+        package: &PackageName,
+        req: &VersionReq,
+        include_yanked: bool,
+        include_revoked: bool,
+    ) -> Result<Option<package::Release>> {
+        Ok(self
+            .load_package(namespace_registry, package)
+            .await?
+            .and_then(|info| {
+                resolve_release(&info.state, req, include_yanked, include_revoked).cloned()
+            }))
+    }
+
     /// Stores the package information in the storage.
     async fn store_package(
         &self,
@@ -133,15 +167,187 @@ pub trait RegistryStorage: Send + Sync {
         info: &PackageInfo,
     ) -> Result<()>;
 
-    /// Loads information about a pending publish operation.
+    /// Loads information about the pending publish operations, in the order
+    /// they were started.
+    ///
+    /// At most one publish operation is pending for each package.
+    async fn load_publishes(&self) -> Result<Vec<PublishInfo>>;
+
+    /// Stores information about the pending publish operations.
+    ///
+    /// If `infos` is empty, any existing publish information is deleted.
+    async fn store_publishes(&self, infos: &[PublishInfo]) -> Result<()>;
+
+    /// Loads information about the most recently started pending publish
+    /// operation.
     ///
     /// Returns `Ok(None)` if the information is not present.
-    async fn load_publish(&self) -> Result<Option<PublishInfo>>;
+    async fn load_publish(&self) -> Result<Option<PublishInfo>> {
+        Ok(self.load_publishes().await?.pop())
+    }
 
     /// Stores information about a pending publish operation.
     ///
-    /// If the info is `None`, the any existing publish information is deleted.
-    async fn store_publish(&self, info: Option<&PublishInfo>) -> Result<()>;
+    /// The info replaces any pending publish for the same package; otherwise,
+    /// it becomes the most recently started pending publish.
+    ///
+    /// If the info is `None`, the most recently started pending publish is
+    /// deleted.
+    async fn store_publish(&self, info: Option<&PublishInfo>) -> Result<()> {
+        let mut publishes = self.load_publishes().await?;
+        match info {
+            Some(info) => match publishes.iter_mut().find(|p| p.name == info.name) {
+                Some(existing) => *existing = info.clone(),
+                None => publishes.push(info.clone()),
+            },
+            None => {
+                publishes.pop();
+            }
+        }
+
+        self.store_publishes(&publishes).await
+    }
+}
+
+/// The kind of registry storage used by a client.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "camelCase")]
+pub enum RegistryStorageKind {
+    /// Registry information is stored as files; see [`FileSystemRegistryStorage`].
+    #[default]
+    FileSystem,
+    /// Registry information is stored in a database; see [`SqliteRegistryStorage`].
+    Sqlite,
+}
+
+/// Represents a registry storage on the local machine of a kind selected at
+/// runtime.
+pub enum LocalRegistryStorage {
+    /// The registry storage uses the file system.
+    FileSystem(FileSystemRegistryStorage),
+    /// The registry storage uses a SQLite database.
+    Sqlite(SqliteRegistryStorage),
+}
+
+impl LocalRegistryStorage {
+    /// Attempts to open a registry storage of the given kind.
+    ///
+    /// If the storage is locked and the lock cannot be acquired, `Ok(None)`
+    /// is returned.
+    pub fn try_lock(
+        kind: RegistryStorageKind,
+        base_dir: impl Into<PathBuf>,
+    ) -> Result<Option<Self>> {
+        Ok(match kind {
+            RegistryStorageKind::FileSystem => {
+                FileSystemRegistryStorage::try_lock(base_dir)?.map(Self::FileSystem)
+            }
+            RegistryStorageKind::Sqlite => {
+                Some(Self::Sqlite(SqliteRegistryStorage::open(base_dir)?))
+            }
+        })
+    }
+
+    /// Opens a registry storage of the given kind.
+    ///
+    /// If the storage is locked and the lock cannot be immediately acquired,
+    /// this function will block.
+    pub fn lock(kind: RegistryStorageKind, base_dir: impl Into<PathBuf>) -> Result<Self> {
+        Ok(match kind {
+            RegistryStorageKind::FileSystem => {
+                Self::FileSystem(FileSystemRegistryStorage::lock(base_dir)?)
+            }
+            RegistryStorageKind::Sqlite => Self::Sqlite(SqliteRegistryStorage::open(base_dir)?),
+        })
+    }
+
+    fn inner(&self) -> &dyn RegistryStorage {
+        match self {
+            Self::FileSystem(storage) => storage,
+            Self::Sqlite(storage) => storage,
+        }
+    }
+}
+
+#[async_trait]
+impl RegistryStorage for LocalRegistryStorage {
+    async fn reset(&self, all_registries: bool) -> Result<()> {
+        self.inner().reset(all_registries).await
+    }
+
+    async fn list_state(&self, all_registries: bool) -> Result<Vec<RegistryStateEntry>> {
+        self.inner().list_state(all_registries).await
+    }
+
+    async fn load_checkpoint(
+        &self,
+        namespace_registry: &Option<RegistryDomain>,
+    ) -> Result<Option<SerdeEnvelope<TimestampedCheckpoint>>> {
+        self.inner().load_checkpoint(namespace_registry).await
+    }
+
+    async fn store_checkpoint(
+        &self,
+        namespace_registry: &Option<RegistryDomain>,
+        ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
+    ) -> Result<()> {
+        self.inner()
+            .store_checkpoint(namespace_registry, ts_checkpoint)
+            .await
+    }
+
+    async fn load_operator(
+        &self,
+        namespace_registry: &Option<RegistryDomain>,
+    ) -> Result<Option<OperatorInfo>> {
+        self.inner().load_operator(namespace_registry).await
+    }
+
+    async fn store_operator(
+        &self,
+        namespace_registry: &Option<RegistryDomain>,
+        operator: OperatorInfo,
+    ) -> Result<()> {
+        self.inner()
+            .store_operator(namespace_registry, operator)
+            .await
+    }
+
+    async fn load_packages(&self) -> Result<Vec<PackageInfo>> {
+        self.inner().load_packages().await
+    }
+
+    async fn load_all_packages(&self) -> Result<IndexMap<String, Vec<PackageInfo>>> {
+        self.inner().load_all_packages().await
+    }
+
+    async fn load_package(
+        &self,
+        namespace_registry: &Option<RegistryDomain>,
+        package: &PackageName,
+    ) -> Result<Option<PackageInfo>> {
+        self.inner().load_package(namespace_registry, package).await
+    }
+
+    async fn store_package(
+        &self,
+        namespace_registry: &Option<RegistryDomain>,
+        info: &PackageInfo,
+    ) -> Result<()> {
+        self.inner().store_package(namespace_registry, info).await
+    }
+
+    async fn load_publishes(&self) -> Result<Vec<PublishInfo>> {
+        self.inner().load_publishes().await
+    }
+
+    async fn store_publishes(&self, infos: &[PublishInfo]) -> Result<()> {
+        self.inner().store_publishes(infos).await
+    }
+
+    async fn store_publish(&self, info: Option<&PublishInfo>) -> Result<()> {
+        self.inner().store_publish(info).await
+    }
 }
 
 /// Trait for content storage implementations.
@@ -153,9 +359,21 @@ pub trait ContentStorage: Send + Sync {
     /// Clear content local data
     async fn clear(&self) -> Result<()>;
 
+    /// Lists the digests of the stored content along with the size of each
+    /// in bytes.
+    async fn list_content(&self) -> Result<Vec<(AnyHash, u64)>>;
+
+    /// Removes the content associated with the given digest.
+    ///
+    /// Returns `false` if the content was not present.
+    async fn remove_content(&self, digest: &AnyHash) -> Result<bool>;
+
     /// Gets the location of the content associated with the given digest if it
     /// exists as a file on disk.
     ///
+    /// The location may be in a read-only directory shared with other users,
+    /// so the file must not be modified.
+    ///
     /// Returns `None` if the content is not present on disk.
     fn content_location(&self, digest: &AnyHash) -> Option<PathBuf>;
 
@@ -173,12 +391,89 @@ pub trait ContentStorage: Send + Sync {
     /// content matches the given digest. If the digests do not match, an
     /// error is returned.
     ///
+    /// Content that is already stored is not written again; if the expected
+    /// digest is already stored, the stream is not read at all.
+    ///
     /// Returns the hash of the written content.
     async fn store_content(
         &self,
         stream: Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + Sync>>,
         expected_digest: Option<&AnyHash>,
     ) -> Result<AnyHash>;
+
+    /// Gets the number of bytes of the content associated with the given
+    /// digest that were stored by an interrupted download.
+    ///
+    /// Returns `0` if there is no partially stored content.
+    async fn partial_content_len(&self, digest: &AnyHash) -> Result<u64>;
+
+    /// Stores the given stream as the content associated with the given
+    /// digest, starting at the given offset into the content.
+    ///
+    /// The stream continues the partially stored content, which is truncated
+    /// to the offset; if the stream fails, the bytes stored so far are kept so
+    /// that the download can be resumed.
+    ///
+    /// The entire content is verified against the digest once the stream
+    /// ends. If the digests do not match, the partially stored content is
+    /// removed and the content is not stored.
+    ///
+    /// Returns the hash of the entire content.
+    async fn store_partial_content(
+        &self,
+        stream: Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + Sync>>,
+        digest: &AnyHash,
+        offset: u64,
+    ) -> Result<AnyHash>;
+
+    /// Links the content associated with the given digest to the given path.
+    ///
+    /// The content is hard-linked where possible and copied otherwise, such
+    /// as when the path is on a different file system. An existing file at
+    /// the path is replaced.
+    ///
+    /// Returns `false` if the content is not present on disk.
+    async fn link_content(&self, digest: &AnyHash, path: &Path) -> Result<bool> {
+        let Some(source) = self.content_location(digest) else {
+            return Ok(false);
+        };
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.with_context(|| {
+                format!(
+                    "failed to create directory `{path}`",
+                    path = parent.display()
+                )
+            })?;
+        }
+
+        match tokio::fs::remove_file(path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(Error::new(e).context(format!(
+                    "failed to remove file `{path}`",
+                    path = path.display()
+                )));
+            }
+            _ => {}
+        }
+
+        if let Err(e) = tokio::fs::hard_link(&source, path).await {
+            tracing::debug!(
+                "failed to link `{source}` to `{path}`, copying instead: {e}",
+                source = source.display(),
+                path = path.display()
+            );
+            tokio::fs::copy(&source, path).await.with_context(|| {
+                format!(
+                    "failed to copy `{source}` to `{path}`",
+                    source = source.display(),
+                    path = path.display()
+                )
+            })?;
+        }
+
+        Ok(true)
+    }
 }
 
 /// Trait for namespace map storage implementations.
@@ -199,54 +494,48 @@ pub trait NamespaceMapStorage: Send + Sync {
     ) -> Result<()>;
 }
 
-/// Represents information about a registry operator.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-#[serde(rename_all = "camelCase")]
-pub struct OperatorInfo {
-    /// The current operator log state
-    #[serde(default)]
-    pub state: operator::LogState,
-    /// The registry log index of the most recent record
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub head_registry_index: Option<RegistryIndex>,
-    /// The fetch token for the most recent record
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub head_fetch_token: Option<String>,
-}
-
-/// Represents information about a registry package.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct PackageInfo {
-    /// The package name to publish.
-    pub name: PackageName,
-    /// The last known checkpoint of the package.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub checkpoint: Option<Checkpoint>,
-    /// The current package log state
-    #[serde(default)]
-    pub state: package::LogState,
-    /// The registry log index of the most recent record
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub head_registry_index: Option<RegistryIndex>,
-    /// The fetch token for the most recent record
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub head_fetch_token: Option<String>,
+/// Represents an item of the local data kept for a registry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RegistryStateItem {
+    /// The most recent checkpoint of the registry.
+    Checkpoint,
+    /// The validated operator log of the registry.
+    Operator,
+    /// The validated log of a package.
+    Package {
+        /// The name of the package.
+        name: PackageName,
+    },
+    /// The pending publish operations.
+    PendingPublishes,
 }
 
-impl PackageInfo {
-    /// Creates a new package info for the given package name.
-    pub fn new(name: impl Into<PackageName>) -> Self {
-        Self {
-            name: name.into(),
-            checkpoint: None,
-            state: package::LogState::default(),
-            head_registry_index: None,
-            head_fetch_token: None,
+impl fmt::Display for RegistryStateItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Checkpoint => write!(f, "checkpoint"),
+            Self::Operator => write!(f, "operator log"),
+            Self::Package { name } => write!(f, "package log `{name}`"),
+            Self::PendingPublishes => write!(f, "pending publishes"),
         }
     }
 }
 
+/// Represents an item of the local data kept for a registry along with its
+/// size.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryStateEntry {
+    /// The name of the registry's storage.
+    pub registry: String,
+    /// The item of local data.
+    #[serde(flatten)]
+    pub item: RegistryStateItem,
+    /// The size of the item in bytes.
+    pub size: u64,
+}
+
 /// Represents a record entry being published.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
@@ -258,7 +547,15 @@ pub enum PublishEntry {
         /// The version of the release.
         version: Version,
         /// The content digest of the release.
+        ///
+        /// This is the content of the release's `component` artifact.
         content: AnyHash,
+        /// The content digests of the release's other artifacts, by name.
+        #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+        artifacts: IndexMap<String, AnyHash>,
+        /// The metadata to include with the release.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metadata: Option<package::ReleaseMetadata>,
     },
     /// A release is being yanked.
     Yank {
@@ -279,6 +576,13 @@ pub enum PublishEntry {
         /// The permission(s) being revoked.
         permissions: Vec<Permission>,
     },
+    /// A key is being revoked.
+    RevokeKey {
+        /// The ID of the key being revoked.
+        key_id: KeyID,
+        /// The time the key is revoked as of.
+        since: SystemTime,
+    },
 }
 
 /// Represents information about a package publish.
@@ -302,48 +606,57 @@ impl PublishInfo {
         self.entries.iter().any(|e| matches!(e, PublishEntry::Init))
     }
 
-    pub(crate) fn finalize(
-        self,
-        signing_key: &signing::PrivateKey,
-    ) -> Result<ProtoEnvelope<PackageRecord>> {
-        let mut entries = Vec::with_capacity(self.entries.len());
-        for entry in self.entries {
-            match entry {
-                PublishEntry::Init => {
-                    entries.push(package::PackageEntry::Init {
-                        hash_algorithm: HashAlgorithm::Sha256,
-                        key: signing_key.public_key(),
-                    });
-                }
-                PublishEntry::Release { version, content } => {
-                    entries.push(package::PackageEntry::Release { version, content });
-                }
-                PublishEntry::Yank { version } => {
-                    entries.push(package::PackageEntry::Yank { version })
-                }
+    /// Builds the package record for the publish with the given timestamp.
+    ///
+    /// `key` is the public key of the signer, used to initialize the package.
+    pub fn build_record(
+        &self,
+        key: &PublicKey,
+        hash_algorithm: HashAlgorithm,
+        timestamp: SystemTime,
+    ) -> PackageRecord {
+        let entries = self
+            .entries
+            .iter()
+            .cloned()
+            .map(|entry| match entry {
+                PublishEntry::Init => package::PackageEntry::Init {
+                    hash_algorithm,
+                    key: key.clone(),
+                },
+                PublishEntry::Release {
+                    version,
+                    content,
+                    artifacts,
+                    metadata,
+                } => package::PackageEntry::Release {
+                    version,
+                    content,
+                    artifacts,
+                    metadata,
+                },
+                PublishEntry::Yank { version } => package::PackageEntry::Yank { version },
                 PublishEntry::Grant { key, permissions } => {
-                    entries.push(package::PackageEntry::GrantFlat { key, permissions })
+                    package::PackageEntry::GrantFlat { key, permissions }
                 }
                 PublishEntry::Revoke {
                     key_id,
                     permissions,
-                } => entries.push(package::PackageEntry::RevokeFlat {
+                } => package::PackageEntry::RevokeFlat {
                     key_id,
                     permissions,
-                }),
-            }
-        }
+                },
+                PublishEntry::RevokeKey { key_id, since } => {
+                    package::PackageEntry::RevokeKey { key_id, since }
+                }
+            })
+            .collect();
 
-        let record = package::PackageRecord {
-            prev: self.head,
+        package::PackageRecord {
+            prev: self.head.clone(),
             version: PACKAGE_RECORD_VERSION,
-            // TODO: this seems wrong to record the current time client-side
-            // How can we guarantee that the timestamps are monotonic?
-            // Should incrementing timestamps even be a requirement?
-            timestamp: SystemTime::now(),
+            timestamp,
             entries,
-        };
-
-        Ok(ProtoEnvelope::signed_contents(signing_key, record)?)
+        }
     }
 }
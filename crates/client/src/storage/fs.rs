@@ -2,10 +2,10 @@
 
 use super::{
     ContentStorage, NamespaceMapStorage, OperatorInfo, PackageInfo, PublishInfo, RegistryDomain,
-    RegistryStorage,
+    RegistryStateEntry, RegistryStateItem, RegistryStorage,
 };
 use crate::lock::FileLock;
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures_util::{Stream, StreamExt, TryStreamExt};
@@ -14,14 +14,15 @@ use serde::{Deserialize, Serialize};
 use std::{
     ffi::OsStr,
     fs,
+    io::{Read, SeekFrom},
     path::{Path, PathBuf},
     pin::Pin,
 };
 use tempfile::NamedTempFile;
-use tokio::io::{AsyncWriteExt, BufReader, BufWriter};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt, BufReader, BufWriter};
 use tokio_util::io::ReaderStream;
 use walkdir::WalkDir;
-use warg_crypto::hash::{AnyHash, Digest, Hash, Sha256};
+use warg_crypto::hash::{AnyHash, Digest, Hash, HashAlgorithm, Sha256};
 use warg_protocol::{
     registry::{LogId, PackageName, TimestampedCheckpoint},
     SerdeEnvelope,
@@ -117,6 +118,56 @@ impl FileSystemRegistryStorage {
     fn pending_publish_path(&self) -> PathBuf {
         self.base_dir.join(PENDING_PUBLISH_FILE)
     }
+
+    /// Lists the local data of the registry stored in the given directory.
+    async fn list_registry_state(&self, dir: &Path) -> Result<Vec<RegistryStateEntry>> {
+        let registry = dir
+            .file_name()
+            .and_then(OsStr::to_str)
+            .unwrap_or_default()
+            .to_string();
+
+        let mut entries = Vec::new();
+        for (file, item) in [
+            ("checkpoint", RegistryStateItem::Checkpoint),
+            ("operator.log", RegistryStateItem::Operator),
+            (PENDING_PUBLISH_FILE, RegistryStateItem::PendingPublishes),
+        ] {
+            if let Some(size) = file_size(&dir.join(file))? {
+                entries.push(RegistryStateEntry {
+                    registry: registry.clone(),
+                    item,
+                    size,
+                });
+            }
+        }
+
+        let packages_dir = dir.join(PACKAGE_LOGS_DIR);
+        for entry in WalkDir::new(&packages_dir).into_iter().flatten() {
+            let path = entry.path();
+            let hidden = path
+                .file_name()
+                .and_then(OsStr::to_str)
+                .map_or(true, |name| name.starts_with('.'));
+            if !path.is_file() || hidden {
+                continue;
+            }
+
+            let info: PackageInfo = load(path).await?.ok_or_else(|| {
+                anyhow!(
+                    "failed to load package state from `{path}`",
+                    path = path.display()
+                )
+            })?;
+            entries.push(RegistryStateEntry {
+                registry: registry.clone(),
+                item: RegistryStateItem::Package { name: info.name },
+                size: file_size(path)?.unwrap_or_default(),
+            });
+        }
+
+        Ok(entries)
+    }
 }
 
 #[async_trait]
@@ -129,6 +180,22 @@ impl RegistryStorage for FileSystemRegistryStorage {
         }
     }
 
+    async fn list_state(&self, all_registries: bool) -> Result<Vec<RegistryStateEntry>> {
+        if !all_registries {
+            return self.list_registry_state(&self.base_dir).await;
+        }
+
+        let mut entries = Vec::new();
+        for dir in fs::read_dir(&self.registries_dir)? {
+            let dir = dir?;
+            if dir.file_type()?.is_dir() {
+                entries.extend(self.list_registry_state(&dir.path()).await?);
+            }
+        }
+
+        Ok(entries)
+    }
+
     async fn load_checkpoint(
         &self,
         namespace_registry: &Option<RegistryDomain>,
@@ -260,26 +327,46 @@ impl RegistryStorage for FileSystemRegistryStorage {
         store(&self.package_path(namespace_registry, &info.name), info).await
     }
 
-    async fn load_publish(&self) -> Result<Option<PublishInfo>> {
-        Ok(load(&self.base_dir.join(PENDING_PUBLISH_FILE))
-            .await?
-            .unwrap_or_default())
+    async fn load_publishes(&self) -> Result<Vec<PublishInfo>> {
+        Ok(
+            match load::<PendingPublishes>(&self.pending_publish_path()).await? {
+                Some(PendingPublishes::Single(info)) => vec![info],
+                Some(PendingPublishes::Multiple(infos)) => infos,
+                None => Vec::new(),
+            },
+        )
     }
 
-    async fn store_publish(&self, info: Option<&PublishInfo>) -> Result<()> {
+    async fn store_publishes(&self, infos: &[PublishInfo]) -> Result<()> {
         let path = self.pending_publish_path();
-        match info {
-            Some(info) => store(&path, info).await,
-            None => delete(&path).await,
+        if infos.is_empty() {
+            delete(&path).await
+        } else {
+            store(&path, infos).await
         }
     }
 }
 
+/// The contents of the pending publish file.
+///
+/// Older clients stored a single pending publish rather than a list.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(super) enum PendingPublishes {
+    Single(PublishInfo),
+    Multiple(Vec<PublishInfo>),
+}
+
 /// Represents a content storage using the local file system.
+///
+/// Content may also be read from an ordered list of read-only fallback
+/// directories, such as a content directory shared between the users of a
+/// machine; content is only ever written to the base directory.
 pub struct FileSystemContentStorage {
     _lock: FileLock,
     base_dir: PathBuf,
     temp_dir: PathBuf,
+    fallback_dirs: Vec<PathBuf>,
 }
 
 impl FileSystemContentStorage {
@@ -296,6 +383,7 @@ impl FileSystemContentStorage {
                 _lock: lock,
                 base_dir,
                 temp_dir,
+                fallback_dirs: Vec::new(),
             })),
             None => Ok(None),
         }
@@ -315,9 +403,20 @@ impl FileSystemContentStorage {
             _lock: lock,
             base_dir,
             temp_dir,
+            fallback_dirs: Vec::new(),
         })
     }
 
+    /// Sets the read-only directories to look up content in, in order, when
+    /// the content is not in the base directory.
+    ///
+    /// The fallback directories are expected to have the same layout as the
+    /// base directory. They are not locked, listed, or cleared.
+    pub fn with_fallback_dirs(mut self, dirs: impl IntoIterator<Item = PathBuf>) -> Self {
+        self.fallback_dirs = dirs.into_iter().collect();
+        self
+    }
+
     fn temp_file(&self) -> Result<NamedTempFile> {
         fs::create_dir_all(&self.temp_dir).with_context(|| {
             format!(
@@ -335,7 +434,62 @@ impl FileSystemContentStorage {
     }
 
     fn content_path(&self, digest: &AnyHash) -> PathBuf {
-        self.base_dir.join(digest.to_string().replace(':', "/"))
+        self.base_dir.join(content_file_name(digest))
+    }
+
+    fn partial_content_path(&self, digest: &AnyHash) -> PathBuf {
+        self.temp_dir
+            .join(format!("{}.partial", digest.to_string().replace(':', "-")))
+    }
+
+    /// Finds the content associated with the given digest in the fallback
+    /// directories.
+    ///
+    /// Fallback directories are less trusted than the base directory, so
+    /// content found in them is only used if it matches the digest.
+    fn fallback_content_path(&self, digest: &AnyHash) -> Option<PathBuf> {
+        let file_name = content_file_name(digest);
+        self.fallback_dirs
+            .iter()
+            .map(|dir| dir.join(&file_name))
+            .filter(|path| path.is_file())
+            .find(|path| match file_digest(path, digest.algorithm()) {
+                Ok(found) if found == *digest => true,
+                Ok(found) => {
+                    tracing::warn!(
+                        "ignoring content `{path}` with digest `{found}` instead of `{digest}`",
+                        path = path.display()
+                    );
+                    false
+                }
+                Err(e) => {
+                    tracing::warn!("ignoring content `{path}`: {e:#}", path = path.display());
+                    false
+                }
+            })
+    }
+}
+
+/// Gets the path of the file for the given content digest relative to a
+/// content directory.
+fn content_file_name(digest: &AnyHash) -> String {
+    digest.to_string().replace(':', "/")
+}
+
+/// Calculates the digest of the given file.
+fn file_digest(path: &Path, algorithm: HashAlgorithm) -> Result<AnyHash> {
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("failed to open `{path}`", path = path.display()))?;
+    let mut hasher = algorithm.hasher();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let len = file
+            .read(&mut buf)
+            .with_context(|| format!("failed to read `{path}`", path = path.display()))?;
+        if len == 0 {
+            return Ok(hasher.finalize());
+        }
+        hasher.update(&buf[..len]);
     }
 }
 
@@ -345,12 +499,60 @@ impl ContentStorage for FileSystemContentStorage {
         remove(&self.base_dir).await
     }
 
+    async fn list_content(&self) -> Result<Vec<(AnyHash, u64)>> {
+        let mut content = Vec::new();
+        for entry in WalkDir::new(&self.base_dir).min_depth(2).max_depth(2) {
+            let entry = entry.with_context(|| {
+                anyhow!(
+                    "failed to walk directory `{path}`",
+                    path = self.base_dir.display()
+                )
+            })?;
+
+            // Content is stored as `<algorithm>/<hex>`; other files, such as
+            // the lock and temporary files, are not content
+            let path = entry.path();
+            let digest = path
+                .parent()
+                .and_then(Path::file_name)
+                .and_then(OsStr::to_str)
+                .zip(path.file_name().and_then(OsStr::to_str))
+                .filter(|(algorithm, _)| *algorithm != TEMP_DIRECTORY)
+                .and_then(|(algorithm, hex)| format!("{algorithm}:{hex}").parse::<AnyHash>().ok());
+            let Some(digest) = digest else {
+                continue;
+            };
+
+            if entry.file_type().is_file() {
+                let size = entry
+                    .metadata()
+                    .with_context(|| {
+                        format!("failed to read metadata of `{path}`", path = path.display())
+                    })?
+                    .len();
+                content.push((digest, size));
+            }
+        }
+
+        Ok(content)
+    }
+
+    async fn remove_content(&self, digest: &AnyHash) -> Result<bool> {
+        let path = self.content_path(digest);
+        if !path.is_file() {
+            return Ok(false);
+        }
+
+        delete(&path).await?;
+        Ok(true)
+    }
+
     fn content_location(&self, digest: &AnyHash) -> Option<PathBuf> {
         let path = self.content_path(digest);
         if path.is_file() {
             Some(path)
         } else {
-            None
+            self.fallback_content_path(digest)
         }
     }
 
@@ -358,10 +560,9 @@ impl ContentStorage for FileSystemContentStorage {
         &self,
         digest: &AnyHash,
     ) -> Result<Option<Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + Sync>>>> {
-        let path = self.content_path(digest);
-        if !path.is_file() {
+        let Some(path) = self.content_location(digest) else {
             return Ok(None);
-        }
+        };
 
         Ok(Some(Box::pin(
             ReaderStream::new(BufReader::new(
@@ -378,6 +579,12 @@ impl ContentStorage for FileSystemContentStorage {
         mut stream: Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + Sync>>,
         expected_digest: Option<&AnyHash>,
     ) -> Result<AnyHash> {
+        if let Some(expected) = expected_digest {
+            if self.content_path(expected).is_file() {
+                return Ok(expected.clone());
+            }
+        }
+
         let (file, path) = self.temp_file()?.into_parts();
         let mut writer = BufWriter::new(tokio::fs::File::from_std(file));
         let mut hasher = Sha256::new();
@@ -407,6 +614,8 @@ impl ContentStorage for FileSystemContentStorage {
 
         drop(writer);
 
+        // Content that is already stored is left untouched; the temporary
+        // file is removed when dropped
         let content_path = self.content_path(&hash);
         if !content_path.is_file() {
             if let Some(parent) = content_path.parent() {
@@ -428,6 +637,112 @@ impl ContentStorage for FileSystemContentStorage {
 
         Ok(hash)
     }
+
+    async fn partial_content_len(&self, digest: &AnyHash) -> Result<u64> {
+        let path = self.partial_content_path(digest);
+        match tokio::fs::metadata(&path).await {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(Error::new(e).context(format!(
+                "failed to read metadata of `{path}`",
+                path = path.display()
+            ))),
+        }
+    }
+
+    async fn store_partial_content(
+        &self,
+        mut stream: Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + Sync>>,
+        digest: &AnyHash,
+        offset: u64,
+    ) -> Result<AnyHash> {
+        let content_path = self.content_path(digest);
+        if content_path.is_file() {
+            return Ok(digest.clone());
+        }
+
+        fs::create_dir_all(&self.temp_dir).with_context(|| {
+            format!(
+                "failed to create directory `{path}`",
+                path = self.temp_dir.display()
+            )
+        })?;
+
+        let path = self.partial_content_path(digest);
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(&path)
+            .await
+            .with_context(|| format!("failed to open `{path}`", path = path.display()))?;
+        let len = file
+            .metadata()
+            .await
+            .with_context(|| format!("failed to read metadata of `{path}`", path = path.display()))?
+            .len();
+        if offset > len {
+            bail!(
+                "cannot continue the {len} bytes of content `{digest}` stored from offset {offset}"
+            );
+        }
+
+        file.set_len(offset)
+            .await
+            .with_context(|| format!("failed to truncate `{path}`", path = path.display()))?;
+        file.seek(SeekFrom::End(0))
+            .await
+            .with_context(|| format!("failed to seek `{path}`", path = path.display()))?;
+
+        // The bytes received before the stream fails are written out so that
+        // the download can be resumed from them
+        let mut writer = BufWriter::new(file);
+        let res = async {
+            while let Some(bytes) = stream.next().await.transpose()? {
+                writer.write_all(&bytes).await.with_context(|| {
+                    format!("failed to write to `{path}`", path = path.display())
+                })?;
+            }
+
+            Ok::<_, Error>(())
+        }
+        .await;
+        writer
+            .shutdown()
+            .await
+            .with_context(|| format!("failed to write `{path}`", path = path.display()))?;
+        drop(writer);
+        res?;
+
+        // The entire content is verified, including the bytes stored by
+        // earlier attempts
+        let hash = file_digest(&path, digest.algorithm())?;
+        if hash != *digest {
+            delete(&path).await?;
+            return Ok(hash);
+        }
+
+        if let Some(parent) = content_path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!(
+                    "failed to create directory `{path}`",
+                    path = parent.display()
+                )
+            })?;
+        }
+
+        tokio::fs::rename(&path, &content_path)
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to move `{path}` to `{content_path}`",
+                    path = path.display(),
+                    content_path = content_path.display()
+                )
+            })?;
+
+        Ok(hash)
+    }
 }
 
 /// Represents a namespace_domain map storage using the local file system.
@@ -465,6 +780,9 @@ impl NamespaceMapStorage for FileSystemNamespaceMapStorage {
         let mut mapping = self.load_namespace_map().await?.unwrap_or_default();
         mapping.insert(namespace, registry_domain.to_string());
         let json = serde_json::to_string(&mapping)?;
+        if let Some(parent) = self.base_dir.parent() {
+            fs::create_dir_all(parent)?;
+        }
         fs::write(&self.base_dir, json)?;
         Ok(())
     }
@@ -482,6 +800,19 @@ async fn remove(path: &Path) -> Result<()> {
         .with_context(|| format!("failed to remove directory `{path}`", path = path.display()))
 }
 
+/// Gets the size of the file at the given path, if it exists.
+fn file_size(path: &Path) -> Result<Option<u64>> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => Ok(Some(metadata.len())),
+        Ok(_) => Ok(None),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::new(e).context(format!(
+            "failed to read metadata of `{path}`",
+            path = path.display()
+        ))),
+    }
+}
+
 async fn load<T: for<'a> Deserialize<'a>>(path: &Path) -> Result<Option<T>> {
     if !path.is_file() {
         return Ok(None);
@@ -530,3 +861,121 @@ async fn delete(path: &Path) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn stream(content: &'static str) -> Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + Sync>> {
+        Box::pin(futures_util::stream::once(async move {
+            Ok(Bytes::from(content))
+        }))
+    }
+
+    #[tokio::test]
+    async fn it_does_not_rewrite_stored_content() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let storage = FileSystemContentStorage::lock(dir.path())?;
+
+        let digest = storage.store_content(stream("content"), None).await?;
+        let path = storage.content_location(&digest).unwrap();
+
+        // Backdate the stored file so a rewrite would be observable
+        let modified = SystemTime::now() - Duration::from_secs(60 * 60);
+        fs::File::options()
+            .write(true)
+            .open(&path)?
+            .set_modified(modified)?;
+
+        assert_eq!(
+            storage.store_content(stream("content"), None).await?,
+            digest
+        );
+        assert_eq!(
+            storage
+                .store_content(stream("ignored"), Some(&digest))
+                .await?,
+            digest
+        );
+
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(metadata.modified()?, modified);
+        assert_eq!(fs::read_to_string(&path)?, "content");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_links_stored_content() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let storage = FileSystemContentStorage::lock(dir.path().join("content"))?;
+        let digest = storage.store_content(stream("content"), None).await?;
+
+        let target = dir.path().join("deps").join("content.wasm");
+        assert!(storage.link_content(&digest, &target).await?);
+        assert_eq!(fs::read_to_string(&target)?, "content");
+
+        // Within the same file system the content is hard-linked
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let source = storage.content_location(&digest).unwrap();
+            assert_eq!(fs::metadata(&target)?.ino(), fs::metadata(source)?.ino());
+        }
+
+        // Linking again replaces the existing file
+        assert!(storage.link_content(&digest, &target).await?);
+        assert_eq!(fs::read_to_string(&target)?, "content");
+
+        let missing: AnyHash = Hash::<Sha256>::of("missing").into();
+        assert!(
+            !storage
+                .link_content(&missing, &dir.path().join("missing.wasm"))
+                .await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_resumes_partially_stored_content() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let storage = FileSystemContentStorage::lock(dir.path())?;
+        let digest: AnyHash = Hash::<Sha256>::of("content").into();
+
+        // A failed stream keeps the bytes received before the failure
+        let interrupted = stream("cont").chain(futures_util::stream::once(async {
+            Err(anyhow!("connection reset"))
+        }));
+        assert!(storage
+            .store_partial_content(Box::pin(interrupted), &digest, 0)
+            .await
+            .is_err());
+        assert_eq!(storage.partial_content_len(&digest).await?, 4);
+        assert!(storage.content_location(&digest).is_none());
+
+        // Bytes past the offset are replaced and the entire content verified
+        assert_eq!(
+            storage
+                .store_partial_content(stream("tent"), &digest, 3)
+                .await?,
+            digest
+        );
+        assert_eq!(storage.partial_content_len(&digest).await?, 0);
+        assert_eq!(
+            fs::read_to_string(storage.content_location(&digest).unwrap())?,
+            "content"
+        );
+
+        // Content not matching the digest is discarded
+        let other: AnyHash = Hash::<Sha256>::of("other").into();
+        storage
+            .store_partial_content(stream("tampered"), &other, 0)
+            .await?;
+        assert_eq!(storage.partial_content_len(&other).await?, 0);
+        assert!(storage.content_location(&other).is_none());
+
+        Ok(())
+    }
+}
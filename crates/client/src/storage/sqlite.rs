@@ -0,0 +1,516 @@
+//! A module for SQLite client storage.
+
+use super::{
+    fs::PendingPublishes, OperatorInfo, PackageInfo, PublishInfo, RegistryDomain,
+    RegistryStateEntry, RegistryStateItem, RegistryStorage,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use indexmap::IndexMap;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use walkdir::WalkDir;
+use warg_protocol::{
+    registry::{PackageName, TimestampedCheckpoint},
+    SerdeEnvelope,
+};
+
+const DATABASE_FILE: &str = "registries.db";
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS checkpoints (
+    registry TEXT PRIMARY KEY,
+    checkpoint TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS operators (
+    registry TEXT PRIMARY KEY,
+    info TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS packages (
+    registry TEXT NOT NULL,
+    name TEXT NOT NULL,
+    info TEXT NOT NULL,
+    PRIMARY KEY (registry, name)
+);
+CREATE TABLE IF NOT EXISTS publishes (
+    registry TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    info TEXT NOT NULL,
+    PRIMARY KEY (registry, position)
+);
+CREATE TABLE IF NOT EXISTS imports (
+    registry TEXT PRIMARY KEY
+);
+"#;
+
+/// Represents a registry storage using a SQLite database.
+///
+/// A single database, in the parent of the registry's base directory, stores
+/// the information of every registry; unlike
+/// [`FileSystemRegistryStorage`](super::FileSystemRegistryStorage), the
+/// storage is not locked and may be shared by concurrent processes.
+pub struct SqliteRegistryStorage {
+    connection: Arc<Mutex<Connection>>,
+    registry: String,
+}
+
+impl SqliteRegistryStorage {
+    /// Opens the registry storage for the registry with the given base
+    /// directory.
+    ///
+    /// The database will be created if it does not exist.
+    ///
+    /// Information stored in the file system layout is imported into the
+    /// database the first time each registry is opened; information already
+    /// in the database is kept.
+    pub fn open(base_dir: impl Into<PathBuf>) -> Result<Self> {
+        let base_dir = base_dir.into();
+        let registries_dir = base_dir.parent().context("base_dir cannot be empty")?;
+        let registry = base_dir
+            .file_name()
+            .and_then(OsStr::to_str)
+            .context("base_dir must have a name")?
+            .to_string();
+
+        fs::create_dir_all(registries_dir).with_context(|| {
+            format!(
+                "failed to create directory `{path}`",
+                path = registries_dir.display()
+            )
+        })?;
+
+        let path = registries_dir.join(DATABASE_FILE);
+        let mut connection = Connection::open(&path)
+            .with_context(|| format!("failed to open database `{path}`", path = path.display()))?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.execute_batch(SCHEMA)?;
+
+        for entry in fs::read_dir(registries_dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            if let Some(name) = entry.file_name().to_str() {
+                import(&mut connection, name, &entry.path()).with_context(|| {
+                    format!(
+                        "failed to import registry storage `{path}`",
+                        path = entry.path().display()
+                    )
+                })?;
+            }
+        }
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            registry,
+        })
+    }
+
+    fn registry(&self, namespace_registry: &Option<RegistryDomain>) -> String {
+        match namespace_registry {
+            Some(nm) => nm.to_string(),
+            None => self.registry.clone(),
+        }
+    }
+
+    async fn call<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection
+                .lock()
+                .map_err(|_| anyhow!("registry storage connection was poisoned"))?;
+            f(&mut connection)
+        })
+        .await
+        .context("registry storage task failed")?
+    }
+}
+
+#[async_trait]
+impl RegistryStorage for SqliteRegistryStorage {
+    async fn reset(&self, all_registries: bool) -> Result<()> {
+        let registry = self.registry.clone();
+        self.call(move |connection| {
+            let tx = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            for table in ["checkpoints", "operators", "packages", "publishes"] {
+                if all_registries {
+                    tx.execute(&format!("DELETE FROM {table}"), [])?;
+                } else {
+                    tx.execute(
+                        &format!("DELETE FROM {table} WHERE registry = ?1"),
+                        [&registry],
+                    )?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list_state(&self, all_registries: bool) -> Result<Vec<RegistryStateEntry>> {
+        let registry = self.registry.clone();
+        self.call(move |connection| {
+            let mut statement = connection.prepare(
+                "SELECT registry, 'checkpoint', NULL, length(checkpoint) FROM checkpoints
+                WHERE ?1 OR registry = ?2
+                UNION ALL
+                SELECT registry, 'operator', NULL, length(info) FROM operators
+                WHERE ?1 OR registry = ?2
+                UNION ALL
+                SELECT registry, 'package', name, length(info) FROM packages
+                WHERE ?1 OR registry = ?2
+                UNION ALL
+                SELECT registry, 'publishes', NULL, sum(length(info)) FROM publishes
+                WHERE ?1 OR registry = ?2 GROUP BY registry
+                ORDER BY 1",
+            )?;
+
+            let rows = statement.query_map(params![all_registries, registry], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })?;
+
+            let mut entries = Vec::new();
+            for row in rows {
+                let (registry, kind, name, size) = row?;
+                let item = match (kind.as_str(), name) {
+                    ("checkpoint", _) => RegistryStateItem::Checkpoint,
+                    ("operator", _) => RegistryStateItem::Operator,
+                    ("package", Some(name)) => RegistryStateItem::Package {
+                        name: PackageName::new(name)?,
+                    },
+                    _ => RegistryStateItem::PendingPublishes,
+                };
+                entries.push(RegistryStateEntry {
+                    registry,
+                    item,
+                    size: size as u64,
+                });
+            }
+
+            Ok(entries)
+        })
+        .await
+    }
+
+    async fn load_checkpoint(
+        &self,
+        namespace_registry: &Option<RegistryDomain>,
+    ) -> Result<Option<SerdeEnvelope<TimestampedCheckpoint>>> {
+        let registry = self.registry(namespace_registry);
+        self.call(move |connection| {
+            connection
+                .query_row(
+                    "SELECT checkpoint FROM checkpoints WHERE registry = ?1",
+                    [&registry],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional()?
+                .map(|checkpoint| decode(&checkpoint))
+                .transpose()
+        })
+        .await
+    }
+
+    async fn store_checkpoint(
+        &self,
+        namespace_registry: &Option<RegistryDomain>,
+        ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
+    ) -> Result<()> {
+        let registry = self.registry(namespace_registry);
+        let checkpoint = encode(ts_checkpoint)?;
+        self.call(move |connection| {
+            connection.execute(
+                "INSERT INTO checkpoints (registry, checkpoint) VALUES (?1, ?2)
+                ON CONFLICT (registry) DO UPDATE SET checkpoint = excluded.checkpoint",
+                params![registry, checkpoint],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn load_operator(
+        &self,
+        namespace_registry: &Option<RegistryDomain>,
+    ) -> Result<Option<OperatorInfo>> {
+        let registry = self.registry(namespace_registry);
+        self.call(move |connection| {
+            connection
+                .query_row(
+                    "SELECT info FROM operators WHERE registry = ?1",
+                    [&registry],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional()?
+                .map(|info| decode(&info))
+                .transpose()
+        })
+        .await
+    }
+
+    async fn store_operator(
+        &self,
+        namespace_registry: &Option<RegistryDomain>,
+        operator: OperatorInfo,
+    ) -> Result<()> {
+        let registry = self.registry(namespace_registry);
+        let info = encode(&operator)?;
+        self.call(move |connection| {
+            connection.execute(
+                "INSERT INTO operators (registry, info) VALUES (?1, ?2)
+                ON CONFLICT (registry) DO UPDATE SET info = excluded.info",
+                params![registry, info],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn load_packages(&self) -> Result<Vec<PackageInfo>> {
+        let registry = self.registry.clone();
+        self.call(move |connection| {
+            let mut statement = connection
+                .prepare("SELECT info FROM packages WHERE registry = ?1 ORDER BY name")?;
+            let rows = statement.query_map([&registry], |row| row.get::<_, String>(0))?;
+            rows.map(|info| decode(&info?)).collect()
+        })
+        .await
+    }
+
+    async fn load_all_packages(&self) -> Result<IndexMap<String, Vec<PackageInfo>>> {
+        self.call(move |connection| {
+            let mut statement = connection
+                .prepare("SELECT registry, info FROM packages ORDER BY registry, name")?;
+            let rows = statement.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
+
+            let mut all_packages = IndexMap::<_, Vec<_>>::new();
+            for row in rows {
+                let (registry, info) = row?;
+                all_packages
+                    .entry(registry)
+                    .or_default()
+                    .push(decode(&info)?);
+            }
+
+            Ok(all_packages)
+        })
+        .await
+    }
+
+    async fn load_package(
+        &self,
+        namespace_registry: &Option<RegistryDomain>,
+        package: &PackageName,
+    ) -> Result<Option<PackageInfo>> {
+        let registry = self.registry(namespace_registry);
+        let name = package.to_string();
+        self.call(move |connection| {
+            connection
+                .query_row(
+                    "SELECT info FROM packages WHERE registry = ?1 AND name = ?2",
+                    [&registry, &name],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional()?
+                .map(|info| decode(&info))
+                .transpose()
+        })
+        .await
+    }
+
+    async fn store_package(
+        &self,
+        namespace_registry: &Option<RegistryDomain>,
+        info: &PackageInfo,
+    ) -> Result<()> {
+        let registry = self.registry(namespace_registry);
+        let name = info.name.to_string();
+        let info = encode(info)?;
+        self.call(move |connection| {
+            connection.execute(
+                "INSERT INTO packages (registry, name, info) VALUES (?1, ?2, ?3)
+                ON CONFLICT (registry, name) DO UPDATE SET info = excluded.info",
+                params![registry, name, info],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn load_publishes(&self) -> Result<Vec<PublishInfo>> {
+        let registry = self.registry.clone();
+        self.call(move |connection| load_publishes(connection, &registry))
+            .await
+    }
+
+    async fn store_publishes(&self, infos: &[PublishInfo]) -> Result<()> {
+        let registry = self.registry.clone();
+        let infos = infos.to_vec();
+        self.call(move |connection| {
+            let tx = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            store_publishes(&tx, &registry, &infos)?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn store_publish(&self, info: Option<&PublishInfo>) -> Result<()> {
+        let registry = self.registry.clone();
+        let info = info.cloned();
+        self.call(move |connection| {
+            // The pending publishes are updated in a single transaction so
+            // that concurrent processes do not lose each other's updates
+            let tx = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let mut publishes = load_publishes(&tx, &registry)?;
+            match info {
+                Some(info) => match publishes.iter_mut().find(|p| p.name == info.name) {
+                    Some(existing) => *existing = info,
+                    None => publishes.push(info),
+                },
+                None => {
+                    publishes.pop();
+                }
+            }
+
+            store_publishes(&tx, &registry, &publishes)?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+fn load_publishes(connection: &Connection, registry: &str) -> Result<Vec<PublishInfo>> {
+    let mut statement =
+        connection.prepare("SELECT info FROM publishes WHERE registry = ?1 ORDER BY position")?;
+    let rows = statement.query_map([registry], |row| row.get::<_, String>(0))?;
+    rows.map(|info| decode(&info?)).collect()
+}
+
+fn store_publishes(connection: &Connection, registry: &str, infos: &[PublishInfo]) -> Result<()> {
+    connection.execute("DELETE FROM publishes WHERE registry = ?1", [registry])?;
+    for (position, info) in infos.iter().enumerate() {
+        connection.execute(
+            "INSERT INTO publishes (registry, position, info) VALUES (?1, ?2, ?3)",
+            params![registry, position, encode(info)?],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Imports a registry's information stored in the file system layout.
+///
+/// Each registry is imported once; information already in the database is
+/// not replaced.
+fn import(connection: &mut Connection, registry: &str, dir: &Path) -> Result<()> {
+    let imported = connection
+        .query_row(
+            "SELECT 1 FROM imports WHERE registry = ?1",
+            [registry],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if imported {
+        return Ok(());
+    }
+
+    let tx = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    if tx.execute(
+        "INSERT OR IGNORE INTO imports (registry) VALUES (?1)",
+        [registry],
+    )? == 0
+    {
+        // Another process imported the registry first
+        return Ok(());
+    }
+
+    if let Some(checkpoint) = read::<SerdeEnvelope<TimestampedCheckpoint>>(&dir.join("checkpoint"))?
+    {
+        tx.execute(
+            "INSERT OR IGNORE INTO checkpoints (registry, checkpoint) VALUES (?1, ?2)",
+            params![registry, encode(&checkpoint)?],
+        )?;
+    }
+
+    if let Some(operator) = read::<OperatorInfo>(&dir.join("operator.log"))? {
+        tx.execute(
+            "INSERT OR IGNORE INTO operators (registry, info) VALUES (?1, ?2)",
+            params![registry, encode(&operator)?],
+        )?;
+    }
+
+    for entry in WalkDir::new(dir.join("package-logs")).into_iter().flatten() {
+        let path = entry.path();
+        if !path.is_file()
+            || path
+                .file_name()
+                .and_then(OsStr::to_str)
+                .map_or(false, |name| name.starts_with('.'))
+        {
+            continue;
+        }
+
+        if let Some(info) = read::<PackageInfo>(path)? {
+            tx.execute(
+                "INSERT OR IGNORE INTO packages (registry, name, info) VALUES (?1, ?2, ?3)",
+                params![registry, info.name.to_string(), encode(&info)?],
+            )?;
+        }
+    }
+
+    let publishes = match read::<PendingPublishes>(&dir.join("pending-publish.json"))? {
+        Some(PendingPublishes::Single(info)) => vec![info],
+        Some(PendingPublishes::Multiple(infos)) => infos,
+        None => Vec::new(),
+    };
+    if !publishes.is_empty() && load_publishes(&tx, registry)?.is_empty() {
+        store_publishes(&tx, registry, &publishes)?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+fn read<T: DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read `{path}`", path = path.display()))?;
+    serde_json::from_str(&contents)
+        .with_context(|| format!("failed to deserialize `{path}`", path = path.display()))
+        .map(Some)
+}
+
+fn encode(value: &impl Serialize) -> Result<String> {
+    serde_json::to_string(value).context("failed to serialize registry storage information")
+}
+
+fn decode<T: DeserializeOwned>(value: &str) -> Result<T> {
+    serde_json::from_str(value).context("failed to deserialize registry storage information")
+}
@@ -0,0 +1,116 @@
+//! A module for pinning the keys trusted to sign the records of packages.
+
+use crate::config::match_namespace;
+use anyhow::{Context, Result};
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    path::Path,
+};
+use warg_crypto::signing::KeyID;
+use warg_protocol::registry::PackageName;
+
+/// A policy of the keys trusted to sign the records of packages.
+///
+/// Records of a package with pinned keys that are signed by any other key
+/// are reported when fetching the package log.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustPolicy {
+    /// Maps package names or namespace patterns to the IDs of the keys
+    /// trusted to sign their records.
+    ///
+    /// A package name, such as `example:hello`, takes precedence over a
+    /// namespace pattern. See [`Config::namespace_registries`] for the syntax
+    /// of namespace patterns.
+    ///
+    /// [`Config::namespace_registries`]: crate::Config::namespace_registries
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub packages: IndexMap<String, IndexSet<KeyID>>,
+}
+
+impl TrustPolicy {
+    /// Reads a trust policy from the given file path.
+    ///
+    /// Returns an empty policy if the file does not exist.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        let policy = fs::read_to_string(path).with_context(|| {
+            format!(
+                "failed to read trust policy file `{path}`",
+                path = path.display()
+            )
+        })?;
+
+        serde_json::from_str(&policy)
+            .with_context(|| format!("failed to deserialize file `{path}`", path = path.display()))
+    }
+
+    /// Writes the trust policy to the given file path.
+    pub fn write_to_file(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!(
+                    "failed to create parent directory `{path}`",
+                    path = parent.display()
+                )
+            })?;
+        }
+
+        serde_json::to_writer_pretty(
+            File::create(path).with_context(|| {
+                format!("failed to create file `{path}`", path = path.display())
+            })?,
+            self,
+        )
+        .with_context(|| format!("failed to serialize file `{path}`", path = path.display()))
+    }
+
+    /// Trusts the given key to sign the records of packages matching the
+    /// given package name or namespace pattern.
+    ///
+    /// Returns `false` if the key was already trusted for the pattern.
+    pub fn add(&mut self, pattern: impl Into<String>, key_id: KeyID) -> bool {
+        self.packages
+            .entry(pattern.into())
+            .or_default()
+            .insert(key_id)
+    }
+
+    /// Gets the keys trusted to sign the records of the given package.
+    ///
+    /// Returns `None` if no keys are pinned for the package, in which case
+    /// any key authorized by the package log is trusted.
+    pub fn trusted_keys(&self, name: &PackageName) -> Option<&IndexSet<KeyID>> {
+        self.packages
+            .get(name.as_ref())
+            .or_else(|| match_namespace(&self.packages, name.namespace()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_prefers_package_names_over_namespaces() {
+        let mut policy = TrustPolicy::default();
+        assert!(policy.add("example", KeyID::from("sha256:a".to_string())));
+        assert!(policy.add("example:hello", KeyID::from("sha256:b".to_string())));
+        assert!(!policy.add("example", KeyID::from("sha256:a".to_string())));
+
+        let keys = |name: &str| {
+            policy
+                .trusted_keys(&PackageName::new(name).unwrap())
+                .map(|keys| keys.iter().map(ToString::to_string).collect::<Vec<_>>())
+        };
+        assert_eq!(keys("example:hello").unwrap(), ["sha256:b"]);
+        assert_eq!(keys("example:world").unwrap(), ["sha256:a"]);
+        assert_eq!(keys("other:hello"), None);
+    }
+}
@@ -2,9 +2,11 @@ use anyhow::{bail, Result};
 use ptree::TreeBuilder;
 use semver::{Comparator, Op, Prerelease, Version, VersionReq};
 use warg_crypto::hash::AnyHash;
-use warg_protocol::package::Release;
+use warg_protocol::package::{LogState, Release};
 use wasmparser::names::KebabStr;
 
+pub use crate::core::resolve_release;
+
 /// Kind of import encountered while parsing
 #[derive(Debug, Eq, PartialEq, Hash)]
 pub enum ImportKind {
@@ -289,6 +291,15 @@ pub fn locked_package(pkg_name: &str, release: &Release, content: &AnyHash) -> S
     )
 }
 
+/// Latest release of a package log matching a version requirement
+pub fn locked_release<'a>(state: &'a LogState, req: &VersionReq) -> Option<&'a Release> {
+    if *req != VersionReq::STAR {
+        state.releases().filter(|r| req.matches(&r.version)).last()
+    } else {
+        state.releases().last()
+    }
+}
+
 /// Package name with version range
 pub fn versioned_package(pkg_name: &str, version: VersionReq) -> String {
     let ver = version.clone().to_string();
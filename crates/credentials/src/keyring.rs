@@ -5,7 +5,7 @@ use indexmap::IndexSet;
 use keyring::Entry;
 use secrecy::{self, Secret};
 use warg_client::RegistryUrl;
-use warg_crypto::signing::PrivateKey;
+use warg_crypto::signing::{PrivateKey, PublicKey};
 
 /// Gets the auth token entry for the given registry and key name.
 pub fn get_auth_token_entry(registry_url: &RegistryUrl) -> Result<Entry> {
@@ -127,6 +127,30 @@ pub fn get_signing_key(
     }
 }
 
+/// Lists the public keys of the signing keys in the keyring for each of the
+/// given registries.
+///
+/// Registries without a signing key in the keyring are omitted.
+pub fn list_signing_keys(keys: &IndexSet<String>) -> Result<Vec<(String, PublicKey)>> {
+    let mut listed = Vec::with_capacity(keys.len());
+    for registry in keys {
+        let entry =
+            Entry::new("warg-signing-key", registry).context("failed to get keyring entry")?;
+        match entry.get_password() {
+            Ok(secret) => {
+                let key = PrivateKey::decode(secret).with_context(|| {
+                    format!("failed to parse signing key for registry `{registry}`")
+                })?;
+                listed.push((registry.clone(), key.public_key()));
+            }
+            Err(keyring::Error::NoEntry) => continue,
+            Err(e) => bail!("failed to get signing key for registry `{registry}`: {e}"),
+        }
+    }
+
+    Ok(listed)
+}
+
 /// Sets the signing key for the given registry host and key name.
 pub fn set_signing_key(
     registry_url: Option<&str>,
@@ -32,12 +32,16 @@ where
 pub trait Signable: Encode {
     const PREFIX: &'static [u8];
 
+    /// Gets the message that is signed to sign this value.
+    fn signing_payload(&self) -> Vec<u8> {
+        [Self::PREFIX, b":", self.encode().as_slice()].concat()
+    }
+
     fn sign(
         &self,
         private_key: &signing::PrivateKey,
     ) -> Result<signing::Signature, SignatureError> {
-        let prefixed_content = [Self::PREFIX, b":", self.encode().as_slice()].concat();
-        private_key.sign(&prefixed_content)
+        private_key.sign(&self.signing_payload())
     }
 
     fn verify(
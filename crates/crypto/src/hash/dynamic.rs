@@ -1,4 +1,5 @@
-use super::{Digest, HashAlgorithm, Sha256};
+use super::{Digest, HashAlgorithm, Sha256, Sha512};
+use crate::{ByteVisitor, VisitBytes};
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
 use std::{fmt, ops::Deref, str::FromStr};
@@ -6,44 +7,45 @@ use thiserror::Error;
 
 pub enum Hasher {
     Sha256(Sha256),
+    Sha512(Sha512),
 }
 
 impl Hasher {
     pub fn update(&mut self, bytes: &[u8]) {
         match self {
             Self::Sha256(d) => d.update(bytes),
+            Self::Sha512(d) => d.update(bytes),
         }
     }
 
     pub fn finalize(self) -> AnyHash {
         let (algo, bytes) = match self {
             Self::Sha256(d) => (HashAlgorithm::Sha256, d.finalize().deref().into()),
+            Self::Sha512(d) => (HashAlgorithm::Sha512, d.finalize().deref().into()),
         };
 
         AnyHash { algo, bytes }
     }
 }
 
+impl ByteVisitor for Hasher {
+    fn visit_bytes(&mut self, bytes: impl AsRef<[u8]>) {
+        self.update(bytes.as_ref())
+    }
+}
+
 impl HashAlgorithm {
     pub fn hasher(&self) -> Hasher {
         match self {
             HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
         }
     }
 
     pub fn digest(&self, content_bytes: &[u8]) -> AnyHash {
-        let hash_bytes: Vec<u8> = match self {
-            HashAlgorithm::Sha256 => {
-                let mut d = Sha256::new();
-                d.update(content_bytes);
-                d.finalize().deref().into()
-            }
-        };
-
-        AnyHash {
-            algo: *self,
-            bytes: hash_bytes,
-        }
+        let mut hasher = self.hasher();
+        hasher.update(content_bytes);
+        hasher.finalize()
     }
 }
 
@@ -58,6 +60,16 @@ impl AnyHash {
         AnyHash { algo, bytes }
     }
 
+    /// Hashes the given content with the given algorithm.
+    ///
+    /// This is equivalent to [`Hash::of`](super::Hash::of) for the digest
+    /// implementing the algorithm.
+    pub fn of(algo: HashAlgorithm, content: impl VisitBytes) -> AnyHash {
+        let mut hasher = algo.hasher();
+        content.visit(&mut hasher);
+        hasher.finalize()
+    }
+
     pub fn algorithm(&self) -> HashAlgorithm {
         self.algo
     }
@@ -143,6 +155,32 @@ mod tests {
         assert_eq!(output, expected)
     }
 
+    #[test]
+    fn test_sha512_labeled_digest() {
+        let input = b"The quick brown fox jumped over the lazy dog";
+        let output = HashAlgorithm::Sha512.digest(input);
+        let output = format!("{}", output);
+
+        assert!(output.starts_with("sha512:"));
+        assert_eq!(output.len(), "sha512:".len() + 128);
+        assert_eq!(output.parse::<AnyHash>().unwrap().to_string(), output);
+    }
+
+    #[test]
+    fn test_any_hash_matches_static_hash() {
+        use crate::hash::Hash;
+
+        let content = ("prefix".as_bytes(), "content");
+        assert_eq!(
+            AnyHash::of(HashAlgorithm::Sha256, content),
+            Hash::<Sha256>::of(content).into()
+        );
+        assert_eq!(
+            AnyHash::of(HashAlgorithm::Sha512, content),
+            Hash::<Sha512>::of(content).into()
+        );
+    }
+
     #[test]
     fn test_labeled_digest_parse_rejects_uppercase() {
         let digest_str = "sha256:7d38b5cd25a2baf85ad3bb5b9311383e671a8a142eb302b324d4a5fba8748c69";
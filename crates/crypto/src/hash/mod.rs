@@ -10,7 +10,7 @@ mod r#static;
 pub use digest::{Digest, Output};
 pub use dynamic::{AnyHash, AnyHashError};
 pub use r#static::Hash;
-pub use sha2::Sha256;
+pub use sha2::{Sha256, Sha512};
 
 use crate::VisitBytes;
 
@@ -21,12 +21,14 @@ use self::r#static::IncorrectLengthError;
 #[non_exhaustive]
 pub enum HashAlgorithm {
     Sha256,
+    Sha512,
 }
 
 impl fmt::Display for HashAlgorithm {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             HashAlgorithm::Sha256 => write!(f, "sha256"),
+            HashAlgorithm::Sha512 => write!(f, "sha512"),
         }
     }
 }
@@ -37,14 +39,20 @@ impl FromStr for HashAlgorithm {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "sha256" => Ok(HashAlgorithm::Sha256),
+            "sha512" => Ok(HashAlgorithm::Sha512),
             _ => Err(Error::msg(format!("Illegal hash algorithm '{}'", s))),
         }
     }
 }
 
-static EMPTY_TREE_HASH: Lazy<Vec<Hash<Sha256>>> = Lazy::new(|| {
-    let mut v: Vec<Hash<Sha256>> = Vec::with_capacity(257);
-    fn empty_tree_hash<D: SupportedDigest>(v: &mut Vec<Hash<D>>, height: u32) -> Hash<D> {
+static EMPTY_TREE_HASH: Lazy<Vec<Hash<Sha256>>> = Lazy::new(empty_tree_hashes);
+static EMPTY_TREE_HASH_SHA512: Lazy<Vec<Hash<Sha512>>> = Lazy::new(empty_tree_hashes);
+
+// Computes the hashes of empty trees of every height up to the bit length of the digest
+fn empty_tree_hashes<D: SupportedDigest>() -> Vec<Hash<D>> {
+    let bits = <D as Digest>::output_size() * 8;
+    let mut v: Vec<Hash<D>> = Vec::with_capacity(bits + 1);
+    fn empty_tree_hash<D: SupportedDigest>(v: &mut Vec<Hash<D>>, height: usize) -> Hash<D> {
         let hash: Hash<D> = if height == 0 {
             hash_empty()
         } else {
@@ -54,9 +62,9 @@ static EMPTY_TREE_HASH: Lazy<Vec<Hash<Sha256>>> = Lazy::new(|| {
         v.push(hash.clone());
         hash
     }
-    empty_tree_hash(&mut v, 256);
+    empty_tree_hash(&mut v, bits);
     v
-});
+}
 
 // If updating this function, also update `hash_empty` in transparency map
 pub(crate) fn hash_empty<D: SupportedDigest>() -> Hash<D> {
@@ -92,11 +100,19 @@ impl SupportedDigest for Sha256 {
     }
 }
 
+impl SupportedDigest for Sha512 {
+    const ALGORITHM: HashAlgorithm = HashAlgorithm::Sha512;
+    fn empty_tree_hash(height: usize) -> &'static Hash<Sha512> {
+        &EMPTY_TREE_HASH_SHA512[height]
+    }
+}
+
 mod private {
-    use sha2::Sha256;
+    use sha2::{Sha256, Sha512};
 
     pub trait Sealed {}
     impl Sealed for Sha256 {}
+    impl Sealed for Sha512 {}
 }
 
 impl<D: SupportedDigest> From<Hash<D>> for AnyHash {
@@ -121,7 +121,7 @@ impl<D: SupportedDigest> TryFrom<Vec<u8>> for Hash<D> {
 
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
         let hash = Hash {
-            digest: GenericArray::from_exact_iter(value.into_iter()).ok_or(IncorrectLengthError)?,
+            digest: GenericArray::from_exact_iter(value).ok_or(IncorrectLengthError)?,
         };
         Ok(hash)
     }
@@ -154,8 +154,7 @@ impl<'de, T: SupportedDigest> Deserialize<'de> for Hash<T> {
                 formatter.write_fmt(format_args!("{} bytes", self.0.as_ref().len()))
             }
 
-            #[cfg(feature = "alloc")]
-            fn visit_byte_buf<E: Error>(self, v: alloc::vec::Vec<u8>) -> Result<Self::Value, E> {
+            fn visit_byte_buf<E: Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
                 self.visit_bytes(&v)
             }
 
@@ -27,6 +27,15 @@ impl SignatureAlgorithm {
             SignatureAlgorithm::EcdsaP256 => HashAlgorithm::Sha256,
         }
     }
+
+    /// Gets the maximum length of a signature's bytes made with this
+    /// algorithm.
+    pub fn max_signature_len(&self) -> usize {
+        match self {
+            // A DER-encoded signature of two 32-byte integers
+            SignatureAlgorithm::EcdsaP256 => 72,
+        }
+    }
 }
 
 impl fmt::Display for SignatureAlgorithm {
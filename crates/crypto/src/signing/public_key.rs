@@ -60,7 +60,7 @@ impl FromStr for PublicKey {
     type Err = PublicKeyParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<&str> = s.split(|c| c == ':').collect();
+        let parts: Vec<&str> = s.split(':').collect();
         if parts.len() != 2 {
             return Err(PublicKeyParseError::IncorrectStructure(parts.len()));
         }
@@ -36,7 +36,7 @@ pub trait Validator:
     fn validate(self, record: &ProtoEnvelope<Self::Record>) -> Result<Self, Self::Error>;
 }
 
-/// Helpers for converting to and from protobuf
+// Helpers for converting to and from protobuf
 
 fn prost_to_pbjson_timestamp(timestamp: prost_types::Timestamp) -> pbjson_types::Timestamp {
     pbjson_types::Timestamp {
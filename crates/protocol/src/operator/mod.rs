@@ -1,5 +1,6 @@
 use anyhow::{Context, Error};
 use prost::Message;
+use std::time::Duration;
 use thiserror::Error;
 use warg_crypto::{hash::AnyHash, Decode, Encode, Signable};
 use warg_protobuf::protocol as protobuf;
@@ -9,7 +10,7 @@ use crate::{pbjson_to_prost_timestamp, prost_to_pbjson_timestamp, registry::Reco
 mod model;
 mod state;
 
-pub use model::{OperatorEntry, OperatorRecord};
+pub use model::{OperatorEntry, OperatorRecord, PackagePattern, Permission};
 pub use state::{LogState, NamespaceState, ValidationError};
 
 /// The currently supported operator protocol version.
@@ -75,6 +76,10 @@ impl TryFrom<protobuf::OperatorEntry> for model::OperatorEntry {
                     .map(TryInto::try_into)
                     .collect::<Result<_, _>>()?,
             },
+            Contents::GrantPackagePublish(grant) => model::OperatorEntry::GrantPackagePublish {
+                key: grant.key.parse()?,
+                pattern: grant.pattern.parse().map_err(Error::msg)?,
+            },
             Contents::RevokeFlat(revoke_flat) => model::OperatorEntry::RevokeFlat {
                 key_id: revoke_flat.key_id.into(),
                 permissions: revoke_flat
@@ -90,6 +95,34 @@ impl TryFrom<protobuf::OperatorEntry> for model::OperatorEntry {
                 namespace: import_namespace.namespace,
                 registry: import_namespace.registry,
             },
+            Contents::SuppressPackage(suppress_package) => model::OperatorEntry::SuppressPackage {
+                log_id: suppress_package.log_id.parse::<AnyHash>()?.into(),
+                reason: suppress_package.reason,
+            },
+            Contents::UnsuppressPackage(unsuppress_package) => {
+                model::OperatorEntry::UnsuppressPackage {
+                    log_id: unsuppress_package.log_id.parse::<AnyHash>()?.into(),
+                }
+            }
+            Contents::SetMaxCheckpointInterval(set_max_checkpoint_interval) => {
+                model::OperatorEntry::SetMaxCheckpointInterval {
+                    interval: Duration::from_secs(set_max_checkpoint_interval.seconds),
+                }
+            }
+            Contents::RotateKey(rotate_key) => model::OperatorEntry::RotateKey {
+                key: rotate_key.key.parse()?,
+                overlap: Duration::from_secs(rotate_key.overlap_seconds),
+            },
+            Contents::ReserveNamespace(reserve_namespace) => {
+                model::OperatorEntry::ReserveNamespace {
+                    namespace: reserve_namespace.namespace,
+                    key_ids: reserve_namespace
+                        .key_ids
+                        .into_iter()
+                        .map(Into::into)
+                        .collect(),
+                }
+            }
         };
         Ok(output)
     }
@@ -112,6 +145,7 @@ impl TryFrom<i32> for model::Permission {
             protobuf::OperatorPermission::Commit => Ok(model::Permission::Commit),
             protobuf::OperatorPermission::DefineNamespace => Ok(model::Permission::DefineNamespace),
             protobuf::OperatorPermission::ImportNamespace => Ok(model::Permission::ImportNamespace),
+            protobuf::OperatorPermission::Publish => Ok(model::Permission::Publish),
         }
     }
 }
@@ -163,6 +197,12 @@ impl<'a> From<&'a model::OperatorEntry> for protobuf::OperatorEntry {
                     permissions: permissions.iter().map(Into::into).collect(),
                 })
             }
+            model::OperatorEntry::GrantPackagePublish { key, pattern } => {
+                Contents::GrantPackagePublish(protobuf::OperatorGrantPackagePublish {
+                    key: key.to_string(),
+                    pattern: pattern.to_string(),
+                })
+            }
             model::OperatorEntry::RevokeFlat {
                 key_id,
                 permissions,
@@ -182,6 +222,34 @@ impl<'a> From<&'a model::OperatorEntry> for protobuf::OperatorEntry {
                 namespace: namespace.clone(),
                 registry: registry.clone(),
             }),
+            model::OperatorEntry::SuppressPackage { log_id, reason } => {
+                Contents::SuppressPackage(protobuf::OperatorSuppressPackage {
+                    log_id: log_id.to_string(),
+                    reason: reason.clone(),
+                })
+            }
+            model::OperatorEntry::UnsuppressPackage { log_id } => {
+                Contents::UnsuppressPackage(protobuf::OperatorUnsuppressPackage {
+                    log_id: log_id.to_string(),
+                })
+            }
+            model::OperatorEntry::SetMaxCheckpointInterval { interval } => {
+                Contents::SetMaxCheckpointInterval(protobuf::OperatorSetMaxCheckpointInterval {
+                    seconds: interval.as_secs(),
+                })
+            }
+            model::OperatorEntry::RotateKey { key, overlap } => {
+                Contents::RotateKey(protobuf::OperatorRotateKey {
+                    key: key.to_string(),
+                    overlap_seconds: overlap.as_secs(),
+                })
+            }
+            model::OperatorEntry::ReserveNamespace { namespace, key_ids } => {
+                Contents::ReserveNamespace(protobuf::OperatorReserveNamespace {
+                    namespace: namespace.clone(),
+                    key_ids: key_ids.iter().map(ToString::to_string).collect(),
+                })
+            }
         };
         let contents = Some(contents);
         protobuf::OperatorEntry { contents }
@@ -194,6 +262,7 @@ impl<'a> From<&'a model::Permission> for i32 {
             model::Permission::Commit => protobuf::OperatorPermission::Commit,
             model::Permission::DefineNamespace => protobuf::OperatorPermission::DefineNamespace,
             model::Permission::ImportNamespace => protobuf::OperatorPermission::ImportNamespace,
+            model::Permission::Publish => protobuf::OperatorPermission::Publish,
         };
         proto_perm.into()
     }
@@ -227,10 +296,18 @@ mod tests {
                     key: bob_pub.clone(),
                     permissions: vec![model::Permission::Commit],
                 },
+                model::OperatorEntry::GrantPackagePublish {
+                    key: bob_pub.clone(),
+                    pattern: "test:*".parse().unwrap(),
+                },
                 model::OperatorEntry::RevokeFlat {
                     key_id: bob_pub.fingerprint(),
                     permissions: vec![model::Permission::Commit],
                 },
+                model::OperatorEntry::ReserveNamespace {
+                    namespace: "test".to_string(),
+                    key_ids: vec![bob_pub.fingerprint()],
+                },
             ],
         };
 
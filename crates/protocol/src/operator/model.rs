@@ -1,8 +1,11 @@
-use crate::registry::RecordId;
+use crate::registry::{LogId, PackageName, RecordId};
 use core::fmt;
 use indexmap::IndexSet;
 use serde::{Deserialize, Serialize};
-use std::{str::FromStr, time::SystemTime};
+use std::{
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 use warg_crypto::hash::{AnyHash, HashAlgorithm};
 use warg_crypto::signing;
 
@@ -36,15 +39,18 @@ pub enum Permission {
     DefineNamespace,
     /// Permission to import namespace from another registry and add to the operator log.
     ImportNamespace,
+    /// Permission to publish package records to any namespace of the registry.
+    Publish,
 }
 
 impl Permission {
     /// Gets an array of all permissions.
-    pub const fn all() -> [Permission; 3] {
+    pub const fn all() -> [Permission; 4] {
         [
             Permission::Commit,
             Permission::DefineNamespace,
             Permission::ImportNamespace,
+            Permission::Publish,
         ]
     }
 }
@@ -55,23 +61,95 @@ impl fmt::Display for Permission {
             Permission::Commit => write!(f, "commit"),
             Permission::DefineNamespace => write!(f, "defineNamespace"),
             Permission::ImportNamespace => write!(f, "importNamespace"),
+            Permission::Publish => write!(f, "publish"),
         }
     }
 }
 
 impl FromStr for Permission {
-    type Err = ();
+    type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "commit" => Ok(Permission::Commit),
             "defineNamespace" => Ok(Permission::DefineNamespace),
             "importNamespace" => Ok(Permission::ImportNamespace),
-            _ => Err(()),
+            "publish" => Ok(Permission::Publish),
+            _ => Err(format!("invalid permission {s:?}")),
         }
     }
 }
 
+/// A pattern of package names to which a grant of permission is limited.
+///
+/// A pattern is either `*` for every package, `<namespace>:*` for every
+/// package in a namespace, or a package name.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum PackagePattern {
+    /// Matches every package.
+    Any,
+    /// Matches every package in the namespace.
+    Namespace(String),
+    /// Matches only the named package.
+    Package(PackageName),
+}
+
+impl PackagePattern {
+    /// Checks if the pattern matches the given package name.
+    pub fn matches(&self, name: &PackageName) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Namespace(namespace) => name.namespace() == namespace,
+            Self::Package(package) => package == name,
+        }
+    }
+}
+
+impl fmt::Display for PackagePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => write!(f, "*"),
+            Self::Namespace(namespace) => write!(f, "{namespace}:*"),
+            Self::Package(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl FromStr for PackagePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "*" {
+            return Ok(Self::Any);
+        }
+
+        if let Some(namespace) = s.strip_suffix(":*") {
+            if PackageName::is_valid_namespace(namespace) {
+                return Ok(Self::Namespace(namespace.to_string()));
+            }
+        } else if let Ok(name) = PackageName::new(s) {
+            return Ok(Self::Package(name));
+        }
+
+        Err(format!(
+            "invalid package pattern {s:?}: expected `*`, `<namespace>:*`, or `<namespace>:<name>`"
+        ))
+    }
+}
+
+impl Serialize for PackagePattern {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PackagePattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum OperatorEntry {
@@ -89,8 +167,18 @@ pub enum OperatorEntry {
         key: signing::PublicKey,
         permissions: Vec<Permission>,
     },
+    /// Grant the specified key permission to publish to the packages
+    /// matching a pattern.
+    /// The author of this entry must have the publish permission.
+    GrantPackagePublish {
+        key: signing::PublicKey,
+        pattern: PackagePattern,
+    },
     /// Remove a permission from a key.
     /// The author of this entry must have the permission.
+    ///
+    /// Revoking the publish permission also revokes any package-scoped
+    /// grants of it.
     RevokeFlat {
         key_id: signing::KeyID,
         permissions: Vec<Permission>,
@@ -99,6 +187,40 @@ pub enum OperatorEntry {
     DefineNamespace { namespace: String },
     /// The registry defines a namespace as imported from another registry.
     ImportNamespace { namespace: String, registry: String },
+    /// The registry suppresses a package, hiding it from listings and
+    /// refusing new records to its log.
+    ///
+    /// The package log itself remains available for auditing.
+    SuppressPackage { log_id: LogId, reason: String },
+    /// The registry lifts the suppression of a package.
+    UnsuppressPackage { log_id: LogId },
+    /// The registry declares the maximum expected interval between checkpoints.
+    ///
+    /// Clients may treat a registry whose latest checkpoint is older than
+    /// this interval as stale.
+    SetMaxCheckpointInterval { interval: Duration },
+    /// Replace the key signing this entry with a new key.
+    /// The author of this entry must have the commit permission.
+    ///
+    /// The new key is granted the permissions of the author. The author's
+    /// key may still sign checkpoints for the given overlap after the
+    /// record's timestamp, after which only the new key may; its
+    /// permissions are otherwise retained until revoked.
+    RotateKey {
+        key: signing::PublicKey,
+        overlap: Duration,
+    },
+    /// Reserve a namespace so that only the given keys may initialize
+    /// packages in it.
+    /// The author of this entry must have the define namespace permission.
+    ///
+    /// Reserving a namespace again adds to the keys it is reserved for.
+    /// Packages in namespaces that are not reserved may be initialized by
+    /// any key permitted to publish them.
+    ReserveNamespace {
+        namespace: String,
+        key_ids: Vec<signing::KeyID>,
+    },
 }
 
 impl OperatorEntry {
@@ -106,8 +228,17 @@ impl OperatorEntry {
     pub fn required_permission(&self) -> Option<Permission> {
         match self {
             Self::Init { .. } => None,
-            Self::GrantFlat { .. } | Self::RevokeFlat { .. } => Some(Permission::Commit),
-            Self::DefineNamespace { .. } => Some(Permission::DefineNamespace),
+            // Suppression and checkpoint policy are acts of the registry operator itself
+            Self::GrantFlat { .. }
+            | Self::GrantPackagePublish { .. }
+            | Self::RevokeFlat { .. }
+            | Self::SuppressPackage { .. }
+            | Self::UnsuppressPackage { .. }
+            | Self::SetMaxCheckpointInterval { .. }
+            | Self::RotateKey { .. } => Some(Permission::Commit),
+            Self::DefineNamespace { .. } | Self::ReserveNamespace { .. } => {
+                Some(Permission::DefineNamespace)
+            }
             Self::ImportNamespace { .. } => Some(Permission::ImportNamespace),
         }
     }
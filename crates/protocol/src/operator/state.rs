@@ -1,12 +1,12 @@
 use super::{model, OPERATOR_RECORD_VERSION};
 use crate::registry::PackageName;
-use crate::registry::RecordId;
+use crate::registry::{LogId, RecordId};
 use crate::ProtoEnvelope;
 use indexmap::{IndexMap, IndexSet};
 use serde::{Deserialize, Serialize};
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
-use warg_crypto::hash::{HashAlgorithm, Sha256};
+use warg_crypto::hash::HashAlgorithm;
 use warg_crypto::{signing, Signable};
 
 #[derive(Error, Debug)]
@@ -67,6 +67,29 @@ pub enum ValidationError {
 
     #[error("the namespace `{namespace}` is already defined and cannot be redefined")]
     NamespaceAlreadyDefined { namespace: String },
+
+    #[error("the package log `{log_id}` is already suppressed")]
+    PackageAlreadySuppressed { log_id: LogId },
+
+    #[error("the package log `{log_id}` is not suppressed")]
+    PackageNotSuppressed { log_id: LogId },
+
+    #[error("the maximum checkpoint interval must be at least one second")]
+    InvalidCheckpointInterval,
+
+    #[error("the key with ID {key_id} was already rotated")]
+    KeyAlreadyRotated { key_id: signing::KeyID },
+
+    #[error("the key with ID {key_id} cannot be rotated to itself")]
+    RotationToSameKey { key_id: signing::KeyID },
+
+    #[error("the namespace `{namespace}` must be reserved for at least one key")]
+    EmptyNamespaceReservation { namespace: String },
+
+    #[error(
+        "the namespace `{namespace}` is imported from another registry and cannot be reserved"
+    )]
+    ImportedNamespaceReservation { namespace: String },
 }
 
 /// The namespace definition.
@@ -120,12 +143,30 @@ pub struct LogState {
     /// The permissions of each key.
     #[serde(skip_serializing_if = "IndexMap::is_empty")]
     permissions: IndexMap<signing::KeyID, IndexSet<model::Permission>>,
+    /// The patterns of packages each key may publish to, in addition to
+    /// the packages permitted by its permissions.
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    package_grants: IndexMap<signing::KeyID, IndexSet<model::PackagePattern>>,
     /// The keys known to the state.
     #[serde(skip_serializing_if = "IndexMap::is_empty")]
     keys: IndexMap<signing::KeyID, signing::PublicKey>,
     /// The namespaces known to the state. The key is the lowercased namespace.
     #[serde(skip_serializing_if = "IndexMap::is_empty")]
     namespaces: IndexMap<String, NamespaceDefinition>,
+    /// The packages suppressed by the operator and the reason for each.
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    suppressed: IndexMap<LogId, String>,
+    /// The maximum expected number of seconds between checkpoints.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_checkpoint_interval: Option<u64>,
+    /// The keys replaced by a rotation and the time, in seconds since the
+    /// Unix epoch, until which each may still sign checkpoints.
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    rotated_keys: IndexMap<signing::KeyID, u64>,
+    /// The keys each reserved namespace is reserved for. The key is the
+    /// lowercased namespace.
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    reserved_namespaces: IndexMap<String, IndexSet<signing::KeyID>>,
 }
 
 impl LogState {
@@ -180,10 +221,95 @@ impl LogState {
         }
     }
 
-    /// Checks the key has permission to sign checkpoints.
-    pub fn key_has_permission_to_sign_checkpoints(&self, key_id: &signing::KeyID) -> bool {
+    /// Gets the reason a package was suppressed by the operator.
+    ///
+    /// Returns `None` if the package is not suppressed.
+    pub fn package_suppression(&self, log_id: &LogId) -> Option<&str> {
+        self.suppressed.get(log_id).map(String::as_str)
+    }
+
+    /// Gets the maximum expected interval between checkpoints declared by
+    /// the operator.
+    ///
+    /// Returns `None` if the operator has not declared an interval.
+    pub fn max_checkpoint_interval(&self) -> Option<Duration> {
+        self.max_checkpoint_interval.map(Duration::from_secs)
+    }
+
+    /// Checks the key has permission to sign a checkpoint with the given
+    /// timestamp, in seconds since the Unix epoch.
+    ///
+    /// A key replaced by a rotation may only sign checkpoints until its
+    /// overlap with the new key ends.
+    pub fn key_has_permission_to_sign_checkpoints(
+        &self,
+        key_id: &signing::KeyID,
+        timestamp: u64,
+    ) -> bool {
         self.check_key_permissions(key_id, &[model::Permission::Commit])
             .is_ok()
+            && self
+                .rotated_keys
+                .get(key_id)
+                .map_or(true, |until| timestamp <= *until)
+    }
+
+    /// Gets the permissions of the given key.
+    pub fn key_permissions(
+        &self,
+        key_id: &signing::KeyID,
+    ) -> impl Iterator<Item = model::Permission> + '_ {
+        self.permissions.get(key_id).into_iter().flatten().copied()
+    }
+
+    /// Gets the time until which a key replaced by a rotation may still sign
+    /// checkpoints.
+    ///
+    /// Returns `None` if the key has not been rotated.
+    pub fn key_rotation_overlap_end(&self, key_id: &signing::KeyID) -> Option<SystemTime> {
+        self.rotated_keys
+            .get(key_id)
+            .map(|until| SystemTime::UNIX_EPOCH + Duration::from_secs(*until))
+    }
+
+    /// Checks whether the operator log permits the key to publish records to
+    /// the given package.
+    ///
+    /// A key may publish to any package with the publish permission or to
+    /// the packages matching a package-scoped grant.
+    ///
+    /// Returns `None` if the key is not known to the operator log, in which
+    /// case publishing is governed by the registry's record policy alone.
+    pub fn key_has_permission_to_publish(
+        &self,
+        key_id: &signing::KeyID,
+        name: &PackageName,
+    ) -> Option<bool> {
+        self.keys.contains_key(key_id).then(|| {
+            self.check_key_permissions(key_id, &[model::Permission::Publish])
+                .is_ok()
+                || self.package_grants.get(key_id).map_or(false, |patterns| {
+                    patterns.iter().any(|pattern| pattern.matches(name))
+                })
+        })
+    }
+
+    /// Gets the IDs of the keys a namespace is reserved for.
+    ///
+    /// Returns `None` if the namespace is not reserved.
+    pub fn namespace_reservation(&self, namespace: &str) -> Option<&IndexSet<signing::KeyID>> {
+        self.reserved_namespaces
+            .get(&namespace.to_ascii_lowercase())
+    }
+
+    /// Checks whether the operator log permits the key to initialize the
+    /// given package.
+    ///
+    /// Only the keys a namespace is reserved for may initialize packages in
+    /// it; packages in other namespaces may be initialized by any key.
+    pub fn key_may_initialize_package(&self, key_id: &signing::KeyID, name: &PackageName) -> bool {
+        self.namespace_reservation(name.namespace())
+            .map_or(true, |key_ids| key_ids.contains(key_id))
     }
 
     fn initialized(&self) -> bool {
@@ -207,10 +333,10 @@ impl LogState {
         self.validate_record_timestamp(record)?;
 
         // Validate entries
-        self.validate_record_entries(envelope.key_id(), &record.entries)?;
+        self.validate_record_entries(envelope.key_id(), record.timestamp, &record.entries)?;
 
         // At this point the digest algorithm must be set via an init entry
-        let _algorithm = self
+        let algorithm = self
             .algorithm
             .ok_or(ValidationError::InitialRecordDoesNotInit)?;
 
@@ -226,7 +352,7 @@ impl LogState {
 
         // Update the state head
         self.head = Some(Head {
-            digest: RecordId::operator_record::<Sha256>(envelope),
+            digest: RecordId::operator_record_for(algorithm, envelope),
             timestamp: record.timestamp,
         });
 
@@ -284,6 +410,7 @@ impl LogState {
     fn validate_record_entries(
         &mut self,
         signer_key_id: &signing::KeyID,
+        timestamp: SystemTime,
         entries: &[model::OperatorEntry],
     ) -> Result<(), ValidationError> {
         for entry in entries {
@@ -311,6 +438,9 @@ impl LogState {
                 model::OperatorEntry::GrantFlat { key, permissions } => {
                     self.validate_grant_entry(signer_key_id, key, permissions)?
                 }
+                model::OperatorEntry::GrantPackagePublish { key, pattern } => {
+                    self.validate_grant_package_publish_entry(signer_key_id, key, pattern)?
+                }
                 model::OperatorEntry::RevokeFlat {
                     key_id,
                     permissions,
@@ -327,6 +457,25 @@ impl LogState {
                         registry: registry.to_string(),
                     },
                 )?,
+                model::OperatorEntry::SuppressPackage { log_id, reason } => {
+                    self.validate_suppress_entry(log_id, reason)?
+                }
+                model::OperatorEntry::UnsuppressPackage { log_id } => {
+                    self.validate_unsuppress_entry(log_id)?
+                }
+                model::OperatorEntry::SetMaxCheckpointInterval { interval } => {
+                    if interval.as_secs() == 0 {
+                        return Err(ValidationError::InvalidCheckpointInterval);
+                    }
+
+                    self.max_checkpoint_interval = Some(interval.as_secs());
+                }
+                model::OperatorEntry::RotateKey { key, overlap } => {
+                    self.validate_rotate_key_entry(signer_key_id, timestamp, key, *overlap)?
+                }
+                model::OperatorEntry::ReserveNamespace { namespace, key_ids } => {
+                    self.validate_reserve_namespace_entry(namespace, key_ids)?
+                }
             }
         }
 
@@ -375,6 +524,25 @@ impl LogState {
         Ok(())
     }
 
+    fn validate_grant_package_publish_entry(
+        &mut self,
+        signer_key_id: &signing::KeyID,
+        key: &signing::PublicKey,
+        pattern: &model::PackagePattern,
+    ) -> Result<(), ValidationError> {
+        // Check that the current key may publish to the packages it grants
+        self.check_key_permissions(signer_key_id, &[model::Permission::Publish])?;
+
+        let grant_key_id = key.fingerprint();
+        self.keys.insert(grant_key_id.clone(), key.clone());
+        self.package_grants
+            .entry(grant_key_id)
+            .or_default()
+            .insert(pattern.clone());
+
+        Ok(())
+    }
+
     fn validate_revoke_entry(
         &mut self,
         signer_key_id: &signing::KeyID,
@@ -385,11 +553,16 @@ impl LogState {
         self.check_key_permissions(signer_key_id, permissions)?;
 
         for permission in permissions {
+            // Revoking the publish permission also revokes package-scoped grants
+            let revoked_grants = *permission == model::Permission::Publish
+                && self.package_grants.swap_remove(key_id).is_some();
+
             if !self
                 .permissions
                 .get_mut(key_id)
                 .map(|set| set.swap_remove(permission))
                 .unwrap_or(false)
+                && !revoked_grants
             {
                 return Err(ValidationError::PermissionNotFoundToRevoke {
                     permission: *permission,
@@ -400,6 +573,45 @@ impl LogState {
         Ok(())
     }
 
+    fn validate_rotate_key_entry(
+        &mut self,
+        signer_key_id: &signing::KeyID,
+        timestamp: SystemTime,
+        key: &signing::PublicKey,
+        overlap: Duration,
+    ) -> Result<(), ValidationError> {
+        let key_id = key.fingerprint();
+        if &key_id == signer_key_id {
+            return Err(ValidationError::RotationToSameKey { key_id });
+        }
+
+        if self.rotated_keys.contains_key(signer_key_id) {
+            return Err(ValidationError::KeyAlreadyRotated {
+                key_id: signer_key_id.clone(),
+            });
+        }
+
+        // The new key takes on every permission of the key it replaces
+        let permissions = self
+            .permissions
+            .get(signer_key_id)
+            .cloned()
+            .unwrap_or_default();
+        self.keys.insert(key_id.clone(), key.clone());
+        self.permissions
+            .entry(key_id)
+            .or_default()
+            .extend(permissions);
+
+        let until = (timestamp + overlap)
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.rotated_keys.insert(signer_key_id.clone(), until);
+
+        Ok(())
+    }
+
     fn validate_namespace(
         &mut self,
         namespace: &str,
@@ -440,6 +652,70 @@ impl LogState {
         }
     }
 
+    fn validate_reserve_namespace_entry(
+        &mut self,
+        namespace: &str,
+        key_ids: &[signing::KeyID],
+    ) -> Result<(), ValidationError> {
+        if !PackageName::is_valid_namespace(namespace) {
+            return Err(ValidationError::InvalidNamespace {
+                namespace: namespace.to_string(),
+            });
+        }
+
+        if key_ids.is_empty() {
+            return Err(ValidationError::EmptyNamespaceReservation {
+                namespace: namespace.to_string(),
+            });
+        }
+
+        match self.namespace_state(namespace) {
+            Ok(Some(NamespaceState::Imported { .. })) => {
+                return Err(ValidationError::ImportedNamespaceReservation {
+                    namespace: namespace.to_string(),
+                })
+            }
+            Ok(_) => {}
+            Err(existing) => {
+                return Err(ValidationError::NamespaceConflict {
+                    namespace: namespace.to_string(),
+                    existing: existing.to_string(),
+                })
+            }
+        }
+
+        self.reserved_namespaces
+            .entry(namespace.to_ascii_lowercase())
+            .or_default()
+            .extend(key_ids.iter().cloned());
+        Ok(())
+    }
+
+    fn validate_suppress_entry(
+        &mut self,
+        log_id: &LogId,
+        reason: &str,
+    ) -> Result<(), ValidationError> {
+        if self.suppressed.contains_key(log_id) {
+            return Err(ValidationError::PackageAlreadySuppressed {
+                log_id: log_id.clone(),
+            });
+        }
+
+        self.suppressed.insert(log_id.clone(), reason.to_string());
+        Ok(())
+    }
+
+    fn validate_unsuppress_entry(&mut self, log_id: &LogId) -> Result<(), ValidationError> {
+        if self.suppressed.shift_remove(log_id).is_none() {
+            return Err(ValidationError::PackageNotSuppressed {
+                log_id: log_id.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
     fn check_key_permissions(
         &self,
         key_id: &signing::KeyID,
@@ -479,7 +755,7 @@ mod tests {
     use warg_crypto::signing::generate_p256_pair;
 
     use std::time::SystemTime;
-    use warg_crypto::hash::HashAlgorithm;
+    use warg_crypto::hash::{HashAlgorithm, Sha256};
 
     #[test]
     fn test_validate_base_log() {
@@ -515,11 +791,17 @@ mod tests {
                     IndexSet::from([
                         model::Permission::Commit,
                         model::Permission::DefineNamespace,
-                        model::Permission::ImportNamespace
+                        model::Permission::ImportNamespace,
+                        model::Permission::Publish,
                     ]),
                 )]),
+                package_grants: IndexMap::new(),
                 keys: IndexMap::from([(alice_id, alice_pub)]),
                 namespaces: IndexMap::new(),
+                suppressed: IndexMap::new(),
+                max_checkpoint_interval: None,
+                rotated_keys: IndexMap::new(),
+                reserved_namespaces: IndexMap::new(),
             }
         );
     }
@@ -558,10 +840,16 @@ mod tests {
                     model::Permission::Commit,
                     model::Permission::DefineNamespace,
                     model::Permission::ImportNamespace,
+                    model::Permission::Publish,
                 ]),
             )]),
+            package_grants: IndexMap::new(),
             keys: IndexMap::from([(alice_id, alice_pub)]),
             namespaces: IndexMap::new(),
+            suppressed: IndexMap::new(),
+            max_checkpoint_interval: None,
+            rotated_keys: IndexMap::new(),
+            reserved_namespaces: IndexMap::new(),
         };
 
         assert_eq!(state, expected);
@@ -640,8 +928,10 @@ mod tests {
                     model::Permission::Commit,
                     model::Permission::DefineNamespace,
                     model::Permission::ImportNamespace,
+                    model::Permission::Publish,
                 ]),
             )]),
+            package_grants: IndexMap::new(),
             keys: IndexMap::from([(alice_id, alice_pub)]),
             namespaces: IndexMap::from([
                 (
@@ -661,6 +951,10 @@ mod tests {
                     },
                 ),
             ]),
+            suppressed: IndexMap::new(),
+            max_checkpoint_interval: None,
+            rotated_keys: IndexMap::new(),
+            reserved_namespaces: IndexMap::new(),
         };
 
         assert_eq!(state, expected);
@@ -721,4 +1015,412 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_namespace_reservation() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, bob_priv) = generate_p256_pair();
+        let bob_id = bob_pub.fingerprint();
+        let carol_id = generate_p256_pair().0.fingerprint();
+
+        let record = model::OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+                model::OperatorEntry::GrantFlat {
+                    key: bob_pub,
+                    permissions: vec![model::Permission::Publish],
+                },
+                model::OperatorEntry::DefineNamespace {
+                    namespace: "wasi".to_string(),
+                },
+                model::OperatorEntry::ImportNamespace {
+                    namespace: "imported".to_string(),
+                    registry: "registry.example.com".to_string(),
+                },
+                model::OperatorEntry::ReserveNamespace {
+                    namespace: "wasi".to_string(),
+                    key_ids: vec![bob_id.clone()],
+                },
+            ],
+        };
+
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = LogState::default().validate(&envelope).unwrap();
+
+        // Only the reserved keys may initialize packages in the namespace
+        let clock = PackageName::new("wasi:clock").unwrap();
+        let other = PackageName::new("test:clock").unwrap();
+        assert!(state.key_may_initialize_package(&bob_id, &clock));
+        assert!(!state.key_may_initialize_package(&carol_id, &clock));
+        assert!(state.key_may_initialize_package(&carol_id, &other));
+
+        // Reserving the namespace again adds to the reserved keys
+        let reserve = |namespace: &str, key_ids: Vec<signing::KeyID>| {
+            ProtoEnvelope::signed_contents(
+                &alice_priv,
+                model::OperatorRecord {
+                    prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+                    version: 0,
+                    timestamp: SystemTime::now(),
+                    entries: vec![model::OperatorEntry::ReserveNamespace {
+                        namespace: namespace.to_string(),
+                        key_ids,
+                    }],
+                },
+            )
+            .expect("failed to sign envelope")
+        };
+        let added = state
+            .clone()
+            .validate(&reserve("wasi", vec![carol_id.clone()]))
+            .unwrap();
+        assert!(added.key_may_initialize_package(&carol_id, &clock));
+        assert_eq!(
+            added.namespace_reservation("WASI").map(IndexSet::len),
+            Some(2)
+        );
+
+        // Imported namespaces and empty reservations are not allowed
+        match state
+            .clone()
+            .validate(&reserve("imported", vec![carol_id.clone()]))
+            .unwrap_err()
+        {
+            ValidationError::ImportedNamespaceReservation { .. } => {}
+            e => panic!("expected a different error, got {e}"),
+        }
+        match state
+            .clone()
+            .validate(&reserve("wasi", vec![]))
+            .unwrap_err()
+        {
+            ValidationError::EmptyNamespaceReservation { .. } => {}
+            e => panic!("expected a different error, got {e}"),
+        }
+
+        // Reserving requires the define namespace permission
+        let record = model::OperatorRecord {
+            prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![model::OperatorEntry::ReserveNamespace {
+                namespace: "test".to_string(),
+                key_ids: vec![bob_id],
+            }],
+        };
+        let envelope =
+            ProtoEnvelope::signed_contents(&bob_priv, record).expect("failed to sign envelope");
+        match state.validate(&envelope).unwrap_err() {
+            ValidationError::UnauthorizedAction {
+                needed_permission: model::Permission::DefineNamespace,
+                ..
+            } => {}
+            e => panic!("expected a different error, got {e}"),
+        }
+    }
+
+    #[test]
+    fn test_package_suppression() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let log_id = LogId::package_log::<Sha256>(&PackageName::new("test:malware").unwrap());
+
+        let record = model::OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+                model::OperatorEntry::SuppressPackage {
+                    log_id: log_id.clone(),
+                    reason: "contains malware".to_string(),
+                },
+            ],
+        };
+
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = LogState::default().validate(&envelope).unwrap();
+        assert_eq!(state.package_suppression(&log_id), Some("contains malware"));
+
+        // Suppressing the package again is not allowed
+        let record = model::OperatorRecord {
+            prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![model::OperatorEntry::SuppressPackage {
+                log_id: log_id.clone(),
+                reason: "still contains malware".to_string(),
+            }],
+        };
+        let other =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        match state.clone().validate(&other).unwrap_err() {
+            ValidationError::PackageAlreadySuppressed { .. } => {}
+            _ => panic!("expected a different error"),
+        }
+
+        let record = model::OperatorRecord {
+            prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![model::OperatorEntry::UnsuppressPackage {
+                log_id: log_id.clone(),
+            }],
+        };
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = state.validate(&envelope).unwrap();
+        assert_eq!(state.package_suppression(&log_id), None);
+
+        // Unsuppressing a package that is not suppressed is not allowed
+        let record = model::OperatorRecord {
+            prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![model::OperatorEntry::UnsuppressPackage { log_id }],
+        };
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        match state.validate(&envelope).unwrap_err() {
+            ValidationError::PackageNotSuppressed { .. } => {}
+            _ => panic!("expected a different error"),
+        }
+    }
+
+    #[test]
+    fn test_max_checkpoint_interval() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+
+        let record = model::OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+                model::OperatorEntry::SetMaxCheckpointInterval {
+                    interval: Duration::from_secs(60),
+                },
+            ],
+        };
+
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = LogState::default().validate(&envelope).unwrap();
+        assert_eq!(
+            state.max_checkpoint_interval(),
+            Some(Duration::from_secs(60))
+        );
+
+        // An interval of less than a second is not allowed
+        let record = model::OperatorRecord {
+            prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![model::OperatorEntry::SetMaxCheckpointInterval {
+                interval: Duration::from_millis(500),
+            }],
+        };
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        match state.validate(&envelope).unwrap_err() {
+            ValidationError::InvalidCheckpointInterval => {}
+            _ => panic!("expected a different error"),
+        }
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let alice_id = alice_pub.fingerprint();
+        let (bob_pub, bob_priv) = generate_p256_pair();
+        let bob_id = bob_pub.fingerprint();
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+
+        let record = model::OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp,
+            entries: vec![
+                model::OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+                model::OperatorEntry::RotateKey {
+                    key: bob_pub.clone(),
+                    overlap: Duration::from_secs(60),
+                },
+            ],
+        };
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = LogState::default().validate(&envelope).unwrap();
+
+        // Either key may sign checkpoints during the overlap
+        assert!(state.key_has_permission_to_sign_checkpoints(&alice_id, 1_060));
+        assert!(state.key_has_permission_to_sign_checkpoints(&bob_id, 1_060));
+        assert_eq!(
+            state.key_rotation_overlap_end(&alice_id),
+            Some(timestamp + Duration::from_secs(60))
+        );
+
+        // Only the new key may sign checkpoints after the overlap
+        assert!(!state.key_has_permission_to_sign_checkpoints(&alice_id, 1_061));
+        assert!(state.key_has_permission_to_sign_checkpoints(&bob_id, 1_061));
+
+        // A key may only be rotated once
+        let record = model::OperatorRecord {
+            prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+            version: 0,
+            timestamp,
+            entries: vec![model::OperatorEntry::RotateKey {
+                key: bob_pub.clone(),
+                overlap: Duration::from_secs(60),
+            }],
+        };
+        let other =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        match state.clone().validate(&other).unwrap_err() {
+            ValidationError::KeyAlreadyRotated { key_id } if key_id == alice_id => {}
+            _ => panic!("expected a different error"),
+        }
+
+        // Nor rotated to itself
+        let record = model::OperatorRecord {
+            prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+            version: 0,
+            timestamp,
+            entries: vec![model::OperatorEntry::RotateKey {
+                key: bob_pub,
+                overlap: Duration::from_secs(60),
+            }],
+        };
+        let other =
+            ProtoEnvelope::signed_contents(&bob_priv, record).expect("failed to sign envelope");
+        match state.clone().validate(&other).unwrap_err() {
+            ValidationError::RotationToSameKey { key_id } if key_id == bob_id => {}
+            _ => panic!("expected a different error"),
+        }
+
+        // The new key may revoke the old key
+        let record = model::OperatorRecord {
+            prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+            version: 0,
+            timestamp,
+            entries: vec![model::OperatorEntry::RevokeFlat {
+                key_id: alice_id.clone(),
+                permissions: model::Permission::all().to_vec(),
+            }],
+        };
+        let envelope =
+            ProtoEnvelope::signed_contents(&bob_priv, record).expect("failed to sign envelope");
+        let state = state.validate(&envelope).unwrap();
+        assert!(!state.key_has_permission_to_sign_checkpoints(&alice_id, 1_000));
+        assert!(state.key_has_permission_to_sign_checkpoints(&bob_id, 1_000));
+    }
+
+    #[test]
+    fn test_package_publish_grants() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, _) = generate_p256_pair();
+        let bob_id = bob_pub.fingerprint();
+        let a = PackageName::new("a:pkg").unwrap();
+        let b = PackageName::new("b:pkg").unwrap();
+
+        let record = model::OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+                model::OperatorEntry::GrantPackagePublish {
+                    key: bob_pub.clone(),
+                    pattern: "a:pkg".parse().unwrap(),
+                },
+            ],
+        };
+
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = LogState::default().validate(&envelope).unwrap();
+        assert_eq!(state.key_has_permission_to_publish(&bob_id, &a), Some(true));
+        assert_eq!(
+            state.key_has_permission_to_publish(&bob_id, &b),
+            Some(false)
+        );
+
+        // A namespace grant allows every package in the namespace
+        let record = model::OperatorRecord {
+            prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![model::OperatorEntry::GrantPackagePublish {
+                key: bob_pub,
+                pattern: "b:*".parse().unwrap(),
+            }],
+        };
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = state.validate(&envelope).unwrap();
+        assert_eq!(state.key_has_permission_to_publish(&bob_id, &b), Some(true));
+
+        // Revoking the publish permission revokes the grants
+        let record = model::OperatorRecord {
+            prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![model::OperatorEntry::RevokeFlat {
+                key_id: bob_id.clone(),
+                permissions: vec![model::Permission::Publish],
+            }],
+        };
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = state.validate(&envelope).unwrap();
+        assert_eq!(
+            state.key_has_permission_to_publish(&bob_id, &a),
+            Some(false)
+        );
+        assert_eq!(
+            state.key_has_permission_to_publish(&bob_id, &b),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_package_patterns() {
+        let name = PackageName::new("a:pkg").unwrap();
+        for (pattern, matches) in [
+            ("*", true),
+            ("a:*", true),
+            ("a:pkg", true),
+            ("b:*", false),
+            ("a:other", false),
+        ] {
+            let pattern: model::PackagePattern = pattern.parse().unwrap();
+            assert_eq!(pattern.matches(&name), matches, "{pattern}");
+        }
+
+        for invalid in ["", "a", "a:", "*:pkg", "a:**"] {
+            assert!(
+                invalid.parse::<model::PackagePattern>().is_err(),
+                "{invalid}"
+            );
+        }
+    }
 }
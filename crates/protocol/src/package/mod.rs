@@ -1,4 +1,5 @@
 use anyhow::Error;
+use indexmap::IndexMap;
 use prost::Message;
 use thiserror::Error;
 use warg_crypto::{hash::AnyHash, Decode, Encode, Signable};
@@ -9,7 +10,10 @@ use crate::{pbjson_to_prost_timestamp, prost_to_pbjson_timestamp, registry::Reco
 mod model;
 mod state;
 
-pub use model::{PackageEntry, PackageRecord, Permission};
+pub use model::{
+    PackageEntry, PackageRecord, Permission, ReleaseMetadata, COMPONENT_ARTIFACT, DEBUG_ARTIFACT,
+    WIT_ARTIFACT,
+};
 pub use state::{LogState, Release, ReleaseState, ValidationError};
 
 /// The currently supported package protocol version.
@@ -83,13 +87,24 @@ impl TryFrom<protobuf::PackageEntry> for model::PackageEntry {
                     .map(TryInto::try_into)
                     .collect::<Result<_, _>>()?,
             },
-            Contents::Release(release) => model::PackageEntry::Release {
-                version: release
-                    .version
-                    .parse()
-                    .map_err(|error| Error::new(error) as Error)?,
-                content: release.content_hash.parse()?,
+            Contents::RevokeKey(revoke_key) => model::PackageEntry::RevokeKey {
+                key_id: revoke_key.key_id.into(),
+                since: pbjson_to_prost_timestamp(revoke_key.since.ok_or(InvalidTimestampError)?)
+                    .try_into()?,
             },
+            Contents::Release(release) => {
+                let (content, artifacts) =
+                    release_artifacts(release.content_hash, release.artifacts)?;
+                model::PackageEntry::Release {
+                    version: release
+                        .version
+                        .parse()
+                        .map_err(|error| Error::new(error) as Error)?,
+                    content,
+                    artifacts,
+                    metadata: release.metadata.map(Into::into),
+                }
+            }
             Contents::Yank(yank) => model::PackageEntry::Yank {
                 version: yank.version.parse()?,
             },
@@ -102,6 +117,47 @@ impl TryFrom<protobuf::PackageEntry> for model::PackageEntry {
 #[error("no content in entry")]
 struct EmptyContentError;
 
+/// Splits the artifacts of a release into its `component` artifact and the
+/// others.
+///
+/// Releases published before releases had artifacts only have content, which
+/// is the `component` artifact.
+fn release_artifacts(
+    content_hash: String,
+    artifacts: Vec<protobuf::PackageArtifact>,
+) -> Result<(AnyHash, IndexMap<String, AnyHash>), Error> {
+    let mut content = (!content_hash.is_empty())
+        .then(|| content_hash.parse())
+        .transpose()?;
+    let mut others = IndexMap::with_capacity(artifacts.len());
+    for artifact in artifacts {
+        if artifact.name == model::COMPONENT_ARTIFACT && content.is_none() {
+            content = Some(artifact.content_hash.parse()?);
+            continue;
+        }
+
+        if artifact.name == model::COMPONENT_ARTIFACT || others.contains_key(&artifact.name) {
+            return Err(Error::new(DuplicateArtifactError {
+                name: artifact.name,
+            }));
+        }
+
+        others.insert(artifact.name, artifact.content_hash.parse()?);
+    }
+
+    Ok((content.ok_or(MissingComponentError)?, others))
+}
+
+#[derive(Error, Debug)]
+#[error("release has more than one artifact named `{name}`")]
+struct DuplicateArtifactError {
+    name: String,
+}
+
+#[derive(Error, Debug)]
+#[error("release has no `component` artifact")]
+struct MissingComponentError;
+
 impl TryFrom<i32> for model::Permission {
     type Error = Error;
 
@@ -172,12 +228,29 @@ impl<'a> From<&'a model::PackageEntry> for protobuf::PackageEntry {
                 key_id: key_id.to_string(),
                 permissions: permissions.iter().map(Into::into).collect(),
             }),
-            model::PackageEntry::Release { version, content } => {
-                Contents::Release(protobuf::PackageRelease {
-                    version: version.to_string(),
-                    content_hash: content.to_string(),
+            model::PackageEntry::RevokeKey { key_id, since } => {
+                Contents::RevokeKey(protobuf::PackageRevokeKey {
+                    key_id: key_id.to_string(),
+                    since: Some(prost_to_pbjson_timestamp((*since).into())),
                 })
             }
+            model::PackageEntry::Release {
+                version,
+                content,
+                artifacts,
+                metadata,
+            } => Contents::Release(protobuf::PackageRelease {
+                version: version.to_string(),
+                content_hash: content.to_string(),
+                metadata: metadata.as_ref().map(Into::into),
+                artifacts: artifacts
+                    .iter()
+                    .map(|(name, content)| protobuf::PackageArtifact {
+                        name: name.clone(),
+                        content_hash: content.to_string(),
+                    })
+                    .collect(),
+            }),
             model::PackageEntry::Yank { version } => Contents::Yank(protobuf::PackageYank {
                 version: version.to_string(),
             }),
@@ -187,6 +260,30 @@ impl<'a> From<&'a model::PackageEntry> for protobuf::PackageEntry {
     }
 }
 
+impl From<protobuf::PackageMetadata> for model::ReleaseMetadata {
+    fn from(metadata: protobuf::PackageMetadata) -> Self {
+        Self {
+            description: metadata.description,
+            license: metadata.license,
+            repository: metadata.repository,
+            homepage: metadata.homepage,
+            keywords: metadata.keywords,
+        }
+    }
+}
+
+impl<'a> From<&'a model::ReleaseMetadata> for protobuf::PackageMetadata {
+    fn from(metadata: &'a model::ReleaseMetadata) -> Self {
+        Self {
+            description: metadata.description.clone(),
+            license: metadata.license.clone(),
+            repository: metadata.repository.clone(),
+            homepage: metadata.homepage.clone(),
+            keywords: metadata.keywords.clone(),
+        }
+    }
+}
+
 impl<'a> From<&'a model::Permission> for i32 {
     fn from(permission: &'a model::Permission) -> Self {
         let proto_perm = match permission {
@@ -232,9 +329,36 @@ mod tests {
                     key_id: bob_pub.fingerprint(),
                     permissions: vec![model::Permission::Release],
                 },
+                model::PackageEntry::RevokeKey {
+                    key_id: bob_pub.fingerprint(),
+                    since: SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000),
+                },
                 model::PackageEntry::Release {
                     version: Version::new(1, 0, 0),
                     content: HashAlgorithm::Sha256.digest(&[0, 1, 2, 3]),
+                    artifacts: IndexMap::new(),
+                    metadata: None,
+                },
+                model::PackageEntry::Release {
+                    version: Version::new(1, 1, 0),
+                    content: HashAlgorithm::Sha256.digest(&[4, 5, 6, 7]),
+                    artifacts: IndexMap::from([
+                        (
+                            model::WIT_ARTIFACT.to_string(),
+                            HashAlgorithm::Sha256.digest(&[8, 9]),
+                        ),
+                        (
+                            model::DEBUG_ARTIFACT.to_string(),
+                            HashAlgorithm::Sha256.digest(&[10, 11]),
+                        ),
+                    ]),
+                    metadata: Some(model::ReleaseMetadata {
+                        description: Some("An example package".to_string()),
+                        license: Some("Apache-2.0 WITH LLVM-exception".to_string()),
+                        repository: Some("https://example.com/repo".to_string()),
+                        homepage: None,
+                        keywords: vec!["example".to_string(), "wasm".to_string()],
+                    }),
                 },
             ],
         };
@@ -254,4 +378,70 @@ mod tests {
 
         assert_eq!(first_envelope, second_envelope);
     }
+
+    #[test]
+    fn test_release_artifacts() {
+        let component = HashAlgorithm::Sha256.digest(&[0, 1, 2, 3]);
+        let wit = HashAlgorithm::Sha256.digest(&[4, 5, 6, 7]);
+        let artifact = |name: &str, content: &AnyHash| protobuf::PackageArtifact {
+            name: name.to_string(),
+            content_hash: content.to_string(),
+        };
+        let release = |content_hash: Option<&AnyHash>, artifacts| {
+            model::PackageEntry::try_from(protobuf::PackageEntry {
+                contents: Some(protobuf::package_entry::Contents::Release(
+                    protobuf::PackageRelease {
+                        version: "1.0.0".to_string(),
+                        content_hash: content_hash.map(ToString::to_string).unwrap_or_default(),
+                        metadata: None,
+                        artifacts,
+                    },
+                )),
+            })
+        };
+
+        // Releases with only content have just a component artifact
+        let entry = release(Some(&component), Vec::new()).unwrap();
+        assert_eq!(entry.contents().collect::<Vec<_>>(), vec![&component]);
+
+        // The component artifact may also be given by name
+        let entry = release(
+            None,
+            vec![
+                artifact(model::WIT_ARTIFACT, &wit),
+                artifact(model::COMPONENT_ARTIFACT, &component),
+            ],
+        )
+        .unwrap();
+        assert_eq!(entry.content(), Some(&component));
+        assert_eq!(entry.contents().collect::<Vec<_>>(), vec![&component, &wit]);
+
+        assert_eq!(
+            release(None, vec![artifact(model::WIT_ARTIFACT, &wit)])
+                .unwrap_err()
+                .to_string(),
+            "release has no `component` artifact"
+        );
+        assert_eq!(
+            release(
+                Some(&component),
+                vec![artifact(model::COMPONENT_ARTIFACT, &component)]
+            )
+            .unwrap_err()
+            .to_string(),
+            "release has more than one artifact named `component`"
+        );
+        assert_eq!(
+            release(
+                Some(&component),
+                vec![
+                    artifact(model::WIT_ARTIFACT, &wit),
+                    artifact(model::WIT_ARTIFACT, &wit)
+                ]
+            )
+            .unwrap_err()
+            .to_string(),
+            "release has more than one artifact named `wit`"
+        );
+    }
 }
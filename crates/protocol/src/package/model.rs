@@ -1,6 +1,6 @@
 use crate::registry::RecordId;
 use core::fmt;
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 use semver::Version;
 use serde::{Deserialize, Serialize};
 use std::{str::FromStr, time::SystemTime};
@@ -24,11 +24,20 @@ impl crate::Record for PackageRecord {
     fn contents(&self) -> IndexSet<&AnyHash> {
         self.entries
             .iter()
-            .filter_map(PackageEntry::content)
+            .flat_map(PackageEntry::contents)
             .collect()
     }
 }
 
+/// The name of the artifact every release has: the released component.
+pub const COMPONENT_ARTIFACT: &str = "component";
+
+/// The name of the artifact holding the WIT package of a release.
+pub const WIT_ARTIFACT: &str = "wit";
+
+/// The name of the artifact holding a debug build of a release.
+pub const DEBUG_ARTIFACT: &str = "debug";
+
 /// Each permission represents the ability to use the specified entry
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -89,9 +98,25 @@ pub enum PackageEntry {
         key_id: signing::KeyID,
         permissions: Vec<Permission>,
     },
+    /// Revoke a key as of the given time.
+    /// The author of this entry must be an authorized key.
+    ///
+    /// Releases the key signed at or after `since` are no longer trusted,
+    /// the key loses its permissions, and it may not sign new records.
+    RevokeKey {
+        key_id: signing::KeyID,
+        since: SystemTime,
+    },
     /// Release a version of a package.
     /// The version must not have been released yet.
-    Release { version: Version, content: AnyHash },
+    Release {
+        version: Version,
+        /// The content of the release's `component` artifact.
+        content: AnyHash,
+        /// The content of the release's other artifacts, by name.
+        artifacts: IndexMap<String, AnyHash>,
+        metadata: Option<ReleaseMetadata>,
+    },
     /// Yank a version of a package.
     /// The version must have been released and not yanked.
     Yank { version: Version },
@@ -101,7 +126,10 @@ impl PackageEntry {
     /// Check permission is required to submit this entry
     pub fn required_permission(&self) -> Option<Permission> {
         match self {
-            Self::Init { .. } | Self::GrantFlat { .. } | Self::RevokeFlat { .. } => None,
+            Self::Init { .. }
+            | Self::GrantFlat { .. }
+            | Self::RevokeFlat { .. }
+            | Self::RevokeKey { .. } => None,
             Self::Release { .. } => Some(Permission::Release),
             Self::Yank { .. } => Some(Permission::Yank),
         }
@@ -109,6 +137,8 @@ impl PackageEntry {
 
     /// Gets the content associated with the entry.
     ///
+    /// For a release, this is the content of its `component` artifact.
+    ///
     /// Returns `None` if the entry does not have content.
     pub fn content(&self) -> Option<&AnyHash> {
         match self {
@@ -116,4 +146,48 @@ impl PackageEntry {
             _ => None,
         }
     }
+
+    /// Gets the content of every artifact associated with the entry.
+    pub fn contents(&self) -> impl Iterator<Item = &AnyHash> {
+        let artifacts = match self {
+            Self::Release { artifacts, .. } => Some(artifacts.values()),
+            _ => None,
+        };
+
+        self.content()
+            .into_iter()
+            .chain(artifacts.into_iter().flatten())
+    }
+}
+
+/// Human-readable metadata describing a release of a package.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseMetadata {
+    /// A short description of the package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The SPDX license expression of the release.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// The URL of the package's source repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    /// The URL of the package's homepage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    /// Keywords for finding the package.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+}
+
+impl ReleaseMetadata {
+    /// Determines if the metadata has no fields set.
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none()
+            && self.license.is_none()
+            && self.repository.is_none()
+            && self.homepage.is_none()
+            && self.keywords.is_empty()
+    }
 }
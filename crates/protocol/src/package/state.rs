@@ -6,8 +6,8 @@ use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 use std::time::SystemTime;
 use thiserror::Error;
-use warg_crypto::hash::{AnyHash, HashAlgorithm, Sha256};
-use warg_crypto::{signing, Signable};
+use warg_crypto::hash::{AnyHash, HashAlgorithm};
+use warg_crypto::{signing, Encode, Signable};
 
 #[derive(Error, Debug)]
 pub enum ValidationError {
@@ -67,6 +67,15 @@ pub enum ValidationError {
 
     #[error("record has lower timestamp than previous")]
     TimestampLowerThanPrevious,
+
+    #[error("the key with ID {key_id} has been revoked")]
+    KeyRevoked { key_id: signing::KeyID },
+
+    #[error("the key with ID {key_id} is not authorized to revoke keys")]
+    KeyRevocationUnauthorized { key_id: signing::KeyID },
+
+    #[error("attempted to revoke key {key_id} which is not known to this package log")]
+    KeyNotFoundToRevoke { key_id: signing::KeyID },
 }
 
 /// Represents the current state of a release.
@@ -76,7 +85,12 @@ pub enum ReleaseState {
     /// The release is currently available.
     Released {
         /// The content digest associated with the release.
+        ///
+        /// This is the content of the release's `component` artifact.
         content: AnyHash,
+        /// The content digests of the release's other artifacts, by name.
+        #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+        artifacts: IndexMap<String, AnyHash>,
     },
     /// The release has been yanked.
     Yanked {
@@ -103,6 +117,9 @@ pub struct Release {
     pub timestamp: SystemTime,
     /// The current state of the release.
     pub state: ReleaseState,
+    /// The metadata included with the release.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<model::ReleaseMetadata>,
 }
 
 impl Release {
@@ -116,14 +133,57 @@ impl Release {
     /// Returns `None` if the release has been yanked.
     pub fn content(&self) -> Option<&AnyHash> {
         match &self.state {
-            ReleaseState::Released { content } => Some(content),
+            ReleaseState::Released { content, .. } => Some(content),
             ReleaseState::Yanked { .. } => None,
         }
     }
+
+    /// Gets the content of the release's artifact with the given name.
+    ///
+    /// Returns `None` if the release has been yanked or has no such artifact.
+    pub fn artifact(&self, name: &str) -> Option<&AnyHash> {
+        match &self.state {
+            ReleaseState::Released { content, .. } if name == model::COMPONENT_ARTIFACT => {
+                Some(content)
+            }
+            ReleaseState::Released { artifacts, .. } => artifacts.get(name),
+            ReleaseState::Yanked { .. } => None,
+        }
+    }
+
+    /// Gets the names and content of the release's artifacts, starting with
+    /// its `component` artifact.
+    ///
+    /// Returns an empty iterator if the release has been yanked.
+    pub fn artifacts(&self) -> impl Iterator<Item = (&str, &AnyHash)> {
+        let (content, artifacts) = match &self.state {
+            ReleaseState::Released { content, artifacts } => (Some(content), Some(artifacts)),
+            ReleaseState::Yanked { .. } => (None, None),
+        };
+
+        content
+            .map(|content| (model::COMPONENT_ARTIFACT, content))
+            .into_iter()
+            .chain(
+                artifacts
+                    .into_iter()
+                    .flatten()
+                    .map(|(name, content)| (name.as_str(), content)),
+            )
+    }
 }
 
 /// Information about the current head of the package log.
 ///
+/// Represents the revocation of a key in a package log.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct KeyRevocation {
+    /// The time the key is revoked as of.
+    #[serde(with = "crate::timestamp")]
+    since: SystemTime,
+}
+
 /// A head is the last validated record digest and timestamp.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
@@ -155,6 +215,9 @@ pub struct LogState {
     /// The keys known to the state.
     #[serde(skip_serializing_if = "IndexMap::is_empty")]
     keys: IndexMap<signing::KeyID, signing::PublicKey>,
+    /// The keys revoked in the log.
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    revoked_keys: IndexMap<signing::KeyID, KeyRevocation>,
 }
 
 impl LogState {
@@ -185,6 +248,30 @@ impl LogState {
         Ok(self)
     }
 
+    /// Validates a package record that is yet to be signed by the key with
+    /// the given ID.
+    ///
+    /// Every check of `validate` is performed except for verifying the
+    /// signature, so that a record the registry would reject can be caught
+    /// before it is signed.
+    ///
+    /// Note that on failure, the log state is consumed to prevent
+    /// invalid state from being used in future validations.
+    pub fn validate_unsigned(
+        mut self,
+        key_id: &signing::KeyID,
+        record: &model::PackageRecord,
+    ) -> Result<Self, ValidationError> {
+        let content_bytes = record.encode();
+        let algorithm = self.validate_record_contents(key_id, record, &content_bytes)?;
+        self.head = Some(Head {
+            digest: RecordId::package_record_bytes(algorithm, &content_bytes),
+            timestamp: record.timestamp,
+        });
+
+        Ok(self)
+    }
+
     /// Gets the releases known to the state.
     ///
     /// The releases are returned in package log order.
@@ -203,14 +290,33 @@ impl LogState {
 
     /// Finds the latest release matching the given version requirement.
     ///
-    /// Releases that have been yanked are not considered.
+    /// Releases that have been yanked or that are not trusted are not
+    /// considered.
     pub fn find_latest_release(&self, req: &VersionReq) -> Option<&Release> {
         self.releases
             .values()
-            .filter(|release| !release.yanked() && req.matches(&release.version))
+            .filter(|release| {
+                !release.yanked() && self.is_trusted(release) && req.matches(&release.version)
+            })
             .max_by(|a, b| a.version.cmp(&b.version))
     }
 
+    /// Gets the time the given key was revoked as of.
+    ///
+    /// Returns `None` if the key has not been revoked.
+    pub fn key_revocation(&self, key_id: &signing::KeyID) -> Option<SystemTime> {
+        self.revoked_keys.get(key_id).map(|r| r.since)
+    }
+
+    /// Determines if a release is trusted.
+    ///
+    /// A release is not trusted if the key that released it was revoked as
+    /// of a time at or before the release.
+    pub fn is_trusted(&self, release: &Release) -> bool {
+        self.key_revocation(&release.by)
+            .map_or(true, |since| release.timestamp < since)
+    }
+
     /// Gets the public key of the given key id.
     ///
     /// Returns `None` if the key id is not recognized.
@@ -235,8 +341,30 @@ impl LogState {
         envelope: &ProtoEnvelope<model::PackageRecord>,
     ) -> Result<(), ValidationError> {
         let record = envelope.as_ref();
-        let record_id = RecordId::package_record::<Sha256>(envelope);
+        let algorithm =
+            self.validate_record_contents(envelope.key_id(), record, envelope.content_bytes())?;
+
+        // Validate the envelope signature
+        let key = &self.keys[envelope.key_id()];
+        model::PackageRecord::verify(key, envelope.content_bytes(), envelope.signature())?;
 
+        // Update the state head
+        self.head = Some(Head {
+            digest: RecordId::package_record_for(algorithm, envelope),
+            timestamp: record.timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Validates the contents of a record signed by the given key, returning
+    // the hash algorithm of the log.
+    fn validate_record_contents(
+        &mut self,
+        signer_key_id: &signing::KeyID,
+        record: &model::PackageRecord,
+        content_bytes: &[u8],
+    ) -> Result<HashAlgorithm, ValidationError> {
         // Validate previous hash
         self.validate_record_hash(record)?;
 
@@ -248,34 +376,25 @@ impl LogState {
 
         // Validate entries
         self.validate_record_entries(
-            &record_id,
-            envelope.key_id(),
+            signer_key_id,
+            content_bytes,
             record.timestamp,
             &record.entries,
         )?;
 
         // At this point the digest algorithm must be set via an init entry
-        let _algorithm = self
+        let algorithm = self
             .algorithm
             .ok_or(ValidationError::InitialRecordDoesNotInit)?;
 
-        // Validate the envelope key id
-        let key = self.keys.get(envelope.key_id()).ok_or_else(|| {
-            ValidationError::KeyIDNotRecognized {
-                key_id: envelope.key_id().clone(),
-            }
-        })?;
-
-        // Validate the envelope signature
-        model::PackageRecord::verify(key, envelope.content_bytes(), envelope.signature())?;
-
-        // Update the state head
-        self.head = Some(Head {
-            digest: record_id,
-            timestamp: record.timestamp,
-        });
+        // Validate the signer key id
+        if !self.keys.contains_key(signer_key_id) {
+            return Err(ValidationError::KeyIDNotRecognized {
+                key_id: signer_key_id.clone(),
+            });
+        }
 
-        Ok(())
+        Ok(algorithm)
     }
 
     fn validate_record_hash(&self, record: &model::PackageRecord) -> Result<(), ValidationError> {
@@ -328,11 +447,17 @@ impl LogState {
 
     fn validate_record_entries(
         &mut self,
-        record_id: &RecordId,
         signer_key_id: &signing::KeyID,
+        content_bytes: &[u8],
         timestamp: SystemTime,
         entries: &[model::PackageEntry],
     ) -> Result<(), ValidationError> {
+        if self.revoked_keys.contains_key(signer_key_id) {
+            return Err(ValidationError::KeyRevoked {
+                key_id: signer_key_id.clone(),
+            });
+        }
+
         for entry in entries {
             if let Some(permission) = entry.required_permission() {
                 self.check_key_permissions(signer_key_id, &[permission])?;
@@ -349,9 +474,9 @@ impl LogState {
             }
 
             // Must have seen an init entry by now
-            if !self.initialized() {
+            let Some(algorithm) = self.algorithm else {
                 return Err(ValidationError::FirstEntryIsNotInit);
-            }
+            };
 
             match entry {
                 model::PackageEntry::Init { .. } => unreachable!(), // handled above
@@ -362,12 +487,24 @@ impl LogState {
                     key_id,
                     permissions,
                 } => self.validate_revoke_entry(signer_key_id, key_id, permissions)?,
-                model::PackageEntry::Release { version, content } => self.validate_release_entry(
-                    record_id,
+                model::PackageEntry::RevokeKey { key_id, since } => {
+                    self.validate_revoke_key_entry(signer_key_id, key_id, *since)?
+                }
+                model::PackageEntry::Release {
+                    version,
+                    content,
+                    artifacts,
+                    metadata,
+                } => self.validate_release_entry(
+                    &RecordId::package_record_bytes(algorithm, content_bytes),
                     signer_key_id,
                     timestamp,
                     version,
-                    content,
+                    ReleaseState::Released {
+                        content: content.clone(),
+                        artifacts: artifacts.clone(),
+                    },
+                    metadata.as_ref(),
                 )?,
                 model::PackageEntry::Yank { version } => {
                     self.validate_yank_entry(signer_key_id, timestamp, version)?
@@ -452,13 +589,49 @@ impl LogState {
         Ok(())
     }
 
+    fn validate_revoke_key_entry(
+        &mut self,
+        signer_key_id: &signing::KeyID,
+        key_id: &signing::KeyID,
+        since: SystemTime,
+    ) -> Result<(), ValidationError> {
+        // Only a key that currently has a permission may revoke keys
+        if self
+            .permissions
+            .get(signer_key_id)
+            .map_or(true, IndexSet::is_empty)
+        {
+            return Err(ValidationError::KeyRevocationUnauthorized {
+                key_id: signer_key_id.clone(),
+            });
+        }
+
+        if !self.keys.contains_key(key_id) {
+            return Err(ValidationError::KeyNotFoundToRevoke {
+                key_id: key_id.clone(),
+            });
+        }
+
+        if self.revoked_keys.contains_key(key_id) {
+            return Err(ValidationError::KeyRevoked {
+                key_id: key_id.clone(),
+            });
+        }
+
+        self.permissions.swap_remove(key_id);
+        self.revoked_keys
+            .insert(key_id.clone(), KeyRevocation { since });
+        Ok(())
+    }
+
     fn validate_release_entry(
         &mut self,
         record_id: &RecordId,
         signer_key_id: &signing::KeyID,
         timestamp: SystemTime,
         version: &Version,
-        content: &AnyHash,
+        state: ReleaseState,
+        metadata: Option<&model::ReleaseMetadata>,
     ) -> Result<(), ValidationError> {
         match self.releases.entry(version.clone()) {
             Entry::Occupied(e) => {
@@ -473,9 +646,8 @@ impl LogState {
                     version,
                     by: signer_key_id.clone(),
                     timestamp,
-                    state: ReleaseState::Released {
-                        content: content.clone(),
-                    },
+                    state,
+                    metadata: metadata.cloned(),
                 });
             }
         }
@@ -544,7 +716,7 @@ mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
     use std::time::{Duration, SystemTime};
-    use warg_crypto::hash::HashAlgorithm;
+    use warg_crypto::hash::{HashAlgorithm, Sha256};
     use warg_crypto::signing::generate_p256_pair;
 
     #[test]
@@ -581,6 +753,7 @@ mod tests {
                 )]),
                 releases: IndexMap::default(),
                 keys: IndexMap::from([(alice_id, alice_pub)]),
+                revoked_keys: IndexMap::default(),
             }
         );
     }
@@ -618,6 +791,12 @@ mod tests {
         // In envelope 1: bob releases 1.1.0
         let timestamp1 = timestamp0 + Duration::from_secs(1);
         let content = hash_algo.digest(&[0, 1, 2, 3]);
+        let wit = hash_algo.digest(&[4, 5, 6, 7]);
+        let artifacts = IndexMap::from([(model::WIT_ARTIFACT.to_string(), wit.clone())]);
+        let metadata = model::ReleaseMetadata {
+            license: Some("MIT".to_string()),
+            ..Default::default()
+        };
         let record1 = model::PackageRecord {
             prev: Some(RecordId::package_record::<Sha256>(&envelope0)),
             version: PACKAGE_RECORD_VERSION,
@@ -625,6 +804,8 @@ mod tests {
             entries: vec![model::PackageEntry::Release {
                 version: Version::new(1, 1, 0),
                 content: content.clone(),
+                artifacts: artifacts.clone(),
+                metadata: Some(metadata.clone()),
             }],
         };
 
@@ -641,8 +822,10 @@ mod tests {
                 by: bob_id.clone(),
                 timestamp: timestamp1,
                 state: ReleaseState::Released {
-                    content: content.clone()
-                }
+                    content: content.clone(),
+                    artifacts: artifacts.clone(),
+                },
+                metadata: Some(metadata.clone()),
             })
         );
         assert!(state
@@ -655,10 +838,26 @@ mod tests {
                 version: Version::new(1, 1, 0),
                 by: bob_id.clone(),
                 timestamp: timestamp1,
-                state: ReleaseState::Released { content }
+                state: ReleaseState::Released {
+                    content: content.clone(),
+                    artifacts,
+                },
+                metadata: Some(metadata.clone()),
             }]
         );
 
+        let release = state.release(&Version::new(1, 1, 0)).unwrap();
+        assert_eq!(release.artifact(model::COMPONENT_ARTIFACT), Some(&content));
+        assert_eq!(release.artifact(model::WIT_ARTIFACT), Some(&wit));
+        assert_eq!(release.artifact(model::DEBUG_ARTIFACT), None);
+        assert_eq!(
+            release.artifacts().collect::<Vec<_>>(),
+            vec![
+                (model::COMPONENT_ARTIFACT, &content),
+                (model::WIT_ARTIFACT, &wit)
+            ]
+        );
+
         // In envelope 2: alice revokes bobs access and yanks 1.1.0
         let timestamp2 = timestamp1 + Duration::from_secs(1);
         let record2 = model::PackageRecord {
@@ -690,7 +889,8 @@ mod tests {
                 state: ReleaseState::Yanked {
                     by: alice_id.clone(),
                     timestamp: timestamp2
-                }
+                },
+                metadata: Some(metadata.clone()),
             }]
         );
 
@@ -719,14 +919,125 @@ mod tests {
                         state: ReleaseState::Yanked {
                             by: alice_id.clone(),
                             timestamp: timestamp2
-                        }
+                        },
+                        metadata: Some(metadata),
                     }
                 )]),
                 keys: IndexMap::from([(alice_id, alice_pub), (bob_id, bob_pub),]),
+                revoked_keys: IndexMap::default(),
             }
         );
     }
 
+    #[test]
+    fn test_revoke_key() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, bob_priv) = generate_p256_pair();
+        let (carol_pub, carol_priv) = generate_p256_pair();
+        let bob_id = bob_pub.fingerprint();
+
+        let hash_algo = HashAlgorithm::Sha256;
+        let release = |version: Version| model::PackageEntry::Release {
+            version,
+            content: hash_algo.digest(&[0, 1, 2, 3]),
+            artifacts: IndexMap::default(),
+            metadata: None,
+        };
+
+        // In envelope 0: alice inits, grants bob release and carol nothing
+        let timestamp0 = SystemTime::now();
+        let record0 = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: timestamp0,
+            entries: vec![
+                model::PackageEntry::Init {
+                    hash_algorithm: hash_algo,
+                    key: alice_pub,
+                },
+                model::PackageEntry::GrantFlat {
+                    key: bob_pub,
+                    permissions: vec![model::Permission::Release],
+                },
+                model::PackageEntry::GrantFlat {
+                    key: carol_pub,
+                    permissions: vec![],
+                },
+            ],
+        };
+        let envelope0 = ProtoEnvelope::signed_contents(&alice_priv, record0).unwrap();
+        let state = LogState::default().validate(&envelope0).unwrap();
+
+        // In envelopes 1 and 2: bob releases 1.0.0 and then 2.0.0
+        let timestamp1 = timestamp0 + Duration::from_secs(1);
+        let record1 = model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&envelope0)),
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: timestamp1,
+            entries: vec![release(Version::new(1, 0, 0))],
+        };
+        let envelope1 = ProtoEnvelope::signed_contents(&bob_priv, record1).unwrap();
+        let state = state.validate(&envelope1).unwrap();
+
+        let timestamp2 = timestamp1 + Duration::from_secs(1);
+        let record2 = model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&envelope1)),
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: timestamp2,
+            entries: vec![release(Version::new(2, 0, 0))],
+        };
+        let envelope2 = ProtoEnvelope::signed_contents(&bob_priv, record2).unwrap();
+        let state = state.validate(&envelope2).unwrap();
+
+        // A key without permissions may not revoke keys
+        let timestamp3 = timestamp2 + Duration::from_secs(1);
+        let revoke = model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&envelope2)),
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: timestamp3,
+            entries: vec![model::PackageEntry::RevokeKey {
+                key_id: bob_id.clone(),
+                since: timestamp2,
+            }],
+        };
+        let envelope = ProtoEnvelope::signed_contents(&carol_priv, revoke.clone()).unwrap();
+        match state.clone().validate(&envelope).unwrap_err() {
+            ValidationError::KeyRevocationUnauthorized { .. } => {}
+            e => panic!("expected a different error, got {e}"),
+        }
+
+        // In envelope 3: alice revokes bob's key as of the release of 2.0.0
+        let envelope3 = ProtoEnvelope::signed_contents(&alice_priv, revoke).unwrap();
+        let state = state.validate(&envelope3).unwrap();
+        assert_eq!(state.key_revocation(&bob_id), Some(timestamp2));
+        assert_eq!(state.key_permissions(&bob_id), None);
+
+        // The release before the revocation is still trusted
+        let release1 = state.release(&Version::new(1, 0, 0)).unwrap();
+        assert!(state.is_trusted(release1));
+        let release2 = state.release(&Version::new(2, 0, 0)).unwrap();
+        assert!(!state.is_trusted(release2));
+        assert_eq!(
+            state
+                .find_latest_release(&VersionReq::STAR)
+                .map(|r| &r.version),
+            Some(&Version::new(1, 0, 0))
+        );
+
+        // Bob may no longer sign records
+        let record4 = model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&envelope3)),
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: timestamp3 + Duration::from_secs(1),
+            entries: vec![release(Version::new(3, 0, 0))],
+        };
+        let envelope4 = ProtoEnvelope::signed_contents(&bob_priv, record4).unwrap();
+        match state.validate(&envelope4).unwrap_err() {
+            ValidationError::KeyRevoked { key_id } => assert_eq!(key_id, bob_id),
+            e => panic!("expected a different error, got {e}"),
+        }
+    }
+
     #[test]
     fn test_rollback() {
         let (alice_pub, alice_priv) = generate_p256_pair();
@@ -761,6 +1072,7 @@ mod tests {
                 IndexSet::from([model::Permission::Release, model::Permission::Yank]),
             )]),
             keys: IndexMap::from([(alice_id, alice_pub)]),
+            revoked_keys: IndexMap::default(),
         };
 
         assert_eq!(state, expected);
@@ -792,4 +1104,70 @@ mod tests {
             _ => panic!("expected a different error"),
         }
     }
+
+    #[test]
+    fn test_validate_unsigned() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, _) = generate_p256_pair();
+        let alice_id = alice_pub.fingerprint();
+
+        let record = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+                model::PackageEntry::Release {
+                    version: Version::new(1, 0, 0),
+                    content: HashAlgorithm::Sha256.digest(&[0, 1, 2, 3]),
+                    artifacts: IndexMap::default(),
+                    metadata: None,
+                },
+            ],
+        };
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, record).unwrap();
+        let state = LogState::default().validate(&envelope).unwrap();
+
+        let release = |version| model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&envelope)),
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![model::PackageEntry::Release {
+                version,
+                content: HashAlgorithm::Sha256.digest(&[4, 5, 6, 7]),
+                artifacts: IndexMap::default(),
+                metadata: None,
+            }],
+        };
+
+        // A record is validated as it would be once signed
+        let record = release(Version::new(1, 1, 0));
+        let next = state.clone().validate_unsigned(&alice_id, &record).unwrap();
+        assert_eq!(
+            next.head().as_ref().unwrap().digest,
+            RecordId::unsigned_package_record_for(HashAlgorithm::Sha256, &record)
+        );
+
+        match state
+            .clone()
+            .validate_unsigned(&alice_id, &release(Version::new(1, 0, 0)))
+            .unwrap_err()
+        {
+            ValidationError::ReleaseOfReleased { version } => {
+                assert_eq!(version, Version::new(1, 0, 0))
+            }
+            e => panic!("expected a different error: {e}"),
+        }
+
+        match state
+            .validate_unsigned(&bob_pub.fingerprint(), &release(Version::new(1, 1, 0)))
+            .unwrap_err()
+        {
+            ValidationError::UnauthorizedAction { .. } => {}
+            e => panic!("expected a different error: {e}"),
+        }
+    }
 }
@@ -54,6 +54,50 @@ impl<Contents> ProtoEnvelope<Contents> {
         })
     }
 
+    /// Create an envelope for some contents using a signature made by the
+    /// key with the given ID.
+    ///
+    /// This allows contents to be signed elsewhere, such as by a hardware
+    /// security module; the signature is expected to be over the contents'
+    /// [`Signable::signing_payload`] and is not verified.
+    pub fn from_signature(
+        contents: Contents,
+        key_id: signing::KeyID,
+        signature: signing::Signature,
+    ) -> Self
+    where
+        Contents: Signable,
+    {
+        ProtoEnvelope {
+            content_bytes: contents.encode(),
+            contents,
+            key_id,
+            signature,
+        }
+    }
+
+    /// Get the maximum length of the protobuf representation of an envelope
+    /// for the given contents signed by the key with the given ID and
+    /// signature algorithm.
+    pub fn max_protobuf_len(
+        contents: &Contents,
+        key_id: &signing::KeyID,
+        algorithm: &signing::SignatureAlgorithm,
+    ) -> usize
+    where
+        Contents: Signable,
+    {
+        let signature_len = STANDARD
+            .encode(vec![0; algorithm.max_signature_len()])
+            .len();
+        protobuf::Envelope {
+            contents: contents.encode(),
+            key_id: key_id.to_string(),
+            signature: format!("{algorithm}:{pad}", pad = "A".repeat(signature_len)),
+        }
+        .encoded_len()
+    }
+
     /// Get the byte representation of the envelope contents.
     pub fn content_bytes(&self) -> &[u8] {
         &self.content_bytes
@@ -136,6 +180,15 @@ pub struct ProtoEnvelopeBody {
     signature: signing::Signature,
 }
 
+impl ProtoEnvelopeBody {
+    /// Get the key ID of the signing key claimed by the envelope.
+    ///
+    /// The signature has not been verified against the key.
+    pub fn key_id(&self) -> &signing::KeyID {
+        &self.key_id
+    }
+}
+
 impl<Content> TryFrom<ProtoEnvelopeBody> for ProtoEnvelope<Content>
 where
     Content: Decode,
@@ -3,10 +3,10 @@ use anyhow::bail;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::str::FromStr;
-use std::time::SystemTime;
-use warg_crypto::hash::{AnyHash, Hash, HashAlgorithm, SupportedDigest};
+use std::time::{Duration, SystemTime};
+use warg_crypto::hash::{AnyHash, HashAlgorithm, SupportedDigest};
 use warg_crypto::prefix::VisitPrefixEncode;
-use warg_crypto::{prefix, ByteVisitor, Signable, VisitBytes};
+use warg_crypto::{prefix, ByteVisitor, Encode, Signable, VisitBytes};
 use wasmparser::names::KebabStr;
 
 /// Type alias for registry log index
@@ -45,6 +45,12 @@ pub struct TimestampedCheckpoint {
     #[serde(flatten)]
     pub checkpoint: Checkpoint,
     pub timestamp: u64,
+    /// The time after which the checkpoint is no longer valid, in seconds
+    /// since the Unix epoch.
+    ///
+    /// A checkpoint without an expiry never expires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
 }
 
 impl TimestampedCheckpoint {
@@ -52,12 +58,29 @@ impl TimestampedCheckpoint {
         Ok(Self {
             checkpoint,
             timestamp: time.duration_since(std::time::UNIX_EPOCH)?.as_secs(),
+            expires: None,
         })
     }
 
     pub fn now(checkpoint: Checkpoint) -> anyhow::Result<Self> {
         Self::new(checkpoint, SystemTime::now())
     }
+
+    /// Sets the checkpoint to expire once the given duration has passed
+    /// since its timestamp.
+    pub fn with_validity(mut self, validity: Duration) -> Self {
+        self.expires = Some(self.timestamp.saturating_add(validity.as_secs()));
+        self
+    }
+
+    /// Determines if the checkpoint has expired as of the given time.
+    pub fn is_expired_at(&self, time: SystemTime) -> bool {
+        let now = time
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.expires.map_or(false, |expires| now > expires)
+    }
 }
 
 impl Signable for TimestampedCheckpoint {
@@ -66,11 +89,19 @@ impl Signable for TimestampedCheckpoint {
 
 impl prefix::VisitPrefixEncode for TimestampedCheckpoint {
     fn visit_pe<BV: ?Sized + ByteVisitor>(&self, visitor: &mut prefix::PrefixEncodeVisitor<BV>) {
-        visitor.visit_str_raw("WARG-TIMESTAMPED-CHECKPOINT-V0");
+        // The expiry is signed along with the roots; checkpoints without one
+        // keep the original encoding so their signatures remain valid
+        match self.expires {
+            Some(_) => visitor.visit_str_raw("WARG-TIMESTAMPED-CHECKPOINT-V1"),
+            None => visitor.visit_str_raw("WARG-TIMESTAMPED-CHECKPOINT-V0"),
+        }
         visitor.visit_unsigned(self.checkpoint.log_length as u64);
         visitor.visit_str(&self.checkpoint.log_root.to_string());
         visitor.visit_str(&self.checkpoint.map_root.to_string());
         visitor.visit_unsigned(self.timestamp);
+        if let Some(expires) = self.expires {
+            visitor.visit_unsigned(expires);
+        }
     }
 }
 
@@ -224,15 +255,27 @@ pub struct LogId(AnyHash);
 
 impl LogId {
     pub fn operator_log<D: SupportedDigest>() -> Self {
-        let prefix: &[u8] = b"WARG-OPERATOR-LOG-ID-V0".as_slice();
-        let hash: Hash<D> = Hash::of(prefix);
-        Self(hash.into())
+        Self::operator_log_for(D::ALGORITHM)
     }
 
     pub fn package_log<D: SupportedDigest>(name: &PackageName) -> Self {
+        Self::package_log_for(D::ALGORITHM, name)
+    }
+
+    /// Gets the operator log ID of a registry using the given hash algorithm.
+    pub fn operator_log_for(algorithm: HashAlgorithm) -> Self {
+        let prefix: &[u8] = b"WARG-OPERATOR-LOG-ID-V0".as_slice();
+        Self(AnyHash::of(algorithm, prefix))
+    }
+
+    /// Gets the log ID of a package in a registry using the given hash algorithm.
+    pub fn package_log_for(algorithm: HashAlgorithm, name: &PackageName) -> Self {
         let prefix: &[u8] = b"WARG-PACKAGE-LOG-ID-V0:".as_slice();
-        let hash: Hash<D> = Hash::of((prefix, name));
-        Self(hash.into())
+        Self(AnyHash::of(algorithm, (prefix, name)))
+    }
+
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.0.algorithm()
     }
 }
 
@@ -276,15 +319,41 @@ impl RecordId {
     }
 
     pub fn operator_record<D: SupportedDigest>(record: &ProtoEnvelope<OperatorRecord>) -> Self {
-        let prefix: &[u8] = b"WARG-OPERATOR-LOG-RECORD-V0:".as_slice();
-        let hash: Hash<D> = Hash::of((prefix, record.content_bytes()));
-        Self(hash.into())
+        Self::operator_record_for(D::ALGORITHM, record)
     }
 
     pub fn package_record<D: SupportedDigest>(record: &ProtoEnvelope<PackageRecord>) -> Self {
+        Self::package_record_for(D::ALGORITHM, record)
+    }
+
+    /// Gets the ID of an operator record hashed with the given algorithm.
+    pub fn operator_record_for(
+        algorithm: HashAlgorithm,
+        record: &ProtoEnvelope<OperatorRecord>,
+    ) -> Self {
+        let prefix: &[u8] = b"WARG-OPERATOR-LOG-RECORD-V0:".as_slice();
+        Self(AnyHash::of(algorithm, (prefix, record.content_bytes())))
+    }
+
+    /// Gets the ID of a package record hashed with the given algorithm.
+    pub fn package_record_for(
+        algorithm: HashAlgorithm,
+        record: &ProtoEnvelope<PackageRecord>,
+    ) -> Self {
+        Self::package_record_bytes(algorithm, record.content_bytes())
+    }
+
+    /// Gets the ID a package record will have once signed, hashed with the
+    /// given algorithm.
+    ///
+    /// The ID of a record does not depend on its signature.
+    pub fn unsigned_package_record_for(algorithm: HashAlgorithm, record: &PackageRecord) -> Self {
+        Self::package_record_bytes(algorithm, &record.encode())
+    }
+
+    pub(crate) fn package_record_bytes(algorithm: HashAlgorithm, content_bytes: &[u8]) -> Self {
         let prefix: &[u8] = b"WARG-PACKAGE-LOG-RECORD-V0:".as_slice();
-        let hash: Hash<D> = Hash::of((prefix, record.content_bytes()));
-        Self(hash.into())
+        Self(AnyHash::of(algorithm, (prefix, content_bytes)))
     }
 }
 
@@ -315,9 +384,49 @@ impl AsRef<[u8]> for RecordId {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use warg_crypto::hash::Sha256;
+    use warg_crypto::hash::{Hash, Sha256, Sha512};
     use warg_transparency::map::Map;
 
+    #[test]
+    fn ids_match_for_each_algorithm() {
+        let name = PackageName::new("test:package").unwrap();
+        let hash: Hash<Sha256> = Hash::of((b"WARG-PACKAGE-LOG-ID-V0:".as_slice(), &name));
+        assert_eq!(LogId::package_log::<Sha256>(&name), LogId(hash.into()));
+
+        let sha512 = LogId::package_log::<Sha512>(&name);
+        assert_eq!(sha512.algorithm(), HashAlgorithm::Sha512);
+        assert_eq!(sha512, LogId::package_log_for(HashAlgorithm::Sha512, &name));
+        assert_ne!(sha512, LogId::package_log::<Sha256>(&name));
+        assert_eq!(
+            LogId::operator_log::<Sha512>(),
+            LogId::operator_log_for(HashAlgorithm::Sha512)
+        );
+    }
+
+    #[test]
+    fn checkpoint_expiry_is_signed() {
+        let checkpoint = Checkpoint {
+            log_root: Hash::<Sha256>::of("log").into(),
+            log_length: 1,
+            map_root: Hash::<Sha256>::of("map").into(),
+        };
+        let time = std::time::UNIX_EPOCH + Duration::from_secs(1000);
+        let ts_checkpoint = TimestampedCheckpoint::new(checkpoint, time).unwrap();
+        assert!(!ts_checkpoint.is_expired_at(time + Duration::from_secs(u32::MAX as u64)));
+
+        let expiring = ts_checkpoint.clone().with_validity(Duration::from_secs(60));
+        assert_eq!(expiring.expires, Some(1060));
+        assert!(!expiring.is_expired_at(time + Duration::from_secs(60)));
+        assert!(expiring.is_expired_at(time + Duration::from_secs(61)));
+
+        let extended = TimestampedCheckpoint {
+            expires: Some(2000),
+            ..expiring.clone()
+        };
+        assert_ne!(ts_checkpoint.encode(), expiring.encode());
+        assert_ne!(expiring.encode(), extended.encode());
+    }
+
     #[test]
     fn log_id() {
         let first = Map::<Sha256, LogId, &'static str>::default();
@@ -1,4 +1,22 @@
-fn main() {
+fn main() -> anyhow::Result<()> {
     // Re-run diesel_migrations::embed_migrations! on change
-    println!("cargo:rerun-if-changed=src/datastore/postgres/migrations")
+    println!("cargo:rerun-if-changed=src/datastore/postgres/migrations");
+
+    #[cfg(feature = "grpc")]
+    compile_replication_protocol()?;
+
+    Ok(())
+}
+
+#[cfg(feature = "grpc")]
+fn compile_replication_protocol() -> anyhow::Result<()> {
+    let proto_file = "proto/warg/replication/replication.proto";
+    println!("cargo:rerun-if-changed={proto_file}");
+
+    let file_descriptor_set = protox::compile([proto_file], ["proto"])?;
+    prost_build::Config::new()
+        .service_generator(tonic_build::configure().service_generator())
+        .compile_fds(file_descriptor_set)?;
+
+    Ok(())
 }
@@ -3,33 +3,39 @@ use std::time::SystemTime;
 use anyhow::Context;
 use axum::{
     debug_handler,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     routing::get,
     Router,
 };
-use serde::Serialize;
-use warg_crypto::{
-    hash::{AnyHash, Sha256},
-    signing::KeyID,
-};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use warg_crypto::{hash::AnyHash, signing::KeyID};
 use warg_protocol::{
     package::{LogState, Permission, Release},
     registry::{LogId, PackageName, RecordId},
     Version,
 };
 
-use crate::{api::v1::Json, services::CoreService};
+use crate::{
+    api::v1::Json,
+    services::{today, CoreService},
+};
+use warg_api::v1::package::DownloadCounts;
 
 #[derive(Clone)]
 pub struct Config {
     core_service: CoreService,
+    download_stats: bool,
 }
 
 impl Config {
-    pub fn new(core_service: CoreService) -> Self {
-        Self { core_service }
+    pub fn new(core_service: CoreService, download_stats: bool) -> Self {
+        Self {
+            core_service,
+            download_stats,
+        }
     }
 
     pub fn into_router(self) -> Router {
@@ -40,16 +46,64 @@ impl Config {
     }
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListPackagesQuery {
+    #[serde(default)]
+    downloads: bool,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum PackageListing {
+    Name(PackageName),
+    #[serde(rename_all = "camelCase")]
+    WithDownloads {
+        name: PackageName,
+        downloads: DownloadCounts,
+    },
+}
+
 #[debug_handler]
 async fn list_package_names(
     State(config): State<Config>,
-) -> Result<Json<Vec<PackageName>>, DebugError> {
-    let names = config
-        .core_service
-        .store()
-        .debug_list_package_names()
-        .await?;
-    Ok(Json(names))
+    Query(query): Query<ListPackagesQuery>,
+) -> Result<Json<Vec<PackageListing>>, DebugError> {
+    let store = config.core_service.store();
+    let operator = store
+        .get_operator_state(&config.core_service.operator_log_id())
+        .await
+        .context("get_operator_state")?;
+
+    // Suppressed packages are not listed
+    let mut names = store.debug_list_package_names().await?;
+    names.retain(|name| {
+        operator
+            .package_suppression(&config.core_service.package_log_id(name))
+            .is_none()
+    });
+
+    if !query.downloads || !config.download_stats {
+        return Ok(Json(names.into_iter().map(PackageListing::Name).collect()));
+    }
+
+    let today = today();
+    let mut listings = Vec::with_capacity(names.len());
+    for name in names {
+        let mut downloads = DownloadCounts::default();
+        for counts in store
+            .get_download_counts(&name, today)
+            .await
+            .context("get_download_counts")?
+            .values()
+        {
+            downloads += *counts;
+        }
+
+        listings.push(PackageListing::WithDownloads { name, downloads });
+    }
+
+    Ok(Json(listings))
 }
 
 #[derive(Serialize)]
@@ -83,6 +137,8 @@ struct EntryInfo {
     version: Option<Version>,
     #[serde(skip_serializing_if = "Option::is_none")]
     content: Option<AnyHash>,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    artifacts: IndexMap<String, AnyHash>,
 }
 
 #[debug_handler]
@@ -98,11 +154,20 @@ async fn get_package_info(
         .context("get_latest_checkpoint")?;
     let checkpoint_log_length = checkpoint.as_ref().checkpoint.log_length;
 
-    let log_id = LogId::package_log::<Sha256>(&package_name);
-    let records = store
-        .get_package_records(&log_id, checkpoint_log_length, None, u16::MAX)
-        .await
-        .context("get_package_records")?;
+    let log_id = config.core_service.package_log_id(&package_name);
+    let mut records = Vec::new();
+    let mut since = None;
+    loop {
+        let page = store
+            .get_package_records(&log_id, checkpoint_log_length, since.as_ref(), u16::MAX)
+            .await
+            .context("get_package_records")?;
+        records.extend(page.records);
+        match page.next {
+            Some(next) => since = Some(next),
+            None => break,
+        }
+    }
 
     let mut package_state = LogState::new();
 
@@ -111,7 +176,10 @@ async fn get_package_info(
         .map(|record| {
             let state = std::mem::take(&mut package_state);
             package_state = state.validate(&record.envelope).context("validate")?;
-            let record_id = RecordId::package_record::<Sha256>(&record.envelope);
+            let record_id = RecordId::package_record_for(
+                config.core_service.hash_algorithm(),
+                &record.envelope,
+            );
             let timestamp = record
                 .envelope
                 .as_ref()
@@ -147,10 +215,21 @@ async fn get_package_info(
                             permissions: permissions.clone(),
                             ..Default::default()
                         },
-                        Release { version, content } => EntryInfo {
+                        RevokeKey { key_id, .. } => EntryInfo {
+                            kind: "revoke-key",
+                            key_id: Some(key_id.clone()),
+                            ..Default::default()
+                        },
+                        Release {
+                            version,
+                            content,
+                            artifacts,
+                            ..
+                        } => EntryInfo {
                             kind: "release",
                             version: Some(version.clone()),
                             content: Some(content.clone()),
+                            artifacts: artifacts.clone(),
                             ..Default::default()
                         },
                         Yank { version } => EntryInfo {
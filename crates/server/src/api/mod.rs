@@ -1,54 +1,124 @@
 use crate::{
-    policy::{content::ContentPolicy, record::RecordPolicy},
-    services::CoreService,
+    audit::AuditLog,
+    content::{ContentStorage, ContentStoreError, EncryptionError, UploadSessions},
+    limits::SharedLimits,
+    metrics::{Metrics, METRICS_CONTENT_TYPE},
+    policy::{
+        content::{ContentPolicy, ContentSourcePolicy},
+        record::{RecordPolicy, ReleaseArtifactPolicy, TimestampSkewPolicy},
+    },
+    services::{
+        ContentCollector, CoreService, DownloadCounter, MirrorStatus, MirrorSync, WebhookDispatcher,
+    },
 };
-use axum::{body::Body, http::Request, Router};
-use std::{path::PathBuf, sync::Arc};
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Path, State},
+    http::{header, HeaderMap, Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use futures::TryStreamExt;
+use secrecy::SecretString;
+use serde::Serialize;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tower::ServiceBuilder;
 use tower_http::{
     cors::{Any, CorsLayer},
-    services::ServeDir,
-    trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer},
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::{DefaultOnResponse, TraceLayer},
     LatencyUnit,
 };
 use tracing::{Level, Span};
 use url::Url;
+use warg_api::v1::{API_VERSION, REQUEST_ID_HEADER_NAME};
+use warg_protocol::registry::RegistryLen;
 
 pub mod v1;
 
 #[cfg(feature = "debug")]
 pub mod debug;
 
+/// The services and policies the API is served with.
+pub struct Config {
+    /// The base URL of the content served by the registry.
+    pub content_base_url: Url,
+    /// The core service of the registry.
+    pub core: CoreService,
+    /// The sessions of content being uploaded.
+    pub uploads: Arc<UploadSessions>,
+    /// The store of content.
+    pub content_store: ContentStorage,
+    /// The policy applied to uploaded content, if any.
+    pub content_policy: Option<Arc<dyn ContentPolicy>>,
+    /// The policy for the sources of content in submitted records.
+    pub content_source_policy: ContentSourcePolicy,
+    /// The policy applied to submitted records, if any.
+    pub record_policy: Option<Arc<dyn RecordPolicy>>,
+    /// The policy for the artifacts of releases.
+    pub artifact_policy: ReleaseArtifactPolicy,
+    /// The policy for the timestamps of submitted records, if any.
+    pub timestamp_policy: Option<TimestampSkewPolicy>,
+    /// The record limits and submission rate limit in effect.
+    pub limits: SharedLimits,
+    /// The audit log to append operations to, if any.
+    pub audit_log: Option<Arc<dyn AuditLog>>,
+    /// The token that authorizes the admin API; it is not served without one.
+    pub admin_token: Option<SecretString>,
+    /// How long the idempotency keys of record submissions are remembered.
+    pub idempotency_key_ttl: Duration,
+    /// The counter of content downloads, if downloads are counted.
+    pub downloads: Option<Arc<DownloadCounter>>,
+    /// The collector of unreferenced content.
+    pub content_collector: Arc<ContentCollector>,
+    /// The dispatcher of webhook events, if any webhooks are configured.
+    pub webhooks: Option<Arc<WebhookDispatcher>>,
+    /// The sync of the upstream registry, if the registry is a mirror.
+    pub mirror: Option<Arc<MirrorSync>>,
+}
+
 /// Creates the router for the API.
-pub fn create_router(
-    content_base_url: Url,
-    core: CoreService,
-    temp_dir: PathBuf,
-    files_dir: PathBuf,
-    content_policy: Option<Arc<dyn ContentPolicy>>,
-    record_policy: Option<Arc<dyn RecordPolicy>>,
-) -> Router {
+pub fn create_router(config: Config) -> Router {
+    let core = config.core.clone();
     let router = Router::new();
     #[cfg(feature = "debug")]
-    let router = router.nest("/debug", debug::Config::new(core.clone()).into_router());
+    let router = router.nest(
+        "/debug",
+        debug::Config::new(core.clone(), config.downloads.is_some()).into_router(),
+    );
+
+    // Content is served from the content store, which may not be local
+    let content = Router::new()
+        .route("/:file_name", get(get_content))
+        .with_state((config.content_store.clone(), core.metrics().clone()));
+
+    let content = match &config.downloads {
+        Some(downloads) => content.layer(middleware::from_fn_with_state(
+            downloads.clone(),
+            count_download,
+        )),
+        None => content,
+    };
+
     router
-        .nest(
-            "/v1",
-            v1::create_router(
-                content_base_url,
-                core,
-                temp_dir,
-                files_dir.clone(),
-                content_policy,
-                record_policy,
-            ),
+        .route("/metrics", get(get_metrics).with_state(core.clone()))
+        .route(
+            "/health",
+            get(get_health).with_state((core, config.mirror.clone())),
         )
-        .nest_service("/content", ServeDir::new(files_dir))
+        .nest("/content", content)
+        .nest(&format!("/{API_VERSION}"), v1::create_router(config))
         .layer(
             ServiceBuilder::new()
+                // Requests without an ID are assigned one, which is returned
+                // to the client and recorded in the request's span
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(PropagateRequestIdLayer::x_request_id())
                 .layer(
                     TraceLayer::new_for_http()
-                        .make_span_with(DefaultMakeSpan::new().include_headers(true))
+                        .make_span_with(make_request_span)
                         .on_request(|request: &Request<Body>, _span: &Span| {
                             tracing::info!("starting {} {}", request.method(), request.uri().path())
                         })
@@ -69,3 +139,198 @@ pub fn create_router(
                 ),
         )
 }
+
+/// Creates the span of a request, carrying the request's ID.
+///
+/// Processing a submitted record continues in this span, so the events of a
+/// publish can be correlated by request ID.
+fn make_request_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER_NAME)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        version = ?request.version(),
+        request_id,
+    )
+}
+
+/// Serves the metrics of the registry in the Prometheus text format.
+async fn get_metrics(State(core): State<CoreService>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, METRICS_CONTENT_TYPE)],
+        core.metrics().render(),
+    )
+}
+
+/// The health of the registry.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Health {
+    status: &'static str,
+    log_length: RegistryLen,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mirror: Option<MirrorStatus>,
+}
+
+/// Serves the health of the registry, including the sync progress of a mirror.
+async fn get_health(
+    State((core, mirror)): State<(CoreService, Option<Arc<MirrorSync>>)>,
+) -> impl IntoResponse {
+    axum::Json(Health {
+        status: "ok",
+        log_length: core.log_length().await,
+        mirror: mirror.map(|mirror| mirror.status()),
+    })
+}
+
+/// Parses a single `bytes` range header value into a `start..end` range.
+///
+/// Returns `Ok(None)` if the header is absent or not a single byte range,
+/// in which case the entire content is served.
+fn parse_range(headers: &HeaderMap, len: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some(range) = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("bytes="))
+    else {
+        return Ok(None);
+    };
+
+    if range.contains(',') {
+        return Ok(None);
+    }
+
+    let (start, end) = range.split_once('-').ok_or(())?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().map_err(|_| ())?;
+            (len.saturating_sub(suffix), len)
+        }
+        (start, "") => (start.parse().map_err(|_| ())?, len),
+        (start, end) => (
+            start.parse().map_err(|_| ())?,
+            end.parse::<u64>()
+                .map_err(|_| ())?
+                .saturating_add(1)
+                .min(len),
+        ),
+    };
+
+    if start >= end {
+        return Err(());
+    }
+
+    Ok(Some((start, end)))
+}
+
+/// Counts successful downloads of content.
+///
+/// Range requests are only counted when they start at the beginning of the
+/// content so that a download fetched in fragments is counted once.
+async fn count_download(
+    State(downloads): State<Arc<DownloadCounter>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let digest = request
+        .uri()
+        .path()
+        .rsplit('/')
+        .next()
+        .and_then(|file_name| file_name.replacen('-', ":", 1).parse().ok());
+    let source = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let initial = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().starts_with("bytes=0-"))
+        .unwrap_or(true);
+
+    let response = next.run(request).await;
+
+    if let Some(digest) = digest {
+        if initial && response.status().is_success() {
+            downloads.count_content(source, &digest);
+        }
+    }
+
+    response
+}
+
+async fn get_content(
+    State((store, metrics)): State<(ContentStorage, Arc<Metrics>)>,
+    Path(file_name): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(digest) = ContentStorage::digest_of(&file_name) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let len = match store.len(&digest).await {
+        Ok(len) => len,
+        Err(ContentStoreError::ContentNotFound(_)) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            tracing::error!("failed to open content `{digest}`: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let Ok(range) = parse_range(&headers, len) else {
+        return (
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [(header::CONTENT_RANGE, format!("bytes */{len}"))],
+        )
+            .into_response();
+    };
+
+    match store.open(&digest, range).await {
+        Ok((_, stream)) => {
+            // Only the bytes actually sent are counted, so a download that is
+            // interrupted and resumed is not counted twice
+            let body = Body::from_stream(
+                stream.inspect_ok(move |chunk| metrics.content_served(chunk.len() as u64)),
+            );
+            match range {
+                Some((start, end)) => (
+                    StatusCode::PARTIAL_CONTENT,
+                    [
+                        (header::CONTENT_LENGTH, (end - start).to_string()),
+                        (
+                            header::CONTENT_RANGE,
+                            format!("bytes {start}-{last}/{len}", last = end - 1),
+                        ),
+                        (header::ACCEPT_RANGES, "bytes".to_string()),
+                    ],
+                    body,
+                )
+                    .into_response(),
+                None => (
+                    StatusCode::OK,
+                    [
+                        (header::CONTENT_LENGTH, len.to_string()),
+                        (header::ACCEPT_RANGES, "bytes".to_string()),
+                    ],
+                    body,
+                )
+                    .into_response(),
+            }
+        }
+        Err(ContentStoreError::Encryption(EncryptionError::InvalidRange { .. })) => (
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [(header::CONTENT_RANGE, format!("bytes */{len}"))],
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("failed to open content `{digest}`: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use super::{ApiError, Json, Path, Query, RequestContext};
+use crate::{
+    audit::{self, AuditFilter, AuditLog, AuditLogError},
+    datastore::DataStoreError,
+    services::{
+        ContentCollector, ContentGcError, CoreService, CoreServiceError, WebhookDispatcher,
+        WebhookError,
+    },
+};
+use axum::{
+    debug_handler,
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post, put},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, SecretString};
+use std::sync::Arc;
+use warg_api::v1::admin::{
+    AdminError, AuditEntriesQuery, AuditEntriesResponse, AuditEntry, AuditOperation,
+    CompactRecordsRequest, CompactRecordsResponse, ContentGcResponse, PruneRecordsRequest,
+    PruneRecordsResponse, RejectRecordRequest, RejectRecordResponse, SubmissionQueueResponse,
+    SuppressPackageRequest, SuppressionResponse, WebhookDeliveriesQuery, WebhookDeliveriesResponse,
+    WebhookDelivery,
+};
+use warg_protocol::{
+    operator::OperatorEntry,
+    registry::{LogId, PackageName, RecordId},
+};
+
+const DEFAULT_AUDIT_ENTRIES_LIMIT: u16 = 100;
+const MAX_AUDIT_ENTRIES_LIMIT: u16 = 1000;
+const DEFAULT_WEBHOOK_DELIVERIES_LIMIT: u16 = 100;
+const MAX_WEBHOOK_DELIVERIES_LIMIT: u16 = 1000;
+
+#[derive(Clone)]
+pub struct Config {
+    core_service: CoreService,
+    token: Arc<SecretString>,
+    audit_log: Option<Arc<dyn AuditLog>>,
+    content_collector: Arc<ContentCollector>,
+    webhooks: Option<Arc<WebhookDispatcher>>,
+}
+
+impl Config {
+    pub fn new(
+        core_service: CoreService,
+        token: SecretString,
+        audit_log: Option<Arc<dyn AuditLog>>,
+        content_collector: Arc<ContentCollector>,
+        webhooks: Option<Arc<WebhookDispatcher>>,
+    ) -> Self {
+        Self {
+            core_service,
+            token: Arc::new(token),
+            audit_log,
+            content_collector,
+            webhooks,
+        }
+    }
+
+    pub fn into_router(self) -> Router {
+        Router::new()
+            .route("/audit", get(get_audit_entries))
+            .route("/queue", get(get_submission_queue))
+            .route("/content/gc", post(collect_content))
+            .route("/records/prune", post(prune_records))
+            .route("/records/compact", post(compact_records))
+            .route("/records/:log_id/:record_id/reject", post(reject_record))
+            .route(
+                "/suppressions/:package_name",
+                put(suppress_package).delete(unsuppress_package),
+            )
+            .route("/webhooks/deliveries", get(get_webhook_deliveries))
+            .route(
+                "/webhooks/deliveries/:id/retry",
+                post(retry_webhook_delivery),
+            )
+            .with_state(self)
+    }
+
+    /// Authorizes a request by its bearer token.
+    fn authorize(&self, headers: &HeaderMap) -> Result<(), AdminApiError> {
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or(AdminApiError::Admin(AdminError::Unauthorized))?;
+
+        if !constant_time_eq(
+            token.trim().as_bytes(),
+            self.token.expose_secret().trim().as_bytes(),
+        ) {
+            return Err(AdminApiError::Admin(AdminError::Unauthorized));
+        }
+
+        Ok(())
+    }
+
+    /// Gets the webhook dispatcher, if webhooks are configured.
+    fn webhooks(&self) -> Result<&Arc<WebhookDispatcher>, AdminApiError> {
+        self.webhooks.as_ref().ok_or_else(|| {
+            AdminApiError::Admin(AdminError::Message {
+                status: StatusCode::NOT_IMPLEMENTED.as_u16(),
+                message: "no webhooks are configured".into(),
+            })
+        })
+    }
+
+    /// Publishes an operator record with the given suppression entry,
+    /// auditing the outcome.
+    async fn publish_suppression(
+        &self,
+        mut entry: AuditEntry,
+        name: PackageName,
+        operator_entry: OperatorEntry,
+    ) -> Result<Json<SuppressionResponse>, AdminApiError> {
+        entry.log_id = Some(self.core_service.package_log_id(&name));
+        entry.package = Some(name);
+
+        match self
+            .core_service
+            .publish_operator_entries(vec![operator_entry])
+            .await
+        {
+            Ok(record_id) => {
+                entry.record_id = Some(record_id.clone());
+                audit::append(self.audit_log.as_deref(), entry).await;
+                Ok(Json(SuppressionResponse { record_id }))
+            }
+            Err(e) => {
+                audit::append(self.audit_log.as_deref(), entry.failed(&e)).await;
+                Err(e.into())
+            }
+        }
+    }
+}
+
+/// Compares two byte strings in time independent of their contents.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+enum AdminApiError {
+    Admin(AdminError),
+    DataStore(ApiError),
+}
+
+impl From<AuditLogError> for AdminApiError {
+    fn from(e: AuditLogError) -> Self {
+        tracing::error!("unexpected audit log error: {e}");
+
+        Self::Admin(AdminError::Message {
+            status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            message: "an error occurred while processing the request".into(),
+        })
+    }
+}
+
+impl From<ContentGcError> for AdminApiError {
+    fn from(e: ContentGcError) -> Self {
+        tracing::error!("unexpected content collection error: {e}");
+
+        Self::Admin(AdminError::Message {
+            status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            message: "an error occurred while processing the request".into(),
+        })
+    }
+}
+
+impl From<WebhookError> for AdminApiError {
+    fn from(e: WebhookError) -> Self {
+        let status = match e {
+            WebhookError::DeliveryNotFound(_) => StatusCode::NOT_FOUND,
+            WebhookError::DeliveryNotFailed(_) => StatusCode::CONFLICT,
+        };
+
+        Self::Admin(AdminError::Message {
+            status: status.as_u16(),
+            message: e.to_string(),
+        })
+    }
+}
+
+impl From<DataStoreError> for AdminApiError {
+    fn from(e: DataStoreError) -> Self {
+        Self::DataStore(e.into())
+    }
+}
+
+impl From<CoreServiceError> for AdminApiError {
+    fn from(e: CoreServiceError) -> Self {
+        match e {
+            CoreServiceError::DataStore(DataStoreError::OperatorValidationFailed(e)) => {
+                Self::Admin(AdminError::Message {
+                    status: StatusCode::CONFLICT.as_u16(),
+                    message: e.to_string(),
+                })
+            }
+            CoreServiceError::DataStore(e) => Self::DataStore(e.into()),
+            e => {
+                tracing::error!("unexpected core service error: {e}");
+
+                Self::Admin(AdminError::Message {
+                    status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                    message: "an error occurred while processing the request".into(),
+                })
+            }
+        }
+    }
+}
+
+impl IntoResponse for AdminApiError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::Admin(e) => (StatusCode::from_u16(e.status()).unwrap(), Json(e)).into_response(),
+            Self::DataStore(e) => e.into_response(),
+        }
+    }
+}
+
+#[debug_handler]
+async fn get_audit_entries(
+    State(config): State<Config>,
+    headers: HeaderMap,
+    Query(query): Query<AuditEntriesQuery>,
+) -> Result<Json<AuditEntriesResponse>, AdminApiError> {
+    config.authorize(&headers)?;
+
+    let audit_log = config.audit_log.as_ref().ok_or_else(|| {
+        AdminApiError::Admin(AdminError::Message {
+            status: StatusCode::NOT_IMPLEMENTED.as_u16(),
+            message: "the audit log is not enabled".into(),
+        })
+    })?;
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_AUDIT_ENTRIES_LIMIT)
+        .min(MAX_AUDIT_ENTRIES_LIMIT);
+    let filter = AuditFilter::new(
+        query
+            .package
+            .as_ref()
+            .map(|name| config.core_service.package_log_id(name)),
+        query.key_id,
+    );
+    let entries = audit_log.recent(&filter, limit as usize).await?;
+
+    Ok(Json(AuditEntriesResponse { entries }))
+}
+
+#[debug_handler]
+async fn get_submission_queue(
+    State(config): State<Config>,
+    headers: HeaderMap,
+) -> Result<Json<SubmissionQueueResponse>, AdminApiError> {
+    config.authorize(&headers)?;
+    Ok(Json(config.core_service.submission_queue()))
+}
+
+#[debug_handler]
+async fn collect_content(
+    State(config): State<Config>,
+    headers: HeaderMap,
+) -> Result<Json<ContentGcResponse>, AdminApiError> {
+    config.authorize(&headers)?;
+
+    let removed = config.content_collector.collect().await?;
+    tracing::info!("removed {removed} unreferenced content file(s)");
+    Ok(Json(ContentGcResponse {
+        removed: removed as u64,
+    }))
+}
+
+#[debug_handler]
+async fn prune_records(
+    State(config): State<Config>,
+    headers: HeaderMap,
+    Json(body): Json<PruneRecordsRequest>,
+) -> Result<Json<PruneRecordsResponse>, AdminApiError> {
+    config.authorize(&headers)?;
+
+    let before = i64::try_from(body.before)
+        .ok()
+        .and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0))
+        .ok_or_else(|| {
+            AdminApiError::Admin(AdminError::Message {
+                status: StatusCode::BAD_REQUEST.as_u16(),
+                message: format!("invalid timestamp `{before}`", before = body.before),
+            })
+        })?;
+
+    let removed = config.core_service.store().prune_rejected(before).await?;
+    tracing::info!("pruned {removed} rejected record(s)");
+    Ok(Json(PruneRecordsResponse {
+        removed: removed as u64,
+    }))
+}
+
+#[debug_handler]
+async fn compact_records(
+    State(config): State<Config>,
+    headers: HeaderMap,
+    Json(body): Json<CompactRecordsRequest>,
+) -> Result<Json<CompactRecordsResponse>, AdminApiError> {
+    config.authorize(&headers)?;
+
+    let keep_last = usize::try_from(body.keep_last).unwrap_or(usize::MAX);
+    let compacted = config
+        .core_service
+        .store()
+        .compact_published(keep_last)
+        .await?;
+    tracing::info!("compacted {compacted} published record(s)");
+    Ok(Json(CompactRecordsResponse {
+        compacted: compacted as u64,
+    }))
+}
+
+#[debug_handler]
+async fn reject_record(
+    State(config): State<Config>,
+    headers: HeaderMap,
+    Path((log_id, record_id)): Path<(LogId, RecordId)>,
+    context: RequestContext,
+    Json(body): Json<RejectRecordRequest>,
+) -> Result<Json<RejectRecordResponse>, AdminApiError> {
+    config.authorize(&headers)?;
+
+    if body.reason.trim().is_empty() {
+        return Err(AdminApiError::Admin(AdminError::Message {
+            status: StatusCode::BAD_REQUEST.as_u16(),
+            message: "a reason for rejecting the record is required".into(),
+        }));
+    }
+
+    let store = config.core_service.store();
+    let mut entry = context.audit_entry(AuditOperation::RecordRejected);
+    entry.package = audit::package_name(store, &log_id).await;
+    entry.key_id = store
+        .get_package_record(&log_id, &record_id)
+        .await
+        .ok()
+        .map(|record| record.envelope.key_id().clone());
+    entry.log_id = Some(log_id.clone());
+    entry.record_id = Some(record_id.clone());
+
+    match config
+        .core_service
+        .reject_package_record(&log_id, &record_id, &body.reason)
+        .await
+    {
+        Ok(()) => {
+            tracing::info!(
+                "rejected record `{record_id}`: {reason}",
+                reason = body.reason
+            );
+            entry.reason = Some(body.reason);
+            audit::append(config.audit_log.as_deref(), entry).await;
+            Ok(Json(RejectRecordResponse { record_id }))
+        }
+        Err(e) => {
+            audit::append(config.audit_log.as_deref(), entry.failed(&e)).await;
+            Err(e.into())
+        }
+    }
+}
+
+#[debug_handler]
+async fn suppress_package(
+    State(config): State<Config>,
+    headers: HeaderMap,
+    Path(package_name): Path<PackageName>,
+    context: RequestContext,
+    Json(body): Json<SuppressPackageRequest>,
+) -> Result<Json<SuppressionResponse>, AdminApiError> {
+    config.authorize(&headers)?;
+
+    if body.reason.trim().is_empty() {
+        return Err(AdminApiError::Admin(AdminError::Message {
+            status: StatusCode::BAD_REQUEST.as_u16(),
+            message: "a reason for suppressing the package is required".into(),
+        }));
+    }
+
+    let log_id = config.core_service.package_log_id(&package_name);
+    config
+        .publish_suppression(
+            context.audit_entry(AuditOperation::PackageSuppressed),
+            package_name,
+            OperatorEntry::SuppressPackage {
+                log_id,
+                reason: body.reason,
+            },
+        )
+        .await
+}
+
+#[debug_handler]
+async fn unsuppress_package(
+    State(config): State<Config>,
+    headers: HeaderMap,
+    Path(package_name): Path<PackageName>,
+    context: RequestContext,
+) -> Result<Json<SuppressionResponse>, AdminApiError> {
+    config.authorize(&headers)?;
+
+    let log_id = config.core_service.package_log_id(&package_name);
+    config
+        .publish_suppression(
+            context.audit_entry(AuditOperation::PackageUnsuppressed),
+            package_name,
+            OperatorEntry::UnsuppressPackage { log_id },
+        )
+        .await
+}
+
+#[debug_handler]
+async fn get_webhook_deliveries(
+    State(config): State<Config>,
+    headers: HeaderMap,
+    Query(query): Query<WebhookDeliveriesQuery>,
+) -> Result<Json<WebhookDeliveriesResponse>, AdminApiError> {
+    config.authorize(&headers)?;
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_WEBHOOK_DELIVERIES_LIMIT)
+        .min(MAX_WEBHOOK_DELIVERIES_LIMIT);
+    let deliveries = config.webhooks()?.deliveries(query.status, limit as usize);
+    Ok(Json(WebhookDeliveriesResponse { deliveries }))
+}
+
+#[debug_handler]
+async fn retry_webhook_delivery(
+    State(config): State<Config>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<WebhookDelivery>, AdminApiError> {
+    config.authorize(&headers)?;
+
+    let delivery = config.webhooks()?.retry(&id)?;
+    tracing::info!("retrying webhook delivery `{id}`");
+    Ok(Json(delivery))
+}
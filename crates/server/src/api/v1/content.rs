@@ -1,9 +1,9 @@
 use super::{Json, Path, RegistryHeader};
+use crate::content::{ContentStorage, ContentStoreError};
 use axum::{
     debug_handler, extract::State, http::StatusCode, response::IntoResponse, routing::get, Router,
 };
 use indexmap::IndexMap;
-use std::path::PathBuf;
 use url::Url;
 use warg_api::v1::content::{ContentError, ContentSource, ContentSourcesResponse};
 use warg_crypto::hash::AnyHash;
@@ -11,14 +11,14 @@ use warg_crypto::hash::AnyHash;
 #[derive(Clone)]
 pub struct Config {
     content_base_url: Url,
-    files_dir: PathBuf,
+    content_store: ContentStorage,
 }
 
 impl Config {
-    pub fn new(content_base_url: Url, files_dir: PathBuf) -> Self {
+    pub fn new(content_base_url: Url, content_store: ContentStorage) -> Self {
         Self {
             content_base_url,
-            files_dir,
+            content_store,
         }
     }
 
@@ -28,23 +28,11 @@ impl Config {
             .with_state(self)
     }
 
-    fn content_present(&self, digest: &AnyHash) -> bool {
-        self.content_path(digest).is_file()
-    }
-
-    fn content_file_name(&self, digest: &AnyHash) -> String {
-        digest.to_string().replace(':', "-")
-    }
-
-    fn content_path(&self, digest: &AnyHash) -> PathBuf {
-        self.files_dir.join(self.content_file_name(digest))
-    }
-
     fn content_url(&self, digest: &AnyHash) -> String {
         self.content_base_url
             .join("content/")
             .unwrap()
-            .join(&self.content_file_name(digest))
+            .join(&ContentStorage::file_name(digest))
             .unwrap()
             .to_string()
     }
@@ -64,9 +52,19 @@ async fn get_content(
     Path(digest): Path<AnyHash>,
     RegistryHeader(_registry_header): RegistryHeader,
 ) -> Result<Json<ContentSourcesResponse>, ContentApiError> {
-    if !config.content_present(&digest) {
-        return Err(ContentApiError(ContentError::ContentDigestNotFound(digest)));
-    }
+    let len = match config.content_store.len(&digest).await {
+        Ok(len) => len,
+        Err(ContentStoreError::ContentNotFound(_)) => {
+            return Err(ContentApiError(ContentError::ContentDigestNotFound(digest)))
+        }
+        Err(e) => {
+            tracing::error!("failed to find content `{digest}`: {e}");
+            return Err(ContentApiError(ContentError::Message {
+                status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                message: "an error occurred while processing the request".into(),
+            }));
+        }
+    };
 
     let mut content_sources = IndexMap::with_capacity(1);
     let url = config.content_url(&digest);
@@ -74,8 +72,8 @@ async fn get_content(
         digest,
         vec![ContentSource::HttpGet {
             url,
-            accept_ranges: false,
-            size: None,
+            accept_ranges: true,
+            size: Some(len),
         }],
     );
 
@@ -1,28 +1,45 @@
-use super::{Json, RegistryHeader};
-use crate::datastore::DataStoreError;
+use super::{ApiError, Json, Path, Query, RegistryHeader};
+use crate::datastore::{DataStoreError, RecordStream};
 use crate::services::CoreService;
-use axum::http::StatusCode;
+use axum::http::{header, HeaderMap, StatusCode};
 use axum::{
+    body::Body,
     debug_handler,
     extract::State,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     routing::{get, post},
     Router,
 };
+use futures::{stream, StreamExt, TryStreamExt};
 use indexmap::IndexMap;
+use std::{convert::Infallible, time::Duration};
+use tokio::sync::mpsc;
+use tracing::Instrument;
 use warg_api::v1::fetch::{
-    FetchError, FetchLogsRequest, FetchLogsResponse, FetchPackageNamesRequest,
-    FetchPackageNamesResponse, PublishedRecord,
+    CheckpointsQuery, CheckpointsResponse, FetchCheckpointQuery, FetchError, FetchLogsRequest,
+    FetchLogsResponse, FetchLogsStreamItem, FetchPackageNamesRequest, FetchPackageNamesResponse,
+    PublishedRecord, FETCH_LOGS_STREAM_MEDIA_TYPE,
 };
-use warg_crypto::hash::{AnyHash, Sha256};
-use warg_protocol::registry::{LogId, RecordId, TimestampedCheckpoint};
-use warg_protocol::SerdeEnvelope;
+use warg_crypto::hash::AnyHash;
+use warg_protocol::registry::{RecordId, TimestampedCheckpoint};
+use warg_protocol::{PublishedProtoEnvelope, SerdeEnvelope};
 
 const DEFAULT_RECORDS_LIMIT: u16 = 100;
 const MAX_RECORDS_LIMIT: u16 = 1000;
+const DEFAULT_TOTAL_RECORDS_LIMIT: u32 = 5000;
+const MAX_TOTAL_RECORDS_LIMIT: u32 = 10000;
 
 const MAX_PACKAGE_NAMES_LIMIT: usize = 1000;
 
+/// The number of lines buffered for a streamed fetch logs response.
+const STREAM_BUFFER: usize = 16;
+
+const DEFAULT_CHECKPOINTS_LIMIT: u16 = 100;
+const MAX_CHECKPOINTS_LIMIT: u16 = 1000;
+
+const DEFAULT_CHECKPOINT_WAIT_TIMEOUT: u64 = 30;
+const MAX_CHECKPOINT_WAIT_TIMEOUT: u64 = 60;
+
 #[derive(Clone)]
 pub struct Config {
     core_service: CoreService,
@@ -36,17 +53,27 @@ impl Config {
     pub fn into_router(self) -> Router {
         Router::new()
             .route("/checkpoint", get(fetch_checkpoint))
+            .route("/checkpoint/:checkpoint_id", get(fetch_checkpoint_by_id))
             .route("/logs", post(fetch_logs))
             .route("/names", post(fetch_package_names))
             .with_state(self)
     }
+
+    pub fn into_checkpoints_router(self) -> Router {
+        Router::new()
+            .route("/", get(list_checkpoints))
+            .with_state(self)
+    }
 }
 
-struct FetchApiError(FetchError);
+enum FetchApiError {
+    Fetch(FetchError),
+    DataStore(ApiError),
+}
 
 impl FetchApiError {
     fn bad_request(message: impl ToString) -> Self {
-        Self(FetchError::Message {
+        Self::Fetch(FetchError::Message {
             status: StatusCode::BAD_REQUEST.as_u16(),
             message: message.to_string(),
         })
@@ -55,38 +82,96 @@ impl FetchApiError {
 
 impl From<DataStoreError> for FetchApiError {
     fn from(e: DataStoreError) -> Self {
-        Self(match e {
+        Self::Fetch(match e {
             DataStoreError::CheckpointNotFound(checkpoint) => {
                 FetchError::CheckpointNotFound(checkpoint)
             }
+            DataStoreError::CheckpointIdNotFound(checkpoint_id) => {
+                FetchError::CheckpointIdNotFound(checkpoint_id)
+            }
             DataStoreError::LogNotFound(log_id) => FetchError::LogNotFound(log_id),
             DataStoreError::RecordNotFound(record_id) => {
                 FetchError::FetchTokenNotFound(record_id.to_string())
             }
-            // Other errors are internal server errors
-            e => {
-                tracing::error!("unexpected data store error: {e}");
-                FetchError::Message {
-                    status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
-                    message: "an error occurred while processing the request".into(),
-                }
-            }
+            e => return Self::DataStore(e.into()),
         })
     }
 }
 
+impl FetchApiError {
+    /// Converts the error into the fetch error sent in a streamed response.
+    fn into_fetch_error(self) -> FetchError {
+        match self {
+            Self::Fetch(e) => e,
+            Self::DataStore(e) => FetchError::Message {
+                status: e.status.as_u16(),
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
 impl IntoResponse for FetchApiError {
     fn into_response(self) -> axum::response::Response {
-        (StatusCode::from_u16(self.0.status()).unwrap(), Json(self.0)).into_response()
+        match self {
+            Self::Fetch(e) => (StatusCode::from_u16(e.status()).unwrap(), Json(e)).into_response(),
+            Self::DataStore(e) => e.into_response(),
+        }
     }
 }
 
+/// Parses a fetch token into the ID of the record it was issued for.
+fn parse_fetch_token(token: Option<String>) -> Result<Option<RecordId>, FetchApiError> {
+    token
+        .map(|token| match token.parse::<AnyHash>() {
+            Ok(hash) => Ok(hash.into()),
+            Err(_) => Err(FetchApiError::Fetch(FetchError::FetchTokenNotFound(token))),
+        })
+        .transpose()
+}
+
+/// Determines if a request accepts a streamed fetch logs response.
+fn accepts_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media_type| {
+            media_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .eq_ignore_ascii_case(FETCH_LOGS_STREAM_MEDIA_TYPE)
+        })
+}
+
+/// Fetches the records of the operator log and the given package logs.
+///
+/// A response holds at most `MAX_RECORDS_LIMIT` records of each log and
+/// `MAX_TOTAL_RECORDS_LIMIT` records in total, and the data store only reads
+/// the requested page of each log, so the memory used to serve a fetch does
+/// not grow with the length of the logs. Clients fetch longer logs a page at
+/// a time, validating each response before requesting the next.
+///
+/// A request accepting newline-delimited JSON is instead answered with a
+/// streamed response; see `stream_logs`.
 #[debug_handler]
+#[tracing::instrument(
+    skip_all,
+    fields(log_length = body.log_length, packages = body.packages.len())
+)]
 async fn fetch_logs(
     State(config): State<Config>,
     RegistryHeader(_registry_header): RegistryHeader,
+    headers: HeaderMap,
     Json(body): Json<FetchLogsRequest<'static>>,
-) -> Result<Json<FetchLogsResponse>, FetchApiError> {
+) -> Result<Response, FetchApiError> {
+    if accepts_stream(&headers) {
+        return stream_logs(config, body).await;
+    }
+
     let limit = body.limit.unwrap_or(DEFAULT_RECORDS_LIMIT);
     if limit == 0 || limit > MAX_RECORDS_LIMIT {
         return Err(FetchApiError::bad_request(format!(
@@ -94,28 +179,41 @@ async fn fetch_logs(
         )));
     }
 
-    let operator_fetch_token: Option<RecordId> = match body.operator {
-        Some(s) => Some(
-            s.parse::<AnyHash>()
-                .map_err(|_| FetchApiError(FetchError::FetchTokenNotFound(s.into_owned())))?
-                .into(),
-        ),
-        None => None,
-    };
-    let operator: Vec<PublishedRecord> = config
+    let total_limit = body.total_limit.unwrap_or(DEFAULT_TOTAL_RECORDS_LIMIT);
+    if total_limit == 0 || total_limit > MAX_TOTAL_RECORDS_LIMIT {
+        return Err(FetchApiError::bad_request(format!(
+            "invalid total records limit value `{total_limit}`: must be between 1 and {MAX_TOTAL_RECORDS_LIMIT}"
+        )));
+    }
+
+    // The number of records that may still be returned across all logs
+    let mut remaining = total_limit;
+    let take = |remaining: u32| limit.min(remaining.try_into().unwrap_or(u16::MAX));
+
+    let operator_fetch_token = parse_fetch_token(body.operator.map(Into::into))?;
+    let page = config
         .core_service
         .store()
         .get_operator_records(
-            &LogId::operator_log::<Sha256>(),
+            &config.core_service.operator_log_id(),
             body.log_length,
             operator_fetch_token.as_ref(),
-            limit,
+            take(remaining),
         )
-        .await?
+        .await?;
+
+    let mut more = page.next.is_some();
+    remaining -= page.records.len() as u32;
+    let operator: Vec<PublishedRecord> = page
+        .records
         .into_iter()
         .map(|envelope| {
             // use the record ID as the fetch token
-            let fetch_token = RecordId::operator_record::<Sha256>(&envelope.envelope).to_string();
+            let fetch_token = RecordId::operator_record_for(
+                config.core_service.hash_algorithm(),
+                &envelope.envelope,
+            )
+            .to_string();
             PublishedRecord {
                 envelope: envelope.into(),
                 fetch_token,
@@ -123,36 +221,41 @@ async fn fetch_logs(
         })
         .collect();
 
-    let mut more = operator.len() == limit as usize;
-
     let mut map = IndexMap::new();
     let packages = body.packages.into_owned();
     for (id, fetch_token) in packages {
-        let since: Option<RecordId> = match fetch_token {
-            Some(s) => Some(
-                s.parse::<AnyHash>()
-                    .map_err(|_| FetchApiError(FetchError::FetchTokenNotFound(s)))?
-                    .into(),
-            ),
-            None => None,
-        };
-        let records: Vec<PublishedRecord> = config
+        // Logs beyond the total limit are left for a subsequent request
+        if remaining == 0 {
+            more = true;
+            break;
+        }
+
+        let since = parse_fetch_token(fetch_token)?;
+        let page = config
             .core_service
             .store()
-            .get_package_records(&id, body.log_length, since.as_ref(), limit)
-            .await?
+            .get_package_records(&id, body.log_length, since.as_ref(), take(remaining))
+            .instrument(tracing::debug_span!("fetch_package_log", log_id = %id))
+            .await?;
+
+        more |= page.next.is_some();
+        remaining -= page.records.len() as u32;
+        let records: Vec<PublishedRecord> = page
+            .records
             .into_iter()
             .map(|envelope| {
                 // use the record ID as the fetch token
-                let fetch_token =
-                    RecordId::package_record::<Sha256>(&envelope.envelope).to_string();
+                let fetch_token = RecordId::package_record_for(
+                    config.core_service.hash_algorithm(),
+                    &envelope.envelope,
+                )
+                .to_string();
                 PublishedRecord {
                     envelope: envelope.into(),
                     fetch_token,
                 }
             })
             .collect();
-        more |= records.len() == limit as usize;
         map.insert(id, records);
     }
 
@@ -160,20 +263,249 @@ async fn fetch_logs(
         more,
         operator,
         packages: map,
-    }))
+    })
+    .into_response())
+}
+
+/// Responds to a fetch logs request with the records as newline-delimited
+/// JSON.
+///
+/// A task reads the records from the data store a page at a time and sends
+/// each line through a bounded channel, so a client reading slowly holds back
+/// the reading of records instead of the records being buffered. As the
+/// memory used does not grow with the number of records, the limits of the
+/// request are unbounded unless given.
+///
+/// An error occurring before the first line is sent is returned as an error
+/// response; an error occurring later ends the response with an error line.
+async fn stream_logs(
+    config: Config,
+    body: FetchLogsRequest<'static>,
+) -> Result<Response, FetchApiError> {
+    if body.limit == Some(0) {
+        return Err(FetchApiError::bad_request(
+            "invalid records limit value `0`: must be at least 1",
+        ));
+    }
+
+    if body.total_limit == Some(0) {
+        return Err(FetchApiError::bad_request(
+            "invalid total records limit value `0`: must be at least 1",
+        ));
+    }
+
+    let (tx, mut rx) = mpsc::channel(STREAM_BUFFER);
+    tokio::spawn(
+        async move {
+            let end = send_logs(&config, body, &tx)
+                .await
+                .map(|more| FetchLogsStreamItem::End { more });
+            let _ = tx.send(end).await;
+        }
+        .in_current_span(),
+    );
+
+    let first = match rx.recv().await {
+        Some(Ok(item)) => item,
+        Some(Err(e)) => return Err(e),
+        // The task panicked before sending a line
+        None => {
+            return Err(FetchApiError::Fetch(FetchError::Message {
+                status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                message: "an error occurred while processing the request".into(),
+            }))
+        }
+    };
+
+    let lines = stream::iter([Ok(first)])
+        .chain(stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|item| (item, rx))
+        }))
+        .map(|item| {
+            let item = item.unwrap_or_else(|e| FetchLogsStreamItem::Error {
+                error: e.into_fetch_error(),
+            });
+            let mut line = serde_json::to_vec(&item).expect("the line should serialize");
+            line.push(b'\n');
+            Ok::<_, Infallible>(line)
+        });
+
+    Ok((
+        [(header::CONTENT_TYPE, FETCH_LOGS_STREAM_MEDIA_TYPE)],
+        Body::from_stream(lines),
+    )
+        .into_response())
+}
+
+/// Sends the lines of the records of a streamed fetch logs response.
+///
+/// Returns whether there are more records to fetch.
+async fn send_logs(
+    config: &Config,
+    body: FetchLogsRequest<'static>,
+    tx: &mpsc::Sender<Result<FetchLogsStreamItem, FetchApiError>>,
+) -> Result<bool, FetchApiError> {
+    let store = config.core_service.store();
+    let algorithm = config.core_service.hash_algorithm();
+    let limit = body.limit.map_or(usize::MAX, usize::from);
+    // The number of records that may still be sent across all logs
+    let mut remaining = body.total_limit.map_or(usize::MAX, |limit| limit as usize);
+
+    let operator_log_id = config.core_service.operator_log_id();
+    let since = parse_fetch_token(body.operator.map(Into::into))?;
+    let mut more = send_records(
+        store.stream_operator_records(&operator_log_id, body.log_length, since),
+        limit,
+        &mut remaining,
+        tx,
+        |record| FetchLogsStreamItem::Operator {
+            record: published_record(
+                RecordId::operator_record_for(algorithm, &record.envelope),
+                record,
+            ),
+        },
+    )
+    .await?;
+
+    for (log_id, fetch_token) in body.packages.into_owned() {
+        // Logs beyond the total limit are left for a subsequent request
+        if remaining == 0 {
+            more = true;
+            break;
+        }
+
+        let since = parse_fetch_token(fetch_token)?;
+        more |= send_records(
+            store.stream_package_records(&log_id, body.log_length, since),
+            limit,
+            &mut remaining,
+            tx,
+            |record| FetchLogsStreamItem::Package {
+                log_id: log_id.clone(),
+                record: published_record(
+                    RecordId::package_record_for(algorithm, &record.envelope),
+                    record,
+                ),
+            },
+        )
+        .instrument(tracing::debug_span!("stream_package_log", %log_id))
+        .await?;
+    }
+
+    Ok(more)
+}
+
+/// Sends a line for each of the given records, up to the given limit of the
+/// log and the remaining limit across all logs.
+///
+/// Returns whether the log has more records than were sent.
+async fn send_records<R>(
+    mut records: RecordStream<'_, R>,
+    limit: usize,
+    remaining: &mut usize,
+    tx: &mpsc::Sender<Result<FetchLogsStreamItem, FetchApiError>>,
+    line: impl Fn(PublishedProtoEnvelope<R>) -> FetchLogsStreamItem,
+) -> Result<bool, FetchApiError> {
+    let mut sent = 0;
+    while let Some(record) = records.try_next().await? {
+        if sent == limit || *remaining == 0 {
+            return Ok(true);
+        }
+
+        // The client stopped reading the response
+        if tx.send(Ok(line(record))).await.is_err() {
+            return Ok(false);
+        }
+
+        sent += 1;
+        *remaining -= 1;
+    }
+
+    Ok(false)
+}
+
+/// Wraps a record with its ID as the fetch token.
+fn published_record<R>(record_id: RecordId, record: PublishedProtoEnvelope<R>) -> PublishedRecord {
+    PublishedRecord {
+        envelope: record.into(),
+        fetch_token: record_id.to_string(),
+    }
 }
 
 #[debug_handler]
+#[tracing::instrument(skip_all, fields(since = ?query.since))]
 async fn fetch_checkpoint(
     State(config): State<Config>,
     RegistryHeader(_registry_header): RegistryHeader,
+    Query(query): Query<FetchCheckpointQuery>,
 ) -> Result<Json<SerdeEnvelope<TimestampedCheckpoint>>, FetchApiError> {
+    if let Some(since) = &query.since {
+        let timeout = query.timeout.unwrap_or(DEFAULT_CHECKPOINT_WAIT_TIMEOUT);
+        if timeout > MAX_CHECKPOINT_WAIT_TIMEOUT {
+            return Err(FetchApiError::bad_request(format!(
+                "invalid timeout value `{timeout}`: must be at most {MAX_CHECKPOINT_WAIT_TIMEOUT}"
+            )));
+        }
+
+        // Wait for a checkpoint other than the given one to be issued; the
+        // latest checkpoint is returned regardless once the timeout elapses
+        let mut checkpoints = config.core_service.subscribe_checkpoints();
+        let _ = tokio::time::timeout(
+            Duration::from_secs(timeout),
+            checkpoints.wait_for(|latest| latest.as_ref().map_or(false, |id| id != since)),
+        )
+        .await;
+    }
+
     Ok(Json(
         config.core_service.store().get_latest_checkpoint().await?,
     ))
 }
 
 #[debug_handler]
+#[tracing::instrument(skip_all, fields(%checkpoint_id))]
+async fn fetch_checkpoint_by_id(
+    State(config): State<Config>,
+    Path(checkpoint_id): Path<AnyHash>,
+    RegistryHeader(_registry_header): RegistryHeader,
+) -> Result<Json<SerdeEnvelope<TimestampedCheckpoint>>, FetchApiError> {
+    Ok(Json(
+        config
+            .core_service
+            .store()
+            .get_checkpoint_by_id(&checkpoint_id)
+            .await?,
+    ))
+}
+
+#[debug_handler]
+#[tracing::instrument(skip_all)]
+async fn list_checkpoints(
+    State(config): State<Config>,
+    RegistryHeader(_registry_header): RegistryHeader,
+    Query(query): Query<CheckpointsQuery>,
+) -> Result<Json<CheckpointsResponse>, FetchApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_CHECKPOINTS_LIMIT);
+    if limit == 0 || limit > MAX_CHECKPOINTS_LIMIT {
+        return Err(FetchApiError::bad_request(format!(
+            "invalid checkpoints limit value `{limit}`: must be between 1 and {MAX_CHECKPOINTS_LIMIT}"
+        )));
+    }
+
+    // Fetch one more checkpoint than requested to determine if there are more
+    let mut checkpoints = config
+        .core_service
+        .store()
+        .get_checkpoints(query.since.as_ref(), limit + 1)
+        .await?;
+    let more = checkpoints.len() > limit as usize;
+    checkpoints.truncate(limit as usize);
+
+    Ok(Json(CheckpointsResponse { checkpoints, more }))
+}
+
+#[debug_handler]
+#[tracing::instrument(skip_all, fields(packages = body.packages.len()))]
 async fn fetch_package_names(
     State(config): State<Config>,
     RegistryHeader(_registry_header): RegistryHeader,
@@ -0,0 +1,127 @@
+use indexmap::IndexMap;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use warg_crypto::hash::AnyHash;
+use warg_protocol::registry::{LogId, RecordId};
+
+/// The default duration for which idempotency keys are remembered.
+pub const DEFAULT_IDEMPOTENCY_KEY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The maximum number of idempotency keys remembered at once.
+///
+/// When exceeded, the oldest keys are forgotten first.
+const MAX_IDEMPOTENCY_KEYS: usize = 10_000;
+
+/// The maximum length of an idempotency key.
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// The outcome of a completed request that is replayed for repeated keys.
+#[derive(Clone)]
+pub enum Outcome {
+    /// The record was accepted by the registry.
+    Accepted(RecordId),
+    /// The request failed with the given serialized error.
+    Failed(serde_json::Value),
+}
+
+/// The result of beginning a request with an idempotency key.
+pub enum Begin {
+    /// The key has not been seen; the request should be performed.
+    New,
+    /// The key has already completed; the outcome should be replayed.
+    Replay(Outcome),
+    /// A request with the key is still in progress.
+    InProgress,
+    /// The key was previously used with a different request.
+    Mismatch,
+}
+
+enum State {
+    InProgress,
+    Completed(Outcome),
+}
+
+struct Entry {
+    created: Instant,
+    fingerprint: AnyHash,
+    state: State,
+}
+
+/// Remembers recent idempotency keys of record submissions.
+///
+/// Keys are scoped to a package log, expire after a time-to-live, and are
+/// bounded in number.
+pub struct IdempotencyCache {
+    ttl: Duration,
+    entries: Mutex<IndexMap<(LogId, String), Entry>>,
+}
+
+impl IdempotencyCache {
+    /// Creates a new cache that remembers keys for the given duration.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Default::default(),
+        }
+    }
+
+    /// Begins a request with the given key and request fingerprint.
+    pub fn begin(&self, log_id: &LogId, key: &str, fingerprint: AnyHash) -> Begin {
+        let mut entries = self.entries.lock().unwrap();
+
+        // Entries are kept in the order they were created, so expired entries are at the front
+        let now = Instant::now();
+        while entries
+            .first()
+            .map(|(_, e)| now.duration_since(e.created) >= self.ttl)
+            .unwrap_or(false)
+        {
+            entries.shift_remove_index(0);
+        }
+
+        let id = (log_id.clone(), key.to_string());
+        if let Some(entry) = entries.get(&id) {
+            if entry.fingerprint != fingerprint {
+                return Begin::Mismatch;
+            }
+
+            return match &entry.state {
+                State::InProgress => Begin::InProgress,
+                State::Completed(outcome) => Begin::Replay(outcome.clone()),
+            };
+        }
+
+        while entries.len() >= MAX_IDEMPOTENCY_KEYS {
+            entries.shift_remove_index(0);
+        }
+
+        entries.insert(
+            id,
+            Entry {
+                created: now,
+                fingerprint,
+                state: State::InProgress,
+            },
+        );
+
+        Begin::New
+    }
+
+    /// Completes a request with the given key, remembering its outcome.
+    pub fn complete(&self, log_id: &LogId, key: &str, outcome: Outcome) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.get_mut(&(log_id.clone(), key.to_string())) {
+            entry.state = State::Completed(outcome);
+        }
+    }
+
+    /// Forgets a request with the given key so that it may be retried.
+    pub fn forget(&self, log_id: &LogId, key: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .shift_remove(&(log_id.clone(), key.to_string()));
+    }
+}
@@ -1,14 +1,7 @@
-use super::{Json, Path, RegistryHeader};
-use crate::datastore::DataStoreError;
+use super::{ApiError, Json, Path, RegistryHeader};
 use crate::services::CoreService;
-use axum::http::StatusCode;
-use axum::{
-    debug_handler, extract::State, response::IntoResponse, response::Response, routing::get, Router,
-};
-use warg_api::v1::ledger::{
-    LedgerError, LedgerSource, LedgerSourceContentType, LedgerSourcesResponse,
-};
-use warg_crypto::hash::HashAlgorithm;
+use axum::{debug_handler, extract::State, response::Response, routing::get, Router};
+use warg_api::v1::ledger::{LedgerSource, LedgerSourceContentType, LedgerSourcesResponse};
 use warg_protocol::registry::RegistryIndex;
 
 const MAX_LEDGER_RECORDS_LIMIT: usize = 1000;
@@ -31,30 +24,11 @@ impl Config {
     }
 }
 
-struct LedgerApiError(LedgerError);
-
-impl From<DataStoreError> for LedgerApiError {
-    fn from(e: DataStoreError) -> Self {
-        tracing::error!("unexpected data store error: {e}");
-
-        Self(LedgerError::Message {
-            status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
-            message: "an error occurred while processing the request".into(),
-        })
-    }
-}
-
-impl IntoResponse for LedgerApiError {
-    fn into_response(self) -> axum::response::Response {
-        (StatusCode::from_u16(self.0.status()).unwrap(), Json(self.0)).into_response()
-    }
-}
-
 #[debug_handler]
 async fn get_ledger_sources(
     State(config): State<Config>,
     RegistryHeader(_registry_header): RegistryHeader,
-) -> Result<Json<LedgerSourcesResponse>, LedgerApiError> {
+) -> Result<Json<LedgerSourcesResponse>, ApiError> {
     let log_length = config
         .core_service
         .store()
@@ -84,7 +58,7 @@ async fn get_ledger_sources(
         .collect::<Vec<LedgerSource>>();
 
     Ok(Json(LedgerSourcesResponse {
-        hash_algorithm: HashAlgorithm::Sha256,
+        hash_algorithm: config.core_service.hash_algorithm(),
         sources,
     }))
 }
@@ -94,7 +68,7 @@ async fn get_ledger_records(
     State(config): State<Config>,
     Path(start): Path<RegistryIndex>,
     RegistryHeader(_registry_header): RegistryHeader,
-) -> Result<Response, LedgerApiError> {
+) -> Result<Response, ApiError> {
     let log_leafs = config
         .core_service
         .store()
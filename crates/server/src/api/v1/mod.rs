@@ -1,29 +1,36 @@
-use crate::{
-    policy::{content::ContentPolicy, record::RecordPolicy},
-    services::CoreService,
-};
+use crate::datastore::DataStoreError;
 use anyhow::Result;
 use axum::{
     async_trait,
     extract::{
-        rejection::{JsonRejection, PathRejection},
-        FromRequest, FromRequestParts,
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        ConnectInfo, FromRequest, FromRequestParts, Request, State,
     },
-    http::{request::Parts, StatusCode},
-    response::IntoResponse,
+    http::{request::Parts, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     Router,
 };
+use rand_core::{OsRng, RngCore};
 use serde::{Serialize, Serializer};
-use std::{path::PathBuf, str::FromStr, sync::Arc};
+use std::{fmt, net::SocketAddr, str::FromStr, sync::Arc};
 use url::Url;
-use warg_api::v1::REGISTRY_HEADER_NAME;
+use warg_api::v1::{
+    admin::{AuditEntry, AuditOperation},
+    REGISTRY_HEADER_NAME,
+};
 
+pub mod admin;
 pub mod content;
 pub mod fetch;
+pub mod idempotency;
 pub mod ledger;
 pub mod monitor;
+pub mod operator;
 pub mod package;
 pub mod proof;
+pub mod rate_limit;
+pub mod registry;
 
 /// An extractor that wraps the JSON extractor of Axum.
 ///
@@ -87,6 +94,22 @@ impl From<PathRejection> for Error {
     }
 }
 
+/// An extractor that wraps the query extractor of Axum.
+///
+/// This extractor returns an API error on rejection.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(Error))]
+pub struct Query<T>(T);
+
+impl From<QueryRejection> for Error {
+    fn from(rejection: QueryRejection) -> Self {
+        Self {
+            status: rejection.status(),
+            message: rejection.body_text(),
+        }
+    }
+}
+
 pub async fn not_found() -> impl IntoResponse {
     Error {
         status: StatusCode::NOT_FOUND,
@@ -94,6 +117,132 @@ pub async fn not_found() -> impl IntoResponse {
     }
 }
 
+/// Represents an error from the data store returned by the API.
+///
+/// The response body has the status, a machine-readable error code, and a
+/// message. Each data store error maps to a status and code as follows:
+///
+/// | Error                                         | Status | Code                   |
+/// |-----------------------------------------------|--------|------------------------|
+/// | `Conflict`                                    | 409    | `conflict`             |
+/// | `CheckpointNotFound`, `CheckpointIdNotFound`, `CheckpointLogRootNotFound` | 404 | `checkpointNotFound` |
+/// | `NoCheckpoints`                               | 503    | `noCheckpoints`        |
+/// | `LogNotFound`                                 | 404    | `logNotFound`          |
+/// | `RecordNotFound`                              | 404    | `recordNotFound`       |
+/// | `LogLeafNotFound`                             | 404    | `logLeafNotFound`      |
+/// | `RecordNotValidated`                          | 409    | `recordNotValidated`   |
+/// | `RecordNotPending`                            | 409    | `recordNotPending`     |
+/// | `OperatorValidationFailed`, `PackageValidationFailed` | 400 | `invalidRecord` |
+/// | `PackageNameConflict`                         | 409    | `packageNameConflict`  |
+/// | `PackageNamespaceConflict`                    | 409    | `namespaceConflict`    |
+/// | `PackageNamespaceNotDefined`                  | 404    | `namespaceNotDefined`  |
+/// | `PackageNamespaceImported`                    | 409    | `namespaceImported`    |
+/// | `PackageSuppressed`                           | 410    | `packageSuppressed`    |
+/// | `KeyUnauthorized`, `UnknownKey`, `SignatureVerificationFailed` | 403 | `unauthorized` |
+/// | `Rejection`                                   | 422    | `rejected`             |
+/// | `RecordsCompacted`                            | 410    | `recordsCompacted`     |
+/// | `Unsupported`                                 | 501    | `unsupported`          |
+/// | `ConnectionPool`                              | 503    | `unavailable`          |
+/// | `UnknownParticipant`, `InvalidRecordContents`, `Diesel` | 500 | `internal`  |
+///
+/// The messages of internal errors are logged rather than returned.
+#[derive(Serialize, Debug)]
+pub struct ApiError {
+    #[serde(serialize_with = "serialize_status")]
+    pub(crate) status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl From<DataStoreError> for ApiError {
+    fn from(e: DataStoreError) -> Self {
+        let (status, code) = match &e {
+            DataStoreError::Conflict => (StatusCode::CONFLICT, "conflict"),
+            DataStoreError::CheckpointNotFound(_)
+            | DataStoreError::CheckpointIdNotFound(_)
+            | DataStoreError::CheckpointLogRootNotFound(_) => {
+                (StatusCode::NOT_FOUND, "checkpointNotFound")
+            }
+            DataStoreError::NoCheckpoints => (StatusCode::SERVICE_UNAVAILABLE, "noCheckpoints"),
+            DataStoreError::LogNotFound(_) => (StatusCode::NOT_FOUND, "logNotFound"),
+            DataStoreError::RecordNotFound(_) => (StatusCode::NOT_FOUND, "recordNotFound"),
+            DataStoreError::LogLeafNotFound(_) => (StatusCode::NOT_FOUND, "logLeafNotFound"),
+            DataStoreError::RecordNotValidated(_) => (StatusCode::CONFLICT, "recordNotValidated"),
+            DataStoreError::RecordNotPending(_) => (StatusCode::CONFLICT, "recordNotPending"),
+            DataStoreError::OperatorValidationFailed(_)
+            | DataStoreError::PackageValidationFailed(_) => {
+                (StatusCode::BAD_REQUEST, "invalidRecord")
+            }
+            DataStoreError::PackageNameConflict { .. } => {
+                (StatusCode::CONFLICT, "packageNameConflict")
+            }
+            DataStoreError::PackageNamespaceConflict { .. } => {
+                (StatusCode::CONFLICT, "namespaceConflict")
+            }
+            DataStoreError::PackageNamespaceNotDefined(_) => {
+                (StatusCode::NOT_FOUND, "namespaceNotDefined")
+            }
+            DataStoreError::PackageNamespaceImported(_) => {
+                (StatusCode::CONFLICT, "namespaceImported")
+            }
+            DataStoreError::PackageSuppressed { .. } => (StatusCode::GONE, "packageSuppressed"),
+            DataStoreError::KeyUnauthorized(_)
+            | DataStoreError::UnknownKey(_)
+            | DataStoreError::SignatureVerificationFailed(_) => {
+                (StatusCode::FORBIDDEN, "unauthorized")
+            }
+            DataStoreError::Rejection(_) => (StatusCode::UNPROCESSABLE_ENTITY, "rejected"),
+            DataStoreError::RecordsCompacted(_) => (StatusCode::GONE, "recordsCompacted"),
+            DataStoreError::Unsupported(_) => (StatusCode::NOT_IMPLEMENTED, "unsupported"),
+            #[cfg(feature = "postgres")]
+            DataStoreError::ConnectionPool(_) => {
+                tracing::error!("unexpected data store error: {e}");
+                return Self {
+                    status: StatusCode::SERVICE_UNAVAILABLE,
+                    code: "unavailable",
+                    message: "the data store is unavailable; try again later".into(),
+                };
+            }
+            DataStoreError::UnknownParticipant(_)
+            | DataStoreError::InvalidRecordContents { .. } => {
+                return Self::internal(e);
+            }
+            #[cfg(feature = "postgres")]
+            DataStoreError::Diesel(_) => return Self::internal(e),
+        };
+
+        Self {
+            status,
+            code,
+            message: e.to_string(),
+        }
+    }
+}
+
+impl ApiError {
+    /// Creates an internal server error, logging the data store error.
+    fn internal(e: DataStoreError) -> Self {
+        tracing::error!("unexpected data store error: {e}");
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            code: "internal",
+            message: "an error occurred while processing the request".into(),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{message}", message = self.message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> axum::response::Response {
+        (self.status, axum::Json(self)).into_response()
+    }
+}
+
 /// An extractor for the `Warg-Registry` header. Currently, this server implementation
 /// does not support this header and returns a `501` error.
 pub struct RegistryHeader(Option<String>);
@@ -124,33 +273,322 @@ impl FromStr for RegistryHeader {
     }
 }
 
-pub fn create_router(
-    content_base_url: Url,
-    core: CoreService,
-    temp_dir: PathBuf,
-    files_dir: PathBuf,
-    content_policy: Option<Arc<dyn ContentPolicy>>,
-    record_policy: Option<Arc<dyn RecordPolicy>>,
-) -> Router {
+/// The request header name that specifies the identifier of a request.
+pub const REQUEST_ID_HEADER_NAME: &str = "x-request-id";
+
+/// The maximum length of a client-provided request identifier.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// An extractor for the context of a request recorded in the audit log.
+///
+/// The request identifier is taken from the `X-Request-Id` header if present;
+/// otherwise, a random identifier is generated.
+pub struct RequestContext {
+    source: Option<String>,
+    request_id: String,
+}
+
+impl RequestContext {
+    /// Creates an audit entry for the given operation in the context of the request.
+    pub fn audit_entry(&self, operation: AuditOperation) -> AuditEntry {
+        let mut entry = AuditEntry::new(operation);
+        entry.source.clone_from(&self.source);
+        entry.request_id = Some(self.request_id.clone());
+        entry
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for RequestContext
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let source = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+
+        let request_id = parts
+            .headers
+            .get(REQUEST_ID_HEADER_NAME)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+            .map(ToString::to_string)
+            .unwrap_or_else(|| {
+                let mut bytes = [0u8; 16];
+                OsRng.fill_bytes(&mut bytes);
+                hex::encode(bytes)
+            });
+
+        Ok(Self { source, request_id })
+    }
+}
+
+pub fn create_router(config: super::Config) -> Router {
+    let core = config.core.clone();
     let proof_config = proof::Config::new(core.clone());
-    let package_config = package::Config::new(
-        core.clone(),
-        files_dir.clone(),
-        temp_dir,
-        content_policy,
-        record_policy,
-    );
+    let package_config = package::Config::new(&config);
     let fetch_config = fetch::Config::new(core.clone());
-    let content_config = content::Config::new(content_base_url, files_dir);
+    let content_config = content::Config::new(config.content_base_url, config.content_store);
     let monitor_config = monitor::Config::new(core.clone());
-    let ledger_config = ledger::Config::new(core);
+    let ledger_config = ledger::Config::new(core.clone());
+    let registry_config = registry::Config::new(config.limits);
+    let operator_config = operator::Config::new(core.clone(), config.audit_log.clone());
 
-    Router::new()
+    let router = match config.admin_token {
+        Some(token) => {
+            let admin_config = admin::Config::new(
+                core,
+                token,
+                config.audit_log,
+                config.content_collector,
+                config.webhooks,
+            );
+            Router::new().nest("/admin", admin_config.into_router())
+        }
+        None => Router::new(),
+    };
+
+    let mut operator_router = operator_config.into_router();
+    let mut package_router = package_config.clone().into_router();
+    if let Some(mirror) = config.mirror {
+        let upstream = Arc::new(mirror.upstream().clone());
+        operator_router = operator_router.layer(middleware::from_fn_with_state(
+            upstream.clone(),
+            reject_submissions,
+        ));
+        package_router =
+            package_router.layer(middleware::from_fn_with_state(upstream, reject_submissions));
+    }
+
+    router
+        .nest(
+            "/checkpoints",
+            fetch_config.clone().into_checkpoints_router(),
+        )
         .nest("/content", content_config.into_router())
         .nest("/fetch", fetch_config.into_router())
         .nest("/ledger", ledger_config.into_router())
-        .nest("/package", package_config.into_router())
-        .nest("/proof", proof_config.into_router())
+        .nest("/operator", operator_router)
+        .nest("/package", package_router)
+        .nest("/packages", package_config.into_list_router())
+        .nest("/proof", proof_config.clone().into_router())
+        .nest("/proofs", proof_config.into_proofs_router())
+        .nest("/registry", registry_config.into_router())
         .nest("/verify", monitor_config.into_router())
         .fallback(not_found)
 }
+
+/// Rejects submissions to a mirror, directing clients to the upstream registry.
+///
+/// Every `POST` of the operator and package APIs submits a record or its content.
+async fn reject_submissions(
+    State(upstream): State<Arc<Url>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+
+    Error {
+        status: StatusCode::FORBIDDEN,
+        message: format!(
+            "this registry is a read-only mirror of `{upstream}`; publish to the upstream registry instead"
+        ),
+    }
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warg_crypto::{
+        hash::{AnyHash, Hash, Sha256},
+        signing::generate_p256_pair,
+    };
+    use warg_protocol::{
+        operator, package,
+        registry::{LogId, LogLeaf, PackageName, RecordId},
+    };
+
+    /// Gets the status and error code of the response for a data store error.
+    async fn respond(e: DataStoreError) -> (StatusCode, String) {
+        let response = ApiError::from(e).into_response();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["status"], status.as_u16());
+        (status, body["code"].as_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn it_maps_data_store_errors_to_statuses() {
+        let hash: AnyHash = Hash::<Sha256>::of("test").into();
+        let name = PackageName::new("test:package").unwrap();
+        let log_id = LogId::package_log::<Sha256>(&name);
+        let record_id = RecordId::from(hash.clone());
+        let (_, signing_key) = generate_p256_pair();
+        let signature = signing_key.sign(b"test").unwrap();
+
+        let cases = [
+            (DataStoreError::Conflict, StatusCode::CONFLICT, "conflict"),
+            (
+                DataStoreError::CheckpointNotFound(1),
+                StatusCode::NOT_FOUND,
+                "checkpointNotFound",
+            ),
+            (
+                DataStoreError::CheckpointIdNotFound(hash.clone()),
+                StatusCode::NOT_FOUND,
+                "checkpointNotFound",
+            ),
+            (
+                DataStoreError::CheckpointLogRootNotFound(hash.clone()),
+                StatusCode::NOT_FOUND,
+                "checkpointNotFound",
+            ),
+            (
+                DataStoreError::NoCheckpoints,
+                StatusCode::SERVICE_UNAVAILABLE,
+                "noCheckpoints",
+            ),
+            (
+                DataStoreError::LogNotFound(log_id.clone()),
+                StatusCode::NOT_FOUND,
+                "logNotFound",
+            ),
+            (
+                DataStoreError::RecordNotFound(record_id.clone()),
+                StatusCode::NOT_FOUND,
+                "recordNotFound",
+            ),
+            (
+                DataStoreError::LogLeafNotFound(0),
+                StatusCode::NOT_FOUND,
+                "logLeafNotFound",
+            ),
+            (
+                DataStoreError::UnknownParticipant(LogLeaf {
+                    log_id: log_id.clone(),
+                    record_id: record_id.clone(),
+                }),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal",
+            ),
+            (
+                DataStoreError::RecordNotValidated(record_id.clone()),
+                StatusCode::CONFLICT,
+                "recordNotValidated",
+            ),
+            (
+                DataStoreError::RecordNotPending(record_id.clone()),
+                StatusCode::CONFLICT,
+                "recordNotPending",
+            ),
+            (
+                DataStoreError::InvalidRecordContents {
+                    record_id: record_id.clone(),
+                    message: "invalid".into(),
+                },
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal",
+            ),
+            (
+                operator::ValidationError::FirstEntryIsNotInit.into(),
+                StatusCode::BAD_REQUEST,
+                "invalidRecord",
+            ),
+            (
+                package::ValidationError::FirstEntryIsNotInit.into(),
+                StatusCode::BAD_REQUEST,
+                "invalidRecord",
+            ),
+            (
+                DataStoreError::PackageNameConflict {
+                    name: name.clone(),
+                    existing: name.clone(),
+                },
+                StatusCode::CONFLICT,
+                "packageNameConflict",
+            ),
+            (
+                DataStoreError::PackageNamespaceConflict {
+                    namespace: "test".into(),
+                    existing: "TEST".into(),
+                },
+                StatusCode::CONFLICT,
+                "namespaceConflict",
+            ),
+            (
+                DataStoreError::PackageNamespaceNotDefined("test".into()),
+                StatusCode::NOT_FOUND,
+                "namespaceNotDefined",
+            ),
+            (
+                DataStoreError::PackageNamespaceImported("test".into()),
+                StatusCode::CONFLICT,
+                "namespaceImported",
+            ),
+            (
+                DataStoreError::PackageSuppressed {
+                    name: name.clone(),
+                    reason: "malware".into(),
+                },
+                StatusCode::GONE,
+                "packageSuppressed",
+            ),
+            (
+                DataStoreError::KeyUnauthorized("key".to_string().into()),
+                StatusCode::FORBIDDEN,
+                "unauthorized",
+            ),
+            (
+                DataStoreError::UnknownKey("key".to_string().into()),
+                StatusCode::FORBIDDEN,
+                "unauthorized",
+            ),
+            (
+                DataStoreError::SignatureVerificationFailed(signature),
+                StatusCode::FORBIDDEN,
+                "unauthorized",
+            ),
+            (
+                DataStoreError::Rejection("rejected".into()),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "rejected",
+            ),
+            (
+                DataStoreError::RecordsCompacted(log_id.clone()),
+                StatusCode::GONE,
+                "recordsCompacted",
+            ),
+            (
+                DataStoreError::Unsupported("testing"),
+                StatusCode::NOT_IMPLEMENTED,
+                "unsupported",
+            ),
+        ];
+
+        for (e, status, code) in cases {
+            let message = e.to_string();
+            assert_eq!(respond(e).await, (status, code.to_string()), "{message}");
+        }
+    }
+
+    #[tokio::test]
+    async fn it_hides_internal_error_messages() {
+        let e = DataStoreError::InvalidRecordContents {
+            record_id: RecordId::from(AnyHash::from(Hash::<Sha256>::of("test"))),
+            message: "corrupt".into(),
+        };
+        let e = ApiError::from(e);
+        assert!(!e.message.contains("corrupt"));
+    }
+}
@@ -1,11 +1,9 @@
-use super::{Json, RegistryHeader};
+use super::{ApiError, Json, RegistryHeader};
 use crate::datastore::DataStoreError;
 use crate::services::CoreService;
-use axum::http::StatusCode;
-use axum::{debug_handler, extract::State, response::IntoResponse, routing::post, Router};
-use warg_api::v1::monitor::{CheckpointVerificationResponse, MonitorError, VerificationState};
-use warg_crypto::hash::Sha256;
-use warg_protocol::registry::{LogId, TimestampedCheckpoint};
+use axum::{debug_handler, extract::State, routing::post, Router};
+use warg_api::v1::monitor::{CheckpointVerificationResponse, VerificationState};
+use warg_protocol::registry::TimestampedCheckpoint;
 use warg_protocol::SerdeEnvelope;
 
 #[derive(Clone)]
@@ -25,25 +23,6 @@ impl Config {
     }
 }
 
-struct MonitorApiError(MonitorError);
-
-impl From<DataStoreError> for MonitorApiError {
-    fn from(e: DataStoreError) -> Self {
-        tracing::error!("unexpected data store error: {e}");
-
-        Self(MonitorError::Message {
-            status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
-            message: "an error occurred while processing the request".into(),
-        })
-    }
-}
-
-impl IntoResponse for MonitorApiError {
-    fn into_response(self) -> axum::response::Response {
-        (StatusCode::from_u16(self.0.status()).unwrap(), Json(self.0)).into_response()
-    }
-}
-
 /// Verifies a checkpoint and its signature.
 ///
 /// Note: Other implementations may choose to perform validation differently
@@ -66,7 +45,7 @@ async fn verify_checkpoint(
     State(config): State<Config>,
     RegistryHeader(_registry_header): RegistryHeader,
     Json(body): Json<SerdeEnvelope<TimestampedCheckpoint>>,
-) -> Result<Json<CheckpointVerificationResponse>, MonitorApiError> {
+) -> Result<Json<CheckpointVerificationResponse>, ApiError> {
     // Do a first pass checking the provided checkpoint against the data store
     let (checkpoint_verification, signature_verification) =
         try_verify_exact_match(&config.core_service, &body).await;
@@ -76,7 +55,7 @@ async fn verify_checkpoint(
         match config
             .core_service
             .store()
-            .verify_timestamped_checkpoint_signature(&LogId::operator_log::<Sha256>(), &body)
+            .verify_timestamped_checkpoint_signature(&config.core_service.operator_log_id(), &body)
             .await
         {
             Ok(_) => VerificationState::Verified,
@@ -84,7 +63,7 @@ async fn verify_checkpoint(
                 DataStoreError::UnknownKey(_)
                 | DataStoreError::SignatureVerificationFailed(_)
                 | DataStoreError::KeyUnauthorized(_) => VerificationState::Invalid,
-                _ => return Err(MonitorApiError::from(error)),
+                _ => return Err(ApiError::from(error)),
             },
         }
     } else {
@@ -0,0 +1,118 @@
+use super::{ApiError, Json, RegistryHeader, RequestContext};
+use crate::{
+    audit::{self, AuditLog},
+    datastore::DataStoreError,
+    services::{CoreService, CoreServiceError},
+};
+use axum::{
+    debug_handler, extract::State, http::StatusCode, response::IntoResponse, routing::post, Router,
+};
+use std::sync::Arc;
+use warg_api::v1::{
+    admin::AuditOperation,
+    operator::{OperatorError, OperatorRecordResponse, PublishOperatorRecordRequest},
+};
+use warg_protocol::{operator, ProtoEnvelope};
+
+#[derive(Clone)]
+pub struct Config {
+    core_service: CoreService,
+    audit_log: Option<Arc<dyn AuditLog>>,
+}
+
+impl Config {
+    pub fn new(core_service: CoreService, audit_log: Option<Arc<dyn AuditLog>>) -> Self {
+        Self {
+            core_service,
+            audit_log,
+        }
+    }
+
+    pub fn into_router(self) -> Router {
+        Router::new()
+            .route("/record", post(publish_record))
+            .with_state(self)
+    }
+}
+
+enum OperatorApiError {
+    Operator(OperatorError),
+    DataStore(ApiError),
+}
+
+impl OperatorApiError {
+    fn bad_request(message: impl ToString) -> Self {
+        Self::Operator(OperatorError::Message {
+            status: StatusCode::BAD_REQUEST.as_u16(),
+            message: message.to_string(),
+        })
+    }
+}
+
+impl From<CoreServiceError> for OperatorApiError {
+    fn from(e: CoreServiceError) -> Self {
+        match e {
+            CoreServiceError::OperatorHeadMismatch => Self::Operator(OperatorError::HeadMismatch),
+            CoreServiceError::DataStore(DataStoreError::OperatorValidationFailed(e)) => {
+                Self::Operator(OperatorError::Rejection(e.to_string()))
+            }
+            CoreServiceError::DataStore(e) => Self::DataStore(e.into()),
+            e => {
+                tracing::error!("unexpected core service error: {e}");
+
+                Self::Operator(OperatorError::Message {
+                    status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                    message: "an error occurred while processing the request".into(),
+                })
+            }
+        }
+    }
+}
+
+impl IntoResponse for OperatorApiError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::Operator(e) => {
+                (StatusCode::from_u16(e.status()).unwrap(), Json(e)).into_response()
+            }
+            Self::DataStore(e) => e.into_response(),
+        }
+    }
+}
+
+#[debug_handler]
+async fn publish_record(
+    State(config): State<Config>,
+    RegistryHeader(_registry_header): RegistryHeader,
+    context: RequestContext,
+    Json(body): Json<PublishOperatorRecordRequest<'static>>,
+) -> Result<Json<OperatorRecordResponse>, OperatorApiError> {
+    let record: ProtoEnvelope<operator::OperatorRecord> = body
+        .record
+        .into_owned()
+        .try_into()
+        .map_err(OperatorApiError::bad_request)?;
+
+    let mut entry = context.audit_entry(AuditOperation::OperatorRecordPublished);
+    entry.log_id = Some(config.core_service.operator_log_id());
+    entry.key_id = Some(record.key_id().clone());
+
+    match config
+        .core_service
+        .publish_signed_operator_record(record)
+        .await
+    {
+        Ok((record_id, registry_index)) => {
+            entry.record_id = Some(record_id.clone());
+            audit::append(config.audit_log.as_deref(), entry).await;
+            Ok(Json(OperatorRecordResponse {
+                record_id,
+                registry_index,
+            }))
+        }
+        Err(e) => {
+            audit::append(config.audit_log.as_deref(), entry.failed(&e)).await;
+            Err(e.into())
+        }
+    }
+}
@@ -1,85 +1,187 @@
-use super::{Json, Path, RegistryHeader};
+use super::{
+    idempotency::{Begin, IdempotencyCache, Outcome, MAX_IDEMPOTENCY_KEY_LEN},
+    rate_limit::SubmissionRateLimiter,
+    ApiError, Json, Path, Query, RegistryHeader, RequestContext,
+};
 use crate::{
-    datastore::{DataStoreError, RecordStatus},
+    audit::{self, AuditLog},
+    content::{ContentStorage, ContentStoreError, UploadError, UploadSessions},
+    datastore::{DataStoreError, Record, RecordStatus},
+    limits::SharedLimits,
     policy::{
-        content::{ContentPolicy, ContentPolicyError},
-        record::{RecordPolicy, RecordPolicyError},
+        content::{ContentPolicy, ContentPolicyError, ContentSourcePolicy},
+        record::{
+            RecordLimitPolicy, RecordPolicy, RecordPolicyError, ReleaseArtifactPolicy,
+            ReleaseMetadataPolicy, TimestampSkewPolicy,
+        },
+    },
+    services::{
+        component_dependencies, record_state_changed, today, CoreService, CoreServiceError,
+        DownloadCounter,
     },
-    services::CoreService,
 };
 use axum::{
-    body::{Body, BodyDataStream},
+    body::Body,
     debug_handler,
-    extract::State,
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{ConnectInfo, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Router,
 };
+use bytes::Bytes;
 use futures::StreamExt;
-use indexmap::IndexMap;
-use std::path::PathBuf;
+use indexmap::{IndexMap, IndexSet};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::fmt;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tempfile::NamedTempFile;
-use tokio::io::AsyncWriteExt;
-use warg_api::v1::package::{
-    MissingContent, PackageError, PackageRecord, PackageRecordState, PublishRecordRequest,
-    UploadEndpoint,
+use std::time::{Duration, Instant};
+use tokio_util::io::ReaderStream;
+use warg_api::v1::{
+    admin::{AuditEntry, AuditOperation},
+    package::{
+        received_range, DownloadCounts, ListPackagesQuery, ListPackagesResponse, MissingContent,
+        MissingContentResponse, PackageDependenciesResponse, PackageDependentsResponse,
+        PackageError, PackageRecord, PackageRecordEntry, PackageRecordState, PackageRecordSummary,
+        PackageRecordsQuery, PackageRecordsResponse, PackageStatsResponse, PublishRecordRequest,
+        ResolvePackageResponse, UploadContentRange, UploadEndpoint, RESOLVE_PACKAGE_NOTE,
+    },
+    IDEMPOTENCY_KEY_HEADER_NAME,
 };
-use warg_crypto::hash::{AnyHash, Sha256};
+use warg_crypto::hash::{AnyHash, HashAlgorithm};
 use warg_protocol::{
-    package,
-    registry::{LogId, RecordId},
-    ProtoEnvelope, Record as _,
+    package::{self, PackageEntry},
+    registry::{LogId, PackageName, RecordId},
+    ProtoEnvelope, Record as _, Version, VersionReq,
 };
 
+/// The number of seconds clients are asked to wait before retrying when the
+/// service is unavailable, such as when the processing queue is full.
+const RETRY_AFTER_SECS: u64 = 1;
+
+const DEFAULT_PACKAGES_LIMIT: u16 = 100;
+const MAX_PACKAGES_LIMIT: u16 = 1000;
+
+const DEFAULT_RECORDS_LIMIT: u16 = 100;
+const MAX_RECORDS_LIMIT: u16 = 1000;
+
 #[derive(Clone)]
 pub struct Config {
     core_service: CoreService,
-    files_dir: PathBuf,
-    temp_dir: PathBuf,
+    content_store: ContentStorage,
+    uploads: Arc<UploadSessions>,
     content_policy: Option<Arc<dyn ContentPolicy>>,
+    content_source_policy: Arc<ContentSourcePolicy>,
     record_policy: Option<Arc<dyn RecordPolicy>>,
+    artifact_policy: Arc<ReleaseArtifactPolicy>,
+    timestamp_policy: Option<TimestampSkewPolicy>,
+    limits: SharedLimits,
+    audit_log: Option<Arc<dyn AuditLog>>,
+    idempotency: Arc<IdempotencyCache>,
+    rate_limiter: Arc<SubmissionRateLimiter>,
+    downloads: Option<Arc<DownloadCounter>>,
 }
 
 impl Config {
-    pub fn new(
-        core_service: CoreService,
-        files_dir: PathBuf,
-        temp_dir: PathBuf,
-        content_policy: Option<Arc<dyn ContentPolicy>>,
-        record_policy: Option<Arc<dyn RecordPolicy>>,
-    ) -> Self {
+    pub fn new(config: &crate::api::Config) -> Self {
         Self {
-            core_service,
-            files_dir,
-            temp_dir,
-            content_policy,
-            record_policy,
+            core_service: config.core.clone(),
+            content_store: config.content_store.clone(),
+            uploads: config.uploads.clone(),
+            content_policy: config.content_policy.clone(),
+            content_source_policy: Arc::new(config.content_source_policy.clone()),
+            record_policy: config.record_policy.clone(),
+            artifact_policy: Arc::new(config.artifact_policy.clone()),
+            timestamp_policy: config.timestamp_policy,
+            limits: config.limits.clone(),
+            audit_log: config.audit_log.clone(),
+            idempotency: Arc::new(IdempotencyCache::new(config.idempotency_key_ttl)),
+            rate_limiter: SubmissionRateLimiter::start(),
+            downloads: config.downloads.clone(),
         }
     }
 
+    /// Appends an entry for the result of an operation to the audit log.
+    async fn audit<T>(&self, entry: AuditEntry, res: &Result<T, PackageApiError>) {
+        let entry = match res {
+            Ok(_) => entry,
+            Err(e) => entry.failed(e),
+        };
+
+        audit::append(self.audit_log.as_deref(), entry).await;
+    }
+
     pub fn into_router(self) -> Router {
         Router::new()
             .route("/:log_id/record", post(publish_record))
             .route("/:log_id/record/:record_id", get(get_record))
+            .route(
+                "/:log_id/record/:record_id/missing",
+                get(get_missing_content),
+            )
             .route(
                 "/:log_id/record/:record_id/content/:digest",
                 post(upload_content),
             )
+            .route("/:log_id/dependencies", get(get_dependencies))
+            .route("/:log_id/dependents", get(get_dependents))
+            // The path segment is the package name rather than the log id for this route
+            .route("/:log_id/resolve", get(resolve_package))
+            .route("/:log_id/stats", get(get_package_stats))
             .with_state(self)
     }
 
-    fn content_present(&self, digest: &AnyHash) -> bool {
-        self.content_path(digest).is_file()
+    /// Creates the router for listing packages and their record history.
+    ///
+    /// This is separate from [`Config::into_router`] as it is served from
+    /// `/packages` rather than `/package`.
+    pub fn into_list_router(self) -> Router {
+        Router::new()
+            .route("/", get(list_packages))
+            .route("/:name/records", get(get_package_records))
+            .with_state(self)
     }
 
-    fn content_file_name(&self, digest: &AnyHash) -> String {
-        digest.to_string().replace(':', "-")
-    }
+    /// Analyzes the content of the releases in the given record for
+    /// dependencies and stores them.
+    ///
+    /// Failing to analyze content never fails the publish; the release
+    /// simply has no dependencies recorded.
+    async fn store_dependencies(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        record: &package::PackageRecord,
+    ) {
+        for entry in &record.entries {
+            let PackageEntry::Release {
+                version, content, ..
+            } = entry
+            else {
+                continue;
+            };
+
+            let dependencies = match self.content_store.read(content).await {
+                Ok(bytes) => component_dependencies(&bytes),
+                Err(e) => {
+                    tracing::warn!("failed to read content `{content}` for analysis: {e}");
+                    continue;
+                }
+            };
 
-    fn content_path(&self, digest: &AnyHash) -> PathBuf {
-        self.files_dir.join(self.content_file_name(digest))
+            if let Err(e) = self
+                .core_service
+                .store()
+                .store_package_dependencies(log_id, record_id, version, &dependencies)
+                .await
+            {
+                tracing::error!(
+                    "failed to store dependencies for record `{record_id}` of `{log_id}`: {e}"
+                );
+            }
+        }
     }
 
     fn build_missing_content<'a>(
@@ -107,26 +209,67 @@ impl Config {
     }
 }
 
-struct PackageApiError(PackageError);
+enum PackageApiError {
+    Package(PackageError),
+    DataStore(ApiError),
+}
 
 impl PackageApiError {
     fn bad_request(message: impl ToString) -> Self {
-        Self(PackageError::Message {
+        Self::Package(PackageError::Message {
             status: StatusCode::BAD_REQUEST.as_u16(),
             message: message.to_string(),
         })
     }
 
+    fn unprocessable(message: impl ToString) -> Self {
+        Self::Package(PackageError::Message {
+            status: StatusCode::UNPROCESSABLE_ENTITY.as_u16(),
+            message: message.to_string(),
+        })
+    }
+
     fn internal_error(e: impl std::fmt::Display) -> Self {
         tracing::error!("unexpected error: {e}");
-        Self(PackageError::Message {
+        Self::Package(PackageError::Message {
             status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
             message: "an error occurred while processing the request".into(),
         })
     }
 
+    fn unavailable(e: CoreServiceError) -> Self {
+        Self::Package(PackageError::Message {
+            status: StatusCode::SERVICE_UNAVAILABLE.as_u16(),
+            message: e.to_string(),
+        })
+    }
+
+    fn too_many_requests(retry_after: Duration) -> Self {
+        // Round up so clients never retry before a submission is allowed
+        let mut secs = retry_after.as_secs();
+        if retry_after.subsec_nanos() > 0 {
+            secs += 1;
+        }
+
+        Self::Package(PackageError::TooManyRequests { retry_after: secs })
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::Package(e) => StatusCode::from_u16(e.status()).unwrap(),
+            Self::DataStore(e) => e.status,
+        }
+    }
+
+    fn to_value(&self) -> serde_json::Result<serde_json::Value> {
+        match self {
+            Self::Package(e) => serde_json::to_value(e),
+            Self::DataStore(e) => serde_json::to_value(e),
+        }
+    }
+
     fn unsupported(message: impl ToString) -> Self {
-        Self(PackageError::Message {
+        Self::Package(PackageError::Message {
             status: StatusCode::NOT_IMPLEMENTED.as_u16(),
             message: message.to_string(),
         })
@@ -135,7 +278,7 @@ impl PackageApiError {
 
 impl From<DataStoreError> for PackageApiError {
     fn from(e: DataStoreError) -> Self {
-        Self(match e {
+        Self::Package(match e {
             DataStoreError::PackageValidationFailed(e) => {
                 return Self::bad_request(e);
             }
@@ -152,14 +295,8 @@ impl From<DataStoreError> for PackageApiError {
             DataStoreError::PackageNameConflict { existing, .. } => {
                 PackageError::PackageNameConflict(existing)
             }
-            // Other errors are internal server errors
-            e => {
-                tracing::error!("unexpected data store error: {e}");
-                PackageError::Message {
-                    status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
-                    message: "an error occurred while processing the request".into(),
-                }
-            }
+            DataStoreError::PackageSuppressed { reason, .. } => PackageError::Suppressed(reason),
+            e => return Self::DataStore(e.into()),
         })
     }
 }
@@ -167,7 +304,9 @@ impl From<DataStoreError> for PackageApiError {
 impl From<ContentPolicyError> for PackageApiError {
     fn from(e: ContentPolicyError) -> Self {
         match e {
-            ContentPolicyError::Rejection(message) => Self(PackageError::Rejection(message)),
+            ContentPolicyError::Rejection(message) => {
+                Self::Package(PackageError::Rejection(message))
+            }
         }
     }
 }
@@ -175,15 +314,43 @@ impl From<ContentPolicyError> for PackageApiError {
 impl From<RecordPolicyError> for PackageApiError {
     fn from(e: RecordPolicyError) -> Self {
         match e {
-            RecordPolicyError::Unauthorized(message) => Self(PackageError::Unauthorized(message)),
-            RecordPolicyError::Rejection(message) => Self(PackageError::Rejection(message)),
+            RecordPolicyError::Unauthorized(message) => {
+                Self::Package(PackageError::Unauthorized(message))
+            }
+            RecordPolicyError::Rejection(message) => {
+                Self::Package(PackageError::Rejection(message))
+            }
+        }
+    }
+}
+
+impl fmt::Display for PackageApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Package(e) => e.fmt(f),
+            Self::DataStore(e) => e.fmt(f),
         }
     }
 }
 
 impl IntoResponse for PackageApiError {
     fn into_response(self) -> axum::response::Response {
-        (StatusCode::from_u16(self.0.status()).unwrap(), Json(self.0)).into_response()
+        let status = self.status();
+        let retry_after = match &self {
+            Self::Package(PackageError::TooManyRequests { retry_after }) => Some(*retry_after),
+            _ if status == StatusCode::SERVICE_UNAVAILABLE => Some(RETRY_AFTER_SECS),
+            _ => None,
+        };
+
+        let response = match self {
+            Self::Package(e) => (status, Json(e)).into_response(),
+            Self::DataStore(e) => e.into_response(),
+        };
+
+        match retry_after {
+            Some(secs) => ([(header::RETRY_AFTER, secs.to_string())], response).into_response(),
+            None => response,
+        }
     }
 }
 
@@ -192,9 +359,119 @@ async fn publish_record(
     State(config): State<Config>,
     Path(log_id): Path<LogId>,
     RegistryHeader(_registry_header): RegistryHeader,
+    context: RequestContext,
+    headers: HeaderMap,
     Json(body): Json<PublishRecordRequest<'static>>,
 ) -> Result<impl IntoResponse, PackageApiError> {
-    let expected_log_id = LogId::package_log::<Sha256>(&body.package_name);
+    let Some(key) = idempotency_key(&headers)? else {
+        return publish(&config, log_id, context, body)
+            .await
+            .map(|(status, record)| (status, Json(record)));
+    };
+
+    let fingerprint = HashAlgorithm::Sha256
+        .digest(&serde_json::to_vec(&body).map_err(PackageApiError::internal_error)?);
+
+    match config.idempotency.begin(&log_id, &key, fingerprint) {
+        Begin::New => {}
+        Begin::Replay(Outcome::Accepted(record_id)) => {
+            tracing::debug!("replaying accepted record `{record_id}` for idempotency key `{key}`");
+            let record = record_response(&config, &log_id, record_id).await?;
+            return Ok((StatusCode::ACCEPTED, Json(record)));
+        }
+        Begin::Replay(Outcome::Failed(error)) => {
+            tracing::debug!("replaying failed submission for idempotency key `{key}`");
+            return Err(PackageApiError::Package(
+                serde_json::from_value(error).map_err(PackageApiError::internal_error)?,
+            ));
+        }
+        Begin::InProgress => {
+            return Err(PackageApiError::Package(PackageError::Message {
+                status: StatusCode::CONFLICT.as_u16(),
+                message: format!("a request with idempotency key `{key}` is still in progress"),
+            }));
+        }
+        Begin::Mismatch => {
+            return Err(PackageApiError::Package(PackageError::Message {
+                status: StatusCode::UNPROCESSABLE_ENTITY.as_u16(),
+                message: format!(
+                    "idempotency key `{key}` was already used for a different request"
+                ),
+            }));
+        }
+    }
+
+    let res = publish(&config, log_id.clone(), context, body).await;
+    match &res {
+        Ok((_, record)) => {
+            config
+                .idempotency
+                .complete(&log_id, &key, Outcome::Accepted(record.record_id.clone()))
+        }
+        // Client errors are deterministic and are replayed; other errors,
+        // including exceeding the rate limit, may be retried
+        Err(e) if e.status().is_client_error() && e.status() != StatusCode::TOO_MANY_REQUESTS => {
+            match e.to_value() {
+                Ok(error) => config
+                    .idempotency
+                    .complete(&log_id, &key, Outcome::Failed(error)),
+                Err(_) => config.idempotency.forget(&log_id, &key),
+            }
+        }
+        Err(_) => config.idempotency.forget(&log_id, &key),
+    }
+
+    res.map(|(status, record)| (status, Json(record)))
+}
+
+/// Gets the idempotency key of a request, if any.
+fn idempotency_key(headers: &HeaderMap) -> Result<Option<String>, PackageApiError> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER_NAME) else {
+        return Ok(None);
+    };
+
+    match value.to_str().map(str::trim) {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN => {
+            Ok(Some(key.to_string()))
+        }
+        _ => Err(PackageApiError::bad_request(format!(
+            "the `{IDEMPOTENCY_KEY_HEADER_NAME}` header must be between 1 and {MAX_IDEMPOTENCY_KEY_LEN} visible ASCII characters"
+        ))),
+    }
+}
+
+#[tracing::instrument(
+    name = "submit_record",
+    skip_all,
+    fields(%log_id, package = %body.package_name, record_id = tracing::field::Empty)
+)]
+async fn publish(
+    config: &Config,
+    log_id: LogId,
+    context: RequestContext,
+    body: PublishRecordRequest<'static>,
+) -> Result<(StatusCode, PackageRecord), PackageApiError> {
+    let mut entry = context.audit_entry(AuditOperation::RecordSubmitted);
+    entry.log_id = Some(log_id.clone());
+    entry.package = Some(body.package_name.as_ref().clone());
+
+    let start = Instant::now();
+    let res = submit_record(config, log_id, body, &mut entry).await;
+    config
+        .core_service
+        .metrics()
+        .observe_submission(start.elapsed());
+    config.audit(entry, &res).await;
+    res
+}
+
+async fn submit_record(
+    config: &Config,
+    log_id: LogId,
+    body: PublishRecordRequest<'static>,
+    entry: &mut AuditEntry,
+) -> Result<(StatusCode, PackageRecord), PackageApiError> {
+    let expected_log_id = config.core_service.package_log_id(&body.package_name);
     if expected_log_id != log_id {
         return Err(PackageApiError::bad_request(format!(
             "package log identifier `{expected_log_id}` derived from `{name}` does not match provided log identifier `{log_id}`",
@@ -207,29 +484,81 @@ async fn publish_record(
         .into_owned()
         .try_into()
         .map_err(PackageApiError::bad_request)?;
+    entry.key_id = Some(record.key_id().clone());
 
-    // Specifying content sources is not allowed in this implementation
-    if !body.content_sources.is_empty() {
-        return Err(PackageApiError::unsupported(
-            "specifying content sources is not supported",
-        ));
-    }
+    // Records exceeding the limits advertised in the registry metadata are
+    // never stored
+    RecordLimitPolicy::new(config.limits.load().record).check(&body.package_name, &record)?;
+
+    // Release metadata must be within its limits and name a valid license
+    ReleaseMetadataPolicy.check(&body.package_name, &record)?;
+
+    // Release artifacts must be of a registered type
+    config.artifact_policy.check(&body.package_name, &record)?;
 
-    // Verify the package name is unique in a case insensitive way and
-    // the namespace is defined in the operator log and not imported
-    // from another registry.
+    // Content sources must be served from an allowed host; the content is
+    // still uploaded to and served from the registry's content store
+    config.content_source_policy.check(
+        &record.as_ref().contents(),
+        body.content_sources
+            .iter()
+            .flat_map(|(digest, sources)| sources.iter().map(move |source| (digest, source))),
+    )?;
+
+    // Verify the package name is unique in a case insensitive way, the
+    // namespace is defined in the operator log and not imported from
+    // another registry, and the package is not suppressed by the operator.
     config
         .core_service
         .store()
-        .verify_can_publish_package(&LogId::operator_log::<Sha256>(), &body.package_name)
+        .verify_can_publish_package(&config.core_service.operator_log_id(), &body.package_name)
         .await?;
 
-    // Preemptively perform the policy check on the record before storing it
-    // This is performed here so that we never store an unauthorized record
-    if let Some(policy) = &config.record_policy {
+    // Records with a timestamp too far from the registry's clock are never stored
+    if let Some(policy) = &config.timestamp_policy {
         policy.check(&body.package_name, &record)?;
     }
 
+    // Keys granted permission to publish in the operator log may publish to
+    // any namespace, or to the packages matching a package-scoped grant; keys
+    // known to the operator log may not publish to any other package
+    let operator = config
+        .core_service
+        .store()
+        .get_operator_state(&config.core_service.operator_log_id())
+        .await?;
+    match operator.key_has_permission_to_publish(record.key_id(), &body.package_name) {
+        Some(true) => {}
+        Some(false) => {
+            return Err(PackageApiError::Package(PackageError::Unauthorized(
+                format!(
+                    "key `{key_id}` does not have permission to publish package `{name}`",
+                    key_id = record.key_id(),
+                    name = body.package_name
+                ),
+            )));
+        }
+        None => {
+            // Preemptively perform the policy check on the record before storing it
+            // This is performed here so that we never store an unauthorized record
+            if let Some(policy) = &config.record_policy {
+                policy.check(&body.package_name, &record)?;
+            }
+        }
+    }
+
+    // Only the keys a namespace is reserved for may initialize its packages
+    if record.as_ref().prev.is_none()
+        && !operator.key_may_initialize_package(record.key_id(), &body.package_name)
+    {
+        return Err(PackageApiError::Package(PackageError::Unauthorized(format!(
+            "namespace `{namespace}` is reserved; key `{key_id}` may not initialize package `{name}`",
+            namespace = body.package_name.namespace(),
+            key_id = record.key_id(),
+            name = body.package_name
+        ))));
+    }
+
     // Verify the signature on the record itself before storing it
     config
         .core_service
@@ -237,39 +566,90 @@ async fn publish_record(
         .verify_package_record_signature(&log_id, &record)
         .await?;
 
-    let record_id = RecordId::package_record::<Sha256>(&record);
-    let mut missing = record.as_ref().contents();
-    missing.retain(|d| !config.content_present(d));
+    // Rate limit by the key that signed the record, so records merely
+    // claiming another publisher's key cannot use up its limit; excess
+    // submissions are never stored
+    if let Some(limit) = config.limits.load().submission_rate {
+        let key_id = record.key_id();
+        config
+            .rate_limiter
+            .check(key_id, limit)
+            .map_err(|retry_after| {
+                tracing::debug!("rate limited record submission by key `{key_id}`");
+                PackageApiError::too_many_requests(retry_after)
+            })?;
+    }
 
-    config
+    let record_id = RecordId::package_record_for(config.core_service.hash_algorithm(), &record);
+    tracing::Span::current().record("record_id", tracing::field::display(&record_id));
+    entry.record_id = Some(record_id.clone());
+    let mut missing = IndexSet::new();
+    for digest in record.as_ref().contents() {
+        if !config
+            .content_store
+            .is_present(digest)
+            .await
+            .map_err(PackageApiError::internal_error)?
+        {
+            missing.insert(digest);
+        }
+    }
+
+    // If the record can be processed immediately, reserve a place in the
+    // processing queue before storing it so a full queue rejects it up front
+    let permit = if missing.is_empty() {
+        Some(
+            config
+                .core_service
+                .reserve_submission()
+                .await
+                .map_err(PackageApiError::unavailable)?,
+        )
+    } else {
+        None
+    };
+
+    // A record that was already submitted, such as by a client retrying
+    // after a timeout, is not processed again; its existing state is
+    // returned instead
+    if let Some(existing) = config
         .core_service
         .store()
         .store_package_record(&log_id, &body.package_name, &record_id, &record, &missing)
-        .await?;
+        .await?
+    {
+        tracing::info!("record was already submitted");
+        return existing_record_response(config, &log_id, record_id, existing)
+            .await
+            .map(|record| (StatusCode::OK, record));
+    }
 
     // If there's no missing content, submit the record for processing now
-    if missing.is_empty() {
+    if let Some(permit) = permit {
         config
-            .core_service
-            .submit_package_record(log_id, record_id.clone())
+            .store_dependencies(&log_id, &record_id, record.as_ref())
             .await;
+        permit.submit(log_id, record_id.clone());
 
         return Ok((
             StatusCode::ACCEPTED,
-            Json(PackageRecord {
+            PackageRecord {
                 record_id,
                 state: PackageRecordState::Processing,
-            }),
+                log_status: None,
+            },
         ));
     }
 
+    record_state_changed(&log_id, &record_id, "sourcing");
     let missing_content = config.build_missing_content(&log_id, &record_id, missing);
     Ok((
         StatusCode::ACCEPTED,
-        Json(PackageRecord {
+        PackageRecord {
             record_id,
             state: PackageRecordState::Sourcing { missing_content },
-        }),
+            log_status: None,
+        },
     ))
 }
 
@@ -279,36 +659,160 @@ async fn get_record(
     Path((log_id, record_id)): Path<(LogId, RecordId)>,
     RegistryHeader(_registry_header): RegistryHeader,
 ) -> Result<Json<PackageRecord>, PackageApiError> {
+    Ok(Json(record_response(&config, &log_id, record_id).await?))
+}
+
+async fn record_response(
+    config: &Config,
+    log_id: &LogId,
+    record_id: RecordId,
+) -> Result<PackageRecord, PackageApiError> {
     let record = config
         .core_service
         .store()
-        .get_package_record(&log_id, &record_id)
+        .get_package_record(log_id, &record_id)
         .await?;
 
+    existing_record_response(config, log_id, record_id, record).await
+}
+
+async fn existing_record_response(
+    config: &Config,
+    log_id: &LogId,
+    record_id: RecordId,
+    record: Record<package::PackageRecord>,
+) -> Result<PackageRecord, PackageApiError> {
     match record.status {
         RecordStatus::MissingContent(missing) => {
-            let missing_content = config.build_missing_content(&log_id, &record_id, &missing);
-            Ok(Json(PackageRecord {
+            let missing_content = config.build_missing_content(log_id, &record_id, &missing);
+            Ok(PackageRecord {
                 record_id,
                 state: PackageRecordState::Sourcing { missing_content },
-            }))
+                log_status: None,
+            })
         }
         // Validated is considered still processing until included in a checkpoint
-        RecordStatus::Pending | RecordStatus::Validated => Ok(Json(PackageRecord {
-            record_id,
-            state: PackageRecordState::Processing,
-        })),
-        RecordStatus::Rejected(reason) => Ok(Json(PackageRecord {
+        RecordStatus::Pending | RecordStatus::Validated => {
+            // The log itself does not exist until its first record is validated
+            let log_status = match config
+                .core_service
+                .store()
+                .get_package_log_status(log_id)
+                .await
+            {
+                Ok(status) => Some(status),
+                Err(DataStoreError::LogNotFound(_)) => None,
+                Err(e) => return Err(e.into()),
+            };
+
+            Ok(PackageRecord {
+                record_id,
+                state: PackageRecordState::Processing,
+                log_status,
+            })
+        }
+        RecordStatus::Rejected(reason) => Ok(PackageRecord {
             record_id,
             state: PackageRecordState::Rejected { reason },
-        })),
+            log_status: None,
+        }),
         RecordStatus::Published => {
             let registry_index = record.registry_index.unwrap();
 
-            Ok(Json(PackageRecord {
+            Ok(PackageRecord {
                 record_id,
                 state: PackageRecordState::Published { registry_index },
-            }))
+                log_status: None,
+            })
+        }
+    }
+}
+
+#[debug_handler]
+async fn get_missing_content(
+    State(config): State<Config>,
+    Path((log_id, record_id)): Path<(LogId, RecordId)>,
+    RegistryHeader(_registry_header): RegistryHeader,
+) -> Result<Json<MissingContentResponse>, PackageApiError> {
+    let store = config.core_service.store();
+    let missing = match store.get_missing_content(&log_id, &record_id).await {
+        Ok(missing) => missing,
+        // A record that is no longer pending is not missing any content
+        Err(DataStoreError::RecordNotPending(_)) => IndexSet::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    // Content may have been stored since the record was published, such as
+    // when it was uploaded for another record; mark it present rather than
+    // have it uploaded again
+    let mut still_missing = IndexSet::new();
+    for digest in missing {
+        if !config
+            .content_store
+            .is_present(&digest)
+            .await
+            .map_err(PackageApiError::internal_error)?
+        {
+            still_missing.insert(digest);
+            continue;
+        }
+
+        // With a full processing queue the content is reported as missing;
+        // uploading it completes the record once the queue has room
+        let Ok(permit) = config.core_service.reserve_submission().await else {
+            still_missing.insert(digest);
+            continue;
+        };
+
+        match store
+            .set_content_present(&log_id, &record_id, &digest)
+            .await
+        {
+            Ok(true) => {
+                let record = store.get_package_record(&log_id, &record_id).await?;
+                config
+                    .store_dependencies(&log_id, &record_id, record.envelope.as_ref())
+                    .await;
+                permit.submit(log_id.clone(), record_id.clone());
+            }
+            Ok(false) => {}
+            // The record was rejected concurrently
+            Err(DataStoreError::RecordNotPending(_)) => {
+                still_missing.clear();
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let missing_content = config.build_missing_content(&log_id, &record_id, &still_missing);
+    Ok(Json(MissingContentResponse {
+        record_id,
+        missing_content,
+    }))
+}
+
+/// The status of a content upload.
+enum UploadStatus {
+    /// All of the content was received and verified.
+    Complete,
+    /// Some of the content has yet to be received.
+    Incomplete { received: u64 },
+    /// The uploaded range did not start at the end of the received content.
+    Unsatisfiable { received: u64 },
+}
+
+impl IntoResponse for UploadStatus {
+    fn into_response(self) -> Response {
+        let (status, received) = match self {
+            Self::Complete => return StatusCode::CREATED.into_response(),
+            Self::Incomplete { received } => (StatusCode::ACCEPTED, received),
+            Self::Unsatisfiable { received } => (StatusCode::RANGE_NOT_SATISFIABLE, received),
+        };
+
+        match received_range(received) {
+            Some(range) => (status, [(header::RANGE, range)]).into_response(),
+            None => status.into_response(),
         }
     }
 }
@@ -318,8 +822,39 @@ async fn upload_content(
     State(config): State<Config>,
     Path((log_id, record_id, digest)): Path<(LogId, RecordId, AnyHash)>,
     RegistryHeader(_registry_header): RegistryHeader,
+    context: RequestContext,
+    headers: HeaderMap,
     body: Body,
-) -> Result<impl IntoResponse, PackageApiError> {
+) -> Result<UploadStatus, PackageApiError> {
+    let mut entry = context.audit_entry(AuditOperation::ContentUploaded);
+    entry.log_id = Some(log_id.clone());
+    entry.record_id = Some(record_id.clone());
+    entry.content = Some(digest.clone());
+
+    let res = store_content(
+        &config, log_id, record_id, digest, &headers, body, &mut entry,
+    )
+    .await;
+
+    // Only audit the upload once it has completed or failed
+    if let Ok(UploadStatus::Incomplete { .. } | UploadStatus::Unsatisfiable { .. }) = &res {
+        return res;
+    }
+
+    config.audit(entry, &res).await;
+    res
+}
+
+#[tracing::instrument(name = "upload_content", skip_all, fields(%log_id, %record_id, %digest))]
+async fn store_content(
+    config: &Config,
+    log_id: LogId,
+    record_id: RecordId,
+    digest: AnyHash,
+    headers: &HeaderMap,
+    body: Body,
+    entry: &mut AuditEntry,
+) -> Result<UploadStatus, PackageApiError> {
     match config
         .core_service
         .store()
@@ -333,47 +868,142 @@ async fn upload_content(
             ));
         }
         Err(DataStoreError::RecordNotPending(_)) => {
-            return Err(PackageApiError(PackageError::RecordNotSourcing))
+            return Err(PackageApiError::Package(PackageError::RecordNotSourcing))
         }
         Err(e) => return Err(e.into()),
     }
 
-    let tmp_path = NamedTempFile::new_in(&config.temp_dir)
-        .map_err(PackageApiError::internal_error)?
-        .into_temp_path();
+    let range = headers
+        .get(header::CONTENT_RANGE)
+        .map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(|v| v.parse::<UploadContentRange>().ok())
+                .ok_or_else(|| PackageApiError::bad_request("invalid content range header"))
+        })
+        .transpose()?;
+
+    let record = config
+        .core_service
+        .store()
+        .get_package_record(&log_id, &record_id)
+        .await?;
+    entry.key_id = Some(record.envelope.key_id().clone());
+    entry.package = audit::package_name(config.core_service.store(), &log_id).await;
+
+    let session = config
+        .uploads
+        .begin(UploadSessions::session_id(
+            record.envelope.key_id(),
+            &digest,
+        ))
+        .ok_or_else(|| {
+            PackageApiError::Package(PackageError::Message {
+                status: StatusCode::CONFLICT.as_u16(),
+                message: format!("an upload of content `{digest}` is already in progress"),
+            })
+        })?;
 
     tracing::debug!(
         "uploading content for record `{record_id}` from `{log_id}` to `{path}`",
-        path = tmp_path.display()
+        path = session.path().display()
     );
 
-    let res = process_content(
-        &tmp_path,
-        &digest,
-        body.into_data_stream(),
-        config.content_policy.as_deref(),
-    )
-    .await;
+    let received = session
+        .received()
+        .await
+        .map_err(PackageApiError::internal_error)?;
+    let received = match range {
+        Some(UploadContentRange::Bytes { start, .. }) if start != received => {
+            return Ok(UploadStatus::Unsatisfiable { received });
+        }
+        // A status request only reports the content received so far
+        Some(UploadContentRange::Status { .. }) => {
+            session
+                .append(false, futures::stream::empty::<Result<Bytes, Infallible>>())
+                .await
+        }
+        // An upload without a range always sends the entire content
+        _ => {
+            session
+                .append(range.is_none(), body.into_data_stream())
+                .await
+        }
+    }
+    .map_err(|e| match e {
+        UploadError::Interrupted(_) => PackageApiError::bad_request(e),
+        UploadError::Io(e) => PackageApiError::internal_error(e),
+    })?;
 
-    // If the error was a rejection, transition the record itself to rejected
-    if let Err(PackageApiError(PackageError::Rejection(reason))) = &res {
-        config
-            .core_service
-            .store()
-            .reject_package_record(
-                &log_id,
-                &record_id,
-                &format!("content with digest `{digest}` was rejected by policy: {reason}"),
-            )
-            .await?;
+    if let Some(total) = range.as_ref().map(UploadContentRange::total) {
+        if received < total {
+            return Ok(UploadStatus::Incomplete { received });
+        }
+
+        if received > total {
+            session
+                .discard()
+                .await
+                .map_err(PackageApiError::internal_error)?;
+            return Err(PackageApiError::bad_request(format!(
+                "received {received} bytes of content but expected {total} bytes"
+            )));
+        }
     }
 
-    // Only persist the file if the content was successfully processed
-    res?;
+    // Content that does not match the digest it was uploaded for is never
+    // checked against policy nor stored; the record still needs the content
+    let checked = match ContentStorage::verify(session.path(), &digest).await {
+        Ok(_) => match config.content_policy.as_deref() {
+            Some(policy) => check_content_policy(session.path(), &digest, policy).await,
+            None => Ok(()),
+        },
+        Err(e @ ContentStoreError::DigestMismatch { .. }) => Err(PackageApiError::unprocessable(e)),
+        Err(e) => Err(PackageApiError::internal_error(e)),
+    };
 
-    tmp_path
-        .persist(config.content_path(&digest))
-        .map_err(PackageApiError::internal_error)?;
+    if let Err(e) = checked {
+        if let Err(e) = session.discard().await {
+            tracing::warn!("failed to discard uploaded content `{digest}`: {e}");
+        }
+
+        // If the error was a rejection, transition the record itself to rejected
+        if let PackageApiError::Package(PackageError::Rejection(reason)) = &e {
+            config
+                .core_service
+                .store()
+                .reject_package_record(
+                    &log_id,
+                    &record_id,
+                    &format!("content with digest `{digest}` was rejected by policy: {reason}"),
+                )
+                .await?;
+            record_state_changed(&log_id, &record_id, "rejected");
+        }
+
+        return Err(e);
+    }
+
+    // Reserve a place in the processing queue in case this is the last
+    // content needed; a full queue rejects the upload so it can be retried
+    // without sending the content again
+    let permit = config
+        .core_service
+        .reserve_submission()
+        .await
+        .map_err(PackageApiError::unavailable)?;
+
+    config
+        .content_store
+        .persist(session.temp_path(), &digest)
+        .await
+        .map_err(|e| match e {
+            ContentStoreError::DigestMismatch { .. } => PackageApiError::unprocessable(e),
+            e => PackageApiError::internal_error(e),
+        })?;
+    drop(session);
+    config.core_service.metrics().content_stored(received);
 
     // If this is the last content needed, submit the record for processing now
     if config
@@ -383,54 +1013,350 @@ async fn upload_content(
         .await?
     {
         config
-            .core_service
-            .submit_package_record(log_id, record_id.clone())
+            .store_dependencies(&log_id, &record_id, record.envelope.as_ref())
             .await;
+        permit.submit(log_id, record_id.clone());
     }
 
-    Ok(StatusCode::CREATED)
+    Ok(UploadStatus::Complete)
+}
+
+#[derive(Deserialize)]
+struct DependenciesQuery {
+    version: Version,
+}
+
+#[debug_handler]
+async fn get_dependencies(
+    State(config): State<Config>,
+    Path(log_id): Path<LogId>,
+    Query(query): Query<DependenciesQuery>,
+    RegistryHeader(_registry_header): RegistryHeader,
+) -> Result<Json<PackageDependenciesResponse>, PackageApiError> {
+    let dependencies = config
+        .core_service
+        .store()
+        .get_package_dependencies(&log_id, &query.version)
+        .await?;
+
+    Ok(Json(PackageDependenciesResponse {
+        version: query.version,
+        dependencies,
+    }))
 }
 
-async fn process_content(
+#[debug_handler]
+async fn get_dependents(
+    State(config): State<Config>,
+    Path(log_id): Path<LogId>,
+    RegistryHeader(_registry_header): RegistryHeader,
+) -> Result<Json<PackageDependentsResponse>, PackageApiError> {
+    let store = config.core_service.store();
+    let operator = store
+        .get_operator_state(&config.core_service.operator_log_id())
+        .await?;
+
+    // Suppressed packages are not listed as dependents
+    let mut dependents = store.get_package_dependents(&log_id).await?;
+    dependents.retain(|d| {
+        operator
+            .package_suppression(&config.core_service.package_log_id(&d.name))
+            .is_none()
+    });
+
+    Ok(Json(PackageDependentsResponse { dependents }))
+}
+
+#[debug_handler]
+async fn list_packages(
+    State(config): State<Config>,
+    Query(query): Query<ListPackagesQuery>,
+    RegistryHeader(_registry_header): RegistryHeader,
+) -> Result<Json<ListPackagesResponse>, PackageApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_PACKAGES_LIMIT);
+    if limit == 0 || limit > MAX_PACKAGES_LIMIT {
+        return Err(PackageApiError::bad_request(format!(
+            "invalid packages limit value `{limit}`: must be between 1 and {MAX_PACKAGES_LIMIT}"
+        )));
+    }
+
+    let store = config.core_service.store();
+    let operator = store
+        .get_operator_state(&config.core_service.operator_log_id())
+        .await?;
+
+    // Suppressed packages are omitted from the page rather than shifting
+    // later pages, so a page may hold fewer than `limit` packages
+    let mut packages = store
+        .list_packages(query.offset.unwrap_or_default(), limit)
+        .await?;
+    packages.retain(|p| operator.package_suppression(&p.log_id).is_none());
+
+    Ok(Json(ListPackagesResponse { packages }))
+}
+
+#[debug_handler]
+async fn get_package_records(
+    State(config): State<Config>,
+    Path(name): Path<PackageName>,
+    Query(query): Query<PackageRecordsQuery>,
+    RegistryHeader(_registry_header): RegistryHeader,
+) -> Result<Json<PackageRecordsResponse>, PackageApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_RECORDS_LIMIT);
+    if limit == 0 || limit > MAX_RECORDS_LIMIT {
+        return Err(PackageApiError::bad_request(format!(
+            "invalid records limit value `{limit}`: must be between 1 and {MAX_RECORDS_LIMIT}"
+        )));
+    }
+
+    let store = config.core_service.store();
+    let log_id = config.core_service.package_log_id(&name);
+    if let Some(reason) = store
+        .get_operator_state(&config.core_service.operator_log_id())
+        .await?
+        .package_suppression(&log_id)
+    {
+        return Err(PackageApiError::Package(PackageError::Suppressed(
+            reason.to_string(),
+        )));
+    }
+
+    let checkpoint = store.get_latest_checkpoint().await?;
+    let page = store
+        .get_package_records(
+            &log_id,
+            checkpoint.as_ref().checkpoint.log_length,
+            query.since.as_ref(),
+            limit,
+        )
+        .await?;
+
+    // The current state of the log is used to flag releases yanked by later records
+    let state = store.get_package_state(&log_id).await?;
+
+    let mut records = Vec::with_capacity(page.records.len());
+    for record in page.records {
+        let envelope = &record.envelope;
+        let record_id =
+            RecordId::package_record_for(config.core_service.hash_algorithm(), envelope);
+        let entries = envelope
+            .as_ref()
+            .entries
+            .iter()
+            .map(|entry| match entry {
+                PackageEntry::Init {
+                    hash_algorithm,
+                    key,
+                } => PackageRecordEntry::Init {
+                    hash_algorithm: hash_algorithm.to_string(),
+                    key_id: key.fingerprint(),
+                },
+                PackageEntry::GrantFlat { key, permissions } => PackageRecordEntry::Grant {
+                    key_id: key.fingerprint(),
+                    permissions: permissions.clone(),
+                },
+                PackageEntry::RevokeFlat {
+                    key_id,
+                    permissions,
+                } => PackageRecordEntry::Revoke {
+                    key_id: key_id.clone(),
+                    permissions: permissions.clone(),
+                },
+                PackageEntry::RevokeKey { key_id, since } => PackageRecordEntry::RevokeKey {
+                    key_id: key_id.clone(),
+                    since: since
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or_default(),
+                },
+                PackageEntry::Release {
+                    version,
+                    content,
+                    artifacts,
+                    metadata,
+                } => PackageRecordEntry::Release {
+                    version: version.clone(),
+                    content: content.clone(),
+                    artifacts: artifacts.clone(),
+                    yanked: state.release(version).map_or(false, |r| r.yanked()),
+                    metadata: metadata.clone(),
+                },
+                PackageEntry::Yank { version } => PackageRecordEntry::Yank {
+                    version: version.clone(),
+                },
+                _ => PackageRecordEntry::Unknown,
+            })
+            .collect();
+
+        records.push(PackageRecordSummary {
+            state: record_response(&config, &log_id, record_id.clone())
+                .await?
+                .state,
+            checkpoint_log_length: store
+                .get_first_checkpoint_including(record.registry_index)
+                .await?,
+            timestamp: envelope
+                .as_ref()
+                .timestamp
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            key_id: envelope.key_id().clone(),
+            record_id,
+            entries,
+        });
+    }
+
+    Ok(Json(PackageRecordsResponse {
+        records,
+        next: page.next,
+    }))
+}
+
+#[derive(Deserialize)]
+struct ResolveQuery {
+    req: VersionReq,
+    #[serde(default)]
+    include_prerelease: bool,
+}
+
+#[debug_handler]
+async fn resolve_package(
+    State(config): State<Config>,
+    Path(name): Path<PackageName>,
+    Query(query): Query<ResolveQuery>,
+    RegistryHeader(_registry_header): RegistryHeader,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+) -> Result<Json<ResolvePackageResponse>, PackageApiError> {
+    let store = config.core_service.store();
+    let log_id = config.core_service.package_log_id(&name);
+    if let Some(reason) = store
+        .get_operator_state(&config.core_service.operator_log_id())
+        .await?
+        .package_suppression(&log_id)
+    {
+        return Err(PackageApiError::Package(PackageError::Suppressed(
+            reason.to_string(),
+        )));
+    }
+
+    let state = store.get_package_state(&log_id).await?;
+
+    let release = state
+        .releases()
+        .filter(|release| {
+            !release.yanked()
+                && (query.req.matches(&release.version)
+                    || (query.include_prerelease
+                        && !release.version.pre.is_empty()
+                        && query.req.matches(&Version::new(
+                            release.version.major,
+                            release.version.minor,
+                            release.version.patch,
+                        ))))
+        })
+        .max_by(|a, b| a.version.cmp(&b.version))
+        .ok_or_else(|| {
+            PackageApiError::Package(PackageError::Message {
+                status: StatusCode::NOT_FOUND.as_u16(),
+                message: format!(
+                    "no release of package `{name}` matches version requirement `{req}`",
+                    req = query.req
+                ),
+            })
+        })?;
+
+    let content = release
+        .content()
+        .expect("yanked releases are not selected")
+        .clone();
+    let registry_index = match store
+        .get_package_record(&log_id, &release.record_id)
+        .await?
+        .registry_index
+    {
+        Some(index) => index,
+        None => {
+            return Err(PackageApiError::internal_error(format!(
+                "record `{record_id}` of a validated release has no registry index",
+                record_id = release.record_id
+            )))
+        }
+    };
+
+    let checkpoint = store.get_latest_checkpoint().await?;
+    let checkpoint =
+        (checkpoint.as_ref().checkpoint.log_length > registry_index).then_some(checkpoint);
+
+    if let Some(downloads) = &config.downloads {
+        downloads.count_release(
+            connect_info.map(|ConnectInfo(addr)| addr.ip()),
+            &name,
+            &release.version,
+            &content,
+        );
+    }
+
+    Ok(Json(ResolvePackageResponse {
+        version: release.version.clone(),
+        content,
+        record_id: release.record_id.clone(),
+        registry_index,
+        checkpoint,
+        note: RESOLVE_PACKAGE_NOTE.to_string(),
+    }))
+}
+
+#[debug_handler]
+async fn get_package_stats(
+    State(config): State<Config>,
+    Path(name): Path<PackageName>,
+    RegistryHeader(_registry_header): RegistryHeader,
+) -> Result<Json<PackageStatsResponse>, PackageApiError> {
+    if config.downloads.is_none() {
+        return Err(PackageApiError::unsupported(
+            "download statistics are not collected by this registry",
+        ));
+    }
+
+    let versions = config
+        .core_service
+        .store()
+        .get_download_counts(&name, today())
+        .await?;
+
+    let mut downloads = DownloadCounts::default();
+    for counts in versions.values() {
+        downloads += *counts;
+    }
+
+    Ok(Json(PackageStatsResponse {
+        name,
+        downloads,
+        versions,
+    }))
+}
+
+async fn check_content_policy(
     path: &std::path::Path,
     digest: &AnyHash,
-    mut stream: BodyDataStream,
-    policy: Option<&dyn ContentPolicy>,
+    policy: &dyn ContentPolicy,
 ) -> Result<(), PackageApiError> {
-    let mut tmp_file = tokio::fs::File::create(&path)
+    let file = tokio::fs::File::open(path)
         .await
         .map_err(PackageApiError::internal_error)?;
+    let mut stream = ReaderStream::new(file);
 
-    let mut hasher = digest.algorithm().hasher();
-    let mut policy = policy.map(|p| p.new_stream_policy(digest)).transpose()?;
-
+    let mut policy = policy.new_stream_policy(digest)?;
     while let Some(chunk) = stream
         .next()
         .await
         .transpose()
         .map_err(PackageApiError::internal_error)?
     {
-        if let Some(policy) = policy.as_mut() {
-            policy.check(&chunk)?;
-        }
-
-        hasher.update(&chunk);
-        tmp_file
-            .write_all(&chunk)
-            .await
-            .map_err(PackageApiError::internal_error)?;
-    }
-
-    let result = hasher.finalize();
-    if &result != digest {
-        return Err(PackageApiError::bad_request(format!(
-            "content digest `{result}` does not match expected digest `{digest}`",
-        )));
-    }
-
-    if let Some(mut policy) = policy {
-        policy.finalize()?;
+        policy.check(&chunk)?;
     }
 
+    policy.finalize()?;
     Ok(())
 }
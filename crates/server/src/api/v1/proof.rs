@@ -1,10 +1,17 @@
-use super::{Json, RegistryHeader};
+use super::{ApiError, Json, Query, RegistryHeader};
+use crate::datastore::DataStoreError;
 use crate::services::{CoreService, CoreServiceError};
 use axum::{
-    debug_handler, extract::State, http::StatusCode, response::IntoResponse, routing::post, Router,
+    debug_handler,
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Router,
 };
 use warg_api::v1::proof::{
-    ConsistencyRequest, ConsistencyResponse, InclusionRequest, InclusionResponse, ProofError,
+    CheckpointConsistencyQuery, ConsistencyRequest, ConsistencyResponse, InclusionRequest,
+    InclusionResponse, ProofError,
 };
 use warg_protocol::registry::{RegistryIndex, RegistryLen};
 
@@ -24,13 +31,22 @@ impl Config {
             .route("/inclusion", post(prove_inclusion))
             .with_state(self)
     }
+
+    pub fn into_proofs_router(self) -> Router {
+        Router::new()
+            .route("/consistency", get(prove_checkpoint_consistency))
+            .with_state(self)
+    }
 }
 
-struct ProofApiError(ProofError);
+enum ProofApiError {
+    Proof(ProofError),
+    DataStore(ApiError),
+}
 
 impl From<CoreServiceError> for ProofApiError {
     fn from(value: CoreServiceError) -> Self {
-        Self(match value {
+        Self::Proof(match value {
             CoreServiceError::CheckpointNotFound(log_length) => {
                 ProofError::CheckpointNotFound(log_length)
             }
@@ -40,6 +56,7 @@ impl From<CoreServiceError> for ProofApiError {
             CoreServiceError::IncorrectProof { root, found } => {
                 ProofError::IncorrectProof { root, found }
             }
+            CoreServiceError::DataStore(e) => return Self::DataStore(e.into()),
             other => {
                 tracing::error!("Unhandled CoreServiceError: {other:?}");
                 ProofError::Message {
@@ -51,9 +68,18 @@ impl From<CoreServiceError> for ProofApiError {
     }
 }
 
+impl From<DataStoreError> for ProofApiError {
+    fn from(e: DataStoreError) -> Self {
+        Self::DataStore(e.into())
+    }
+}
+
 impl IntoResponse for ProofApiError {
     fn into_response(self) -> axum::response::Response {
-        (StatusCode::from_u16(self.0.status()).unwrap(), Json(self.0)).into_response()
+        match self {
+            Self::Proof(e) => (StatusCode::from_u16(e.status()).unwrap(), Json(e)).into_response(),
+            Self::DataStore(e) => e.into_response(),
+        }
     }
 }
 
@@ -63,14 +89,44 @@ async fn prove_consistency(
     RegistryHeader(_registry_header): RegistryHeader,
     Json(body): Json<ConsistencyRequest>,
 ) -> Result<Json<ConsistencyResponse>, ProofApiError> {
-    let bundle = config
+    let proof = config
         .core
         .log_consistency_proof(body.from as RegistryLen, body.to as RegistryLen)
         .await?;
 
-    Ok(Json(ConsistencyResponse {
-        proof: bundle.encode(),
-    }))
+    Ok(Json(ConsistencyResponse { proof }))
+}
+
+/// Proves the consistency between two checkpoints identified by their log
+/// roots.
+///
+/// Unlike `prove_consistency`, either checkpoint may be any checkpoint the
+/// registry has issued, which lets monitors audit the growth of the log
+/// between checkpoints they have observed.
+#[debug_handler]
+async fn prove_checkpoint_consistency(
+    State(config): State<Config>,
+    RegistryHeader(_registry_header): RegistryHeader,
+    Query(query): Query<CheckpointConsistencyQuery>,
+) -> Result<Json<ConsistencyResponse>, ProofApiError> {
+    let store = config.core.store();
+    let from = store.get_checkpoint_by_log_root(&query.from).await?;
+    let to = store.get_checkpoint_by_log_root(&query.to).await?;
+    let from = from.as_ref().checkpoint.log_length;
+    let to = to.as_ref().checkpoint.log_length;
+    if from > to {
+        return Err(ProofApiError::Proof(ProofError::Message {
+            status: StatusCode::UNPROCESSABLE_ENTITY.as_u16(),
+            message: format!(
+                "checkpoint with log root `{from_root}` follows checkpoint with log root `{to_root}`",
+                from_root = query.from,
+                to_root = query.to
+            ),
+        }));
+    }
+
+    let proof = config.core.log_consistency_proof(from, to).await?;
+    Ok(Json(ConsistencyResponse { proof }))
 }
 
 #[debug_handler]
@@ -86,11 +142,8 @@ async fn prove_inclusion(
         .map(|index| index as RegistryIndex)
         .collect::<Vec<RegistryIndex>>();
 
-    let log_bundle = config.core.log_inclusion_proofs(log_length, &leafs).await?;
-    let map_bundle = config.core.map_inclusion_proofs(log_length, &leafs).await?;
+    let log = config.core.log_inclusion_proofs(log_length, &leafs).await?;
+    let map = config.core.map_inclusion_proofs(log_length, &leafs).await?;
 
-    Ok(Json(InclusionResponse {
-        log: log_bundle.encode(),
-        map: map_bundle.encode(),
-    }))
+    Ok(Json(InclusionResponse { log, map }))
 }
@@ -0,0 +1,209 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    num::NonZeroU32,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::time::MissedTickBehavior;
+use warg_crypto::signing::KeyID;
+
+/// The most keys tracked at once; the least recently used key is forgotten
+/// to track another.
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+/// The interval at which keys whose buckets have refilled are forgotten.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The rate at which record submissions are allowed for a single key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// The number of submissions allowed per minute.
+    pub per_minute: NonZeroU32,
+    /// The number of submissions allowed in a burst.
+    pub burst: NonZeroU32,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// When the bucket will have refilled to its burst size.
+    full: Instant,
+    /// The order in which the key was last used.
+    used: u64,
+}
+
+#[derive(Default)]
+struct Buckets {
+    buckets: HashMap<KeyID, Bucket>,
+    /// The tracked keys by the order in which they were last used.
+    by_use: BTreeMap<u64, KeyID>,
+    next_use: u64,
+}
+
+/// Limits the rate of record submissions per signing key.
+///
+/// Each key has a token bucket that holds up to the burst size and refills at
+/// the rate of the limit; a submission takes one token.
+///
+/// The limit is given with each submission so that it may change while the
+/// server is running; buckets are capped to the new burst size as they refill.
+///
+/// At most [`MAX_TRACKED_KEYS`] keys are tracked; keys whose buckets have
+/// refilled are indistinguishable from keys that were never seen and are
+/// periodically forgotten.
+#[derive(Default)]
+pub struct SubmissionRateLimiter {
+    buckets: Mutex<Buckets>,
+}
+
+impl SubmissionRateLimiter {
+    /// Starts a rate limiter that periodically forgets keys whose buckets
+    /// have refilled.
+    ///
+    /// The limiter stops forgetting keys once it is dropped.
+    pub fn start() -> Arc<Self> {
+        let limiter = Arc::new(Self::default());
+
+        let weak = Arc::downgrade(&limiter);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            // The first tick completes immediately
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                match weak.upgrade() {
+                    Some(limiter) => limiter.sweep(),
+                    None => break,
+                }
+            }
+        });
+
+        limiter
+    }
+
+    /// Takes a submission for the given key under the given limit.
+    ///
+    /// Returns the duration to wait before retrying if the key has exceeded
+    /// the limit.
+    pub fn check(&self, key_id: &KeyID, limit: RateLimit) -> Result<(), Duration> {
+        let now = Instant::now();
+        // The number of tokens added to a bucket per second
+        let rate = f64::from(limit.per_minute.get()) / 60.0;
+        let capacity = f64::from(limit.burst.get());
+        let mut buckets = self.buckets.lock().unwrap();
+        let Buckets {
+            buckets,
+            by_use,
+            next_use,
+        } = &mut *buckets;
+
+        let used = *next_use;
+        *next_use += 1;
+        let bucket = match buckets.get_mut(key_id) {
+            Some(bucket) => {
+                by_use.remove(&bucket.used);
+                bucket
+            }
+            None => {
+                if buckets.len() >= MAX_TRACKED_KEYS {
+                    if let Some((_, key_id)) = by_use.pop_first() {
+                        buckets.remove(&key_id);
+                    }
+                }
+
+                buckets.entry(key_id.clone()).or_insert(Bucket {
+                    tokens: capacity,
+                    updated: now,
+                    full: now,
+                    used,
+                })
+            }
+        };
+        by_use.insert(used, key_id.clone());
+        bucket.used = used;
+
+        bucket.tokens =
+            (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate).min(capacity);
+        bucket.updated = now;
+
+        let res = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        };
+
+        bucket.full = now + Duration::from_secs_f64((capacity - bucket.tokens) / rate);
+        res
+    }
+
+    /// Forgets the keys whose buckets have refilled.
+    fn sweep(&self) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let Buckets {
+            buckets, by_use, ..
+        } = &mut *buckets;
+        buckets.retain(|_, bucket| {
+            let keep = bucket.full > now;
+            if !keep {
+                by_use.remove(&bucket.used);
+            }
+            keep
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(per_minute: u32, burst: u32) -> RateLimit {
+        RateLimit {
+            per_minute: NonZeroU32::new(per_minute).unwrap(),
+            burst: NonZeroU32::new(burst).unwrap(),
+        }
+    }
+
+    fn key_id(i: usize) -> KeyID {
+        KeyID::from(format!("sha256:{i:064x}"))
+    }
+
+    #[test]
+    fn it_tracks_a_bounded_number_of_keys() {
+        let limiter = SubmissionRateLimiter::default();
+        let limit = limit(1, 1);
+
+        // None of the buckets refill during the test
+        for i in 0..MAX_TRACKED_KEYS {
+            limiter.check(&key_id(i), limit).unwrap();
+        }
+        limiter.check(&key_id(0), limit).unwrap_err();
+
+        // Tracking another key forgets the least recently used one
+        limiter.check(&key_id(MAX_TRACKED_KEYS), limit).unwrap();
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.buckets.len(), MAX_TRACKED_KEYS);
+        assert_eq!(buckets.by_use.len(), MAX_TRACKED_KEYS);
+        assert!(buckets.buckets.contains_key(&key_id(0)));
+        assert!(!buckets.buckets.contains_key(&key_id(1)));
+    }
+
+    #[test]
+    fn it_forgets_refilled_buckets() {
+        let limiter = SubmissionRateLimiter::default();
+        let (idle, busy) = (key_id(0), key_id(1));
+        limiter.check(&idle, limit(60_000, 1)).unwrap();
+        limiter.check(&busy, limit(1, 1)).unwrap();
+
+        std::thread::sleep(Duration::from_millis(10));
+        limiter.sweep();
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(!buckets.buckets.contains_key(&idle));
+        assert!(buckets.buckets.contains_key(&busy));
+        assert_eq!(buckets.by_use.len(), 1);
+    }
+}
@@ -0,0 +1,29 @@
+use super::{Json, RegistryHeader};
+use crate::limits::SharedLimits;
+use axum::{debug_handler, extract::State, routing::get, Router};
+use warg_api::v1::registry::RegistryMetadata;
+
+#[derive(Clone)]
+pub struct Config {
+    limits: SharedLimits,
+}
+
+impl Config {
+    pub fn new(limits: SharedLimits) -> Self {
+        Self { limits }
+    }
+
+    pub fn into_router(self) -> Router {
+        Router::new().route("/", get(get_metadata)).with_state(self)
+    }
+}
+
+#[debug_handler]
+async fn get_metadata(
+    State(config): State<Config>,
+    RegistryHeader(_registry_header): RegistryHeader,
+) -> Json<RegistryMetadata> {
+    Json(RegistryMetadata {
+        record_limits: config.limits.load().record,
+    })
+}
@@ -0,0 +1,402 @@
+use super::{AuditFilter, AuditLog, AuditLogError};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+use warg_api::v1::admin::AuditEntry;
+use warg_crypto::hash::{AnyHash, HashAlgorithm};
+
+/// The default maximum size of an audit log file before it is rotated.
+pub const DEFAULT_MAX_AUDIT_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// The default number of rotated audit log files to keep.
+pub const DEFAULT_MAX_AUDIT_FILES: usize = 5;
+
+/// An audit log that appends entries to a file as JSON lines.
+///
+/// When the file would exceed its maximum size, it is rotated: `audit.log`
+/// is renamed to `audit.log.1`, `audit.log.1` to `audit.log.2`, and so on,
+/// with the oldest file beyond the maximum number of files removed.
+///
+/// When hash chaining is enabled, each line records the hash of the line
+/// before it (continuing across rotated files), so that modifying or removing
+/// an entry can be detected with [`verify_audit_files`].
+pub struct FileAuditLog {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    hash_chain: bool,
+    state: Mutex<FileState>,
+}
+
+struct FileState {
+    file: File,
+    /// The hash of the last line appended when hash chaining is enabled.
+    ///
+    /// This is `None` until the first append, when it is read from the files.
+    last_hash: Option<Option<AnyHash>>,
+}
+
+/// A line of a hash-chained audit log file.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChainedLine<'a> {
+    #[serde(flatten)]
+    entry: Cow<'a, AuditEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prev_hash: Option<AnyHash>,
+}
+
+fn line_hash(line: &str) -> AnyHash {
+    HashAlgorithm::Sha256.digest(line.as_bytes())
+}
+
+async fn read_lines(path: &Path) -> Result<Option<String>, AuditLogError> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn non_empty_lines(contents: &str) -> impl DoubleEndedIterator<Item = &str> {
+    contents.lines().filter(|l| !l.trim().is_empty())
+}
+
+/// Verifies the hash chain of the given audit log files.
+///
+/// The files must be given oldest first (e.g. `audit.log.2`, `audit.log.1`,
+/// `audit.log`); the chain is expected to continue from one file to the next.
+/// The first entry of the first file is not checked as the files it follows
+/// may have been removed by rotation.
+///
+/// Returns the number of entries verified.
+pub async fn verify_audit_files(paths: &[impl AsRef<Path>]) -> Result<usize, AuditLogError> {
+    let mut prev: Option<AnyHash> = None;
+    let mut count = 0;
+    for path in paths {
+        let path = path.as_ref();
+        let contents = tokio::fs::read_to_string(path).await?;
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let chained: ChainedLine = serde_json::from_str(line)?;
+            if let Some(expected) = &prev {
+                if chained.prev_hash.as_ref() != Some(expected) {
+                    return Err(AuditLogError::BrokenChain {
+                        path: path.to_path_buf(),
+                        line: index + 1,
+                    });
+                }
+            }
+
+            prev = Some(line_hash(line));
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+impl FileAuditLog {
+    /// Opens the audit log at the given path, creating it if it does not exist.
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self, AuditLogError> {
+        let path = path.into();
+        let file = Self::open_file(&path).await?;
+        Ok(Self {
+            path,
+            max_size: DEFAULT_MAX_AUDIT_FILE_SIZE,
+            max_files: DEFAULT_MAX_AUDIT_FILES,
+            hash_chain: false,
+            state: Mutex::new(FileState {
+                file,
+                last_hash: None,
+            }),
+        })
+    }
+
+    /// Sets the maximum size of the audit log file before it is rotated.
+    ///
+    /// A maximum size of zero disables rotation.
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Sets the number of rotated audit log files to keep.
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// Sets whether each appended entry is chained to the previous entry by hash.
+    pub fn with_hash_chain(mut self, hash_chain: bool) -> Self {
+        self.hash_chain = hash_chain;
+        self
+    }
+
+    /// Gets the path of the current audit log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the path of the rotated audit log file with the given index.
+    pub fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self
+            .path
+            .file_name()
+            .map(OsString::from)
+            .unwrap_or_default();
+        name.push(format!(".{index}"));
+        self.path.with_file_name(name)
+    }
+
+    async fn open_file(path: &Path) -> Result<File, AuditLogError> {
+        Ok(OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?)
+    }
+
+    /// Gets the hash of the last line of the current (or, if empty, most recently rotated) file.
+    async fn last_line_hash(&self) -> Result<Option<AnyHash>, AuditLogError> {
+        for path in [self.path.clone(), self.rotated_path(1)] {
+            if let Some(contents) = read_lines(&path).await? {
+                if let Some(line) = non_empty_lines(&contents).next_back() {
+                    return Ok(Some(line_hash(line)));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn rotate(&self, file: &mut File) -> Result<(), AuditLogError> {
+        file.flush().await?;
+
+        if self.max_files == 0 {
+            tokio::fs::remove_file(&self.path).await?;
+        } else {
+            let oldest = self.rotated_path(self.max_files);
+            if tokio::fs::try_exists(&oldest).await? {
+                tokio::fs::remove_file(&oldest).await?;
+            }
+
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if tokio::fs::try_exists(&from).await? {
+                    tokio::fs::rename(&from, self.rotated_path(index + 1)).await?;
+                }
+            }
+
+            tokio::fs::rename(&self.path, self.rotated_path(1)).await?;
+        }
+
+        *file = Self::open_file(&self.path).await?;
+        Ok(())
+    }
+}
+
+#[axum::async_trait]
+impl AuditLog for FileAuditLog {
+    async fn append(&self, entry: &AuditEntry) -> Result<(), AuditLogError> {
+        let mut state = self.state.lock().await;
+
+        let mut line = if self.hash_chain {
+            let prev_hash = match state.last_hash.take() {
+                Some(hash) => hash,
+                None => self.last_line_hash().await?,
+            };
+
+            serde_json::to_string(&ChainedLine {
+                entry: Cow::Borrowed(entry),
+                prev_hash,
+            })?
+        } else {
+            serde_json::to_string(entry)?
+        };
+
+        let hash = self.hash_chain.then(|| line_hash(&line));
+        line.push('\n');
+
+        let len = state.file.metadata().await?.len();
+        if self.max_size > 0 && len > 0 && len + line.len() as u64 > self.max_size {
+            self.rotate(&mut state.file).await?;
+        }
+
+        state.file.write_all(line.as_bytes()).await?;
+        state.file.flush().await?;
+        state.last_hash = hash.map(Some);
+        Ok(())
+    }
+
+    async fn recent(
+        &self,
+        filter: &AuditFilter,
+        limit: usize,
+    ) -> Result<Vec<AuditEntry>, AuditLogError> {
+        // Hold the lock so the files are not rotated while being read
+        let _state = self.state.lock().await;
+
+        let paths = (1..=self.max_files)
+            .rev()
+            .map(|index| self.rotated_path(index))
+            .chain(std::iter::once(self.path.clone()));
+
+        let mut entries = VecDeque::with_capacity(limit);
+        for path in paths {
+            let Some(contents) = read_lines(&path).await? else {
+                continue;
+            };
+
+            for line in non_empty_lines(&contents) {
+                let entry: AuditEntry = serde_json::from_str(line)?;
+                if !filter.matches(&entry) {
+                    continue;
+                }
+
+                if entries.len() == limit {
+                    entries.pop_front();
+                }
+
+                if limit > 0 {
+                    entries.push_back(entry);
+                }
+            }
+        }
+
+        Ok(entries.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warg_api::v1::admin::AuditOperation;
+    use warg_crypto::hash::Sha256;
+    use warg_protocol::registry::{LogId, PackageName};
+
+    fn entry(package: &str) -> AuditEntry {
+        let package = PackageName::new(package).unwrap();
+        let mut entry = AuditEntry::new(AuditOperation::RecordSubmitted);
+        entry.log_id = Some(LogId::package_log::<Sha256>(&package));
+        entry.package = Some(package);
+        entry
+    }
+
+    #[tokio::test]
+    async fn it_rotates_files() -> Result<(), AuditLogError> {
+        let dir = tempfile::tempdir()?;
+        let line_len = serde_json::to_string(&entry("test:a"))?.len() as u64 + 1;
+        let log = FileAuditLog::open(dir.path().join("audit.log"))
+            .await?
+            .with_max_size(line_len * 2)
+            .with_max_files(2);
+
+        for _ in 0..7 {
+            log.append(&entry("test:a")).await?;
+        }
+
+        // Two entries per file: the current file has one entry and two rotated files are kept
+        assert!(log.rotated_path(1).is_file());
+        assert!(log.rotated_path(2).is_file());
+        assert!(!log.rotated_path(3).exists());
+        assert_eq!(log.recent(&AuditFilter::default(), 100).await?.len(), 5);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_filters_recent_entries() -> Result<(), AuditLogError> {
+        let dir = tempfile::tempdir()?;
+        let log = FileAuditLog::open(dir.path().join("audit.log")).await?;
+
+        for package in ["test:a", "test:b", "test:a", "test:c", "test:a"] {
+            log.append(&entry(package)).await?;
+        }
+
+        let package = PackageName::new("test:a").unwrap();
+        let filter = AuditFilter::new(Some(LogId::package_log::<Sha256>(&package)), None);
+        assert_eq!(log.recent(&filter, 100).await?.len(), 3);
+        assert_eq!(log.recent(&filter, 2).await?.len(), 2);
+        assert_eq!(log.recent(&AuditFilter::default(), 100).await?.len(), 5);
+
+        let entries = log.recent(&AuditFilter::default(), 2).await?;
+        assert_eq!(
+            entries
+                .iter()
+                .map(|e| e.package.as_ref().unwrap().as_ref())
+                .collect::<Vec<_>>(),
+            ["test:c", "test:a"]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_verifies_hash_chains() -> Result<(), AuditLogError> {
+        let dir = tempfile::tempdir()?;
+        let line_len = serde_json::to_string(&ChainedLine {
+            entry: Cow::Owned(entry("test:a")),
+            prev_hash: Some(line_hash("")),
+        })?
+        .len() as u64
+            + 1;
+        let path = dir.path().join("audit.log");
+
+        let log = FileAuditLog::open(&path)
+            .await?
+            .with_max_size(line_len * 3)
+            .with_max_files(2)
+            .with_hash_chain(true);
+        for _ in 0..4 {
+            log.append(&entry("test:a")).await?;
+        }
+        drop(log);
+
+        // The chain continues when the log is reopened
+        let log = FileAuditLog::open(&path)
+            .await?
+            .with_max_size(line_len * 3)
+            .with_max_files(2)
+            .with_hash_chain(true);
+        log.append(&entry("test:b")).await?;
+
+        let files = [log.rotated_path(1), path.clone()];
+        assert_eq!(verify_audit_files(&files).await?, 5);
+        assert_eq!(log.recent(&AuditFilter::default(), 100).await?.len(), 5);
+
+        // Modifying an entry breaks the chain at the following entry
+        let contents = tokio::fs::read_to_string(&files[0]).await?;
+        tokio::fs::write(&files[0], contents.replacen("test:a", "test:z", 2)).await?;
+        match verify_audit_files(&files).await {
+            Err(AuditLogError::BrokenChain { path, line }) => {
+                assert_eq!(path, files[0]);
+                assert_eq!(line, 2);
+            }
+            res => panic!("unexpected result: {res:?}"),
+        }
+
+        // An unchained log does not verify
+        let log = FileAuditLog::open(dir.path().join("unchained.log")).await?;
+        log.append(&entry("test:a")).await?;
+        log.append(&entry("test:a")).await?;
+        assert!(matches!(
+            verify_audit_files(&[log.path()]).await,
+            Err(AuditLogError::BrokenChain { line: 2, .. })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+//! Module for the server's audit log of mutating operations.
+
+use crate::datastore::{DataStore, DataStoreError};
+use std::{path::PathBuf, sync::Arc};
+use thiserror::Error;
+use warg_api::v1::admin::AuditEntry;
+use warg_crypto::signing::KeyID;
+use warg_protocol::registry::{LogId, PackageName};
+
+mod file;
+
+pub use file::*;
+
+/// Represents an error from an audit log.
+#[derive(Debug, Error)]
+pub enum AuditLogError {
+    /// An entry could not be serialized or deserialized.
+    #[error("invalid audit entry: {0}")]
+    InvalidEntry(#[from] serde_json::Error),
+    /// A data store error occurred.
+    #[error(transparent)]
+    DataStore(#[from] DataStoreError),
+    /// An I/O error occurred.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// An entry of a hash-chained audit log does not follow the previous entry.
+    #[error("audit log entry on line {line} of `{path}` does not match the hash of the previous entry", path = path.display())]
+    BrokenChain {
+        /// The path of the audit log file.
+        path: PathBuf,
+        /// The line number of the entry.
+        line: usize,
+    },
+}
+
+/// Filters the entries returned from an audit log.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AuditFilter {
+    /// Only match entries for the given log.
+    pub log_id: Option<LogId>,
+    /// Only match entries for records signed by the given key.
+    pub key_id: Option<KeyID>,
+}
+
+impl AuditFilter {
+    /// Creates a filter for the given log and key id.
+    pub fn new(log_id: Option<LogId>, key_id: Option<KeyID>) -> Self {
+        Self { log_id, key_id }
+    }
+
+    /// Determines if the given entry matches the filter.
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.log_id
+            .as_ref()
+            .map(|id| entry.log_id.as_ref() == Some(id))
+            .unwrap_or(true)
+            && self
+                .key_id
+                .as_ref()
+                .map(|id| entry.key_id.as_ref() == Some(id))
+                .unwrap_or(true)
+    }
+}
+
+/// Implemented by audit logs.
+///
+/// Audit logs are append-only; entries are never modified once appended.
+#[axum::async_trait]
+pub trait AuditLog: Send + Sync {
+    /// Appends an entry to the audit log.
+    async fn append(&self, entry: &AuditEntry) -> Result<(), AuditLogError>;
+
+    /// Gets the most recent entries matching the filter, oldest first.
+    async fn recent(
+        &self,
+        filter: &AuditFilter,
+        limit: usize,
+    ) -> Result<Vec<AuditEntry>, AuditLogError>;
+}
+
+/// An audit log that stores entries in the server's data store.
+pub struct DataStoreAuditLog(Arc<dyn DataStore>);
+
+impl DataStoreAuditLog {
+    /// Creates a new audit log backed by the given data store.
+    pub fn new(store: Arc<dyn DataStore>) -> Self {
+        Self(store)
+    }
+}
+
+#[axum::async_trait]
+impl AuditLog for DataStoreAuditLog {
+    async fn append(&self, entry: &AuditEntry) -> Result<(), AuditLogError> {
+        Ok(self.0.store_audit_entry(entry).await?)
+    }
+
+    async fn recent(
+        &self,
+        filter: &AuditFilter,
+        limit: usize,
+    ) -> Result<Vec<AuditEntry>, AuditLogError> {
+        Ok(self.0.get_audit_entries(filter, limit).await?)
+    }
+}
+
+/// Appends an entry to the given audit log, if any.
+///
+/// Failing to append to the audit log never fails the audited operation;
+/// the failure is logged instead.
+pub async fn append(audit_log: Option<&dyn AuditLog>, entry: AuditEntry) {
+    if let Some(audit_log) = audit_log {
+        if let Err(e) = audit_log.append(&entry).await {
+            tracing::error!("failed to append to the audit log: {e}; entry: {entry:?}");
+        }
+    }
+}
+
+/// Looks up the name of the package with the given log for an audit entry.
+pub(crate) async fn package_name(store: &dyn DataStore, log_id: &LogId) -> Option<PackageName> {
+    store
+        .get_package_names(std::slice::from_ref(log_id))
+        .await
+        .ok()?
+        .swap_remove(log_id)
+        .flatten()
+}
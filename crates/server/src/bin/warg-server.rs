@@ -1,13 +1,32 @@
-use anyhow::{Context, Result};
-use clap::{Parser, ValueEnum};
-use secrecy::SecretString;
-use std::{net::SocketAddr, path::PathBuf};
+use anyhow::{bail, Context, Result};
+use clap::{Args as ClapArgs, Parser, Subcommand, ValueEnum};
+use secrecy::{ExposeSecret, SecretString};
+use std::{net::SocketAddr, num::NonZeroU32, path::PathBuf, time::Duration};
 use tokio::signal;
 use tracing_subscriber::filter::LevelFilter;
 use url::Url;
-use warg_crypto::signing::PrivateKey;
-use warg_protocol::operator;
-use warg_server::{args::get_opt_secret, policy::record::AuthorizedKeyPolicy, Config, Server};
+use warg_api::v1::{
+    admin::{AdminError, AuditEntriesQuery, AuditEntriesResponse},
+    paths,
+};
+use warg_crypto::{
+    hash::HashAlgorithm,
+    signing::{KeyID, PrivateKey},
+};
+use warg_protocol::{operator, registry::PackageName};
+use warg_server::{
+    api::v1::rate_limit::RateLimit,
+    args::get_opt_secret,
+    audit::{
+        verify_audit_files, FileAuditLog, DEFAULT_MAX_AUDIT_FILES, DEFAULT_MAX_AUDIT_FILE_SIZE,
+    },
+    config::{ContentStoreConfig, DataStoreConfig, ServerConfig},
+    content::{ContentEncryption, MasterKey},
+    limits::{Limits, SharedLimits},
+    policy::record::{validate_artifact_type, AuthorizedKeyPolicy},
+    services::{Webhook, DEFAULT_DOWNLOAD_FLUSH_INTERVAL},
+    Config, Server,
+};
 
 #[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
 enum DataStoreKind {
@@ -17,27 +36,114 @@ enum DataStoreKind {
     Memory,
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum ContentStoreKind {
+    #[default]
+    FileSystem,
+    #[cfg(feature = "s3")]
+    S3,
+}
+
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
     /// Use verbose output
-    #[arg(short, long, env = "WARG_VERBOSE", action = clap::ArgAction::Count)]
+    #[arg(short, long, env = "WARG_VERBOSE", action = clap::ArgAction::Count, global = true)]
     verbose: u8,
 
-    /// Address to listen to
-    #[arg(short, long, env = "WARG_LISTEN", default_value = "127.0.0.1:8090")]
-    listen: SocketAddr,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The path to the server configuration file.
+    ///
+    /// Options given on the command line take precedence over the file. The
+    /// limits and rate limits of the file are reloaded on SIGHUP.
+    #[arg(long, env = "WARG_CONFIG_FILE")]
+    config: Option<PathBuf>,
+
+    /// Address to listen to (defaults to `127.0.0.1:8090`).
+    #[arg(short, long, env = "WARG_LISTEN")]
+    listen: Option<SocketAddr>,
 
     /// The content storage directory to use.
+    ///
+    /// Required unless specified in the configuration file.
     #[arg(long, env = "WARG_CONTENT_DIR")]
-    content_dir: PathBuf,
+    content_dir: Option<PathBuf>,
+
+    /// The content store to use for the server (defaults to `file-system`).
+    ///
+    /// Partial uploads are kept in the content directory regardless of the store.
+    #[arg(long, env = "WARG_CONTENT_STORE")]
+    content_store: Option<ContentStoreKind>,
+
+    /// The endpoint URL of the S3-compatible object store if content-store is set to s3.
+    ///
+    /// Credentials are read from the `AWS_ACCESS_KEY_ID` and
+    /// `AWS_SECRET_ACCESS_KEY` environment variables.
+    #[cfg(feature = "s3")]
+    #[arg(long, env = "WARG_S3_ENDPOINT")]
+    s3_endpoint: Option<Url>,
+
+    /// The bucket to store content in if content-store is set to s3.
+    #[cfg(feature = "s3")]
+    #[arg(long, env = "WARG_S3_BUCKET")]
+    s3_bucket: Option<String>,
+
+    /// The region of the bucket (defaults to `us-east-1`).
+    #[cfg(feature = "s3")]
+    #[arg(long, env = "WARG_S3_REGION")]
+    s3_region: Option<String>,
+
+    /// The key prefix to store content under in the bucket.
+    #[cfg(feature = "s3")]
+    #[arg(long, env = "WARG_S3_PREFIX")]
+    s3_prefix: Option<String>,
 
     /// The base content URL to use; defaults to the server address.
     #[arg(long, env = "WARG_CONTENT_BASE_URL")]
     content_base_url: Option<Url>,
 
-    /// The data store to use for the server.
-    #[arg(long, env = "WARG_DATA_STORE", default_value = "memory")]
-    data_store: DataStoreKind,
+    /// The path to the hex-encoded master key used to encrypt content at rest.
+    ///
+    /// If not specified, content is stored unencrypted.
+    #[arg(long, env = "WARG_CONTENT_ENCRYPTION_KEY_FILE")]
+    content_encryption_key_file: Option<PathBuf>,
+
+    /// The path to a previous content encryption master key.
+    ///
+    /// Content encrypted with a previous key is re-wrapped with the current
+    /// key when the server starts.
+    #[arg(
+        long,
+        env = "WARG_CONTENT_ENCRYPTION_PREVIOUS_KEY_FILE",
+        requires = "content_encryption_key_file",
+        value_delimiter = ','
+    )]
+    content_encryption_previous_key_file: Vec<PathBuf>,
+
+    /// A host, other than the content host, that content sources specified
+    /// when publishing may be served from.
+    #[arg(long, env = "WARG_CONTENT_SOURCE_HOSTS", value_delimiter = ',')]
+    content_source_host: Vec<String>,
+
+    /// The maximum size, in bytes, of content declared by a content source.
+    #[arg(long, env = "WARG_MAX_CONTENT_SOURCE_SIZE")]
+    max_content_source_size: Option<u64>,
+
+    /// A type of release artifact, other than `component`, `wit`, and
+    /// `debug`, that may be published.
+    #[arg(
+        long,
+        env = "WARG_ARTIFACT_TYPES",
+        value_delimiter = ',',
+        value_parser = parse_artifact_type
+    )]
+    artifact_type: Vec<String>,
+
+    /// The data store to use for the server (defaults to `memory`).
+    #[arg(long, env = "WARG_DATA_STORE")]
+    data_store: Option<DataStoreKind>,
 
     /// The database connection URL if data-store is set to postgres.
     ///
@@ -67,6 +173,23 @@ struct Args {
     #[arg(long, env = "WARG_OPERATOR_KEY_FILE", conflicts_with = "operator_key")]
     operator_key_file: Option<PathBuf>,
 
+    /// The key the operator key is being rotated to.
+    ///
+    /// Checkpoints are signed with this key once a rotation to it is
+    /// published to the operator log.
+    ///
+    /// Prefer using `next-operator-key-file`, or environment variable variation.
+    #[arg(long, env = "WARG_NEXT_OPERATOR_KEY")]
+    next_operator_key: Option<SecretString>,
+
+    /// The path to the key the operator key is being rotated to.
+    #[arg(
+        long,
+        env = "WARG_NEXT_OPERATOR_KEY_FILE",
+        conflicts_with = "next_operator_key"
+    )]
+    next_operator_key_file: Option<PathBuf>,
+
     /// The path to the authorized keys record policy file.
     #[arg(long, env = "WARG_AUTHORIZED_KEYS_FILE")]
     authorized_keys_file: Option<PathBuf>,
@@ -74,6 +197,294 @@ struct Args {
     /// The initial namespace defined for this registry.
     #[arg(long, env = "WARG_NAMESPACE")]
     namespace: Option<String>,
+
+    /// The hash algorithm of log IDs, record IDs, and checkpoints (defaults to `sha256`).
+    ///
+    /// The algorithm cannot be changed once the registry has been initialized.
+    #[arg(long, env = "WARG_HASH_ALGORITHM")]
+    hash_algorithm: Option<HashAlgorithm>,
+
+    /// The most records included in a single checkpoint.
+    ///
+    /// A checkpoint is issued as soon as this many records are validated.
+    #[arg(long, env = "WARG_MAX_CHECKPOINT_LEAVES")]
+    max_checkpoint_leaves: Option<usize>,
+
+    /// The fewest records checkpointed before the checkpoint interval elapses.
+    ///
+    /// Once this many records are validated, a checkpoint is issued as soon as
+    /// no more submissions are queued.
+    #[arg(long, env = "WARG_MIN_CHECKPOINT_LEAVES")]
+    min_checkpoint_leaves: Option<usize>,
+
+    /// The maximum number of seconds between checkpoints declared to clients.
+    ///
+    /// Clients may treat the registry as stale when its latest checkpoint is older.
+    #[arg(long, env = "WARG_MAX_CHECKPOINT_INTERVAL")]
+    max_checkpoint_interval: Option<u64>,
+
+    /// The number of seconds a checkpoint is valid for.
+    ///
+    /// Checkpoints are signed with an expiry and re-signed before it passes;
+    /// clients refuse the state of a registry whose latest checkpoint expired.
+    #[arg(long, env = "WARG_CHECKPOINT_VALIDITY")]
+    checkpoint_validity: Option<u64>,
+
+    /// The number of records that may be waiting to be processed (defaults to 256).
+    ///
+    /// When the queue is full, submissions are rejected with a 503 response.
+    #[arg(long, env = "WARG_SUBMISSION_QUEUE_DEPTH")]
+    submission_queue_depth: Option<usize>,
+
+    /// The number of milliseconds a submission waits for room in a full queue (defaults to 1000).
+    ///
+    /// Submissions still waiting when it elapses are rejected with a 503 response.
+    #[arg(long, env = "WARG_SUBMISSION_TIMEOUT_MS")]
+    submission_timeout_ms: Option<u64>,
+
+    #[command(flatten)]
+    limits: LimitArgs,
+
+    /// The maximum number of seconds a record's timestamp may differ from the server's clock (defaults to one hour).
+    ///
+    /// Submitted records outside of the window are rejected; `0` disables the check.
+    #[arg(long, env = "WARG_MAX_TIMESTAMP_SKEW")]
+    max_timestamp_skew: Option<u64>,
+
+    /// The path to the audit log file to append mutating operations to.
+    #[arg(long, env = "WARG_AUDIT_LOG_FILE")]
+    audit_log_file: Option<PathBuf>,
+
+    /// The maximum size in bytes of the audit log file before it is rotated (defaults to 10 MiB).
+    #[arg(long, env = "WARG_AUDIT_LOG_MAX_SIZE")]
+    audit_log_max_size: Option<u64>,
+
+    /// The number of rotated audit log files to keep (defaults to 5).
+    #[arg(long, env = "WARG_AUDIT_LOG_MAX_FILES")]
+    audit_log_max_files: Option<usize>,
+
+    /// Chain each audit log entry to the previous entry by hash.
+    ///
+    /// The chain may be checked with `warg-server audit verify`.
+    #[arg(long, env = "WARG_AUDIT_LOG_HASH_CHAIN", requires = "audit_log_file")]
+    audit_log_hash_chain: bool,
+
+    /// Keep the audit log in the data store instead of a file.
+    #[arg(
+        long,
+        env = "WARG_AUDIT_LOG_DATA_STORE",
+        conflicts_with = "audit_log_file"
+    )]
+    audit_log_data_store: bool,
+
+    /// The bearer token required by the administration API.
+    ///
+    /// If neither this nor `admin-token-file` is specified, the
+    /// administration API is disabled.
+    #[arg(long, env = "WARG_ADMIN_TOKEN")]
+    admin_token: Option<SecretString>,
+
+    /// The path to the bearer token required by the administration API.
+    #[arg(long, env = "WARG_ADMIN_TOKEN_FILE", conflicts_with = "admin_token")]
+    admin_token_file: Option<PathBuf>,
+
+    /// Collect package download statistics.
+    ///
+    /// Counts are flushed to the data store every minute.
+    #[arg(long, env = "WARG_DOWNLOAD_STATS")]
+    download_stats: bool,
+
+    /// The number of seconds between removals of unreferenced content.
+    ///
+    /// If not set, unreferenced content is only removed through the administration API.
+    #[arg(long, env = "WARG_CONTENT_GC_INTERVAL")]
+    content_gc_interval: Option<u64>,
+
+    /// The number of seconds unreferenced content is kept before it may be removed (defaults to one day).
+    #[arg(long, env = "WARG_CONTENT_GC_GRACE_PERIOD")]
+    content_gc_grace_period: Option<u64>,
+
+    /// Run as a read-only mirror of the registry at the given URL.
+    ///
+    /// The upstream's log, checkpoints, and content are replicated in the
+    /// background; submissions are rejected with a 403 response.
+    #[arg(long, env = "WARG_MIRROR", value_name = "UPSTREAM_URL")]
+    mirror: Option<Url>,
+
+    /// The number of seconds between syncs with the upstream registry (defaults to 10).
+    #[arg(long, env = "WARG_MIRROR_SYNC_INTERVAL", requires = "mirror")]
+    mirror_sync_interval: Option<u64>,
+
+    /// Replicate the upstream registry over its gRPC replication service at
+    /// the given endpoint instead of its JSON API.
+    #[cfg(feature = "grpc")]
+    #[arg(
+        long,
+        env = "WARG_MIRROR_GRPC_ENDPOINT",
+        value_name = "ENDPOINT",
+        requires = "mirror"
+    )]
+    mirror_grpc_endpoint: Option<Url>,
+
+    /// Serve the gRPC replication service on the given address.
+    #[cfg(feature = "grpc")]
+    #[arg(long, env = "WARG_GRPC_ADDR")]
+    grpc_addr: Option<SocketAddr>,
+}
+
+/// The limits that may also be set in the configuration file.
+#[derive(ClapArgs, Debug, Clone, Copy)]
+struct LimitArgs {
+    /// The number of records each signing key may submit per minute.
+    ///
+    /// When exceeded, submissions are rejected with a 429 response.
+    #[arg(long, env = "WARG_SUBMISSION_RATE_LIMIT")]
+    submission_rate_limit: Option<NonZeroU32>,
+
+    /// The number of records each signing key may submit in a burst (defaults to the rate limit).
+    #[arg(
+        long,
+        env = "WARG_SUBMISSION_BURST",
+        requires = "submission_rate_limit"
+    )]
+    submission_burst: Option<NonZeroU32>,
+
+    /// The maximum size, in bytes, of a submitted record's serialized envelope.
+    #[arg(long, env = "WARG_MAX_RECORD_SIZE")]
+    max_record_size: Option<u64>,
+
+    /// The maximum number of entries in a submitted record.
+    #[arg(long, env = "WARG_MAX_RECORD_ENTRIES")]
+    max_record_entries: Option<u32>,
+}
+
+impl LimitArgs {
+    /// Applies the limits given on the command line to the given limits.
+    fn apply(&self, mut limits: Limits) -> Limits {
+        if let Some(per_minute) = self.submission_rate_limit {
+            limits.submission_rate = Some(RateLimit {
+                per_minute,
+                burst: self.submission_burst.unwrap_or(per_minute),
+            });
+        }
+
+        if let Some(max_size) = self.max_record_size {
+            limits.record.max_record_size = Some(max_size);
+        }
+
+        if let Some(max_entries) = self.max_record_entries {
+            limits.record.max_record_entries = Some(max_entries);
+        }
+
+        limits
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Administer a running registry server.
+    #[command(subcommand)]
+    Admin(AdminCommand),
+    /// Inspect audit log files.
+    #[command(subcommand)]
+    Audit(AuditFileCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum AdminCommand {
+    /// Query the audit log of the server.
+    #[command(subcommand)]
+    Audit(AuditCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum AuditCommand {
+    /// Print the most recent audit log entries as JSON lines.
+    Tail(AuditTailArgs),
+}
+
+#[derive(Subcommand, Debug)]
+enum AuditFileCommand {
+    /// Verify the hash chain of audit log files.
+    Verify(AuditVerifyArgs),
+}
+
+#[derive(ClapArgs, Debug)]
+struct AuditVerifyArgs {
+    /// The audit log files to verify, oldest first.
+    #[arg(required = true, value_name = "FILE")]
+    files: Vec<PathBuf>,
+}
+
+impl AuditVerifyArgs {
+    async fn exec(self) -> Result<()> {
+        let count = verify_audit_files(&self.files)
+            .await
+            .context("failed to verify the audit log")?;
+        println!("verified {count} audit log entries");
+        Ok(())
+    }
+}
+
+#[derive(ClapArgs, Debug)]
+struct AuditTailArgs {
+    /// The URL of the registry server.
+    #[arg(long, env = "WARG_ADMIN_URL", default_value = "http://127.0.0.1:8090")]
+    url: Url,
+
+    /// The bearer token of the administration API.
+    ///
+    /// Prefer using `admin-token-file`, or environment variable variation.
+    #[arg(long, env = "WARG_ADMIN_TOKEN")]
+    admin_token: Option<SecretString>,
+
+    /// The path to the bearer token of the administration API.
+    #[arg(long, env = "WARG_ADMIN_TOKEN_FILE", conflicts_with = "admin_token")]
+    admin_token_file: Option<PathBuf>,
+
+    /// Only print entries for the given package.
+    #[arg(long, value_name = "PACKAGE")]
+    package: Option<PackageName>,
+
+    /// Only print entries for records signed by the given key id.
+    #[arg(long, value_name = "KEY_ID")]
+    key_id: Option<String>,
+
+    /// The maximum number of entries to print.
+    #[arg(long, short = 'n', default_value = "20")]
+    limit: u16,
+}
+
+impl AuditTailArgs {
+    async fn exec(self) -> Result<()> {
+        let token = get_opt_secret("admin-token", self.admin_token_file, self.admin_token)?;
+        let url = self.url.join(paths::admin_audit())?;
+        let response = reqwest::Client::new()
+            .get(url)
+            .bearer_auth(token.expose_secret().trim())
+            .query(&AuditEntriesQuery {
+                package: self.package,
+                key_id: self.key_id.map(KeyID::from),
+                limit: Some(self.limit),
+            })
+            .send()
+            .await
+            .context("failed to query the audit log")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            match response.json::<AdminError>().await {
+                Ok(e) => bail!("failed to query the audit log: {e}"),
+                Err(_) => bail!("failed to query the audit log: server returned {status}"),
+            }
+        }
+
+        for entry in response.json::<AuditEntriesResponse>().await?.entries {
+            println!("{entry}", entry = serde_json::to_string(&entry)?);
+        }
+
+        Ok(())
+    }
 }
 
 impl Args {
@@ -95,6 +506,21 @@ async fn main() -> Result<()> {
     args.init_tracing();
     tracing::debug!("args: {args:?}");
 
+    match args.command {
+        Some(Command::Admin(AdminCommand::Audit(AuditCommand::Tail(tail)))) => {
+            return tail.exec().await
+        }
+        Some(Command::Audit(AuditFileCommand::Verify(verify))) => return verify.exec().await,
+        None => {}
+    }
+
+    let file = args
+        .config
+        .as_deref()
+        .map(ServerConfig::from_file)
+        .transpose()?
+        .unwrap_or_default();
+
     let operator_key_str =
         get_opt_secret("operator-key", args.operator_key_file, args.operator_key)?;
     let operator_key =
@@ -104,14 +530,182 @@ async fn main() -> Result<()> {
         .as_ref()
         .map(|namespace| vec![(namespace.to_lowercase(), operator::NamespaceState::Defined)]);
 
-    let mut config = Config::new(operator_key, namespaces, args.content_dir)
-        .with_addr(args.listen)
-        .with_shutdown(shutdown_signal());
+    let limits = args.limits.apply(file.limits());
+    let content_dir = args.content_dir.or(file.content_dir).context(
+        "a content directory is required; specify `--content-dir` or `content-dir` in the configuration file",
+    )?;
+    let mut config = Config::new(operator_key, namespaces, content_dir)
+        .with_shutdown(shutdown_signal())
+        .with_limits(limits);
+
+    if let Some(addr) = args.listen.or(file.listen) {
+        config = config.with_addr(addr);
+    }
+
+    if args.next_operator_key.is_some() || args.next_operator_key_file.is_some() {
+        let next_operator_key_str = get_opt_secret(
+            "next-operator-key",
+            args.next_operator_key_file,
+            args.next_operator_key,
+        )?;
+        config = config.with_next_operator_key(
+            PrivateKey::decode(next_operator_key_str)
+                .context("failed to parse next operator key")?,
+        );
+    }
 
-    if let Some(url) = args.content_base_url {
+    if let Some(url) = args.content_base_url.or(file.content_base_url) {
         config = config.with_content_base_url(url);
     }
 
+    if let Some(algorithm) = args.hash_algorithm {
+        config = config.with_hash_algorithm(algorithm);
+    }
+
+    if let Some(interval) = file.checkpoints.interval() {
+        config = config.with_checkpoint_interval(interval);
+    }
+
+    if let Some(leaves) = args
+        .max_checkpoint_leaves
+        .or(file.checkpoints.max_leaves.map(|leaves| leaves.get()))
+    {
+        config = config.with_max_checkpoint_leaves(leaves);
+    }
+
+    if let Some(leaves) = args
+        .min_checkpoint_leaves
+        .or(file.checkpoints.min_leaves.map(|leaves| leaves.get()))
+    {
+        config = config.with_min_checkpoint_leaves(leaves);
+    }
+
+    if let Some(interval) = args
+        .max_checkpoint_interval
+        .map(Duration::from_secs)
+        .or(file.checkpoints.max_interval())
+    {
+        config = config.with_max_checkpoint_interval(interval);
+    }
+
+    if let Some(validity) = args
+        .checkpoint_validity
+        .map(Duration::from_secs)
+        .or(file.checkpoints.validity())
+    {
+        config = config.with_checkpoint_validity(validity);
+    }
+
+    if let Some(depth) = args
+        .submission_queue_depth
+        .or(file.submissions.queue_depth.map(|depth| depth.get()))
+    {
+        config = config.with_submission_queue_depth(depth);
+    }
+
+    if let Some(ms) = args.submission_timeout_ms.or(file.submissions.timeout_ms) {
+        config = config.with_submission_timeout(Duration::from_millis(ms));
+    }
+
+    if let Some(path) = args.content_encryption_key_file {
+        let mut encryption = ContentEncryption::new(read_master_key(&path)?);
+        for path in args.content_encryption_previous_key_file {
+            encryption = encryption.with_previous_key(read_master_key(&path)?);
+        }
+        config = config.with_content_encryption(encryption);
+    }
+
+    for host in args.content_source_host {
+        config = config.with_content_source_host(host);
+    }
+
+    if let Some(max_size) = args.max_content_source_size {
+        config = config.with_max_content_source_size(max_size);
+    }
+
+    for name in args.artifact_type {
+        config = config.with_artifact_type(name);
+    }
+
+    if let Some(secs) = args.max_timestamp_skew {
+        config = config.with_max_timestamp_skew(match secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        });
+    }
+
+    if let Some(path) = args.audit_log_file {
+        let audit_log = FileAuditLog::open(&path)
+            .await
+            .with_context(|| format!("failed to open audit log {path:?}"))?
+            .with_max_size(
+                args.audit_log_max_size
+                    .unwrap_or(DEFAULT_MAX_AUDIT_FILE_SIZE),
+            )
+            .with_max_files(args.audit_log_max_files.unwrap_or(DEFAULT_MAX_AUDIT_FILES))
+            .with_hash_chain(args.audit_log_hash_chain);
+        config = config.with_audit_log(audit_log);
+    } else if args.audit_log_data_store {
+        config = config.with_data_store_audit_log();
+    }
+
+    if args.admin_token.is_some() || args.admin_token_file.is_some() {
+        config = config.with_admin_token(get_opt_secret(
+            "admin-token",
+            args.admin_token_file,
+            args.admin_token,
+        )?);
+    }
+
+    if args.download_stats {
+        config = config.with_download_stats(DEFAULT_DOWNLOAD_FLUSH_INTERVAL);
+    }
+
+    if let Some(secs) = args.content_gc_interval {
+        config = config.with_content_gc_interval(Duration::from_secs(secs));
+    }
+
+    if let Some(secs) = args.content_gc_grace_period {
+        config = config.with_content_gc_grace_period(Duration::from_secs(secs));
+    }
+
+    for endpoint in file.webhooks.endpoints {
+        let path = &endpoint.secret_file;
+        let secret = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read webhook secret from {path:?}"))?;
+        let webhook = endpoint.packages.into_iter().fold(
+            Webhook::new(endpoint.url, secret.trim().to_string().into()),
+            Webhook::with_package,
+        );
+        config = config.with_webhook(webhook);
+    }
+
+    if let Some(max_attempts) = file.webhooks.max_attempts {
+        config = config.with_webhook_max_attempts(max_attempts.get());
+    }
+
+    if let Some(path) = file.webhooks.dead_letter_file {
+        config = config.with_webhook_dead_letter_file(path);
+    }
+
+    if let Some(upstream) = args.mirror {
+        config = config.with_mirror(upstream);
+    }
+
+    if let Some(secs) = args.mirror_sync_interval {
+        config = config.with_mirror_sync_interval(Duration::from_secs(secs));
+    }
+
+    #[cfg(feature = "grpc")]
+    if let Some(endpoint) = args.mirror_grpc_endpoint {
+        config = config.with_mirror_grpc_endpoint(endpoint);
+    }
+
+    #[cfg(feature = "grpc")]
+    if let Some(addr) = args.grpc_addr {
+        config = config.with_grpc_addr(addr);
+    }
+
     if let Some(path) = args.authorized_keys_file {
         let authorized_keys_data = std::fs::read_to_string(&path)
             .with_context(|| format!("failed to read authorized keys from {path:?}"))?;
@@ -120,27 +714,135 @@ async fn main() -> Result<()> {
         config = config.with_record_policy(authorized_key_policy);
     }
 
-    let config = match args.data_store {
+    let content_store = match args.content_store {
+        Some(ContentStoreKind::FileSystem) => ContentStoreConfig::FileSystem,
+        #[cfg(feature = "s3")]
+        Some(ContentStoreKind::S3) => ContentStoreConfig::S3 {
+            endpoint: args
+                .s3_endpoint
+                .context("option `s3-endpoint` is required for the s3 content store")?,
+            bucket: args
+                .s3_bucket
+                .context("option `s3-bucket` is required for the s3 content store")?,
+            region: args.s3_region,
+            prefix: args.s3_prefix,
+        },
+        None => file.content_store.unwrap_or(ContentStoreConfig::FileSystem),
+    };
+
+    let config = match content_store {
+        #[cfg(feature = "s3")]
+        ContentStoreConfig::S3 {
+            endpoint,
+            bucket,
+            region,
+            prefix,
+        } => {
+            use warg_server::content::{S3ContentStore, S3Credentials};
+            tracing::info!("using s3 content store with bucket `{bucket}` at `{endpoint}`");
+            let mut store = S3ContentStore::new(endpoint, bucket, S3Credentials::from_env()?)?;
+            if let Some(region) = region {
+                store = store.with_region(region);
+            }
+            if let Some(prefix) = prefix {
+                store = store.with_prefix(prefix);
+            }
+            config.with_content_store(store)
+        }
+        ContentStoreConfig::FileSystem => config,
+    };
+
+    let config = match (args.data_store, file.data_store) {
         #[cfg(feature = "postgres")]
-        DataStoreKind::Postgres => {
-            use warg_server::datastore::PostgresDataStore;
-            tracing::info!("using postgres data store");
+        (Some(DataStoreKind::Postgres), _) => {
             let database_url =
                 get_opt_secret("database-url", args.database_url_file, args.database_url)?;
-            let pg_store = PostgresDataStore::new(database_url)?;
-            if args.database_run_migrations {
-                tracing::info!("running any pending database migration(s)");
-                pg_store.run_pending_migrations().await?;
-            }
-            config.with_data_store(pg_store)
+            with_postgres(config, database_url, args.database_run_migrations).await?
+        }
+        #[cfg(feature = "postgres")]
+        (
+            None,
+            Some(DataStoreConfig::Postgres {
+                database_url_file,
+                run_migrations,
+            }),
+        ) => {
+            let database_url = get_opt_secret("database-url", Some(database_url_file), None)?;
+            with_postgres(
+                config,
+                database_url,
+                run_migrations || args.database_run_migrations,
+            )
+            .await?
         }
-        DataStoreKind::Memory => {
+        (Some(DataStoreKind::Memory), _) | (None, Some(DataStoreConfig::Memory) | None) => {
             tracing::info!("using memory data store");
             config
         }
     };
 
-    Server::new(config).run().await
+    let server = Server::new(config).initialize().await?;
+
+    #[cfg(unix)]
+    if let Some(path) = args.config {
+        tokio::spawn(reload_limits(path, args.limits, server.limits()));
+    }
+
+    server.serve().await
+}
+
+#[cfg(feature = "postgres")]
+async fn with_postgres(
+    config: Config,
+    database_url: SecretString,
+    run_migrations: bool,
+) -> Result<Config> {
+    use warg_server::datastore::PostgresDataStore;
+    tracing::info!("using postgres data store");
+    let pg_store = PostgresDataStore::new(database_url)?;
+    if run_migrations {
+        tracing::info!("running any pending database migration(s)");
+        pg_store.run_pending_migrations().await?;
+    }
+    Ok(config.with_data_store(pg_store))
+}
+
+/// Reloads the limits of the configuration file whenever SIGHUP is received.
+///
+/// Limits given on the command line continue to take precedence over the
+/// file; an invalid file is reported and the limits in effect are kept.
+#[cfg(unix)]
+async fn reload_limits(path: PathBuf, args: LimitArgs, limits: SharedLimits) {
+    let mut hangup = signal::unix::signal(signal::unix::SignalKind::hangup())
+        .expect("failed to install signal handler");
+
+    while hangup.recv().await.is_some() {
+        match ServerConfig::from_file(&path) {
+            Ok(file) => {
+                let reloaded = args.apply(file.limits());
+                tracing::info!(
+                    "reloaded limits from `{path}`: {reloaded:?}",
+                    path = path.display()
+                );
+                limits.store(reloaded);
+            }
+            Err(e) => {
+                tracing::error!("failed to reload limits; keeping the limits in effect: {e:#}");
+            }
+        }
+    }
+}
+
+fn parse_artifact_type(name: &str) -> Result<String, String> {
+    validate_artifact_type(name)?;
+    Ok(name.to_string())
+}
+
+fn read_master_key(path: &std::path::Path) -> Result<MasterKey> {
+    std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read content encryption key from {path:?}"))?
+        .parse()
+        .with_context(|| format!("failed to decode content encryption key from {path:?}"))
 }
 
 async fn shutdown_signal() {
@@ -0,0 +1,366 @@
+//! The server configuration file.
+//!
+//! The configuration file is a TOML file that may be used instead of, or
+//! together with, command line options:
+//!
+//! ```toml
+//! listen = "127.0.0.1:8090"
+//! content-dir = "content"
+//!
+//! [data-store]
+//! kind = "memory"
+//!
+//! [checkpoints]
+//! interval = 5
+//! max-leaves = 1000
+//! min-leaves = 100
+//! max-interval = 300
+//! validity = 3600
+//!
+//! [submissions]
+//! queue-depth = 256
+//! timeout-ms = 1000
+//!
+//! [limits]
+//! max-record-size = 65536
+//! max-record-entries = 100
+//!
+//! [rate-limits]
+//! submissions = { per-minute = 60, burst = 10 }
+//!
+//! [webhooks]
+//! max-attempts = 5
+//! dead-letter-file = "webhooks-dead-letter.jsonl"
+//!
+//! [[webhooks.endpoints]]
+//! url = "https://example.com/hooks/warg"
+//! secret-file = "webhook-secret"
+//! packages = ["example", "other:hello"]
+//! ```
+//!
+//! The `limits` and `rate-limits` sections may be reloaded while the server
+//! is running; changes to other sections take effect when it restarts.
+
+use crate::{api::v1::rate_limit::RateLimit, limits::Limits};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    fs,
+    net::SocketAddr,
+    num::{NonZeroU32, NonZeroU64, NonZeroUsize},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use url::Url;
+use warg_api::v1::registry::RecordLimits;
+
+/// The contents of a server configuration file.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ServerConfig {
+    /// The address to listen on.
+    pub listen: Option<SocketAddr>,
+    /// The content storage directory.
+    ///
+    /// A relative path is relative to the configuration file.
+    pub content_dir: Option<PathBuf>,
+    /// The base content URL; defaults to the server address.
+    pub content_base_url: Option<Url>,
+    /// The data store to use.
+    pub data_store: Option<DataStoreConfig>,
+    /// The content store to use.
+    pub content_store: Option<ContentStoreConfig>,
+    /// The checkpoint settings.
+    #[serde(default)]
+    pub checkpoints: CheckpointConfig,
+    /// The submission queue settings.
+    #[serde(default)]
+    pub submissions: SubmissionConfig,
+    /// The record limits.
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    /// The rate limits.
+    #[serde(default)]
+    pub rate_limits: RateLimitsConfig,
+    /// The webhooks notified of published package records.
+    #[serde(default)]
+    pub webhooks: WebhooksConfig,
+}
+
+/// The data store selected in a server configuration file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum DataStoreConfig {
+    /// An in-memory data store.
+    Memory,
+    /// A PostgreSQL data store.
+    #[cfg(feature = "postgres")]
+    #[serde(rename_all = "kebab-case")]
+    Postgres {
+        /// The path to the database connection URL file.
+        ///
+        /// A relative path is relative to the configuration file.
+        database_url_file: PathBuf,
+        /// Whether to run pending database migrations at startup.
+        #[serde(default)]
+        run_migrations: bool,
+    },
+}
+
+/// The content store selected in a server configuration file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum ContentStoreConfig {
+    /// Content files are stored in the content directory.
+    FileSystem,
+    /// Content is stored in an S3-compatible object store.
+    ///
+    /// Credentials are read from the `AWS_ACCESS_KEY_ID` and
+    /// `AWS_SECRET_ACCESS_KEY` environment variables.
+    #[cfg(feature = "s3")]
+    S3 {
+        /// The endpoint URL of the object store.
+        endpoint: Url,
+        /// The bucket to store content in.
+        bucket: String,
+        /// The region of the bucket (defaults to `us-east-1`).
+        region: Option<String>,
+        /// The key prefix to store content under in the bucket.
+        prefix: Option<String>,
+    },
+}
+
+/// The checkpoint settings of a server configuration file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CheckpointConfig {
+    /// The number of seconds between checkpoints.
+    pub interval: Option<NonZeroU64>,
+    /// The most records included in a checkpoint.
+    pub max_leaves: Option<NonZeroUsize>,
+    /// The fewest records checkpointed early once no submissions are queued.
+    pub min_leaves: Option<NonZeroUsize>,
+    /// The maximum number of seconds between checkpoints declared to clients.
+    pub max_interval: Option<NonZeroU64>,
+    /// The number of seconds a checkpoint is valid for.
+    pub validity: Option<NonZeroU64>,
+}
+
+impl CheckpointConfig {
+    /// Gets the interval between checkpoints.
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval.map(|secs| Duration::from_secs(secs.get()))
+    }
+
+    /// Gets the maximum interval between checkpoints declared to clients.
+    pub fn max_interval(&self) -> Option<Duration> {
+        self.max_interval
+            .map(|secs| Duration::from_secs(secs.get()))
+    }
+
+    /// Gets how long a checkpoint is valid for.
+    pub fn validity(&self) -> Option<Duration> {
+        self.validity.map(|secs| Duration::from_secs(secs.get()))
+    }
+}
+
+/// The submission queue settings of a server configuration file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SubmissionConfig {
+    /// The number of records that may be waiting to be processed.
+    pub queue_depth: Option<NonZeroUsize>,
+    /// The number of milliseconds a submission waits for room in a full queue.
+    pub timeout_ms: Option<u64>,
+}
+
+/// The record limits of a server configuration file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// The maximum size, in bytes, of a submitted record's serialized envelope.
+    pub max_record_size: Option<NonZeroU64>,
+    /// The maximum number of entries in a submitted record.
+    pub max_record_entries: Option<NonZeroU32>,
+}
+
+/// The rate limits of a server configuration file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RateLimitsConfig {
+    /// The rate at which each signing key may submit records.
+    pub submissions: Option<RateLimitConfig>,
+}
+
+/// A rate limit in a server configuration file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// The number of submissions allowed per minute.
+    pub per_minute: NonZeroU32,
+    /// The number of submissions allowed in a burst (defaults to the rate).
+    pub burst: Option<NonZeroU32>,
+}
+
+/// The webhook settings of a server configuration file.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WebhooksConfig {
+    /// The endpoints to notify.
+    #[serde(default)]
+    pub endpoints: Vec<WebhookEndpointConfig>,
+    /// The number of times a delivery is attempted before it is considered failed.
+    pub max_attempts: Option<NonZeroU32>,
+    /// The file failed deliveries are appended to.
+    ///
+    /// A relative path is relative to the configuration file.
+    pub dead_letter_file: Option<PathBuf>,
+}
+
+/// A webhook endpoint in a server configuration file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WebhookEndpointConfig {
+    /// The URL payloads are posted to.
+    pub url: Url,
+    /// The path to the file containing the secret payloads are signed with.
+    ///
+    /// A relative path is relative to the configuration file.
+    pub secret_file: PathBuf,
+    /// The package names, namespaces, or prefix patterns to notify the
+    /// endpoint of; the endpoint is notified of every package if empty.
+    #[serde(default)]
+    pub packages: Vec<String>,
+}
+
+impl ServerConfig {
+    /// Reads and validates the server configuration file at the given path.
+    ///
+    /// Relative paths in the file are resolved against the file's directory.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).with_context(|| {
+            format!(
+                "failed to read configuration file `{path}`",
+                path = path.display()
+            )
+        })?;
+
+        let mut config: Self = toml::from_str(&contents).with_context(|| {
+            format!("invalid configuration file `{path}`", path = path.display())
+        })?;
+
+        if let Some(parent) = path.parent() {
+            config.content_dir = config.content_dir.map(|p| parent.join(p));
+            config.webhooks.dead_letter_file =
+                config.webhooks.dead_letter_file.map(|p| parent.join(p));
+            for endpoint in &mut config.webhooks.endpoints {
+                endpoint.secret_file = parent.join(&endpoint.secret_file);
+            }
+            #[cfg(feature = "postgres")]
+            if let Some(DataStoreConfig::Postgres {
+                database_url_file, ..
+            }) = &mut config.data_store
+            {
+                *database_url_file = parent.join(&*database_url_file);
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Gets the record limits and submission rate limit of the file.
+    pub fn limits(&self) -> Limits {
+        Limits {
+            record: RecordLimits {
+                max_record_size: self.limits.max_record_size.map(NonZeroU64::get),
+                max_record_entries: self.limits.max_record_entries.map(NonZeroU32::get),
+            },
+            submission_rate: self.rate_limits.submissions.map(|limit| RateLimit {
+                per_minute: limit.per_minute,
+                burst: limit.burst.unwrap_or(limit.per_minute),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Result<ServerConfig, String> {
+        toml::from_str(contents).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_limits() {
+        let config = parse(
+            r#"
+listen = "0.0.0.0:8090"
+
+[limits]
+max-record-size = 1024
+
+[rate-limits]
+submissions = { per-minute = 60 }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.listen, Some("0.0.0.0:8090".parse().unwrap()));
+        let limits = config.limits();
+        assert_eq!(limits.record.max_record_size, Some(1024));
+        assert_eq!(limits.record.max_record_entries, None);
+        let rate = limits.submission_rate.unwrap();
+        assert_eq!(rate.per_minute.get(), 60);
+        assert_eq!(rate.burst.get(), 60);
+
+        assert_eq!(parse("").unwrap().limits(), Limits::default());
+    }
+
+    #[test]
+    fn test_webhooks() {
+        let config = parse(
+            r#"
+[webhooks]
+max-attempts = 3
+
+[[webhooks.endpoints]]
+url = "https://example.com/hook"
+secret-file = "secret"
+packages = ["example"]
+
+[[webhooks.endpoints]]
+url = "https://example.com/other"
+secret-file = "other-secret"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.webhooks.max_attempts, NonZeroU32::new(3));
+        assert_eq!(config.webhooks.dead_letter_file, None);
+        assert_eq!(config.webhooks.endpoints.len(), 2);
+        assert_eq!(config.webhooks.endpoints[0].packages, ["example"]);
+        assert!(config.webhooks.endpoints[1].packages.is_empty());
+
+        let message = parse("[[webhooks.endpoints]]\nurl = \"https://example.com\"\n").unwrap_err();
+        assert!(message.contains("missing field `secret-file`"), "{message}");
+    }
+
+    #[test]
+    fn test_invalid_values_are_located() {
+        let message = parse("[limits]\nmax-record-size = 0\n").unwrap_err();
+        assert!(message.contains("line 2, column 19"), "{message}");
+
+        let message = parse("[rate-limits]\nsubmissions = { burst = 10 }\n").unwrap_err();
+        assert!(message.contains("line 2, column 15"), "{message}");
+        assert!(message.contains("missing field `per-minute`"), "{message}");
+
+        let message = parse("[limits]\nmax-record-sise = 10\n").unwrap_err();
+        assert!(message.contains("line 2, column 1"), "{message}");
+        assert!(
+            message.contains("unknown field `max-record-sise`"),
+            "{message}"
+        );
+    }
+}
@@ -0,0 +1,566 @@
+//! Module for encrypting content at rest.
+//!
+//! Encrypted content files start with a fixed-size header followed by the
+//! ciphertext of the content, which has the same length as the plaintext.
+//!
+//! Each file is encrypted with its own randomly generated key using the
+//! ChaCha20 stream cipher, which allows any byte range of the content to be
+//! decrypted without decrypting what precedes it. The file key is wrapped
+//! (encrypted and authenticated) by a master key and stored in the header
+//! along with the plaintext digest of the content.
+//!
+//! Rotating the master key only requires rewriting the header of each file.
+
+use bytes::Bytes;
+use chacha20::{
+    cipher::{KeyIvInit, StreamCipher, StreamCipherSeek},
+    ChaCha20,
+};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305,
+};
+use futures::{Stream, StreamExt};
+use rand_core::{OsRng, RngCore};
+use secrecy::{ExposeSecret, Secret};
+use std::{io::SeekFrom, path::Path, str::FromStr};
+use thiserror::Error;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+use tokio_util::io::ReaderStream;
+use warg_crypto::hash::{AnyHash, Digest, Sha256};
+
+/// The magic bytes identifying an encrypted content file.
+const MAGIC: &[u8; 8] = b"WARGENC\x01";
+
+/// The length of the header of an encrypted content file.
+pub const HEADER_LEN: u64 = 256;
+
+const KEY_LEN: usize = 32;
+const KEY_ID_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const WRAPPED_KEY_LEN: usize = KEY_LEN + 16;
+const DIGEST_LEN: usize =
+    HEADER_LEN as usize - MAGIC.len() - KEY_ID_LEN - 2 * NONCE_LEN - WRAPPED_KEY_LEN - 1;
+
+/// The size of the chunks used when encrypting content.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Represents an error from content encryption.
+#[derive(Debug, Error)]
+pub enum EncryptionError {
+    /// The master key is not valid.
+    #[error("invalid master key: expected {KEY_LEN} hex-encoded bytes")]
+    InvalidMasterKey,
+    /// The content file is not encrypted.
+    #[error("content is not encrypted")]
+    NotEncrypted,
+    /// The header of the content file is not valid.
+    #[error("invalid encrypted content header")]
+    InvalidHeader,
+    /// The master key that wrapped the file key is not configured.
+    #[error("content was encrypted with unknown master key `{0}`")]
+    UnknownMasterKey(String),
+    /// The file key could not be unwrapped.
+    #[error("failed to unwrap the content key")]
+    UnwrapFailed,
+    /// The requested range is outside of the content.
+    #[error("range {start}..{end} is outside of content of length {len}")]
+    InvalidRange {
+        /// The start of the requested range.
+        start: u64,
+        /// The end of the requested range.
+        end: u64,
+        /// The length of the content.
+        len: u64,
+    },
+    /// An I/O error occurred.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// The result type for content encryption.
+pub type EncryptionResult<T> = Result<T, EncryptionError>;
+
+/// A master key used to wrap the keys of encrypted content files.
+///
+/// Master keys may come from configuration or from a key management
+/// service; they are constructed from the raw key bytes either way.
+pub struct MasterKey {
+    key: Secret<[u8; KEY_LEN]>,
+    id: [u8; KEY_ID_LEN],
+}
+
+impl MasterKey {
+    /// Creates a master key from the given key bytes.
+    pub fn new(key: [u8; KEY_LEN]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(b"warg-content-master-key");
+        hasher.update(key);
+
+        Self {
+            key: Secret::new(key),
+            id: hasher.finalize().into(),
+        }
+    }
+
+    /// Generates a new random master key.
+    pub fn generate() -> Self {
+        let mut key = [0; KEY_LEN];
+        OsRng.fill_bytes(&mut key);
+        Self::new(key)
+    }
+
+    /// Gets the identifier of the master key.
+    ///
+    /// The identifier is stored in the header of encrypted files.
+    pub fn id(&self) -> String {
+        hex::encode(self.id)
+    }
+
+    /// Gets the hex encoding of the master key.
+    pub fn encode(&self) -> String {
+        hex::encode(self.key.expose_secret())
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(self.key.expose_secret().into())
+    }
+}
+
+impl FromStr for MasterKey {
+    type Err = EncryptionError;
+
+    fn from_str(s: &str) -> EncryptionResult<Self> {
+        let bytes = hex::decode(s.trim()).map_err(|_| EncryptionError::InvalidMasterKey)?;
+        Ok(Self::new(
+            bytes
+                .try_into()
+                .map_err(|_| EncryptionError::InvalidMasterKey)?,
+        ))
+    }
+}
+
+impl std::fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MasterKey")
+            .field("id", &self.id())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The header of an encrypted content file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedHeader {
+    key_id: [u8; KEY_ID_LEN],
+    wrap_nonce: [u8; NONCE_LEN],
+    wrapped_key: [u8; WRAPPED_KEY_LEN],
+    stream_nonce: [u8; NONCE_LEN],
+    /// The plaintext digest of the content.
+    pub digest: AnyHash,
+}
+
+impl EncryptedHeader {
+    fn encode(&self) -> [u8; HEADER_LEN as usize] {
+        let digest = self.digest.to_string();
+        assert!(digest.len() <= DIGEST_LEN, "digest is too long for header");
+
+        let mut header = [0; HEADER_LEN as usize];
+        let mut offset = 0;
+        for field in [
+            MAGIC.as_slice(),
+            &self.key_id,
+            &self.wrap_nonce,
+            &self.wrapped_key,
+            &self.stream_nonce,
+            &[digest.len() as u8],
+            digest.as_bytes(),
+        ] {
+            header[offset..offset + field.len()].copy_from_slice(field);
+            offset += field.len();
+        }
+
+        header
+    }
+
+    fn decode(header: &[u8; HEADER_LEN as usize]) -> EncryptionResult<Self> {
+        let (magic, rest) = header.split_at(MAGIC.len());
+        if magic != MAGIC {
+            return Err(EncryptionError::NotEncrypted);
+        }
+
+        let (key_id, rest) = rest.split_at(KEY_ID_LEN);
+        let (wrap_nonce, rest) = rest.split_at(NONCE_LEN);
+        let (wrapped_key, rest) = rest.split_at(WRAPPED_KEY_LEN);
+        let (stream_nonce, rest) = rest.split_at(NONCE_LEN);
+        let digest_len = rest[0] as usize;
+        if digest_len > DIGEST_LEN {
+            return Err(EncryptionError::InvalidHeader);
+        }
+
+        let digest = rest
+            .get(1..1 + digest_len)
+            .and_then(|d| std::str::from_utf8(d).ok())
+            .and_then(|d| d.parse().ok())
+            .ok_or(EncryptionError::InvalidHeader)?;
+
+        Ok(Self {
+            key_id: key_id.try_into().unwrap(),
+            wrap_nonce: wrap_nonce.try_into().unwrap(),
+            wrapped_key: wrapped_key.try_into().unwrap(),
+            stream_nonce: stream_nonce.try_into().unwrap(),
+            digest,
+        })
+    }
+
+    /// The additional authenticated data used when wrapping the file key.
+    ///
+    /// This binds the file key to the content digest and stream nonce.
+    fn aad(&self) -> Vec<u8> {
+        let mut aad = self.digest.to_string().into_bytes();
+        aad.extend_from_slice(&self.stream_nonce);
+        aad
+    }
+}
+
+/// Encrypts and decrypts content files using a set of master keys.
+///
+/// New files are always wrapped with the current master key; previous
+/// master keys are only used to unwrap the keys of existing files.
+#[derive(Debug)]
+pub struct ContentEncryption {
+    current: MasterKey,
+    previous: Vec<MasterKey>,
+}
+
+impl ContentEncryption {
+    /// Creates a new content encryption with the given current master key.
+    pub fn new(current: MasterKey) -> Self {
+        Self {
+            current,
+            previous: Vec::new(),
+        }
+    }
+
+    /// Adds a previous master key used to decrypt existing content.
+    pub fn with_previous_key(mut self, key: MasterKey) -> Self {
+        self.previous.push(key);
+        self
+    }
+
+    fn master_key(&self, id: &[u8; KEY_ID_LEN]) -> EncryptionResult<&MasterKey> {
+        std::iter::once(&self.current)
+            .chain(&self.previous)
+            .find(|k| &k.id == id)
+            .ok_or_else(|| EncryptionError::UnknownMasterKey(hex::encode(id)))
+    }
+
+    fn wrap(&self, file_key: &[u8; KEY_LEN], header: &mut EncryptedHeader) {
+        OsRng.fill_bytes(&mut header.wrap_nonce);
+        let wrapped = self
+            .current
+            .cipher()
+            .encrypt(
+                (&header.wrap_nonce).into(),
+                Payload {
+                    msg: file_key,
+                    aad: &header.aad(),
+                },
+            )
+            .expect("wrapping a key should not fail");
+
+        header.key_id = self.current.id;
+        header.wrapped_key.copy_from_slice(&wrapped);
+    }
+
+    fn unwrap(&self, header: &EncryptedHeader) -> EncryptionResult<Secret<[u8; KEY_LEN]>> {
+        let key = self
+            .master_key(&header.key_id)?
+            .cipher()
+            .decrypt(
+                (&header.wrap_nonce).into(),
+                Payload {
+                    msg: &header.wrapped_key,
+                    aad: &header.aad(),
+                },
+            )
+            .map_err(|_| EncryptionError::UnwrapFailed)?;
+
+        Ok(Secret::new(
+            key.try_into().map_err(|_| EncryptionError::UnwrapFailed)?,
+        ))
+    }
+
+    fn stream_cipher(&self, header: &EncryptedHeader, position: u64) -> EncryptionResult<ChaCha20> {
+        let key = self.unwrap(header)?;
+        let mut cipher = ChaCha20::new(key.expose_secret().into(), (&header.stream_nonce).into());
+        cipher.seek(position);
+        Ok(cipher)
+    }
+
+    /// Encrypts the plaintext file at `src` with the given digest to `dst`.
+    pub async fn encrypt_file(
+        &self,
+        src: &Path,
+        dst: &Path,
+        digest: &AnyHash,
+    ) -> EncryptionResult<()> {
+        let mut file_key = [0; KEY_LEN];
+        OsRng.fill_bytes(&mut file_key);
+        let file_key = Secret::new(file_key);
+
+        let mut header = EncryptedHeader {
+            key_id: [0; KEY_ID_LEN],
+            wrap_nonce: [0; NONCE_LEN],
+            wrapped_key: [0; WRAPPED_KEY_LEN],
+            stream_nonce: [0; NONCE_LEN],
+            digest: digest.clone(),
+        };
+        OsRng.fill_bytes(&mut header.stream_nonce);
+        self.wrap(file_key.expose_secret(), &mut header);
+
+        let mut cipher = ChaCha20::new(
+            file_key.expose_secret().into(),
+            (&header.stream_nonce).into(),
+        );
+
+        let mut input = File::open(src).await?;
+        let mut output = File::create(dst).await?;
+        output.write_all(&header.encode()).await?;
+
+        let mut buf = vec![0; CHUNK_SIZE];
+        loop {
+            let n = input.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+
+            cipher.apply_keystream(&mut buf[..n]);
+            output.write_all(&buf[..n]).await?;
+        }
+
+        output.sync_all().await?;
+        Ok(())
+    }
+
+    /// Reads the header of the given content file.
+    ///
+    /// Returns [`EncryptionError::NotEncrypted`] if the file is plaintext.
+    pub async fn read_header(&self, path: &Path) -> EncryptionResult<EncryptedHeader> {
+        let mut file = File::open(path).await?;
+        read_header(&mut file).await
+    }
+
+    /// Opens a stream decrypting the given range of an encrypted content file.
+    ///
+    /// The range is `start..end` in plaintext offsets; `None` decrypts the
+    /// entire content.
+    ///
+    /// Returns the plaintext length of the content along with the stream.
+    pub async fn decrypt_range(
+        &self,
+        path: &Path,
+        range: Option<(u64, u64)>,
+    ) -> EncryptionResult<(
+        u64,
+        impl Stream<Item = std::io::Result<Bytes>> + Send + 'static,
+    )> {
+        let mut file = File::open(path).await?;
+        let header = read_header(&mut file).await?;
+        let len = file.metadata().await?.len().saturating_sub(HEADER_LEN);
+        let (start, end) = range.unwrap_or((0, len));
+        if start > end || end > len {
+            return Err(EncryptionError::InvalidRange { start, end, len });
+        }
+
+        let mut cipher = self.stream_cipher(&header, start)?;
+        file.seek(SeekFrom::Start(HEADER_LEN + start)).await?;
+
+        let stream =
+            ReaderStream::with_capacity(file.take(end - start), CHUNK_SIZE).map(move |chunk| {
+                let mut chunk = chunk?.to_vec();
+                cipher.apply_keystream(&mut chunk);
+                Ok(Bytes::from(chunk))
+            });
+
+        Ok((len, stream))
+    }
+
+    /// Reads and decrypts the entire content of an encrypted content file.
+    pub async fn decrypt(&self, path: &Path) -> EncryptionResult<Vec<u8>> {
+        let (len, mut stream) = self.decrypt_range(path, None).await?;
+        let mut content = Vec::with_capacity(len as usize);
+        while let Some(chunk) = stream.next().await {
+            content.extend_from_slice(&chunk?);
+        }
+
+        Ok(content)
+    }
+
+    /// Re-wraps the key of an encrypted content file with the current master key.
+    ///
+    /// Only the header of the file is rewritten; the content is not re-encrypted.
+    ///
+    /// Returns `false` if the file was already wrapped with the current master key.
+    pub async fn rewrap(&self, path: &Path) -> EncryptionResult<bool> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path).await?;
+        let mut header = read_header(&mut file).await?;
+        if header.key_id == self.current.id {
+            return Ok(false);
+        }
+
+        let file_key = self.unwrap(&header)?;
+        self.wrap(file_key.expose_secret(), &mut header);
+
+        file.seek(SeekFrom::Start(0)).await?;
+        file.write_all(&header.encode()).await?;
+        file.sync_all().await?;
+        Ok(true)
+    }
+}
+
+async fn read_header(file: &mut File) -> EncryptionResult<EncryptedHeader> {
+    let mut header = [0; HEADER_LEN as usize];
+    match file.read_exact(&mut header).await {
+        Ok(_) => EncryptedHeader::decode(&header),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            Err(EncryptionError::NotEncrypted)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use warg_crypto::hash::HashAlgorithm;
+
+    fn content(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    async fn encrypt(
+        encryption: &ContentEncryption,
+        dir: &Path,
+        content: &[u8],
+    ) -> (AnyHash, std::path::PathBuf) {
+        let digest = HashAlgorithm::Sha256.digest(content);
+        let src = dir.join("plain");
+        let dst = dir.join("encrypted");
+        tokio::fs::write(&src, content).await.unwrap();
+        encryption.encrypt_file(&src, &dst, &digest).await.unwrap();
+        (digest, dst)
+    }
+
+    async fn collect(
+        encryption: &ContentEncryption,
+        path: &Path,
+        range: Option<(u64, u64)>,
+    ) -> EncryptionResult<Vec<u8>> {
+        let (_, mut stream) = encryption.decrypt_range(path, range).await?;
+        let mut bytes = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            bytes.extend_from_slice(&chunk?);
+        }
+        Ok(bytes)
+    }
+
+    #[tokio::test]
+    async fn it_round_trips_content() {
+        let dir = tempdir().unwrap();
+        let encryption = ContentEncryption::new(MasterKey::generate());
+        let plaintext = content(CHUNK_SIZE * 2 + 17);
+        let (digest, path) = encrypt(&encryption, dir.path(), &plaintext).await;
+
+        let encrypted = tokio::fs::read(&path).await.unwrap();
+        assert_eq!(encrypted.len() as u64, plaintext.len() as u64 + HEADER_LEN);
+        assert_ne!(&encrypted[HEADER_LEN as usize..], plaintext.as_slice());
+
+        assert_eq!(encryption.read_header(&path).await.unwrap().digest, digest);
+        assert_eq!(encryption.decrypt(&path).await.unwrap(), plaintext);
+    }
+
+    #[tokio::test]
+    async fn it_decrypts_partial_ranges() {
+        let dir = tempdir().unwrap();
+        let encryption = ContentEncryption::new(MasterKey::generate());
+        let plaintext = content(CHUNK_SIZE + 1000);
+        let (_, path) = encrypt(&encryption, dir.path(), &plaintext).await;
+
+        // Ranges starting and ending inside, on, and across cipher blocks and chunks
+        let len = plaintext.len() as u64;
+        for (start, end) in [
+            (0, 1),
+            (1, 63),
+            (63, 65),
+            (64, 128),
+            (100, 5000),
+            (CHUNK_SIZE as u64 - 3, CHUNK_SIZE as u64 + 3),
+            (len - 1, len),
+            (len, len),
+            (0, len),
+        ] {
+            assert_eq!(
+                collect(&encryption, &path, Some((start, end)))
+                    .await
+                    .unwrap(),
+                &plaintext[start as usize..end as usize],
+                "range {start}..{end}"
+            );
+        }
+
+        assert!(matches!(
+            collect(&encryption, &path, Some((0, len + 1))).await,
+            Err(EncryptionError::InvalidRange { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_rewraps_with_a_new_master_key() {
+        let dir = tempdir().unwrap();
+        let old = MasterKey::generate();
+        let old_hex = old.encode();
+        let plaintext = content(1000);
+        let (_, path) = encrypt(&ContentEncryption::new(old), dir.path(), &plaintext).await;
+        let ciphertext = tokio::fs::read(&path).await.unwrap()[HEADER_LEN as usize..].to_vec();
+
+        let new = MasterKey::generate();
+        let new_hex = new.encode();
+        let encryption = ContentEncryption::new(new).with_previous_key(old_hex.parse().unwrap());
+        assert!(encryption.rewrap(&path).await.unwrap());
+        assert!(!encryption.rewrap(&path).await.unwrap());
+
+        // The data itself is not re-encrypted
+        assert_eq!(
+            &tokio::fs::read(&path).await.unwrap()[HEADER_LEN as usize..],
+            ciphertext.as_slice()
+        );
+
+        // The new master key alone can decrypt the content
+        let encryption = ContentEncryption::new(new_hex.parse().unwrap());
+        assert_eq!(encryption.decrypt(&path).await.unwrap(), plaintext);
+
+        // The old master key alone can no longer decrypt the content
+        let encryption = ContentEncryption::new(old_hex.parse().unwrap());
+        assert!(matches!(
+            encryption.decrypt(&path).await,
+            Err(EncryptionError::UnknownMasterKey(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_detects_plaintext_files() {
+        let dir = tempdir().unwrap();
+        let encryption = ContentEncryption::new(MasterKey::generate());
+        let path = dir.path().join("plain");
+        tokio::fs::write(&path, b"\0asm").await.unwrap();
+
+        assert!(matches!(
+            encryption.read_header(&path).await,
+            Err(EncryptionError::NotEncrypted)
+        ));
+    }
+}
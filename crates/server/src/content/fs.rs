@@ -0,0 +1,219 @@
+use super::{
+    ContentEncryption, ContentStorage, ContentStore, ContentStoreError, ContentStoreResult,
+    ContentStream, EncryptionError, HEADER_LEN,
+};
+use futures::StreamExt;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+use tempfile::{NamedTempFile, TempPath};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+use tokio_util::io::ReaderStream;
+use warg_crypto::hash::AnyHash;
+
+/// A content store that keeps content files in a local directory,
+/// optionally encrypting them at rest.
+///
+/// Content is always addressed by its plaintext digest.
+#[derive(Clone)]
+pub struct FileSystemContentStore {
+    files_dir: PathBuf,
+    encryption: Option<Arc<ContentEncryption>>,
+}
+
+impl FileSystemContentStore {
+    /// Creates a new content store for the given files directory.
+    ///
+    /// The directory is expected to exist.
+    pub fn new(files_dir: PathBuf) -> Self {
+        Self {
+            files_dir,
+            encryption: None,
+        }
+    }
+
+    /// Encrypts content at rest with the given encryption.
+    pub fn with_encryption(mut self, encryption: Arc<ContentEncryption>) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    /// Determines if the content store encrypts content at rest.
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption.is_some()
+    }
+
+    /// Gets the directory the content files are stored in.
+    pub fn files_dir(&self) -> &Path {
+        &self.files_dir
+    }
+
+    /// Gets the path of the content with the given digest.
+    pub fn path(&self, digest: &AnyHash) -> PathBuf {
+        self.files_dir.join(ContentStorage::file_name(digest))
+    }
+
+    /// Migrates the stored content to the current encryption configuration.
+    ///
+    /// Plaintext content files are encrypted and the keys of files encrypted
+    /// with a previous master key are re-wrapped with the current master key.
+    ///
+    /// Returns the number of files that were migrated.
+    pub async fn migrate(&self) -> ContentStoreResult<usize> {
+        let Some(encryption) = &self.encryption else {
+            return Ok(0);
+        };
+
+        let mut migrated = 0;
+        let mut entries = tokio::fs::read_dir(&self.files_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if !entry.file_type().await?.is_file() {
+                continue;
+            }
+
+            match encryption.read_header(&path).await {
+                Ok(_) => {
+                    if encryption.rewrap(&path).await? {
+                        migrated += 1;
+                    }
+                }
+                Err(EncryptionError::NotEncrypted) => {
+                    let Some(digest) = Self::digest_of(&path) else {
+                        tracing::warn!(
+                            "skipping unrecognized content file `{path}`",
+                            path = path.display()
+                        );
+                        continue;
+                    };
+
+                    let encrypted = NamedTempFile::new_in(&self.files_dir)?.into_temp_path();
+                    encryption.encrypt_file(&path, &encrypted, &digest).await?;
+                    encrypted.persist(&path).map_err(|e| e.error)?;
+                    migrated += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(migrated)
+    }
+
+    fn existing_path(&self, digest: &AnyHash) -> ContentStoreResult<PathBuf> {
+        let path = self.path(digest);
+        if !path.is_file() {
+            return Err(ContentStoreError::ContentNotFound(digest.clone()));
+        }
+
+        Ok(path)
+    }
+
+    /// Gets the digest of content from its file name.
+    fn digest_of(path: &Path) -> Option<AnyHash> {
+        ContentStorage::digest_of(path.file_name()?.to_str()?)
+    }
+}
+
+#[axum::async_trait]
+impl ContentStore for FileSystemContentStore {
+    async fn put(
+        &self,
+        digest: &AnyHash,
+        _len: u64,
+        mut content: ContentStream,
+    ) -> ContentStoreResult<()> {
+        let (file, path) = NamedTempFile::new_in(&self.files_dir)?.into_parts();
+        let mut file = File::from_std(file);
+        while let Some(chunk) = content.next().await.transpose()? {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        drop(file);
+
+        self.put_file(digest, path).await
+    }
+
+    async fn put_file(&self, digest: &AnyHash, path: TempPath) -> ContentStoreResult<()> {
+        let dest = self.path(digest);
+        match &self.encryption {
+            Some(encryption) => {
+                let encrypted = NamedTempFile::new_in(&self.files_dir)?.into_temp_path();
+                encryption.encrypt_file(&path, &encrypted, digest).await?;
+                encrypted.persist(dest).map_err(|e| e.error)?;
+            }
+            None => {
+                path.persist(dest).map_err(|e| e.error)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get(
+        &self,
+        digest: &AnyHash,
+        range: Option<(u64, u64)>,
+    ) -> ContentStoreResult<(u64, ContentStream)> {
+        let path = self.existing_path(digest)?;
+        if let Some(encryption) = &self.encryption {
+            let (len, stream) = encryption.decrypt_range(&path, range).await?;
+            return Ok((len, stream.boxed()));
+        }
+
+        let mut file = File::open(&path).await?;
+        let len = file.metadata().await?.len();
+        let (start, end) = range.unwrap_or((0, len));
+        if start > end || end > len {
+            return Err(EncryptionError::InvalidRange { start, end, len }.into());
+        }
+
+        file.seek(std::io::SeekFrom::Start(start)).await?;
+        Ok((len, ReaderStream::new(file.take(end - start)).boxed()))
+    }
+
+    async fn exists(&self, digest: &AnyHash) -> ContentStoreResult<bool> {
+        Ok(self.path(digest).is_file())
+    }
+
+    async fn delete(&self, digest: &AnyHash) -> ContentStoreResult<()> {
+        match tokio::fs::remove_file(self.path(digest)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn size(&self, digest: &AnyHash) -> ContentStoreResult<u64> {
+        let len = tokio::fs::metadata(self.existing_path(digest)?)
+            .await?
+            .len();
+        Ok(match &self.encryption {
+            Some(_) => len.saturating_sub(HEADER_LEN),
+            None => len,
+        })
+    }
+
+    async fn list(&self) -> ContentStoreResult<Vec<(AnyHash, SystemTime)>> {
+        let mut content = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.files_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() {
+                continue;
+            }
+
+            // Unrecognized files, such as content being persisted, are left alone
+            let Some(digest) = Self::digest_of(&entry.path()) else {
+                continue;
+            };
+
+            content.push((digest, metadata.modified()?));
+        }
+
+        Ok(content)
+    }
+}
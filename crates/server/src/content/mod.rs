@@ -0,0 +1,286 @@
+//! Module for the server's content storage.
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use indexmap::IndexSet;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tempfile::{NamedTempFile, TempPath};
+use thiserror::Error;
+use tokio::fs::File;
+use tokio_util::io::ReaderStream;
+use warg_crypto::hash::AnyHash;
+
+mod encryption;
+mod fs;
+#[cfg(feature = "s3")]
+mod s3;
+mod upload;
+
+pub use encryption::*;
+pub use fs::*;
+#[cfg(feature = "s3")]
+pub use s3::*;
+pub use upload::*;
+
+/// Represents an error from the content store.
+#[derive(Debug, Error)]
+pub enum ContentStoreError {
+    /// The content was not found.
+    #[error("content with digest `{0}` was not found")]
+    ContentNotFound(AnyHash),
+    /// An encryption error occurred.
+    #[error(transparent)]
+    Encryption(#[from] EncryptionError),
+    /// The content does not match its digest.
+    #[error("content digest `{actual}` does not match expected digest `{expected}`")]
+    DigestMismatch {
+        /// The expected digest of the content.
+        expected: AnyHash,
+        /// The actual digest of the content.
+        actual: AnyHash,
+    },
+    /// An error occurred in the content store backend.
+    #[error(transparent)]
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+    /// An I/O error occurred.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// The result type for the content store.
+pub type ContentStoreResult<T> = Result<T, ContentStoreError>;
+
+/// A boxed stream of content bytes.
+pub type ContentStream = std::pin::Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+/// Implemented by content store backends.
+///
+/// Content is verified against its digest by [`ContentStorage`] before it is
+/// put into a store, so stores need not verify content themselves.
+#[axum::async_trait]
+pub trait ContentStore: Send + Sync {
+    /// Puts the content with the given digest and length into the store.
+    async fn put(
+        &self,
+        digest: &AnyHash,
+        len: u64,
+        content: ContentStream,
+    ) -> ContentStoreResult<()>;
+
+    /// Puts the content of the given temporary file into the store.
+    ///
+    /// The default implementation streams the file with [`ContentStore::put`].
+    async fn put_file(&self, digest: &AnyHash, path: TempPath) -> ContentStoreResult<()> {
+        let file = File::open(&path).await?;
+        let len = file.metadata().await?.len();
+        self.put(digest, len, ReaderStream::new(file).boxed()).await
+    }
+
+    /// Gets a stream of the content with the given digest.
+    ///
+    /// The range is `start..end` in content offsets; `None` streams the
+    /// entire content.
+    ///
+    /// Returns the length of the content along with the stream.
+    async fn get(
+        &self,
+        digest: &AnyHash,
+        range: Option<(u64, u64)>,
+    ) -> ContentStoreResult<(u64, ContentStream)>;
+
+    /// Determines if the content with the given digest is in the store.
+    async fn exists(&self, digest: &AnyHash) -> ContentStoreResult<bool>;
+
+    /// Deletes the content with the given digest.
+    ///
+    /// Deleting content that is not in the store is not an error.
+    async fn delete(&self, digest: &AnyHash) -> ContentStoreResult<()>;
+
+    /// Gets the length of the content with the given digest.
+    async fn size(&self, digest: &AnyHash) -> ContentStoreResult<u64>;
+
+    /// Lists the digests of the content in the store along with the time
+    /// each was last modified.
+    async fn list(&self) -> ContentStoreResult<Vec<(AnyHash, SystemTime)>>;
+}
+
+/// Stores content by digest in a content store.
+///
+/// Content is verified against its digest before it is stored, regardless
+/// of the content store in use.
+#[derive(Clone)]
+pub struct ContentStorage {
+    store: Arc<dyn ContentStore>,
+    temp_dir: PathBuf,
+}
+
+impl ContentStorage {
+    /// Creates a new content storage for the given content store.
+    ///
+    /// Content being verified is written to files in the given temporary
+    /// directory, which is expected to exist.
+    pub fn new(store: Arc<dyn ContentStore>, temp_dir: PathBuf) -> Self {
+        Self { store, temp_dir }
+    }
+
+    /// Gets the file name of the content with the given digest.
+    pub fn file_name(digest: &AnyHash) -> String {
+        digest.to_string().replace(':', "-")
+    }
+
+    /// Gets the digest of content from its file name.
+    pub fn digest_of(file_name: &str) -> Option<AnyHash> {
+        file_name.replacen('-', ":", 1).parse().ok()
+    }
+
+    /// Creates a temporary file to write content to before persisting it.
+    pub fn temp_file(&self) -> ContentStoreResult<NamedTempFile> {
+        Ok(NamedTempFile::new_in(&self.temp_dir)?)
+    }
+
+    /// Gets the length of the content with the given digest.
+    pub async fn len(&self, digest: &AnyHash) -> ContentStoreResult<u64> {
+        self.store.size(digest).await
+    }
+
+    /// Determines if the content with the given digest is present.
+    pub async fn is_present(&self, digest: &AnyHash) -> ContentStoreResult<bool> {
+        self.store.exists(digest).await
+    }
+
+    /// Verifies the content at the given temporary path against the digest
+    /// and persists it to the content store.
+    ///
+    /// Returns the length of the content.
+    pub async fn persist(&self, tmp_path: TempPath, digest: &AnyHash) -> ContentStoreResult<u64> {
+        let len = Self::verify(&tmp_path, digest).await?;
+        self.store.put_file(digest, tmp_path).await?;
+        Ok(len)
+    }
+
+    /// Verifies the content of the file at the given path against the digest
+    /// by hashing it with the digest's algorithm.
+    ///
+    /// Returns the length of the content.
+    pub async fn verify(path: &Path, digest: &AnyHash) -> ContentStoreResult<u64> {
+        let mut stream = ReaderStream::new(File::open(path).await?);
+        let mut hasher = digest.algorithm().hasher();
+        let mut len = 0;
+        while let Some(chunk) = stream.next().await.transpose()? {
+            hasher.update(&chunk);
+            len += chunk.len() as u64;
+        }
+
+        let actual = hasher.finalize();
+        if &actual != digest {
+            return Err(ContentStoreError::DigestMismatch {
+                expected: digest.clone(),
+                actual,
+            });
+        }
+
+        Ok(len)
+    }
+
+    /// Reads the entire content with the given digest.
+    pub async fn read(&self, digest: &AnyHash) -> ContentStoreResult<Vec<u8>> {
+        let (len, mut stream) = self.store.get(digest, None).await?;
+        let mut content = Vec::with_capacity(len.try_into().unwrap_or_default());
+        while let Some(chunk) = stream.next().await.transpose()? {
+            content.extend_from_slice(&chunk);
+        }
+
+        Ok(content)
+    }
+
+    /// Opens a stream of the content with the given digest.
+    ///
+    /// The range is `start..end` in content offsets; `None` streams the
+    /// entire content.
+    ///
+    /// Returns the length of the content along with the stream.
+    pub async fn open(
+        &self,
+        digest: &AnyHash,
+        range: Option<(u64, u64)>,
+    ) -> ContentStoreResult<(u64, ContentStream)> {
+        self.store.get(digest, range).await
+    }
+
+    /// Removes content that is not in the given set of referenced digests.
+    ///
+    /// Content modified within the grace period before `now` is kept so
+    /// content uploaded for records not yet stored is not removed.
+    ///
+    /// Returns the number of content files that were removed.
+    pub async fn remove_unreferenced(
+        &self,
+        referenced: &IndexSet<AnyHash>,
+        grace_period: Duration,
+        now: SystemTime,
+    ) -> ContentStoreResult<usize> {
+        let mut removed = 0;
+        for (digest, modified) in self.store.list().await? {
+            if referenced.contains(&digest) {
+                continue;
+            }
+
+            let expired = now
+                .duration_since(modified)
+                .map(|age| age >= grace_period)
+                .unwrap_or(false);
+            if !expired {
+                continue;
+            }
+
+            self.store.delete(&digest).await?;
+            tracing::debug!("removed unreferenced content `{digest}`");
+            removed += 1;
+        }
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use warg_crypto::hash::{Hash, Sha256};
+
+    #[tokio::test]
+    async fn it_verifies_content_before_storing_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = ContentStorage::new(
+            Arc::new(FileSystemContentStore::new(dir.path().to_path_buf())),
+            dir.path().to_path_buf(),
+        );
+        let digest: AnyHash = Hash::<Sha256>::of("content").into();
+
+        let mut file = storage.temp_file().unwrap();
+        file.write_all(b"tampered").unwrap();
+        match storage.persist(file.into_temp_path(), &digest).await {
+            Err(ContentStoreError::DigestMismatch { expected, actual }) => {
+                assert_eq!(expected, digest);
+                assert_eq!(actual, Hash::<Sha256>::of("tampered").into());
+            }
+            res => panic!("expected a digest mismatch, got {res:?}"),
+        }
+        assert!(!storage.is_present(&digest).await.unwrap());
+
+        let mut file = storage.temp_file().unwrap();
+        file.write_all(b"content").unwrap();
+        assert_eq!(
+            storage
+                .persist(file.into_temp_path(), &digest)
+                .await
+                .unwrap(),
+            7
+        );
+        assert_eq!(storage.read(&digest).await.unwrap(), b"content");
+    }
+}
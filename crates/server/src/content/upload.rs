@@ -0,0 +1,187 @@
+//! Module for resumable content uploads.
+//!
+//! Partially uploaded content is kept in a file per upload session until the
+//! upload completes, so an interrupted upload can resume from the last byte
+//! the registry received.
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use std::{
+    collections::HashSet,
+    io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tempfile::TempPath;
+use thiserror::Error;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt};
+use warg_crypto::{
+    hash::{AnyHash, Hash, Sha256},
+    signing::KeyID,
+};
+
+/// The duration after which partial uploads that have not been written to
+/// are removed.
+pub const UPLOAD_SESSION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Represents an error receiving content for an upload session.
+#[derive(Debug, Error)]
+pub enum UploadError {
+    /// The request body failed before all of it was received.
+    #[error("the upload was interrupted: {0}")]
+    Interrupted(String),
+    /// An I/O error occurred.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Stores partially uploaded content so interrupted uploads can be resumed.
+///
+/// Partial content is keyed by an upload session ID derived from the
+/// publishing key and the content digest; publishing the same content with
+/// the same key resumes the upload, even for a different record.
+pub struct UploadSessions {
+    dir: PathBuf,
+    active: Mutex<HashSet<String>>,
+}
+
+impl UploadSessions {
+    /// Creates a new upload session store in the given directory.
+    ///
+    /// The directory is expected to exist.
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            active: Default::default(),
+        }
+    }
+
+    /// Gets the upload session ID for the given key and content digest.
+    pub fn session_id(key_id: &KeyID, digest: &AnyHash) -> String {
+        hex::encode(Hash::<Sha256>::of(format!("{key_id}\n{digest}").as_str()).bytes())
+    }
+
+    /// Begins an upload for the given session.
+    ///
+    /// Returns `None` if an upload for the session is already in progress.
+    pub fn begin(self: &Arc<Self>, id: String) -> Option<UploadSession> {
+        if !self.active.lock().unwrap().insert(id.clone()) {
+            return None;
+        }
+
+        Some(UploadSession {
+            path: self.dir.join(&id),
+            sessions: self.clone(),
+            id,
+        })
+    }
+
+    /// Removes partial uploads that have not been written to within
+    /// [`UPLOAD_SESSION_TTL`].
+    ///
+    /// Returns the number of partial uploads that were removed.
+    pub async fn remove_expired(&self) -> io::Result<usize> {
+        let mut removed = 0;
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            let expired = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .map(|age| age >= UPLOAD_SESSION_TTL)
+                .unwrap_or(false);
+
+            if metadata.is_file() && expired {
+                tokio::fs::remove_file(entry.path()).await?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+/// An upload in progress for an upload session.
+///
+/// Only one upload may be in progress for a session at a time; the session
+/// is released when this is dropped.
+pub struct UploadSession {
+    sessions: Arc<UploadSessions>,
+    id: String,
+    path: PathBuf,
+}
+
+impl UploadSession {
+    /// Gets the number of bytes received for the session.
+    pub async fn received(&self) -> io::Result<u64> {
+        match tokio::fs::metadata(&self.path).await {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Appends the given stream to the received bytes.
+    ///
+    /// If `restart` is `true`, previously received bytes are discarded first.
+    ///
+    /// Bytes received before the stream fails are kept so the upload can be
+    /// resumed. Returns the total number of bytes received.
+    pub async fn append<S, E>(&self, restart: bool, mut stream: S) -> Result<u64, UploadError>
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin,
+        E: std::fmt::Display,
+    {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(!restart)
+            .write(true)
+            .truncate(restart)
+            .open(&self.path)
+            .await?;
+
+        let res = async {
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(|e| UploadError::Interrupted(e.to_string()))?;
+                file.write_all(&chunk).await?;
+            }
+
+            Ok::<_, UploadError>(())
+        }
+        .await;
+
+        file.flush().await?;
+        res?;
+
+        Ok(file.metadata().await?.len())
+    }
+
+    /// Discards the received bytes of the session.
+    pub async fn discard(self) -> io::Result<()> {
+        match tokio::fs::remove_file(&self.path).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Gets a temporary path for the received content.
+    ///
+    /// The received content is removed when the returned path is dropped
+    /// unless it is persisted.
+    pub fn temp_path(&self) -> TempPath {
+        TempPath::from_path(self.path.clone())
+    }
+
+    /// Gets the path of the received content.
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+}
+
+impl Drop for UploadSession {
+    fn drop(&mut self) {
+        self.sessions.active.lock().unwrap().remove(&self.id);
+    }
+}
@@ -1,8 +1,19 @@
-use super::{DataStore, DataStoreError};
+use super::{
+    add_daily_downloads, package_summary, DataStore, DataStoreError, DownloadCount,
+    PackageRecordCheck, RecordPage,
+};
+use crate::audit::AuditFilter;
+use chrono::{DateTime, Utc};
 use futures::Stream;
 use indexmap::{IndexMap, IndexSet};
-use std::{pin::Pin, sync::Arc};
+use std::{collections::BTreeMap, pin::Pin, sync::Arc};
 use tokio::sync::RwLock;
+use warg_api::v1::{
+    admin::AuditEntry,
+    package::{
+        DownloadCounts, PackageDependency, PackageDependent, PackageLogStatus, PackageSummary,
+    },
+};
 use warg_crypto::{hash::AnyHash, Encode, Signable};
 use warg_protocol::{
     operator,
@@ -10,17 +21,22 @@ use warg_protocol::{
     registry::{
         LogId, LogLeaf, PackageName, RecordId, RegistryIndex, RegistryLen, TimestampedCheckpoint,
     },
-    ProtoEnvelope, PublishedProtoEnvelope, SerdeEnvelope,
+    ProtoEnvelope, PublishedProtoEnvelope, Record as _, SerdeEnvelope, Version,
 };
 
 struct Entry<R> {
     registry_index: RegistryIndex,
-    record_content: ProtoEnvelope<R>,
+    record_id: RecordId,
+    /// The record envelope; `None` if the entry was compacted.
+    record_content: Option<ProtoEnvelope<R>>,
 }
 
 struct Log<S, R> {
     state: S,
     entries: Vec<Entry<R>>,
+    /// The releases of compacted entries, so the content they reference
+    /// remains known.
+    compacted_releases: Vec<(Version, AnyHash)>,
 }
 
 impl<S, R> Default for Log<S, R>
@@ -31,10 +47,75 @@ where
         Self {
             state: S::default(),
             entries: Vec::new(),
+            compacted_releases: Vec::new(),
         }
     }
 }
 
+impl<S, R: Clone> Log<S, R> {
+    /// Gets a page of the entries included in the given registry log length,
+    /// starting with the entry at the given index.
+    ///
+    /// Returns `None` if an entry of the page was compacted.
+    fn page(
+        &self,
+        start: usize,
+        registry_log_length: RegistryLen,
+        limit: u16,
+    ) -> Option<RecordPage<R>> {
+        let mut entries = self
+            .entries
+            .iter()
+            .skip(start)
+            .take_while(|entry| entry.registry_index < registry_log_length);
+
+        let records = entries
+            .by_ref()
+            .take(limit as usize)
+            .map(|entry| {
+                Some(PublishedProtoEnvelope {
+                    envelope: entry.record_content.clone()?,
+                    registry_index: entry.registry_index,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let next = match entries.next() {
+            Some(_) => (start + records.len())
+                .checked_sub(1)
+                .map(|index| self.entries[index].record_id.clone()),
+            None => None,
+        };
+
+        Some(RecordPage { records, next })
+    }
+}
+
+impl Log<package::LogState, package::PackageRecord> {
+    /// Discards the envelopes of the published entries, except for the last
+    /// `keep_last` entries included in the given registry log length.
+    ///
+    /// Returns the number of entries that were compacted.
+    fn compact(&mut self, registry_log_length: RegistryLen, keep_last: usize) -> usize {
+        let published = self
+            .entries
+            .iter()
+            .take_while(|entry| entry.registry_index < registry_log_length)
+            .count();
+
+        let mut compacted = 0;
+        for entry in &mut self.entries[..published.saturating_sub(keep_last)] {
+            if let Some(record) = entry.record_content.take() {
+                self.compacted_releases
+                    .extend(package_releases(record.as_ref()));
+                compacted += 1;
+            }
+        }
+
+        compacted
+    }
+}
+
 struct Record {
     /// Index in the log's entries.
     index: usize,
@@ -56,41 +137,234 @@ enum RejectedRecord {
     Operator {
         record: ProtoEnvelope<operator::OperatorRecord>,
         reason: String,
+        rejected_at: DateTime<Utc>,
     },
     Package {
         record: ProtoEnvelope<package::PackageRecord>,
         reason: String,
+        rejected_at: DateTime<Utc>,
     },
 }
 
+impl RejectedRecord {
+    fn rejected_at(&self) -> DateTime<Utc> {
+        match self {
+            Self::Operator { rejected_at, .. } | Self::Package { rejected_at, .. } => *rejected_at,
+        }
+    }
+}
+
 enum RecordStatus {
     Pending(PendingRecord),
     Rejected(RejectedRecord),
     Validated(Record),
 }
 
+/// The data of a single log.
+///
+/// Each log is locked independently, so a long operation on one log does
+/// not block operations on other logs.
 #[derive(Default)]
-struct State {
-    operators: IndexMap<LogId, Log<operator::LogState, operator::OperatorRecord>>,
-    packages: IndexMap<LogId, Log<package::LogState, package::PackageRecord>>,
+struct LogData {
+    /// The validated entries of an operator log.
+    operator: Option<Log<operator::LogState, operator::OperatorRecord>>,
+    /// The validated entries of a package log.
+    package: Option<Log<package::LogState, package::PackageRecord>>,
+    records: IndexMap<RecordId, RecordStatus>,
+    dependencies: IndexMap<RecordId, IndexMap<Version, Vec<PackageDependency>>>,
+}
+
+impl LogData {
+    /// Gets the index of the log entry following the given record.
+    ///
+    /// Only validated records have an entry in the log.
+    fn index_after(&self, since: &RecordId) -> Result<usize, DataStoreError> {
+        match self.records.get(since) {
+            Some(RecordStatus::Validated(record)) => Ok(record.index + 1),
+            Some(_) => Err(DataStoreError::RecordNotValidated(since.clone())),
+            None => Err(DataStoreError::RecordNotFound(since.clone())),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Names {
     package_names: IndexMap<LogId, Option<PackageName>>,
     package_names_lowercase: IndexMap<String, PackageName>,
+}
+
+/// The checkpoints of the registry and the leafs of its log.
+#[derive(Default)]
+struct RegistryLog {
     checkpoints: IndexMap<RegistryLen, SerdeEnvelope<TimestampedCheckpoint>>,
-    records: IndexMap<LogId, IndexMap<RecordId, RecordStatus>>,
+    checkpoint_ids: IndexMap<AnyHash, RegistryLen>,
     log_leafs: IndexMap<RegistryIndex, LogLeaf>,
 }
 
+impl RegistryLog {
+    /// Gets the log length of the latest checkpoint.
+    fn published_length(&self) -> RegistryLen {
+        self.checkpoints
+            .last()
+            .map(|(_, c)| c.as_ref().checkpoint.log_length)
+            .unwrap_or_default()
+    }
+}
+
+/// The daily download counts of each release.
+type Downloads = IndexMap<PackageName, IndexMap<Version, BTreeMap<u64, u64>>>;
+
+/// The state of the data store.
+///
+/// To prevent deadlocks, a log's lock is always acquired before the locks of
+/// `names` and `registry`, and no two logs are locked at the same time.
+#[derive(Default)]
+struct State {
+    logs: RwLock<IndexMap<LogId, Arc<RwLock<LogData>>>>,
+    names: RwLock<Names>,
+    registry: RwLock<RegistryLog>,
+    audit_entries: RwLock<Vec<AuditEntry>>,
+    downloads: RwLock<Downloads>,
+}
+
+impl State {
+    /// Gets the data of the given log, if it exists.
+    async fn find_log(&self, log_id: &LogId) -> Option<Arc<RwLock<LogData>>> {
+        self.logs.read().await.get(log_id).cloned()
+    }
+
+    /// Gets the data of the given log.
+    async fn log(&self, log_id: &LogId) -> Result<Arc<RwLock<LogData>>, DataStoreError> {
+        self.find_log(log_id)
+            .await
+            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))
+    }
+
+    /// Gets the data of the given log, creating it if it does not exist.
+    async fn log_or_default(&self, log_id: &LogId) -> Arc<RwLock<LogData>> {
+        if let Some(log) = self.find_log(log_id).await {
+            return log;
+        }
+
+        self.logs
+            .write()
+            .await
+            .entry(log_id.clone())
+            .or_default()
+            .clone()
+    }
+
+    /// Gets the data of every log.
+    async fn all_logs(&self) -> Vec<(LogId, Arc<RwLock<LogData>>)> {
+        self.logs
+            .read()
+            .await
+            .iter()
+            .map(|(log_id, log)| (log_id.clone(), log.clone()))
+            .collect()
+    }
+
+    /// Gets the name of the package with the given log.
+    async fn package_name(&self, log_id: &LogId) -> Option<PackageName> {
+        self.names
+            .read()
+            .await
+            .package_names
+            .get(log_id)
+            .cloned()
+            .flatten()
+    }
+
+    /// Gets a package record of the given log.
+    async fn package_record(
+        &self,
+        log_id: &LogId,
+        data: &LogData,
+        record_id: &RecordId,
+    ) -> Result<super::Record<package::PackageRecord>, DataStoreError> {
+        let status = data
+            .records
+            .get(record_id)
+            .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
+
+        let (status, envelope, registry_index) = match status {
+            RecordStatus::Pending(PendingRecord::Package { record, missing }) => (
+                if missing.is_empty() {
+                    super::RecordStatus::Pending
+                } else {
+                    super::RecordStatus::MissingContent(missing.iter().cloned().collect())
+                },
+                record.clone().unwrap(),
+                None,
+            ),
+            RecordStatus::Rejected(RejectedRecord::Package { record, reason, .. }) => (
+                super::RecordStatus::Rejected(reason.into()),
+                record.clone(),
+                None,
+            ),
+            RecordStatus::Validated(r) => {
+                let log = data
+                    .package
+                    .as_ref()
+                    .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
+
+                let published_length = self.registry.read().await.published_length();
+
+                (
+                    if r.registry_index < published_length {
+                        super::RecordStatus::Published
+                    } else {
+                        super::RecordStatus::Validated
+                    },
+                    log.entries[r.index]
+                        .record_content
+                        .clone()
+                        .ok_or_else(|| DataStoreError::RecordsCompacted(log_id.clone()))?,
+                    Some(r.registry_index),
+                )
+            }
+            _ => return Err(DataStoreError::RecordNotFound(record_id.clone())),
+        };
+
+        Ok(super::Record {
+            status,
+            envelope,
+            registry_index,
+        })
+    }
+}
+
 /// Represents an in-memory data store.
 ///
-/// Data is not persisted between restarts of the server.
+/// Data is not persisted between restarts of the server; clones share the
+/// same data, so a clone may be given to a restarted server in tests.
 ///
-/// Note: this is mainly used for testing, so it is not very efficient as
-/// it shares a single RwLock for all operations.
-pub struct MemoryDataStore(Arc<RwLock<State>>);
+/// Each log has its own lock, so fetching a large log does not block
+/// submissions to other logs. Committing a record updates its log while
+/// holding the lock of the registry's log, so a fetch never sees a
+/// checkpoint that includes a record missing from the record's log.
+#[derive(Clone)]
+pub struct MemoryDataStore(Arc<State>);
+
+/// Gets the releases of a package record along with the content of each of
+/// their artifacts.
+fn package_releases(record: &package::PackageRecord) -> Vec<(Version, AnyHash)> {
+    record
+        .entries
+        .iter()
+        .flat_map(|entry| match entry {
+            PackageEntry::Release { version, .. } => entry
+                .contents()
+                .map(|content| (version.clone(), content.clone()))
+                .collect(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
 
 impl MemoryDataStore {
     pub fn new() -> Self {
-        Self(Arc::new(RwLock::new(State::default())))
+        Self(Arc::new(State::default()))
     }
 }
 
@@ -108,14 +382,36 @@ impl DataStore for MemoryDataStore {
         Pin<Box<dyn Stream<Item = Result<TimestampedCheckpoint, DataStoreError>> + Send>>,
         DataStoreError,
     > {
-        Ok(Box::pin(futures::stream::empty()))
+        let registry = self.0.registry.read().await;
+        let mut checkpoints = registry
+            .checkpoints
+            .iter()
+            .map(|(len, checkpoint)| (*len, checkpoint.as_ref().clone()))
+            .collect::<Vec<_>>();
+        checkpoints.sort_by_key(|(len, _)| *len);
+
+        Ok(Box::pin(futures::stream::iter(
+            checkpoints
+                .into_iter()
+                .map(|(_, checkpoint)| Ok(checkpoint)),
+        )))
     }
 
     async fn get_all_validated_records(
         &self,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<LogLeaf, DataStoreError>> + Send>>, DataStoreError>
     {
-        Ok(Box::pin(futures::stream::empty()))
+        let registry = self.0.registry.read().await;
+        let mut leafs = registry
+            .log_leafs
+            .iter()
+            .map(|(index, leaf)| (*index, leaf.clone()))
+            .collect::<Vec<_>>();
+        leafs.sort_by_key(|(index, _)| *index);
+
+        Ok(Box::pin(futures::stream::iter(
+            leafs.into_iter().map(|(_, leaf)| Ok(leaf)),
+        )))
     }
 
     async fn get_log_leafs_starting_with_registry_index(
@@ -123,17 +419,17 @@ impl DataStore for MemoryDataStore {
         starting_index: RegistryIndex,
         limit: usize,
     ) -> Result<Vec<(RegistryIndex, LogLeaf)>, DataStoreError> {
-        let state = self.0.read().await;
+        let registry = self.0.registry.read().await;
 
-        let limit = if limit > state.log_leafs.len() - starting_index {
-            state.log_leafs.len() - starting_index
+        let limit = if limit > registry.log_leafs.len() - starting_index {
+            registry.log_leafs.len() - starting_index
         } else {
             limit
         };
 
         let mut leafs = Vec::with_capacity(limit);
         for entry in starting_index..starting_index + limit {
-            match state.log_leafs.get(&entry) {
+            match registry.log_leafs.get(&entry) {
                 Some(log_leaf) => leafs.push((entry, log_leaf.clone())),
                 None => break,
             }
@@ -146,11 +442,11 @@ impl DataStore for MemoryDataStore {
         &self,
         entries: &[RegistryIndex],
     ) -> Result<Vec<LogLeaf>, DataStoreError> {
-        let state = self.0.read().await;
+        let registry = self.0.registry.read().await;
 
         let mut leafs = Vec::with_capacity(entries.len());
         for entry in entries {
-            match state.log_leafs.get(entry) {
+            match registry.log_leafs.get(entry) {
                 Some(log_leaf) => leafs.push(log_leaf.clone()),
                 None => return Err(DataStoreError::LogLeafNotFound(*entry)),
             }
@@ -163,12 +459,12 @@ impl DataStore for MemoryDataStore {
         &self,
         log_ids: &[LogId],
     ) -> Result<IndexMap<LogId, Option<PackageName>>, DataStoreError> {
-        let state = self.0.read().await;
+        let names = self.0.names.read().await;
 
         log_ids
             .iter()
             .map(|log_id| {
-                if let Some(opt_package_name) = state.package_names.get(log_id) {
+                if let Some(opt_package_name) = names.package_names.get(log_id) {
                     Ok((log_id.clone(), opt_package_name.clone()))
                 } else {
                     Err(DataStoreError::LogNotFound(log_id.clone()))
@@ -183,8 +479,8 @@ impl DataStore for MemoryDataStore {
         record_id: &RecordId,
         record: &ProtoEnvelope<operator::OperatorRecord>,
     ) -> Result<(), DataStoreError> {
-        let mut state = self.0.write().await;
-        let prev = state.records.entry(log_id.clone()).or_default().insert(
+        let log = self.0.log_or_default(log_id).await;
+        let prev = log.write().await.records.insert(
             record_id.clone(),
             RecordStatus::Pending(PendingRecord::Operator {
                 record: Some(record.clone()),
@@ -201,12 +497,11 @@ impl DataStore for MemoryDataStore {
         record_id: &RecordId,
         reason: &str,
     ) -> Result<(), DataStoreError> {
-        let mut state = self.0.write().await;
+        let log = self.0.log(log_id).await?;
+        let mut log = log.write().await;
 
-        let status = state
+        let status = log
             .records
-            .get_mut(log_id)
-            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?
             .get_mut(record_id)
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
@@ -218,6 +513,7 @@ impl DataStore for MemoryDataStore {
         *status = RecordStatus::Rejected(RejectedRecord::Operator {
             record,
             reason: reason.to_string(),
+            rejected_at: Utc::now(),
         });
 
         Ok(())
@@ -229,25 +525,21 @@ impl DataStore for MemoryDataStore {
         record_id: &RecordId,
         registry_index: RegistryIndex,
     ) -> Result<(), DataStoreError> {
-        let mut state = self.0.write().await;
+        let data = self.0.log(log_id).await?;
+        let mut data = data.write().await;
 
-        let State {
-            operators,
-            records,
-            log_leafs,
-            ..
-        } = &mut *state;
+        let LogData {
+            operator, records, ..
+        } = &mut *data;
 
         let status = records
-            .get_mut(log_id)
-            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?
             .get_mut(record_id)
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
         match status {
             RecordStatus::Pending(PendingRecord::Operator { record }) => {
                 let record = record.take().unwrap();
-                let log = operators.entry(log_id.clone()).or_default();
+                let log = operator.get_or_insert_with(Default::default);
                 match log
                     .state
                     .clone()
@@ -255,17 +547,20 @@ impl DataStore for MemoryDataStore {
                     .map_err(DataStoreError::from)
                 {
                     Ok(s) => {
+                        // The leaf is inserted while the log is still locked
+                        let mut registry = self.0.registry.write().await;
                         log.state = s;
                         let index = log.entries.len();
                         log.entries.push(Entry {
                             registry_index,
-                            record_content: record,
+                            record_id: record_id.clone(),
+                            record_content: Some(record),
                         });
                         *status = RecordStatus::Validated(Record {
                             index,
                             registry_index,
                         });
-                        log_leafs.insert(
+                        registry.log_leafs.insert(
                             registry_index,
                             LogLeaf {
                                 log_id: log_id.clone(),
@@ -278,6 +573,7 @@ impl DataStore for MemoryDataStore {
                         *status = RecordStatus::Rejected(RejectedRecord::Operator {
                             record,
                             reason: e.to_string(),
+                            rejected_at: Utc::now(),
                         });
                         Err(e)
                     }
@@ -294,7 +590,7 @@ impl DataStore for MemoryDataStore {
         record_id: &RecordId,
         record: &ProtoEnvelope<package::PackageRecord>,
         missing: &IndexSet<&AnyHash>,
-    ) -> Result<(), DataStoreError> {
+    ) -> Result<Option<super::Record<package::PackageRecord>>, DataStoreError> {
         // Ensure the set of missing hashes is a subset of the record contents.
         debug_assert!({
             use warg_protocol::Record;
@@ -302,24 +598,39 @@ impl DataStore for MemoryDataStore {
             missing.is_subset(&contents)
         });
 
-        let mut state = self.0.write().await;
-        let prev = state.records.entry(log_id.clone()).or_default().insert(
-            record_id.clone(),
-            RecordStatus::Pending(PendingRecord::Package {
-                record: Some(record.clone()),
-                missing: missing.iter().map(|&d| d.clone()).collect(),
-            }),
-        );
-        state
+        let log = self.0.log_or_default(log_id).await;
+        {
+            let mut data = log.write().await;
+            if data.records.contains_key(record_id) {
+                // The record was already submitted, such as by a client
+                // retrying a submission that timed out
+                let existing = self.0.package_record(log_id, &data, record_id).await?;
+                if existing.envelope != *record {
+                    return Err(DataStoreError::Conflict);
+                }
+
+                return Ok(Some(existing));
+            }
+
+            data.records.insert(
+                record_id.clone(),
+                RecordStatus::Pending(PendingRecord::Package {
+                    record: Some(record.clone()),
+                    missing: missing.iter().map(|&d| d.clone()).collect(),
+                }),
+            );
+        }
+
+        let mut names = self.0.names.write().await;
+        names
             .package_names
             .insert(log_id.clone(), Some(package_name.clone()));
-        state.package_names_lowercase.insert(
+        names.package_names_lowercase.insert(
             package_name.as_ref().to_ascii_lowercase(),
             package_name.clone(),
         );
 
-        assert!(prev.is_none());
-        Ok(())
+        Ok(None)
     }
 
     async fn reject_package_record(
@@ -328,12 +639,11 @@ impl DataStore for MemoryDataStore {
         record_id: &RecordId,
         reason: &str,
     ) -> Result<(), DataStoreError> {
-        let mut state = self.0.write().await;
+        let log = self.0.log(log_id).await?;
+        let mut log = log.write().await;
 
-        let status = state
+        let status = log
             .records
-            .get_mut(log_id)
-            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?
             .get_mut(record_id)
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
@@ -345,6 +655,7 @@ impl DataStore for MemoryDataStore {
         *status = RecordStatus::Rejected(RejectedRecord::Package {
             record,
             reason: reason.to_string(),
+            rejected_at: Utc::now(),
         });
 
         Ok(())
@@ -355,44 +666,50 @@ impl DataStore for MemoryDataStore {
         log_id: &LogId,
         record_id: &RecordId,
         registry_index: RegistryIndex,
+        check: &PackageRecordCheck<'_>,
     ) -> Result<(), DataStoreError> {
-        let mut state = self.0.write().await;
+        let data = self.0.log(log_id).await?;
+        let mut data = data.write().await;
 
-        let State {
-            packages,
-            records,
-            log_leafs,
-            ..
-        } = &mut *state;
+        let LogData {
+            package, records, ..
+        } = &mut *data;
 
         let status = records
-            .get_mut(log_id)
-            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?
             .get_mut(record_id)
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
         match status {
             RecordStatus::Pending(PendingRecord::Package { record, .. }) => {
                 let record = record.take().unwrap();
-                let log = packages.entry(log_id.clone()).or_default();
+                let log = package.get_or_insert_with(Default::default);
+
+                // The record is validated against a copy of the log's state,
+                // which replaces the state only once the check also passes
                 match log
                     .state
                     .clone()
                     .validate(&record)
                     .map_err(DataStoreError::from)
-                {
+                    .and_then(|state| {
+                        check(&record, &state)?;
+                        Ok(state)
+                    }) {
                     Ok(state) => {
+                        // The leaf is inserted while the log is still locked
+                        let mut registry = self.0.registry.write().await;
                         log.state = state;
                         let index = log.entries.len();
                         log.entries.push(Entry {
                             registry_index,
-                            record_content: record,
+                            record_id: record_id.clone(),
+                            record_content: Some(record),
                         });
                         *status = RecordStatus::Validated(Record {
                             index,
                             registry_index,
                         });
-                        log_leafs.insert(
+                        registry.log_leafs.insert(
                             registry_index,
                             LogLeaf {
                                 log_id: log_id.clone(),
@@ -405,6 +722,7 @@ impl DataStore for MemoryDataStore {
                         *status = RecordStatus::Rejected(RejectedRecord::Package {
                             record,
                             reason: e.to_string(),
+                            rejected_at: Utc::now(),
                         });
                         Err(e)
                     }
@@ -420,13 +738,11 @@ impl DataStore for MemoryDataStore {
         record_id: &RecordId,
         digest: &AnyHash,
     ) -> Result<bool, DataStoreError> {
-        let state = self.0.read().await;
-        let log = state
-            .records
-            .get(log_id)
-            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
+        let log = self.0.log(log_id).await?;
+        let log = log.read().await;
 
         let status = log
+            .records
             .get(record_id)
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
@@ -442,19 +758,37 @@ impl DataStore for MemoryDataStore {
         }
     }
 
+    async fn get_missing_content(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+    ) -> Result<IndexSet<AnyHash>, DataStoreError> {
+        let log = self.0.log(log_id).await?;
+        let log = log.read().await;
+
+        let status = log
+            .records
+            .get(record_id)
+            .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
+
+        match status {
+            RecordStatus::Pending(PendingRecord::Operator { .. }) => Ok(IndexSet::new()),
+            RecordStatus::Pending(PendingRecord::Package { missing, .. }) => Ok(missing.clone()),
+            _ => Err(DataStoreError::RecordNotPending(record_id.clone())),
+        }
+    }
+
     async fn set_content_present(
         &self,
         log_id: &LogId,
         record_id: &RecordId,
         digest: &AnyHash,
     ) -> Result<bool, DataStoreError> {
-        let mut state = self.0.write().await;
-        let log = state
-            .records
-            .get_mut(log_id)
-            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
+        let log = self.0.log(log_id).await?;
+        let mut log = log.write().await;
 
         let status = log
+            .records
             .get_mut(record_id)
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
@@ -478,12 +812,33 @@ impl DataStore for MemoryDataStore {
 
     async fn store_checkpoint(
         &self,
-        _checkpoint_id: &AnyHash,
+        checkpoint_id: &AnyHash,
         ts_checkpoint: SerdeEnvelope<TimestampedCheckpoint>,
+        participants: &[LogLeaf],
     ) -> Result<(), DataStoreError> {
-        let mut state = self.0.write().await;
+        // Verify every participant before storing anything; validated
+        // records never change status, so the checks remain true below
+        for leaf in participants {
+            let validated = match self.0.find_log(&leaf.log_id).await {
+                Some(log) => matches!(
+                    log.read().await.records.get(&leaf.record_id),
+                    Some(RecordStatus::Validated(_))
+                ),
+                None => false,
+            };
+
+            if !validated {
+                return Err(DataStoreError::UnknownParticipant(leaf.clone()));
+            }
+        }
+
+        let mut registry = self.0.registry.write().await;
 
-        state
+        registry.checkpoint_ids.insert(
+            checkpoint_id.clone(),
+            ts_checkpoint.as_ref().checkpoint.log_length,
+        );
+        registry
             .checkpoints
             .insert(ts_checkpoint.as_ref().checkpoint.log_length, ts_checkpoint);
 
@@ -493,8 +848,12 @@ impl DataStore for MemoryDataStore {
     async fn get_latest_checkpoint(
         &self,
     ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, DataStoreError> {
-        let state = self.0.read().await;
-        let checkpoint = state.checkpoints.values().last().unwrap();
+        let registry = self.0.registry.read().await;
+        let checkpoint = registry
+            .checkpoints
+            .values()
+            .last()
+            .ok_or(DataStoreError::NoCheckpoints)?;
         Ok(checkpoint.clone())
     }
 
@@ -502,51 +861,115 @@ impl DataStore for MemoryDataStore {
         &self,
         log_length: RegistryLen,
     ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, DataStoreError> {
-        let state = self.0.read().await;
-        let checkpoint = state
+        let registry = self.0.registry.read().await;
+        let checkpoint = registry
             .checkpoints
             .get(&log_length)
             .ok_or_else(|| DataStoreError::CheckpointNotFound(log_length))?;
         Ok(checkpoint.clone())
     }
 
+    async fn get_first_checkpoint_including(
+        &self,
+        registry_index: RegistryIndex,
+    ) -> Result<Option<RegistryLen>, DataStoreError> {
+        let registry = self.0.registry.read().await;
+        Ok(registry
+            .checkpoints
+            .keys()
+            .find(|log_length| **log_length > registry_index)
+            .copied())
+    }
+
+    async fn get_checkpoint_by_id(
+        &self,
+        checkpoint_id: &AnyHash,
+    ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, DataStoreError> {
+        let registry = self.0.registry.read().await;
+        let checkpoint = registry
+            .checkpoint_ids
+            .get(checkpoint_id)
+            .and_then(|log_length| registry.checkpoints.get(log_length))
+            .ok_or_else(|| DataStoreError::CheckpointIdNotFound(checkpoint_id.clone()))?;
+        Ok(checkpoint.clone())
+    }
+
+    async fn get_checkpoints(
+        &self,
+        since: Option<&AnyHash>,
+        limit: u16,
+    ) -> Result<Vec<SerdeEnvelope<TimestampedCheckpoint>>, DataStoreError> {
+        let registry = self.0.registry.read().await;
+
+        // Checkpoints are stored in order of increasing log length
+        let start = match since {
+            Some(checkpoint_id) => {
+                let log_length = registry
+                    .checkpoint_ids
+                    .get(checkpoint_id)
+                    .ok_or_else(|| DataStoreError::CheckpointIdNotFound(checkpoint_id.clone()))?;
+                registry.checkpoints.get_index_of(log_length).unwrap() + 1
+            }
+            None => 0,
+        };
+
+        Ok(registry
+            .checkpoints
+            .values()
+            .skip(start)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_checkpoint_by_log_root(
+        &self,
+        log_root: &AnyHash,
+    ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, DataStoreError> {
+        let registry = self.0.registry.read().await;
+        let checkpoint = registry
+            .checkpoints
+            .values()
+            .find(|checkpoint| &checkpoint.as_ref().checkpoint.log_root == log_root)
+            .ok_or_else(|| DataStoreError::CheckpointLogRootNotFound(log_root.clone()))?;
+        Ok(checkpoint.clone())
+    }
+
     async fn get_operator_records(
         &self,
         log_id: &LogId,
         registry_log_length: RegistryLen,
         since: Option<&RecordId>,
         limit: u16,
-    ) -> Result<Vec<PublishedProtoEnvelope<operator::OperatorRecord>>, DataStoreError> {
-        let state = self.0.read().await;
+    ) -> Result<RecordPage<operator::OperatorRecord>, DataStoreError> {
+        let data = self.0.log(log_id).await?;
+        let data = data.read().await;
 
-        let log = state
-            .operators
-            .get(log_id)
+        let log = data
+            .operator
+            .as_ref()
             .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
 
-        if !state.checkpoints.contains_key(&registry_log_length) {
+        // Entries included in the checkpoint were committed before it was
+        // stored, so the registry's lock need not be held while paging
+        if !self
+            .0
+            .registry
+            .read()
+            .await
+            .checkpoints
+            .contains_key(&registry_log_length)
+        {
             return Err(DataStoreError::CheckpointNotFound(registry_log_length));
         };
 
         let start_log_idx = match since {
-            Some(since) => match &state.records[log_id][since] {
-                RecordStatus::Validated(record) => record.index + 1,
-                _ => unreachable!(),
-            },
+            Some(since) => data.index_after(since)?,
             None => 0,
         };
 
-        Ok(log
-            .entries
-            .iter()
-            .skip(start_log_idx)
-            .take_while(|entry| entry.registry_index < registry_log_length)
-            .map(|entry| PublishedProtoEnvelope {
-                envelope: entry.record_content.clone(),
-                registry_index: entry.registry_index,
-            })
-            .take(limit as usize)
-            .collect())
+        log.page(start_log_idx, registry_log_length, limit)
+            .ok_or_else(|| DataStoreError::RecordsCompacted(log_id.clone()))
     }
 
     async fn get_package_records(
@@ -555,37 +978,35 @@ impl DataStore for MemoryDataStore {
         registry_log_length: RegistryLen,
         since: Option<&RecordId>,
         limit: u16,
-    ) -> Result<Vec<PublishedProtoEnvelope<package::PackageRecord>>, DataStoreError> {
-        let state = self.0.read().await;
+    ) -> Result<RecordPage<package::PackageRecord>, DataStoreError> {
+        let data = self.0.log(log_id).await?;
+        let data = data.read().await;
 
-        let log = state
-            .packages
-            .get(log_id)
+        let log = data
+            .package
+            .as_ref()
             .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
 
-        if !state.checkpoints.contains_key(&registry_log_length) {
+        // Entries included in the checkpoint were committed before it was
+        // stored, so the registry's lock need not be held while paging
+        if !self
+            .0
+            .registry
+            .read()
+            .await
+            .checkpoints
+            .contains_key(&registry_log_length)
+        {
             return Err(DataStoreError::CheckpointNotFound(registry_log_length));
         };
 
         let start_log_idx = match since {
-            Some(since) => match &state.records[log_id][since] {
-                RecordStatus::Validated(record) => record.index + 1,
-                _ => unreachable!(),
-            },
+            Some(since) => data.index_after(since)?,
             None => 0,
         };
 
-        Ok(log
-            .entries
-            .iter()
-            .skip(start_log_idx)
-            .take_while(|entry| entry.registry_index < registry_log_length)
-            .map(|entry| PublishedProtoEnvelope {
-                envelope: entry.record_content.clone(),
-                registry_index: entry.registry_index,
-            })
-            .take(limit as usize)
-            .collect())
+        log.page(start_log_idx, registry_log_length, limit)
+            .ok_or_else(|| DataStoreError::RecordsCompacted(log_id.clone()))
     }
 
     async fn get_operator_record(
@@ -593,11 +1014,10 @@ impl DataStore for MemoryDataStore {
         log_id: &LogId,
         record_id: &RecordId,
     ) -> Result<super::Record<operator::OperatorRecord>, DataStoreError> {
-        let state = self.0.read().await;
-        let status = state
+        let data = self.0.log(log_id).await?;
+        let data = data.read().await;
+        let status = data
             .records
-            .get(log_id)
-            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?
             .get(record_id)
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
@@ -605,22 +1025,18 @@ impl DataStore for MemoryDataStore {
             RecordStatus::Pending(PendingRecord::Operator { record, .. }) => {
                 (super::RecordStatus::Pending, record.clone().unwrap(), None)
             }
-            RecordStatus::Rejected(RejectedRecord::Operator { record, reason }) => (
+            RecordStatus::Rejected(RejectedRecord::Operator { record, reason, .. }) => (
                 super::RecordStatus::Rejected(reason.into()),
                 record.clone(),
                 None,
             ),
             RecordStatus::Validated(r) => {
-                let log = state
-                    .operators
-                    .get(log_id)
+                let log = data
+                    .operator
+                    .as_ref()
                     .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
 
-                let published_length = state
-                    .checkpoints
-                    .last()
-                    .map(|(_, c)| c.as_ref().checkpoint.log_length)
-                    .unwrap_or_default();
+                let published_length = self.0.registry.read().await.published_length();
 
                 (
                     if r.registry_index < published_length {
@@ -628,7 +1044,10 @@ impl DataStore for MemoryDataStore {
                     } else {
                         super::RecordStatus::Validated
                     },
-                    log.entries[r.index].record_content.clone(),
+                    log.entries[r.index]
+                        .record_content
+                        .clone()
+                        .ok_or_else(|| DataStoreError::RecordsCompacted(log_id.clone()))?,
                     Some(r.registry_index),
                 )
             }
@@ -647,53 +1066,9 @@ impl DataStore for MemoryDataStore {
         log_id: &LogId,
         record_id: &RecordId,
     ) -> Result<super::Record<package::PackageRecord>, DataStoreError> {
-        let state = self.0.read().await;
-        let status = state
-            .records
-            .get(log_id)
-            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?
-            .get(record_id)
-            .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
-
-        let (status, envelope, registry_index) = match status {
-            RecordStatus::Pending(PendingRecord::Package { record, .. }) => {
-                (super::RecordStatus::Pending, record.clone().unwrap(), None)
-            }
-            RecordStatus::Rejected(RejectedRecord::Package { record, reason }) => (
-                super::RecordStatus::Rejected(reason.into()),
-                record.clone(),
-                None,
-            ),
-            RecordStatus::Validated(r) => {
-                let log = state
-                    .packages
-                    .get(log_id)
-                    .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
-
-                let published_length = state
-                    .checkpoints
-                    .last()
-                    .map(|(_, c)| c.as_ref().checkpoint.log_length)
-                    .unwrap_or_default();
-
-                (
-                    if r.registry_index < published_length {
-                        super::RecordStatus::Published
-                    } else {
-                        super::RecordStatus::Validated
-                    },
-                    log.entries[r.index].record_content.clone(),
-                    Some(r.registry_index),
-                )
-            }
-            _ => return Err(DataStoreError::RecordNotFound(record_id.clone())),
-        };
-
-        Ok(super::Record {
-            status,
-            envelope,
-            registry_index,
-        })
+        let data = self.0.log(log_id).await?;
+        let data = data.read().await;
+        self.0.package_record(log_id, &data, record_id).await
     }
 
     async fn verify_package_record_signature(
@@ -701,15 +1076,23 @@ impl DataStore for MemoryDataStore {
         log_id: &LogId,
         record: &ProtoEnvelope<package::PackageRecord>,
     ) -> Result<(), DataStoreError> {
-        let state = self.0.read().await;
-        let key = match state
-            .packages
-            .get(log_id)
+        let data = self.0.find_log(log_id).await;
+        let data = match &data {
+            Some(data) => Some(data.read().await),
+            None => None,
+        };
+        let key = match data
+            .as_ref()
+            .and_then(|data| data.package.as_ref())
             .and_then(|log| log.state.public_key(record.key_id()))
         {
             Some(key) => Some(key),
             None => match record.as_ref().entries.first() {
-                Some(PackageEntry::Init { key, .. }) => Some(key),
+                // A new log's first record is signed by the key it
+                // initializes the log with
+                Some(PackageEntry::Init { key, .. }) if key.fingerprint() == *record.key_id() => {
+                    Some(key)
+                }
                 _ => return Err(DataStoreError::UnknownKey(record.key_id().clone())),
             },
         }
@@ -719,21 +1102,71 @@ impl DataStore for MemoryDataStore {
             .map_err(|_| DataStoreError::SignatureVerificationFailed(record.signature().clone()))
     }
 
+    async fn get_operator_state(
+        &self,
+        log_id: &LogId,
+    ) -> Result<operator::LogState, DataStoreError> {
+        let data = self.0.log(log_id).await?;
+        let data = data.read().await;
+        data.operator
+            .as_ref()
+            .map(|log| log.state.clone())
+            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))
+    }
+
+    async fn get_package_state(&self, log_id: &LogId) -> Result<package::LogState, DataStoreError> {
+        let data = self.0.log(log_id).await?;
+        let data = data.read().await;
+        data.package
+            .as_ref()
+            .map(|log| log.state.clone())
+            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))
+    }
+
+    async fn get_package_log_status(
+        &self,
+        log_id: &LogId,
+    ) -> Result<PackageLogStatus, DataStoreError> {
+        let data = self.0.log(log_id).await?;
+        let data = data.read().await;
+        let log = data
+            .package
+            .as_ref()
+            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
+
+        let registry = self.0.registry.read().await;
+        let checkpoint = registry.checkpoints.values().last();
+        let checkpointed = checkpoint
+            .map(|c| c.as_ref().checkpoint.log_length)
+            .unwrap_or(0);
+
+        Ok(PackageLogStatus {
+            head: log.state.head().as_ref().map(|head| head.digest.clone()),
+            pending_records: log
+                .entries
+                .iter()
+                .rev()
+                .take_while(|e| e.registry_index >= checkpointed)
+                .count() as u64,
+            checkpoint_timestamp: checkpoint.map(|c| c.as_ref().timestamp),
+        })
+    }
+
     async fn verify_can_publish_package(
         &self,
         operator_log_id: &LogId,
         package_name: &PackageName,
     ) -> Result<(), DataStoreError> {
-        let state = self.0.read().await;
+        let data = self.0.log(operator_log_id).await?;
+        let data = data.read().await;
+        let operator = &data
+            .operator
+            .as_ref()
+            .ok_or_else(|| DataStoreError::LogNotFound(operator_log_id.clone()))?
+            .state;
 
         // verify namespace is defined and not imported
-        match state
-            .operators
-            .get(operator_log_id)
-            .ok_or_else(|| DataStoreError::LogNotFound(operator_log_id.clone()))?
-            .state
-            .namespace_state(package_name.namespace())
-        {
+        match operator.namespace_state(package_name.namespace()) {
             Ok(Some(state)) => match state {
                 operator::NamespaceState::Defined => {}
                 operator::NamespaceState::Imported { .. } => {
@@ -755,8 +1188,23 @@ impl DataStore for MemoryDataStore {
             }
         }
 
+        // verify package is not suppressed by the operator
+        if let Some(reason) = operator.package_suppression(&LogId::package_log_for(
+            operator_log_id.algorithm(),
+            package_name,
+        )) {
+            return Err(DataStoreError::PackageSuppressed {
+                name: package_name.clone(),
+                reason: reason.to_string(),
+            });
+        }
+
         // verify package name is unique in a case insensitive way
-        match state
+        match self
+            .0
+            .names
+            .read()
+            .await
             .package_names_lowercase
             .get(&package_name.as_ref().to_ascii_lowercase())
         {
@@ -775,11 +1223,12 @@ impl DataStore for MemoryDataStore {
         operator_log_id: &LogId,
         ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
     ) -> Result<(), DataStoreError> {
-        let state = self.0.read().await;
+        let data = self.0.log(operator_log_id).await?;
+        let data = data.read().await;
 
-        let state = &state
-            .operators
-            .get(operator_log_id)
+        let state = &data
+            .operator
+            .as_ref()
             .ok_or_else(|| DataStoreError::LogNotFound(operator_log_id.clone()))?
             .state;
 
@@ -794,7 +1243,10 @@ impl DataStore for MemoryDataStore {
             ts_checkpoint.signature().clone(),
         )))?;
 
-        if !state.key_has_permission_to_sign_checkpoints(ts_checkpoint.key_id()) {
+        if !state.key_has_permission_to_sign_checkpoints(
+            ts_checkpoint.key_id(),
+            ts_checkpoint.as_ref().timestamp,
+        ) {
             return Err(DataStoreError::KeyUnauthorized(
                 ts_checkpoint.key_id().clone(),
             ));
@@ -803,13 +1255,1025 @@ impl DataStore for MemoryDataStore {
         Ok(())
     }
 
+    async fn store_package_dependencies(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        version: &Version,
+        dependencies: &[PackageDependency],
+    ) -> Result<(), DataStoreError> {
+        let data = self.0.log(log_id).await?;
+        let mut data = data.write().await;
+
+        if !data.records.contains_key(record_id) {
+            return Err(DataStoreError::RecordNotFound(record_id.clone()));
+        }
+
+        data.dependencies
+            .entry(record_id.clone())
+            .or_default()
+            .insert(version.clone(), dependencies.to_vec());
+
+        Ok(())
+    }
+
+    async fn get_package_dependencies(
+        &self,
+        log_id: &LogId,
+        version: &Version,
+    ) -> Result<Vec<PackageDependency>, DataStoreError> {
+        let data = self.0.log(log_id).await?;
+        let data = data.read().await;
+
+        Ok(data
+            .dependencies
+            .iter()
+            .filter(|(record_id, _)| {
+                matches!(
+                    data.records.get(*record_id),
+                    Some(RecordStatus::Validated(_))
+                )
+            })
+            .find_map(|(_, releases)| releases.get(version).cloned())
+            .unwrap_or_default())
+    }
+
+    async fn get_package_dependents(
+        &self,
+        log_id: &LogId,
+    ) -> Result<Vec<PackageDependent>, DataStoreError> {
+        let mut dependents = Vec::new();
+        for (dependent_log_id, data) in self.0.all_logs().await {
+            let data = data.read().await;
+            if data.dependencies.is_empty() {
+                continue;
+            }
+
+            let name = match self.0.package_name(&dependent_log_id).await {
+                Some(name) => name,
+                None => continue,
+            };
+
+            for (record_id, releases) in &data.dependencies {
+                if !matches!(
+                    data.records.get(record_id),
+                    Some(RecordStatus::Validated(_))
+                ) {
+                    continue;
+                }
+
+                for (version, dependencies) in releases {
+                    if dependencies
+                        .iter()
+                        .any(|d| LogId::package_log_for(log_id.algorithm(), &d.name) == *log_id)
+                    {
+                        dependents.push(PackageDependent {
+                            name: name.clone(),
+                            version: version.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(dependents)
+    }
+
+    async fn get_content_releases(
+        &self,
+        digest: &AnyHash,
+    ) -> Result<Vec<(PackageName, Version)>, DataStoreError> {
+        let mut releases = Vec::new();
+        for (log_id, data) in self.0.all_logs().await {
+            let data = data.read().await;
+            let log = match &data.package {
+                Some(log) => log,
+                None => continue,
+            };
+
+            let name = match self.0.package_name(&log_id).await {
+                Some(name) => name,
+                None => continue,
+            };
+
+            for (version, content) in log
+                .entries
+                .iter()
+                .filter_map(|entry| entry.record_content.as_ref())
+                .flat_map(|record| package_releases(record.as_ref()))
+                .chain(log.compacted_releases.iter().cloned())
+            {
+                if &content == digest {
+                    releases.push((name.clone(), version));
+                }
+            }
+        }
+
+        Ok(releases)
+    }
+
+    async fn list_packages(
+        &self,
+        offset: u64,
+        limit: u16,
+    ) -> Result<Vec<PackageSummary>, DataStoreError> {
+        let mut summaries = Vec::new();
+        let mut skip = offset.try_into().unwrap_or(usize::MAX);
+        for (log_id, data) in self.0.all_logs().await {
+            if summaries.len() >= limit.into() {
+                break;
+            }
+
+            let data = data.read().await;
+            let log = match &data.package {
+                Some(log) if !log.entries.is_empty() => log,
+                _ => continue,
+            };
+
+            let name = match self.0.package_name(&log_id).await {
+                Some(name) => name,
+                None => continue,
+            };
+
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+
+            summaries.push(package_summary(
+                name,
+                log_id,
+                log.entries.len() as u64,
+                &log.state,
+            ));
+        }
+
+        Ok(summaries)
+    }
+
+    async fn get_referenced_content(&self) -> Result<IndexSet<AnyHash>, DataStoreError> {
+        let mut referenced = IndexSet::new();
+        for (_, data) in self.0.all_logs().await {
+            let data = data.read().await;
+
+            let validated = data
+                .package
+                .iter()
+                .flat_map(|log| &log.entries)
+                .filter_map(|entry| entry.record_content.as_ref());
+            let compacted = data
+                .package
+                .iter()
+                .flat_map(|log| &log.compacted_releases)
+                .map(|(_, content)| content.clone());
+            let pending = data.records.values().filter_map(|status| match status {
+                RecordStatus::Pending(PendingRecord::Package {
+                    record: Some(record),
+                    ..
+                }) => Some(record),
+                _ => None,
+            });
+
+            referenced.extend(
+                validated
+                    .chain(pending)
+                    .flat_map(|record| record.as_ref().contents())
+                    .cloned()
+                    .chain(compacted),
+            );
+        }
+
+        Ok(referenced)
+    }
+
+    async fn record_download_counts(
+        &self,
+        day: u64,
+        counts: &[DownloadCount],
+    ) -> Result<(), DataStoreError> {
+        let mut downloads = self.0.downloads.write().await;
+
+        for count in counts {
+            *downloads
+                .entry(count.name.clone())
+                .or_default()
+                .entry(count.version.clone())
+                .or_default()
+                .entry(day)
+                .or_default() += count.count;
+        }
+
+        Ok(())
+    }
+
+    async fn get_download_counts(
+        &self,
+        name: &PackageName,
+        today: u64,
+    ) -> Result<IndexMap<Version, DownloadCounts>, DataStoreError> {
+        let downloads = self.0.downloads.read().await;
+
+        Ok(downloads
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|(version, days)| {
+                let mut counts = DownloadCounts::default();
+                for (day, count) in days {
+                    add_daily_downloads(&mut counts, *day, today, *count);
+                }
+                (version.clone(), counts)
+            })
+            .collect())
+    }
+
+    async fn store_audit_entry(&self, entry: &AuditEntry) -> Result<(), DataStoreError> {
+        self.0.audit_entries.write().await.push(entry.clone());
+        Ok(())
+    }
+
+    async fn get_audit_entries(
+        &self,
+        filter: &AuditFilter,
+        limit: usize,
+    ) -> Result<Vec<AuditEntry>, DataStoreError> {
+        let audit_entries = self.0.audit_entries.read().await;
+        let mut entries = audit_entries
+            .iter()
+            .rev()
+            .filter(|e| filter.matches(e))
+            .take(limit)
+            .cloned()
+            .collect::<Vec<_>>();
+        entries.reverse();
+        Ok(entries)
+    }
+
+    async fn prune_rejected(&self, before: DateTime<Utc>) -> Result<usize, DataStoreError> {
+        let mut pruned = 0;
+        for (_, data) in self.0.all_logs().await {
+            let mut data = data.write().await;
+            let records = &mut data.records;
+            let len = records.len();
+            records.retain(|_, status| {
+                !matches!(status, RecordStatus::Rejected(record) if record.rejected_at() < before)
+            });
+            pruned += len - records.len();
+        }
+
+        Ok(pruned)
+    }
+
+    async fn compact_published(&self, keep_last: usize) -> Result<usize, DataStoreError> {
+        let published_length = self.0.registry.read().await.published_length();
+
+        let mut compacted = 0;
+        for (_, data) in self.0.all_logs().await {
+            if let Some(log) = &mut data.write().await.package {
+                compacted += log.compact(published_length, keep_last);
+            }
+        }
+
+        Ok(compacted)
+    }
+
     #[cfg(feature = "debug")]
     async fn debug_list_package_names(&self) -> anyhow::Result<Vec<PackageName>> {
-        let state = self.0.read().await;
-        Ok(state
+        let names = self.0.names.read().await;
+        Ok(names
             .package_names
             .values()
             .filter_map(|opt_package_name| opt_package_name.clone())
             .collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::time::{Duration, SystemTime};
+    use tokio::time::timeout;
+    use warg_api::v1::fetch::{FetchLogsStreamItem, PublishedRecord};
+    use warg_crypto::{
+        hash::{Hash, HashAlgorithm, Sha256},
+        signing::generate_p256_pair,
+    };
+    use warg_protocol::{
+        package::PACKAGE_RECORD_VERSION,
+        registry::{Checkpoint, TimestampedCheckpoint},
+    };
+
+    /// Stores a package log of the given number of records, each with a
+    /// release, and a checkpoint including all of them.
+    async fn store_package_log(
+        store: &MemoryDataStore,
+        name: &PackageName,
+        count: usize,
+    ) -> Result<(LogId, Vec<RecordId>), DataStoreError> {
+        let (public_key, signing_key) = generate_p256_pair();
+        let log_id = LogId::package_log::<Sha256>(name);
+        let content: AnyHash = Hash::<Sha256>::of("content").into();
+
+        let mut prev = None;
+        let mut record_ids = Vec::with_capacity(count);
+        for i in 0..count {
+            let mut entries = Vec::new();
+            if prev.is_none() {
+                entries.push(PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: public_key.clone(),
+                });
+            }
+            entries.push(PackageEntry::Release {
+                version: Version::new(0, 0, i as u64),
+                content: content.clone(),
+                artifacts: Default::default(),
+                metadata: None,
+            });
+
+            let record = ProtoEnvelope::signed_contents(
+                &signing_key,
+                package::PackageRecord {
+                    prev: prev.clone(),
+                    version: PACKAGE_RECORD_VERSION,
+                    timestamp: SystemTime::now(),
+                    entries,
+                },
+            )
+            .unwrap();
+            let record_id = RecordId::package_record::<Sha256>(&record);
+
+            store
+                .store_package_record(&log_id, name, &record_id, &record, &IndexSet::new())
+                .await?;
+            store
+                .commit_package_record(&log_id, &record_id, i as RegistryIndex, &|_, _| Ok(()))
+                .await?;
+
+            prev = Some(record_id.clone());
+            record_ids.push(record_id);
+        }
+
+        let checkpoint = TimestampedCheckpoint::now(Checkpoint {
+            log_root: content.clone(),
+            log_length: count as RegistryLen,
+            map_root: content.clone(),
+        })
+        .unwrap();
+        let checkpoint_id: AnyHash = Hash::<Sha256>::of(&checkpoint.checkpoint).into();
+        let participants = record_ids
+            .iter()
+            .map(|record_id| LogLeaf {
+                log_id: log_id.clone(),
+                record_id: record_id.clone(),
+            })
+            .collect::<Vec<_>>();
+        store
+            .store_checkpoint(
+                &checkpoint_id,
+                SerdeEnvelope::signed_contents(&signing_key, checkpoint).unwrap(),
+                &participants,
+            )
+            .await?;
+
+        Ok((log_id, record_ids))
+    }
+
+    /// Creates a signed checkpoint of the given log length.
+    fn signed_checkpoint(
+        log_length: RegistryLen,
+    ) -> (AnyHash, SerdeEnvelope<TimestampedCheckpoint>) {
+        let (_, signing_key) = generate_p256_pair();
+        let root: AnyHash = Hash::<Sha256>::of(log_length.to_string().as_str()).into();
+        let checkpoint = TimestampedCheckpoint::now(Checkpoint {
+            log_root: root.clone(),
+            log_length,
+            map_root: root,
+        })
+        .unwrap();
+        let checkpoint_id: AnyHash = Hash::<Sha256>::of(&checkpoint.checkpoint).into();
+        (
+            checkpoint_id,
+            SerdeEnvelope::signed_contents(&signing_key, checkpoint).unwrap(),
+        )
+    }
+
+    /// Stores a synthetic package log of the given number of records and a
+    /// checkpoint including all of them.
+    ///
+    /// Every entry shares the envelope of a single record and is stored
+    /// without being validated, so that long logs are quick to build.
+    async fn store_synthetic_package_log(
+        store: &MemoryDataStore,
+        name: &PackageName,
+        count: usize,
+    ) -> Result<(LogId, Vec<RecordId>), DataStoreError> {
+        let (_, signing_key) = generate_p256_pair();
+        let log_id = LogId::package_log::<Sha256>(name);
+        let record = ProtoEnvelope::signed_contents(
+            &signing_key,
+            package::PackageRecord {
+                prev: None,
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: Vec::new(),
+            },
+        )
+        .unwrap();
+
+        let mut data = LogData::default();
+        let log = data.package.get_or_insert_with(Default::default);
+        let mut record_ids = Vec::with_capacity(count);
+        for index in 0..count {
+            let record_id: RecordId =
+                AnyHash::from(Hash::<Sha256>::of(index.to_string().as_str())).into();
+            log.entries.push(Entry {
+                registry_index: index as RegistryIndex,
+                record_id: record_id.clone(),
+                record_content: Some(record.clone()),
+            });
+            data.records.insert(
+                record_id.clone(),
+                RecordStatus::Validated(Record {
+                    index,
+                    registry_index: index as RegistryIndex,
+                }),
+            );
+            record_ids.push(record_id);
+        }
+
+        store
+            .0
+            .logs
+            .write()
+            .await
+            .insert(log_id.clone(), Arc::new(RwLock::new(data)));
+        let (checkpoint_id, checkpoint) = signed_checkpoint(count as RegistryLen);
+        store
+            .store_checkpoint(&checkpoint_id, checkpoint, &[])
+            .await?;
+
+        Ok((log_id, record_ids))
+    }
+
+    #[tokio::test]
+    async fn it_reports_no_checkpoints_when_empty() -> Result<(), DataStoreError> {
+        let store = MemoryDataStore::default();
+        assert!(matches!(
+            store.get_latest_checkpoint().await,
+            Err(DataStoreError::NoCheckpoints)
+        ));
+
+        store_package_log(&store, &PackageName::new("test:empty").unwrap(), 1).await?;
+        assert_eq!(
+            store
+                .get_latest_checkpoint()
+                .await?
+                .as_ref()
+                .checkpoint
+                .log_length,
+            1
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_rejects_unknown_checkpoint_participants() -> Result<(), DataStoreError> {
+        let store = MemoryDataStore::default();
+        let name = PackageName::new("test:participants").unwrap();
+        let (log_id, record_ids) = store_package_log(&store, &name, 1).await?;
+
+        let unknown_log = LogLeaf {
+            log_id: LogId::package_log::<Sha256>(&PackageName::new("test:unknown").unwrap()),
+            record_id: record_ids[0].clone(),
+        };
+        let unknown_record = LogLeaf {
+            log_id: log_id.clone(),
+            record_id: RecordId::from(AnyHash::from(Hash::<Sha256>::of("unknown"))),
+        };
+
+        for leaf in [unknown_log, unknown_record] {
+            let (checkpoint_id, checkpoint) = signed_checkpoint(2);
+            match store
+                .store_checkpoint(&checkpoint_id, checkpoint, std::slice::from_ref(&leaf))
+                .await
+            {
+                Err(DataStoreError::UnknownParticipant(l)) => assert_eq!(l, leaf),
+                res => panic!("expected an unknown participant error, got {res:?}"),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_stores_no_partial_checkpoint() -> Result<(), DataStoreError> {
+        let store = MemoryDataStore::default();
+        let name = PackageName::new("test:partial").unwrap();
+        let (log_id, record_ids) = store_package_log(&store, &name, 2).await?;
+
+        // Fail on the last participant, after every other one was checked
+        let mut participants = record_ids
+            .iter()
+            .map(|record_id| LogLeaf {
+                log_id: log_id.clone(),
+                record_id: record_id.clone(),
+            })
+            .collect::<Vec<_>>();
+        participants.push(LogLeaf {
+            log_id: log_id.clone(),
+            record_id: RecordId::from(AnyHash::from(Hash::<Sha256>::of("pending"))),
+        });
+
+        let (checkpoint_id, checkpoint) = signed_checkpoint(3);
+        assert!(matches!(
+            store
+                .store_checkpoint(&checkpoint_id, checkpoint.clone(), &participants)
+                .await,
+            Err(DataStoreError::UnknownParticipant(_))
+        ));
+
+        assert_eq!(
+            store
+                .get_latest_checkpoint()
+                .await?
+                .as_ref()
+                .checkpoint
+                .log_length,
+            2
+        );
+        assert!(matches!(
+            store.get_checkpoint(3).await,
+            Err(DataStoreError::CheckpointNotFound(3))
+        ));
+        assert!(matches!(
+            store.get_checkpoint_by_id(&checkpoint_id).await,
+            Err(DataStoreError::CheckpointIdNotFound(_))
+        ));
+        assert_eq!(store.get_all_checkpoints().await?.count().await, 1);
+
+        // The checkpoint is stored once the participants are corrected
+        participants.pop();
+        store
+            .store_checkpoint(&checkpoint_id, checkpoint, &participants)
+            .await?;
+        assert_eq!(
+            store
+                .get_checkpoint_by_id(&checkpoint_id)
+                .await?
+                .as_ref()
+                .checkpoint
+                .log_length,
+            3
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_streams_package_records_in_bounded_memory() -> Result<(), DataStoreError> {
+        const RECORDS: usize = 50_000;
+
+        let store = MemoryDataStore::default();
+        let name = PackageName::new("test:long").unwrap();
+        let (log_id, record_ids) = store_synthetic_package_log(&store, &name, RECORDS).await?;
+
+        // Each record is encoded as the line of a streamed fetch response,
+        // which is dropped as though it were sent
+        let stream_lines = |since: Option<RecordId>, take: usize| {
+            let records = store
+                .stream_package_records(&log_id, RECORDS as RegistryLen, since)
+                .take(take);
+            async {
+                let mut lines = 0;
+                let mut bytes = 0;
+                let mut records = std::pin::pin!(records);
+                while let Some(record) = records.next().await {
+                    let record = record?;
+                    assert_eq!(record.registry_index, lines as RegistryIndex);
+                    let line = serde_json::to_vec(&FetchLogsStreamItem::Package {
+                        log_id: log_id.clone(),
+                        record: PublishedRecord {
+                            envelope: record.into(),
+                            fetch_token: record_ids[lines].to_string(),
+                        },
+                    })
+                    .unwrap();
+                    lines += 1;
+                    bytes += line.len() + 1;
+                }
+                Ok::<_, DataStoreError>((lines, bytes))
+            }
+        };
+
+        let (lines, first_peak) = allocations::peak(stream_lines(None, 1000)).await;
+        assert_eq!(lines?.0, 1000);
+        let (lines, peak) = allocations::peak(stream_lines(None, RECORDS)).await;
+        let (lines, bytes) = lines?;
+        assert_eq!(lines, RECORDS);
+
+        // Streaming fifty times the records uses about the same memory,
+        // a small fraction of a response buffering every line
+        assert!(
+            peak <= first_peak * 2,
+            "streaming {RECORDS} records peaked at {peak} bytes, but 1000 records peaked at {first_peak} bytes"
+        );
+        assert!(
+            peak * 100 < bytes,
+            "streaming {RECORDS} records peaked at {peak} bytes of {bytes} bytes of lines"
+        );
+
+        // The stream starts after the given record
+        let remaining = store
+            .stream_package_records(
+                &log_id,
+                RECORDS as RegistryLen,
+                Some(record_ids[RECORDS - 3].clone()),
+            )
+            .map(|record| record.map(|record| record.registry_index))
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            remaining,
+            [RECORDS as RegistryIndex - 2, RECORDS as RegistryIndex - 1]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_pages_package_records() -> Result<(), DataStoreError> {
+        const RECORDS: usize = 500;
+
+        let store = MemoryDataStore::default();
+        let name = PackageName::new("test:paged").unwrap();
+        let (log_id, record_ids) = store_package_log(&store, &name, RECORDS).await?;
+
+        let mut records = Vec::new();
+        let mut pages = 0;
+        let mut since = None;
+        loop {
+            let page = store
+                .get_package_records(&log_id, RECORDS as RegistryLen, since.as_ref(), 100)
+                .await?;
+            assert!(page.records.len() <= 100);
+            pages += 1;
+            records.extend(page.records);
+            match page.next {
+                Some(next) => since = Some(next),
+                None => break,
+            }
+        }
+
+        assert_eq!(pages, 5);
+        assert_eq!(records.len(), RECORDS);
+        for (i, record) in records.iter().enumerate() {
+            assert_eq!(record.registry_index, i as RegistryIndex);
+            assert_eq!(
+                RecordId::package_record::<Sha256>(&record.envelope),
+                record_ids[i]
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_rejects_paging_since_unvalidated_records() -> Result<(), DataStoreError> {
+        let store = MemoryDataStore::default();
+        let name = PackageName::new("test:since").unwrap();
+        let (log_id, record_ids) = store_package_log(&store, &name, 1).await?;
+
+        let (_, signing_key) = generate_p256_pair();
+        let record = ProtoEnvelope::signed_contents(
+            &signing_key,
+            package::PackageRecord {
+                prev: Some(record_ids[0].clone()),
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: Vec::new(),
+            },
+        )
+        .unwrap();
+        let pending = RecordId::package_record::<Sha256>(&record);
+        store
+            .store_package_record(&log_id, &name, &pending, &record, &IndexSet::new())
+            .await?;
+        assert!(matches!(
+            store.get_package_records(&log_id, 1, Some(&pending), 100).await,
+            Err(DataStoreError::RecordNotValidated(id)) if id == pending
+        ));
+
+        let unknown = RecordId::from(AnyHash::from(Hash::<Sha256>::of("unknown")));
+        assert!(matches!(
+            store.get_package_records(&log_id, 1, Some(&unknown), 100).await,
+            Err(DataStoreError::RecordNotFound(id)) if id == unknown
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_compacts_published_records() -> Result<(), DataStoreError> {
+        const RECORDS: usize = 50;
+
+        let store = MemoryDataStore::default();
+        let name = PackageName::new("test:compacted").unwrap();
+        let (log_id, record_ids) = store_package_log(&store, &name, RECORDS).await?;
+        let len = RECORDS as RegistryLen;
+
+        assert_eq!(store.compact_published(10).await?, 40);
+        assert_eq!(store.compact_published(10).await?, 0);
+
+        // Fetching since the last compacted record works
+        let page = store
+            .get_package_records(&log_id, len, Some(&record_ids[39]), 100)
+            .await?;
+        assert_eq!(page.records.len(), 10);
+        assert_eq!(page.records[0].registry_index, 40);
+
+        // Fetching compacted records fails
+        for since in [None, Some(&record_ids[38])] {
+            assert!(matches!(
+                store.get_package_records(&log_id, len, since, 100).await,
+                Err(DataStoreError::RecordsCompacted(id)) if id == log_id
+            ));
+        }
+        assert!(matches!(
+            store.get_package_record(&log_id, &record_ids[0]).await,
+            Err(DataStoreError::RecordsCompacted(_))
+        ));
+
+        // The releases of compacted records are still known
+        let content: AnyHash = Hash::<Sha256>::of("content").into();
+        assert_eq!(store.get_content_releases(&content).await?.len(), RECORDS);
+        assert!(store.get_referenced_content().await?.contains(&content));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_prunes_rejected_records() -> Result<(), DataStoreError> {
+        let store = MemoryDataStore::default();
+        let name = PackageName::new("test:pruned").unwrap();
+        let (log_id, _) = store_package_log(&store, &name, 1).await?;
+
+        let (_, signing_key) = generate_p256_pair();
+        let record = ProtoEnvelope::signed_contents(
+            &signing_key,
+            package::PackageRecord {
+                prev: None,
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: Vec::new(),
+            },
+        )
+        .unwrap();
+        let record_id = RecordId::package_record::<Sha256>(&record);
+        store
+            .store_package_record(&log_id, &name, &record_id, &record, &IndexSet::new())
+            .await?;
+        store
+            .reject_package_record(&log_id, &record_id, "rejected")
+            .await?;
+
+        let rejected_at = Utc::now();
+        assert_eq!(
+            store
+                .prune_rejected(rejected_at - chrono::Duration::hours(1))
+                .await?,
+            0
+        );
+        assert_eq!(
+            store
+                .prune_rejected(rejected_at + chrono::Duration::seconds(1))
+                .await?,
+            1
+        );
+        assert!(matches!(
+            store.get_package_record(&log_id, &record_id).await,
+            Err(DataStoreError::RecordNotFound(_))
+        ));
+        assert_eq!(
+            store.get_package_state(&log_id).await?.releases().count(),
+            1
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_rolls_back_records_failing_the_check() -> Result<(), DataStoreError> {
+        let store = MemoryDataStore::default();
+        let name = PackageName::new("test:checked").unwrap();
+        let log_id = LogId::package_log::<Sha256>(&name);
+        let (public_key, signing_key) = generate_p256_pair();
+        let content: AnyHash = Hash::<Sha256>::of("content").into();
+
+        let record = |prev: Option<RecordId>, entries: Vec<PackageEntry>| {
+            let record = ProtoEnvelope::signed_contents(
+                &signing_key,
+                package::PackageRecord {
+                    prev,
+                    version: PACKAGE_RECORD_VERSION,
+                    timestamp: SystemTime::now(),
+                    entries,
+                },
+            )
+            .unwrap();
+            (RecordId::package_record::<Sha256>(&record), record)
+        };
+        let release = |version| PackageEntry::Release {
+            version,
+            content: content.clone(),
+            artifacts: Default::default(),
+            metadata: None,
+        };
+
+        let (init_id, init) = record(
+            None,
+            vec![PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: public_key.clone(),
+            }],
+        );
+        store
+            .store_package_record(&log_id, &name, &init_id, &init, &IndexSet::new())
+            .await?;
+        store
+            .commit_package_record(&log_id, &init_id, 0, &|_, _| Ok(()))
+            .await?;
+
+        // A record that validates but fails the check is rejected
+        let (bad_id, bad) = record(Some(init_id.clone()), vec![release(Version::new(1, 0, 0))]);
+        store
+            .store_package_record(&log_id, &name, &bad_id, &bad, &IndexSet::new())
+            .await?;
+        let res = store
+            .commit_package_record(&log_id, &bad_id, 1, &|record, state| {
+                // The check sees the state with the record applied
+                assert_eq!(
+                    state.head().as_ref().map(|head| &head.digest),
+                    Some(&RecordId::package_record::<Sha256>(record))
+                );
+                Err(DataStoreError::Rejection("checked".to_string()))
+            })
+            .await;
+        assert!(matches!(res, Err(DataStoreError::Rejection(_))));
+        assert!(matches!(
+            store.get_package_record(&log_id, &bad_id).await?.status,
+            crate::datastore::RecordStatus::Rejected(_)
+        ));
+
+        // The log's state is unchanged, so the next record validates against the previous head
+        let (good_id, good) = record(Some(init_id), vec![release(Version::new(1, 0, 0))]);
+        store
+            .store_package_record(&log_id, &name, &good_id, &good, &IndexSet::new())
+            .await?;
+        store
+            .commit_package_record(&log_id, &good_id, 1, &|_, _| Ok(()))
+            .await?;
+
+        let state = store.get_package_state(&log_id).await?;
+        assert_eq!(
+            state.head().as_ref().map(|head| &head.digest),
+            Some(&good_id)
+        );
+        assert_eq!(state.releases().count(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_does_not_block_other_logs_during_a_fetch() -> Result<(), DataStoreError> {
+        let store = MemoryDataStore::default();
+        let fetched = PackageName::new("test:fetched").unwrap();
+        let (fetched_id, _) = store_package_log(&store, &fetched, 100).await?;
+
+        // Hold the fetched log's lock for as long as a large fetch would
+        let data = store.0.log(&fetched_id).await?;
+        let fetch = data.read().await;
+
+        // Another package can be submitted, committed, and fetched meanwhile
+        let submitted = PackageName::new("test:submitted").unwrap();
+        let (submitted_id, _) = timeout(
+            Duration::from_secs(10),
+            store_package_log(&store, &submitted, 10),
+        )
+        .await
+        .expect("submitting another package should not wait for the fetch")?;
+        let page = timeout(
+            Duration::from_secs(10),
+            store.get_package_records(&submitted_id, 10, None, 100),
+        )
+        .await
+        .expect("fetching another package should not wait for the fetch")?;
+        assert_eq!(page.records.len(), 10);
+
+        // Concurrent fetches of the same log are not blocked either
+        let page = timeout(
+            Duration::from_secs(10),
+            store.get_package_records(&fetched_id, 100, None, 100),
+        )
+        .await
+        .expect("fetching the same package should not wait for the fetch")?;
+        assert_eq!(page.records.len(), 100);
+
+        // Updating the fetched log waits until the fetch completes
+        assert!(
+            timeout(Duration::from_millis(100), store.compact_published(0))
+                .await
+                .is_err()
+        );
+        drop(fetch);
+        timeout(Duration::from_secs(10), store.compact_published(0))
+            .await
+            .expect("compaction should complete after the fetch")?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_does_not_duplicate_restored_dependencies() -> Result<(), DataStoreError> {
+        let store = MemoryDataStore::default();
+        let name = PackageName::new("test:dependent").unwrap();
+        let (log_id, record_ids) = store_package_log(&store, &name, 1).await?;
+        let version = Version::new(0, 0, 0);
+        let dependencies = vec![PackageDependency {
+            name: PackageName::new("test:dependency").unwrap(),
+            import: "unlocked-dep=<test:dependency@{>=1.0.0}>".to_string(),
+        }];
+
+        // Dependencies are stored again when a record is resubmitted
+        for _ in 0..2 {
+            store
+                .store_package_dependencies(&log_id, &record_ids[0], &version, &dependencies)
+                .await?;
+        }
+
+        assert_eq!(
+            store.get_package_dependencies(&log_id, &version).await?,
+            dependencies
+        );
+        Ok(())
+    }
+
+    /// Measures the memory allocated by tests.
+    mod allocations {
+        use std::{
+            alloc::{GlobalAlloc, Layout, System},
+            cell::Cell,
+            future::Future,
+        };
+
+        /// Counts the bytes allocated by each thread, so that the memory used
+        /// by a test is measured apart from the tests running beside it.
+        struct CountingAllocator;
+
+        #[global_allocator]
+        static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+        thread_local! {
+            static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+            static PEAK: Cell<isize> = const { Cell::new(0) };
+        }
+
+        fn track(bytes: isize) {
+            // The thread's counters are unavailable while it is torn down
+            let _ = ALLOCATED.try_with(|allocated| {
+                let current = allocated.get() + bytes;
+                allocated.set(current);
+                let _ = PEAK.try_with(|peak| peak.set(peak.get().max(current)));
+            });
+        }
+
+        unsafe impl GlobalAlloc for CountingAllocator {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                let ptr = System.alloc(layout);
+                if !ptr.is_null() {
+                    track(layout.size() as isize);
+                }
+                ptr
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout);
+                track(-(layout.size() as isize));
+            }
+        }
+
+        /// Runs the given future on the current thread, returning its output
+        /// and the most bytes it had allocated at once.
+        pub async fn peak<T>(future: impl Future<Output = T>) -> (T, usize) {
+            let start = ALLOCATED.with(Cell::get);
+            PEAK.with(|peak| peak.set(start));
+            let output = future.await;
+            let peak = PEAK.with(Cell::get) - start;
+            (output, peak.max(0) as usize)
+        }
+    }
+}
@@ -1,7 +1,15 @@
-use futures::Stream;
+use crate::audit::AuditFilter;
+use chrono::{DateTime, Utc};
+use futures::{stream, Stream, StreamExt, TryStreamExt};
 use indexmap::{IndexMap, IndexSet};
-use std::pin::Pin;
+use std::{future::Future, pin::Pin, time::UNIX_EPOCH};
 use thiserror::Error;
+use warg_api::v1::{
+    admin::AuditEntry,
+    package::{
+        DownloadCounts, PackageDependency, PackageDependent, PackageLogStatus, PackageSummary,
+    },
+};
 use warg_crypto::{
     hash::AnyHash,
     signing::{KeyID, Signature},
@@ -11,7 +19,7 @@ use warg_protocol::{
     registry::{
         LogId, LogLeaf, PackageName, RecordId, RegistryIndex, RegistryLen, TimestampedCheckpoint,
     },
-    ProtoEnvelope, PublishedProtoEnvelope, SerdeEnvelope,
+    ProtoEnvelope, PublishedProtoEnvelope, SerdeEnvelope, Version,
 };
 
 mod memory;
@@ -30,6 +38,15 @@ pub enum DataStoreError {
     #[error("checkpoint log length `{0}` was not found")]
     CheckpointNotFound(RegistryLen),
 
+    #[error("no checkpoint has been stored")]
+    NoCheckpoints,
+
+    #[error("checkpoint `{0}` was not found")]
+    CheckpointIdNotFound(AnyHash),
+
+    #[error("checkpoint with log root `{0}` was not found")]
+    CheckpointLogRootNotFound(AnyHash),
+
     #[error("log `{0}` was not found")]
     LogNotFound(LogId),
 
@@ -39,6 +56,12 @@ pub enum DataStoreError {
     #[error("log leaf {0} was not found")]
     LogLeafNotFound(RegistryIndex),
 
+    #[error("checkpoint participant `{}` is not a validated record of log `{}`", .0.record_id, .0.log_id)]
+    UnknownParticipant(LogLeaf),
+
+    #[error("record `{0}` has not been validated")]
+    RecordNotValidated(RecordId),
+
     #[error("record `{0}` cannot be validated as it is not in a pending state")]
     RecordNotPending(RecordId),
 
@@ -71,6 +94,9 @@ pub enum DataStoreError {
     )]
     PackageNamespaceImported(String),
 
+    #[error("the package `{name}` was suppressed by the registry operator: {reason}")]
+    PackageSuppressed { name: PackageName, reason: String },
+
     #[error("key id `{0}` does not have permission")]
     KeyUnauthorized(KeyID),
 
@@ -83,6 +109,12 @@ pub enum DataStoreError {
     #[error("the record was rejected: {0}")]
     Rejection(String),
 
+    #[error("the requested records of log `{0}` have been compacted")]
+    RecordsCompacted(LogId),
+
+    #[error("the data store does not support {0}")]
+    Unsupported(&'static str),
+
     #[cfg(feature = "postgres")]
     #[error("a connection could not be established to the PostgreSQL server: {0}")]
     ConnectionPool(#[from] diesel_async::pooled_connection::deadpool::PoolError),
@@ -107,6 +139,16 @@ pub enum RecordStatus {
     Published,
 }
 
+/// Represents a page of published records of a log.
+pub struct RecordPage<R> {
+    /// The records of the page, in log order.
+    pub records: Vec<PublishedProtoEnvelope<R>>,
+    /// The record to fetch the next page since.
+    ///
+    /// This is `None` if there are no more records in the log.
+    pub next: Option<RecordId>,
+}
+
 /// Represents a record in a log.
 pub struct Record<T>
 where
@@ -122,6 +164,111 @@ where
     pub registry_index: Option<RegistryIndex>,
 }
 
+/// Represents the number of downloads of a package release.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadCount {
+    /// The name of the downloaded package.
+    pub name: PackageName,
+    /// The downloaded version of the package.
+    pub version: Version,
+    /// The number of downloads.
+    pub count: u64,
+}
+
+/// Adds the downloads of a day to the given counts.
+///
+/// Days are numbered from the Unix epoch; `today` determines which of the
+/// recent windows the downloads fall in.
+pub(crate) fn add_daily_downloads(counts: &mut DownloadCounts, day: u64, today: u64, count: u64) {
+    let age = today.saturating_sub(day);
+    counts.total += count;
+    if age < 1 {
+        counts.last_day += count;
+    }
+    if age < 7 {
+        counts.last_week += count;
+    }
+    if age < 30 {
+        counts.last_month += count;
+    }
+}
+
+/// Summarizes a package from the validated state of its log.
+pub(crate) fn package_summary(
+    name: PackageName,
+    log_id: LogId,
+    record_count: u64,
+    state: &package::LogState,
+) -> PackageSummary {
+    let latest = state
+        .releases()
+        .filter(|r| !r.yanked())
+        .max_by(|a, b| a.version.cmp(&b.version));
+
+    PackageSummary {
+        name,
+        log_id,
+        record_count,
+        latest_version: latest.map(|r| r.version.clone()),
+        metadata: latest.and_then(|r| r.metadata.clone()),
+        updated: state
+            .head()
+            .as_ref()
+            .and_then(|head| head.timestamp.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+    }
+}
+
+/// A check performed when committing a record, after the record has been
+/// validated against its log.
+///
+/// The check is given the record and the state of the log with the record
+/// applied; if it fails, the record is rejected and the log is left as it
+/// was before validation.
+pub type RecordCheck<'a, R, S> =
+    dyn Fn(&ProtoEnvelope<R>, &S) -> Result<(), DataStoreError> + Send + Sync + 'a;
+
+/// A check performed when committing a package record.
+///
+/// See [`RecordCheck`].
+pub type PackageRecordCheck<'a> = RecordCheck<'a, package::PackageRecord, package::LogState>;
+
+/// The number of records read at a time by the record streams of a data store.
+const STREAM_PAGE_SIZE: u16 = 100;
+
+/// A stream of the published records of a log.
+pub type RecordStream<'a, R> =
+    Pin<Box<dyn Stream<Item = Result<PublishedProtoEnvelope<R>, DataStoreError>> + Send + 'a>>;
+
+/// Streams the records of a log, reading the page after each record with
+/// the given function until a page without a next record is read.
+fn stream_pages<'a, R, F, Fut>(since: Option<RecordId>, mut read: F) -> RecordStream<'a, R>
+where
+    R: Send + 'a,
+    F: FnMut(Option<RecordId>) -> Fut + Send + 'a,
+    Fut: Future<Output = Result<RecordPage<R>, DataStoreError>> + Send + 'a,
+{
+    // The state is `None` once the last page was read
+    let pages = stream::try_unfold(Some(since), move |since| {
+        let page = since.map(&mut read);
+        async move {
+            match page {
+                Some(page) => page
+                    .await
+                    .map(|page| Some((page.records, page.next.map(Some)))),
+                None => Ok(None),
+            }
+        }
+    });
+
+    Box::pin(
+        pages
+            .map_ok(|records| stream::iter(records).map(Ok))
+            .try_flatten(),
+    )
+}
+
 /// Implemented by data stores.
 #[axum::async_trait]
 pub trait DataStore: Send + Sync {
@@ -182,6 +329,11 @@ pub trait DataStore: Send + Sync {
     ///
     /// The `missing` set is the set of content digests that are currently
     /// missing from data storage.
+    ///
+    /// Storing a record that was already stored is not an error; the record
+    /// is not stored again and the existing record is returned instead. If a
+    /// different record with the same identifier was stored,
+    /// `DataStoreError::Conflict` is returned.
     async fn store_package_record(
         &self,
         log_id: &LogId,
@@ -189,7 +341,7 @@ pub trait DataStore: Send + Sync {
         record_id: &RecordId,
         record: &ProtoEnvelope<package::PackageRecord>,
         missing: &IndexSet<&AnyHash>,
-    ) -> Result<(), DataStoreError>;
+    ) -> Result<Option<Record<package::PackageRecord>>, DataStoreError>;
 
     /// Rejects the given package record.
     ///
@@ -205,12 +357,14 @@ pub trait DataStore: Send + Sync {
     ///
     /// The record must be in a pending state.
     ///
-    /// If validation succeeds, the record will be considered part of the log.
+    /// If validation and the given check succeed, the record will be
+    /// considered part of the log; otherwise, the record is rejected.
     async fn commit_package_record(
         &self,
         log_id: &LogId,
         record_id: &RecordId,
         registry_index: RegistryIndex,
+        check: &PackageRecordCheck<'_>,
     ) -> Result<(), DataStoreError>;
 
     /// Determines if the given content digest is missing for the record.
@@ -223,6 +377,15 @@ pub trait DataStore: Send + Sync {
         digest: &AnyHash,
     ) -> Result<bool, DataStoreError>;
 
+    /// Gets the digests of the content that is missing for the record.
+    ///
+    /// The record must be in a pending state.
+    async fn get_missing_content(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+    ) -> Result<IndexSet<AnyHash>, DataStoreError>;
+
     /// Sets the present flag for the given record and content digest.
     ///
     /// The record must be in a pending state.
@@ -239,13 +402,22 @@ pub trait DataStore: Send + Sync {
     ) -> Result<bool, DataStoreError>;
 
     /// Stores a new checkpoint.
+    ///
+    /// The participants are the log leafs first included in the checkpoint;
+    /// each must be a validated record of its log. The store is atomic: if
+    /// any participant is unknown, `DataStoreError::UnknownParticipant` is
+    /// returned and nothing is stored.
     async fn store_checkpoint(
         &self,
         checkpoint_id: &AnyHash,
         ts_checkpoint: SerdeEnvelope<TimestampedCheckpoint>,
+        participants: &[LogLeaf],
     ) -> Result<(), DataStoreError>;
 
     /// Gets the latest checkpoint.
+    ///
+    /// Returns [`DataStoreError::NoCheckpoints`] if no checkpoint has been
+    /// stored yet, such as while the registry is initializing.
     async fn get_latest_checkpoint(
         &self,
     ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, DataStoreError>;
@@ -256,6 +428,40 @@ pub trait DataStore: Send + Sync {
         log_length: RegistryLen,
     ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, DataStoreError>;
 
+    /// Gets the log length of the first checkpoint that includes the record
+    /// at the given registry index.
+    ///
+    /// Returns `None` if no checkpoint includes the record yet.
+    async fn get_first_checkpoint_including(
+        &self,
+        registry_index: RegistryIndex,
+    ) -> Result<Option<RegistryLen>, DataStoreError>;
+
+    /// Get checkpoint by checkpoint id.
+    async fn get_checkpoint_by_id(
+        &self,
+        checkpoint_id: &AnyHash,
+    ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, DataStoreError>;
+
+    /// Gets checkpoints in the order they were stored.
+    ///
+    /// If `since` is given, only checkpoints stored after the checkpoint with
+    /// that identifier are returned; [`DataStoreError::CheckpointIdNotFound`]
+    /// is returned if there is no such checkpoint.
+    ///
+    /// At most `limit` checkpoints are returned.
+    async fn get_checkpoints(
+        &self,
+        since: Option<&AnyHash>,
+        limit: u16,
+    ) -> Result<Vec<SerdeEnvelope<TimestampedCheckpoint>>, DataStoreError>;
+
+    /// Gets the checkpoint with the given log root.
+    async fn get_checkpoint_by_log_root(
+        &self,
+        log_root: &AnyHash,
+    ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, DataStoreError>;
+
     /// Gets package names from log IDs. If package name is unavailable, a corresponding `None` is returned.
     async fn get_package_names(
         &self,
@@ -269,23 +475,73 @@ pub trait DataStore: Send + Sync {
         limit: usize,
     ) -> Result<Vec<(RegistryIndex, LogLeaf)>, DataStoreError>;
 
-    /// Gets the operator records for the given registry log length.
+    /// Gets a page of the operator records for the given registry log length.
+    ///
+    /// The page starts after the `since` record, if given, and contains at
+    /// most `limit` records.
     async fn get_operator_records(
         &self,
         log_id: &LogId,
         registry_log_length: RegistryLen,
         since: Option<&RecordId>,
         limit: u16,
-    ) -> Result<Vec<PublishedProtoEnvelope<operator::OperatorRecord>>, DataStoreError>;
+    ) -> Result<RecordPage<operator::OperatorRecord>, DataStoreError>;
 
-    /// Gets the package records for the given registry log length.
+    /// Gets a page of the package records for the given registry log length.
+    ///
+    /// The page starts after the `since` record, if given, and contains at
+    /// most `limit` records.
     async fn get_package_records(
         &self,
         log_id: &LogId,
         registry_log_length: RegistryLen,
         since: Option<&RecordId>,
         limit: u16,
-    ) -> Result<Vec<PublishedProtoEnvelope<package::PackageRecord>>, DataStoreError>;
+    ) -> Result<RecordPage<package::PackageRecord>, DataStoreError>;
+
+    /// Gets a stream of the operator records for the given registry log length.
+    ///
+    /// The stream starts after the `since` record, if given. Records are read
+    /// a page at a time with `get_operator_records`, so the memory used by the
+    /// stream does not grow with the length of the log.
+    fn stream_operator_records<'a>(
+        &'a self,
+        log_id: &'a LogId,
+        registry_log_length: RegistryLen,
+        since: Option<RecordId>,
+    ) -> RecordStream<'a, operator::OperatorRecord> {
+        stream_pages(since, move |since| async move {
+            self.get_operator_records(
+                log_id,
+                registry_log_length,
+                since.as_ref(),
+                STREAM_PAGE_SIZE,
+            )
+            .await
+        })
+    }
+
+    /// Gets a stream of the package records for the given registry log length.
+    ///
+    /// The stream starts after the `since` record, if given. Records are read
+    /// a page at a time with `get_package_records`, so the memory used by the
+    /// stream does not grow with the length of the log.
+    fn stream_package_records<'a>(
+        &'a self,
+        log_id: &'a LogId,
+        registry_log_length: RegistryLen,
+        since: Option<RecordId>,
+    ) -> RecordStream<'a, package::PackageRecord> {
+        stream_pages(since, move |since| async move {
+            self.get_package_records(
+                log_id,
+                registry_log_length,
+                since.as_ref(),
+                STREAM_PAGE_SIZE,
+            )
+            .await
+        })
+    }
 
     /// Gets an operator record.
     async fn get_operator_record(
@@ -301,6 +557,24 @@ pub trait DataStore: Send + Sync {
         record_id: &RecordId,
     ) -> Result<Record<package::PackageRecord>, DataStoreError>;
 
+    /// Gets the current validator state of the operator log.
+    async fn get_operator_state(
+        &self,
+        log_id: &LogId,
+    ) -> Result<operator::LogState, DataStoreError>;
+
+    /// Gets the current validator state of a package log.
+    ///
+    /// The state reflects all validated records of the log, including
+    /// records that are not yet part of a checkpoint.
+    async fn get_package_state(&self, log_id: &LogId) -> Result<package::LogState, DataStoreError>;
+
+    /// Gets the progress of a package log toward the next checkpoint.
+    async fn get_package_log_status(
+        &self,
+        log_id: &LogId,
+    ) -> Result<PackageLogStatus, DataStoreError>;
+
     /// Verifies the signature of a package record.
     ///
     /// This is different from `validate_package_record` in that
@@ -329,6 +603,100 @@ pub trait DataStore: Send + Sync {
         ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
     ) -> Result<(), DataStoreError>;
 
+    /// Stores the dependencies of a release in the given package record.
+    ///
+    /// The dependencies are only reported once the record has been validated.
+    async fn store_package_dependencies(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        version: &Version,
+        dependencies: &[PackageDependency],
+    ) -> Result<(), DataStoreError>;
+
+    /// Gets the dependencies of the given release of a package.
+    async fn get_package_dependencies(
+        &self,
+        log_id: &LogId,
+        version: &Version,
+    ) -> Result<Vec<PackageDependency>, DataStoreError>;
+
+    /// Gets the package releases that depend on the package with the given log.
+    ///
+    /// The package does not need to have been published to this registry.
+    async fn get_package_dependents(
+        &self,
+        log_id: &LogId,
+    ) -> Result<Vec<PackageDependent>, DataStoreError>;
+
+    /// Gets the package releases with the given content.
+    ///
+    /// Only releases in validated records are returned.
+    async fn get_content_releases(
+        &self,
+        digest: &AnyHash,
+    ) -> Result<Vec<(PackageName, Version)>, DataStoreError>;
+
+    /// Gets a page of the packages with at least one validated record.
+    ///
+    /// Packages are ordered by when their first record was validated.
+    async fn list_packages(
+        &self,
+        offset: u64,
+        limit: u16,
+    ) -> Result<Vec<PackageSummary>, DataStoreError>;
+
+    /// Gets the digests of content referenced by package records that were
+    /// not rejected.
+    async fn get_referenced_content(&self) -> Result<IndexSet<AnyHash>, DataStoreError>;
+
+    /// Adds to the download counts of package releases on the given day.
+    ///
+    /// Days are numbered from the Unix epoch.
+    async fn record_download_counts(
+        &self,
+        day: u64,
+        counts: &[DownloadCount],
+    ) -> Result<(), DataStoreError>;
+
+    /// Gets the download counts of each release of a package as of the given day.
+    ///
+    /// Releases that have never been downloaded are not included.
+    async fn get_download_counts(
+        &self,
+        name: &PackageName,
+        today: u64,
+    ) -> Result<IndexMap<Version, DownloadCounts>, DataStoreError>;
+
+    /// Appends an entry to the audit log.
+    async fn store_audit_entry(&self, entry: &AuditEntry) -> Result<(), DataStoreError>;
+
+    /// Gets the most recent audit entries matching the filter, oldest first.
+    async fn get_audit_entries(
+        &self,
+        filter: &AuditFilter,
+        limit: usize,
+    ) -> Result<Vec<AuditEntry>, DataStoreError>;
+
+    /// Removes the records that were rejected before the given time.
+    ///
+    /// Returns the number of records that were removed.
+    async fn prune_rejected(&self, before: DateTime<Utc>) -> Result<usize, DataStoreError>;
+
+    /// Discards the envelopes of published package records, except for the
+    /// last `keep_last` published records of each package log.
+    ///
+    /// The record identifiers and registry indexes are retained so that
+    /// proofs about the structure of the registry log are unaffected;
+    /// fetching the compacted records fails with
+    /// [`DataStoreError::RecordsCompacted`].
+    ///
+    /// The operator log is never compacted as clients validate it from its
+    /// first record.
+    ///
+    /// Returns the number of records that were compacted.
+    async fn compact_published(&self, keep_last: usize) -> Result<usize, DataStoreError>;
+
     // Returns a list of package names, for debugging only.
     #[cfg(feature = "debug")]
     #[doc(hidden)]
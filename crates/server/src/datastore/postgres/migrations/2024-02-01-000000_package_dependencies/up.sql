@@ -0,0 +1,17 @@
+-- Represents the dependencies of a package release, derived from the
+-- imports of the released component.
+CREATE TABLE dependencies (
+  id SERIAL PRIMARY KEY,
+  record_id INTEGER NOT NULL REFERENCES records(id),
+  version TEXT NOT NULL,
+  log_id TEXT NOT NULL, -- the log id of the dependency package
+  name TEXT NOT NULL,
+  import TEXT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX dependencies_record_id_idx ON dependencies (record_id);
+CREATE INDEX dependencies_log_id_idx ON dependencies (log_id);
+
+SELECT diesel_manage_updated_at('dependencies');
@@ -0,0 +1,11 @@
+-- Represents the append-only audit log of mutating operations.
+CREATE TABLE audit_entries (
+  id BIGSERIAL PRIMARY KEY,
+  log_id TEXT, -- the log the operation applied to, if any
+  key_id TEXT, -- the key that signed the record, if any
+  entry JSONB NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX audit_entries_log_id_idx ON audit_entries (log_id);
+CREATE INDEX audit_entries_key_id_idx ON audit_entries (key_id);
@@ -0,0 +1,13 @@
+-- Represents the number of downloads of a package release on a day.
+CREATE TABLE download_counts (
+  id SERIAL PRIMARY KEY,
+  name TEXT NOT NULL,
+  version TEXT NOT NULL,
+  day BIGINT NOT NULL, -- the number of days since the Unix epoch
+  count BIGINT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  UNIQUE (name, version, day)
+);
+
+SELECT diesel_manage_updated_at('download_counts');
@@ -0,0 +1,4 @@
+-- The time after which a checkpoint is no longer valid, in seconds since the
+-- Unix epoch; checkpoints without an expiry never expire.
+ALTER TABLE checkpoints
+  ADD COLUMN expires BIGINT;
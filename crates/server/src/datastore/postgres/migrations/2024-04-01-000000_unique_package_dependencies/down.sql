@@ -0,0 +1 @@
+DROP INDEX dependencies_record_id_version_import_idx;
@@ -0,0 +1,10 @@
+-- Removes dependencies stored more than once for the same release, such as
+-- when storing them was retried, and prevents them from being stored again.
+DELETE FROM dependencies a USING dependencies b
+  WHERE a.id > b.id
+    AND a.record_id = b.record_id
+    AND a.version = b.version
+    AND a.import = b.import;
+
+CREATE UNIQUE INDEX dependencies_record_id_version_import_idx
+  ON dependencies (record_id, version, import);
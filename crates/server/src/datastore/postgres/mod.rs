@@ -1,11 +1,16 @@
 use self::models::{
-    CheckpointData, NewCheckpoint, NewContent, NewLog, NewRecord, ParsedText, RecordContent,
-    RecordStatus, TextRef,
+    CheckpointData, NewAuditEntry, NewCheckpoint, NewContent, NewDependency, NewDownloadCount,
+    NewLog, NewRecord, ParsedText, RecordContent, RecordStatus, TextRef,
 };
-use super::{DataStore, DataStoreError, Record};
+use super::{
+    add_daily_downloads, package_summary, DataStore, DataStoreError, DownloadCount,
+    PackageRecordCheck, Record, RecordCheck, RecordPage,
+};
+use crate::audit::AuditFilter;
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
 use diesel::sql_types::{Nullable, Text};
-use diesel::{prelude::*, result::DatabaseErrorKind};
+use diesel::{prelude::*, result::DatabaseErrorKind, upsert::excluded};
 use diesel_async::{
     pooled_connection::{deadpool::Pool, AsyncDieselConnectionManager},
     scoped_futures::ScopedFutureExt,
@@ -19,6 +24,12 @@ use futures::{Stream, StreamExt};
 use indexmap::{IndexMap, IndexSet};
 use secrecy::{ExposeSecret, SecretString};
 use std::pin::Pin;
+use warg_api::v1::{
+    admin::AuditEntry,
+    package::{
+        DownloadCounts, PackageDependency, PackageDependent, PackageLogStatus, PackageSummary,
+    },
+};
 use warg_crypto::{hash::AnyHash, Decode, Encode, Signable};
 use warg_protocol::{
     operator,
@@ -27,7 +38,7 @@ use warg_protocol::{
         Checkpoint, LogId, LogLeaf, PackageName, RecordId, RegistryIndex, RegistryLen,
         TimestampedCheckpoint,
     },
-    ProtoEnvelope, PublishedProtoEnvelope, Record as _, SerdeEnvelope, Validator,
+    ProtoEnvelope, PublishedProtoEnvelope, Record as _, SerdeEnvelope, Validator, Version,
 };
 
 mod models;
@@ -41,7 +52,7 @@ async fn get_records<R: Decode>(
     registry_log_length: RegistryLen,
     since: Option<&RecordId>,
     limit: i64,
-) -> Result<Vec<PublishedProtoEnvelope<R>>, DataStoreError> {
+) -> Result<RecordPage<R>, DataStoreError> {
     schema::checkpoints::table
         .select(schema::checkpoints::log_length)
         .filter(schema::checkpoints::log_length.eq(registry_log_length as i64))
@@ -58,7 +69,8 @@ async fn get_records<R: Decode>(
             schema::records::registry_log_index,
         ))
         .order_by(schema::records::id.asc())
-        .limit(limit)
+        // Fetch an extra record to determine if there are more records
+        .limit(limit + 1)
         .filter(
             schema::records::log_id
                 .eq(log_id)
@@ -67,20 +79,38 @@ async fn get_records<R: Decode>(
         );
 
     if let Some(since) = since {
-        let record_id = schema::records::table
-            .select(schema::records::id)
-            .filter(schema::records::record_id.eq(TextRef(since)))
-            .first::<i32>(conn)
+        let (record_id, status) = schema::records::table
+            .select((schema::records::id, schema::records::status))
+            .filter(
+                schema::records::log_id
+                    .eq(log_id)
+                    .and(schema::records::record_id.eq(TextRef(since))),
+            )
+            .first::<(i32, RecordStatus)>(conn)
             .await
             .optional()?
             .ok_or_else(|| DataStoreError::RecordNotFound(since.clone()))?;
 
+        if status != RecordStatus::Validated {
+            return Err(DataStoreError::RecordNotValidated(since.clone()));
+        }
+
         query = query.filter(schema::records::id.gt(record_id));
     }
 
-    query
+    let mut rows = query
         .load::<(ParsedText<AnyHash>, Vec<u8>, Option<i64>)>(conn)
-        .await?
+        .await?;
+
+    let next = if rows.len() as i64 > limit {
+        rows.truncate(limit as usize);
+        rows.last()
+            .map(|(record_id, _, _)| RecordId::from(record_id.0.clone()))
+    } else {
+        None
+    };
+
+    let records = rows
         .into_iter()
         .map(
             |(record_id, c, index)| match ProtoEnvelope::from_protobuf(&c) {
@@ -94,7 +124,9 @@ async fn get_records<R: Decode>(
                 }),
             },
         )
-        .collect::<Result<_, _>>()
+        .collect::<Result<_, _>>()?;
+
+    Ok(RecordPage { records, next })
 }
 
 async fn insert_record<V>(
@@ -201,7 +233,23 @@ async fn reject_record(
         .await?;
 
     if count != 1 {
-        return Err(DataStoreError::RecordNotFound(record_id.clone()));
+        let exists = schema::records::table
+            .select(schema::records::id)
+            .filter(
+                schema::records::record_id
+                    .eq(TextRef(record_id))
+                    .and(schema::records::log_id.eq(log_id)),
+            )
+            .first::<i32>(conn)
+            .await
+            .optional()?
+            .is_some();
+
+        return Err(if exists {
+            DataStoreError::RecordNotPending(record_id.clone())
+        } else {
+            DataStoreError::RecordNotFound(record_id.clone())
+        });
     }
 
     Ok(())
@@ -212,6 +260,7 @@ async fn commit_record<V>(
     log_id: i32,
     record_id: &RecordId,
     registry_index: RegistryIndex,
+    check: &RecordCheck<'_, V::Record, V>,
 ) -> Result<(), DataStoreError>
 where
     V: Validator + 'static,
@@ -248,8 +297,9 @@ where
                 }
             })?;
 
-            // Validate the record
-            let validator = validator.0.validate(&record).map_err(Into::into)?;
+            // Validate the record; a failed check rolls back the transaction
+            let validator = validator.0.validate(&record)?;
+            check(&record, &validator)?;
 
             // Store the updated validation state
             diesel::update(schema::logs::table)
@@ -391,6 +441,25 @@ impl PostgresDataStore {
     }
 }
 
+/// Converts a stored checkpoint to a checkpoint envelope.
+fn checkpoint_envelope(checkpoint: CheckpointData) -> SerdeEnvelope<TimestampedCheckpoint> {
+    SerdeEnvelope::from_parts_unchecked(
+        TimestampedCheckpoint {
+            checkpoint: Checkpoint {
+                log_root: checkpoint.log_root.0,
+                log_length: checkpoint.log_length as RegistryLen,
+                map_root: checkpoint.map_root.0,
+            },
+            timestamp: checkpoint.timestamp.try_into().unwrap(),
+            expires: checkpoint
+                .expires
+                .map(|expires| expires.try_into().unwrap()),
+        },
+        checkpoint.key_id.0,
+        checkpoint.signature.0,
+    )
+}
+
 #[axum::async_trait]
 impl DataStore for PostgresDataStore {
     async fn get_all_checkpoints(
@@ -416,6 +485,9 @@ impl DataStore for PostgresDataStore {
                             map_root: checkpoint.map_root.0,
                         },
                         timestamp: checkpoint.timestamp.try_into().unwrap(),
+                        expires: checkpoint
+                            .expires
+                            .map(|expires| expires.try_into().unwrap()),
                     })
                 },
             )
@@ -608,8 +680,14 @@ impl DataStore for PostgresDataStore {
             .optional()?
             .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
 
-        match commit_record::<operator::LogState>(conn.as_mut(), log_id, record_id, registry_index)
-            .await
+        match commit_record::<operator::LogState>(
+            conn.as_mut(),
+            log_id,
+            record_id,
+            registry_index,
+            &|_, _| Ok(()),
+        )
+        .await
         {
             Ok(()) => Ok(()),
             Err(e) => {
@@ -626,9 +704,9 @@ impl DataStore for PostgresDataStore {
         record_id: &RecordId,
         record: &ProtoEnvelope<package::PackageRecord>,
         missing: &IndexSet<&AnyHash>,
-    ) -> Result<(), DataStoreError> {
+    ) -> Result<Option<Record<package::PackageRecord>>, DataStoreError> {
         let mut conn = self.pool.get().await?;
-        insert_record::<package::LogState>(
+        match insert_record::<package::LogState>(
             conn.as_mut(),
             log_id,
             Some(package_name.as_ref()),
@@ -637,6 +715,23 @@ impl DataStore for PostgresDataStore {
             missing,
         )
         .await
+        {
+            Ok(()) => Ok(None),
+            // The record may have already been submitted, such as by a client
+            // retrying a submission that timed out
+            Err(DataStoreError::Conflict) => {
+                let existing = self
+                    .get_package_record(log_id, record_id)
+                    .await
+                    .map_err(|_| DataStoreError::Conflict)?;
+                if existing.envelope != *record {
+                    return Err(DataStoreError::Conflict);
+                }
+
+                Ok(Some(existing))
+            }
+            Err(e) => Err(e),
+        }
     }
 
     async fn reject_package_record(
@@ -662,6 +757,7 @@ impl DataStore for PostgresDataStore {
         log_id: &LogId,
         record_id: &RecordId,
         registry_index: RegistryIndex,
+        check: &PackageRecordCheck<'_>,
     ) -> Result<(), DataStoreError> {
         let mut conn = self.pool.get().await?;
         let log_id = schema::logs::table
@@ -672,8 +768,14 @@ impl DataStore for PostgresDataStore {
             .optional()?
             .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
 
-        match commit_record::<package::LogState>(conn.as_mut(), log_id, record_id, registry_index)
-            .await
+        match commit_record::<package::LogState>(
+            conn.as_mut(),
+            log_id,
+            record_id,
+            registry_index,
+            check,
+        )
+        .await
         {
             Ok(()) => Ok(()),
             Err(e) => {
@@ -707,6 +809,40 @@ impl DataStore for PostgresDataStore {
             .ok_or_else(|| DataStoreError::RecordNotPending(record_id.clone()))
     }
 
+    async fn get_missing_content(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+    ) -> Result<IndexSet<AnyHash>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+        let id = schema::records::table
+            .inner_join(schema::logs::table)
+            .select(schema::records::id)
+            .filter(
+                schema::records::status
+                    .eq(RecordStatus::Pending)
+                    .and(schema::logs::log_id.eq(TextRef(log_id)))
+                    .and(schema::records::record_id.eq(TextRef(record_id))),
+            )
+            .first::<i32>(conn.as_mut())
+            .await
+            .optional()?
+            .ok_or_else(|| DataStoreError::RecordNotPending(record_id.clone()))?;
+
+        Ok(schema::contents::table
+            .select(schema::contents::digest)
+            .filter(
+                schema::contents::record_id
+                    .eq(id)
+                    .and(schema::contents::missing.eq(true)),
+            )
+            .load::<ParsedText<AnyHash>>(conn.as_mut())
+            .await?
+            .into_iter()
+            .map(|d| d.0)
+            .collect())
+    }
+
     async fn set_content_present(
         &self,
         log_id: &LogId,
@@ -773,11 +909,29 @@ impl DataStore for PostgresDataStore {
         &self,
         checkpoint_id: &AnyHash,
         ts_checkpoint: SerdeEnvelope<TimestampedCheckpoint>,
+        participants: &[LogLeaf],
     ) -> Result<(), DataStoreError> {
         let mut conn = self.pool.get().await?;
 
         conn.transaction::<_, DataStoreError, _>(|conn| {
             async move {
+                // Verify every participant is a validated record of its log
+                for leaf in participants {
+                    schema::records::table
+                        .inner_join(schema::logs::table)
+                        .select(schema::records::id)
+                        .filter(
+                            schema::logs::log_id
+                                .eq(TextRef(&leaf.log_id))
+                                .and(schema::records::record_id.eq(TextRef(&leaf.record_id)))
+                                .and(schema::records::status.eq(RecordStatus::Validated)),
+                        )
+                        .first::<i32>(conn)
+                        .await
+                        .optional()?
+                        .ok_or_else(|| DataStoreError::UnknownParticipant(leaf.clone()))?;
+                }
+
                 let TimestampedCheckpoint {
                     checkpoint:
                         Checkpoint {
@@ -786,6 +940,7 @@ impl DataStore for PostgresDataStore {
                             map_root,
                         },
                     timestamp,
+                    expires,
                 } = ts_checkpoint.as_ref();
 
                 // Replacing any existing checkpoint with the same checkpoint_id
@@ -806,6 +961,7 @@ impl DataStore for PostgresDataStore {
                         key_id: TextRef(ts_checkpoint.key_id()),
                         signature: TextRef(ts_checkpoint.signature()),
                         timestamp: (*timestamp).try_into().unwrap(),
+                        expires: expires.map(|expires| expires.try_into().unwrap()),
                     })
                     .returning(schema::checkpoints::id)
                     .get_result::<i32>(conn)
@@ -828,7 +984,9 @@ impl DataStore for PostgresDataStore {
         let checkpoint = schema::checkpoints::table
             .order_by(schema::checkpoints::id.desc())
             .first::<CheckpointData>(&mut conn)
-            .await?;
+            .await
+            .optional()?
+            .ok_or(DataStoreError::NoCheckpoints)?;
 
         let log_length = checkpoint.log_length.try_into().unwrap();
 
@@ -840,6 +998,9 @@ impl DataStore for PostgresDataStore {
                     map_root: checkpoint.map_root.0,
                 },
                 timestamp: checkpoint.timestamp.try_into().unwrap(),
+                expires: checkpoint
+                    .expires
+                    .map(|expires| expires.try_into().unwrap()),
             },
             checkpoint.key_id.0,
             checkpoint.signature.0,
@@ -867,19 +1028,113 @@ impl DataStore for PostgresDataStore {
                     map_root: checkpoint.map_root.0,
                 },
                 timestamp: checkpoint.timestamp.try_into().unwrap(),
+                expires: checkpoint
+                    .expires
+                    .map(|expires| expires.try_into().unwrap()),
+            },
+            checkpoint.key_id.0,
+            checkpoint.signature.0,
+        ))
+    }
+
+    async fn get_first_checkpoint_including(
+        &self,
+        registry_index: RegistryIndex,
+    ) -> Result<Option<RegistryLen>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+
+        Ok(schema::checkpoints::table
+            .filter(schema::checkpoints::log_length.gt(registry_index as i64))
+            .order(schema::checkpoints::log_length.asc())
+            .select(schema::checkpoints::log_length)
+            .first::<i64>(&mut conn)
+            .await
+            .optional()?
+            .map(|log_length| log_length as RegistryLen))
+    }
+
+    async fn get_checkpoint_by_id(
+        &self,
+        checkpoint_id: &AnyHash,
+    ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+
+        let checkpoint = schema::checkpoints::table
+            .filter(schema::checkpoints::checkpoint_id.eq(TextRef(checkpoint_id)))
+            .first::<CheckpointData>(&mut conn)
+            .await
+            .optional()?
+            .ok_or_else(|| DataStoreError::CheckpointIdNotFound(checkpoint_id.clone()))?;
+
+        Ok(SerdeEnvelope::from_parts_unchecked(
+            TimestampedCheckpoint {
+                checkpoint: Checkpoint {
+                    log_root: checkpoint.log_root.0,
+                    log_length: checkpoint.log_length as RegistryLen,
+                    map_root: checkpoint.map_root.0,
+                },
+                timestamp: checkpoint.timestamp.try_into().unwrap(),
+                expires: checkpoint
+                    .expires
+                    .map(|expires| expires.try_into().unwrap()),
             },
             checkpoint.key_id.0,
             checkpoint.signature.0,
         ))
     }
 
+    async fn get_checkpoints(
+        &self,
+        since: Option<&AnyHash>,
+        limit: u16,
+    ) -> Result<Vec<SerdeEnvelope<TimestampedCheckpoint>>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+
+        let after = match since {
+            Some(checkpoint_id) => schema::checkpoints::table
+                .select(schema::checkpoints::id)
+                .filter(schema::checkpoints::checkpoint_id.eq(TextRef(checkpoint_id)))
+                .first::<i32>(&mut conn)
+                .await
+                .optional()?
+                .ok_or_else(|| DataStoreError::CheckpointIdNotFound(checkpoint_id.clone()))?,
+            None => 0,
+        };
+
+        Ok(schema::checkpoints::table
+            .filter(schema::checkpoints::id.gt(after))
+            .order_by(schema::checkpoints::id)
+            .limit(limit as i64)
+            .load::<CheckpointData>(&mut conn)
+            .await?
+            .into_iter()
+            .map(checkpoint_envelope)
+            .collect())
+    }
+
+    async fn get_checkpoint_by_log_root(
+        &self,
+        log_root: &AnyHash,
+    ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+
+        let checkpoint = schema::checkpoints::table
+            .filter(schema::checkpoints::log_root.eq(TextRef(log_root)))
+            .first::<CheckpointData>(&mut conn)
+            .await
+            .optional()?
+            .ok_or_else(|| DataStoreError::CheckpointLogRootNotFound(log_root.clone()))?;
+
+        Ok(checkpoint_envelope(checkpoint))
+    }
+
     async fn get_operator_records(
         &self,
         log_id: &LogId,
         registry_log_length: RegistryLen,
         since: Option<&RecordId>,
         limit: u16,
-    ) -> Result<Vec<PublishedProtoEnvelope<operator::OperatorRecord>>, DataStoreError> {
+    ) -> Result<RecordPage<operator::OperatorRecord>, DataStoreError> {
         let mut conn = self.pool.get().await?;
         let log_id = schema::logs::table
             .select(schema::logs::id)
@@ -898,7 +1153,7 @@ impl DataStore for PostgresDataStore {
         registry_log_length: RegistryLen,
         since: Option<&RecordId>,
         limit: u16,
-    ) -> Result<Vec<PublishedProtoEnvelope<package::PackageRecord>>, DataStoreError> {
+    ) -> Result<RecordPage<package::PackageRecord>, DataStoreError> {
         let mut conn = self.pool.get().await?;
         let log_id = schema::logs::table
             .select(schema::logs::id)
@@ -929,6 +1184,92 @@ impl DataStore for PostgresDataStore {
         get_record::<package::LogState>(conn.as_mut(), log_id, record_id).await
     }
 
+    async fn get_operator_state(
+        &self,
+        log_id: &LogId,
+    ) -> Result<operator::LogState, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+
+        schema::logs::table
+            .select(schema::logs::validator)
+            .filter(schema::logs::log_id.eq(TextRef(log_id)))
+            .first::<Json<operator::LogState>>(&mut conn)
+            .await
+            .optional()?
+            .map(|validator| validator.0)
+            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))
+    }
+
+    async fn get_package_state(&self, log_id: &LogId) -> Result<package::LogState, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+
+        schema::logs::table
+            .select(schema::logs::validator)
+            .filter(schema::logs::log_id.eq(TextRef(log_id)))
+            .first::<Json<package::LogState>>(&mut conn)
+            .await
+            .optional()?
+            .map(|validator| validator.0)
+            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))
+    }
+
+    async fn get_package_log_status(
+        &self,
+        log_id: &LogId,
+    ) -> Result<PackageLogStatus, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+
+        let checkpoint = schema::checkpoints::table
+            .select((
+                schema::checkpoints::log_length,
+                schema::checkpoints::timestamp,
+            ))
+            .order_by(schema::checkpoints::id.desc())
+            .first::<(i64, i64)>(&mut conn)
+            .await
+            .optional()?;
+
+        let log_id = schema::logs::table
+            .select(schema::logs::id)
+            .filter(schema::logs::log_id.eq(TextRef(log_id)))
+            .first::<i32>(&mut conn)
+            .await
+            .optional()?
+            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
+
+        let head = schema::records::table
+            .select(schema::records::record_id)
+            .filter(
+                schema::records::log_id
+                    .eq(log_id)
+                    .and(schema::records::status.eq(RecordStatus::Validated)),
+            )
+            .order_by(schema::records::registry_log_index.desc())
+            .first::<ParsedText<AnyHash>>(&mut conn)
+            .await
+            .optional()?;
+
+        let pending_records = schema::records::table
+            .filter(
+                schema::records::log_id
+                    .eq(log_id)
+                    .and(schema::records::status.eq(RecordStatus::Validated))
+                    .and(
+                        schema::records::registry_log_index
+                            .ge(checkpoint.map(|(log_length, _)| log_length).unwrap_or(0)),
+                    ),
+            )
+            .count()
+            .get_result::<i64>(&mut conn)
+            .await?;
+
+        Ok(PackageLogStatus {
+            head: head.map(|digest| digest.0.into()),
+            pending_records: pending_records.try_into().unwrap(),
+            checkpoint_timestamp: checkpoint.map(|(_, timestamp)| timestamp.try_into().unwrap()),
+        })
+    }
+
     async fn verify_package_record_signature(
         &self,
         log_id: &LogId,
@@ -950,7 +1291,11 @@ impl DataStore for PostgresDataStore {
         {
             Some(key) => key,
             None => match record.as_ref().entries.get(0) {
-                Some(PackageEntry::Init { key, .. }) => key,
+                // A new log's first record is signed by the key it
+                // initializes the log with
+                Some(PackageEntry::Init { key, .. }) if key.fingerprint() == *record.key_id() => {
+                    key
+                }
                 _ => return Err(DataStoreError::UnknownKey(record.key_id().clone())),
             },
         };
@@ -997,6 +1342,17 @@ impl DataStore for PostgresDataStore {
             }
         }
 
+        // verify package is not suppressed by the operator
+        if let Some(reason) = validator.package_suppression(&LogId::package_log_for(
+            operator_log_id.algorithm(),
+            package_name,
+        )) {
+            return Err(DataStoreError::PackageSuppressed {
+                name: package_name.clone(),
+                reason: reason.to_string(),
+            });
+        }
+
         // verify package name is unique in a case insensitive way
         match schema::logs::table
             .select(schema::logs::name)
@@ -1043,7 +1399,10 @@ impl DataStore for PostgresDataStore {
             ts_checkpoint.signature().clone(),
         )))?;
 
-        if !validator.key_has_permission_to_sign_checkpoints(ts_checkpoint.key_id()) {
+        if !validator.key_has_permission_to_sign_checkpoints(
+            ts_checkpoint.key_id(),
+            ts_checkpoint.as_ref().timestamp,
+        ) {
             return Err(DataStoreError::KeyUnauthorized(
                 ts_checkpoint.key_id().clone(),
             ));
@@ -1052,6 +1411,393 @@ impl DataStore for PostgresDataStore {
         Ok(())
     }
 
+    async fn store_package_dependencies(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        version: &Version,
+        dependencies: &[PackageDependency],
+    ) -> Result<(), DataStoreError> {
+        let mut conn = self.pool.get().await?;
+        let id = schema::records::table
+            .inner_join(schema::logs::table)
+            .select(schema::records::id)
+            .filter(
+                schema::logs::log_id
+                    .eq(TextRef(log_id))
+                    .and(schema::records::record_id.eq(TextRef(record_id))),
+            )
+            .first::<i32>(conn.as_mut())
+            .await
+            .optional()?
+            .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
+
+        let dependency_log_ids = dependencies
+            .iter()
+            .map(|d| LogId::package_log_for(log_id.algorithm(), &d.name))
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(schema::dependencies::table)
+            .values(
+                dependencies
+                    .iter()
+                    .enumerate()
+                    .map(|(i, d)| NewDependency {
+                        record_id: id,
+                        version: TextRef(version),
+                        log_id: TextRef(&dependency_log_ids[i]),
+                        name: d.name.as_ref(),
+                        import: &d.import,
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            // Dependencies stored again, such as when a record is resubmitted,
+            // are not duplicated
+            .on_conflict_do_nothing()
+            .execute(conn.as_mut())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_package_dependencies(
+        &self,
+        log_id: &LogId,
+        version: &Version,
+    ) -> Result<Vec<PackageDependency>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+        let log_id = schema::logs::table
+            .select(schema::logs::id)
+            .filter(schema::logs::log_id.eq(TextRef(log_id)))
+            .first::<i32>(conn.as_mut())
+            .await
+            .optional()?
+            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
+
+        Ok(schema::dependencies::table
+            .inner_join(schema::records::table)
+            .select((schema::dependencies::name, schema::dependencies::import))
+            .filter(
+                schema::records::log_id
+                    .eq(log_id)
+                    .and(schema::records::status.eq(RecordStatus::Validated))
+                    .and(schema::dependencies::version.eq(TextRef(version))),
+            )
+            .order_by(schema::dependencies::id.asc())
+            .load::<(String, String)>(conn.as_mut())
+            .await?
+            .into_iter()
+            .filter_map(|(name, import)| {
+                Some(PackageDependency {
+                    name: name.parse().ok()?,
+                    import,
+                })
+            })
+            .collect())
+    }
+
+    async fn get_package_dependents(
+        &self,
+        log_id: &LogId,
+    ) -> Result<Vec<PackageDependent>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+
+        Ok(schema::dependencies::table
+            .inner_join(schema::records::table.inner_join(schema::logs::table))
+            .select((schema::logs::name, schema::dependencies::version))
+            .distinct()
+            .filter(
+                schema::records::status
+                    .eq(RecordStatus::Validated)
+                    .and(schema::dependencies::log_id.eq(TextRef(log_id))),
+            )
+            .load::<(Option<String>, ParsedText<Version>)>(conn.as_mut())
+            .await?
+            .into_iter()
+            .filter_map(|(name, version)| {
+                Some(PackageDependent {
+                    name: PackageName::new(name?).ok()?,
+                    version: version.0,
+                })
+            })
+            .collect())
+    }
+
+    async fn get_content_releases(
+        &self,
+        digest: &AnyHash,
+    ) -> Result<Vec<(PackageName, Version)>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+        let records = schema::contents::table
+            .inner_join(schema::records::table.inner_join(schema::logs::table))
+            .select((
+                schema::logs::name,
+                schema::records::record_id,
+                schema::records::content,
+            ))
+            .filter(
+                schema::contents::digest
+                    .eq(TextRef(digest))
+                    .and(schema::records::status.eq(RecordStatus::Validated)),
+            )
+            .load::<(Option<String>, ParsedText<AnyHash>, Vec<u8>)>(conn.as_mut())
+            .await?;
+
+        let mut releases = Vec::new();
+        for (name, record_id, content) in records {
+            let Some(name) = name.and_then(|name| PackageName::new(name).ok()) else {
+                continue;
+            };
+
+            let record =
+                ProtoEnvelope::<package::PackageRecord>::from_protobuf(&content).map_err(|e| {
+                    DataStoreError::InvalidRecordContents {
+                        record_id: record_id.0.into(),
+                        message: e.to_string(),
+                    }
+                })?;
+
+            for entry in &record.as_ref().entries {
+                match entry {
+                    PackageEntry::Release { version, .. }
+                        if entry.contents().any(|content| content == digest) =>
+                    {
+                        releases.push((name.clone(), version.clone()));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(releases)
+    }
+
+    async fn list_packages(
+        &self,
+        offset: u64,
+        limit: u16,
+    ) -> Result<Vec<PackageSummary>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+        let validated = schema::records::table.filter(
+            schema::records::log_id
+                .eq(schema::logs::id)
+                .and(schema::records::status.eq(RecordStatus::Validated)),
+        );
+
+        let logs = schema::logs::table
+            .select((
+                schema::logs::id,
+                schema::logs::log_id,
+                schema::logs::name,
+                schema::logs::validator,
+            ))
+            .filter(
+                schema::logs::name
+                    .is_not_null()
+                    .and(diesel::dsl::exists(validated)),
+            )
+            .order_by(schema::logs::id)
+            .offset(offset.try_into().unwrap_or(i64::MAX))
+            .limit(limit.into())
+            .load::<(
+                i32,
+                ParsedText<AnyHash>,
+                Option<String>,
+                Json<package::LogState>,
+            )>(conn.as_mut())
+            .await?;
+
+        let mut packages = Vec::with_capacity(logs.len());
+        for (id, log_id, name, validator) in logs {
+            let Some(name) = name.and_then(|name| PackageName::new(name).ok()) else {
+                continue;
+            };
+
+            let record_count = schema::records::table
+                .filter(
+                    schema::records::log_id
+                        .eq(id)
+                        .and(schema::records::status.eq(RecordStatus::Validated)),
+                )
+                .count()
+                .get_result::<i64>(conn.as_mut())
+                .await?;
+
+            packages.push(package_summary(
+                name,
+                log_id.0.into(),
+                record_count as u64,
+                &validator.0,
+            ));
+        }
+
+        Ok(packages)
+    }
+
+    async fn get_referenced_content(&self) -> Result<IndexSet<AnyHash>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+        Ok(schema::contents::table
+            .inner_join(schema::records::table)
+            .select(schema::contents::digest)
+            .filter(schema::records::status.ne(RecordStatus::Rejected))
+            .distinct()
+            .load::<ParsedText<AnyHash>>(conn.as_mut())
+            .await?
+            .into_iter()
+            .map(|digest| digest.0)
+            .collect())
+    }
+
+    async fn record_download_counts(
+        &self,
+        day: u64,
+        counts: &[DownloadCount],
+    ) -> Result<(), DataStoreError> {
+        if counts.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = self.pool.get().await?;
+        diesel::insert_into(schema::download_counts::table)
+            .values(
+                counts
+                    .iter()
+                    .map(|c| NewDownloadCount {
+                        name: c.name.as_ref(),
+                        version: TextRef(&c.version),
+                        day: day.try_into().unwrap(),
+                        count: c.count.try_into().unwrap_or(i64::MAX),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .on_conflict((
+                schema::download_counts::name,
+                schema::download_counts::version,
+                schema::download_counts::day,
+            ))
+            .do_update()
+            .set(
+                schema::download_counts::count
+                    .eq(schema::download_counts::count + excluded(schema::download_counts::count)),
+            )
+            .execute(conn.as_mut())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_download_counts(
+        &self,
+        name: &PackageName,
+        today: u64,
+    ) -> Result<IndexMap<Version, DownloadCounts>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+        let rows = schema::download_counts::table
+            .select((
+                schema::download_counts::version,
+                schema::download_counts::day,
+                schema::download_counts::count,
+            ))
+            .filter(schema::download_counts::name.eq(name.as_ref()))
+            .order_by(schema::download_counts::id)
+            .load::<(ParsedText<Version>, i64, i64)>(conn.as_mut())
+            .await?;
+
+        let mut counts = IndexMap::<Version, DownloadCounts>::new();
+        for (version, day, count) in rows {
+            add_daily_downloads(
+                counts.entry(version.0).or_default(),
+                day.try_into().unwrap_or_default(),
+                today,
+                count.try_into().unwrap_or_default(),
+            );
+        }
+
+        Ok(counts)
+    }
+
+    async fn store_audit_entry(&self, entry: &AuditEntry) -> Result<(), DataStoreError> {
+        let mut conn = self.pool.get().await?;
+        diesel::insert_into(schema::audit_entries::table)
+            .values(NewAuditEntry {
+                log_id: entry.log_id.as_ref().map(TextRef),
+                key_id: entry.key_id.as_ref().map(TextRef),
+                entry: &Json(entry.clone()),
+            })
+            .execute(conn.as_mut())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_audit_entries(
+        &self,
+        filter: &AuditFilter,
+        limit: usize,
+    ) -> Result<Vec<AuditEntry>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+        let mut query = schema::audit_entries::table
+            .into_boxed()
+            .select(schema::audit_entries::entry)
+            .order_by(schema::audit_entries::id.desc())
+            .limit(limit as i64);
+
+        if let Some(log_id) = &filter.log_id {
+            query = query.filter(schema::audit_entries::log_id.eq(TextRef(log_id)));
+        }
+
+        if let Some(key_id) = &filter.key_id {
+            query = query.filter(schema::audit_entries::key_id.eq(TextRef(key_id)));
+        }
+
+        let mut entries = query
+            .load::<Json<AuditEntry>>(conn.as_mut())
+            .await?
+            .into_iter()
+            .map(|entry| entry.0)
+            .collect::<Vec<_>>();
+        entries.reverse();
+        Ok(entries)
+    }
+
+    async fn prune_rejected(&self, before: DateTime<Utc>) -> Result<usize, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+        conn.transaction::<_, DataStoreError, _>(|conn| {
+            async move {
+                // A rejected record is no longer updated, so the time it was
+                // last updated is the time it was rejected
+                let ids = schema::records::table
+                    .select(schema::records::id)
+                    .filter(
+                        schema::records::status
+                            .eq(RecordStatus::Rejected)
+                            .and(schema::records::updated_at.lt(before)),
+                    )
+                    .load::<i32>(conn)
+                    .await?;
+
+                diesel::delete(schema::contents::table)
+                    .filter(schema::contents::record_id.eq_any(&ids))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(schema::dependencies::table)
+                    .filter(schema::dependencies::record_id.eq_any(&ids))
+                    .execute(conn)
+                    .await?;
+                Ok(diesel::delete(schema::records::table)
+                    .filter(schema::records::id.eq_any(&ids))
+                    .execute(conn)
+                    .await?)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    async fn compact_published(&self, _keep_last: usize) -> Result<usize, DataStoreError> {
+        Err(DataStoreError::Unsupported("compacting published records"))
+    }
+
     #[cfg(feature = "debug")]
     async fn debug_list_package_names(&self) -> anyhow::Result<Vec<PackageName>> {
         let mut conn = self.pool.get().await?;
@@ -1,4 +1,6 @@
-use super::schema::{checkpoints, contents, logs, records};
+use super::schema::{
+    audit_entries, checkpoints, contents, dependencies, download_counts, logs, records,
+};
 use chrono::{DateTime, Utc};
 use diesel::{
     deserialize::{self, FromSql},
@@ -10,11 +12,15 @@ use diesel::{
 use diesel_json::Json;
 use serde::Serialize;
 use std::{fmt::Display, io::Write, str::FromStr};
+use warg_api::v1::admin::AuditEntry;
 use warg_crypto::{
     hash::AnyHash,
     signing::{KeyID, Signature},
 };
-use warg_protocol::registry::{LogId, RecordId};
+use warg_protocol::{
+    registry::{LogId, RecordId},
+    Version,
+};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, diesel_derive_enum::DbEnum)]
 #[ExistingTypePath = "crate::datastore::postgres::schema::sql_types::RecordStatus"]
@@ -87,8 +93,11 @@ pub struct NewCheckpoint<'a> {
     pub key_id: TextRef<'a, KeyID>,
     pub signature: TextRef<'a, Signature>,
     pub timestamp: i64,
+    pub expires: Option<i64>,
 }
 
+// Every column is queried, though not every column is read
+#[allow(dead_code)]
 #[derive(Queryable)]
 #[diesel(table_name = checkpoints)]
 pub struct CheckpointData {
@@ -102,6 +111,7 @@ pub struct CheckpointData {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub timestamp: i64,
+    pub expires: Option<i64>,
 }
 
 /// Selects only the record content and status
@@ -121,3 +131,30 @@ pub struct NewContent<'a> {
     pub digest: TextRef<'a, AnyHash>,
     pub missing: bool,
 }
+
+#[derive(Insertable)]
+#[diesel(table_name = dependencies)]
+pub struct NewDependency<'a> {
+    pub record_id: i32,
+    pub version: TextRef<'a, Version>,
+    pub log_id: TextRef<'a, LogId>,
+    pub name: &'a str,
+    pub import: &'a str,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = download_counts)]
+pub struct NewDownloadCount<'a> {
+    pub name: &'a str,
+    pub version: TextRef<'a, Version>,
+    pub day: i64,
+    pub count: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = audit_entries)]
+pub struct NewAuditEntry<'a> {
+    pub log_id: Option<TextRef<'a, LogId>>,
+    pub key_id: Option<TextRef<'a, KeyID>>,
+    pub entry: &'a Json<AuditEntry>,
+}
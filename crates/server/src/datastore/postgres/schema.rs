@@ -6,6 +6,16 @@ pub mod sql_types {
     pub struct RecordStatus;
 }
 
+diesel::table! {
+    audit_entries (id) {
+        id -> Int8,
+        log_id -> Nullable<Text>,
+        key_id -> Nullable<Text>,
+        entry -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     checkpoints (id) {
         id -> Int4,
@@ -18,6 +28,7 @@ diesel::table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         timestamp -> Int8,
+        expires -> Nullable<Int8>,
     }
 }
 
@@ -32,6 +43,31 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    dependencies (id) {
+        id -> Int4,
+        record_id -> Int4,
+        version -> Text,
+        log_id -> Text,
+        name -> Text,
+        import -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    download_counts (id) {
+        id -> Int4,
+        name -> Text,
+        version -> Text,
+        day -> Int8,
+        count -> Int8,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     logs (id) {
         id -> Int4,
@@ -61,6 +97,15 @@ diesel::table! {
 }
 
 diesel::joinable!(contents -> records (record_id));
+diesel::joinable!(dependencies -> records (record_id));
 diesel::joinable!(records -> logs (log_id));
 
-diesel::allow_tables_to_appear_in_same_query!(checkpoints, contents, logs, records,);
+diesel::allow_tables_to_appear_in_same_query!(
+    audit_entries,
+    checkpoints,
+    contents,
+    dependencies,
+    download_counts,
+    logs,
+    records,
+);
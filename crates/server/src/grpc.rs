@@ -0,0 +1,456 @@
+//! The gRPC service replicating the registry log to other registry servers.
+//!
+//! A mirror started with [`Config::with_mirror_grpc_endpoint`] ingests the
+//! log through this service instead of the JSON API.
+//!
+//! [`Config::with_mirror_grpc_endpoint`]: crate::Config::with_mirror_grpc_endpoint
+
+use crate::{content::ContentStorage, datastore::DataStoreError, services::CoreService};
+use anyhow::{Context, Result};
+use futures::Future;
+use proto::{
+    replication_server::{Replication, ReplicationServer},
+    ContentManifestEntry, GetContentManifestRequest, GetRecordsRequest, PublishedRecord,
+    RecordBatch, StreamLeavesRequest, StreamLeavesResponse,
+};
+use tokio::{net::TcpListener, sync::mpsc};
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tokio_util::sync::CancellationToken;
+use tonic::{Request, Response, Status};
+use url::Url;
+use warg_crypto::{hash::AnyHash, Decode};
+use warg_protocol::{
+    registry::{
+        Checkpoint, LogId, LogLeaf, RecordId, RegistryIndex, RegistryLen, TimestampedCheckpoint,
+    },
+    ProtoEnvelope, PublishedProtoEnvelope, SerdeEnvelope,
+};
+
+/// The types of the replication protocol.
+pub mod proto {
+    #![allow(clippy::all)]
+
+    // Generated by [`tonic-build`]
+    tonic::include_proto!("warg.replication");
+}
+
+/// The maximum number of leaves sent in a single response.
+const LEAVES_BATCH_SIZE: usize = 1000;
+
+/// The maximum number of records sent in a single batch.
+const RECORDS_BATCH_SIZE: u16 = 1000;
+
+/// The number of responses buffered for a streaming RPC.
+const STREAM_BUFFER: usize = 16;
+
+type ResponseStream<T> = ReceiverStream<Result<T, Status>>;
+
+impl From<&LogLeaf> for proto::LogLeaf {
+    fn from(leaf: &LogLeaf) -> Self {
+        Self {
+            log_id: leaf.log_id.to_string(),
+            record_id: leaf.record_id.to_string(),
+        }
+    }
+}
+
+impl TryFrom<proto::LogLeaf> for LogLeaf {
+    type Error = anyhow::Error;
+
+    fn try_from(leaf: proto::LogLeaf) -> Result<Self> {
+        Ok(Self {
+            log_id: parse_hash(&leaf.log_id)?.into(),
+            record_id: parse_hash(&leaf.record_id)?.into(),
+        })
+    }
+}
+
+impl From<&SerdeEnvelope<TimestampedCheckpoint>> for proto::Checkpoint {
+    fn from(envelope: &SerdeEnvelope<TimestampedCheckpoint>) -> Self {
+        let ts_checkpoint = envelope.as_ref();
+        Self {
+            log_length: ts_checkpoint.checkpoint.log_length as u64,
+            log_root: ts_checkpoint.checkpoint.log_root.to_string(),
+            map_root: ts_checkpoint.checkpoint.map_root.to_string(),
+            timestamp: ts_checkpoint.timestamp,
+            expires: ts_checkpoint.expires,
+            key_id: envelope.key_id().to_string(),
+            signature: envelope.signature().to_string(),
+        }
+    }
+}
+
+impl TryFrom<proto::Checkpoint> for SerdeEnvelope<TimestampedCheckpoint> {
+    type Error = anyhow::Error;
+
+    fn try_from(checkpoint: proto::Checkpoint) -> Result<Self> {
+        Ok(Self::from_parts_unchecked(
+            TimestampedCheckpoint {
+                checkpoint: Checkpoint {
+                    log_root: parse_hash(&checkpoint.log_root)?,
+                    log_length: checkpoint.log_length as RegistryLen,
+                    map_root: parse_hash(&checkpoint.map_root)?,
+                },
+                timestamp: checkpoint.timestamp,
+                expires: checkpoint.expires,
+            },
+            checkpoint.key_id.into(),
+            checkpoint
+                .signature
+                .parse()
+                .context("invalid checkpoint signature")?,
+        ))
+    }
+}
+
+impl<Contents> From<PublishedProtoEnvelope<Contents>> for PublishedRecord {
+    fn from(record: PublishedProtoEnvelope<Contents>) -> Self {
+        Self {
+            envelope: record.envelope.to_protobuf(),
+            registry_index: record.registry_index as u64,
+        }
+    }
+}
+
+impl<Contents: Decode> TryFrom<PublishedRecord> for PublishedProtoEnvelope<Contents> {
+    type Error = anyhow::Error;
+
+    fn try_from(record: PublishedRecord) -> Result<Self> {
+        Ok(Self {
+            envelope: ProtoEnvelope::from_protobuf(&record.envelope)
+                .context("invalid record envelope")?,
+            registry_index: record.registry_index as RegistryIndex,
+        })
+    }
+}
+
+pub(crate) fn parse_hash(hash: &str) -> Result<AnyHash> {
+    hash.parse()
+        .with_context(|| format!("invalid hash `{hash}`"))
+}
+
+fn invalid_argument(e: anyhow::Error) -> Status {
+    Status::invalid_argument(format!("{e:#}"))
+}
+
+fn data_store_status(e: DataStoreError) -> Status {
+    match e {
+        DataStoreError::NoCheckpoints => Status::unavailable(e.to_string()),
+        DataStoreError::LogNotFound(_) => Status::not_found(e.to_string()),
+        e => {
+            tracing::error!("unexpected data store error: {e}");
+            Status::internal("an error occurred while processing the request")
+        }
+    }
+}
+
+/// Serves the registry log to replicating registry servers.
+#[derive(Clone)]
+pub struct ReplicationService {
+    core: CoreService,
+    content_store: ContentStorage,
+    content_base_url: Url,
+    shutdown: CancellationToken,
+}
+
+impl ReplicationService {
+    /// Creates a new replication service.
+    ///
+    /// Content is advertised as downloadable from the given content base URL.
+    pub fn new(core: CoreService, content_store: ContentStorage, content_base_url: Url) -> Self {
+        Self {
+            core,
+            content_store,
+            content_base_url,
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    /// Serves the service on the given listener until the given token is
+    /// cancelled.
+    ///
+    /// Open leaf streams are ended on shutdown.
+    pub async fn serve(
+        mut self,
+        listener: TcpListener,
+        shutdown: CancellationToken,
+    ) -> Result<(), tonic::transport::Error> {
+        self.shutdown = shutdown.clone();
+        tonic::transport::Server::builder()
+            .add_service(ReplicationServer::new(self))
+            .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async move {
+                shutdown.cancelled().await
+            })
+            .await
+    }
+
+    /// Sends the leaves of each checkpoint after the given log length until
+    /// the stream is closed or the service shuts down.
+    async fn push_leaves(
+        &self,
+        mut log_length: RegistryLen,
+        tx: &mpsc::Sender<Result<StreamLeavesResponse, Status>>,
+    ) -> Result<(), Status> {
+        // Subscribe first so that no checkpoint stored from here on is missed
+        let mut checkpoints = self.core.subscribe_checkpoints();
+        loop {
+            match self.core.store().get_latest_checkpoint().await {
+                Ok(ts_checkpoint) => {
+                    let end = ts_checkpoint.as_ref().checkpoint.log_length;
+                    if end < log_length {
+                        return Err(Status::out_of_range(format!(
+                            "log length {log_length} is beyond the latest checkpoint's log length {end}"
+                        )));
+                    }
+
+                    if end > log_length {
+                        self.send_leaves(log_length, &ts_checkpoint, tx).await?;
+                        log_length = end;
+                    }
+                }
+                Err(DataStoreError::NoCheckpoints) => {}
+                Err(e) => return Err(data_store_status(e)),
+            }
+
+            tokio::select! {
+                _ = self.shutdown.cancelled() => return Ok(()),
+                _ = tx.closed() => return Ok(()),
+                changed = checkpoints.changed() => if changed.is_err() {
+                    return Ok(());
+                },
+            }
+        }
+    }
+
+    /// Sends the leaves from the given registry index up to the log length of
+    /// the given checkpoint, followed by the checkpoint.
+    async fn send_leaves(
+        &self,
+        start: RegistryIndex,
+        ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
+        tx: &mpsc::Sender<Result<StreamLeavesResponse, Status>>,
+    ) -> Result<(), Status> {
+        let end = ts_checkpoint.as_ref().checkpoint.log_length;
+        let mut index = start;
+        while index < end {
+            let leaves = self
+                .core
+                .store()
+                .get_log_leafs_starting_with_registry_index(index, LEAVES_BATCH_SIZE)
+                .await
+                .map_err(data_store_status)?
+                .iter()
+                .take(end - index)
+                .map(|(_, leaf)| leaf.into())
+                .collect::<Vec<proto::LogLeaf>>();
+            if leaves.is_empty() {
+                tracing::error!("the log leaf at registry index {index} was not found");
+                return Err(Status::internal(
+                    "an error occurred while processing the request",
+                ));
+            }
+
+            index += leaves.len();
+            let response = StreamLeavesResponse {
+                leaves,
+                checkpoint: (index == end).then(|| ts_checkpoint.into()),
+            };
+            if tx.send(Ok(response)).await.is_err() {
+                return Ok(());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends the records of the given logs published at the given log length
+    /// in batches.
+    async fn send_records(
+        &self,
+        log_length: RegistryLen,
+        logs: Vec<(LogId, Option<RecordId>)>,
+        tx: &mpsc::Sender<Result<RecordBatch, Status>>,
+    ) -> Result<(), Status> {
+        let store = self.core.store();
+        let operator_log_id = self.core.operator_log_id();
+        for (log_id, mut since) in logs {
+            let name = if log_id == operator_log_id {
+                None
+            } else {
+                let name = store
+                    .get_package_names(std::slice::from_ref(&log_id))
+                    .await
+                    .map_err(data_store_status)?
+                    .swap_remove(&log_id)
+                    .flatten()
+                    .ok_or_else(|| Status::not_found(format!("log `{log_id}` was not found")))?;
+                Some(name.to_string())
+            };
+
+            loop {
+                let (records, next) = if name.is_none() {
+                    let page = store
+                        .get_operator_records(
+                            &log_id,
+                            log_length,
+                            since.as_ref(),
+                            RECORDS_BATCH_SIZE,
+                        )
+                        .await
+                        .map_err(data_store_status)?;
+                    (
+                        page.records.into_iter().map(Into::into).collect(),
+                        page.next,
+                    )
+                } else {
+                    let page = store
+                        .get_package_records(
+                            &log_id,
+                            log_length,
+                            since.as_ref(),
+                            RECORDS_BATCH_SIZE,
+                        )
+                        .await
+                        .map_err(data_store_status)?;
+                    (
+                        page.records.into_iter().map(Into::into).collect(),
+                        page.next,
+                    )
+                };
+
+                let batch = RecordBatch {
+                    log_id: log_id.to_string(),
+                    name: name.clone(),
+                    records,
+                };
+                if tx.send(Ok(batch)).await.is_err() {
+                    return Ok(());
+                }
+
+                match next {
+                    Some(next) => since = Some(next),
+                    None => break,
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends where each of the given content digests can be downloaded from.
+    async fn send_manifest(
+        &self,
+        digests: Vec<AnyHash>,
+        tx: &mpsc::Sender<Result<ContentManifestEntry, Status>>,
+    ) -> Result<(), Status> {
+        for digest in digests {
+            let present = self.content_store.is_present(&digest).await.map_err(|e| {
+                tracing::error!("failed to find content `{digest}`: {e}");
+                Status::internal("an error occurred while processing the request")
+            })?;
+
+            let entry = ContentManifestEntry {
+                url: present.then(|| self.content_url(&digest)),
+                digest: digest.to_string(),
+            };
+            if tx.send(Ok(entry)).await.is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn content_url(&self, digest: &AnyHash) -> String {
+        self.content_base_url
+            .join("content/")
+            .unwrap()
+            .join(&ContentStorage::file_name(digest))
+            .unwrap()
+            .to_string()
+    }
+
+    /// Runs a task sending the responses of a streaming RPC.
+    ///
+    /// An error returned by the task ends the stream.
+    fn stream<T, F>(task: impl FnOnce(mpsc::Sender<Result<T, Status>>) -> F) -> ResponseStream<T>
+    where
+        T: Send + 'static,
+        F: Future<Output = Result<(), Status>> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        let errors = tx.clone();
+        let task = task(tx);
+        tokio::spawn(async move {
+            if let Err(status) = task.await {
+                let _ = errors.send(Err(status)).await;
+            }
+        });
+        ReceiverStream::new(rx)
+    }
+}
+
+#[tonic::async_trait]
+impl Replication for ReplicationService {
+    type StreamLeavesStream = ResponseStream<StreamLeavesResponse>;
+    type GetRecordsStream = ResponseStream<RecordBatch>;
+    type GetContentManifestStream = ResponseStream<ContentManifestEntry>;
+
+    async fn stream_leaves(
+        &self,
+        request: Request<StreamLeavesRequest>,
+    ) -> Result<Response<Self::StreamLeavesStream>, Status> {
+        let log_length = request.into_inner().log_length as RegistryLen;
+        let service = self.clone();
+        Ok(Response::new(Self::stream(|tx| async move {
+            service.push_leaves(log_length, &tx).await
+        })))
+    }
+
+    async fn get_records(
+        &self,
+        request: Request<GetRecordsRequest>,
+    ) -> Result<Response<Self::GetRecordsStream>, Status> {
+        let request = request.into_inner();
+        let log_length = request.log_length as RegistryLen;
+        let logs = request
+            .logs
+            .into_iter()
+            .map(|cursor| {
+                Ok((
+                    parse_hash(&cursor.log_id)?.into(),
+                    cursor
+                        .since
+                        .as_deref()
+                        .map(parse_hash)
+                        .transpose()?
+                        .map(Into::into),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map_err(invalid_argument)?;
+
+        let service = self.clone();
+        Ok(Response::new(Self::stream(|tx| async move {
+            service.send_records(log_length, logs, &tx).await
+        })))
+    }
+
+    async fn get_content_manifest(
+        &self,
+        request: Request<GetContentManifestRequest>,
+    ) -> Result<Response<Self::GetContentManifestStream>, Status> {
+        let digests = request
+            .into_inner()
+            .digests
+            .iter()
+            .map(|digest| parse_hash(digest))
+            .collect::<Result<Vec<_>>>()
+            .map_err(invalid_argument)?;
+
+        let service = self.clone();
+        Ok(Response::new(Self::stream(|tx| async move {
+            service.send_manifest(digests, &tx).await
+        })))
+    }
+}
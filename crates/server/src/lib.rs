@@ -1,19 +1,50 @@
-use crate::{api::create_router, datastore::MemoryDataStore};
-use anyhow::{Context, Result};
+use crate::{
+    api::{
+        create_router,
+        v1::{idempotency::DEFAULT_IDEMPOTENCY_KEY_TTL, rate_limit::RateLimit},
+    },
+    audit::{AuditLog, DataStoreAuditLog},
+    content::{
+        ContentEncryption, ContentStorage, ContentStore, FileSystemContentStore, UploadSessions,
+    },
+    datastore::MemoryDataStore,
+    limits::{Limits, SharedLimits},
+};
+use anyhow::{bail, Context, Result};
 use axum::Router;
 use datastore::DataStore;
 use futures::Future;
-use policy::{content::ContentPolicy, record::RecordPolicy};
-use services::CoreService;
-use std::{fs, net::SocketAddr, path::PathBuf, pin::Pin, sync::Arc, time::Duration};
-use tokio::{net::TcpListener, task::JoinHandle};
+use policy::{
+    content::{ContentPolicy, ContentSourcePolicy},
+    record::{
+        RecordPolicy, ReleaseArtifactPolicy, TimestampSkewPolicy, DEFAULT_MAX_TIMESTAMP_SKEW,
+    },
+};
+use secrecy::SecretString;
+use services::{
+    CheckpointPolicy, ContentCollector, CoreService, CoreServiceConfig, DownloadCounter,
+    MirrorSync, Webhook, WebhookDispatcher, DEFAULT_CONTENT_GC_GRACE_PERIOD,
+    DEFAULT_MIRROR_SYNC_INTERVAL, DEFAULT_SUBMISSION_TIMEOUT, DEFAULT_WEBHOOK_MAX_ATTEMPTS,
+    DEFAULT_WEBHOOK_RETRY_DELAY,
+};
+use std::{
+    fs, net::SocketAddr, num::NonZeroU32, path::PathBuf, pin::Pin, sync::Arc, time::Duration,
+};
+use tokio::net::TcpListener;
 use url::Url;
-use warg_crypto::signing::PrivateKey;
+use warg_crypto::{hash::HashAlgorithm, signing::PrivateKey};
 use warg_protocol::operator;
 
 pub mod api;
 pub mod args;
+pub mod audit;
+pub mod config;
+pub mod content;
 pub mod datastore;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod limits;
+pub mod metrics;
 pub mod policy;
 pub mod services;
 
@@ -25,30 +56,82 @@ type ShutdownFut = Pin<Box<dyn Future<Output = ()> + Send + Sync>>;
 /// The server configuration.
 pub struct Config {
     operator_key: PrivateKey,
+    next_operator_key: Option<PrivateKey>,
     namespaces: Option<Vec<(String, operator::NamespaceState)>>,
+    hash_algorithm: Option<HashAlgorithm>,
     addr: Option<SocketAddr>,
     data_store: Option<Box<dyn DataStore>>,
     content_dir: PathBuf,
+    content_store: Option<Arc<dyn ContentStore>>,
     content_base_url: Option<Url>,
+    content_encryption: Option<ContentEncryption>,
+    content_source_hosts: Vec<String>,
+    max_content_source_size: Option<u64>,
     shutdown: Option<ShutdownFut>,
     checkpoint_interval: Option<Duration>,
+    max_checkpoint_leaves: Option<usize>,
+    min_checkpoint_leaves: Option<usize>,
+    max_checkpoint_interval: Option<Duration>,
+    checkpoint_validity: Option<Duration>,
+    submission_queue_depth: Option<usize>,
+    submission_timeout: Option<Duration>,
+    idempotency_key_ttl: Option<Duration>,
     content_policy: Option<Arc<dyn ContentPolicy>>,
     record_policy: Option<Arc<dyn RecordPolicy>>,
+    artifact_types: Vec<String>,
+    max_timestamp_skew: Option<Duration>,
+    limits: Limits,
+    audit_log: Option<Arc<dyn AuditLog>>,
+    data_store_audit_log: bool,
+    admin_token: Option<SecretString>,
+    download_flush_interval: Option<Duration>,
+    content_gc_grace_period: Option<Duration>,
+    content_gc_interval: Option<Duration>,
+    webhooks: Vec<Webhook>,
+    webhook_max_attempts: Option<u32>,
+    webhook_retry_delay: Option<Duration>,
+    webhook_dead_letter_file: Option<PathBuf>,
+    mirror: Option<Url>,
+    mirror_sync_interval: Option<Duration>,
+    #[cfg(feature = "grpc")]
+    mirror_grpc_endpoint: Option<Url>,
+    #[cfg(feature = "grpc")]
+    grpc_addr: Option<SocketAddr>,
 }
 
 impl std::fmt::Debug for Config {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Config")
+        let mut debug = f.debug_struct("Config");
+        debug
             .field("operator_key", &"<redacted>")
+            .field(
+                "next_operator_key",
+                &self.next_operator_key.as_ref().map(|_| "<redacted>"),
+            )
             .field("namespaces", &self.namespaces)
+            .field("hash_algorithm", &self.hash_algorithm)
             .field("addr", &self.addr)
             .field(
                 "data_store",
                 &self.data_store.as_ref().map(|_| "dyn DataStore"),
             )
             .field("content_dir", &self.content_dir)
+            .field(
+                "content_store",
+                &self.content_store.as_ref().map(|_| "dyn ContentStore"),
+            )
+            .field("content_encryption", &self.content_encryption)
+            .field("content_source_hosts", &self.content_source_hosts)
+            .field("max_content_source_size", &self.max_content_source_size)
             .field("shutdown", &self.shutdown.as_ref().map(|_| "dyn Future"))
             .field("checkpoint_interval", &self.checkpoint_interval)
+            .field("max_checkpoint_leaves", &self.max_checkpoint_leaves)
+            .field("min_checkpoint_leaves", &self.min_checkpoint_leaves)
+            .field("max_checkpoint_interval", &self.max_checkpoint_interval)
+            .field("checkpoint_validity", &self.checkpoint_validity)
+            .field("submission_queue_depth", &self.submission_queue_depth)
+            .field("submission_timeout", &self.submission_timeout)
+            .field("idempotency_key_ttl", &self.idempotency_key_ttl)
             .field(
                 "content_policy",
                 &self.content_policy.as_ref().map(|_| "dyn ContentPolicy"),
@@ -57,7 +140,34 @@ impl std::fmt::Debug for Config {
                 "record_policy",
                 &self.record_policy.as_ref().map(|_| "dyn RecordPolicy"),
             )
-            .finish()
+            .field("artifact_types", &self.artifact_types)
+            .field("max_timestamp_skew", &self.max_timestamp_skew)
+            .field("limits", &self.limits)
+            .field(
+                "audit_log",
+                &self.audit_log.as_ref().map(|_| "dyn AuditLog"),
+            )
+            .field("data_store_audit_log", &self.data_store_audit_log)
+            .field(
+                "admin_token",
+                &self.admin_token.as_ref().map(|_| "<redacted>"),
+            )
+            .field("download_flush_interval", &self.download_flush_interval)
+            .field("content_gc_grace_period", &self.content_gc_grace_period)
+            .field("content_gc_interval", &self.content_gc_interval)
+            .field("webhooks", &self.webhooks)
+            .field("webhook_max_attempts", &self.webhook_max_attempts)
+            .field("webhook_retry_delay", &self.webhook_retry_delay)
+            .field("webhook_dead_letter_file", &self.webhook_dead_letter_file)
+            .field("mirror", &self.mirror)
+            .field("mirror_sync_interval", &self.mirror_sync_interval);
+
+        #[cfg(feature = "grpc")]
+        debug
+            .field("mirror_grpc_endpoint", &self.mirror_grpc_endpoint)
+            .field("grpc_addr", &self.grpc_addr);
+
+        debug.finish()
     }
 }
 
@@ -70,15 +180,47 @@ impl Config {
     ) -> Self {
         Self {
             operator_key,
+            next_operator_key: None,
             namespaces,
+            hash_algorithm: None,
             addr: None,
             data_store: None,
             content_dir,
+            content_store: None,
             content_base_url: None,
+            content_encryption: None,
+            content_source_hosts: Vec::new(),
+            max_content_source_size: None,
             shutdown: None,
             checkpoint_interval: None,
+            max_checkpoint_leaves: None,
+            min_checkpoint_leaves: None,
+            max_checkpoint_interval: None,
+            checkpoint_validity: None,
+            submission_queue_depth: None,
+            submission_timeout: None,
+            idempotency_key_ttl: None,
             content_policy: None,
             record_policy: None,
+            artifact_types: Vec::new(),
+            max_timestamp_skew: Some(DEFAULT_MAX_TIMESTAMP_SKEW),
+            limits: Limits::default(),
+            audit_log: None,
+            data_store_audit_log: false,
+            admin_token: None,
+            download_flush_interval: None,
+            content_gc_grace_period: None,
+            content_gc_interval: None,
+            webhooks: Vec::new(),
+            webhook_max_attempts: None,
+            webhook_retry_delay: None,
+            webhook_dead_letter_file: None,
+            mirror: None,
+            mirror_sync_interval: None,
+            #[cfg(feature = "grpc")]
+            mirror_grpc_endpoint: None,
+            #[cfg(feature = "grpc")]
+            grpc_addr: None,
         }
     }
 
@@ -96,6 +238,45 @@ impl Config {
         self
     }
 
+    /// Specify the content store to use.
+    ///
+    /// If this is not specified, the server will store content files in the
+    /// content directory. Partial uploads are kept in the content directory
+    /// regardless of the content store.
+    pub fn with_content_store(mut self, store: impl ContentStore + 'static) -> Self {
+        self.content_store = Some(Arc::new(store));
+        self
+    }
+
+    /// Specify the encryption to use for content stored at rest.
+    ///
+    /// Encryption is only supported when content files are stored in the
+    /// content directory.
+    ///
+    /// When the server is initialized, existing plaintext content is
+    /// encrypted and content encrypted with a previous master key is
+    /// re-wrapped with the current master key.
+    pub fn with_content_encryption(mut self, encryption: ContentEncryption) -> Self {
+        self.content_encryption = Some(encryption);
+        self
+    }
+
+    /// Allows content sources specified when publishing a record to be
+    /// served from the given host.
+    ///
+    /// Content sources served from the content base URL are always allowed.
+    pub fn with_content_source_host(mut self, host: impl Into<String>) -> Self {
+        self.content_source_hosts.push(host.into());
+        self
+    }
+
+    /// Sets the maximum size of content declared by a content source
+    /// specified when publishing a record (defaults to 100 MiB).
+    pub fn with_max_content_source_size(mut self, max_size: u64) -> Self {
+        self.max_content_source_size = Some(max_size);
+        self
+    }
+
     /// Specify the data store to use.
     ///
     /// If this is not specified, the server will use an in-memory data store.
@@ -124,11 +305,148 @@ impl Config {
     }
 
     /// Sets the checkpoint interval to use for the server.
+    ///
+    /// This is the longest time a validated record waits to be included in
+    /// a checkpoint.
     pub fn with_checkpoint_interval(mut self, interval: Duration) -> Self {
         self.checkpoint_interval = Some(interval);
         self
     }
 
+    /// Sets the most records included in a single checkpoint.
+    ///
+    /// A checkpoint is issued as soon as this many records are validated,
+    /// without waiting for the checkpoint interval.
+    pub fn with_max_checkpoint_leaves(mut self, leaves: usize) -> Self {
+        self.max_checkpoint_leaves = Some(leaves);
+        self
+    }
+
+    /// Sets the fewest records that are checkpointed before the checkpoint
+    /// interval elapses.
+    ///
+    /// Once this many records are validated, a checkpoint is issued as soon
+    /// as no more submissions are queued.
+    pub fn with_min_checkpoint_leaves(mut self, leaves: usize) -> Self {
+        self.min_checkpoint_leaves = Some(leaves);
+        self
+    }
+
+    /// Sets the hash algorithm of log IDs, record IDs, and checkpoints.
+    ///
+    /// Defaults to SHA-256. The algorithm of an existing registry cannot be
+    /// changed; the server fails to start if it differs from the algorithm
+    /// the data store was initialized with.
+    pub fn with_hash_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = Some(algorithm);
+        self
+    }
+
+    /// Sets the maximum checkpoint interval declared to clients.
+    ///
+    /// The interval is recorded in the operator log so that clients can
+    /// detect a registry that has stopped issuing checkpoints. It is rounded
+    /// up to whole seconds and should be greater than the checkpoint interval.
+    pub fn with_max_checkpoint_interval(mut self, interval: Duration) -> Self {
+        self.max_checkpoint_interval = Some(interval);
+        self
+    }
+
+    /// Sets how long checkpoints are valid for.
+    ///
+    /// Checkpoints are signed with an expiry so that clients can refuse the
+    /// state of a registry that has stopped issuing checkpoints. The latest
+    /// checkpoint is re-signed before it expires even when no records are
+    /// published. By default, checkpoints do not expire.
+    pub fn with_checkpoint_validity(mut self, validity: Duration) -> Self {
+        self.checkpoint_validity = Some(validity);
+        self
+    }
+
+    /// Sets the key the operator key is being rotated to.
+    ///
+    /// The server signs checkpoints with the operator key until a rotation
+    /// to this key is published to the operator log, and with this key
+    /// thereafter.
+    pub fn with_next_operator_key(mut self, key: PrivateKey) -> Self {
+        self.next_operator_key = Some(key);
+        self
+    }
+
+    /// Sets the number of records that may be waiting to be processed.
+    ///
+    /// When the queue is full, submissions are rejected with a
+    /// `503 Service Unavailable` response until it drains.
+    pub fn with_submission_queue_depth(mut self, depth: usize) -> Self {
+        self.submission_queue_depth = Some(depth);
+        self
+    }
+
+    /// Sets how long a submission waits for room in a full queue before it
+    /// is rejected with a `503 Service Unavailable` response.
+    pub fn with_submission_timeout(mut self, timeout: Duration) -> Self {
+        self.submission_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how long the idempotency keys of record submissions are remembered.
+    ///
+    /// A submission repeated with the same key within this duration returns
+    /// the outcome of the original submission.
+    pub fn with_idempotency_key_ttl(mut self, ttl: Duration) -> Self {
+        self.idempotency_key_ttl = Some(ttl);
+        self
+    }
+
+    /// Limits the rate of record submissions per signing key.
+    ///
+    /// Each key may submit `burst` records at once, after which submissions
+    /// are allowed at `per_minute` records per minute. Excess submissions are
+    /// rejected with a `429 Too Many Requests` response.
+    pub fn with_submission_rate_limit(mut self, per_minute: NonZeroU32, burst: NonZeroU32) -> Self {
+        self.limits.submission_rate = Some(RateLimit { per_minute, burst });
+        self
+    }
+
+    /// Sets the maximum difference between the timestamp of a submitted
+    /// record and the server's clock (defaults to one hour).
+    ///
+    /// Records outside of the window are rejected; `None` disables the check.
+    pub fn with_max_timestamp_skew(mut self, max_skew: Option<Duration>) -> Self {
+        self.max_timestamp_skew = max_skew;
+        self
+    }
+
+    /// Sets the maximum size, in bytes, of a submitted record's serialized
+    /// envelope.
+    ///
+    /// The limit is advertised to clients through the registry metadata API;
+    /// records exceeding it are rejected. If not set, record size is unlimited.
+    pub fn with_max_record_size(mut self, max_size: u64) -> Self {
+        self.limits.record.max_record_size = Some(max_size);
+        self
+    }
+
+    /// Sets the maximum number of entries in a submitted record.
+    ///
+    /// The limit is advertised to clients through the registry metadata API;
+    /// records exceeding it are rejected. If not set, the number of entries
+    /// is unlimited.
+    pub fn with_max_record_entries(mut self, max_entries: u32) -> Self {
+        self.limits.record.max_record_entries = Some(max_entries);
+        self
+    }
+
+    /// Sets the record limits and submission rate limit, replacing any
+    /// limits set before.
+    ///
+    /// The limits may be changed while the server is running through
+    /// [`InitializedServer::limits`].
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     /// Sets the content policy to use for the server.
     pub fn with_content_policy(mut self, policy: impl ContentPolicy + 'static) -> Self {
         self.content_policy = Some(Arc::new(policy));
@@ -140,6 +458,135 @@ impl Config {
         self.record_policy = Some(Arc::new(policy));
         self
     }
+
+    /// Registers a type of release artifact that may be published.
+    ///
+    /// Releases may always have `component`, `wit`, and `debug` artifacts.
+    pub fn with_artifact_type(mut self, name: impl Into<String>) -> Self {
+        self.artifact_types.push(name.into());
+        self
+    }
+
+    /// Sets the audit log to append mutating operations to.
+    pub fn with_audit_log(mut self, audit_log: impl AuditLog + 'static) -> Self {
+        self.audit_log = Some(Arc::new(audit_log));
+        self.data_store_audit_log = false;
+        self
+    }
+
+    /// Appends mutating operations to an audit log kept in the server's data store.
+    pub fn with_data_store_audit_log(mut self) -> Self {
+        self.audit_log = None;
+        self.data_store_audit_log = true;
+        self
+    }
+
+    /// Sets the bearer token required by the administration API.
+    ///
+    /// If not set, the administration API is disabled.
+    pub fn with_admin_token(mut self, token: SecretString) -> Self {
+        self.admin_token = Some(token);
+        self
+    }
+
+    /// Enables collection of package download statistics.
+    ///
+    /// Downloads are counted in memory and flushed to the data store at the
+    /// given interval; counts not yet flushed are lost if the server stops.
+    pub fn with_download_stats(mut self, flush_interval: Duration) -> Self {
+        self.download_flush_interval = Some(flush_interval);
+        self
+    }
+
+    /// Sets how long unreferenced content is kept before it may be removed.
+    ///
+    /// Content is unreferenced when it is only referenced by rejected records
+    /// or by no record at all. Defaults to one day.
+    pub fn with_content_gc_grace_period(mut self, grace_period: Duration) -> Self {
+        self.content_gc_grace_period = Some(grace_period);
+        self
+    }
+
+    /// Removes unreferenced content at the given interval.
+    ///
+    /// If not set, unreferenced content is only removed on request through
+    /// the administration API.
+    pub fn with_content_gc_interval(mut self, interval: Duration) -> Self {
+        self.content_gc_interval = Some(interval);
+        self
+    }
+
+    /// Notifies the given webhook when package records are published.
+    ///
+    /// May be called multiple times to add more webhooks.
+    pub fn with_webhook(mut self, webhook: Webhook) -> Self {
+        self.webhooks.push(webhook);
+        self
+    }
+
+    /// Sets the number of times a webhook delivery is attempted before it
+    /// is considered failed.
+    ///
+    /// Defaults to five attempts.
+    pub fn with_webhook_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.webhook_max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Sets the delay before a failed webhook delivery is first retried.
+    ///
+    /// The delay doubles with each subsequent attempt. Defaults to one second.
+    pub fn with_webhook_retry_delay(mut self, delay: Duration) -> Self {
+        self.webhook_retry_delay = Some(delay);
+        self
+    }
+
+    /// Appends webhook deliveries that exhaust their attempts to the given
+    /// file, one JSON object per line.
+    pub fn with_webhook_dead_letter_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.webhook_dead_letter_file = Some(path.into());
+        self
+    }
+
+    /// Runs the server as a read-only mirror of the given upstream registry.
+    ///
+    /// The mirror replicates the upstream's log, checkpoints, and content in
+    /// the background and rejects submissions with a `403 Forbidden` response
+    /// pointing at the upstream. The namespaces and operator key of the
+    /// configuration are not used while mirroring.
+    pub fn with_mirror(mut self, upstream: Url) -> Self {
+        self.mirror = Some(upstream);
+        self
+    }
+
+    /// Sets the interval at which a mirror syncs with its upstream registry
+    /// (defaults to 10 seconds).
+    pub fn with_mirror_sync_interval(mut self, interval: Duration) -> Self {
+        self.mirror_sync_interval = Some(interval);
+        self
+    }
+
+    /// Replicates the upstream registry of a mirror over the gRPC
+    /// replication service at the given endpoint instead of its JSON API.
+    ///
+    /// The upstream pushes its checkpoints as they are issued; the mirror
+    /// sync interval is used as the delay before reconnecting after a
+    /// failure. Only used when running as a mirror.
+    #[cfg(feature = "grpc")]
+    pub fn with_mirror_grpc_endpoint(mut self, endpoint: Url) -> Self {
+        self.mirror_grpc_endpoint = Some(endpoint);
+        self
+    }
+
+    /// Serves the gRPC replication service on the given address.
+    ///
+    /// Other registry servers may mirror the registry through the service
+    /// with [`Config::with_mirror_grpc_endpoint`].
+    #[cfg(feature = "grpc")]
+    pub fn with_grpc_addr(mut self, addr: impl Into<SocketAddr>) -> Self {
+        self.grpc_addr = Some(addr.into());
+        self
+    }
 }
 
 /// Represents the warg registry server.
@@ -183,55 +630,230 @@ impl Server {
             config = self.config
         );
 
-        let store = self
+        let store: Arc<dyn DataStore> = self
             .config
             .data_store
-            .unwrap_or_else(|| Box::<MemoryDataStore>::default());
-        let (core, core_handle) = CoreService::start(
-            self.config.operator_key,
-            self.config.namespaces,
-            store,
+            .unwrap_or_else(|| Box::<MemoryDataStore>::default())
+            .into();
+        let audit_log = if self.config.data_store_audit_log {
+            Some(Arc::new(DataStoreAuditLog::new(store.clone())) as Arc<dyn AuditLog>)
+        } else {
+            self.config.audit_log
+        };
+        let downloads = self
+            .config
+            .download_flush_interval
+            .map(|interval| DownloadCounter::start(store.clone(), interval));
+        let gc_store = store.clone();
+        let hash_algorithm = self.config.hash_algorithm.unwrap_or(HashAlgorithm::Sha256);
+        let mut checkpoint_policy = CheckpointPolicy::new(
             self.config
                 .checkpoint_interval
                 .unwrap_or(DEFAULT_CHECKPOINT_INTERVAL),
-        )
-        .await?;
-
-        let temp_dir = self.config.content_dir.join("tmp");
-        fs::create_dir_all(&temp_dir).with_context(|| {
+        );
+        checkpoint_policy.max_leaves = self.config.max_checkpoint_leaves;
+        checkpoint_policy.min_leaves = self.config.min_checkpoint_leaves;
+        let core = if self.config.mirror.is_some() {
+            CoreService::start_mirror(self.config.operator_key, hash_algorithm, store).await?
+        } else {
+            let mut core_config = CoreServiceConfig::new(
+                self.config.operator_key,
+                hash_algorithm,
+                store,
+                checkpoint_policy,
+            );
+            core_config.next_operator_key = self.config.next_operator_key;
+            core_config.namespaces = self.config.namespaces;
+            core_config.max_checkpoint_interval = self.config.max_checkpoint_interval;
+            core_config.checkpoint_validity = self.config.checkpoint_validity;
+            core_config.audit_log = audit_log.clone();
+            if let Some(depth) = self.config.submission_queue_depth {
+                core_config.submission_queue_depth = depth;
+            }
+
+            CoreService::start(core_config)
+                .await?
+                .with_submission_timeout(
+                    self.config
+                        .submission_timeout
+                        .unwrap_or(DEFAULT_SUBMISSION_TIMEOUT),
+                )
+        };
+
+        let uploads_dir = self.config.content_dir.join("uploads");
+        fs::create_dir_all(&uploads_dir).with_context(|| {
             format!(
-                "failed to create content temp directory `{path}`",
-                path = temp_dir.display()
+                "failed to create content uploads directory `{path}`",
+                path = uploads_dir.display()
             )
         })?;
 
-        let files_dir = self.config.content_dir.join("files");
-        fs::create_dir_all(&files_dir).with_context(|| {
-            format!(
-                "failed to create content files directory `{path}`",
-                path = files_dir.display()
-            )
-        })?;
+        let uploads = Arc::new(UploadSessions::new(uploads_dir.clone()));
+        let expired = uploads
+            .remove_expired()
+            .await
+            .context("failed to remove expired content uploads")?;
+        if expired > 0 {
+            tracing::info!("removed {expired} expired partial content upload(s)");
+        }
+
+        let content_store = match self.config.content_store {
+            Some(store) => {
+                if self.config.content_encryption.is_some() {
+                    bail!("content encryption is only supported when storing content in the content directory");
+                }
+
+                store
+            }
+            None => {
+                let files_dir = self.config.content_dir.join("files");
+                fs::create_dir_all(&files_dir).with_context(|| {
+                    format!(
+                        "failed to create content files directory `{path}`",
+                        path = files_dir.display()
+                    )
+                })?;
+
+                let mut store = FileSystemContentStore::new(files_dir);
+                if let Some(encryption) = self.config.content_encryption {
+                    store = store.with_encryption(Arc::new(encryption));
+                }
+
+                let migrated = store
+                    .migrate()
+                    .await
+                    .context("failed to migrate content to the current encryption")?;
+                if migrated > 0 {
+                    tracing::info!("migrated {migrated} content file(s) to the current encryption");
+                }
+
+                Arc::new(store)
+            }
+        };
+
+        // Content being verified is written next to partial uploads
+        let content_store = ContentStorage::new(content_store, uploads_dir);
+
+        let content_collector = ContentCollector::new(
+            gc_store,
+            content_store.clone(),
+            self.config
+                .content_gc_grace_period
+                .unwrap_or(DEFAULT_CONTENT_GC_GRACE_PERIOD),
+        );
+        if let Some(interval) = self.config.content_gc_interval {
+            content_collector.start(interval);
+        }
+
+        let webhooks = if self.config.webhooks.is_empty() {
+            None
+        } else {
+            let webhooks = WebhookDispatcher::new(
+                self.config.webhooks,
+                self.config
+                    .webhook_max_attempts
+                    .unwrap_or(DEFAULT_WEBHOOK_MAX_ATTEMPTS),
+                self.config
+                    .webhook_retry_delay
+                    .unwrap_or(DEFAULT_WEBHOOK_RETRY_DELAY),
+                self.config.webhook_dead_letter_file,
+            );
+            webhooks.start(core.clone());
+            Some(webhooks)
+        };
+
+        let mirror = match self.config.mirror {
+            Some(upstream) => {
+                tracing::info!("mirroring upstream registry `{upstream}`");
+                let mirror = MirrorSync::new(upstream, core.clone(), content_store.clone());
+                let interval = self
+                    .config
+                    .mirror_sync_interval
+                    .unwrap_or(DEFAULT_MIRROR_SYNC_INTERVAL);
+
+                #[cfg(feature = "grpc")]
+                if let Some(endpoint) = self.config.mirror_grpc_endpoint {
+                    tracing::info!("replicating over gRPC from `{endpoint}`");
+                    mirror.start_streaming(endpoint, interval)?;
+                } else {
+                    mirror.start(interval);
+                }
+
+                #[cfg(not(feature = "grpc"))]
+                mirror.start(interval);
+
+                Some(mirror)
+            }
+            None => None,
+        };
 
         let content_base_url = self
             .config
             .content_base_url
             .unwrap_or_else(|| Url::parse(&format!("http://{addr}")).unwrap());
 
-        let router = create_router(
-            content_base_url,
-            core,
-            temp_dir,
-            files_dir,
-            self.config.content_policy,
-            self.config.record_policy,
+        let mut content_source_policy = ContentSourcePolicy::new(&content_base_url);
+        for host in self.config.content_source_hosts {
+            content_source_policy = content_source_policy.with_allowed_host(host);
+        }
+        if let Some(max_size) = self.config.max_content_source_size {
+            content_source_policy = content_source_policy.with_max_size(max_size);
+        }
+
+        let artifact_policy = self.config.artifact_types.into_iter().fold(
+            ReleaseArtifactPolicy::new(),
+            ReleaseArtifactPolicy::with_type,
         );
 
+        #[cfg(feature = "grpc")]
+        let grpc = match self.config.grpc_addr {
+            Some(addr) => {
+                tracing::debug!("binding gRPC replication service to address `{addr}`");
+                let listener = TcpListener::bind(addr)
+                    .await
+                    .with_context(|| format!("failed to bind to address `{addr}`"))?;
+                let service = grpc::ReplicationService::new(
+                    core.clone(),
+                    content_store.clone(),
+                    content_base_url.clone(),
+                );
+                Some((listener, service))
+            }
+            None => None,
+        };
+
+        let limits = SharedLimits::new(self.config.limits);
+        let router = create_router(api::Config {
+            content_base_url,
+            core: core.clone(),
+            uploads,
+            content_store,
+            content_policy: self.config.content_policy,
+            content_source_policy,
+            record_policy: self.config.record_policy,
+            artifact_policy,
+            timestamp_policy: self.config.max_timestamp_skew.map(TimestampSkewPolicy::new),
+            limits: limits.clone(),
+            audit_log,
+            admin_token: self.config.admin_token,
+            idempotency_key_ttl: self
+                .config
+                .idempotency_key_ttl
+                .unwrap_or(DEFAULT_IDEMPOTENCY_KEY_TTL),
+            downloads,
+            content_collector,
+            webhooks,
+            mirror,
+        });
+
         Ok(InitializedServer {
             listener,
             router,
-            core_handle,
+            core,
+            limits,
             shutdown: self.config.shutdown,
+            #[cfg(feature = "grpc")]
+            grpc,
         })
     }
 }
@@ -240,8 +862,11 @@ impl Server {
 pub struct InitializedServer {
     listener: TcpListener,
     router: Router,
-    core_handle: JoinHandle<()>,
+    core: CoreService,
+    limits: SharedLimits,
     shutdown: Option<ShutdownFut>,
+    #[cfg(feature = "grpc")]
+    grpc: Option<(TcpListener, grpc::ReplicationService)>,
 }
 
 impl InitializedServer {
@@ -251,15 +876,49 @@ impl InitializedServer {
         self.listener.local_addr()
     }
 
+    /// Returns the listening address of the gRPC replication service, if it
+    /// is served.
+    #[cfg(feature = "grpc")]
+    pub fn grpc_addr(&self) -> std::io::Result<Option<SocketAddr>> {
+        self.grpc
+            .as_ref()
+            .map(|(listener, _)| listener.local_addr())
+            .transpose()
+    }
+
+    /// Gets a handle to the record limits and submission rate limit in
+    /// effect, which may be used to change them while the server is running.
+    pub fn limits(&self) -> SharedLimits {
+        self.limits.clone()
+    }
+
     /// Serves the server's services. On server shutdown, awaits completion of
     /// background task(s) before returning.
     pub async fn serve(self) -> Result<()> {
         let addr = self.local_addr()?;
 
-        let server = axum::serve::serve(self.listener, self.router.into_make_service());
+        let server = axum::serve::serve(
+            self.listener,
+            self.router
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         tracing::info!("listening on {addr}");
 
+        #[cfg(feature = "grpc")]
+        let grpc_shutdown = tokio_util::sync::CancellationToken::new();
+        #[cfg(feature = "grpc")]
+        let grpc = match self.grpc {
+            Some((listener, service)) => {
+                tracing::info!(
+                    "serving gRPC replication on {addr}",
+                    addr = listener.local_addr()?
+                );
+                Some(tokio::spawn(service.serve(listener, grpc_shutdown.clone())))
+            }
+            None => None,
+        };
+
         if let Some(shutdown) = self.shutdown {
             tracing::debug!("server is running with a shutdown signal");
             server.with_graceful_shutdown(shutdown).await?;
@@ -268,8 +927,15 @@ impl InitializedServer {
             server.await?;
         }
 
+        #[cfg(feature = "grpc")]
+        if let Some(grpc) = grpc {
+            tracing::info!("waiting for gRPC replication service to stop");
+            grpc_shutdown.cancel();
+            grpc.await??;
+        }
+
         tracing::info!("waiting for core service to stop");
-        self.core_handle.await?;
+        self.core.shutdown().await?;
 
         tracing::info!("server shutdown complete");
         Ok(())
@@ -0,0 +1,40 @@
+//! Limits on record submissions that may be changed while the server runs.
+
+use crate::api::v1::rate_limit::RateLimit;
+use std::sync::{Arc, RwLock};
+use warg_api::v1::registry::RecordLimits;
+
+/// The limits on record submissions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The limits on the size of submitted records.
+    pub record: RecordLimits,
+    /// The rate at which each signing key may submit records.
+    ///
+    /// If `None`, submissions are not rate limited.
+    pub submission_rate: Option<RateLimit>,
+}
+
+/// A handle to the limits in effect, shared with the request handlers.
+///
+/// Replacing the limits affects the checks made after the replacement; the
+/// server keeps serving, so requests being handled are not interrupted.
+#[derive(Debug, Default, Clone)]
+pub struct SharedLimits(Arc<RwLock<Arc<Limits>>>);
+
+impl SharedLimits {
+    /// Creates a new handle to the given limits.
+    pub fn new(limits: Limits) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(limits))))
+    }
+
+    /// Gets the limits currently in effect.
+    pub fn load(&self) -> Arc<Limits> {
+        self.0.read().unwrap().clone()
+    }
+
+    /// Replaces the limits in effect for subsequent requests.
+    pub fn store(&self, limits: Limits) {
+        *self.0.write().unwrap() = Arc::new(limits);
+    }
+}
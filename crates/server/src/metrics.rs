@@ -0,0 +1,369 @@
+//! Metrics of the registry server in the Prometheus text exposition format.
+//!
+//! The services record events as they happen so that rendering the metrics
+//! never needs to inspect the data store or take the service's state lock.
+
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicI64, AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The content type of the rendered metrics.
+pub const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// The upper bounds, in seconds, of the submission duration histogram buckets.
+const SUBMISSION_DURATION_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// A histogram of durations with fixed buckets.
+struct Histogram {
+    buckets: &'static [f64],
+    // Non-cumulative counts per bucket, with a final bucket for `+Inf`
+    counts: Vec<AtomicU64>,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Self {
+        Self {
+            buckets,
+            counts: (0..=buckets.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let index = self
+            .buckets
+            .iter()
+            .position(|bound| secs <= *bound)
+            .unwrap_or(self.buckets.len());
+        self.counts[index].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in self.buckets.iter().zip(&self.counts) {
+            cumulative += count.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {cumulative}");
+        }
+
+        cumulative += self.counts[self.buckets.len()].load(Ordering::Relaxed);
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {cumulative}");
+        let _ = writeln!(
+            out,
+            "{name}_sum {sum}",
+            sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0
+        );
+        let _ = writeln!(out, "{name}_count {cumulative}");
+    }
+}
+
+/// The metrics of the registry server.
+pub struct Metrics {
+    packages: AtomicI64,
+    pending_records: AtomicI64,
+    validated_records: AtomicI64,
+    published_records: AtomicI64,
+    rejected_records: AtomicI64,
+    checkpoints_issued: AtomicU64,
+    checkpoint_batch: AtomicU64,
+    // Milliseconds since the Unix epoch at which the next checkpoint is due
+    next_checkpoint_at: AtomicU64,
+    content_bytes: AtomicU64,
+    served_bytes: AtomicU64,
+    submission_duration: Histogram,
+}
+
+// Gets the current time in milliseconds since the Unix epoch.
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            packages: Default::default(),
+            pending_records: Default::default(),
+            validated_records: Default::default(),
+            published_records: Default::default(),
+            rejected_records: Default::default(),
+            checkpoints_issued: Default::default(),
+            checkpoint_batch: Default::default(),
+            next_checkpoint_at: Default::default(),
+            content_bytes: Default::default(),
+            served_bytes: Default::default(),
+            submission_duration: Histogram::new(&SUBMISSION_DURATION_BUCKETS),
+        }
+    }
+}
+
+impl Metrics {
+    /// Sets the number of packages and package records restored from the
+    /// data store on startup.
+    ///
+    /// Validated records are those not yet included in a checkpoint.
+    pub fn restore(&self, packages: u64, validated: u64, published: u64) {
+        self.packages.store(packages as i64, Ordering::Relaxed);
+        self.validated_records
+            .store(validated as i64, Ordering::Relaxed);
+        self.published_records
+            .store(published as i64, Ordering::Relaxed);
+    }
+
+    /// Records that a package record was queued for processing.
+    pub fn record_submitted(&self) {
+        self.pending_records.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records that a queued package record was validated.
+    ///
+    /// `initial` is true if the record is the first record of its package log.
+    pub fn record_validated(&self, initial: bool) {
+        self.pending_records.fetch_sub(1, Ordering::Relaxed);
+        self.validated_records.fetch_add(1, Ordering::Relaxed);
+        if initial {
+            self.packages.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records that a queued package record was rejected.
+    pub fn record_rejected(&self) {
+        self.pending_records.fetch_sub(1, Ordering::Relaxed);
+        self.rejected_records.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records that a queued package record failed to be processed.
+    pub fn record_failed(&self) {
+        self.pending_records.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Records that a checkpoint was issued, publishing every validated record.
+    pub fn checkpoint_issued(&self) {
+        let validated = self.validated_records.swap(0, Ordering::Relaxed);
+        self.published_records
+            .fetch_add(validated, Ordering::Relaxed);
+        self.checkpoints_issued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Sets the number of records waiting to be included in the next
+    /// checkpoint.
+    pub fn set_checkpoint_batch(&self, records: usize) {
+        self.checkpoint_batch
+            .store(records as u64, Ordering::Relaxed);
+    }
+
+    /// Records that the next checkpoint is due after the given duration.
+    pub fn checkpoint_scheduled(&self, after: Duration) {
+        let at = now_millis().saturating_add(after.as_millis().min(u64::MAX as u128) as u64);
+        self.next_checkpoint_at.store(at, Ordering::Relaxed);
+    }
+
+    /// Records that content of the given size was stored.
+    pub fn content_stored(&self, bytes: u64) {
+        self.content_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Records that the given number of bytes of content were served.
+    pub fn content_served(&self, bytes: u64) {
+        self.served_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Records the time taken to handle a record submission.
+    pub fn observe_submission(&self, duration: Duration) {
+        self.submission_duration.observe(duration);
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(
+            out,
+            "# HELP warg_packages The number of packages in the registry."
+        );
+        let _ = writeln!(out, "# TYPE warg_packages gauge");
+        let _ = writeln!(
+            out,
+            "warg_packages {}",
+            self.packages.load(Ordering::Relaxed)
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP warg_records The number of package records by status."
+        );
+        let _ = writeln!(out, "# TYPE warg_records gauge");
+        for (status, count) in [
+            ("pending", &self.pending_records),
+            ("rejected", &self.rejected_records),
+            ("validated", &self.validated_records),
+            ("published", &self.published_records),
+        ] {
+            let _ = writeln!(
+                out,
+                "warg_records{{status=\"{status}\"}} {count}",
+                count = count.load(Ordering::Relaxed)
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP warg_checkpoints_issued_total The number of checkpoints issued."
+        );
+        let _ = writeln!(out, "# TYPE warg_checkpoints_issued_total counter");
+        let _ = writeln!(
+            out,
+            "warg_checkpoints_issued_total {}",
+            self.checkpoints_issued.load(Ordering::Relaxed)
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP warg_checkpoint_batch_records The number of records waiting to be included in the next checkpoint."
+        );
+        let _ = writeln!(out, "# TYPE warg_checkpoint_batch_records gauge");
+        let _ = writeln!(
+            out,
+            "warg_checkpoint_batch_records {}",
+            self.checkpoint_batch.load(Ordering::Relaxed)
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP warg_next_checkpoint_seconds The time until the next checkpoint is due."
+        );
+        let _ = writeln!(out, "# TYPE warg_next_checkpoint_seconds gauge");
+        let _ = writeln!(
+            out,
+            "warg_next_checkpoint_seconds {}",
+            self.next_checkpoint_at
+                .load(Ordering::Relaxed)
+                .saturating_sub(now_millis()) as f64
+                / 1000.0
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP warg_content_stored_bytes_total The number of bytes of content stored."
+        );
+        let _ = writeln!(out, "# TYPE warg_content_stored_bytes_total counter");
+        let _ = writeln!(
+            out,
+            "warg_content_stored_bytes_total {}",
+            self.content_bytes.load(Ordering::Relaxed)
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP warg_content_served_bytes_total The number of bytes of content served."
+        );
+        let _ = writeln!(out, "# TYPE warg_content_served_bytes_total counter");
+        let _ = writeln!(
+            out,
+            "warg_content_served_bytes_total {}",
+            self.served_bytes.load(Ordering::Relaxed)
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP warg_record_submission_duration_seconds The time taken to handle a package record submission."
+        );
+        let _ = writeln!(
+            out,
+            "# TYPE warg_record_submission_duration_seconds histogram"
+        );
+        self.submission_duration
+            .render(&mut out, "warg_record_submission_duration_seconds");
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_moves_records_through_states() {
+        let metrics = Metrics::default();
+        metrics.restore(1, 0, 2);
+
+        metrics.record_submitted();
+        metrics.record_submitted();
+        metrics.record_validated(true);
+        metrics.record_rejected();
+        let rendered = metrics.render();
+        assert!(rendered.contains("warg_packages 2\n"));
+        assert!(rendered.contains("warg_records{status=\"pending\"} 0\n"));
+        assert!(rendered.contains("warg_records{status=\"rejected\"} 1\n"));
+        assert!(rendered.contains("warg_records{status=\"validated\"} 1\n"));
+
+        metrics.checkpoint_issued();
+        let rendered = metrics.render();
+        assert!(rendered.contains("warg_records{status=\"validated\"} 0\n"));
+        assert!(rendered.contains("warg_records{status=\"published\"} 3\n"));
+        assert!(rendered.contains("warg_checkpoints_issued_total 1\n"));
+    }
+
+    #[test]
+    fn it_renders_the_checkpoint_batch() {
+        let metrics = Metrics::default();
+        let rendered = metrics.render();
+        assert!(rendered.contains("warg_checkpoint_batch_records 0\n"));
+        assert!(rendered.contains("warg_next_checkpoint_seconds 0\n"));
+
+        metrics.set_checkpoint_batch(3);
+        metrics.checkpoint_scheduled(Duration::from_secs(60));
+        let rendered = metrics.render();
+        assert!(rendered.contains("warg_checkpoint_batch_records 3\n"));
+        let next = rendered
+            .lines()
+            .find_map(|line| line.strip_prefix("warg_next_checkpoint_seconds "))
+            .unwrap()
+            .parse::<f64>()
+            .unwrap();
+        assert!(
+            next > 59.0 && next <= 60.0,
+            "unexpected time until checkpoint {next}"
+        );
+    }
+
+    #[test]
+    fn it_renders_cumulative_histogram_buckets() {
+        let metrics = Metrics::default();
+        metrics.observe_submission(Duration::from_millis(20));
+        metrics.observe_submission(Duration::from_secs(60));
+
+        let rendered = metrics.render();
+        let name = "warg_record_submission_duration_seconds";
+        assert!(rendered.contains(&format!("{name}_bucket{{le=\"0.01\"}} 0\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{le=\"0.025\"}} 1\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{le=\"10\"}} 1\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{le=\"+Inf\"}} 2\n")));
+        assert!(rendered.contains(&format!("{name}_sum 60.02\n")));
+        assert!(rendered.contains(&format!("{name}_count 2\n")));
+
+        // Every sample belongs to a metric family declared with a type
+        for line in rendered.lines().filter(|l| !l.starts_with('#')) {
+            let family = line
+                .split(['{', ' '])
+                .next()
+                .unwrap()
+                .trim_end_matches("_bucket")
+                .trim_end_matches("_sum")
+                .trim_end_matches("_count");
+            assert!(
+                rendered.contains(&format!("# TYPE {family} ")),
+                "missing type for `{line}`"
+            );
+        }
+    }
+}
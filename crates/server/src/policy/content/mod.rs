@@ -2,8 +2,10 @@
 use thiserror::Error;
 use warg_crypto::hash::AnyHash;
 
+mod sources;
 mod wasm;
 
+pub use sources::*;
 pub use wasm::*;
 
 /// Represents a content policy error.
@@ -0,0 +1,235 @@
+use super::{ContentPolicyError, ContentPolicyResult};
+use indexmap::{IndexMap, IndexSet};
+use url::{Origin, Url};
+use warg_api::v1::ContentSource;
+use warg_crypto::hash::AnyHash;
+
+/// The default maximum size of content declared by a content source.
+pub const DEFAULT_MAX_CONTENT_SOURCE_SIZE: u64 = 100 * 1024 * 1024;
+
+/// A policy that validates the content sources specified when publishing a
+/// record.
+///
+/// Each content source must refer to content of the record, be served from
+/// the registry's own content host or an allowed host, and declare a size
+/// no larger than the maximum; a digest or URL may only be listed once.
+#[derive(Clone)]
+pub struct ContentSourcePolicy {
+    origin: Origin,
+    allowed_hosts: IndexSet<String>,
+    max_size: u64,
+}
+
+impl ContentSourcePolicy {
+    /// Creates a new content source policy for a registry serving content
+    /// from the given base URL.
+    pub fn new(content_base_url: &Url) -> Self {
+        Self {
+            origin: content_base_url.origin(),
+            allowed_hosts: IndexSet::new(),
+            max_size: DEFAULT_MAX_CONTENT_SOURCE_SIZE,
+        }
+    }
+
+    /// Allows content sources served from the given host.
+    pub fn with_allowed_host(mut self, host: impl Into<String>) -> Self {
+        self.allowed_hosts.insert(host.into().to_lowercase());
+        self
+    }
+
+    /// Sets the maximum size of content declared by a content source.
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Checks the content sources specified for a record with the given
+    /// content digests.
+    pub fn check<'a>(
+        &self,
+        contents: &IndexSet<&AnyHash>,
+        sources: impl IntoIterator<Item = (&'a AnyHash, &'a ContentSource)>,
+    ) -> ContentPolicyResult<()> {
+        let mut digests = IndexSet::new();
+        let mut urls = IndexMap::new();
+        for (digest, source) in sources {
+            if !contents.contains(digest) {
+                return Err(rejection(format!(
+                    "content `{digest}` is not referenced by the record"
+                )));
+            }
+
+            if !digests.insert(digest) {
+                return Err(rejection(format!(
+                    "content `{digest}` is listed more than once"
+                )));
+            }
+
+            let ContentSource::HttpGet { url, size, .. } = source;
+            if let Some(other) = urls.insert(url.as_str(), digest) {
+                return Err(rejection(format!(
+                    "content source `{url}` is listed for both `{other}` and `{digest}`"
+                )));
+            }
+
+            self.check_url(url)?;
+
+            match size {
+                Some(size) if *size > self.max_size => {
+                    return Err(rejection(format!(
+                        "content `{digest}` has a declared size of {size} bytes, exceeding the maximum of {max} bytes",
+                        max = self.max_size
+                    )));
+                }
+                Some(_) => {}
+                None => {
+                    return Err(rejection(format!(
+                        "content source `{url}` must declare the size of the content"
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_url(&self, url: &str) -> ContentPolicyResult<()> {
+        let parsed = Url::parse(url)
+            .map_err(|e| rejection(format!("content source `{url}` is not a valid URL: {e}")))?;
+
+        if parsed.origin() == self.origin {
+            return Ok(());
+        }
+
+        let allowed = matches!(parsed.scheme(), "http" | "https")
+            && parsed
+                .host_str()
+                .map_or(false, |host| self.allowed_hosts.contains(host));
+        if !allowed {
+            return Err(rejection(format!(
+                "content source `{url}` is not served from an allowed host"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+fn rejection(message: String) -> ContentPolicyError {
+    ContentPolicyError::Rejection(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warg_crypto::hash::{Hash, Sha256};
+
+    fn digest(content: &str) -> AnyHash {
+        Hash::<Sha256>::of(content).into()
+    }
+
+    fn source(url: &str, size: Option<u64>) -> ContentSource {
+        ContentSource::HttpGet {
+            url: url.to_string(),
+            accept_ranges: false,
+            size,
+        }
+    }
+
+    fn policy() -> ContentSourcePolicy {
+        ContentSourcePolicy::new(&"https://registry.example.com".parse().unwrap())
+            .with_allowed_host("cdn.example.com")
+            .with_max_size(1024)
+    }
+
+    fn assert_rejected(res: ContentPolicyResult<()>, expected: &str) {
+        match res {
+            Err(ContentPolicyError::Rejection(message)) => {
+                assert!(message.contains(expected), "unexpected message: {message}")
+            }
+            Ok(()) => panic!("expected a rejection containing `{expected}`"),
+        }
+    }
+
+    #[test]
+    fn test_allowed_sources() {
+        let (a, b) = (digest("a"), digest("b"));
+        let contents = IndexSet::from([&a, &b]);
+        let first = source("https://registry.example.com/content/a", Some(1024));
+        let second = source("http://CDN.example.com/b", Some(1));
+
+        assert!(policy()
+            .check(&contents, [(&a, &first), (&b, &second)])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_off_host_source() {
+        let a = digest("a");
+        let contents = IndexSet::from([&a]);
+
+        for url in [
+            "https://evil.example.com/a",
+            "http://registry.example.com/content/a",
+            "https://registry.example.com:8443/content/a",
+            "ftp://cdn.example.com/a",
+        ] {
+            assert_rejected(
+                policy().check(&contents, [(&a, &source(url, Some(1)))]),
+                "is not served from an allowed host",
+            );
+        }
+    }
+
+    #[test]
+    fn test_oversize_source() {
+        let a = digest("a");
+        let contents = IndexSet::from([&a]);
+
+        assert_rejected(
+            policy().check(
+                &contents,
+                [(&a, &source("https://cdn.example.com/a", Some(1025)))],
+            ),
+            "exceeding the maximum of 1024 bytes",
+        );
+        assert_rejected(
+            policy().check(
+                &contents,
+                [(&a, &source("https://cdn.example.com/a", None))],
+            ),
+            "must declare the size",
+        );
+    }
+
+    #[test]
+    fn test_duplicate_digest() {
+        let (a, b) = (digest("a"), digest("b"));
+        let contents = IndexSet::from([&a, &b]);
+        let first = source("https://cdn.example.com/a", Some(1));
+        let second = source("https://cdn.example.com/a2", Some(1));
+
+        assert_rejected(
+            policy().check(&contents, [(&a, &first), (&a, &second)]),
+            "is listed more than once",
+        );
+        assert_rejected(
+            policy().check(&contents, [(&a, &first), (&b, &first)]),
+            "is listed for both",
+        );
+    }
+
+    #[test]
+    fn test_unreferenced_digest() {
+        let (a, b) = (digest("a"), digest("b"));
+        let contents = IndexSet::from([&a]);
+
+        assert_rejected(
+            policy().check(
+                &contents,
+                [(&b, &source("https://cdn.example.com/b", Some(1)))],
+            ),
+            "is not referenced by the record",
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use super::{RecordPolicy, RecordPolicyError, RecordPolicyResult};
+use indexmap::IndexSet;
+use warg_protocol::{
+    package::{PackageEntry, PackageRecord, DEBUG_ARTIFACT, WIT_ARTIFACT},
+    registry::PackageName,
+    ProtoEnvelope,
+};
+
+/// The maximum length of an artifact type name, in characters.
+pub const MAX_ARTIFACT_TYPE_LENGTH: usize = 64;
+
+/// Validates the name of an artifact type.
+///
+/// Artifact type names are lowercase kebab-case, such as `wit` or
+/// `debug-info`.
+pub fn validate_artifact_type(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_ARTIFACT_TYPE_LENGTH {
+        return Err(format!(
+            "artifact type `{name}` must be between 1 and {MAX_ARTIFACT_TYPE_LENGTH} characters"
+        ));
+    }
+
+    if name.split('-').any(|word| {
+        word.is_empty()
+            || !word
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    }) {
+        return Err(format!(
+            "artifact type `{name}` is not lowercase kebab-case"
+        ));
+    }
+
+    Ok(())
+}
+
+/// A policy that rejects releases with artifacts of unregistered types.
+///
+/// Every release has a `component` artifact; the `wit` and `debug` artifact
+/// types are always registered and other types may be registered with
+/// [`ReleaseArtifactPolicy::with_type`].
+#[derive(Debug, Clone)]
+pub struct ReleaseArtifactPolicy {
+    types: IndexSet<String>,
+}
+
+impl ReleaseArtifactPolicy {
+    /// Creates a new release artifact policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an artifact type.
+    ///
+    /// The name should be validated with [`validate_artifact_type`].
+    pub fn with_type(mut self, name: impl Into<String>) -> Self {
+        self.types.insert(name.into());
+        self
+    }
+}
+
+impl Default for ReleaseArtifactPolicy {
+    fn default() -> Self {
+        Self {
+            types: IndexSet::from([WIT_ARTIFACT.to_string(), DEBUG_ARTIFACT.to_string()]),
+        }
+    }
+}
+
+impl RecordPolicy for ReleaseArtifactPolicy {
+    fn check(
+        &self,
+        _name: &PackageName,
+        record: &ProtoEnvelope<PackageRecord>,
+    ) -> RecordPolicyResult<()> {
+        for entry in &record.as_ref().entries {
+            let PackageEntry::Release {
+                version, artifacts, ..
+            } = entry
+            else {
+                continue;
+            };
+
+            if let Some(name) = artifacts.keys().find(|name| !self.types.contains(*name)) {
+                return Err(RecordPolicyError::Rejection(format!(
+                    "version {version} has an artifact of unregistered type `{name}`"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_artifact_type() {
+        for name in ["wit", "debug", "debug-info", "wasi-p2"] {
+            assert!(validate_artifact_type(name).is_ok(), "{name}");
+        }
+
+        for name in [
+            "",
+            "Wit",
+            "debug_info",
+            "-wit",
+            "wit-",
+            "debug--info",
+            "docs.tar",
+        ] {
+            assert!(validate_artifact_type(name).is_err(), "{name}");
+        }
+
+        assert!(validate_artifact_type(&"a".repeat(MAX_ARTIFACT_TYPE_LENGTH)).is_ok());
+        assert!(validate_artifact_type(&"a".repeat(MAX_ARTIFACT_TYPE_LENGTH + 1)).is_err());
+    }
+}
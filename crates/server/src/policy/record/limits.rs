@@ -0,0 +1,94 @@
+use super::{RecordPolicy, RecordPolicyError, RecordPolicyResult};
+use warg_api::v1::registry::RecordLimits;
+use warg_protocol::{package::PackageRecord, registry::PackageName, ProtoEnvelope};
+
+/// A policy that rejects records exceeding the registry's record limits.
+///
+/// The limits are advertised to clients through the registry metadata API so
+/// that a publish may be split across records before it is submitted.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RecordLimitPolicy {
+    limits: RecordLimits,
+}
+
+impl RecordLimitPolicy {
+    /// Creates a new record limit policy with the given limits.
+    pub fn new(limits: RecordLimits) -> Self {
+        Self { limits }
+    }
+
+    /// Gets the limits enforced by the policy.
+    pub fn limits(&self) -> RecordLimits {
+        self.limits
+    }
+
+    fn check_size(&self, size: usize, entries: usize) -> RecordPolicyResult<()> {
+        if let Some(max) = self.limits.max_record_entries {
+            if entries > max as usize {
+                return Err(RecordPolicyError::Rejection(format!(
+                    "record has {entries} entries, exceeding the maximum of {max} entries per record"
+                )));
+            }
+        }
+
+        if let Some(max) = self.limits.max_record_size {
+            if size as u64 > max {
+                return Err(RecordPolicyError::Rejection(format!(
+                    "record is {size} bytes, exceeding the maximum record size of {max} bytes"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl RecordPolicy for RecordLimitPolicy {
+    fn check(
+        &self,
+        _name: &PackageName,
+        record: &ProtoEnvelope<PackageRecord>,
+    ) -> RecordPolicyResult<()> {
+        self.check_size(record.to_protobuf().len(), record.as_ref().entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_record_size: Option<u64>, max_record_entries: Option<u32>) -> RecordLimitPolicy {
+        RecordLimitPolicy::new(RecordLimits {
+            max_record_size,
+            max_record_entries,
+        })
+    }
+
+    #[test]
+    fn test_record_within_limits() {
+        assert!(policy(None, None)
+            .check_size(usize::MAX, usize::MAX)
+            .is_ok());
+        assert!(policy(Some(100), Some(10)).check_size(100, 10).is_ok());
+        assert!(policy(Some(100), Some(10)).check_size(1, 1).is_ok());
+    }
+
+    #[test]
+    fn test_record_exceeding_limits() {
+        match policy(Some(100), None).check_size(101, 1) {
+            Err(RecordPolicyError::Rejection(message)) => assert!(
+                message.contains("maximum record size of 100 bytes"),
+                "{message}"
+            ),
+            res => panic!("unexpected result: {res:?}"),
+        }
+
+        match policy(None, Some(10)).check_size(1, 11) {
+            Err(RecordPolicyError::Rejection(message)) => assert!(
+                message.contains("maximum of 10 entries per record"),
+                "{message}"
+            ),
+            res => panic!("unexpected result: {res:?}"),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use super::{RecordPolicy, RecordPolicyError, RecordPolicyResult};
+use url::Url;
+use warg_protocol::{
+    package::{PackageEntry, PackageRecord, ReleaseMetadata},
+    registry::PackageName,
+    ProtoEnvelope,
+};
+
+/// The maximum length of a release's description, in characters.
+pub const MAX_DESCRIPTION_LENGTH: usize = 1000;
+
+/// The maximum length of a release's license expression, in characters.
+pub const MAX_LICENSE_LENGTH: usize = 256;
+
+/// The maximum length of a release's repository or homepage URL, in
+/// characters.
+pub const MAX_URL_LENGTH: usize = 2048;
+
+/// The maximum number of keywords of a release.
+pub const MAX_KEYWORDS: usize = 10;
+
+/// The maximum length of a release keyword, in characters.
+pub const MAX_KEYWORD_LENGTH: usize = 32;
+
+/// A policy that rejects records with invalid release metadata.
+///
+/// Descriptions, licenses, URLs, and keywords are limited in length, licenses
+/// must be valid SPDX expressions, and URLs must be HTTP or HTTPS URLs.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReleaseMetadataPolicy;
+
+impl ReleaseMetadataPolicy {
+    fn check_metadata(&self, metadata: &ReleaseMetadata) -> Result<(), String> {
+        if let Some(description) = &metadata.description {
+            check_length("description", description, MAX_DESCRIPTION_LENGTH)?;
+        }
+
+        if let Some(license) = &metadata.license {
+            check_length("license", license, MAX_LICENSE_LENGTH)?;
+            spdx::Expression::parse(license).map_err(|e| {
+                format!(
+                    "license `{license}` is not a valid SPDX expression: {reason}",
+                    reason = e.reason
+                )
+            })?;
+        }
+
+        for (field, url) in [
+            ("repository", &metadata.repository),
+            ("homepage", &metadata.homepage),
+        ] {
+            if let Some(url) = url {
+                check_length(field, url, MAX_URL_LENGTH)?;
+                match Url::parse(url) {
+                    Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+                    _ => return Err(format!("{field} `{url}` is not an HTTP or HTTPS URL")),
+                }
+            }
+        }
+
+        if metadata.keywords.len() > MAX_KEYWORDS {
+            return Err(format!(
+                "release has {count} keywords, exceeding the maximum of {MAX_KEYWORDS} keywords",
+                count = metadata.keywords.len()
+            ));
+        }
+
+        for keyword in &metadata.keywords {
+            check_length("keyword", keyword, MAX_KEYWORD_LENGTH)?;
+            if keyword.is_empty() || keyword.chars().any(char::is_whitespace) {
+                return Err(format!(
+                    "keyword `{keyword}` must not be empty or contain whitespace"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn check_length(field: &str, value: &str, max: usize) -> Result<(), String> {
+    let len = value.chars().count();
+    if len > max {
+        return Err(format!(
+            "{field} is {len} characters, exceeding the maximum of {max} characters"
+        ));
+    }
+
+    Ok(())
+}
+
+impl RecordPolicy for ReleaseMetadataPolicy {
+    fn check(
+        &self,
+        _name: &PackageName,
+        record: &ProtoEnvelope<PackageRecord>,
+    ) -> RecordPolicyResult<()> {
+        for entry in &record.as_ref().entries {
+            let PackageEntry::Release {
+                version,
+                metadata: Some(metadata),
+                ..
+            } = entry
+            else {
+                continue;
+            };
+
+            self.check_metadata(metadata).map_err(|message| {
+                RecordPolicyError::Rejection(format!(
+                    "invalid metadata for version {version}: {message}"
+                ))
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(metadata: ReleaseMetadata) -> Result<(), String> {
+        ReleaseMetadataPolicy.check_metadata(&metadata)
+    }
+
+    #[test]
+    fn test_valid_metadata() {
+        assert!(check(ReleaseMetadata::default()).is_ok());
+        assert!(check(ReleaseMetadata {
+            description: Some("An example package".to_string()),
+            license: Some("Apache-2.0 WITH LLVM-exception OR MIT".to_string()),
+            repository: Some("https://github.com/example/hello".to_string()),
+            homepage: Some("http://example.com".to_string()),
+            keywords: vec!["example".to_string(), "wasi-http".to_string()],
+        })
+        .is_ok());
+    }
+
+    #[test]
+    fn test_invalid_metadata() {
+        let message = check(ReleaseMetadata {
+            license: Some("Apache 2".to_string()),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(
+            message.contains("license `Apache 2` is not a valid SPDX expression"),
+            "{message}"
+        );
+
+        let message = check(ReleaseMetadata {
+            description: Some("x".repeat(MAX_DESCRIPTION_LENGTH + 1)),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(message.contains("maximum of 1000 characters"), "{message}");
+
+        let message = check(ReleaseMetadata {
+            repository: Some("ftp://example.com".to_string()),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(message.contains("not an HTTP or HTTPS URL"), "{message}");
+
+        let message = check(ReleaseMetadata {
+            keywords: vec!["two words".to_string()],
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(message.contains("must not be empty"), "{message}");
+
+        let message = check(ReleaseMetadata {
+            keywords: vec!["keyword".to_string(); MAX_KEYWORDS + 1],
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(message.contains("maximum of 10 keywords"), "{message}");
+    }
+}
@@ -2,8 +2,16 @@
 use thiserror::Error;
 use warg_protocol::{package::PackageRecord, registry::PackageName, ProtoEnvelope};
 
+mod artifacts;
 mod authorization;
+mod limits;
+mod metadata;
+mod timestamp;
+pub use artifacts::*;
 pub use authorization::*;
+pub use limits::*;
+pub use metadata::*;
+pub use timestamp::*;
 
 /// Represents a record policy error.
 #[derive(Debug, Error)]
@@ -0,0 +1,100 @@
+use super::{RecordPolicy, RecordPolicyError, RecordPolicyResult};
+use std::time::{Duration, SystemTime};
+use warg_protocol::{package::PackageRecord, registry::PackageName, ProtoEnvelope};
+
+/// The default maximum difference between a record's timestamp and the
+/// registry's clock.
+pub const DEFAULT_MAX_TIMESTAMP_SKEW: Duration = Duration::from_secs(60 * 60);
+
+/// A policy that rejects records with timestamps too far from the registry's
+/// clock.
+///
+/// The policy only applies to submitted records; records already in a log
+/// are not checked.
+#[derive(Debug, Clone, Copy)]
+pub struct TimestampSkewPolicy {
+    max_skew: Duration,
+}
+
+impl TimestampSkewPolicy {
+    /// Creates a new timestamp skew policy with the given maximum skew.
+    pub fn new(max_skew: Duration) -> Self {
+        Self { max_skew }
+    }
+
+    /// Gets the maximum skew allowed by the policy.
+    pub fn max_skew(&self) -> Duration {
+        self.max_skew
+    }
+
+    fn check_at(&self, timestamp: SystemTime, now: SystemTime) -> RecordPolicyResult<()> {
+        let (skew, direction) = match timestamp.duration_since(now) {
+            Ok(ahead) => (ahead, "ahead of"),
+            Err(e) => (e.duration(), "behind"),
+        };
+
+        if skew > self.max_skew {
+            return Err(RecordPolicyError::Rejection(format!(
+                "record timestamp is {skew} second(s) {direction} the registry clock, exceeding the maximum skew of {max} second(s)",
+                skew = skew.as_secs(),
+                max = self.max_skew.as_secs(),
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for TimestampSkewPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_TIMESTAMP_SKEW)
+    }
+}
+
+impl RecordPolicy for TimestampSkewPolicy {
+    fn check(
+        &self,
+        _name: &PackageName,
+        record: &ProtoEnvelope<PackageRecord>,
+    ) -> RecordPolicyResult<()> {
+        self.check_at(record.as_ref().timestamp, SystemTime::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp_within_skew() {
+        let policy = TimestampSkewPolicy::new(Duration::from_secs(60));
+        let now = SystemTime::now();
+        let just_inside = Duration::from_secs(59);
+
+        assert!(policy.check_at(now, now).is_ok());
+        assert!(policy.check_at(now + just_inside, now).is_ok());
+        assert!(policy.check_at(now - just_inside, now).is_ok());
+        assert!(policy.check_at(now + policy.max_skew(), now).is_ok());
+    }
+
+    #[test]
+    fn test_timestamp_outside_skew() {
+        let policy = TimestampSkewPolicy::new(Duration::from_secs(60));
+        let now = SystemTime::now();
+        let just_outside = Duration::from_secs(61);
+
+        match policy.check_at(now + just_outside, now) {
+            Err(RecordPolicyError::Rejection(message)) => {
+                assert!(message.contains("61 second(s) ahead of"), "{message}")
+            }
+            res => panic!("unexpected result: {res:?}"),
+        }
+
+        match policy.check_at(now - just_outside, now) {
+            Err(RecordPolicyError::Rejection(message)) => {
+                assert!(message.contains("61 second(s) behind"), "{message}")
+            }
+            res => panic!("unexpected result: {res:?}"),
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{
+    content::{ContentStorage, ContentStoreError},
+    datastore::{DataStore, DataStoreError},
+};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use thiserror::Error;
+use tokio::{sync::Mutex, time::MissedTickBehavior};
+
+/// The default duration unreferenced content is kept before it is removed.
+pub const DEFAULT_CONTENT_GC_GRACE_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Represents an error collecting unreferenced content.
+#[derive(Debug, Error)]
+pub enum ContentGcError {
+    /// A data store error occurred.
+    #[error(transparent)]
+    DataStore(#[from] DataStoreError),
+    /// A content store error occurred.
+    #[error(transparent)]
+    ContentStore(#[from] ContentStoreError),
+}
+
+/// Removes content that is only referenced by rejected records or by no
+/// record at all.
+///
+/// Content is only removed once it has gone unmodified for the grace period.
+pub struct ContentCollector {
+    store: Arc<dyn DataStore>,
+    content_store: ContentStorage,
+    grace_period: Duration,
+    // Serializes collections so a background pass and an on-demand pass do
+    // not race to remove the same files
+    lock: Mutex<()>,
+}
+
+impl ContentCollector {
+    /// Creates a new collector for the given data and content stores.
+    pub fn new(
+        store: Arc<dyn DataStore>,
+        content_store: ContentStorage,
+        grace_period: Duration,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            store,
+            content_store,
+            grace_period,
+            lock: Default::default(),
+        })
+    }
+
+    /// Starts collecting unreferenced content at the given interval.
+    ///
+    /// Collection continues for the lifetime of the runtime.
+    pub fn start(self: &Arc<Self>, interval: Duration) {
+        let collector = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            // The first tick completes immediately
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                match collector.collect().await {
+                    Ok(0) => {}
+                    Ok(removed) => tracing::info!("removed {removed} unreferenced content file(s)"),
+                    Err(e) => tracing::warn!("failed to collect unreferenced content: {e}"),
+                }
+            }
+        });
+    }
+
+    /// Removes unreferenced content older than the grace period.
+    ///
+    /// Returns the number of content files that were removed.
+    pub async fn collect(&self) -> Result<usize, ContentGcError> {
+        self.collect_at(SystemTime::now()).await
+    }
+
+    /// Removes unreferenced content older than the grace period as of the
+    /// given time.
+    ///
+    /// Returns the number of content files that were removed.
+    pub async fn collect_at(&self, now: SystemTime) -> Result<usize, ContentGcError> {
+        let _guard = self.lock.lock().await;
+        let referenced = self.store.get_referenced_content().await?;
+        Ok(self
+            .content_store
+            .remove_unreferenced(&referenced, self.grace_period, now)
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{content::FileSystemContentStore, datastore::MemoryDataStore};
+    use indexmap::IndexSet;
+    use warg_crypto::{
+        hash::{AnyHash, Hash, HashAlgorithm, Sha256},
+        signing::{generate_p256_pair, PrivateKey},
+    };
+    use warg_protocol::{
+        package::{PackageEntry, PackageRecord, PACKAGE_RECORD_VERSION},
+        registry::{LogId, PackageName, RecordId},
+        ProtoEnvelope, Version,
+    };
+
+    const GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+    /// Stores a record releasing the given content, uploading the content.
+    async fn store_release(
+        store: &MemoryDataStore,
+        files: &FileSystemContentStore,
+        signing_key: &PrivateKey,
+        name: &str,
+        content: &str,
+    ) -> (LogId, RecordId) {
+        let name = PackageName::new(name).unwrap();
+        let log_id = LogId::package_log::<Sha256>(&name);
+        let digest: AnyHash = Hash::<Sha256>::of(content).into();
+        let record = ProtoEnvelope::signed_contents(
+            signing_key,
+            PackageRecord {
+                prev: None,
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: vec![
+                    PackageEntry::Init {
+                        hash_algorithm: HashAlgorithm::Sha256,
+                        key: signing_key.public_key(),
+                    },
+                    PackageEntry::Release {
+                        version: Version::new(1, 0, 0),
+                        content: digest.clone(),
+                        artifacts: Default::default(),
+                        metadata: None,
+                    },
+                ],
+            },
+        )
+        .unwrap();
+        let record_id = RecordId::package_record::<Sha256>(&record);
+
+        store
+            .store_package_record(
+                &log_id,
+                &name,
+                &record_id,
+                &record,
+                &IndexSet::from([&digest]),
+            )
+            .await
+            .unwrap();
+        tokio::fs::write(files.path(&digest), content)
+            .await
+            .unwrap();
+        assert!(store
+            .set_content_present(&log_id, &record_id, &digest)
+            .await
+            .unwrap());
+
+        (log_id, record_id)
+    }
+
+    #[tokio::test]
+    async fn it_removes_rejected_and_orphaned_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = FileSystemContentStore::new(dir.path().to_path_buf());
+        let content_store = ContentStorage::new(Arc::new(files.clone()), dir.path().to_path_buf());
+        let store = MemoryDataStore::default();
+        let (_, signing_key) = generate_p256_pair();
+
+        let (log_id, record_id) =
+            store_release(&store, &files, &signing_key, "test:published", "published").await;
+        store
+            .commit_package_record(&log_id, &record_id, 0, &|_, _| Ok(()))
+            .await
+            .unwrap();
+
+        let (log_id, record_id) =
+            store_release(&store, &files, &signing_key, "test:rejected", "rejected").await;
+        store
+            .reject_package_record(&log_id, &record_id, "rejected for testing")
+            .await
+            .unwrap();
+
+        let orphaned: AnyHash = Hash::<Sha256>::of("orphaned").into();
+        tokio::fs::write(files.path(&orphaned), "orphaned")
+            .await
+            .unwrap();
+
+        let collector = ContentCollector::new(Arc::new(store), content_store.clone(), GRACE_PERIOD);
+        let now = SystemTime::now();
+
+        // Nothing is removed within the grace period
+        assert_eq!(collector.collect_at(now).await.unwrap(), 0);
+        assert!(content_store.is_present(&orphaned).await.unwrap());
+
+        assert_eq!(
+            collector
+                .collect_at(now + GRACE_PERIOD + Duration::from_secs(1))
+                .await
+                .unwrap(),
+            2
+        );
+        for (digest, present) in [
+            (Hash::<Sha256>::of("published").into(), true),
+            (Hash::<Sha256>::of("rejected").into(), false),
+            (orphaned, false),
+        ] {
+            assert_eq!(
+                content_store.is_present(&digest).await.unwrap(),
+                present,
+                "{digest}"
+            );
+        }
+    }
+}
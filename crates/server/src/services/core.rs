@@ -1,5 +1,9 @@
 use std::{
-    sync::Arc,
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
     time::{Duration, SystemTime},
 };
 
@@ -7,18 +11,23 @@ use futures::{pin_mut, StreamExt};
 use indexmap::IndexMap;
 use thiserror::Error;
 use tokio::{
-    sync::{mpsc, RwLock},
-    task::JoinHandle,
-    time::MissedTickBehavior,
+    sync::{
+        mpsc::{self, error::TrySendError},
+        watch, RwLock,
+    },
+    task::{JoinError, JoinHandle},
+    time::Instant,
 };
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, Span};
 use warg_crypto::{
-    hash::{AnyHash, Hash, Sha256, SupportedDigest},
-    signing::PrivateKey,
+    hash::{AnyHash, Hash, HashAlgorithm, Sha256, Sha512, SupportedDigest},
+    signing::{PrivateKey, SignatureError},
 };
 use warg_protocol::{
-    operator,
+    operator, package,
     registry::{
-        Checkpoint, LogId, LogLeaf, MapLeaf, RecordId, RegistryIndex, RegistryLen,
+        Checkpoint, LogId, LogLeaf, MapLeaf, PackageName, RecordId, RegistryIndex, RegistryLen,
         TimestampedCheckpoint,
     },
     ProtoEnvelope, SerdeEnvelope,
@@ -28,70 +37,758 @@ use warg_transparency::{
     map::{Map, MapProofBundle},
 };
 
-use crate::datastore::{DataStore, DataStoreError};
+use crate::{
+    audit::{self, AuditLog},
+    datastore::{DataStore, DataStoreError},
+    metrics::Metrics,
+};
+use warg_api::v1::admin::{AuditEntry, AuditOperation, SubmissionQueueResponse};
+
+/// The default number of records that may be waiting to be processed.
+pub const DEFAULT_SUBMISSION_QUEUE_DEPTH: usize = 256;
+
+/// The default time a submission waits for room in a full queue.
+pub const DEFAULT_SUBMISSION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The policy for batching validated records into checkpoints.
+///
+/// A checkpoint is issued at least every `interval`; it is issued early once
+/// `max_leaves` records are waiting to be checkpointed, or once `min_leaves`
+/// records are waiting and the submission queue is empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointPolicy {
+    /// The longest time between checkpoints.
+    pub interval: Duration,
+    /// The most records included in a checkpoint, if limited.
+    pub max_leaves: Option<usize>,
+    /// The fewest records that are checkpointed early when no more
+    /// submissions are queued, if any.
+    pub min_leaves: Option<usize>,
+}
+
+impl CheckpointPolicy {
+    /// Creates a policy that issues a checkpoint every `interval`.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            max_leaves: None,
+            min_leaves: None,
+        }
+    }
+
+    /// Limits the number of records included in a checkpoint.
+    pub fn with_max_leaves(mut self, leaves: usize) -> Self {
+        self.max_leaves = Some(leaves);
+        self
+    }
+
+    /// Sets the number of records that are checkpointed early when no more
+    /// submissions are queued.
+    pub fn with_min_leaves(mut self, leaves: usize) -> Self {
+        self.min_leaves = Some(leaves);
+        self
+    }
+
+    fn validate(&self) -> Result<(), CoreServiceError> {
+        let invalid = match (self.min_leaves, self.max_leaves) {
+            (Some(0), _) | (_, Some(0)) => "checkpoint leaves must be greater than zero",
+            (Some(min), Some(max)) if min > max => {
+                "the minimum checkpoint leaves must not exceed the maximum"
+            }
+            _ => return Ok(()),
+        };
+
+        Err(CoreServiceError::InitializationFailure(invalid.into()))
+    }
+
+    // Whether a batch of the given number of records must be checkpointed.
+    fn is_full(&self, batch: usize) -> bool {
+        self.max_leaves.map_or(false, |max| batch >= max)
+    }
+
+    // Whether a batch of the given number of records may be checkpointed
+    // before the interval elapses.
+    fn is_ready(&self, batch: usize) -> bool {
+        self.min_leaves.map_or(false, |min| batch >= min)
+    }
+}
+
+/// The configuration the [`CoreService`] is started with.
+pub struct CoreServiceConfig {
+    /// The key checkpoints are signed with.
+    pub operator_key: PrivateKey,
+    /// The key checkpoints are signed with instead of `operator_key` once
+    /// the operator log permits it to sign checkpoints, such as after the
+    /// operator key is rotated to it.
+    pub next_operator_key: Option<PrivateKey>,
+    /// The algorithm log IDs, record IDs, and checkpoints are hashed with,
+    /// which must be the algorithm the registry was initialized with.
+    pub hash_algorithm: HashAlgorithm,
+    /// The namespaces defined when the operator log is initialized.
+    pub namespaces: Option<Vec<(String, operator::NamespaceState)>>,
+    /// The data store of the registry.
+    pub store: Arc<dyn DataStore>,
+    /// The policy for batching validated records into checkpoints.
+    pub checkpoint_policy: CheckpointPolicy,
+    /// The maximum checkpoint interval declared in the operator log before
+    /// the first checkpoint is issued, if it differs from the declared one.
+    pub max_checkpoint_interval: Option<Duration>,
+    /// How long after their timestamp checkpoints expire; the latest
+    /// checkpoint is re-signed before it expires even if no records were
+    /// published.
+    pub checkpoint_validity: Option<Duration>,
+    /// The audit log records processed and checkpoints issued are appended to.
+    pub audit_log: Option<Arc<dyn AuditLog>>,
+    /// The number of records that may be waiting to be processed; further
+    /// submissions are rejected until the queue drains.
+    pub submission_queue_depth: usize,
+}
+
+impl CoreServiceConfig {
+    /// Creates a configuration with the default submission queue depth and
+    /// no checkpoint expiry.
+    pub fn new(
+        operator_key: PrivateKey,
+        hash_algorithm: HashAlgorithm,
+        store: Arc<dyn DataStore>,
+        checkpoint_policy: CheckpointPolicy,
+    ) -> Self {
+        Self {
+            operator_key,
+            next_operator_key: None,
+            hash_algorithm,
+            namespaces: None,
+            store,
+            checkpoint_policy,
+            max_checkpoint_interval: None,
+            checkpoint_validity: None,
+            audit_log: None,
+            submission_queue_depth: DEFAULT_SUBMISSION_QUEUE_DEPTH,
+        }
+    }
+
+    /// Sets how long after their timestamp checkpoints expire.
+    pub fn with_checkpoint_validity(mut self, validity: Duration) -> Self {
+        self.checkpoint_validity = Some(validity);
+        self
+    }
+
+    /// Sets the number of records that may be waiting to be processed.
+    pub fn with_submission_queue_depth(mut self, depth: usize) -> Self {
+        self.submission_queue_depth = depth;
+        self
+    }
+}
 
 #[derive(Clone)]
-pub struct CoreService<Digest: SupportedDigest = Sha256> {
-    inner: Arc<Inner<Digest>>,
+pub struct CoreService {
+    inner: Arc<dyn Transparency>,
+
+    // Channel sender used by `reserve_submission` to serialize submissions.
+    submit_entry_tx: mpsc::Sender<Submission>,
+
+    // How long a submission waits for room in a full queue.
+    submission_timeout: Duration,
+
+    // Counters of the submission queue.
+    queue_counters: Arc<QueueCounters>,
+
+    // Signals the state update task to stop.
+    shutdown: CancellationToken,
+
+    // The state update task, taken by the first call to `shutdown`.
+    task: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+#[derive(Default)]
+struct QueueCounters {
+    submitted: AtomicU64,
+    rejected: AtomicU64,
+}
 
-    // Channel sender used by `submit_package_record` to serialize submissions.
-    submit_entry_tx: mpsc::Sender<LogLeaf>,
+// A package record waiting to be processed.
+//
+// The span of the submitting request is kept so that processing and
+// publishing the record are traced as part of that request.
+struct Submission {
+    leaf: LogLeaf,
+    span: Span,
 }
 
-impl<Digest: SupportedDigest> CoreService<Digest> {
+/// A reserved slot in the queue of records waiting to be processed.
+///
+/// Dropping the permit without submitting a record releases the slot.
+pub struct SubmissionPermit {
+    permit: mpsc::OwnedPermit<Submission>,
+    counters: Arc<QueueCounters>,
+    metrics: Arc<Metrics>,
+}
+
+impl SubmissionPermit {
+    /// Submits a package record to be processed.
+    ///
+    /// The record is processed in the current tracing span.
+    pub fn submit(self, log_id: LogId, record_id: RecordId) {
+        record_state_changed(&log_id, &record_id, "processing");
+        self.permit.send(Submission {
+            leaf: LogLeaf { log_id, record_id },
+            span: Span::current(),
+        });
+        self.counters.submitted.fetch_add(1, Ordering::Relaxed);
+        self.metrics.record_submitted();
+    }
+}
+
+impl CoreService {
     /// Starts the `CoreService`, returning a `clone`able handle to the
-    /// service and a [`JoinHandle`] which should be awaited after dropping all
-    /// copies of the service handle to allow for graceful shutdown.
-    pub async fn start(
+    /// service.
+    ///
+    /// [`CoreService::shutdown`] should be awaited before exiting so that
+    /// queued records are processed and checkpointed.
+    pub async fn start(config: CoreServiceConfig) -> Result<Self, CoreServiceError> {
+        if config.submission_queue_depth == 0 {
+            return Err(CoreServiceError::InitializationFailure(
+                "the submission queue depth must be greater than zero".into(),
+            ));
+        }
+
+        config.checkpoint_policy.validate()?;
+
+        let settings = Settings {
+            operator_key: config.operator_key,
+            next_operator_key: config.next_operator_key,
+            namespaces: config.namespaces,
+            store: config.store,
+            checkpoint_policy: config.checkpoint_policy,
+            max_checkpoint_interval: config.max_checkpoint_interval,
+            checkpoint_validity: config.checkpoint_validity,
+            audit_log: config.audit_log,
+            mirror: false,
+        };
+        Self::start_with_settings(
+            config.hash_algorithm,
+            settings,
+            config.submission_queue_depth,
+        )
+        .await
+    }
+
+    /// Starts the `CoreService` as a read-only mirror of another registry.
+    ///
+    /// A mirror neither initializes an operator log nor issues checkpoints of
+    /// its own; its log is replicated from the upstream registry with
+    /// [`CoreService::replicate`] and submissions are rejected.
+    ///
+    /// The operator key is not used to sign anything while mirroring.
+    pub async fn start_mirror(
         operator_key: PrivateKey,
-        namespaces: Option<Vec<(String, operator::NamespaceState)>>,
-        store: Box<dyn DataStore>,
-        checkpoint_interval: Duration,
-    ) -> Result<(Self, JoinHandle<()>), CoreServiceError> {
-        // Build service
-        let mut inner = Inner {
+        hash_algorithm: HashAlgorithm,
+        store: Arc<dyn DataStore>,
+    ) -> Result<Self, CoreServiceError> {
+        let settings = Settings {
             operator_key,
+            next_operator_key: None,
+            namespaces: None,
             store,
-            state: Default::default(),
+            checkpoint_policy: CheckpointPolicy::new(Duration::MAX),
+            max_checkpoint_interval: None,
+            checkpoint_validity: None,
+            audit_log: None,
+            mirror: true,
         };
-        inner.initialize(namespaces).await?;
+        Self::start_with_settings(hash_algorithm, settings, 1).await
+    }
 
-        // Spawn state update task
-        let inner = Arc::new(inner);
-        let (submit_entry_tx, submit_entry_rx) = tokio::sync::mpsc::channel(4);
-        let handle = tokio::spawn(
-            inner
-                .clone()
-                .process_state_updates(submit_entry_rx, checkpoint_interval),
-        );
+    async fn start_with_settings(
+        hash_algorithm: HashAlgorithm,
+        settings: Settings,
+        submission_queue_depth: usize,
+    ) -> Result<Self, CoreServiceError> {
+        let (submit_entry_tx, submit_entry_rx) = mpsc::channel(submission_queue_depth);
+        let shutdown = CancellationToken::new();
+        let (inner, handle) = match hash_algorithm {
+            HashAlgorithm::Sha256 => {
+                Inner::<Sha256>::start(settings, submit_entry_rx, shutdown.clone()).await?
+            }
+            HashAlgorithm::Sha512 => {
+                Inner::<Sha512>::start(settings, submit_entry_rx, shutdown.clone()).await?
+            }
+            algorithm => {
+                return Err(CoreServiceError::InitializationFailure(format!(
+                    "hash algorithm `{algorithm}` is not supported"
+                )))
+            }
+        };
 
-        let svc = Self {
+        Ok(Self {
             inner,
             submit_entry_tx,
-        };
-        Ok((svc, handle))
+            submission_timeout: DEFAULT_SUBMISSION_TIMEOUT,
+            queue_counters: Default::default(),
+            shutdown,
+            task: Arc::new(Mutex::new(Some(handle))),
+        })
+    }
+
+    /// Shuts down the service.
+    ///
+    /// New submissions are rejected with [`CoreServiceError::ShuttingDown`];
+    /// records already queued are processed and a final checkpoint is issued
+    /// before this returns.
+    ///
+    /// Only the first call waits for the service to stop.
+    pub async fn shutdown(&self) -> Result<(), CoreServiceError> {
+        self.shutdown.cancel();
+        let task = self.task.lock().unwrap().take();
+        if let Some(task) = task {
+            task.await?;
+        }
+
+        Ok(())
+    }
+
+    /// Gets the hash algorithm of the registry.
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.inner.hash_algorithm()
+    }
+
+    /// Gets the length of the registry log, including records not yet part
+    /// of a checkpoint.
+    pub async fn log_length(&self) -> RegistryLen {
+        self.inner.log_length().await
+    }
+
+    /// Replicates records of an upstream registry along with the upstream's
+    /// checkpoint that includes them.
+    ///
+    /// The records must be given in registry log order and immediately
+    /// follow the records already in the log. The log and map roots of the
+    /// checkpoint are verified against the records before any of them is
+    /// stored; the records are then validated and committed, and the
+    /// checkpoint is stored once its signature is verified against the
+    /// replicated operator log.
+    pub async fn replicate(
+        &self,
+        records: Vec<MirroredRecord>,
+        checkpoint: SerdeEnvelope<TimestampedCheckpoint>,
+    ) -> Result<(), CoreServiceError> {
+        self.inner.replicate(records, checkpoint).await
+    }
+
+    /// Gets the ID of the registry's operator log.
+    pub fn operator_log_id(&self) -> LogId {
+        LogId::operator_log_for(self.hash_algorithm())
+    }
+
+    /// Gets the ID of the log of the given package.
+    pub fn package_log_id(&self, name: &PackageName) -> LogId {
+        LogId::package_log_for(self.hash_algorithm(), name)
     }
 
     /// Constructs a log consistency proof between the given log tree roots.
+    ///
+    /// Returns the encoded proof bundle.
     pub async fn log_consistency_proof(
         &self,
         from_log_length: RegistryLen,
         to_log_length: RegistryLen,
-    ) -> Result<LogProofBundle<Digest, LogLeaf>, CoreServiceError> {
-        let state = self.inner.state.read().await;
-
-        let proof = state.log.prove_consistency(from_log_length, to_log_length);
-        LogProofBundle::bundle(vec![proof], vec![], &state.log)
-            .map_err(CoreServiceError::BundleFailure)
+    ) -> Result<Vec<u8>, CoreServiceError> {
+        self.inner
+            .log_consistency_proof(from_log_length, to_log_length)
+            .await
     }
 
     /// Constructs log inclusion proofs for the given entries at the given log tree root.
+    ///
+    /// Returns the encoded proof bundle.
     pub async fn log_inclusion_proofs(
         &self,
         log_length: RegistryLen,
         entries: &[RegistryIndex],
-    ) -> Result<LogProofBundle<Digest, LogLeaf>, CoreServiceError> {
-        let state = self.inner.state.read().await;
+    ) -> Result<Vec<u8>, CoreServiceError> {
+        self.inner.log_inclusion_proofs(log_length, entries).await
+    }
+
+    /// Constructs map inclusion proofs for the given entries at the given map tree root.
+    ///
+    /// Returns the encoded proof bundle.
+    pub async fn map_inclusion_proofs(
+        &self,
+        log_length: RegistryLen,
+        entries: &[RegistryIndex],
+    ) -> Result<Vec<u8>, CoreServiceError> {
+        self.inner.map_inclusion_proofs(log_length, entries).await
+    }
+
+    /// Signs and publishes an operator record with the given entries.
+    ///
+    /// The record is validated against the current operator log state and
+    /// appended to the log immediately; it is included in the next checkpoint.
+    pub async fn publish_operator_entries(
+        &self,
+        entries: Vec<operator::OperatorEntry>,
+    ) -> Result<RecordId, CoreServiceError> {
+        self.inner.publish_operator_record(entries).await
+    }
+
+    /// Publishes an operator record signed by a key other than the registry's.
+    ///
+    /// The record must follow the current head of the operator log and is
+    /// validated against the operator log state before it is appended.
+    pub async fn publish_signed_operator_record(
+        &self,
+        record: ProtoEnvelope<operator::OperatorRecord>,
+    ) -> Result<(RecordId, RegistryIndex), CoreServiceError> {
+        self.inner.publish_signed_operator_record(record).await
+    }
+
+    /// Rejects a pending package record with the given reason.
+    ///
+    /// The record may be waiting on content or queued for processing; if it
+    /// is still queued, the service skips it once dequeued. Clients checking
+    /// on the record are told it was rejected with the given reason.
+    pub async fn reject_package_record(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        reason: &str,
+    ) -> Result<(), CoreServiceError> {
+        self.inner
+            .reject_package_record(log_id, record_id, reason)
+            .await
+    }
+
+    /// Gets the data store associated with the transparency service.
+    pub fn store(&self) -> &dyn DataStore {
+        self.inner.store()
+    }
+
+    /// Sets how long a submission waits for room in a full queue before it
+    /// is rejected with [`CoreServiceError::Unavailable`].
+    ///
+    /// Defaults to [`DEFAULT_SUBMISSION_TIMEOUT`].
+    pub fn with_submission_timeout(mut self, timeout: Duration) -> Self {
+        self.submission_timeout = timeout;
+        self
+    }
+
+    /// Reserves a slot in the queue of records waiting to be processed.
+    ///
+    /// Callers should reserve a slot before storing a record so that a full
+    /// queue rejects the record up front rather than leaving it unprocessed.
+    ///
+    /// Returns [`CoreServiceError::Unavailable`] if the queue has no room
+    /// within the submission timeout or the service has stopped unexpectedly.
+    pub async fn reserve_submission(&self) -> Result<SubmissionPermit, CoreServiceError> {
+        let permit = match self.submit_entry_tx.clone().try_reserve_owned() {
+            Ok(permit) => Some(permit),
+            // Wait for room in a full queue until the deadline
+            Err(TrySendError::Full(tx)) => {
+                match tokio::time::timeout(self.submission_timeout, tx.reserve_owned()).await {
+                    Ok(permit) => permit.ok(),
+                    Err(_) => {
+                        self.queue_counters.rejected.fetch_add(1, Ordering::Relaxed);
+                        tracing::warn!(
+                            "rejecting submission: the queue of {capacity} record(s) is full",
+                            capacity = self.submit_entry_tx.max_capacity()
+                        );
+                        return Err(CoreServiceError::Unavailable);
+                    }
+                }
+            }
+            Err(TrySendError::Closed(_)) => None,
+        };
+
+        match permit {
+            Some(permit) => Ok(SubmissionPermit {
+                permit,
+                counters: self.queue_counters.clone(),
+                metrics: self.inner.metrics().clone(),
+            }),
+            None if self.shutdown.is_cancelled() => Err(CoreServiceError::ShuttingDown),
+            None => {
+                tracing::error!("rejecting submission: the service has stopped");
+                Err(CoreServiceError::Unavailable)
+            }
+        }
+    }
+
+    /// Gets the metrics recorded by the service.
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        self.inner.metrics()
+    }
+
+    /// Subscribes to the identifiers of checkpoints issued by the service.
+    ///
+    /// The receiver is notified each time a checkpoint with a new identifier is
+    /// stored; its value is `None` until the first checkpoint after the
+    /// service started is stored.
+    pub fn subscribe_checkpoints(&self) -> watch::Receiver<Option<AnyHash>> {
+        self.inner.subscribe_checkpoints()
+    }
+
+    /// Gets the current state of the queue of records waiting to be processed.
+    pub fn submission_queue(&self) -> SubmissionQueueResponse {
+        let capacity = self.submit_entry_tx.max_capacity();
+        SubmissionQueueResponse {
+            capacity: capacity as u64,
+            depth: (capacity - self.submit_entry_tx.capacity()) as u64,
+            submitted: self.queue_counters.submitted.load(Ordering::Relaxed),
+            rejected: self.queue_counters.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A record of an upstream registry replicated by a mirror.
+pub enum MirroredRecord {
+    /// A record of the operator log.
+    Operator(ProtoEnvelope<operator::OperatorRecord>),
+    /// A record of a package log.
+    Package {
+        /// The log of the package.
+        log_id: LogId,
+        /// The name of the package.
+        name: PackageName,
+        /// The record.
+        record: ProtoEnvelope<package::PackageRecord>,
+    },
+}
+
+impl MirroredRecord {
+    fn log_leaf(&self, operator_log_id: &LogId, algorithm: HashAlgorithm) -> LogLeaf {
+        match self {
+            Self::Operator(record) => LogLeaf {
+                log_id: operator_log_id.clone(),
+                record_id: RecordId::operator_record_for(algorithm, record),
+            },
+            Self::Package { log_id, record, .. } => LogLeaf {
+                log_id: log_id.clone(),
+                record_id: RecordId::package_record_for(algorithm, record),
+            },
+        }
+    }
+}
+
+// The settings the service is started with.
+struct Settings {
+    operator_key: PrivateKey,
+    next_operator_key: Option<PrivateKey>,
+    namespaces: Option<Vec<(String, operator::NamespaceState)>>,
+    store: Arc<dyn DataStore>,
+    checkpoint_policy: CheckpointPolicy,
+    max_checkpoint_interval: Option<Duration>,
+    checkpoint_validity: Option<Duration>,
+    audit_log: Option<Arc<dyn AuditLog>>,
+    mirror: bool,
+}
+
+// The operations of the service that depend on the registry's hash algorithm.
+#[axum::async_trait]
+trait Transparency: Send + Sync {
+    fn hash_algorithm(&self) -> HashAlgorithm;
+
+    fn store(&self) -> &dyn DataStore;
+
+    fn metrics(&self) -> &Arc<Metrics>;
+
+    fn subscribe_checkpoints(&self) -> watch::Receiver<Option<AnyHash>>;
+
+    async fn log_length(&self) -> RegistryLen;
+
+    async fn replicate(
+        &self,
+        records: Vec<MirroredRecord>,
+        checkpoint: SerdeEnvelope<TimestampedCheckpoint>,
+    ) -> Result<(), CoreServiceError>;
+
+    async fn log_consistency_proof(
+        &self,
+        from_log_length: RegistryLen,
+        to_log_length: RegistryLen,
+    ) -> Result<Vec<u8>, CoreServiceError>;
+
+    async fn log_inclusion_proofs(
+        &self,
+        log_length: RegistryLen,
+        entries: &[RegistryIndex],
+    ) -> Result<Vec<u8>, CoreServiceError>;
+
+    async fn map_inclusion_proofs(
+        &self,
+        log_length: RegistryLen,
+        entries: &[RegistryIndex],
+    ) -> Result<Vec<u8>, CoreServiceError>;
+
+    async fn publish_operator_record(
+        &self,
+        entries: Vec<operator::OperatorEntry>,
+    ) -> Result<RecordId, CoreServiceError>;
+
+    async fn publish_signed_operator_record(
+        &self,
+        record: ProtoEnvelope<operator::OperatorRecord>,
+    ) -> Result<(RecordId, RegistryIndex), CoreServiceError>;
+
+    async fn reject_package_record(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        reason: &str,
+    ) -> Result<(), CoreServiceError>;
+}
+
+#[axum::async_trait]
+impl<Digest: SupportedDigest> Transparency for Inner<Digest> {
+    fn hash_algorithm(&self) -> HashAlgorithm {
+        Digest::ALGORITHM
+    }
+
+    fn store(&self) -> &dyn DataStore {
+        self.store.as_ref()
+    }
+
+    fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    fn subscribe_checkpoints(&self) -> watch::Receiver<Option<AnyHash>> {
+        self.checkpoints.subscribe()
+    }
+
+    async fn log_length(&self) -> RegistryLen {
+        self.state.read().await.log.length() as RegistryLen
+    }
+
+    // Verifies, validates, and commits records replicated from an upstream
+    // registry, then stores the upstream's checkpoint.
+    async fn replicate(
+        &self,
+        records: Vec<MirroredRecord>,
+        checkpoint: SerdeEnvelope<TimestampedCheckpoint>,
+    ) -> Result<(), CoreServiceError> {
+        let mut state = self.state.write().await;
+        let operator_log_id = LogId::operator_log::<Digest>();
+        let leaves = records
+            .iter()
+            .map(|record| record.log_leaf(&operator_log_id, Digest::ALGORITHM))
+            .collect::<Vec<_>>();
+
+        // Compute the roots on a copy of the log so that records not
+        // included in the upstream checkpoint are never stored
+        let mut log = state.log.clone();
+        let mut map = state.map.clone();
+        for leaf in &leaves {
+            log.push(leaf);
+            map = map.insert(
+                leaf.log_id.clone(),
+                MapLeaf {
+                    record_id: leaf.record_id.clone(),
+                },
+            );
+        }
+
+        let log_checkpoint = log.checkpoint();
+        let computed = Checkpoint {
+            log_length: log_checkpoint.length() as RegistryLen,
+            log_root: log_checkpoint.root().into(),
+            map_root: map.root().into(),
+        };
+        if computed != checkpoint.as_ref().checkpoint {
+            return Err(CoreServiceError::MirrorCheckpointMismatch(
+                checkpoint.as_ref().checkpoint.log_length,
+            ));
+        }
+
+        let mut latest = self.latest_checkpoint.lock().await;
+        if let Some(latest) = latest.as_ref() {
+            check_checkpoint_monotonicity(latest, checkpoint.as_ref())?;
+        }
+
+        let mut operator = match self.store.get_operator_state(&operator_log_id).await {
+            Ok(operator) => operator,
+            Err(DataStoreError::LogNotFound(_)) => Default::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        for (record, leaf) in records.into_iter().zip(leaves.clone()) {
+            match record {
+                MirroredRecord::Operator(record) => {
+                    self.commit_operator_record(&mut state, operator, record)
+                        .await?;
+                    operator = self.store.get_operator_state(&operator_log_id).await?;
+                }
+                MirroredRecord::Package {
+                    log_id,
+                    name,
+                    record,
+                } => {
+                    let initial = record.as_ref().prev.is_none();
+                    let registry_index = state.log.length() as RegistryIndex;
+                    self.store
+                        .store_package_record(
+                            &log_id,
+                            &name,
+                            &leaf.record_id,
+                            &record,
+                            &Default::default(),
+                        )
+                        .await?;
+                    self.store
+                        .commit_package_record(
+                            &log_id,
+                            &leaf.record_id,
+                            registry_index,
+                            &|record, _| check_publish_permission(&operator, &name, record),
+                        )
+                        .await?;
+
+                    state.push_entry(leaf);
+                    self.metrics.record_submitted();
+                    self.metrics.record_validated(initial);
+                }
+            }
+        }
+
+        self.store
+            .verify_timestamped_checkpoint_signature(&operator_log_id, &checkpoint)
+            .await?;
+
+        // Snapshot the map at the checkpoint for map inclusion proofs
+        let checkpoint_id: AnyHash = Hash::<Digest>::of(state.checkpoint()).into();
+        let timestamped = checkpoint.as_ref().clone();
+        self.store
+            .store_checkpoint(&checkpoint_id, checkpoint, &leaves)
+            .await?;
+        if latest.as_ref().map(|l| l.checkpoint.log_length)
+            != Some(timestamped.checkpoint.log_length)
+        {
+            self.metrics.checkpoint_issued();
+        }
+        *latest = Some(timestamped);
+
+        self.notify_checkpoint(checkpoint_id);
+        Ok(())
+    }
+
+    async fn log_consistency_proof(
+        &self,
+        from_log_length: RegistryLen,
+        to_log_length: RegistryLen,
+    ) -> Result<Vec<u8>, CoreServiceError> {
+        let state = self.state.read().await;
+
+        let proof = state.log.prove_consistency(from_log_length, to_log_length);
+        let bundle = LogProofBundle::bundle(vec![proof], vec![], &state.log)
+            .map_err(CoreServiceError::BundleFailure)?;
+        Ok(bundle.encode())
+    }
+
+    async fn log_inclusion_proofs(
+        &self,
+        log_length: RegistryLen,
+        entries: &[RegistryIndex],
+    ) -> Result<Vec<u8>, CoreServiceError> {
+        let state = self.state.read().await;
 
         let proofs = entries
             .iter()
@@ -105,16 +802,17 @@ impl<Digest: SupportedDigest> CoreService<Digest> {
             })
             .collect::<Result<Vec<_>, CoreServiceError>>()?;
 
-        LogProofBundle::bundle(vec![], proofs, &state.log).map_err(CoreServiceError::BundleFailure)
+        let bundle = LogProofBundle::bundle(vec![], proofs, &state.log)
+            .map_err(CoreServiceError::BundleFailure)?;
+        Ok(bundle.encode())
     }
 
-    /// Constructs map inclusion proofs for the given entries at the given map tree root.
-    pub async fn map_inclusion_proofs(
+    async fn map_inclusion_proofs(
         &self,
         log_length: RegistryLen,
         entries: &[RegistryIndex],
-    ) -> Result<MapProofBundle<Digest, LogId, MapLeaf>, CoreServiceError> {
-        let state = self.inner.state.read().await;
+    ) -> Result<Vec<u8>, CoreServiceError> {
+        let state = self.state.read().await;
 
         let (map_root, map) = state
             .map_index
@@ -122,7 +820,6 @@ impl<Digest: SupportedDigest> CoreService<Digest> {
             .ok_or_else(|| CoreServiceError::CheckpointNotFound(log_length))?;
 
         let indexes = self
-            .inner
             .store
             .get_log_leafs_with_registry_index(entries)
             .await
@@ -152,72 +849,215 @@ impl<Digest: SupportedDigest> CoreService<Digest> {
             })
             .collect::<Result<Vec<_>, CoreServiceError>>()?;
 
-        Ok(MapProofBundle::bundle(proofs))
+        Ok(MapProofBundle::bundle(proofs).encode())
     }
 
-    /// Gets the data store associated with the transparency service.
-    pub fn store(&self) -> &dyn DataStore {
-        self.inner.store.as_ref()
+    // Signs, validates, and commits a new operator record.
+    async fn publish_operator_record(
+        &self,
+        entries: Vec<operator::OperatorEntry>,
+    ) -> Result<RecordId, CoreServiceError> {
+        // Holding the state lock orders the record with package records being processed
+        let mut state = self.state.write().await;
+
+        let log_id = LogId::operator_log::<Digest>();
+        let operator = self.store.get_operator_state(&log_id).await?;
+        let head = operator
+            .head()
+            .clone()
+            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
+
+        let record = operator::OperatorRecord {
+            prev: Some(head.digest),
+            version: operator::OPERATOR_RECORD_VERSION,
+            timestamp: SystemTime::now().max(head.timestamp),
+            entries,
+        };
+        let timestamp = record
+            .timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let operator_key = self.current_operator_key(&operator, timestamp);
+        let signed_record = ProtoEnvelope::signed_contents(&operator_key, record)?;
+        let (record_id, _) = self
+            .commit_operator_record(&mut state, operator, signed_record)
+            .await?;
+        Ok(record_id)
     }
 
-    /// Submits a package record to be processed.
-    pub async fn submit_package_record(&self, log_id: LogId, record_id: RecordId) {
-        self.submit_entry_tx
-            .send(LogLeaf { log_id, record_id })
+    // Validates and commits an operator record signed by another key.
+    async fn publish_signed_operator_record(
+        &self,
+        record: ProtoEnvelope<operator::OperatorRecord>,
+    ) -> Result<(RecordId, RegistryIndex), CoreServiceError> {
+        let mut state = self.state.write().await;
+
+        let log_id = LogId::operator_log::<Digest>();
+        let operator = self.store.get_operator_state(&log_id).await?;
+        if record.as_ref().prev != operator.head().as_ref().map(|h| h.digest.clone()) {
+            return Err(CoreServiceError::OperatorHeadMismatch);
+        }
+
+        self.commit_operator_record(&mut state, operator, record)
             .await
-            .unwrap()
+    }
+
+    // Rejects a pending package record on behalf of the operator.
+    async fn reject_package_record(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        reason: &str,
+    ) -> Result<(), CoreServiceError> {
+        // Holding the state lock prevents the record from being committed concurrently
+        let _state = self.state.write().await;
+
+        self.store
+            .reject_package_record(log_id, record_id, reason)
+            .await?;
+        record_state_changed(log_id, record_id, "rejected");
+        self.metrics.record_rejected();
+        Ok(())
     }
 }
 
 struct Inner<Digest: SupportedDigest> {
     // Operator signing key
-    operator_key: PrivateKey,
+    operator_key: Mutex<Arc<PrivateKey>>,
+
+    // The key the operator key is being rotated to; it replaces the operator
+    // key once the operator log permits it to sign checkpoints.
+    next_operator_key: Mutex<Option<Arc<PrivateKey>>>,
 
     // DataStore persists transparency state.
-    store: Box<dyn DataStore>,
+    store: Arc<dyn DataStore>,
+
+    // Audit log of processed records and issued checkpoints.
+    audit_log: Option<Arc<dyn AuditLog>>,
+
+    // How long a signed checkpoint is valid for, if checkpoints expire.
+    checkpoint_validity: Option<Duration>,
+
+    // Metrics of processed records and issued checkpoints.
+    metrics: Arc<Metrics>,
+
+    // Publishes the identifier of the latest stored checkpoint.
+    checkpoints: watch::Sender<Option<AnyHash>>,
+
+    // The latest stored checkpoint; new checkpoints may not regress from it.
+    latest_checkpoint: tokio::sync::Mutex<Option<TimestampedCheckpoint>>,
 
     // In-memory transparency state.
     state: RwLock<State<Digest>>,
+
+    // Records validated since the last issued checkpoint, along with the span
+    // they were processed in; guarded by the state lock.
+    unpublished: Mutex<Vec<(LogLeaf, Span)>>,
 }
 
 impl<Digest: SupportedDigest> Inner<Digest> {
+    // Initializes the service's state and spawns its state update task.
+    async fn start(
+        settings: Settings,
+        submit_entry_rx: mpsc::Receiver<Submission>,
+        shutdown: CancellationToken,
+    ) -> Result<(Arc<dyn Transparency>, JoinHandle<()>), CoreServiceError> {
+        let mut inner = Self {
+            operator_key: Mutex::new(Arc::new(settings.operator_key)),
+            next_operator_key: Mutex::new(settings.next_operator_key.map(Arc::new)),
+            store: settings.store,
+            audit_log: settings.audit_log,
+            checkpoint_validity: settings.checkpoint_validity,
+            metrics: Default::default(),
+            checkpoints: watch::channel(None).0,
+            latest_checkpoint: Default::default(),
+            state: Default::default(),
+            unpublished: Default::default(),
+        };
+        inner
+            .initialize(settings.namespaces, settings.mirror)
+            .await?;
+
+        // A mirror never processes submissions; its task only waits to stop
+        if settings.mirror {
+            let handle = tokio::spawn(shutdown.cancelled_owned());
+            return Ok((Arc::new(inner), handle));
+        }
+
+        if let Some(interval) = settings.max_checkpoint_interval {
+            inner.declare_max_checkpoint_interval(interval).await?;
+        }
+
+        // Expiring checkpoints are re-signed at least twice per validity
+        // window so that the latest checkpoint never expires
+        let mut checkpoint_policy = settings.checkpoint_policy;
+        if let Some(validity) = settings.checkpoint_validity {
+            checkpoint_policy.interval = checkpoint_policy.interval.min(validity / 2);
+        }
+
+        let inner = Arc::new(inner);
+        let handle = tokio::spawn(inner.clone().process_state_updates(
+            submit_entry_rx,
+            checkpoint_policy,
+            shutdown,
+        ));
+        Ok((inner, handle))
+    }
+
     // Load state from DataStore or initialize empty state, returning any
     // entries that are not yet part of a checkpoint.
+    //
+    // A mirror without records starts with an empty log to be replicated.
     async fn initialize(
         &mut self,
         namespaces: Option<Vec<(String, operator::NamespaceState)>>,
+        mirror: bool,
     ) -> Result<(), CoreServiceError> {
         tracing::debug!("Initializing CoreService");
 
-        let published = self.store.get_all_validated_records().await?.peekable();
-        pin_mut!(published);
+        let empty = {
+            let published = self.store.get_all_validated_records().await?.peekable();
+            pin_mut!(published);
+            published.as_mut().peek().await.is_none()
+        };
+
+        if empty && mirror {
+            tracing::debug!("No existing records; waiting to replicate the upstream registry");
+            return Ok(());
+        }
 
         // If there are no published records, initialize a new state
-        if published.as_mut().peek().await.is_none() {
+        if empty {
             tracing::debug!("No existing records; initializing new state");
             return self.initialize_new(namespaces).await;
         }
 
-        // Reconstruct internal state from previously-stored data
-        let mut checkpoints = self.store.get_all_checkpoints().await?;
-        let mut checkpoints_by_len: IndexMap<RegistryLen, Checkpoint> = Default::default();
-        while let Some(checkpoint) = checkpoints.next().await {
-            let checkpoint = checkpoint?.checkpoint;
-            checkpoints_by_len.insert(checkpoint.log_length, checkpoint);
-        }
-
-        let state = self.state.get_mut();
-        while let Some(entry) = published.next().await {
-            state.push_entry(entry?);
-            if let Some(stored_checkpoint) =
-                checkpoints_by_len.get(&(state.log.length() as RegistryLen))
-            {
-                // Validate stored checkpoint (and update internal state as a side-effect)
-                let computed_checkpoint = state.checkpoint();
-                assert!(stored_checkpoint == &computed_checkpoint);
+        // The operator log is identified by the registry's hash algorithm
+        match self
+            .store
+            .get_operator_state(&LogId::operator_log::<Digest>())
+            .await
+        {
+            Ok(_) => {}
+            Err(DataStoreError::LogNotFound(_)) => {
+                return Err(CoreServiceError::InitializationFailure(format!(
+                    "the registry was not initialized with the `{algorithm}` hash algorithm",
+                    algorithm = Digest::ALGORITHM
+                )));
             }
+            Err(e) => return Err(e.into()),
         }
 
+        // Reconstruct internal state from previously-stored data
+        *self.state.get_mut() = State::from_datastore(self.store.as_ref(), &self.metrics).await?;
+        // Records may have been stored without a checkpoint if the registry
+        // stopped before its first checkpoint was issued
+        *self.latest_checkpoint.get_mut() = match self.store.get_latest_checkpoint().await {
+            Ok(checkpoint) => Some(checkpoint.into_contents()),
+            Err(DataStoreError::NoCheckpoints) => None,
+            Err(e) => return Err(e.into()),
+        };
         Ok(())
     }
 
@@ -230,7 +1070,7 @@ impl<Digest: SupportedDigest> Inner<Digest> {
         // Construct operator init record
         let init = operator::OperatorEntry::Init {
             hash_algorithm: Digest::ALGORITHM,
-            key: self.operator_key.public_key(),
+            key: self.operator_key.get_mut().unwrap().public_key(),
         };
         let entries = if let Some(namespaces) = namespaces {
             let mut entries = Vec::with_capacity(1 + namespaces.len());
@@ -260,7 +1100,8 @@ impl<Digest: SupportedDigest> Inner<Digest> {
             entries,
         };
         let signed_init_record =
-            ProtoEnvelope::signed_contents(&self.operator_key, init_record).unwrap();
+            ProtoEnvelope::signed_contents(self.operator_key.get_mut().unwrap(), init_record)
+                .unwrap();
         let log_id = LogId::operator_log::<Digest>();
         let record_id = RecordId::operator_record::<Digest>(&signed_init_record);
 
@@ -286,32 +1127,168 @@ impl<Digest: SupportedDigest> Inner<Digest> {
         Ok(())
     }
 
+    // Declares the maximum checkpoint interval in the operator log, rounded up
+    // to whole seconds, unless it is already declared.
+    async fn declare_max_checkpoint_interval(
+        &self,
+        interval: Duration,
+    ) -> Result<(), CoreServiceError> {
+        let interval =
+            Duration::from_secs(interval.as_secs() + u64::from(interval.subsec_nanos() > 0));
+        let state = self
+            .store
+            .get_operator_state(&LogId::operator_log::<Digest>())
+            .await?;
+        if state.max_checkpoint_interval() == Some(interval) {
+            return Ok(());
+        }
+
+        tracing::info!(
+            "declaring a maximum checkpoint interval of {secs} second(s)",
+            secs = interval.as_secs()
+        );
+        self.publish_operator_record(vec![operator::OperatorEntry::SetMaxCheckpointInterval {
+            interval,
+        }])
+        .await?;
+        Ok(())
+    }
+
+    // Validates the record against the operator log state and commits it.
+    async fn commit_operator_record(
+        &self,
+        state: &mut State<Digest>,
+        operator: operator::LogState,
+        record: ProtoEnvelope<operator::OperatorRecord>,
+    ) -> Result<(RecordId, RegistryIndex), CoreServiceError> {
+        let log_id = LogId::operator_log::<Digest>();
+        let record_id = RecordId::operator_record::<Digest>(&record);
+
+        // Validate before storing so that an invalid record is never stored
+        operator.validate(&record).map_err(DataStoreError::from)?;
+
+        let registry_index = state.log.length() as RegistryIndex;
+        self.store
+            .store_operator_record(&log_id, &record_id, &record)
+            .await?;
+        self.store
+            .commit_operator_record(&log_id, &record_id, registry_index)
+            .await?;
+
+        state.push_entry(LogLeaf {
+            log_id,
+            record_id: record_id.clone(),
+        });
+
+        Ok((record_id, registry_index))
+    }
+
     // Runs the service's state update loop.
+    //
+    // Submissions are processed in order by this task alone, so each
+    // checkpoint includes exactly the records validated since the last one.
     async fn process_state_updates(
         self: Arc<Self>,
-        mut submit_entry_rx: mpsc::Receiver<LogLeaf>,
-        checkpoint_interval: Duration,
+        mut submit_entry_rx: mpsc::Receiver<Submission>,
+        policy: CheckpointPolicy,
+        shutdown: CancellationToken,
     ) {
-        let mut checkpoint = self
-            .store
-            .get_latest_checkpoint()
-            .await
-            .unwrap()
-            .into_contents()
-            .checkpoint;
+        // If the service cannot resume from the latest checkpoint, stop
+        // processing; further submissions fail with `ShuttingDown`
+        let mut checkpoint = match self.store.get_latest_checkpoint().await {
+            Ok(checkpoint) => checkpoint.into_contents().checkpoint,
+            // Without a checkpoint, the first one issued covers the whole log
+            Err(DataStoreError::NoCheckpoints) => Checkpoint {
+                log_root: Hash::<Digest>::default().into(),
+                log_length: 0,
+                map_root: Hash::<Digest>::default().into(),
+            },
+            Err(e) => {
+                tracing::error!("failed to load the latest checkpoint: {e}");
+                return;
+            }
+        };
 
-        let mut checkpoint_interval = tokio::time::interval(checkpoint_interval);
-        checkpoint_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first checkpoint is due immediately so that records stored
+        // before a restart are checkpointed
+        let mut deadline = Instant::now();
+        // A submission received while checking whether the queue is empty
+        let mut queued = None;
 
         loop {
-            tokio::select! {
-                entry = submit_entry_rx.recv() => match entry {
-                    Some(entry) => self.process_package_entry(&entry).await,
-                    None => break, // Channel closed
+            let submission = match queued.take() {
+                Some(submission) => Some(submission),
+                None => tokio::select! {
+                    entry = submit_entry_rx.recv() => match entry {
+                        Some(entry) => Some(entry),
+                        None => break, // Channel closed
+                    },
+                    _ = tokio::time::sleep_until(deadline) => None,
+                    _ = shutdown.cancelled() => break,
                 },
-                _ = checkpoint_interval.tick() => self.update_checkpoint(&mut checkpoint).await,
+            };
+
+            let due = match submission {
+                Some(submission) => {
+                    self.process_submission(submission).await;
+                    let batch = self.unpublished.lock().unwrap().len();
+                    if policy.is_full(batch) {
+                        true
+                    } else if policy.is_ready(batch) {
+                        // Checkpoint the batch early once the burst of
+                        // submissions ends
+                        match submit_entry_rx.try_recv() {
+                            Ok(submission) => {
+                                queued = Some(submission);
+                                false
+                            }
+                            Err(_) => true,
+                        }
+                    } else {
+                        false
+                    }
+                }
+                None => true,
+            };
+
+            // A steady stream of submissions may keep the deadline from
+            // being polled
+            if due || Instant::now() >= deadline {
+                self.update_checkpoint(&mut checkpoint).await;
+                deadline = Instant::now() + policy.interval;
+                self.metrics.checkpoint_scheduled(policy.interval);
+            }
+        }
+
+        // Stop accepting submissions, then process what was already queued so
+        // that accepted records are not left pending across a restart
+        submit_entry_rx.close();
+        let mut drained = 0;
+        while let Some(entry) = submit_entry_rx.recv().await {
+            self.process_submission(entry).await;
+            drained += 1;
+            if policy.is_full(self.unpublished.lock().unwrap().len()) {
+                self.update_checkpoint(&mut checkpoint).await;
             }
         }
+
+        if drained > 0 {
+            tracing::info!("processed {drained} queued record(s) while shutting down");
+        }
+
+        self.update_checkpoint(&mut checkpoint).await;
+    }
+
+    // Processes a submitted package record in a span following its submission
+    async fn process_submission(&self, submission: Submission) {
+        let Submission { leaf, span } = submission;
+        let span = tracing::info_span!(
+            parent: &span,
+            "process_record",
+            log_id = %leaf.log_id,
+            record_id = %leaf.record_id
+        );
+        self.process_package_entry(&leaf).instrument(span).await
     }
 
     // Processes a submitted package entry
@@ -321,56 +1298,235 @@ impl<Digest: SupportedDigest> Inner<Digest> {
         let mut state = self.state.write().await;
         let LogLeaf { log_id, record_id } = entry;
 
+        let record = self.store.get_package_record(log_id, record_id).await.ok();
+        let key_id = record
+            .as_ref()
+            .map(|record| record.envelope.key_id().clone());
+        let initial = record.map_or(false, |record| record.envelope.as_ref().prev.is_none());
+
         // Validate and commit the package entry to the store
         let registry_index = state.log.length() as RegistryIndex;
-        let commit_res = self
-            .store
-            .commit_package_record(log_id, record_id, registry_index)
-            .await;
+        let package = audit::package_name(self.store.as_ref(), log_id).await;
+        let commit_res = match (
+            &package,
+            self.store
+                .get_operator_state(&LogId::operator_log::<Digest>())
+                .await,
+        ) {
+            (Some(name), Ok(operator)) => {
+                self.store
+                    .commit_package_record(log_id, record_id, registry_index, &|record, _| {
+                        check_publish_permission(&operator, name, record)
+                    })
+                    .await
+            }
+            (None, _) => Err(DataStoreError::LogNotFound(log_id.clone())),
+            (_, Err(e)) => Err(e),
+        };
+
+        let mut audit_entry = AuditEntry::new(AuditOperation::RecordProcessed);
+        audit_entry.log_id = Some(log_id.clone());
+        audit_entry.package = package;
+        audit_entry.record_id = Some(record_id.clone());
+        audit_entry.key_id = key_id;
 
         if let Err(err) = commit_res {
-            match err {
+            match &err {
+                DataStoreError::RecordNotPending(_) => {
+                    // The record was rejected by the operator while it was queued
+                    tracing::debug!("record `{record_id}` is no longer pending; skipping");
+                    return;
+                }
                 DataStoreError::Rejection(_)
                 | DataStoreError::OperatorValidationFailed(_)
                 | DataStoreError::PackageValidationFailed(_) => {
                     // The record failed to validate and was rejected; do not include it in the next checkpoint
                     tracing::debug!("record `{record_id}` rejected: {err:?}");
+                    record_state_changed(log_id, record_id, "rejected");
+                    self.metrics.record_rejected();
                 }
                 e => {
                     // TODO: this should be made more robust with a proper reliable message
                     // queue with retry logic
                     tracing::error!("failed to validate package record `{record_id}`: {e}");
+                    self.metrics.record_failed();
                 }
             }
+
+            audit::append(self.audit_log.as_deref(), audit_entry.failed(err)).await;
             return;
         }
 
         state.push_entry(entry.clone());
+        self.metrics.record_validated(initial);
+        record_state_changed(log_id, record_id, "validated");
+        {
+            let mut unpublished = self.unpublished.lock().unwrap();
+            unpublished.push((entry.clone(), Span::current()));
+            self.metrics.set_checkpoint_batch(unpublished.len());
+        }
+        drop(state);
+
+        audit::append(self.audit_log.as_deref(), audit_entry).await;
     }
 
     // Store a checkpoint including the given new entries
     async fn update_checkpoint(&self, checkpoint: &mut Checkpoint) {
-        {
+        let (issued, mut included) = {
             // Recalculate the checkpoint if necessary
             let mut state = self.state.write().await;
-            if state.log.length() as RegistryLen != checkpoint.log_length {
+            let issued = if state.log.length() as RegistryLen != checkpoint.log_length {
                 *checkpoint = state.checkpoint();
                 tracing::debug!("Updating to checkpoint {checkpoint:?}");
+                self.metrics.checkpoint_issued();
+                true
+            } else {
+                false
+            };
+
+            // Every record validated so far is included in the checkpoint
+            self.metrics.set_checkpoint_batch(0);
+            (
+                issued,
+                std::mem::take(&mut *self.unpublished.lock().unwrap()),
+            )
+        };
+
+        let res = self
+            .sign_and_store_checkpoint(checkpoint.clone(), &mut included)
+            .await;
+        match &res {
+            Ok(()) => {
+                for (LogLeaf { log_id, record_id }, span) in included {
+                    span.in_scope(|| record_state_changed(&log_id, &record_id, "published"));
+                }
+            }
+            Err(err) => {
+                tracing::error!("Error storing checkpoint {checkpoint:?}: {err:?}");
+
+                // The records are published once the checkpoint is stored
+                let mut unpublished = self.unpublished.lock().unwrap();
+                unpublished.splice(0..0, included);
+                self.metrics.set_checkpoint_batch(unpublished.len());
             }
         }
 
-        if let Err(err) = self.sign_and_store_checkpoint(checkpoint.clone()).await {
-            tracing::error!("Error storing checkpoint {checkpoint:?}: {err:?}");
+        if issued {
+            let mut entry = AuditEntry::new(AuditOperation::CheckpointIssued);
+            entry.log_length = Some(checkpoint.log_length);
+            if let Err(err) = res {
+                entry = entry.failed(err);
+            }
+
+            audit::append(self.audit_log.as_deref(), entry).await;
         }
     }
 
-    async fn sign_and_store_checkpoint(&self, checkpoint: Checkpoint) -> anyhow::Result<()> {
-        let checkpoint_id = Hash::<Digest>::of(&checkpoint).into();
-        let timestamped = TimestampedCheckpoint::now(checkpoint.clone())?;
-        let signed = SerdeEnvelope::signed_contents(&self.operator_key, timestamped)?;
-        self.store.store_checkpoint(&checkpoint_id, signed).await?;
+    async fn sign_and_store_checkpoint(
+        &self,
+        checkpoint: Checkpoint,
+        participants: &mut Vec<(LogLeaf, Span)>,
+    ) -> anyhow::Result<()> {
+        let checkpoint_id: AnyHash = Hash::<Digest>::of(&checkpoint).into();
+        let mut timestamped = TimestampedCheckpoint::now(checkpoint)?;
+        if let Some(validity) = self.checkpoint_validity {
+            timestamped = timestamped.with_validity(validity);
+        }
+
+        // Refuse to sign a checkpoint that goes backwards, such as after the
+        // registry's clock is rewound
+        let mut head = self.latest_checkpoint.lock().await;
+        if let Some(latest) = head.as_ref() {
+            check_checkpoint_monotonicity(latest, &timestamped)?;
+        }
+
+        let operator = self
+            .store
+            .get_operator_state(&LogId::operator_log::<Digest>())
+            .await?;
+        let operator_key = self.current_operator_key(&operator, timestamped.timestamp);
+        let signed = SerdeEnvelope::signed_contents(&operator_key, timestamped.clone())?;
+        self.store_checkpoint(&checkpoint_id, signed, participants)
+            .await?;
+        *head = Some(timestamped);
+
+        self.notify_checkpoint(checkpoint_id);
         Ok(())
     }
+
+    // Stores a signed checkpoint, retrying without any participant the data
+    // store does not know as a validated record.
+    async fn store_checkpoint(
+        &self,
+        checkpoint_id: &AnyHash,
+        signed: SerdeEnvelope<TimestampedCheckpoint>,
+        participants: &mut Vec<(LogLeaf, Span)>,
+    ) -> Result<(), DataStoreError> {
+        loop {
+            let leafs = participants
+                .iter()
+                .map(|(leaf, _)| leaf.clone())
+                .collect::<Vec<_>>();
+            let leaf = match self
+                .store
+                .store_checkpoint(checkpoint_id, signed.clone(), &leafs)
+                .await
+            {
+                Err(DataStoreError::UnknownParticipant(leaf)) => leaf,
+                res => return res,
+            };
+
+            tracing::error!(
+                log_id = %leaf.log_id,
+                record_id = %leaf.record_id,
+                "Excluding unknown participant from checkpoint {checkpoint_id}"
+            );
+            let len = participants.len();
+            participants.retain(|(l, _)| l != &leaf);
+            if participants.len() == len {
+                return Err(DataStoreError::UnknownParticipant(leaf));
+            }
+        }
+    }
+
+    // Gets the operator key, switching to the next operator key once the
+    // given operator log state permits it to sign checkpoints at the given
+    // time, in seconds since the Unix epoch.
+    fn current_operator_key(
+        &self,
+        operator: &operator::LogState,
+        timestamp: u64,
+    ) -> Arc<PrivateKey> {
+        let mut operator_key = self.operator_key.lock().unwrap();
+        let mut next_operator_key = self.next_operator_key.lock().unwrap();
+        if let Some(next) = next_operator_key.as_ref() {
+            let key_id = next.public_key().fingerprint();
+            if operator.key_has_permission_to_sign_checkpoints(&key_id, timestamp) {
+                tracing::info!(%key_id, "switching to the rotated operator key");
+                *operator_key = next_operator_key.take().unwrap();
+            }
+        }
+
+        operator_key.clone()
+    }
+
+    // Notifies subscribers of a stored checkpoint.
+    fn notify_checkpoint(&self, checkpoint_id: AnyHash) {
+        // Checkpoints are re-signed each interval; only notify of new ones
+        self.checkpoints.send_if_modified(|latest| {
+            if latest.as_ref() == Some(&checkpoint_id) {
+                return false;
+            }
+
+            *latest = Some(checkpoint_id);
+            true
+        });
+    }
+}
+
+/// Emits an event for a package record transitioning to the given state.
+pub(crate) fn record_state_changed(log_id: &LogId, record_id: &RecordId, state: &'static str) {
+    tracing::info!(%log_id, %record_id, state, "package record state changed");
 }
 
 type VerifiableMap<Digest> = Map<Digest, LogId, MapLeaf>;
@@ -389,6 +1545,52 @@ struct State<Digest: SupportedDigest> {
 }
 
 impl<Digest: SupportedDigest> State<Digest> {
+    // Rebuilds the state by replaying the validated records of the given
+    // store, verifying each stored checkpoint along the way.
+    //
+    // The package and record counts of the given metrics are restored from
+    // the replayed records.
+    async fn from_datastore(
+        store: &dyn DataStore,
+        metrics: &Metrics,
+    ) -> Result<Self, CoreServiceError> {
+        let mut checkpoints = store.get_all_checkpoints().await?;
+        let mut checkpoints_by_len: IndexMap<RegistryLen, Checkpoint> = Default::default();
+        while let Some(checkpoint) = checkpoints.next().await {
+            let checkpoint = checkpoint?.checkpoint;
+            checkpoints_by_len.insert(checkpoint.log_length, checkpoint);
+        }
+
+        let operator_log_id = LogId::operator_log::<Digest>();
+        let mut packages = HashSet::new();
+        let mut validated = 0;
+        let mut published = 0;
+
+        let mut state = Self::default();
+        let mut records = store.get_all_validated_records().await?;
+        while let Some(entry) = records.next().await {
+            let entry = entry?;
+            if entry.log_id != operator_log_id {
+                packages.insert(entry.log_id.clone());
+                validated += 1;
+            }
+
+            state.push_entry(entry);
+            if let Some(stored_checkpoint) =
+                checkpoints_by_len.get(&(state.log.length() as RegistryLen))
+            {
+                // Validate stored checkpoint (and update internal state as a side-effect)
+                let computed_checkpoint = state.checkpoint();
+                assert!(stored_checkpoint == &computed_checkpoint);
+                published += validated;
+                validated = 0;
+            }
+        }
+
+        metrics.restore(packages.len() as u64, validated, published);
+        Ok(state)
+    }
+
     fn push_entry(&mut self, log_leaf: LogLeaf) {
         let node = self.log.push(&log_leaf);
         self.leaf_index.push(node);
@@ -432,4 +1634,441 @@ pub enum CoreServiceError {
     DataStore(#[from] DataStoreError),
     #[error("initialization failed: {0}")]
     InitializationFailure(String),
+    #[error("the service is unavailable to process records; try again later")]
+    Unavailable,
+    #[error("the service is shutting down")]
+    ShuttingDown,
+    #[error("failed to sign record: {0}")]
+    SigningFailure(#[from] SignatureError),
+    #[error("the service task failed: {0}")]
+    TaskFailed(#[from] JoinError),
+    #[error("the record does not follow the head of the operator log")]
+    OperatorHeadMismatch,
+    #[error("the replicated records do not match the upstream checkpoint at log length `{0}`")]
+    MirrorCheckpointMismatch(RegistryLen),
+    #[error("refusing to sign checkpoint at log length `{to_log_length}` (timestamp {to_timestamp}) as it regresses from the latest checkpoint at log length `{from_log_length}` (timestamp {from_timestamp})")]
+    CheckpointRegression {
+        from_log_length: RegistryLen,
+        from_timestamp: u64,
+        to_log_length: RegistryLen,
+        to_timestamp: u64,
+    },
+}
+
+// Rejects a new checkpoint if its log length or timestamp is behind the
+// latest stored checkpoint.
+fn check_checkpoint_monotonicity(
+    latest: &TimestampedCheckpoint,
+    next: &TimestampedCheckpoint,
+) -> Result<(), CoreServiceError> {
+    if next.checkpoint.log_length < latest.checkpoint.log_length
+        || next.timestamp < latest.timestamp
+    {
+        return Err(CoreServiceError::CheckpointRegression {
+            from_log_length: latest.checkpoint.log_length,
+            from_timestamp: latest.timestamp,
+            to_log_length: next.checkpoint.log_length,
+            to_timestamp: next.timestamp,
+        });
+    }
+
+    Ok(())
+}
+
+// Rejects a package record if the operator log does not permit the signing
+// key to publish to the package, such as when its permission was revoked or
+// the package's namespace was reserved for other keys.
+fn check_publish_permission(
+    operator: &operator::LogState,
+    name: &PackageName,
+    record: &ProtoEnvelope<package::PackageRecord>,
+) -> Result<(), DataStoreError> {
+    let key_id = record.key_id();
+    if operator.key_has_permission_to_publish(key_id, name) == Some(false) {
+        return Err(DataStoreError::Rejection(format!(
+            "key `{key_id}` does not have permission to publish package `{name}`"
+        )));
+    }
+
+    if record.as_ref().prev.is_none() && !operator.key_may_initialize_package(key_id, name) {
+        return Err(DataStoreError::Rejection(format!(
+            "namespace `{namespace}` is reserved; key `{key_id}` may not initialize package `{name}`",
+            namespace = name.namespace()
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datastore::{MemoryDataStore, RecordStatus};
+    use warg_crypto::signing::generate_p256_pair;
+    use warg_protocol::{package, registry::PackageName};
+
+    #[tokio::test]
+    async fn it_rejects_submissions_when_the_queue_is_full() -> Result<(), CoreServiceError> {
+        let (_, operator_key) = generate_p256_pair();
+        let core = CoreService::start(
+            CoreServiceConfig::new(
+                operator_key,
+                HashAlgorithm::Sha256,
+                Arc::new(MemoryDataStore::default()),
+                CheckpointPolicy::new(Duration::from_secs(60)),
+            )
+            .with_submission_queue_depth(2),
+        )
+        .await?
+        .with_submission_timeout(Duration::from_millis(10));
+
+        let first = core.reserve_submission().await?;
+        let second = core.reserve_submission().await?;
+        assert!(matches!(
+            core.reserve_submission().await,
+            Err(CoreServiceError::Unavailable)
+        ));
+
+        let queue = core.submission_queue();
+        assert_eq!(queue.capacity, 2);
+        assert_eq!(queue.depth, 2);
+        assert_eq!(queue.rejected, 1);
+
+        // Releasing a reservation frees its slot
+        drop(first);
+        let _third = core.reserve_submission().await?;
+        assert_eq!(core.submission_queue().rejected, 1);
+
+        // A submission waits for a slot released before the deadline
+        let core = core.with_submission_timeout(Duration::from_secs(60));
+        let waiting = tokio::spawn({
+            let core = core.clone();
+            async move { core.reserve_submission().await.map(drop) }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(second);
+        waiting.await??;
+        assert_eq!(core.submission_queue().rejected, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_keeps_processing_after_unknown_log_submissions() -> Result<(), CoreServiceError> {
+        let (_, operator_key) = generate_p256_pair();
+        let core = CoreService::start(
+            CoreServiceConfig::new(
+                operator_key,
+                HashAlgorithm::Sha256,
+                Arc::new(MemoryDataStore::default()),
+                CheckpointPolicy::new(Duration::from_millis(10)),
+            )
+            .with_submission_queue_depth(2),
+        )
+        .await?;
+
+        let log_id = LogId::package_log::<Sha256>(&PackageName::new("test:unknown").unwrap());
+        let record_id = RecordId::from(AnyHash::from(Hash::<Sha256>::of("unknown")));
+        core.reserve_submission().await?.submit(log_id, record_id);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The submission is skipped rather than stopping the service
+        let checkpoint = core.store().get_latest_checkpoint().await?;
+        assert_eq!(checkpoint.as_ref().checkpoint.log_length, 1);
+        assert_eq!(core.submission_queue().depth, 0);
+        core.reserve_submission().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_drains_the_queue_on_shutdown() -> Result<(), CoreServiceError> {
+        let (_, operator_key) = generate_p256_pair();
+        let core = CoreService::start(
+            CoreServiceConfig::new(
+                operator_key,
+                HashAlgorithm::Sha256,
+                Arc::new(MemoryDataStore::default()),
+                CheckpointPolicy::new(Duration::from_secs(60)),
+            )
+            .with_submission_queue_depth(2),
+        )
+        .await?;
+
+        let log_id = LogId::package_log::<Sha256>(&PackageName::new("test:unknown").unwrap());
+        let record_id = RecordId::from(AnyHash::from(Hash::<Sha256>::of("unknown")));
+        core.reserve_submission().await?.submit(log_id, record_id);
+        core.shutdown().await?;
+
+        assert_eq!(core.submission_queue().depth, 0);
+        assert!(matches!(
+            core.reserve_submission().await,
+            Err(CoreServiceError::ShuttingDown)
+        ));
+
+        // Subsequent calls return immediately
+        core.shutdown().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_rejects_records_signed_by_revoked_keys() -> Result<(), CoreServiceError> {
+        let (_, operator_key) = generate_p256_pair();
+        let core = CoreService::start(
+            CoreServiceConfig::new(
+                operator_key,
+                HashAlgorithm::Sha256,
+                Arc::new(MemoryDataStore::default()),
+                CheckpointPolicy::new(Duration::from_secs(60)),
+            )
+            .with_submission_queue_depth(2),
+        )
+        .await?;
+
+        let (public_key, signing_key) = generate_p256_pair();
+        core.publish_operator_entries(vec![operator::OperatorEntry::GrantFlat {
+            key: public_key.clone(),
+            permissions: vec![operator::Permission::Publish],
+        }])
+        .await?;
+        core.publish_operator_entries(vec![operator::OperatorEntry::RevokeFlat {
+            key_id: public_key.fingerprint(),
+            permissions: vec![operator::Permission::Publish],
+        }])
+        .await?;
+
+        let name = PackageName::new("test:revoked").unwrap();
+        let log_id = LogId::package_log::<Sha256>(&name);
+        let record = ProtoEnvelope::signed_contents(
+            &signing_key,
+            package::PackageRecord {
+                prev: None,
+                version: package::PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: vec![package::PackageEntry::Init {
+                    hash_algorithm: Sha256::ALGORITHM,
+                    key: public_key,
+                }],
+            },
+        )?;
+        let record_id = RecordId::package_record::<Sha256>(&record);
+        core.store()
+            .store_package_record(&log_id, &name, &record_id, &record, &Default::default())
+            .await?;
+        core.reserve_submission()
+            .await?
+            .submit(log_id.clone(), record_id.clone());
+        core.shutdown().await?;
+
+        let record = core.store().get_package_record(&log_id, &record_id).await?;
+        match record.status {
+            RecordStatus::Rejected(reason) => {
+                assert!(reason.contains("does not have permission to publish"))
+            }
+            status => panic!("expected the record to be rejected: {status:?}"),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_resigns_checkpoints_before_they_expire() -> Result<(), CoreServiceError> {
+        let (_, operator_key) = generate_p256_pair();
+        let store = Arc::new(MemoryDataStore::default());
+        let core = CoreService::start(
+            CoreServiceConfig::new(
+                operator_key,
+                HashAlgorithm::Sha256,
+                store.clone(),
+                CheckpointPolicy::new(Duration::from_secs(3600)),
+            )
+            .with_checkpoint_validity(Duration::from_secs(2))
+            .with_submission_queue_depth(2),
+        )
+        .await?;
+
+        // No records are published, but the checkpoint is still re-signed
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let first = store.get_latest_checkpoint().await?.into_contents();
+        assert_eq!(first.expires, Some(first.timestamp + 2));
+
+        tokio::time::sleep(Duration::from_millis(2100)).await;
+        let latest = store.get_latest_checkpoint().await?.into_contents();
+        core.shutdown().await?;
+
+        assert_eq!(latest.checkpoint, first.checkpoint);
+        assert!(latest.timestamp > first.timestamp);
+        assert_eq!(latest.expires, Some(latest.timestamp + 2));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_batches_bursts_of_records_into_checkpoints() -> Result<(), CoreServiceError> {
+        let (_, operator_key) = generate_p256_pair();
+        let restart_key = PrivateKey::decode(operator_key.encode().to_string()).unwrap();
+        let store = Arc::new(MemoryDataStore::default());
+        let policy = CheckpointPolicy::new(Duration::from_secs(3600))
+            .with_max_leaves(250)
+            .with_min_leaves(10);
+        let core = CoreService::start(
+            CoreServiceConfig::new(operator_key, HashAlgorithm::Sha256, store.clone(), policy)
+                .with_submission_queue_depth(1000),
+        )
+        .await?;
+
+        // Store the records first so that they are submitted in one burst
+        let (public_key, signing_key) = generate_p256_pair();
+        let mut leaves = Vec::with_capacity(1000);
+        for i in 0..1000 {
+            let name = PackageName::new(format!("test:package{i}")).unwrap();
+            let log_id = LogId::package_log::<Sha256>(&name);
+            let record = ProtoEnvelope::signed_contents(
+                &signing_key,
+                package::PackageRecord {
+                    prev: None,
+                    version: package::PACKAGE_RECORD_VERSION,
+                    timestamp: SystemTime::now(),
+                    entries: vec![package::PackageEntry::Init {
+                        hash_algorithm: Sha256::ALGORITHM,
+                        key: public_key.clone(),
+                    }],
+                },
+            )?;
+            let record_id = RecordId::package_record::<Sha256>(&record);
+            store
+                .store_package_record(&log_id, &name, &record_id, &record, &Default::default())
+                .await?;
+            leaves.push(LogLeaf { log_id, record_id });
+        }
+
+        // Let the first scheduled checkpoint be issued before the burst
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        for leaf in &leaves {
+            core.reserve_submission()
+                .await?
+                .submit(leaf.log_id.clone(), leaf.record_id.clone());
+        }
+        core.shutdown().await?;
+
+        let mut lengths = Vec::new();
+        let mut checkpoints = store.get_all_checkpoints().await?;
+        while let Some(checkpoint) = checkpoints.next().await {
+            lengths.push(checkpoint?.checkpoint.log_length);
+        }
+        assert_eq!(lengths, [1, 251, 501, 751, 1001]);
+
+        // Each record is published by the checkpoint of its batch
+        for (i, leaf) in leaves.iter().enumerate() {
+            let record = store
+                .get_package_record(&leaf.log_id, &leaf.record_id)
+                .await?;
+            assert!(matches!(record.status, RecordStatus::Published));
+            let index = record.registry_index.unwrap();
+            assert_eq!(index, i + 1);
+            let covering = lengths.iter().find(|len| **len > index).unwrap();
+            assert_eq!(*covering, lengths[1 + i / 250]);
+        }
+
+        // Restarting verifies every stored checkpoint against the log
+        let core = CoreService::start(
+            CoreServiceConfig::new(restart_key, HashAlgorithm::Sha256, store, policy)
+                .with_submission_queue_depth(2),
+        )
+        .await?;
+        assert!(core
+            .metrics()
+            .render()
+            .contains("warg_records{status=\"published\"} 1000\n"));
+        core.shutdown().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_refuses_to_sign_regressing_checkpoints() -> Result<(), CoreServiceError> {
+        let (_, operator_key) = generate_p256_pair();
+        let signing_key = PrivateKey::decode(operator_key.encode().to_string()).unwrap();
+        let store = Arc::new(MemoryDataStore::default());
+        let core = CoreService::start(
+            CoreServiceConfig::new(
+                operator_key,
+                HashAlgorithm::Sha256,
+                store.clone(),
+                CheckpointPolicy::new(Duration::from_secs(60)),
+            )
+            .with_submission_queue_depth(2),
+        )
+        .await?;
+        core.shutdown().await?;
+
+        // Simulate a restart after the registry's clock was rewound by storing
+        // the latest checkpoint with a timestamp in the future
+        let latest = store.get_latest_checkpoint().await?.into_contents();
+        let future = TimestampedCheckpoint {
+            timestamp: latest.timestamp + 3600,
+            ..latest
+        };
+        let checkpoint_id: AnyHash = Hash::<Sha256>::of(&future.checkpoint).into();
+        store
+            .store_checkpoint(
+                &checkpoint_id,
+                SerdeEnvelope::signed_contents(&signing_key, future.clone())?,
+                &[],
+            )
+            .await?;
+
+        let core = CoreService::start(
+            CoreServiceConfig::new(
+                signing_key,
+                HashAlgorithm::Sha256,
+                store.clone(),
+                CheckpointPolicy::new(Duration::from_millis(10)),
+            )
+            .with_submission_queue_depth(2),
+        )
+        .await?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        core.shutdown().await?;
+
+        // The checkpoint was never re-signed with an older timestamp
+        let checkpoint = store.get_latest_checkpoint().await?.into_contents();
+        assert_eq!(checkpoint, future);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_detects_checkpoint_regressions() {
+        let checkpoint = |log_length, timestamp| TimestampedCheckpoint {
+            checkpoint: Checkpoint {
+                log_root: Hash::<Sha256>::default().into(),
+                log_length,
+                map_root: Hash::<Sha256>::default().into(),
+            },
+            timestamp,
+            expires: None,
+        };
+
+        let latest = checkpoint(2, 100);
+        assert!(check_checkpoint_monotonicity(&latest, &checkpoint(2, 100)).is_ok());
+        assert!(check_checkpoint_monotonicity(&latest, &checkpoint(3, 101)).is_ok());
+        assert!(matches!(
+            check_checkpoint_monotonicity(&latest, &checkpoint(1, 101)),
+            Err(CoreServiceError::CheckpointRegression {
+                from_log_length: 2,
+                to_log_length: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            check_checkpoint_monotonicity(&latest, &checkpoint(3, 99)),
+            Err(CoreServiceError::CheckpointRegression {
+                from_timestamp: 100,
+                to_timestamp: 99,
+                ..
+            })
+        ));
+    }
 }
@@ -0,0 +1,117 @@
+use indexmap::IndexSet;
+use warg_api::v1::package::PackageDependency;
+use warg_protocol::registry::PackageName;
+use wasmparser::{Chunk, Encoding, Parser, Payload};
+
+/// Analyzes the given content for the packages it depends on.
+///
+/// Dependencies are derived from the imports of a WebAssembly component;
+/// imports that do not name a package are ignored.
+///
+/// Content that is not a valid component has no dependencies.
+pub fn component_dependencies(bytes: &[u8]) -> Vec<PackageDependency> {
+    match component_imports(bytes) {
+        Ok(imports) => imports
+            .into_iter()
+            .filter_map(|import| {
+                Some(PackageDependency {
+                    name: import_package_name(&import)?,
+                    import,
+                })
+            })
+            .collect(),
+        Err(e) => {
+            tracing::debug!("content could not be analyzed for dependencies: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// Gets the names of the top-level imports of a component.
+fn component_imports(mut bytes: &[u8]) -> wasmparser::Result<IndexSet<String>> {
+    let mut parser = Parser::new(0);
+    let mut imports = IndexSet::new();
+
+    loop {
+        let payload = match parser.parse(bytes, true)? {
+            Chunk::NeedMoreData(_) => unreachable!(),
+            Chunk::Parsed { consumed, payload } => {
+                bytes = &bytes[consumed..];
+                payload
+            }
+        };
+
+        match payload {
+            Payload::Version {
+                encoding: Encoding::Module,
+                ..
+            } => return Ok(IndexSet::new()),
+            Payload::ComponentImportSection(reader) => {
+                for import in reader {
+                    imports.insert(import?.name.0.to_string());
+                }
+            }
+            // Skip over nested modules and components as their imports are
+            // satisfied by the outer component
+            Payload::ModuleSection { range, .. } | Payload::ComponentSection { range, .. } => {
+                match bytes.get(range.end - range.start..) {
+                    Some(rest) => bytes = rest,
+                    None => break,
+                }
+            }
+            Payload::CodeSectionStart { .. } => parser.skip_section(),
+            Payload::End(_) => break,
+            _ => {}
+        }
+    }
+
+    Ok(imports)
+}
+
+/// Maps a component import name to the name of the package it refers to.
+///
+/// Supports dependency imports (e.g. `unlocked-dep=<foo:bar@{>=1.0.0}>`) and
+/// interface imports (e.g. `wasi:http/types@0.2.0`).
+fn import_package_name(import: &str) -> Option<PackageName> {
+    let name = match import.split_once('=') {
+        Some(("locked-dep" | "unlocked-dep", rest)) => rest.strip_prefix('<')?.split('>').next()?,
+        Some(_) => return None,
+        None => import,
+    };
+
+    let name = name.split(['/', '@']).next()?;
+    PackageName::new(name).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn maps_import_names_to_packages() {
+        for (import, expected) in [
+            ("unlocked-dep=<foo:bar@{>=1.0.0}>", Some("foo:bar")),
+            ("unlocked-dep=<foo:bar>", Some("foo:bar")),
+            (
+                "locked-dep=<foo:bar@1.0.0>,integrity=<sha256-abc>",
+                Some("foo:bar"),
+            ),
+            ("wasi:http/types@0.2.0", Some("wasi:http")),
+            ("wasi:cli/run", Some("wasi:cli")),
+            ("foo", None),
+            ("url=<https://example.com>", None),
+        ] {
+            assert_eq!(
+                import_package_name(import).as_ref().map(|n| n.as_ref()),
+                expected,
+                "unexpected package for import `{import}`"
+            );
+        }
+    }
+
+    #[test]
+    fn non_component_content_has_no_dependencies() {
+        assert!(component_dependencies(b"not wasm").is_empty());
+        assert!(component_dependencies(b"\0asm\x01\0\0\0").is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+use crate::datastore::{DataStore, DownloadCount};
+use indexmap::IndexMap;
+use std::{
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::time::MissedTickBehavior;
+use warg_crypto::hash::AnyHash;
+use warg_protocol::{registry::PackageName, Version};
+
+/// The default interval at which download counts are flushed to the data store.
+pub const DEFAULT_DOWNLOAD_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The duration in which repeated downloads of the same content by the same
+/// client are counted once.
+const REPEAT_WINDOW: Duration = Duration::from_secs(60);
+
+/// The maximum number of distinct releases and contents counted between flushes.
+///
+/// When exceeded, downloads of further releases and contents are dropped.
+const MAX_PENDING_COUNTS: usize = 10_000;
+
+/// The maximum number of recent downloads remembered to detect repeats.
+///
+/// When exceeded, the oldest downloads are forgotten first.
+const MAX_RECENT_DOWNLOADS: usize = 10_000;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Gets the current day, numbered from the Unix epoch.
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / SECS_PER_DAY
+}
+
+#[derive(Default)]
+struct Pending {
+    releases: IndexMap<(PackageName, Version), u64>,
+    contents: IndexMap<AnyHash, u64>,
+    recent: IndexMap<(IpAddr, AnyHash), Instant>,
+    dropped: u64,
+}
+
+impl Pending {
+    /// Determines if a download of the given content by the given client
+    /// repeats a recent download, remembering the download if not.
+    fn is_repeat(&mut self, source: Option<IpAddr>, digest: &AnyHash) -> bool {
+        let Some(source) = source else {
+            return false;
+        };
+
+        // Downloads are kept in the order they were made, so expired downloads are at the front
+        let now = Instant::now();
+        while self
+            .recent
+            .first()
+            .map(|(_, at)| now.duration_since(*at) >= REPEAT_WINDOW)
+            .unwrap_or(false)
+        {
+            self.recent.shift_remove_index(0);
+        }
+
+        let key = (source, digest.clone());
+        if self.recent.contains_key(&key) {
+            return true;
+        }
+
+        while self.recent.len() >= MAX_RECENT_DOWNLOADS {
+            self.recent.shift_remove_index(0);
+        }
+
+        self.recent.insert(key, now);
+        false
+    }
+}
+
+/// Increments the count for the given key unless the map is full.
+///
+/// Returns `false` if the download was dropped.
+fn increment<K: std::hash::Hash + Eq>(counts: &mut IndexMap<K, u64>, key: K) -> bool {
+    if let Some(count) = counts.get_mut(&key) {
+        *count += 1;
+        return true;
+    }
+
+    if counts.len() >= MAX_PENDING_COUNTS {
+        return false;
+    }
+
+    counts.insert(key, 1);
+    true
+}
+
+/// Counts package downloads in memory and periodically flushes the counts
+/// to the data store.
+///
+/// Counting a download never waits on the data store; downloads that cannot
+/// be counted or flushed are dropped rather than delaying the download.
+pub struct DownloadCounter {
+    store: Arc<dyn DataStore>,
+    pending: Mutex<Pending>,
+}
+
+impl DownloadCounter {
+    /// Starts a counter that flushes its counts to the given data store at
+    /// the given interval.
+    ///
+    /// The counter stops flushing once it is dropped; counts not yet flushed
+    /// are lost.
+    pub fn start(store: Arc<dyn DataStore>, flush_interval: Duration) -> Arc<Self> {
+        let counter = Arc::new(Self {
+            store,
+            pending: Default::default(),
+        });
+
+        let weak = Arc::downgrade(&counter);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(flush_interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            // The first tick completes immediately
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                match weak.upgrade() {
+                    Some(counter) => counter.flush().await,
+                    None => break,
+                }
+            }
+        });
+
+        counter
+    }
+
+    /// Counts a download of a release of a package with the given content.
+    pub fn count_release(
+        &self,
+        source: Option<IpAddr>,
+        name: &PackageName,
+        version: &Version,
+        digest: &AnyHash,
+    ) {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.is_repeat(source, digest) {
+            return;
+        }
+
+        if !increment(&mut pending.releases, (name.clone(), version.clone())) {
+            pending.dropped += 1;
+        }
+    }
+
+    /// Counts a download of content.
+    ///
+    /// The content is attributed to the package releases with that content
+    /// when the counts are flushed.
+    pub fn count_content(&self, source: Option<IpAddr>, digest: &AnyHash) {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.is_repeat(source, digest) {
+            return;
+        }
+
+        if !increment(&mut pending.contents, digest.clone()) {
+            pending.dropped += 1;
+        }
+    }
+
+    /// Flushes the pending counts to the data store.
+    pub async fn flush(&self) {
+        let (mut releases, contents, dropped) = {
+            let mut pending = self.pending.lock().unwrap();
+            (
+                std::mem::take(&mut pending.releases),
+                std::mem::take(&mut pending.contents),
+                std::mem::take(&mut pending.dropped),
+            )
+        };
+
+        if dropped > 0 {
+            tracing::warn!("dropped {dropped} download(s) because too many were pending");
+        }
+
+        for (digest, count) in contents {
+            match self.store.get_content_releases(&digest).await {
+                Ok(content_releases) => {
+                    for release in content_releases {
+                        *releases.entry(release).or_default() += count;
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("failed to find the releases of content `{digest}`: {e}");
+                }
+            }
+        }
+
+        if releases.is_empty() {
+            return;
+        }
+
+        let counts = releases
+            .into_iter()
+            .map(|((name, version), count)| DownloadCount {
+                name,
+                version,
+                count,
+            })
+            .collect::<Vec<_>>();
+
+        if let Err(e) = self.store.record_download_counts(today(), &counts).await {
+            tracing::warn!(
+                "failed to record {len} download count(s): {e}",
+                len = counts.len()
+            );
+        }
+    }
+}
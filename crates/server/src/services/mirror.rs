@@ -0,0 +1,724 @@
+use super::{component_dependencies, CoreService, CoreServiceError, MirroredRecord};
+use crate::{
+    content::{ContentStorage, ContentStoreError},
+    datastore::DataStoreError,
+};
+use indexmap::IndexMap;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+use tokio::{io::AsyncWriteExt, time::MissedTickBehavior};
+use url::Url;
+use warg_api::v1::{
+    content::ContentSourcesResponse,
+    fetch::{
+        FetchLogsRequest, FetchLogsResponse, FetchPackageNamesRequest, FetchPackageNamesResponse,
+    },
+    ledger::LedgerSourcesResponse,
+    paths, ContentSource,
+};
+use warg_crypto::hash::{AnyHash, HashAlgorithm};
+use warg_protocol::{
+    operator, package,
+    registry::{
+        LogId, LogLeaf, PackageName, RecordId, RegistryIndex, RegistryLen, TimestampedCheckpoint,
+    },
+    PublishedProtoEnvelope, Record as _, SerdeEnvelope,
+};
+
+#[cfg(feature = "grpc")]
+mod grpc;
+
+/// The default interval at which a mirror syncs with its upstream registry.
+pub const DEFAULT_MIRROR_SYNC_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The number of records requested from the upstream registry at a time.
+const FETCH_RECORDS_LIMIT: u16 = 1000;
+
+/// The number of package names requested from the upstream registry at a time.
+const FETCH_NAMES_LIMIT: usize = 1000;
+
+/// The heads of the operator log and of package logs, as fetch tokens.
+type LogHeads = (Option<String>, IndexMap<LogId, Option<String>>);
+
+/// Represents an error syncing a mirror with its upstream registry.
+#[derive(Debug, Error)]
+pub enum MirrorError {
+    /// A request to the upstream registry failed.
+    #[error("failed to request `{url}` from the upstream registry: {source}")]
+    Request {
+        /// The URL of the request.
+        url: Url,
+        /// The underlying error.
+        source: reqwest::Error,
+    },
+    /// The upstream registry responded with an unexpected status.
+    #[error("the upstream registry responded to `{url}` with status {status}")]
+    Status {
+        /// The URL of the request.
+        url: Url,
+        /// The status of the response.
+        status: u16,
+    },
+    /// The upstream registry uses a different hash algorithm.
+    #[error(
+        "the upstream registry uses the `{upstream}` hash algorithm but the mirror uses `{mirror}`"
+    )]
+    HashAlgorithmMismatch {
+        /// The hash algorithm of the upstream registry.
+        upstream: HashAlgorithm,
+        /// The hash algorithm of the mirror.
+        mirror: HashAlgorithm,
+    },
+    /// The upstream registry's log is shorter than the mirror's.
+    #[error(
+        "the upstream registry log length `{upstream}` is behind the mirror log length `{mirror}`"
+    )]
+    UpstreamBehind {
+        /// The log length of the upstream registry.
+        upstream: RegistryLen,
+        /// The log length of the mirror.
+        mirror: RegistryLen,
+    },
+    /// The upstream registry did not return the record at a registry index.
+    #[error("the upstream registry did not return record `{record_id}` at registry index {index}")]
+    RecordMissing {
+        /// The registry index of the record.
+        index: RegistryIndex,
+        /// The identifier of the record.
+        record_id: RecordId,
+    },
+    /// The upstream registry returned an invalid response.
+    #[error("the upstream registry returned an invalid response: {0}")]
+    InvalidResponse(String),
+    /// The upstream registry has no source for referenced content.
+    #[error("the upstream registry has no source for content `{0}`")]
+    ContentUnavailable(AnyHash),
+    /// Content downloaded from the upstream registry does not match its digest.
+    #[error("content downloaded from `{url}` does not match digest `{digest}`")]
+    ContentMismatch {
+        /// The URL the content was downloaded from.
+        url: Url,
+        /// The expected digest of the content.
+        digest: AnyHash,
+    },
+    /// A replication request to the upstream registry failed.
+    #[cfg(feature = "grpc")]
+    #[error("replication request to the upstream registry failed: {0}")]
+    Rpc(Box<tonic::Status>),
+    /// The replication endpoint of the upstream registry is invalid.
+    #[cfg(feature = "grpc")]
+    #[error("invalid replication endpoint `{endpoint}`: {source}")]
+    Endpoint {
+        /// The replication endpoint.
+        endpoint: Url,
+        /// The underlying error.
+        source: tonic::transport::Error,
+    },
+    /// An I/O error occurred.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// A content store error occurred.
+    #[error(transparent)]
+    ContentStore(#[from] ContentStoreError),
+    /// A data store error occurred.
+    #[error(transparent)]
+    DataStore(#[from] DataStoreError),
+    /// The replicated records could not be committed.
+    #[error(transparent)]
+    Core(#[from] CoreServiceError),
+}
+
+#[cfg(feature = "grpc")]
+impl From<tonic::Status> for MirrorError {
+    fn from(status: tonic::Status) -> Self {
+        Self::Rpc(Box::new(status))
+    }
+}
+
+/// The progress of a mirror replicating its upstream registry.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorStatus {
+    /// The URL of the upstream registry.
+    pub upstream: String,
+    /// The length of the mirror's log.
+    pub log_length: RegistryLen,
+    /// The length of the upstream registry's log as of the last sync attempt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_log_length: Option<RegistryLen>,
+    /// The number of upstream records not yet replicated.
+    pub records_behind: RegistryLen,
+    /// The time, in seconds since the Unix epoch, of the last successful sync.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_successful_sync: Option<u64>,
+    /// The error of the last sync attempt, if it failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Replicates the log and content of an upstream registry.
+///
+/// The upstream's checkpoints are verified against the replicated records and
+/// its records are validated by the mirror's data store as they are committed.
+pub struct MirrorSync {
+    upstream: Url,
+    core: CoreService,
+    content_store: ContentStorage,
+    client: reqwest::Client,
+    status: Mutex<MirrorStatus>,
+    // Serializes syncs so that records are never replicated twice
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl MirrorSync {
+    /// Creates a new mirror of the given upstream registry.
+    pub fn new(mut upstream: Url, core: CoreService, content_store: ContentStorage) -> Arc<Self> {
+        // API paths are relative to the upstream URL
+        if !upstream.path().ends_with('/') {
+            upstream.set_path(&format!("{path}/", path = upstream.path()));
+        }
+
+        Arc::new(Self {
+            status: Mutex::new(MirrorStatus {
+                upstream: upstream.to_string(),
+                log_length: 0,
+                upstream_log_length: None,
+                records_behind: 0,
+                last_successful_sync: None,
+                last_error: None,
+            }),
+            upstream,
+            core,
+            content_store,
+            client: reqwest::Client::new(),
+            lock: Default::default(),
+        })
+    }
+
+    /// Gets the URL of the upstream registry.
+    pub fn upstream(&self) -> &Url {
+        &self.upstream
+    }
+
+    /// Gets the progress of the mirror.
+    pub fn status(&self) -> MirrorStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Starts syncing with the upstream registry at the given interval.
+    ///
+    /// The first sync starts immediately; syncing continues for the lifetime
+    /// of the runtime.
+    pub fn start(self: &Arc<Self>, interval: Duration) {
+        let mirror = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                match mirror.sync().await {
+                    Ok(0) => {}
+                    Ok(count) => tracing::info!(
+                        "replicated {count} record(s) from `{upstream}`",
+                        upstream = mirror.upstream
+                    ),
+                    Err(e) => tracing::warn!(
+                        "failed to sync with `{upstream}`: {e}",
+                        upstream = mirror.upstream
+                    ),
+                }
+            }
+        });
+    }
+
+    /// Replicates the records of the upstream registry's latest checkpoint.
+    ///
+    /// Returns the number of records that were replicated.
+    pub async fn sync(&self) -> Result<usize, MirrorError> {
+        let _guard = self.lock.lock().await;
+        let res = self.replicate().await;
+        self.update_status(&res).await;
+        res
+    }
+
+    /// Updates the progress of the mirror after a sync attempt.
+    async fn update_status(&self, res: &Result<usize, MirrorError>) {
+        let log_length = self.core.log_length().await;
+        let mut status = self.status.lock().unwrap();
+        status.log_length = log_length;
+        status.records_behind = status
+            .upstream_log_length
+            .map_or(0, |upstream| upstream.saturating_sub(log_length));
+        match res {
+            Ok(_) => {
+                status.last_successful_sync = Some(
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs(),
+                );
+                status.last_error = None;
+            }
+            Err(e) => status.last_error = Some(e.to_string()),
+        }
+    }
+
+    async fn replicate(&self) -> Result<usize, MirrorError> {
+        let ledger: LedgerSourcesResponse = self.get(paths::ledger_sources()).await?;
+        let algorithm = self.core.hash_algorithm();
+        if ledger.hash_algorithm != algorithm {
+            return Err(MirrorError::HashAlgorithmMismatch {
+                upstream: ledger.hash_algorithm,
+                mirror: algorithm,
+            });
+        }
+
+        let checkpoint: SerdeEnvelope<TimestampedCheckpoint> =
+            self.get(paths::fetch_checkpoint()).await?;
+        let upstream_len = checkpoint.as_ref().checkpoint.log_length;
+        self.status.lock().unwrap().upstream_log_length = Some(upstream_len);
+
+        let log_length = self.core.log_length().await;
+        if upstream_len < log_length {
+            return Err(MirrorError::UpstreamBehind {
+                upstream: upstream_len,
+                mirror: log_length,
+            });
+        }
+
+        let leaves = self.fetch_leaves(log_length, upstream_len).await?;
+        let records = self
+            .fetch_records(log_length, upstream_len, &leaves)
+            .await?;
+        for record in &records {
+            if let MirroredRecord::Package { record, .. } = record {
+                for digest in record.as_ref().contents() {
+                    self.fetch_content(digest).await?;
+                }
+            }
+        }
+
+        self.commit(records, &leaves, checkpoint).await
+    }
+
+    /// Commits replicated records and the upstream checkpoint that includes
+    /// them, once their content is present.
+    ///
+    /// Returns the number of records that were committed.
+    async fn commit(
+        &self,
+        records: Vec<MirroredRecord>,
+        leaves: &[LogLeaf],
+        checkpoint: SerdeEnvelope<TimestampedCheckpoint>,
+    ) -> Result<usize, MirrorError> {
+        let count = records.len();
+        let releases = records
+            .iter()
+            .zip(leaves)
+            .filter_map(|(record, leaf)| match record {
+                MirroredRecord::Package { record, .. } => Some((leaf.clone(), record.clone())),
+                MirroredRecord::Operator(_) => None,
+            })
+            .collect::<Vec<_>>();
+        self.core.replicate(records, checkpoint).await?;
+
+        for (leaf, record) in releases {
+            self.store_dependencies(&leaf, record.as_ref()).await;
+        }
+
+        Ok(count)
+    }
+
+    /// Fetches the leaves of the upstream registry log in the given range.
+    async fn fetch_leaves(
+        &self,
+        start: RegistryLen,
+        end: RegistryLen,
+    ) -> Result<Vec<LogLeaf>, MirrorError> {
+        let algorithm = self.core.hash_algorithm();
+        let digest_len = algorithm.digest(&[]).bytes().len();
+
+        let mut leaves = Vec::with_capacity(end - start);
+        while start + leaves.len() < end {
+            let index = start + leaves.len();
+            let url = self.url(&format!("v1/ledger/records/{index}"))?;
+            let bytes = self
+                .send(self.client.get(url.clone()), &url)
+                .await?
+                .bytes()
+                .await
+                .map_err(|source| MirrorError::Request { url, source })?;
+
+            if bytes.is_empty() || bytes.len() % (digest_len * 2) != 0 {
+                return Err(MirrorError::InvalidResponse(format!(
+                    "unexpected ledger of {len} bytes at registry index {index}",
+                    len = bytes.len()
+                )));
+            }
+
+            leaves.extend(
+                bytes
+                    .chunks_exact(digest_len * 2)
+                    .take(end - index)
+                    .map(|leaf| LogLeaf {
+                        log_id: AnyHash::new(algorithm, leaf[..digest_len].to_vec()).into(),
+                        record_id: AnyHash::new(algorithm, leaf[digest_len..].to_vec()).into(),
+                    }),
+            );
+        }
+
+        Ok(leaves)
+    }
+
+    /// Fetches the records of the given leaves, in registry log order.
+    async fn fetch_records(
+        &self,
+        start: RegistryLen,
+        end: RegistryLen,
+        leaves: &[LogLeaf],
+    ) -> Result<Vec<MirroredRecord>, MirrorError> {
+        let algorithm = self.core.hash_algorithm();
+        let (mut operator, mut packages) = self.log_heads(leaves).await?;
+
+        let mut fetched = FetchedRecords {
+            names: self.fetch_names(packages.keys().cloned().collect()).await?,
+            ..Default::default()
+        };
+        loop {
+            let response: FetchLogsResponse = self
+                .post(
+                    paths::fetch_logs(),
+                    &FetchLogsRequest {
+                        log_length: end,
+                        limit: Some(FETCH_RECORDS_LIMIT),
+                        total_limit: None,
+                        operator: operator.as_deref().map(Cow::Borrowed),
+                        packages: Cow::Borrowed(&packages),
+                    },
+                )
+                .await?;
+
+            if let Some(last) = response.operator.last() {
+                operator = Some(last.fetch_token.clone());
+            }
+            for record in response.operator {
+                fetched.insert_operator(
+                    algorithm,
+                    record
+                        .envelope
+                        .try_into()
+                        .map_err(|e| MirrorError::InvalidResponse(format!("{e}")))?,
+                );
+            }
+
+            for (log_id, records) in response.packages {
+                if let (Some(last), Some(since)) = (records.last(), packages.get_mut(&log_id)) {
+                    *since = Some(last.fetch_token.clone());
+                }
+                for record in records {
+                    fetched.insert_package(
+                        algorithm,
+                        record
+                            .envelope
+                            .try_into()
+                            .map_err(|e| MirrorError::InvalidResponse(format!("{e}")))?,
+                    );
+                }
+            }
+
+            if !response.more {
+                break;
+            }
+        }
+
+        fetched.into_log_order(start, leaves, &self.core.operator_log_id())
+    }
+
+    /// Gets the heads of the operator log and of the package logs of the
+    /// given leaves, to fetch the records after.
+    ///
+    /// A head is `None` if the mirror has no records of the log yet.
+    async fn log_heads(&self, leaves: &[LogLeaf]) -> Result<LogHeads, MirrorError> {
+        let store = self.core.store();
+        let operator_log_id = self.core.operator_log_id();
+
+        let operator = match store.get_operator_state(&operator_log_id).await {
+            Ok(state) => state.head().as_ref().map(|head| head.digest.to_string()),
+            Err(DataStoreError::LogNotFound(_)) => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut packages = IndexMap::new();
+        for leaf in leaves {
+            if leaf.log_id == operator_log_id || packages.contains_key(&leaf.log_id) {
+                continue;
+            }
+
+            let since = match store.get_package_state(&leaf.log_id).await {
+                Ok(state) => state.head().as_ref().map(|head| head.digest.to_string()),
+                Err(DataStoreError::LogNotFound(_)) => None,
+                Err(e) => return Err(e.into()),
+            };
+            packages.insert(leaf.log_id.clone(), since);
+        }
+
+        Ok((operator, packages))
+    }
+
+    /// Fetches the names of the given package logs.
+    async fn fetch_names(
+        &self,
+        log_ids: Vec<LogId>,
+    ) -> Result<IndexMap<LogId, Option<PackageName>>, MirrorError> {
+        let mut names = IndexMap::with_capacity(log_ids.len());
+        for chunk in log_ids.chunks(FETCH_NAMES_LIMIT) {
+            let response: FetchPackageNamesResponse = self
+                .post(
+                    paths::fetch_package_names(),
+                    &FetchPackageNamesRequest {
+                        packages: Cow::Owned(chunk.to_vec()),
+                    },
+                )
+                .await?;
+            names.extend(response.packages);
+        }
+
+        Ok(names)
+    }
+
+    /// Downloads content from the upstream registry unless it is present.
+    async fn fetch_content(&self, digest: &AnyHash) -> Result<(), MirrorError> {
+        if self.content_store.is_present(digest).await? {
+            return Ok(());
+        }
+
+        let sources: ContentSourcesResponse = self.get(&paths::content_sources(digest)).await?;
+        let url = sources
+            .content_sources
+            .get(digest)
+            .and_then(|sources| {
+                sources.iter().find_map(|source| match source {
+                    ContentSource::HttpGet { url, .. } => self.upstream.join(url).ok(),
+                    #[allow(unreachable_patterns)]
+                    _ => None,
+                })
+            })
+            .ok_or_else(|| MirrorError::ContentUnavailable(digest.clone()))?;
+
+        self.download_content(url, digest).await
+    }
+
+    /// Downloads content from the given URL, verifying it against its digest.
+    async fn download_content(&self, url: Url, digest: &AnyHash) -> Result<(), MirrorError> {
+        tracing::debug!("downloading content `{digest}` from `{url}`");
+        let mut response = self.send(self.client.get(url.clone()), &url).await?;
+
+        let (file, path) = self.content_store.temp_file()?.into_parts();
+        let mut file = tokio::fs::File::from_std(file);
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|source| MirrorError::Request {
+                url: url.clone(),
+                source,
+            })?
+        {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        drop(file);
+
+        let len = match self.content_store.persist(path, digest).await {
+            Ok(len) => len,
+            Err(ContentStoreError::DigestMismatch { .. }) => {
+                return Err(MirrorError::ContentMismatch {
+                    url,
+                    digest: digest.clone(),
+                })
+            }
+            Err(e) => return Err(e.into()),
+        };
+        self.core.metrics().content_stored(len);
+        Ok(())
+    }
+
+    /// Analyzes the content of the releases in a replicated record for
+    /// dependencies and stores them.
+    async fn store_dependencies(&self, leaf: &LogLeaf, record: &package::PackageRecord) {
+        for entry in &record.entries {
+            let package::PackageEntry::Release {
+                version, content, ..
+            } = entry
+            else {
+                continue;
+            };
+
+            let dependencies = match self.content_store.read(content).await {
+                Ok(bytes) => component_dependencies(&bytes),
+                Err(e) => {
+                    tracing::warn!("failed to read content `{content}` for analysis: {e}");
+                    continue;
+                }
+            };
+
+            if let Err(e) = self
+                .core
+                .store()
+                .store_package_dependencies(&leaf.log_id, &leaf.record_id, version, &dependencies)
+                .await
+            {
+                tracing::error!(
+                    "failed to store dependencies for record `{record_id}` of `{log_id}`: {e}",
+                    record_id = leaf.record_id,
+                    log_id = leaf.log_id
+                );
+            }
+        }
+    }
+
+    fn url(&self, path: &str) -> Result<Url, MirrorError> {
+        self.upstream
+            .join(path)
+            .map_err(|e| MirrorError::InvalidResponse(format!("invalid path `{path}`: {e}")))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, MirrorError> {
+        let url = self.url(path)?;
+        let response = self.send(self.client.get(url.clone()), &url).await?;
+        response
+            .json()
+            .await
+            .map_err(|source| MirrorError::Request { url, source })
+    }
+
+    async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &impl Serialize,
+    ) -> Result<T, MirrorError> {
+        let url = self.url(path)?;
+        let response = self
+            .send(self.client.post(url.clone()).json(body), &url)
+            .await?;
+        response
+            .json()
+            .await
+            .map_err(|source| MirrorError::Request { url, source })
+    }
+
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        url: &Url,
+    ) -> Result<reqwest::Response, MirrorError> {
+        let response = request
+            .send()
+            .await
+            .map_err(|source| MirrorError::Request {
+                url: url.clone(),
+                source,
+            })?;
+
+        if !response.status().is_success() {
+            return Err(MirrorError::Status {
+                url: url.clone(),
+                status: response.status().as_u16(),
+            });
+        }
+
+        Ok(response)
+    }
+}
+
+/// The records fetched from an upstream registry, by record identifier.
+#[derive(Default)]
+struct FetchedRecords {
+    operator: HashMap<RecordId, PublishedProtoEnvelope<operator::OperatorRecord>>,
+    packages: HashMap<RecordId, PublishedProtoEnvelope<package::PackageRecord>>,
+    names: IndexMap<LogId, Option<PackageName>>,
+}
+
+impl FetchedRecords {
+    fn insert_operator(
+        &mut self,
+        algorithm: HashAlgorithm,
+        record: PublishedProtoEnvelope<operator::OperatorRecord>,
+    ) {
+        self.operator.insert(
+            RecordId::operator_record_for(algorithm, &record.envelope),
+            record,
+        );
+    }
+
+    fn insert_package(
+        &mut self,
+        algorithm: HashAlgorithm,
+        record: PublishedProtoEnvelope<package::PackageRecord>,
+    ) {
+        self.packages.insert(
+            RecordId::package_record_for(algorithm, &record.envelope),
+            record,
+        );
+    }
+
+    /// Orders the records of the given leaves, the first of which is at the
+    /// given registry index, in registry log order.
+    ///
+    /// Every leaf must have a fetched record published at its index.
+    fn into_log_order(
+        mut self,
+        start: RegistryIndex,
+        leaves: &[LogLeaf],
+        operator_log_id: &LogId,
+    ) -> Result<Vec<MirroredRecord>, MirrorError> {
+        leaves
+            .iter()
+            .enumerate()
+            .map(|(i, leaf)| {
+                let index = start + i;
+                let missing = || MirrorError::RecordMissing {
+                    index,
+                    record_id: leaf.record_id.clone(),
+                };
+
+                if &leaf.log_id == operator_log_id {
+                    let record = self
+                        .operator
+                        .remove(&leaf.record_id)
+                        .filter(|record| record.registry_index == index)
+                        .ok_or_else(missing)?;
+                    return Ok(MirroredRecord::Operator(record.envelope));
+                }
+
+                let record = self
+                    .packages
+                    .remove(&leaf.record_id)
+                    .filter(|record| record.registry_index == index)
+                    .ok_or_else(missing)?;
+                let name = self
+                    .names
+                    .get(&leaf.log_id)
+                    .cloned()
+                    .flatten()
+                    .ok_or_else(|| {
+                        MirrorError::InvalidResponse(format!(
+                            "the name of package log `{log_id}` is unknown",
+                            log_id = leaf.log_id
+                        ))
+                    })?;
+                Ok(MirroredRecord::Package {
+                    log_id: leaf.log_id.clone(),
+                    name,
+                    record: record.envelope,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,247 @@
+use super::{FetchedRecords, MirrorError, MirrorSync};
+use crate::{
+    grpc::{
+        parse_hash,
+        proto::{
+            replication_client::ReplicationClient, GetContentManifestRequest, GetRecordsRequest,
+            LogCursor, StreamLeavesRequest,
+        },
+    },
+    services::MirroredRecord,
+};
+use indexmap::IndexSet;
+use std::{sync::Arc, time::Duration};
+use tonic::transport::{Channel, Endpoint};
+use url::Url;
+use warg_crypto::hash::AnyHash;
+use warg_protocol::{
+    registry::{LogId, LogLeaf, RegistryLen, TimestampedCheckpoint},
+    Record as _, SerdeEnvelope,
+};
+
+fn invalid_response(e: anyhow::Error) -> MirrorError {
+    MirrorError::InvalidResponse(format!("{e:#}"))
+}
+
+impl MirrorSync {
+    /// Starts replicating the upstream registry over its gRPC replication
+    /// service at the given endpoint.
+    ///
+    /// The upstream pushes the leaves of each checkpoint as it is issued; if
+    /// the stream fails, it is reopened after the given interval. Syncing
+    /// continues for the lifetime of the runtime.
+    pub fn start_streaming(
+        self: &Arc<Self>,
+        endpoint: Url,
+        retry_interval: Duration,
+    ) -> Result<(), MirrorError> {
+        let channel = Endpoint::from_shared(endpoint.to_string())
+            .map_err(|source| MirrorError::Endpoint {
+                endpoint: endpoint.clone(),
+                source,
+            })?
+            .connect_lazy();
+
+        let mirror = self.clone();
+        tokio::spawn(async move {
+            let mut client = ReplicationClient::new(channel);
+            loop {
+                if let Err(e) = mirror.stream(&mut client).await {
+                    tracing::warn!("failed to replicate from `{endpoint}`: {e}");
+                    mirror.update_status(&Err(e)).await;
+                }
+
+                tokio::time::sleep(retry_interval).await;
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Replicates the checkpoints pushed by the upstream registry until the
+    /// stream ends.
+    async fn stream(&self, client: &mut ReplicationClient<Channel>) -> Result<(), MirrorError> {
+        let log_length = self.core.log_length().await;
+        let mut stream = client
+            .stream_leaves(StreamLeavesRequest {
+                log_length: log_length as u64,
+            })
+            .await?
+            .into_inner();
+
+        let mut leaves = Vec::new();
+        while let Some(response) = stream.message().await? {
+            for leaf in response.leaves {
+                leaves.push(LogLeaf::try_from(leaf).map_err(invalid_response)?);
+            }
+
+            let Some(checkpoint) = response.checkpoint else {
+                continue;
+            };
+
+            let checkpoint = checkpoint.try_into().map_err(invalid_response)?;
+            let _guard = self.lock.lock().await;
+            let count = self
+                .replicate_checkpoint(client, std::mem::take(&mut leaves), checkpoint)
+                .await?;
+            tracing::info!(
+                "replicated {count} record(s) from `{upstream}`",
+                upstream = self.upstream
+            );
+            self.update_status(&Ok(count)).await;
+        }
+
+        Ok(())
+    }
+
+    /// Replicates the records of the given leaves, which lead up to the given
+    /// checkpoint.
+    ///
+    /// Returns the number of records that were replicated.
+    async fn replicate_checkpoint(
+        &self,
+        client: &mut ReplicationClient<Channel>,
+        leaves: Vec<LogLeaf>,
+        checkpoint: SerdeEnvelope<TimestampedCheckpoint>,
+    ) -> Result<usize, MirrorError> {
+        let algorithm = self.core.hash_algorithm();
+        let upstream = checkpoint.as_ref().checkpoint.log_root.algorithm();
+        if upstream != algorithm {
+            return Err(MirrorError::HashAlgorithmMismatch {
+                upstream,
+                mirror: algorithm,
+            });
+        }
+
+        let end = checkpoint.as_ref().checkpoint.log_length;
+        self.status.lock().unwrap().upstream_log_length = Some(end);
+
+        let start = self.core.log_length().await;
+        if start + leaves.len() != end {
+            return Err(MirrorError::InvalidResponse(format!(
+                "received {len} leaves after log length {start} for a checkpoint at log length {end}",
+                len = leaves.len()
+            )));
+        }
+
+        let records = self.get_records(client, start, end, &leaves).await?;
+        let digests = records
+            .iter()
+            .filter_map(|record| match record {
+                MirroredRecord::Package { record, .. } => Some(record.as_ref().contents()),
+                MirroredRecord::Operator(_) => None,
+            })
+            .flatten()
+            .cloned()
+            .collect::<IndexSet<_>>();
+        self.get_content(client, digests).await?;
+
+        self.commit(records, &leaves, checkpoint).await
+    }
+
+    /// Gets the records of the given leaves, in registry log order.
+    async fn get_records(
+        &self,
+        client: &mut ReplicationClient<Channel>,
+        start: RegistryLen,
+        end: RegistryLen,
+        leaves: &[LogLeaf],
+    ) -> Result<Vec<MirroredRecord>, MirrorError> {
+        let algorithm = self.core.hash_algorithm();
+        let operator_log_id = self.core.operator_log_id();
+        let (operator, packages) = self.log_heads(leaves).await?;
+
+        let mut logs = Vec::with_capacity(packages.len() + 1);
+        if leaves.iter().any(|leaf| leaf.log_id == operator_log_id) {
+            logs.push(LogCursor {
+                log_id: operator_log_id.to_string(),
+                since: operator,
+            });
+        }
+        logs.extend(packages.into_iter().map(|(log_id, since)| LogCursor {
+            log_id: log_id.to_string(),
+            since,
+        }));
+
+        let mut stream = client
+            .get_records(GetRecordsRequest {
+                log_length: end as u64,
+                logs,
+            })
+            .await?
+            .into_inner();
+
+        let mut fetched = FetchedRecords::default();
+        while let Some(batch) = stream.message().await? {
+            let log_id: LogId = parse_hash(&batch.log_id).map_err(invalid_response)?.into();
+            let Some(name) = batch.name else {
+                for record in batch.records {
+                    fetched
+                        .insert_operator(algorithm, record.try_into().map_err(invalid_response)?);
+                }
+                continue;
+            };
+
+            let name = name.parse().map_err(invalid_response)?;
+            if self.core.package_log_id(&name) != log_id {
+                return Err(MirrorError::InvalidResponse(format!(
+                    "package `{name}` does not have log `{log_id}`"
+                )));
+            }
+
+            fetched.names.insert(log_id, Some(name));
+            for record in batch.records {
+                fetched.insert_package(algorithm, record.try_into().map_err(invalid_response)?);
+            }
+        }
+
+        fetched.into_log_order(start, leaves, &operator_log_id)
+    }
+
+    /// Downloads the given content from where the upstream registry's
+    /// content manifest points to, unless it is present.
+    async fn get_content(
+        &self,
+        client: &mut ReplicationClient<Channel>,
+        digests: IndexSet<AnyHash>,
+    ) -> Result<(), MirrorError> {
+        let mut missing = Vec::with_capacity(digests.len());
+        for digest in digests {
+            if !self.content_store.is_present(&digest).await? {
+                missing.push(digest);
+            }
+        }
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let mut stream = client
+            .get_content_manifest(GetContentManifestRequest {
+                digests: missing.iter().map(ToString::to_string).collect(),
+            })
+            .await?
+            .into_inner();
+
+        while let Some(entry) = stream.message().await? {
+            let digest = parse_hash(&entry.digest).map_err(invalid_response)?;
+            if !missing.contains(&digest) {
+                return Err(MirrorError::InvalidResponse(format!(
+                    "the content manifest has unrequested content `{digest}`"
+                )));
+            }
+
+            let url = entry
+                .url
+                .and_then(|url| self.upstream.join(&url).ok())
+                .ok_or_else(|| MirrorError::ContentUnavailable(digest.clone()))?;
+            self.download_content(url, &digest).await?;
+            missing.retain(|d| d != &digest);
+        }
+
+        match missing.into_iter().next() {
+            Some(digest) => Err(MirrorError::ContentUnavailable(digest)),
+            None => Ok(()),
+        }
+    }
+}
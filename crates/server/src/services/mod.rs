@@ -1,3 +1,20 @@
+mod content_gc;
 mod core;
+mod dependencies;
+mod downloads;
+mod mirror;
+mod webhooks;
 
-pub use self::core::{CoreService, CoreServiceError};
+pub use self::content_gc::{ContentCollector, ContentGcError, DEFAULT_CONTENT_GC_GRACE_PERIOD};
+pub(crate) use self::core::record_state_changed;
+pub use self::core::{
+    CheckpointPolicy, CoreService, CoreServiceConfig, CoreServiceError, MirroredRecord,
+    SubmissionPermit, DEFAULT_SUBMISSION_QUEUE_DEPTH, DEFAULT_SUBMISSION_TIMEOUT,
+};
+pub use self::dependencies::component_dependencies;
+pub use self::downloads::{today, DownloadCounter, DEFAULT_DOWNLOAD_FLUSH_INTERVAL};
+pub use self::mirror::{MirrorError, MirrorStatus, MirrorSync, DEFAULT_MIRROR_SYNC_INTERVAL};
+pub use self::webhooks::{
+    Webhook, WebhookDispatcher, WebhookError, DEFAULT_WEBHOOK_MAX_ATTEMPTS,
+    DEFAULT_WEBHOOK_RETRY_DELAY,
+};
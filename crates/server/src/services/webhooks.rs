@@ -0,0 +1,616 @@
+use super::CoreService;
+use crate::datastore::DataStoreError;
+use hmac::{Hmac, Mac};
+use indexmap::IndexMap;
+use rand_core::{OsRng, RngCore};
+use reqwest::header;
+use secrecy::{ExposeSecret, SecretString};
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+use tokio::{io::AsyncWriteExt, sync::Notify};
+use url::Url;
+use warg_api::v1::{
+    admin::{WebhookDelivery, WebhookDeliveryStatus},
+    webhook::{
+        WebhookEntry, WebhookPayload, WEBHOOK_DELIVERY_HEADER_NAME, WEBHOOK_SIGNATURE_HEADER_NAME,
+    },
+};
+use warg_crypto::hash::{AnyHash, Sha256};
+use warg_protocol::{
+    package::PackageEntry,
+    registry::{PackageName, RegistryLen},
+};
+
+/// The default number of times a webhook delivery is attempted before it
+/// is considered failed.
+pub const DEFAULT_WEBHOOK_MAX_ATTEMPTS: u32 = 5;
+/// The default delay before a failed webhook delivery is first retried.
+///
+/// The delay doubles with each subsequent attempt.
+pub const DEFAULT_WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The maximum delay between attempts of a delivery.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+/// The time an endpoint has to respond to a delivery.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// The number of deliveries kept for the administration API.
+///
+/// Once this many deliveries are in flight, new deliveries wait for one of
+/// them to finish.
+const MAX_RECENT_DELIVERIES: usize = 1000;
+/// The number of log leaves read from the data store at a time.
+const LEAVES_BATCH_SIZE: usize = 1000;
+
+/// Represents an error managing webhook deliveries.
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    /// The delivery is not known.
+    #[error("webhook delivery `{0}` was not found")]
+    DeliveryNotFound(String),
+    /// The delivery has not failed.
+    #[error("webhook delivery `{0}` has not failed")]
+    DeliveryNotFailed(String),
+}
+
+/// An endpoint notified when package records are published.
+#[derive(Clone, Debug)]
+pub struct Webhook {
+    url: Url,
+    secret: SecretString,
+    packages: Vec<String>,
+}
+
+impl Webhook {
+    /// Creates a webhook notifying the given URL of the records of every
+    /// package, signing payloads with the given secret.
+    pub fn new(url: Url, secret: SecretString) -> Self {
+        Self {
+            url,
+            secret,
+            packages: Vec::new(),
+        }
+    }
+
+    /// Limits the webhook to packages matching the given package name (e.g.
+    /// `example:hello`), namespace (e.g. `example`) or prefix pattern (e.g.
+    /// `example-*` or `example:hello-*`).
+    ///
+    /// May be called multiple times; a package matching any of the patterns
+    /// is notified.
+    pub fn with_package(mut self, pattern: impl Into<String>) -> Self {
+        self.packages.push(pattern.into());
+        self
+    }
+
+    /// Gets the URL of the webhook.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Determines whether the webhook is notified of the given package.
+    pub fn matches(&self, name: &PackageName) -> bool {
+        self.packages.is_empty()
+            || self
+                .packages
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => name.as_ref().starts_with(prefix),
+                    None => pattern == name.as_ref() || pattern == name.namespace(),
+                })
+    }
+
+    /// Computes the value of the signature header of the given payload.
+    fn signature(&self, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.expose_secret().as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+}
+
+struct Delivery {
+    webhook: usize,
+    body: Vec<u8>,
+    info: WebhookDelivery,
+}
+
+/// Delivers signed notifications of published package records to webhooks.
+///
+/// Failed deliveries are retried with exponential backoff; deliveries that
+/// exhaust their attempts are logged and appended to the dead-letter file,
+/// if one is configured, and may be retried through the administration API.
+pub struct WebhookDispatcher {
+    webhooks: Vec<Webhook>,
+    client: reqwest::Client,
+    max_attempts: u32,
+    retry_delay: Duration,
+    dead_letter_file: Option<PathBuf>,
+    deliveries: Mutex<IndexMap<String, Delivery>>,
+    capacity: usize,
+    // Notified whenever a delivery stops being in flight
+    finished: Notify,
+    // Serializes appends to the dead-letter file
+    dead_letter_lock: tokio::sync::Mutex<()>,
+}
+
+impl WebhookDispatcher {
+    /// Creates a new dispatcher for the given webhooks.
+    pub fn new(
+        webhooks: Vec<Webhook>,
+        max_attempts: u32,
+        retry_delay: Duration,
+        dead_letter_file: Option<PathBuf>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            webhooks,
+            client: reqwest::Client::new(),
+            max_attempts: max_attempts.max(1),
+            retry_delay,
+            dead_letter_file,
+            deliveries: Default::default(),
+            capacity: MAX_RECENT_DELIVERIES,
+            finished: Default::default(),
+            dead_letter_lock: Default::default(),
+        })
+    }
+
+    /// Starts notifying the webhooks of package records as they are
+    /// published.
+    ///
+    /// Only records included in checkpoints issued after the dispatcher is
+    /// started are delivered. Dispatching continues for the lifetime of the
+    /// runtime.
+    pub fn start(self: &Arc<Self>, core: CoreService) {
+        let dispatcher = self.clone();
+        tokio::spawn(async move {
+            // Subscribe before reading the latest checkpoint so that no
+            // checkpoint issued in between is missed
+            let mut checkpoints = core.subscribe_checkpoints();
+            let mut log_length = match core.store().get_latest_checkpoint().await {
+                Ok(checkpoint) => checkpoint.as_ref().checkpoint.log_length,
+                Err(DataStoreError::NoCheckpoints) => 0,
+                Err(e) => {
+                    tracing::warn!("failed to get the latest checkpoint for webhooks: {e}");
+                    core.log_length().await
+                }
+            };
+
+            while checkpoints.changed().await.is_ok() {
+                let Some(checkpoint_id) = checkpoints.borrow_and_update().clone() else {
+                    continue;
+                };
+
+                // On failure, the records not yet enqueued are retried with
+                // the next checkpoint
+                if let Err(e) = dispatcher
+                    .enqueue_published(&core, &mut log_length, &checkpoint_id)
+                    .await
+                {
+                    tracing::warn!(
+                        "failed to notify webhooks of records in checkpoint `{checkpoint_id}`: {e}"
+                    );
+                }
+            }
+        });
+    }
+
+    /// Gets the most recent deliveries, oldest first.
+    pub fn deliveries(
+        &self,
+        status: Option<WebhookDeliveryStatus>,
+        limit: usize,
+    ) -> Vec<WebhookDelivery> {
+        let deliveries = self.deliveries.lock().unwrap();
+        let mut matching = deliveries
+            .values()
+            .rev()
+            .filter(|d| status.map(|s| d.info.status == s).unwrap_or(true))
+            .take(limit)
+            .map(|d| d.info.clone())
+            .collect::<Vec<_>>();
+        matching.reverse();
+        matching
+    }
+
+    /// Retries a failed delivery.
+    ///
+    /// Returns the delivery as it is being retried.
+    pub fn retry(self: &Arc<Self>, id: &str) -> Result<WebhookDelivery, WebhookError> {
+        let info = {
+            let mut deliveries = self.deliveries.lock().unwrap();
+            let delivery = deliveries
+                .get_mut(id)
+                .ok_or_else(|| WebhookError::DeliveryNotFound(id.to_string()))?;
+            if delivery.info.status != WebhookDeliveryStatus::Failed {
+                return Err(WebhookError::DeliveryNotFailed(id.to_string()));
+            }
+
+            delivery.info.status = WebhookDeliveryStatus::Pending;
+            delivery.info.attempts = 0;
+            delivery.info.clone()
+        };
+
+        self.dispatch(info.id.clone());
+        Ok(info)
+    }
+
+    /// Enqueues deliveries for the package records published after the given
+    /// log length up to the given checkpoint.
+    ///
+    /// The log length is advanced past each record as it is enqueued, so that
+    /// a record is enqueued once even if a later record fails to load.
+    async fn enqueue_published(
+        self: &Arc<Self>,
+        core: &CoreService,
+        log_length: &mut RegistryLen,
+        checkpoint_id: &AnyHash,
+    ) -> Result<(), DataStoreError> {
+        let store = core.store();
+        let end = store
+            .get_checkpoint_by_id(checkpoint_id)
+            .await?
+            .as_ref()
+            .checkpoint
+            .log_length;
+        let operator_log_id = core.operator_log_id();
+
+        while *log_length < end {
+            let leaves = store
+                .get_log_leafs_starting_with_registry_index(
+                    *log_length,
+                    LEAVES_BATCH_SIZE.min(end - *log_length),
+                )
+                .await?;
+            if leaves.is_empty() {
+                break;
+            }
+
+            for (registry_index, leaf) in leaves {
+                if leaf.log_id == operator_log_id {
+                    *log_length = registry_index + 1;
+                    continue;
+                }
+
+                let Some(name) = store
+                    .get_package_names(std::slice::from_ref(&leaf.log_id))
+                    .await?
+                    .swap_remove(&leaf.log_id)
+                    .flatten()
+                else {
+                    *log_length = registry_index + 1;
+                    continue;
+                };
+
+                if !self.webhooks.iter().any(|w| w.matches(&name)) {
+                    *log_length = registry_index + 1;
+                    continue;
+                }
+
+                let record = store
+                    .get_package_record(&leaf.log_id, &leaf.record_id)
+                    .await?;
+                let entries = record
+                    .envelope
+                    .as_ref()
+                    .entries
+                    .iter()
+                    .filter_map(|entry| match entry {
+                        PackageEntry::Release {
+                            version, content, ..
+                        } => Some(WebhookEntry::Release {
+                            version: version.clone(),
+                            content: content.clone(),
+                        }),
+                        PackageEntry::Yank { version } => Some(WebhookEntry::Yank {
+                            version: version.clone(),
+                        }),
+                        _ => None,
+                    })
+                    .collect();
+
+                self.enqueue(WebhookPayload {
+                    package: name,
+                    log_id: leaf.log_id,
+                    record_id: leaf.record_id,
+                    registry_index,
+                    checkpoint: checkpoint_id.clone(),
+                    entries,
+                })
+                .await;
+                *log_length = registry_index + 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Enqueues a delivery of the given payload to every matching webhook.
+    ///
+    /// Waits for a delivery to finish while every kept delivery is in flight.
+    async fn enqueue(self: &Arc<Self>, payload: WebhookPayload) {
+        let body = serde_json::to_vec(&payload).expect("payload should serialize");
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+
+        for (index, webhook) in self.webhooks.iter().enumerate() {
+            if !webhook.matches(&payload.package) {
+                continue;
+            }
+
+            let mut id = [0u8; 16];
+            OsRng.fill_bytes(&mut id);
+            let id = hex::encode(id);
+            let delivery = Delivery {
+                webhook: index,
+                body: body.clone(),
+                info: WebhookDelivery {
+                    id: id.clone(),
+                    url: webhook.url.to_string(),
+                    status: WebhookDeliveryStatus::Pending,
+                    attempts: 0,
+                    timestamp,
+                    last_error: None,
+                    payload: payload.clone(),
+                },
+            };
+
+            self.insert(delivery).await;
+            self.dispatch(id);
+        }
+    }
+
+    /// Keeps the given delivery, evicting the oldest finished delivery if
+    /// there is no room.
+    ///
+    /// Deliveries in flight are never evicted; if every kept delivery is in
+    /// flight, this waits until one finishes.
+    async fn insert(&self, delivery: Delivery) {
+        loop {
+            let finished = self.finished.notified();
+            tokio::pin!(finished);
+            // Register for notification before checking, so a delivery
+            // finishing in between is not missed
+            finished.as_mut().enable();
+
+            {
+                let mut deliveries = self.deliveries.lock().unwrap();
+                if deliveries.len() < self.capacity {
+                    deliveries.insert(delivery.info.id.clone(), delivery);
+                    return;
+                }
+
+                if let Some(oldest) = deliveries
+                    .values()
+                    .position(|d| d.info.status != WebhookDeliveryStatus::Pending)
+                {
+                    deliveries.shift_remove_index(oldest);
+                    deliveries.insert(delivery.info.id.clone(), delivery);
+                    return;
+                }
+            }
+
+            finished.await;
+        }
+    }
+
+    /// Spawns a task attempting the given delivery until it succeeds or
+    /// exhausts its attempts.
+    fn dispatch(self: &Arc<Self>, id: String) {
+        let dispatcher = self.clone();
+        tokio::spawn(async move { dispatcher.deliver(&id).await });
+    }
+
+    async fn deliver(&self, id: &str) {
+        loop {
+            let (webhook, body, attempt) = {
+                let mut deliveries = self.deliveries.lock().unwrap();
+                let Some(delivery) = deliveries.get_mut(id) else {
+                    return;
+                };
+                delivery.info.attempts += 1;
+                (
+                    &self.webhooks[delivery.webhook],
+                    delivery.body.clone(),
+                    delivery.info.attempts,
+                )
+            };
+
+            let result = self.send(webhook, id, body).await;
+            let failed = {
+                let mut deliveries = self.deliveries.lock().unwrap();
+                let Some(delivery) = deliveries.get_mut(id) else {
+                    return;
+                };
+                match result {
+                    Ok(()) => {
+                        delivery.info.status = WebhookDeliveryStatus::Delivered;
+                        delivery.info.last_error = None;
+                        self.finished.notify_waiters();
+                        return;
+                    }
+                    Err(e) => {
+                        delivery.info.last_error = Some(e);
+                        if attempt < self.max_attempts {
+                            None
+                        } else {
+                            delivery.info.status = WebhookDeliveryStatus::Failed;
+                            self.finished.notify_waiters();
+                            Some(delivery.info.clone())
+                        }
+                    }
+                }
+            };
+
+            match failed {
+                Some(delivery) => return self.dead_letter(delivery).await,
+                None => tokio::time::sleep(self.backoff(attempt)).await,
+            }
+        }
+    }
+
+    /// Sends a delivery to the given webhook.
+    async fn send(&self, webhook: &Webhook, id: &str, body: Vec<u8>) -> Result<(), String> {
+        let response = self
+            .client
+            .post(webhook.url.clone())
+            .timeout(DELIVERY_TIMEOUT)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(WEBHOOK_SIGNATURE_HEADER_NAME, webhook.signature(&body))
+            .header(WEBHOOK_DELIVERY_HEADER_NAME, id)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            Err(format!("endpoint responded with status {status}"))
+        }
+    }
+
+    /// Gets the delay before the attempt following the given one.
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.retry_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(MAX_RETRY_DELAY)
+    }
+
+    /// Records a delivery that exhausted its attempts.
+    async fn dead_letter(&self, delivery: WebhookDelivery) {
+        tracing::warn!(
+            "webhook delivery `{id}` to `{url}` failed after {attempts} attempt(s): {error}",
+            id = delivery.id,
+            url = delivery.url,
+            attempts = delivery.attempts,
+            error = delivery.last_error.as_deref().unwrap_or_default()
+        );
+
+        let Some(path) = &self.dead_letter_file else {
+            return;
+        };
+
+        let mut line = serde_json::to_vec(&delivery).expect("delivery should serialize");
+        line.push(b'\n');
+
+        let _guard = self.dead_letter_lock.lock().await;
+        let result = async {
+            tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?
+                .write_all(&line)
+                .await
+        }
+        .await;
+
+        if let Err(e) = result {
+            tracing::error!(
+                "failed to write webhook delivery `{id}` to dead-letter file `{path}`: {e}",
+                id = delivery.id,
+                path = path.display()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warg_crypto::hash::{Hash, HashAlgorithm};
+    use warg_protocol::registry::{LogId, RecordId};
+
+    #[test]
+    fn it_matches_package_patterns() {
+        let webhook = |patterns: &[&str]| {
+            patterns.iter().fold(
+                Webhook::new(
+                    "http://localhost/hook".parse().unwrap(),
+                    SecretString::new("secret".into()),
+                ),
+                |webhook, pattern| webhook.with_package(*pattern),
+            )
+        };
+        let name = |name: &str| PackageName::new(name).unwrap();
+
+        assert!(webhook(&[]).matches(&name("example:hello")));
+        assert!(webhook(&["example"]).matches(&name("example:hello")));
+        assert!(webhook(&["example:hello"]).matches(&name("example:hello")));
+        assert!(!webhook(&["example:hello"]).matches(&name("example:world")));
+        assert!(webhook(&["example-*"]).matches(&name("example-foo:hello")));
+        assert!(!webhook(&["example-*"]).matches(&name("example:hello")));
+        assert!(webhook(&["other", "example:hel*"]).matches(&name("example:hello")));
+    }
+
+    fn payload(name: &str) -> WebhookPayload {
+        let name = PackageName::new(name).unwrap();
+        let digest = AnyHash::from(Hash::<Sha256>::of(name.as_ref()));
+        WebhookPayload {
+            log_id: LogId::package_log_for(HashAlgorithm::Sha256, &name),
+            package: name,
+            record_id: RecordId::from(digest.clone()),
+            registry_index: 0,
+            checkpoint: digest,
+            entries: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_waits_for_room_instead_of_evicting_deliveries_in_flight() {
+        // The endpoint accepts connections but never responds, keeping
+        // deliveries in flight
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{addr}/hook", addr = listener.local_addr().unwrap());
+        let dispatcher = Arc::new(WebhookDispatcher {
+            capacity: 1,
+            ..Arc::into_inner(WebhookDispatcher::new(
+                vec![Webhook::new(
+                    url.parse().unwrap(),
+                    SecretString::new("secret".into()),
+                )],
+                1,
+                DEFAULT_WEBHOOK_RETRY_DELAY,
+                None,
+            ))
+            .unwrap()
+        });
+
+        dispatcher.enqueue(payload("example:first")).await;
+        let in_flight = dispatcher.deliveries(None, 10);
+        assert_eq!(in_flight.len(), 1);
+        assert_eq!(in_flight[0].status, WebhookDeliveryStatus::Pending);
+
+        let second = tokio::spawn({
+            let dispatcher = dispatcher.clone();
+            async move { dispatcher.enqueue(payload("example:second")).await }
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!second.is_finished());
+        let deliveries = dispatcher.deliveries(None, 10);
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].id, in_flight[0].id);
+        assert_eq!(deliveries[0].status, WebhookDeliveryStatus::Pending);
+
+        // Once the delivery in flight finishes, it makes room for the next
+        {
+            let mut deliveries = dispatcher.deliveries.lock().unwrap();
+            deliveries[0].info.status = WebhookDeliveryStatus::Failed;
+            dispatcher.finished.notify_waiters();
+        }
+        tokio::time::timeout(Duration::from_secs(5), second)
+            .await
+            .unwrap()
+            .unwrap();
+        let deliveries = dispatcher.deliveries(None, 10);
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].payload.package.as_ref(), "example:second");
+    }
+}
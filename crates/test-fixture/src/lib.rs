@@ -0,0 +1,452 @@
+//! An in-process Warg registry for end-to-end tests.
+//!
+//! [`TestRegistry::start`] spawns a registry server backed by an in-memory
+//! data store in a temporary directory and exposes the keys and client
+//! configuration needed to interact with it.
+//!
+//! The server runs as a task on the current tokio runtime; dropping the
+//! registry shuts the server down and waits for it to exit, so tests using
+//! the fixture must run on a multi-threaded runtime:
+//!
+//! ```ignore
+//! #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+//! async fn it_publishes() -> anyhow::Result<()> {
+//!     let registry = warg_test_fixture::TestRegistry::start().await?;
+//!     let published = registry
+//!         .publish_package("test:foo", "1.0.0", b"\0asm\x0d\0\x01\0")
+//!         .await?;
+//!
+//!     let client = registry.client()?;
+//!     client.upsert([&published.name]).await?;
+//!     registry.shutdown().await
+//! }
+//! ```
+
+#![deny(missing_docs)]
+
+use anyhow::{bail, Context, Result};
+use indexmap::IndexSet;
+use std::{
+    borrow::Cow,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime},
+};
+use tempfile::TempDir;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use warg_api::v1::{package::PublishRecordRequest, paths};
+use warg_client::{
+    api,
+    storage::{ContentStorage, PublishEntry, PublishInfo},
+    ClientError, Config, FileSystemClient, StorageLockResult,
+};
+use warg_crypto::{
+    hash::{AnyHash, HashAlgorithm, Sha256},
+    signing::{generate_p256_pair, PrivateKey},
+};
+use warg_protocol::{
+    operator,
+    package::{PackageEntry, PackageRecord, PACKAGE_RECORD_VERSION},
+    registry::{Checkpoint, LogId, PackageName, RecordId},
+    ProtoEnvelope, ProtoEnvelopeBody, Version,
+};
+use warg_server::{
+    limits::SharedLimits,
+    policy::{content::WasmContentPolicy, record::AuthorizedKeyPolicy},
+    Server,
+};
+
+/// The namespace defined by the test registry.
+pub const TEST_NAMESPACE: &str = "test";
+
+/// The admin token tests configure registries with to use the admin API.
+pub const ADMIN_TOKEN: &str = "secret-admin-token";
+
+/// The interval at which the test registry produces checkpoints.
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The interval at which the fixture polls the registry.
+const POLL_INTERVAL: Duration = Duration::from_millis(25);
+
+/// The number of checkpoint intervals to wait for a new checkpoint.
+const CHECKPOINT_WAIT_INTERVALS: u32 = 5;
+
+/// A registry server running in-process for the duration of a test.
+///
+/// Content and client storage live in a temporary directory that is removed
+/// when the registry is dropped.
+pub struct TestRegistry {
+    url: String,
+    operator_key: PrivateKey,
+    publisher_key: PrivateKey,
+    next_client: AtomicUsize,
+    http: reqwest::Client,
+    limits: SharedLimits,
+    shutdown: CancellationToken,
+    task: Option<JoinHandle<()>>,
+    root: TempDir,
+}
+
+impl TestRegistry {
+    /// Starts a new test registry.
+    ///
+    /// The registry defines the [`TEST_NAMESPACE`] namespace, accepts only
+    /// WebAssembly content, and authorizes only the key returned by
+    /// [`TestRegistry::publisher_key`] to publish to the namespace.
+    pub async fn start() -> Result<Self> {
+        Self::start_with_config(|config| config).await
+    }
+
+    /// Starts a new test registry, customizing the server configuration with
+    /// the given function.
+    pub async fn start_with_config(
+        configure: impl FnOnce(warg_server::Config) -> warg_server::Config,
+    ) -> Result<Self> {
+        Self::start_with_namespace_and_config(TEST_NAMESPACE, configure).await
+    }
+
+    /// Starts a new test registry that defines the given namespace instead
+    /// of [`TEST_NAMESPACE`].
+    ///
+    /// The key returned by [`TestRegistry::publisher_key`] is authorized to
+    /// publish to the namespace.
+    pub async fn start_with_namespace(namespace: &str) -> Result<Self> {
+        Self::start_with_namespace_and_config(namespace, |config| config).await
+    }
+
+    async fn start_with_namespace_and_config(
+        namespace: &str,
+        configure: impl FnOnce(warg_server::Config) -> warg_server::Config,
+    ) -> Result<Self> {
+        let root = TempDir::new().context("failed to create registry directory")?;
+        let (_, operator_key) = generate_p256_pair();
+        let (publisher_public_key, publisher_key) = generate_p256_pair();
+
+        let server_dir = root.path().join("server");
+        std::fs::create_dir_all(&server_dir)?;
+
+        let shutdown = CancellationToken::new();
+        let config = warg_server::Config::new(
+            PrivateKey::decode(operator_key.encode().to_string())?,
+            Some(vec![(
+                namespace.to_string(),
+                operator::NamespaceState::Defined,
+            )]),
+            server_dir,
+        )
+        .with_addr(([127, 0, 0, 1], 0))
+        .with_shutdown(shutdown.clone().cancelled_owned())
+        .with_checkpoint_interval(CHECKPOINT_INTERVAL)
+        .with_content_policy(WasmContentPolicy::default())
+        .with_record_policy(
+            AuthorizedKeyPolicy::new()
+                .with_namespace_key(namespace, publisher_public_key.fingerprint())?,
+        );
+
+        let server = Server::new(configure(config)).initialize().await?;
+        let addr = server.local_addr()?;
+        let limits = server.limits();
+        tracing::debug!("test registry running at {addr}");
+
+        let task = tokio::spawn(async move {
+            if let Err(e) = server.serve().await {
+                tracing::error!("test registry failed: {e:?}");
+            }
+        });
+
+        Ok(Self {
+            url: format!("http://{addr}"),
+            operator_key,
+            publisher_key,
+            next_client: AtomicUsize::new(0),
+            http: reqwest::Client::new(),
+            limits,
+            shutdown,
+            task: Some(task),
+            root,
+        })
+    }
+
+    /// Gets the URL of the registry.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Gets the operator key of the registry.
+    pub fn operator_key(&self) -> &PrivateKey {
+        &self.operator_key
+    }
+
+    /// Gets the key authorized to publish to the namespace of the registry.
+    pub fn publisher_key(&self) -> &PrivateKey {
+        &self.publisher_key
+    }
+
+    /// Gets a handle to the limits in effect in the registry.
+    pub fn limits(&self) -> &SharedLimits {
+        &self.limits
+    }
+
+    /// Gets the path of the content with the given digest in the registry's
+    /// content storage.
+    pub fn content_path(&self, digest: &AnyHash) -> PathBuf {
+        self.root
+            .path()
+            .join("server")
+            .join("files")
+            .join(digest.to_string().replace(':', "-"))
+    }
+
+    /// Creates a client configuration for the registry.
+    ///
+    /// Each configuration uses its own client storage, so clients created from
+    /// different configurations share no local state.
+    pub fn client_config(&self) -> Config {
+        let id = self.next_client.fetch_add(1, Ordering::SeqCst);
+        let dir = self.client_dir(id);
+
+        Config {
+            home_url: Some(self.url.clone()),
+            registries: Default::default(),
+            namespace_registries: Default::default(),
+            registries_dir: Some(dir.join("registries")),
+            content_dir: Some(dir.join("content")),
+            content_fallback_dirs: Vec::new(),
+            namespace_map_path: Some(dir.join("namespaces")),
+            trust_policy_path: Some(dir.join("trust.json")),
+            keys: IndexSet::new(),
+            default_key: None,
+            keyring_auth: false,
+            stale_checkpoint: Default::default(),
+            checkpoint_grace_period: None,
+            offline: false,
+            retry: None,
+            storage: Default::default(),
+        }
+    }
+
+    /// Creates a client for the registry with its own client storage.
+    pub fn client(&self) -> Result<FileSystemClient> {
+        client_with_config(&self.client_config())
+    }
+
+    /// Creates an API client for the registry.
+    pub fn api_client(&self) -> Result<api::Client> {
+        api::Client::new(self.url.as_str(), None)
+    }
+
+    /// Builds a request submitting the given record to the log of the given
+    /// package, for a registry using SHA-256 log identifiers.
+    ///
+    /// Unlike publishing with a client, nothing is checked before the record
+    /// is submitted and the response is not interpreted, so tests can send
+    /// any record and inspect the registry's response.
+    pub fn submit_record(
+        &self,
+        name: &PackageName,
+        record: impl Into<ProtoEnvelopeBody>,
+    ) -> reqwest::RequestBuilder {
+        self.http
+            .post(format!(
+                "{url}/{path}",
+                url = self.url,
+                path = paths::publish_package_record(&LogId::package_log::<Sha256>(name))
+            ))
+            .json(&PublishRecordRequest {
+                package_name: Cow::Borrowed(name),
+                record: Cow::Owned(record.into()),
+                content_sources: Default::default(),
+            })
+    }
+
+    /// Publishes a release of a package with the given content using the
+    /// publisher key.
+    ///
+    /// The package is initialized if it does not yet exist.
+    ///
+    /// Returns once the release has been published, with the content digest.
+    pub async fn publish_simple(
+        &self,
+        name: &str,
+        version: &str,
+        bytes: impl Into<Vec<u8>>,
+    ) -> Result<AnyHash> {
+        Ok(self.publish_package(name, version, bytes).await?.digest)
+    }
+
+    /// Publishes a release of a package with the given content using the
+    /// publisher key.
+    ///
+    /// The package is initialized if it does not yet exist.
+    ///
+    /// Returns once a checkpoint includes the release.
+    pub async fn publish_package(
+        &self,
+        name: &str,
+        version: &str,
+        bytes: impl Into<Vec<u8>>,
+    ) -> Result<PublishedPackage> {
+        let name = PackageName::new(name)?;
+        let version: Version = version
+            .parse()
+            .with_context(|| format!("invalid version `{version}`"))?;
+        let content = bytes.into();
+
+        let client = self.client()?;
+        let digest = client
+            .content()
+            .store_content(
+                Box::pin(futures::stream::once(async move { Ok(content.into()) })),
+                None,
+            )
+            .await?;
+
+        let release = PublishEntry::Release {
+            version: version.clone(),
+            content: digest.clone(),
+            artifacts: Default::default(),
+            metadata: None,
+        };
+
+        let record_id = match client
+            .publish_with_info(
+                &self.publisher_key,
+                PublishInfo {
+                    name: name.clone(),
+                    head: None,
+                    entries: vec![release.clone()],
+                },
+            )
+            .await
+        {
+            Ok(record_id) => record_id,
+            Err(
+                ClientError::MustInitializePackage { .. }
+                | ClientError::PackageDoesNotExist { .. }
+                | ClientError::PackageDoesNotExistWithHint { .. },
+            ) => {
+                client
+                    .publish_with_info(
+                        &self.publisher_key,
+                        PublishInfo {
+                            name: name.clone(),
+                            head: None,
+                            entries: vec![PublishEntry::Init, release],
+                        },
+                    )
+                    .await?
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let result = client
+            .wait_for_publish(&name, &record_id, POLL_INTERVAL)
+            .await?;
+        if let Some(reason) = result.reason() {
+            bail!("failed to publish `{name}`: {reason}");
+        }
+
+        Ok(PublishedPackage {
+            name,
+            version,
+            digest,
+            record_id,
+        })
+    }
+
+    /// Waits for the registry to produce a new checkpoint.
+    ///
+    /// Records accepted by the registry are only visible to clients once a
+    /// checkpoint includes them; this waits until the log has grown beyond the
+    /// latest checkpoint at the time of the call. If no records are pending,
+    /// the latest checkpoint is returned after a few checkpoint intervals.
+    pub async fn advance_checkpoint(&self) -> Result<Checkpoint> {
+        let client = self.api_client()?;
+        let start = client
+            .latest_checkpoint()
+            .await?
+            .as_ref()
+            .checkpoint
+            .clone();
+
+        let deadline =
+            tokio::time::Instant::now() + CHECKPOINT_INTERVAL * CHECKPOINT_WAIT_INTERVALS;
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let latest = client
+                .latest_checkpoint()
+                .await?
+                .as_ref()
+                .checkpoint
+                .clone();
+            if latest.log_length > start.log_length || tokio::time::Instant::now() >= deadline {
+                return Ok(latest);
+            }
+        }
+    }
+
+    /// Shuts down the registry and waits for the server to exit.
+    pub async fn shutdown(mut self) -> Result<()> {
+        self.shutdown.cancel();
+        if let Some(task) = self.task.take() {
+            task.await.context("test registry task panicked")?;
+        }
+
+        Ok(())
+    }
+
+    fn client_dir(&self, id: usize) -> PathBuf {
+        self.root.path().join("clients").join(id.to_string())
+    }
+}
+
+impl Drop for TestRegistry {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+        if let Some(task) = self.task.take() {
+            futures::executor::block_on(async move {
+                task.await.ok();
+            });
+        }
+    }
+}
+
+/// A release published to a [`TestRegistry`].
+#[derive(Debug, Clone)]
+pub struct PublishedPackage {
+    /// The name of the package.
+    pub name: PackageName,
+    /// The version of the release.
+    pub version: Version,
+    /// The digest of the release content.
+    pub digest: AnyHash,
+    /// The identifier of the record that published the release.
+    pub record_id: RecordId,
+}
+
+/// Creates a record initializing a package log with the given key, signed
+/// by the key.
+pub fn init_record(signing_key: &PrivateKey) -> Result<ProtoEnvelope<PackageRecord>> {
+    Ok(ProtoEnvelope::signed_contents(
+        signing_key,
+        PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: signing_key.public_key(),
+            }],
+        },
+    )?)
+}
+
+/// Creates a client with the given configuration.
+pub fn client_with_config(config: &Config) -> Result<FileSystemClient> {
+    match FileSystemClient::try_new_with_config(None, config, None)? {
+        StorageLockResult::Acquired(client) => Ok(client),
+        _ => bail!("failed to acquire storage lock"),
+    }
+}
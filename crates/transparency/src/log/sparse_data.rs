@@ -24,10 +24,7 @@ where
 {
     fn get_index(&self, node: Node) -> Option<usize> {
         let result = self.data.binary_search_by_key(&node, |entry| entry.0);
-        match result {
-            Ok(index) => Some(index),
-            Err(_) => None,
-        }
+        result.ok()
     }
 }
 
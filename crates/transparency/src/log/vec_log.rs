@@ -14,7 +14,7 @@ use super::{hash_branch, hash_empty, hash_leaf, Checkpoint, LogBuilder, LogData}
 
 /// A verifiable log where the node hashes are stored
 /// contiguously in memory by index.
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct VecLog<D, V>
 where
     D: SupportedDigest,
@@ -28,6 +28,21 @@ where
     _value: PhantomData<V>,
 }
 
+// Implemented manually as neither the digest nor the value type need be `Clone`
+impl<D, V> Clone for VecLog<D, V>
+where
+    D: SupportedDigest,
+    V: VisitBytes,
+{
+    fn clone(&self) -> Self {
+        Self {
+            length: self.length,
+            tree: self.tree.clone(),
+            _value: PhantomData,
+        }
+    }
+}
+
 /// Height is the number of child-edges between the node and leaves
 /// A leaf has height 0
 ///
@@ -6,7 +6,7 @@ use warg_crypto::VisitBytes;
 
 use super::link::Link;
 use super::node::Node;
-use super::path::Path;
+use super::path::{tree_height, Path};
 use super::proof::Proof;
 
 /// Immutable Map w/ Inclusion Proofs
@@ -112,7 +112,7 @@ where
 {
     fn default() -> Self {
         Self {
-            link: Link::new(Node::Empty(256)),
+            link: Link::new(Node::Empty(tree_height::<D>())),
             len: 0,
             _key: PhantomData,
             _value: PhantomData,
@@ -164,7 +164,7 @@ where
 impl<D, K, V> Map<D, K, V>
 where
     D: SupportedDigest,
-    K: VisitBytes + Clone + ?Sized,
+    K: VisitBytes + Clone,
     V: VisitBytes + Clone,
 {
     /// The hash of the root of the tree.
@@ -26,7 +26,7 @@ pub use proof_bundle::ProofBundle as MapProofBundle;
 #[cfg(test)]
 mod test {
     use warg_crypto::{
-        hash::{Sha256, SupportedDigest},
+        hash::{Sha256, Sha512, SupportedDigest},
         VisitBytes,
     };
 
@@ -135,4 +135,17 @@ mod test {
         let fourth = third.insert("foo", "qux");
         check(&fourth, "foo", "qux");
     }
+
+    #[test]
+    fn prove_sha512() {
+        let first = Map::<Sha512, &'static str, &'static str>::default();
+        assert_eq!(&first.root().clone(), Sha512::empty_tree_hash(512));
+
+        let second = first.insert("foo", "bar").insert("bar", "bat");
+        for (key, value) in [("foo", "bar"), ("bar", "bat")] {
+            let proof = second.prove(key).unwrap();
+            assert_eq!(second.root().clone(), proof.evaluate(&key, &value));
+        }
+        assert!(second.prove("qux").is_none());
+    }
 }
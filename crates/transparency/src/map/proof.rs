@@ -9,7 +9,7 @@ use warg_crypto::{
 
 use super::{
     map::{hash_branch, hash_leaf},
-    path::{ReversePath, Side},
+    path::{tree_height, ReversePath, Side},
 };
 
 /// An inclusion proof of the specified value in a map
@@ -64,7 +64,7 @@ where
         // Get the path from bottom to top.
         let path = ReversePath::<D>::new(Hash::of(key));
 
-        let fill = repeat(None).take(256 - self.peers.len());
+        let fill = repeat(None).take(tree_height::<D>() - self.peers.len());
         // Calculate the leaf hash.
         let mut hash = hash_leaf(value);
 
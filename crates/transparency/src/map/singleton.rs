@@ -51,7 +51,7 @@ impl<D: SupportedDigest> Singleton<D> {
         if self.key() == &key {
             let new_singleton = Singleton::new(key, value, path.height() + 1);
             (Node::Singleton(new_singleton), false)
-        } else if cur_path.get(256 - self.height) != cur_side {
+        } else if cur_path.get(self.key.bit_len() - self.height) != cur_side {
             let node = Node::Singleton(Singleton::new(key, value, path.height()));
             let original = Node::Singleton(Singleton::new(
                 self.key.clone(),
@@ -74,10 +74,10 @@ impl<D: SupportedDigest> Singleton<D> {
             let fork = match cur_side {
                 Side::Left => Fork::new(
                     Arc::new(Link::new(down_one)),
-                    Arc::new(Link::new(Node::Empty(256 - cur_index))),
+                    Arc::new(Link::new(Node::Empty(self.key.bit_len() - cur_index))),
                 ),
                 Side::Right => Fork::new(
-                    Arc::new(Link::new(Node::Empty(256 - cur_index))),
+                    Arc::new(Link::new(Node::Empty(self.key.bit_len() - cur_index))),
                     Arc::new(Link::new(down_one)),
                 ),
             };
@@ -0,0 +1,127 @@
+//! Monitors a Warg registry's checkpoints.
+//!
+//! The monitor tails the checkpoints issued by the registry and verifies that
+//! each one is consistent with the one before it, proving that the registry
+//! log only ever grows.
+//!
+//! ```text
+//! cargo run --example monitor -- --registry http://127.0.0.1:8090
+//! ```
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use std::time::Duration;
+use warg_client::api;
+use warg_crypto::hash::AnyHash;
+use warg_protocol::registry::Checkpoint;
+
+/// Tails the checkpoints of a registry, verifying their consistency.
+#[derive(Parser)]
+#[clap(name = "monitor")]
+struct Args {
+    /// The URL of the registry to monitor.
+    #[clap(long, value_name = "URL")]
+    registry: String,
+    /// The identifier of the checkpoint to start monitoring after; defaults
+    /// to the first checkpoint issued by the registry.
+    #[clap(long, value_name = "CHECKPOINT")]
+    since: Option<AnyHash>,
+    /// Exit after verifying the given number of checkpoints.
+    #[clap(long, value_name = "N")]
+    count: Option<usize>,
+    /// The number of seconds to wait for a new checkpoint before asking again
+    /// (defaults to 30).
+    #[clap(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let client = api::Client::new(args.registry.as_str(), None)?;
+
+    let mut since = args.since;
+    let mut previous = match &since {
+        Some(id) => Some(
+            client
+                .fetch_checkpoint(id)
+                .await?
+                .as_ref()
+                .checkpoint
+                .clone(),
+        ),
+        None => None,
+    };
+    let mut verified = 0;
+    loop {
+        let response = client.list_checkpoints(since.as_ref(), None).await?;
+        for ts_checkpoint in &response.checkpoints {
+            let checkpoint = &ts_checkpoint.as_ref().checkpoint;
+            if let Some(previous) = &previous {
+                verify(&client, previous, checkpoint).await?;
+            }
+
+            println!(
+                "verified checkpoint at log length {length} with log root `{root}`",
+                length = checkpoint.log_length,
+                root = checkpoint.log_root
+            );
+            verified += 1;
+            if args.count == Some(verified) {
+                return Ok(());
+            }
+
+            since = Some(AnyHash::of(checkpoint.log_root.algorithm(), checkpoint));
+            previous = Some(checkpoint.clone());
+        }
+
+        if response.more {
+            continue;
+        }
+
+        // Wait for the registry to issue a checkpoint after the last one seen
+        if let Some(since) = &since {
+            client
+                .wait_for_checkpoint(since, Duration::from_secs(args.timeout.unwrap_or(30)))
+                .await?;
+        } else {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+/// Verifies that the log of a checkpoint only grew from that of the
+/// checkpoint issued before it.
+async fn verify(client: &api::Client, from: &Checkpoint, to: &Checkpoint) -> Result<()> {
+    if to.log_length < from.log_length {
+        bail!(
+            "checkpoint at log length {to_length} follows checkpoint at log length {from_length}",
+            to_length = to.log_length,
+            from_length = from.log_length
+        );
+    }
+
+    if to.log_length == from.log_length {
+        if to.log_root != from.log_root {
+            bail!(
+                "checkpoints at log length {length} have different log roots `{from_root}` and `{to_root}`",
+                length = to.log_length,
+                from_root = from.log_root,
+                to_root = to.log_root
+            );
+        }
+
+        return Ok(());
+    }
+
+    client
+        .prove_checkpoint_consistency(&from.log_root, &to.log_root)
+        .await
+        .with_context(|| {
+            format!(
+                "failed to verify consistency from log length {from_length} to {to_length}",
+                from_length = from.log_length,
+                to_length = to.log_length
+            )
+        })
+}
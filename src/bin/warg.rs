@@ -1,19 +1,17 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use dialoguer::{theme::ColorfulTheme, Confirm};
 use std::process::exit;
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::{filter::LevelFilter, EnvFilter};
 use warg_cli::commands::{
-    BundleCommand, ClearCommand, ConfigCommand, DependenciesCommand, DownloadCommand, InfoCommand,
-    KeyCommand, LockCommand, LoginCommand, LogoutCommand, PublishCommand, ResetCommand, Retry,
-    UpdateCommand,
+    version, BundleCommand, CleanCommand, ClearCommand, CompletionsCommand, ConfigCommand,
+    DependenciesCommand, DownloadCommand, FetchCommand, InfoCommand, KeyCommand, LockCommand,
+    LogCommand, LoginCommand, LogoutCommand, OperatorCommand, PublishCommand, ResetCommand, Retry,
+    SbomCommand, TrustCommand, UpdateCommand, VerifyCommand, VersionCommand, WatchCommand,
+    WitCommand,
 };
 use warg_client::ClientError;
 
-fn version() -> &'static str {
-    option_env!("CARGO_VERSION_INFO").unwrap_or(env!("CARGO_PKG_VERSION"))
-}
-
 /// Warg component registry client.
 #[derive(Parser)]
 #[clap(
@@ -29,21 +27,38 @@ enum WargCli {
     Key(KeyCommand),
     Lock(LockCommand),
     Bundle(BundleCommand),
+    Sbom(SbomCommand),
     Dependencies(DependenciesCommand),
     Download(DownloadCommand),
+    Log(LogCommand),
     Update(UpdateCommand),
+    Verify(VerifyCommand),
+    Watch(WatchCommand),
+    Wit(WitCommand),
+    Fetch(FetchCommand),
     #[clap(subcommand)]
     Publish(PublishCommand),
+    #[clap(subcommand)]
+    Operator(OperatorCommand),
+    #[clap(subcommand)]
+    Trust(TrustCommand),
     Reset(ResetCommand),
     Clear(ClearCommand),
+    Clean(CleanCommand),
     Login(LoginCommand),
     Logout(LogoutCommand),
+    Completions(CompletionsCommand),
+    Version(VersionCommand),
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
+        .with_env_filter(
+            EnvFilter::builder()
+                .with_default_directive(LevelFilter::WARN.into())
+                .from_env_lossy(),
+        )
         .init();
 
     if let Err(e) = match WargCli::parse() {
@@ -52,14 +67,25 @@ async fn main() -> Result<()> {
         WargCli::Key(cmd) => cmd.exec().await,
         WargCli::Lock(cmd) => cmd.exec(None).await,
         WargCli::Bundle(cmd) => cmd.exec(None).await,
+        WargCli::Sbom(cmd) => cmd.exec().await,
         WargCli::Dependencies(cmd) => cmd.exec(None).await,
         WargCli::Download(cmd) => cmd.exec(None).await,
+        WargCli::Log(cmd) => cmd.exec().await,
         WargCli::Update(cmd) => cmd.exec(None).await,
+        WargCli::Verify(cmd) => cmd.exec().await,
+        WargCli::Watch(cmd) => cmd.exec().await,
+        WargCli::Wit(cmd) => cmd.exec(None).await,
+        WargCli::Fetch(cmd) => cmd.exec().await,
         WargCli::Publish(cmd) => cmd.exec(None).await,
+        WargCli::Operator(cmd) => cmd.exec().await,
+        WargCli::Trust(cmd) => cmd.exec().await,
         WargCli::Reset(cmd) => cmd.exec().await,
         WargCli::Clear(cmd) => cmd.exec().await,
+        WargCli::Clean(cmd) => cmd.exec().await,
         WargCli::Login(cmd) => cmd.exec().await,
         WargCli::Logout(cmd) => cmd.exec().await,
+        WargCli::Completions(cmd) => cmd.exec(WargCli::command()),
+        WargCli::Version(cmd) => cmd.exec().await,
     } {
         if let Some(e) = e.downcast_ref::<ClientError>() {
             describe_client_error_or_retry(e).await?;
@@ -77,6 +103,24 @@ async fn describe_client_error_or_retry(e: &ClientError) -> Result<()> {
         ClientError::NoHomeRegistryUrl => {
             eprintln!("error: {e}; use the `config` subcommand to set a home registry URL");
         }
+        ClientError::StaleRegistry { .. } => {
+            eprintln!("error: {e}");
+            eprintln!("use `--allow-stale` to use the registry's state anyway");
+        }
+        ClientError::UntrustedPublisher { name, key_id, .. } => {
+            eprintln!("error: {e}");
+            eprintln!("use `warg trust add {name} {key_id}` to trust the key");
+        }
+        ClientError::PrevalidationFailed { .. } => {
+            eprintln!("error: {e}");
+            eprintln!(
+                "use `--skip-prevalidation` if the package log in client storage is out of date"
+            );
+        }
+        ClientError::NamespaceNotRouted { namespace, .. } => {
+            eprintln!("error: {e}");
+            eprintln!("use `warg config route add {namespace} <REGISTRY>` to route the namespace");
+        }
         ClientError::PackageValidationFailed { name, inner } => {
             eprintln!("error: the log for package `{name}` is invalid: {inner}")
         }
@@ -117,6 +161,8 @@ async fn describe_client_error_or_retry(e: &ClientError) -> Result<()> {
                             )))
                             .await
                         }
+                        WargCli::Sbom(cmd) => cmd.exec().await,
+                        WargCli::Fetch(cmd) => cmd.exec().await,
                         WargCli::Dependencies(cmd) => {
                             cmd.exec(Some(Retry::new(
                                 namespace.to_string(),
@@ -138,6 +184,16 @@ async fn describe_client_error_or_retry(e: &ClientError) -> Result<()> {
                             )))
                             .await
                         }
+                        WargCli::Log(cmd) => cmd.exec().await,
+                        WargCli::Verify(cmd) => cmd.exec().await,
+                        WargCli::Watch(cmd) => cmd.exec().await,
+                        WargCli::Wit(cmd) => {
+                            cmd.exec(Some(Retry::new(
+                                namespace.to_string(),
+                                registry.to_string(),
+                            )))
+                            .await
+                        }
                         WargCli::Publish(cmd) => {
                             cmd.exec(Some(Retry::new(
                                 namespace.to_string(),
@@ -145,10 +201,15 @@ async fn describe_client_error_or_retry(e: &ClientError) -> Result<()> {
                             )))
                             .await
                         }
+                        WargCli::Operator(cmd) => cmd.exec().await,
+                        WargCli::Trust(cmd) => cmd.exec().await,
                         WargCli::Reset(cmd) => cmd.exec().await,
                         WargCli::Clear(cmd) => cmd.exec().await,
+                        WargCli::Clean(cmd) => cmd.exec().await,
                         WargCli::Login(cmd) => cmd.exec().await,
                         WargCli::Logout(cmd) => cmd.exec().await,
+                        WargCli::Completions(cmd) => cmd.exec(WargCli::command()),
+                        WargCli::Version(cmd) => cmd.exec().await,
                     } {
                         if let Some(e) = e.downcast_ref::<ClientError>() {
                             describe_client_error(e).await?;
@@ -172,6 +233,24 @@ async fn describe_client_error(e: &ClientError) -> Result<()> {
         ClientError::NoHomeRegistryUrl => {
             eprintln!("error: {e}; use the `config` subcommand to set a default URL");
         }
+        ClientError::StaleRegistry { .. } => {
+            eprintln!("error: {e}");
+            eprintln!("use `--allow-stale` to use the registry's state anyway");
+        }
+        ClientError::UntrustedPublisher { name, key_id, .. } => {
+            eprintln!("error: {e}");
+            eprintln!("use `warg trust add {name} {key_id}` to trust the key");
+        }
+        ClientError::PrevalidationFailed { .. } => {
+            eprintln!("error: {e}");
+            eprintln!(
+                "use `--skip-prevalidation` if the package log in client storage is out of date"
+            );
+        }
+        ClientError::NamespaceNotRouted { namespace, .. } => {
+            eprintln!("error: {e}");
+            eprintln!("use `warg config route add {namespace} <REGISTRY>` to route the namespace");
+        }
         ClientError::PackageValidationFailed { name, inner } => {
             eprintln!("error: the log for package `{name}` is invalid: {inner}")
         }
@@ -16,37 +16,61 @@ use warg_credentials::keyring::{get_auth_token, get_signing_key};
 use warg_crypto::signing::PrivateKey;
 
 mod bundle;
+mod clean;
 mod clear;
+mod completions;
 mod config;
 mod dependencies;
 mod download;
+mod fetch;
 mod info;
 mod key;
 mod lock;
+mod log;
 mod login;
 mod logout;
+mod operator;
+mod output;
 mod publish;
 mod reset;
+mod sbom;
+mod trust;
 mod update;
+mod verify;
+mod version;
+mod watch;
+mod wit;
 
 pub use self::bundle::*;
+pub use self::clean::*;
 pub use self::clear::*;
+pub use self::completions::*;
 pub use self::config::*;
 pub use self::dependencies::*;
 pub use self::download::*;
+pub use self::fetch::*;
 pub use self::info::*;
 pub use self::key::*;
 pub use self::lock::*;
+pub use self::log::*;
 pub use self::login::*;
 pub use self::logout::*;
+pub use self::operator::*;
+pub use self::output::*;
 pub use self::publish::*;
 pub use self::reset::*;
+pub use self::sbom::*;
+pub use self::trust::*;
 pub use self::update::*;
+pub use self::verify::*;
+pub use self::version::*;
+pub use self::watch::*;
+pub use self::wit::*;
 
 /// Common options for commands.
 #[derive(Args)]
 pub struct CommonOptions {
-    /// The URL of the registry to use.
+    /// The URL or configured alias of the registry to use.
     #[clap(long, value_name = "URL")]
     pub registry: Option<String>,
     /// The path to the client configuration file to use.
@@ -54,8 +78,26 @@ pub struct CommonOptions {
     /// If not specified, the following locations are searched in order: `./warg-config.json`, `<system-config-dir>/warg/config.json`.
     ///
     /// If no configuration file is found, a default configuration is used.
-    #[clap(long, value_name = "CONFIG")]
+    #[clap(long, value_name = "CONFIG", global = true)]
     pub config: Option<PathBuf>,
+    /// Serve package logs and content from client storage only, without
+    /// contacting the registry.
+    #[clap(long)]
+    pub offline: bool,
+    /// Use the registry's state even if its latest checkpoint has expired.
+    #[clap(long)]
+    pub allow_stale: bool,
+    /// Fail when a fetched package record is signed by a key that is not
+    /// trusted for the package, instead of warning.
+    #[clap(long)]
+    pub strict_trust: bool,
+    /// Publish records without first validating them against the package
+    /// log in client storage, such as when it is known to be out of date.
+    #[clap(long)]
+    pub skip_prevalidation: bool,
+    /// The format of the command's output (`human` or `json`).
+    #[clap(long, value_name = "FORMAT", default_value = "human")]
+    pub format: OutputFormat,
 }
 
 impl CommonOptions {
@@ -72,50 +114,109 @@ impl CommonOptions {
             .unwrap_or_default())
     }
 
+    /// Gets the URL of the registry selected with `--registry`, if any.
+    ///
+    /// Registry aliases are resolved using the given configuration.
+    pub fn registry_url(&self, config: &Config) -> Option<String> {
+        self.registry
+            .as_deref()
+            .map(|registry| config.resolve_registry(registry).to_string())
+    }
+
+    /// Gets the output of the command in the selected format.
+    pub fn output(&self) -> Output {
+        Output::new(self.format)
+    }
+
     /// Creates the warg client to use.
     pub async fn create_client(
         &self,
         config: &Config,
         retry: Option<Retry>,
     ) -> Result<FileSystemClient, ClientError> {
-        let client = match FileSystemClient::try_new_with_config(
+        let mut client = match FileSystemClient::try_new_with_config(
             self.registry.as_deref(),
             config,
             self.auth_token(config)?,
         )? {
             StorageLockResult::Acquired(client) => Ok(client),
             StorageLockResult::NotAcquired(path) => {
-                println!(
+                self.output().status(format_args!(
                     "blocking on lock for directory `{path}`...",
                     path = path.display()
-                );
+                ));
 
                 FileSystemClient::new_with_config(
                     self.registry.as_deref(),
                     config,
-                    self.auth_token(config)?.map(Secret::from),
+                    self.auth_token(config)?,
                 )
             }
-        }?;
+        }?
+        .with_offline(config.offline || self.offline)
+        .with_allow_stale(self.allow_stale)
+        .with_strict_trust(self.strict_trust)
+        .with_skip_prevalidation(self.skip_prevalidation);
+
+        // Registries serving routed namespaces are authenticated with their own tokens
+        if config.keyring_auth {
+            for (pattern, registry) in &config.namespace_registries {
+                let url = RegistryUrl::new(config.resolve_registry(registry))?;
+                let auth_token = get_auth_token(&url)?;
+                client =
+                    client.with_namespace_registry(pattern.clone(), url.to_string(), auth_token)?;
+            }
+        }
+
         if let Some(retry) = retry {
             retry.store_namespace(&client).await?;
         }
         Ok(client)
     }
 
+    /// Reports the registry the client uses for the namespace it was last
+    /// refreshed for.
+    pub fn report_registry<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage>(
+        &self,
+        client: &Client<R, C, N>,
+    ) {
+        self.output().status(format_args!(
+            "using registry `{registry}`",
+            registry = registry_name(client)
+        ));
+    }
+
     /// Gets the signing key for the given registry URL.
     pub fn signing_key<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage>(
         &self,
         client: &Client<R, C, N>,
     ) -> Result<PrivateKey> {
-        let registry_url = if let Some(nm) = &client.get_warg_registry() {
+        let registry_url = if let Some(url) = client.routed_registry() {
+            Some(url.clone())
+        } else if let Some(nm) = &client.get_warg_registry() {
             Some(RegistryUrl::new(nm.to_string())?)
         } else {
             None
         };
         let config = self.read_config()?;
+        let mut key_name = registry_url.map(|reg| reg.safe_label());
+
+        // Fall back to the configured default key when the registry has no key of its own
+        if let Some(default_key) = &config.default_key {
+            let has_key = match &key_name {
+                Some(name) => config.keys.contains(name),
+                None => config
+                    .home_url
+                    .as_ref()
+                    .map_or(false, |url| config.keys.contains(url)),
+            };
+            if !has_key {
+                key_name = Some(default_key.clone());
+            }
+        }
+
         get_signing_key(
-            registry_url.map(|reg| reg.safe_label()).as_deref(),
+            key_name.as_deref(),
             &config.keys,
             config.home_url.as_deref(),
         )
@@ -123,7 +224,7 @@ impl CommonOptions {
     /// Gets the auth token for the given registry URL.
     pub fn auth_token(&self, config: &Config) -> Result<Option<Secret<String>>> {
         if config.keyring_auth {
-            return if let Some(reg_url) = &self.registry {
+            return if let Some(reg_url) = self.registry_url(config) {
                 Ok(get_auth_token(&RegistryUrl::new(reg_url)?)?)
             } else if let Some(url) = config.home_url.as_ref() {
                 Ok(get_auth_token(&RegistryUrl::new(url)?)?)
@@ -135,6 +236,20 @@ impl CommonOptions {
     }
 }
 
+/// Gets the name of the registry the client uses for the namespace it was
+/// last refreshed for.
+///
+/// This is the URL of the registry, or its domain for a namespace the home
+/// registry imports from another registry.
+pub fn registry_name<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage>(
+    client: &Client<R, C, N>,
+) -> String {
+    match (client.routed_registry(), client.get_warg_registry()) {
+        (None, Some(domain)) => domain.to_string(),
+        _ => client.url().to_string(),
+    }
+}
+
 /// Namespace mapping to store when retrying a command after receiving a hint header
 pub struct Retry {
     namespace: String,
@@ -0,0 +1,75 @@
+use super::CommonOptions;
+use anyhow::{bail, Result};
+use clap::Args;
+use dialoguer::{theme::ColorfulTheme, Confirm};
+
+/// Removes unused local data.
+#[derive(Args)]
+pub struct CleanCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// Remove cached content that is not referenced by any known release.
+    #[clap(long)]
+    pub content: bool,
+    /// Clean without asking for confirmation.
+    #[clap(long, short)]
+    pub yes: bool,
+    /// List the local data that would be removed without removing it.
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+impl CleanCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        if !self.content {
+            bail!("nothing to clean: specify `--content` to remove unreferenced content");
+        }
+
+        let config = self.common.read_config()?;
+        let client = self.common.create_client(&config, None).await?;
+
+        let unreferenced = client.unreferenced_content().await?;
+        if self.dry_run {
+            for (digest, size) in &unreferenced {
+                println!("{digest} ({size} bytes)");
+            }
+
+            println!(
+                "would remove {count} content file(s) totaling {total} bytes",
+                count = unreferenced.len(),
+                total = unreferenced.iter().map(|(_, size)| size).sum::<u64>()
+            );
+            return Ok(());
+        }
+
+        if unreferenced.is_empty() {
+            println!("no unreferenced content to remove");
+            return Ok(());
+        }
+
+        if !self.yes
+            && !Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!(
+                    "are you sure you want to remove {count} unreferenced content file(s)",
+                    count = unreferenced.len()
+                ))
+                .interact()?
+        {
+            return Ok(());
+        }
+
+        let removed = client.prune_content().await?;
+        for (digest, size) in &removed {
+            println!("removed {digest} ({size} bytes)");
+        }
+
+        println!(
+            "reclaimed {total} bytes from {count} content file(s)",
+            count = removed.len(),
+            total = removed.iter().map(|(_, size)| size).sum::<u64>()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use anyhow::Result;
+use clap::{Args, Command};
+use clap_complete::Shell;
+use std::io::{self, Write};
+
+/// Generate shell completions for the `warg` tool.
+///
+/// For example, `warg completions bash > /etc/bash_completion.d/warg` installs
+/// completions for bash.
+#[derive(Args)]
+pub struct CompletionsCommand {
+    /// The shell to generate completions for.
+    #[clap(value_name = "SHELL")]
+    pub shell: Shell,
+}
+
+impl CompletionsCommand {
+    /// Executes the command, writing completions for the given command to
+    /// standard output.
+    pub fn exec(self, mut command: Command) -> Result<()> {
+        let name = command
+            .get_bin_name()
+            .unwrap_or_else(|| command.get_name())
+            .to_string();
+
+        // Generate into a buffer so that a closed pipe is reported as an error
+        let mut completions = Vec::new();
+        clap_complete::generate(self.shell, &mut command, name, &mut completions);
+        io::stdout().write_all(&completions)?;
+        Ok(())
+    }
+}
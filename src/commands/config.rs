@@ -1,12 +1,17 @@
 use super::CommonOptions;
 use anyhow::{bail, Context, Result};
-use clap::Args;
+use clap::{Args, Subcommand};
 use std::path::PathBuf;
-use warg_client::{Config, RegistryUrl};
+use warg_client::{api, storage::RegistryStorageKind, Config, RegistryUrl};
 
 /// Creates a new warg configuration file.
 #[derive(Args)]
+#[clap(args_conflicts_with_subcommands = true)]
 pub struct ConfigCommand {
+    /// The configuration subcommand to run, if any.
+    #[clap(subcommand)]
+    pub subcommand: Option<ConfigSubcommand>,
+
     /// The common command options.
     #[clap(flatten)]
     pub common: CommonOptions,
@@ -19,6 +24,15 @@ pub struct ConfigCommand {
     #[clap(long, value_name = "CONTENT")]
     pub content_dir: Option<PathBuf>,
 
+    /// A read-only content directory to fall back to, such as one shared
+    /// between users; may be repeated.
+    #[clap(long = "content-fallback-dir", value_name = "CONTENT")]
+    pub content_fallback_dirs: Vec<PathBuf>,
+
+    /// The kind of storage to use for registry information.
+    #[clap(long, value_enum, value_name = "STORAGE")]
+    pub storage: Option<RegistryStorageKind>,
+
     /// Overwrite the existing configuration file.
     #[clap(long)]
     pub overwrite: bool,
@@ -37,6 +51,10 @@ pub struct ConfigCommand {
 impl ConfigCommand {
     /// Executes the command.
     pub async fn exec(self) -> Result<()> {
+        if let Some(subcommand) = self.subcommand {
+            return subcommand.exec(&self.common).await;
+        }
+
         let path = self
             .path
             .map(Ok)
@@ -49,10 +67,10 @@ impl ConfigCommand {
             );
         }
 
+        let existing = self.common.read_config()?;
         let home_url = &self
             .common
-            .registry
-            .clone()
+            .registry_url(&existing)
             .map(RegistryUrl::new)
             .transpose()?
             .map(|u| u.to_string());
@@ -65,11 +83,25 @@ impl ConfigCommand {
         let cwd = std::env::current_dir().context("failed to determine current directory")?;
         let config = Config {
             home_url: home_url.clone(),
+            registries: Default::default(),
+            namespace_registries: Default::default(),
             registries_dir: self.registries_dir.map(|p| cwd.join(p)),
             content_dir: self.content_dir.map(|p| cwd.join(p)),
+            content_fallback_dirs: self
+                .content_fallback_dirs
+                .into_iter()
+                .map(|p| cwd.join(p))
+                .collect(),
             namespace_map_path: self.namespace_path.map(|p| cwd.join(p)),
-            keys: self.common.read_config()?.keys,
+            trust_policy_path: existing.trust_policy_path,
+            keys: existing.keys,
+            default_key: None,
             keyring_auth: false,
+            stale_checkpoint: Default::default(),
+            checkpoint_grace_period: None,
+            offline: false,
+            retry: None,
+            storage: self.storage.unwrap_or_default(),
         };
 
         config.write_to_file(&path)?;
@@ -82,3 +114,198 @@ impl ConfigCommand {
         Ok(())
     }
 }
+
+/// Subcommands of the `config` command.
+#[derive(Subcommand)]
+pub enum ConfigSubcommand {
+    /// Manage the namespace routes used to pick a registry for a package.
+    #[clap(subcommand)]
+    Route(RouteCommand),
+    /// Sets the home registry.
+    ///
+    /// The registry is checked to be reachable before it is set.
+    SetRegistry {
+        /// The URL of the home registry.
+        #[clap(value_name = "URL")]
+        url: String,
+        /// Set the registry without checking that it is reachable.
+        #[clap(long)]
+        no_verify: bool,
+    },
+    /// Adds a named registry that may be selected with `--registry <ALIAS>`.
+    AddRegistry {
+        /// The alias of the registry.
+        #[clap(value_name = "ALIAS")]
+        alias: String,
+        /// The URL of the registry.
+        #[clap(value_name = "URL")]
+        url: String,
+    },
+    /// Sets the name of the signing key to use for registries without a key of their own.
+    SetDefaultKey {
+        /// The name of the signing key in the keyring.
+        #[clap(value_name = "NAME")]
+        name: String,
+    },
+    /// Prints the configuration, or a single setting of it, as JSON.
+    Get {
+        /// The setting to print (e.g. `homeUrl`).
+        #[clap(value_name = "SETTING")]
+        setting: Option<String>,
+    },
+}
+
+impl ConfigSubcommand {
+    /// Executes the subcommand.
+    pub async fn exec(self, common: &CommonOptions) -> Result<()> {
+        match self {
+            Self::Route(cmd) => cmd.exec(common).await,
+            Self::SetRegistry { url, no_verify } => {
+                let url = RegistryUrl::new(&url)?;
+                if !no_verify {
+                    api::Client::new(url.to_string(), None)?
+                        .latest_checkpoint()
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "failed to reach registry `{url}`; use `--no-verify` to set it anyway"
+                            )
+                        })?;
+                }
+
+                update_config(common, |config| {
+                    config.home_url = Some(url.to_string());
+                    Ok(())
+                })?;
+                println!("set the home registry to `{url}`");
+                Ok(())
+            }
+            Self::AddRegistry { alias, url } => {
+                if alias.is_empty()
+                    || !alias
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                {
+                    bail!("registry alias `{alias}` may only contain letters, digits, `-` and `_`");
+                }
+
+                let url = RegistryUrl::new(&url)?;
+                update_config(common, |config| {
+                    config.registries.insert(alias.clone(), url.to_string());
+                    Ok(())
+                })?;
+                println!("added registry `{alias}` for `{url}`");
+                Ok(())
+            }
+            Self::SetDefaultKey { name } => {
+                update_config(common, |config| {
+                    if !config.keys.contains(&name) {
+                        bail!("no signing key named `{name}` is stored in the local keyring");
+                    }
+
+                    config.default_key = Some(name.clone());
+                    Ok(())
+                })?;
+                println!("set the default signing key to `{name}`");
+                Ok(())
+            }
+            Self::Get { setting } => {
+                let config = serde_json::to_value(common.read_config()?)?;
+                let value = match &setting {
+                    Some(setting) => config
+                        .get(setting)
+                        .with_context(|| format!("`{setting}` is not set in the configuration"))?,
+                    None => &config,
+                };
+
+                match value {
+                    serde_json::Value::String(s) => println!("{s}"),
+                    value => println!("{}", serde_json::to_string_pretty(value)?),
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Reads, updates, and writes back the configuration file used by the command.
+fn update_config(
+    common: &CommonOptions,
+    update: impl FnOnce(&mut Config) -> Result<()>,
+) -> Result<()> {
+    let path = common
+        .config
+        .clone()
+        .map(Ok)
+        .unwrap_or_else(Config::default_file_path)?;
+    let mut config = if path.is_file() {
+        Config::from_file(&path)?
+    } else {
+        Config::default()
+    };
+
+    update(&mut config)?;
+    config.write_to_file(&path)
+}
+
+/// Manage the namespace routes used to pick a registry for a package.
+///
+/// Packages in a routed namespace are resolved, downloaded and verified
+/// against the routed registry, with trust state kept separately from the
+/// home registry. When routes are configured, a namespace that is neither
+/// routed nor served by the home registry is an error.
+#[derive(Subcommand)]
+pub enum RouteCommand {
+    /// Routes a package namespace to a registry.
+    Add {
+        /// The package namespace to route (e.g. `wasi`), or a pattern such as `wasi:*` or `acme-*`.
+        #[clap(value_name = "NAMESPACE")]
+        namespace: String,
+        /// The URL or alias of the registry to route the namespace to.
+        #[clap(value_name = "REGISTRY")]
+        registry: String,
+    },
+    /// Lists the configured namespace routes.
+    List,
+}
+
+impl RouteCommand {
+    /// Executes the command.
+    pub async fn exec(self, common: &CommonOptions) -> Result<()> {
+        match self {
+            Self::Add {
+                namespace,
+                registry,
+            } => {
+                if namespace.is_empty() {
+                    bail!("the namespace to route must not be empty");
+                }
+
+                update_config(common, |config| {
+                    // Aliases are kept so the route follows the alias
+                    let registry = if config.registries.contains_key(&registry) {
+                        registry.clone()
+                    } else {
+                        RegistryUrl::new(&registry)?.to_string()
+                    };
+                    config
+                        .namespace_registries
+                        .insert(namespace.clone(), registry);
+                    Ok(())
+                })?;
+                println!("routed namespace `{namespace}` to registry `{registry}`");
+            }
+            Self::List => {
+                let config = common.read_config()?;
+                if config.namespace_registries.is_empty() {
+                    println!("no namespace routes configured");
+                }
+                for (pattern, registry) in &config.namespace_registries {
+                    println!("{pattern} -> {registry}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,29 +1,83 @@
 use super::{CommonOptions, Retry};
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use async_recursion::async_recursion;
 use clap::Args;
+use indexmap::IndexMap;
 use ptree::{output::print_tree, TreeBuilder};
-use std::fs;
+use serde::Serialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 use warg_client::{
     storage::{ContentStorage, PackageInfo, RegistryStorage},
     version_util::{
         create_child_node, new_tree, version_string, DependencyImportParser, ImportKind,
     },
-    FileSystemClient,
+    ClientError, FileSystemClient,
+};
+use warg_protocol::{
+    package::ReleaseState,
+    registry::{PackageName, RecordId},
+    Version, VersionReq,
 };
-use warg_protocol::{package::ReleaseState, registry::PackageName, VersionReq};
 use wasmparser::{Chunk, ComponentImport, ComponentImportSectionReader, Parser, Payload};
 
+/// The package or component to print dependencies of.
+#[derive(Clone)]
+pub enum DependenciesTarget {
+    /// A package in the registry.
+    Package(PackageName),
+    /// A component file on the local file system.
+    Component(PathBuf),
+}
+
+impl FromStr for DependenciesTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let path = Path::new(s);
+        if path.is_file() || path.extension().map_or(false, |ext| ext == "wasm") {
+            return Ok(Self::Component(path.to_path_buf()));
+        }
+
+        Ok(Self::Package(PackageName::new(s)?))
+    }
+}
+
 /// Print Dependency Tree
 #[derive(Args)]
+#[clap(disable_version_flag = true)]
 pub struct DependenciesCommand {
     /// The common command options.
     #[clap(flatten)]
     pub common: CommonOptions,
 
-    /// Only show information for the specified package.
-    #[clap(value_name = "PACKAGE")]
-    pub package: PackageName,
+    /// The package to show information for, or the path to a component
+    /// whose imports are resolved against the registry.
+    #[clap(value_name = "PACKAGE|PATH")]
+    pub target: DependenciesTarget,
+
+    /// Print the resolution of a component's imports as JSON.
+    ///
+    /// Equivalent to `--format json`.
+    #[clap(long, conflicts_with = "remote")]
+    pub json: bool,
+
+    /// Use the dependency information analyzed by the registry instead of
+    /// downloading and parsing package content.
+    #[clap(long)]
+    pub remote: bool,
+
+    /// The version of the package to show dependencies for; defaults to the
+    /// latest release.
+    #[clap(long, value_name = "VERSION", requires = "remote")]
+    pub version: Option<Version>,
+
+    /// Show the package releases that depend on the package instead.
+    #[clap(long, requires = "remote")]
+    pub reverse: bool,
 }
 
 impl DependenciesCommand {
@@ -31,11 +85,27 @@ impl DependenciesCommand {
     pub async fn exec(self, retry: Option<Retry>) -> Result<()> {
         let config = self.common.read_config()?;
         let mut client = self.common.create_client(&config, retry).await?;
-        client.refresh_namespace(self.package.namespace()).await?;
+
+        let package = match &self.target {
+            DependenciesTarget::Package(package) => package,
+            DependenciesTarget::Component(path) => {
+                if self.remote {
+                    bail!("`--remote` cannot be used with a component");
+                }
+
+                return self.print_component(&mut client, path).await;
+            }
+        };
+
+        client.refresh_namespace(package.namespace()).await?;
+
+        if self.remote {
+            return self.print_remote(&client, package).await;
+        }
 
         if let Some(info) = client
             .registry()
-            .load_package(client.get_warg_registry(), &self.package)
+            .load_package(client.get_warg_registry(), package)
             .await?
         {
             Self::print_package_info(&client, &info).await?;
@@ -44,6 +114,121 @@ impl DependenciesCommand {
         Ok(())
     }
 
+    async fn print_remote(&self, client: &FileSystemClient, package: &PackageName) -> Result<()> {
+        if self.reverse {
+            let mut tree = TreeBuilder::new(package.to_string());
+            for dependent in client.remote_dependents(package).await? {
+                tree.add_empty_child(format!("{}@{}", dependent.name, dependent.version));
+            }
+            print_tree(&tree.build())?;
+            return Ok(());
+        }
+
+        let version = match &self.version {
+            Some(version) => version.clone(),
+            None => {
+                client.upsert([package]).await?;
+                match client
+                    .registry()
+                    .load_package(client.get_warg_registry(), package)
+                    .await?
+                    .and_then(|info| info.state.releases().last().map(|r| r.version.clone()))
+                {
+                    Some(version) => version,
+                    None => bail!("package `{package}` has no releases"),
+                }
+            }
+        };
+
+        let mut tree = new_tree(package.namespace(), package.name(), &version);
+        for dependency in client.remote_dependencies(package, &version).await? {
+            tree.add_empty_child(dependency.import);
+        }
+        print_tree(&tree.build())?;
+        Ok(())
+    }
+
+    async fn print_component(&self, client: &mut FileSystemClient, path: &Path) -> Result<()> {
+        let bytes = fs::read(path)
+            .with_context(|| format!("failed to read component `{path}`", path = path.display()))?;
+        let imports = DepsParser::new().parse(&bytes).with_context(|| {
+            format!("failed to parse component `{path}`", path = path.display())
+        })?;
+
+        let mut known = IndexMap::new();
+        let mut resolution = ComponentResolution::default();
+        for import in imports {
+            let import = import.name.0;
+            let Some((package, requirement)) = import_requirement(import)? else {
+                continue;
+            };
+
+            if !known.contains_key(&package) {
+                let exists = Self::fetch_package(client, &package).await?;
+                known.insert(package.clone(), exists);
+            }
+
+            let release = client
+                .registry()
+                .resolve_version(
+                    client.get_warg_registry(),
+                    &package,
+                    &requirement,
+                    false,
+                    false,
+                )
+                .await?;
+            match release {
+                Some(release) => resolution.resolved.push(ResolvedImport {
+                    import: import.to_string(),
+                    package,
+                    requirement: requirement.to_string(),
+                    version: release.version,
+                    record_id: release.record_id,
+                }),
+                None => {
+                    let reason = if known[&package] {
+                        UnresolvedReason::NoMatchingVersion
+                    } else {
+                        UnresolvedReason::UnknownPackage
+                    };
+                    resolution.unresolved.push(UnresolvedImport {
+                        import: import.to_string(),
+                        package,
+                        requirement: requirement.to_string(),
+                        reason,
+                    });
+                }
+            }
+        }
+
+        if self.json || self.common.output().is_json() {
+            println!("{}", serde_json::to_string_pretty(&resolution)?);
+            return Ok(());
+        }
+
+        resolution.print();
+        Ok(())
+    }
+
+    /// Fetches the latest state of a package, returning whether the package exists.
+    async fn fetch_package(client: &mut FileSystemClient, package: &PackageName) -> Result<bool> {
+        if !client.is_offline() {
+            client.refresh_namespace(package.namespace()).await?;
+            match client.upsert([package]).await {
+                Ok(()) => {}
+                Err(ClientError::PackageDoesNotExist { .. }) => return Ok(false),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(client
+            .registry()
+            .load_package(client.get_warg_registry(), package)
+            .await?
+            .is_some())
+    }
+
     #[async_recursion]
     async fn parse_deps<'a>(
         id: &'a PackageName,
@@ -61,7 +246,7 @@ impl DependenciesCommand {
         if let Some(pkg) = package {
             let latest = pkg.state.releases().last();
             if let Some(l) = latest {
-                if let ReleaseState::Released { content } = &l.state {
+                if let ReleaseState::Released { content, .. } = &l.state {
                     let path = client.content().content_location(content);
                     if let Some(p) = path {
                         let bytes = fs::read(p)?;
@@ -102,7 +287,7 @@ impl DependenciesCommand {
             if let Some(l) = latest {
                 client.download(&info.name, &VersionReq::STAR).await?;
                 let mut tree = new_tree(info.name.namespace(), info.name.name(), &l.version);
-                if let ReleaseState::Released { content } = &l.state {
+                if let ReleaseState::Released { content, .. } = &l.state {
                     let path = client.content().content_location(content);
                     if let Some(p) = path {
                         let bytes = fs::read(&p)?;
@@ -140,6 +325,147 @@ impl DependenciesCommand {
     }
 }
 
+/// Maps a component import name to the registry package and version
+/// requirement it refers to.
+///
+/// Returns `Ok(None)` if the import is not qualified by a package name.
+fn import_requirement(import: &str) -> Result<Option<(PackageName, VersionReq)>> {
+    if import.starts_with("locked-dep=") || import.starts_with("unlocked-dep=") {
+        let import = DependencyImportParser {
+            next: import,
+            offset: 0,
+        }
+        .parse()?;
+        return Ok(Some((PackageName::new(import.name)?, import.req)));
+    }
+
+    let (path, version) = match import.split_once('@') {
+        Some((path, version)) => (path, Some(version)),
+        None => (import, None),
+    };
+    if !path.contains(':') {
+        return Ok(None);
+    }
+
+    let package = path.split('/').next().unwrap_or(path);
+    let requirement = match version {
+        // An interface version is compatible with later releases of its package
+        Some(version) => VersionReq::parse(&format!("^{version}"))
+            .with_context(|| format!("invalid version in import `{import}`"))?,
+        None => VersionReq::STAR,
+    };
+
+    Ok(Some((PackageName::new(package)?, requirement)))
+}
+
+/// The resolution of a component's imports against the registry.
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ComponentResolution {
+    resolved: Vec<ResolvedImport>,
+    unresolved: Vec<UnresolvedImport>,
+}
+
+impl ComponentResolution {
+    fn print(&self) {
+        if self.resolved.is_empty() && self.unresolved.is_empty() {
+            println!("component has no package imports");
+            return;
+        }
+
+        if !self.resolved.is_empty() {
+            let rows = self
+                .resolved
+                .iter()
+                .map(|r| {
+                    [
+                        r.import.clone(),
+                        r.package.to_string(),
+                        r.requirement.clone(),
+                        r.version.to_string(),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            print_table(["IMPORT", "PACKAGE", "REQUIREMENT", "VERSION"], &rows);
+        }
+
+        if !self.unresolved.is_empty() {
+            if !self.resolved.is_empty() {
+                println!();
+            }
+
+            println!("unresolved imports:");
+            let rows = self
+                .unresolved
+                .iter()
+                .map(|u| {
+                    [
+                        u.import.clone(),
+                        u.package.to_string(),
+                        u.requirement.clone(),
+                        u.reason.to_string(),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            print_table(["IMPORT", "PACKAGE", "REQUIREMENT", "REASON"], &rows);
+        }
+    }
+}
+
+fn print_table(header: [&str; 4], rows: &[[String; 4]]) {
+    let width = |i: usize| {
+        rows.iter()
+            .map(|row| row[i].len())
+            .chain([header[i].len()])
+            .max()
+            .unwrap_or_default()
+    };
+    let (a, b, c) = (width(0), width(1), width(2));
+
+    println!(
+        "{:a$}  {:b$}  {:c$}  {}",
+        header[0], header[1], header[2], header[3]
+    );
+    for [import, package, requirement, last] in rows {
+        println!("{import:a$}  {package:b$}  {requirement:c$}  {last}");
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResolvedImport {
+    import: String,
+    package: PackageName,
+    requirement: String,
+    version: Version,
+    record_id: RecordId,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UnresolvedImport {
+    import: String,
+    package: PackageName,
+    requirement: String,
+    reason: UnresolvedReason,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum UnresolvedReason {
+    UnknownPackage,
+    NoMatchingVersion,
+}
+
+impl std::fmt::Display for UnresolvedReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownPackage => write!(f, "unknown package"),
+            Self::NoMatchingVersion => write!(f, "no matching version"),
+        }
+    }
+}
+
 struct DepsParser {}
 
 impl DepsParser {
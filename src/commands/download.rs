@@ -1,52 +1,138 @@
-use super::{CommonOptions, Retry};
-use anyhow::{anyhow, Result};
+use super::{registry_name, CommonOptions, Retry};
+use anyhow::{anyhow, bail, Context, Result};
 use clap::Args;
-use warg_protocol::{registry::PackageName, VersionReq};
+use indexmap::IndexSet;
+use std::{collections::HashMap, path::PathBuf};
+use warg_crypto::hash::AnyHash;
+use warg_protocol::{package::COMPONENT_ARTIFACT, registry::PackageName, VersionReq};
 
-/// Download a warg registry package.
+/// Download warg registry packages.
 #[derive(Args)]
 #[clap(disable_version_flag = true)]
 pub struct DownloadCommand {
     /// The common command options.
     #[clap(flatten)]
     pub common: CommonOptions,
-    /// The package name to download.
-    #[clap(value_name = "PACKAGE")]
-    pub name: PackageName,
+    /// The names of the packages to download.
+    #[clap(value_name = "PACKAGE", required = true)]
+    pub names: Vec<PackageName>,
     #[clap(long, short, value_name = "VERSION")]
-    /// The version requirement of the package to download; defaults to `*`.
+    /// The version requirement of the packages to download; defaults to `*`.
     pub version: Option<VersionReq>,
+    /// Resolve the version as of the checkpoint with the given id.
+    ///
+    /// The package log is fetched and verified up to the checkpoint without
+    /// modifying client storage.
+    #[clap(long, value_name = "CHECKPOINT")]
+    pub at_checkpoint: Option<AnyHash>,
+    /// The artifact of the release to download, such as `wit`.
+    #[clap(long, value_name = "ARTIFACT", default_value = COMPONENT_ARTIFACT)]
+    pub artifact: String,
+    /// Write the downloaded content to the given path.
+    ///
+    /// The content is always kept in client storage as well. Only allowed
+    /// when downloading a single package.
+    #[clap(long, short, value_name = "OUTPUT")]
+    pub output: Option<PathBuf>,
+    /// Allow resolving to a release signed by a key that was revoked as of
+    /// the release.
+    #[clap(long)]
+    pub include_revoked: bool,
+    /// The maximum number of package contents to download at once.
+    #[clap(long, value_name = "JOBS", default_value = "4")]
+    pub jobs: usize,
 }
 
 impl DownloadCommand {
     /// Executes the command.
     pub async fn exec(self, retry: Option<Retry>) -> Result<()> {
+        if self.output.is_some() && self.names.len() > 1 {
+            bail!("`--output` may only be used when downloading a single package");
+        }
+
         let config = self.common.read_config()?;
-        let mut client = self.common.create_client(&config, retry).await?;
-        client.refresh_namespace(self.name.namespace()).await?;
+        let mut client = self
+            .common
+            .create_client(&config, retry)
+            .await?
+            .with_include_revoked(self.include_revoked);
 
-        println!("downloading package `{name}`...", name = self.name);
+        let requirement = self.version.clone().unwrap_or(VersionReq::STAR);
+        let mut releases = Vec::with_capacity(self.names.len());
+        for name in &self.names {
+            client.refresh_namespace(name.namespace()).await?;
 
-        let res = client
-            .download(
-                &self.name,
-                self.version.as_ref().unwrap_or(&VersionReq::STAR),
-            )
-            .await?
-            .ok_or_else(|| {
+            let registry = registry_name(&client);
+            println!("downloading package `{name}` from registry `{registry}`...");
+
+            let res = match &self.at_checkpoint {
+                Some(checkpoint_id) => {
+                    client
+                        .resolve_artifact_download_at_checkpoint(
+                            name,
+                            &requirement,
+                            checkpoint_id,
+                            &self.artifact,
+                        )
+                        .await?
+                }
+                None => {
+                    client
+                        .resolve_artifact_download(name, &requirement, &self.artifact)
+                        .await?
+                }
+            };
+
+            let (version, digest) = res.ok_or_else(|| {
                 anyhow!(
-                    "a version of package `{name}` that satisfies `{version}` was not found",
-                    name = self.name,
-                    version = self.version.as_ref().unwrap_or(&VersionReq::STAR)
+                    "a version of package `{name}` that satisfies `{requirement}` was not found"
                 )
             })?;
+            releases.push((name, version, digest, registry));
+        }
+
+        // Content is downloaded from the registry serving each package
+        let registries: IndexSet<_> = releases
+            .iter()
+            .map(|(_, _, _, registry)| registry.clone())
+            .collect();
+        let mut paths = HashMap::new();
+        for registry in registries {
+            let mut digests = Vec::new();
+            for (name, _, digest, _) in releases.iter().filter(|(_, _, _, r)| *r == registry) {
+                if registry_name(&client) != registry {
+                    client.refresh_namespace(name.namespace()).await?;
+                }
+                digests.push(digest.clone());
+            }
+
+            for (digest, res) in client.download_contents(digests, self.jobs).await {
+                paths.insert(digest, res?);
+            }
+        }
+
+        // Releases of several packages may share the same content
+        for (name, version, digest, _) in &releases {
+            let path = &paths[digest];
+            if self.artifact == COMPONENT_ARTIFACT {
+                println!("downloaded version {version} of package `{name}` ({digest})");
+            } else {
+                println!(
+                    "downloaded `{artifact}` artifact of version {version} of package `{name}` ({digest})",
+                    artifact = self.artifact
+                );
+            }
 
-        println!(
-            "downloaded version {version} of package `{name}` ({digest})",
-            name = self.name,
-            version = res.version,
-            digest = res.digest
-        );
+            if let Some(output) = &self.output {
+                tokio::fs::copy(path, output).await.with_context(|| {
+                    format!(
+                        "failed to write package content to `{path}`",
+                        path = output.display()
+                    )
+                })?;
+                println!("wrote package content to `{path}`", path = output.display());
+            }
+        }
 
         Ok(())
     }
@@ -0,0 +1,372 @@
+use super::CommonOptions;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use clap::{Args, Subcommand};
+use itertools::Itertools;
+use serde::Serialize;
+use std::fmt;
+use warg_client::PackageFetch;
+use warg_crypto::{hash::HashAlgorithm, signing::KeyID};
+use warg_protocol::{
+    operator::{OperatorEntry, OperatorRecord},
+    registry::{LogId, PackageName, RecordId, RegistryIndex},
+    PublishedProtoEnvelope,
+};
+
+/// Fetch logs from a warg registry.
+///
+/// Fetches a single package log, starting to track the package if needed,
+/// or with `--all` the operator log and every tracked package log.
+#[derive(Args)]
+#[clap(args_conflicts_with_subcommands = true)]
+pub struct FetchCommand {
+    /// The fetch subcommand to run, if any.
+    #[clap(subcommand)]
+    pub subcommand: Option<FetchSubcommand>,
+
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+
+    /// Fetch the operator log and every package log in client storage.
+    #[clap(long, conflicts_with = "package")]
+    pub all: bool,
+
+    /// The package to fetch.
+    #[clap(value_name = "PACKAGE", required_unless_present = "all")]
+    pub package: Option<PackageName>,
+}
+
+impl FetchCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        if let Some(subcommand) = self.subcommand {
+            return subcommand.exec().await;
+        }
+
+        let config = self.common.read_config()?;
+        let mut client = self.common.create_client(&config, None).await?;
+
+        let packages = match &self.package {
+            Some(package) => {
+                client.refresh_namespace(package.namespace()).await?;
+                client.fetch_packages([package]).await?
+            }
+            None => {
+                let (operator, packages) = client.fetch_all().await?;
+                println!("fetched {len} new operator record(s)", len = operator.len());
+                packages
+            }
+        };
+
+        let mut failed = Vec::new();
+        for PackageFetch {
+            name,
+            new_records,
+            head,
+        } in packages
+        {
+            let head = head
+                .map(|h| format!("`{h}`"))
+                .unwrap_or_else(|| "none".into());
+            match new_records {
+                Ok(count) => {
+                    println!("fetched {count} new record(s) for package `{name}` (head {head})")
+                }
+                Err(e) => {
+                    println!("failed to fetch package `{name}` (head {head}): {e}");
+                    failed.push(name);
+                }
+            }
+        }
+
+        if !failed.is_empty() {
+            bail!(
+                "failed to fetch {len} package(s): {names}",
+                len = failed.len(),
+                names = failed.iter().map(|n| format!("`{n}`")).join(", ")
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// The subcommands of the fetch command.
+#[derive(Subcommand)]
+pub enum FetchSubcommand {
+    /// Fetch the operator log.
+    Operator(FetchOperatorCommand),
+}
+
+impl FetchSubcommand {
+    /// Executes the subcommand.
+    pub async fn exec(self) -> Result<()> {
+        match self {
+            Self::Operator(cmd) => cmd.exec().await,
+        }
+    }
+}
+
+/// Fetch the operator log records since the last fetch.
+#[derive(Args)]
+pub struct FetchOperatorCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+
+    /// Print the fetched records as JSON.
+    ///
+    /// Equivalent to `--format json`.
+    #[clap(long)]
+    pub json: bool,
+}
+
+impl FetchOperatorCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = self.common.read_config()?;
+        let client = self.common.create_client(&config, None).await?;
+
+        let records = client.fetch_operator().await?;
+        let algorithm = client.hash_algorithm().await?;
+        let records = records
+            .iter()
+            .map(|record| RecordListing::new(record, algorithm))
+            .collect::<Vec<_>>();
+
+        if self.json || self.common.output().is_json() {
+            println!("{}", serde_json::to_string_pretty(&records)?);
+            return Ok(());
+        }
+
+        if records.is_empty() {
+            println!("operator log is up to date");
+            return Ok(());
+        }
+
+        println!("fetched {len} operator record(s):", len = records.len());
+        for record in &records {
+            println!(
+                "\n  record `{id}` (registry index {index})",
+                id = record.record_id,
+                index = record.registry_index
+            );
+            println!(
+                "    signed by `{key_id}` at {timestamp}",
+                key_id = record.key_id,
+                timestamp = record.timestamp
+            );
+            for entry in &record.entries {
+                println!("    {entry}");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordListing {
+    record_id: RecordId,
+    registry_index: RegistryIndex,
+    key_id: KeyID,
+    timestamp: String,
+    entries: Vec<EntryListing>,
+}
+
+impl RecordListing {
+    fn new(record: &PublishedProtoEnvelope<OperatorRecord>, algorithm: HashAlgorithm) -> Self {
+        let envelope = &record.envelope;
+        Self {
+            record_id: RecordId::operator_record_for(algorithm, envelope),
+            registry_index: record.registry_index,
+            key_id: envelope.key_id().clone(),
+            timestamp: DateTime::<Utc>::from(envelope.as_ref().timestamp).to_rfc3339(),
+            entries: envelope
+                .as_ref()
+                .entries
+                .iter()
+                .map(EntryListing::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum EntryListing {
+    #[serde(rename_all = "camelCase")]
+    Init {
+        hash_algorithm: String,
+        key_id: KeyID,
+    },
+    #[serde(rename_all = "camelCase")]
+    Grant {
+        key_id: KeyID,
+        permissions: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    GrantPackagePublish {
+        key_id: KeyID,
+        pattern: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Revoke {
+        key_id: KeyID,
+        permissions: Vec<String>,
+    },
+    DefineNamespace {
+        namespace: String,
+    },
+    ImportNamespace {
+        namespace: String,
+        registry: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    SuppressPackage {
+        log_id: LogId,
+        reason: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    UnsuppressPackage {
+        log_id: LogId,
+    },
+    #[serde(rename_all = "camelCase")]
+    SetMaxCheckpointInterval {
+        interval_secs: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    RotateKey {
+        key_id: KeyID,
+        overlap_secs: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    ReserveNamespace {
+        namespace: String,
+        key_ids: Vec<KeyID>,
+    },
+    Other {
+        description: String,
+    },
+}
+
+impl From<&OperatorEntry> for EntryListing {
+    fn from(entry: &OperatorEntry) -> Self {
+        match entry {
+            OperatorEntry::Init {
+                hash_algorithm,
+                key,
+            } => Self::Init {
+                hash_algorithm: hash_algorithm.to_string(),
+                key_id: key.fingerprint(),
+            },
+            OperatorEntry::GrantFlat { key, permissions } => Self::Grant {
+                key_id: key.fingerprint(),
+                permissions: permissions.iter().map(ToString::to_string).collect(),
+            },
+            OperatorEntry::GrantPackagePublish { key, pattern } => Self::GrantPackagePublish {
+                key_id: key.fingerprint(),
+                pattern: pattern.to_string(),
+            },
+            OperatorEntry::RevokeFlat {
+                key_id,
+                permissions,
+            } => Self::Revoke {
+                key_id: key_id.clone(),
+                permissions: permissions.iter().map(ToString::to_string).collect(),
+            },
+            OperatorEntry::DefineNamespace { namespace } => Self::DefineNamespace {
+                namespace: namespace.clone(),
+            },
+            OperatorEntry::ImportNamespace {
+                namespace,
+                registry,
+            } => Self::ImportNamespace {
+                namespace: namespace.clone(),
+                registry: registry.clone(),
+            },
+            OperatorEntry::SuppressPackage { log_id, reason } => Self::SuppressPackage {
+                log_id: log_id.clone(),
+                reason: reason.clone(),
+            },
+            OperatorEntry::UnsuppressPackage { log_id } => Self::UnsuppressPackage {
+                log_id: log_id.clone(),
+            },
+            OperatorEntry::SetMaxCheckpointInterval { interval } => {
+                Self::SetMaxCheckpointInterval {
+                    interval_secs: interval.as_secs(),
+                }
+            }
+            OperatorEntry::RotateKey { key, overlap } => Self::RotateKey {
+                key_id: key.fingerprint(),
+                overlap_secs: overlap.as_secs(),
+            },
+            OperatorEntry::ReserveNamespace { namespace, key_ids } => Self::ReserveNamespace {
+                namespace: namespace.clone(),
+                key_ids: key_ids.clone(),
+            },
+            entry => Self::Other {
+                description: format!("{entry:?}"),
+            },
+        }
+    }
+}
+
+impl fmt::Display for EntryListing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Init {
+                hash_algorithm,
+                key_id,
+            } => write!(f, "init with key `{key_id}` using {hash_algorithm}"),
+            Self::Grant {
+                key_id,
+                permissions,
+            } => write!(
+                f,
+                "grant {permissions} to key `{key_id}`",
+                permissions = permissions.iter().join(", ")
+            ),
+            Self::GrantPackagePublish { key_id, pattern } => {
+                write!(f, "grant publish to `{pattern}` to key `{key_id}`")
+            }
+            Self::Revoke {
+                key_id,
+                permissions,
+            } => write!(
+                f,
+                "revoke {permissions} from key `{key_id}`",
+                permissions = permissions.iter().join(", ")
+            ),
+            Self::DefineNamespace { namespace } => write!(f, "define namespace `{namespace}`"),
+            Self::ImportNamespace {
+                namespace,
+                registry,
+            } => write!(f, "import namespace `{namespace}` from `{registry}`"),
+            Self::SuppressPackage { log_id, reason } => {
+                write!(f, "suppress package log `{log_id}`: {reason}")
+            }
+            Self::UnsuppressPackage { log_id } => {
+                write!(f, "lift suppression of package log `{log_id}`")
+            }
+            Self::SetMaxCheckpointInterval { interval_secs } => {
+                write!(f, "set maximum checkpoint interval to {interval_secs}s")
+            }
+            Self::RotateKey {
+                key_id,
+                overlap_secs,
+            } => write!(
+                f,
+                "rotate signing key to `{key_id}` with an overlap of {overlap_secs}s"
+            ),
+            Self::ReserveNamespace { namespace, key_ids } => write!(
+                f,
+                "reserve namespace `{namespace}` for {key_ids}",
+                key_ids = key_ids.iter().map(|id| format!("key `{id}`")).join(", ")
+            ),
+            Self::Other { description } => write!(f, "{description}"),
+        }
+    }
+}
@@ -1,12 +1,18 @@
 use super::CommonOptions;
 use anyhow::Result;
 use clap::{ArgAction, Args};
+use indexmap::IndexMap;
+use serde::Serialize;
 use warg_client::{
     storage::{ContentStorage, NamespaceMapStorage, PackageInfo, RegistryStorage},
     Client,
 };
 use warg_crypto::hash::AnyHash;
-use warg_protocol::{registry::PackageName, Version};
+use warg_protocol::{
+    package::ReleaseMetadata,
+    registry::{Checkpoint, PackageName, RecordId},
+    Version,
+};
 
 /// Display client storage information.
 #[derive(Args)]
@@ -22,6 +28,106 @@ pub struct InfoCommand {
     /// Only show the namespace map
     #[clap(short, long, value_name = "NAMESPACES", action = ArgAction::SetTrue)]
     pub namespaces: bool,
+
+    /// Show the package as of the checkpoint with the given id.
+    ///
+    /// The package log is fetched and verified up to the checkpoint without
+    /// modifying client storage.
+    #[clap(long, value_name = "CHECKPOINT", requires = "package")]
+    pub at_checkpoint: Option<AnyHash>,
+
+    /// Print the information as JSON.
+    ///
+    /// Equivalent to `--format json`.
+    #[clap(long)]
+    pub json: bool,
+}
+
+/// The client storage information printed with `--json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StorageInfo {
+    registry: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checkpoint: Option<CheckpointInfo>,
+    packages: Vec<PackageListing>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    namespaces: Option<IndexMap<String, String>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckpointInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<AnyHash>,
+    log_length: usize,
+    log_root: AnyHash,
+    map_root: AnyHash,
+}
+
+impl CheckpointInfo {
+    fn new(checkpoint: &Checkpoint, id: Option<AnyHash>) -> Self {
+        Self {
+            id,
+            log_length: checkpoint.log_length,
+            log_root: checkpoint.log_root.clone(),
+            map_root: checkpoint.map_root.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PackageListing {
+    name: PackageName,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head: Option<RecordId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<ReleaseMetadata>,
+    versions: Vec<VersionListing>,
+}
+
+impl From<&PackageInfo> for PackageListing {
+    fn from(info: &PackageInfo) -> Self {
+        Self {
+            name: info.name.clone(),
+            head: info.state.head().as_ref().map(|h| h.digest.clone()),
+            // The package is described by the metadata of its latest release
+            metadata: info
+                .state
+                .releases()
+                .filter(|r| !r.yanked() && info.state.is_trusted(r))
+                .max_by(|a, b| a.version.cmp(&b.version))
+                .and_then(|r| r.metadata.clone()),
+            versions: info
+                .state
+                .releases()
+                .map(|r| VersionListing {
+                    version: r.version.clone(),
+                    content: r.content().cloned(),
+                    artifacts: r
+                        .artifacts()
+                        .skip(1)
+                        .map(|(name, content)| (name.to_string(), content.clone()))
+                        .collect(),
+                    yanked: r.yanked(),
+                    revoked: !info.state.is_trusted(r),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VersionListing {
+    version: Version,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<AnyHash>,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    artifacts: IndexMap<String, AnyHash>,
+    yanked: bool,
+    revoked: bool,
 }
 
 impl InfoCommand {
@@ -30,61 +136,135 @@ impl InfoCommand {
         let config = self.common.read_config()?;
         let mut client = self.common.create_client(&config, None).await?;
 
-        println!("registry: {url}", url = client.url());
-        println!("\npackages in client storage:");
-        match self.package {
-            Some(package) => {
-                client.refresh_namespace(package.namespace()).await?;
-                if let Some(info) = client
-                    .registry()
-                    .load_package(client.get_warg_registry(), &package)
-                    .await?
-                {
-                    Self::print_package_info(&info);
-                }
+        if let Some(package) = &self.package {
+            client.refresh_namespace(package.namespace()).await?;
+        }
+
+        let (checkpoint, packages) = match (&self.package, &self.at_checkpoint) {
+            (Some(package), Some(checkpoint_id)) => {
+                let info = client.package_at_checkpoint(package, checkpoint_id).await?;
+                let checkpoint = info
+                    .checkpoint
+                    .as_ref()
+                    .map(|c| CheckpointInfo::new(c, Some(checkpoint_id.clone())));
+                (checkpoint, vec![info])
             }
-            None => {
-                client
+            (package, _) => {
+                let checkpoint = client
                     .registry()
-                    .load_packages()
+                    .load_checkpoint(client.get_warg_registry())
                     .await?
-                    .iter()
-                    .for_each(Self::print_package_info);
+                    .map(|c| CheckpointInfo::new(&c.as_ref().checkpoint, None));
+                let packages = match package {
+                    Some(package) => client
+                        .registry()
+                        .load_package(client.get_warg_registry(), package)
+                        .await?
+                        .into_iter()
+                        .collect(),
+                    None => client.registry().load_packages().await?,
+                };
+                (checkpoint, packages)
             }
+        };
+
+        let namespaces = if self.namespaces {
+            Some(Self::load_namespace_map(&client).await?)
+        } else {
+            None
+        };
+
+        let info = StorageInfo {
+            registry: client.url().to_string(),
+            checkpoint,
+            packages: packages.iter().map(PackageListing::from).collect(),
+            namespaces,
+        };
+
+        if self.json || self.common.output().is_json() {
+            println!("{}", serde_json::to_string_pretty(&info)?);
+            return Ok(());
+        }
+
+        println!("registry: {url}", url = info.registry);
+        if let Some(checkpoint) = &info.checkpoint {
+            Self::print_checkpoint(checkpoint);
         }
 
-        if self.namespaces {
+        match &self.at_checkpoint {
+            Some(checkpoint_id) => println!("\npackage as of checkpoint `{checkpoint_id}`:"),
+            None => println!("\npackages in client storage:"),
+        }
+        info.packages.iter().for_each(Self::print_package);
+
+        if let Some(namespaces) = &info.namespaces {
             println!("\nnamespace mappings in client storage");
-            Self::print_namespace_map(&client).await?;
-            return Ok(());
+            for (namespace, registry) in namespaces {
+                println!("  {namespace}={registry}");
+            }
         }
 
         Ok(())
     }
 
-    fn print_package_info(info: &PackageInfo) {
-        println!("  name: {name}", name = info.name);
-        println!("  versions:");
-        info.state.releases().for_each(|r| {
-            if let Some(content) = r.content() {
-                Self::print_release(&r.version, content);
-            }
-        });
+    fn print_checkpoint(checkpoint: &CheckpointInfo) {
+        println!("checkpoint:");
+        if let Some(id) = &checkpoint.id {
+            println!("  id: {id}");
+        }
+        println!("  log length: {len}", len = checkpoint.log_length);
+        println!("  log root: {root}", root = checkpoint.log_root);
+        println!("  map root: {root}", root = checkpoint.map_root);
     }
 
-    fn print_release(version: &Version, content: &AnyHash) {
-        println!("    {version} ({content})");
+    fn print_package(package: &PackageListing) {
+        println!("  name: {name}", name = package.name);
+        if let Some(head) = &package.head {
+            println!("  head: {head}");
+        }
+        if let Some(metadata) = &package.metadata {
+            if let Some(description) = &metadata.description {
+                println!("  description: {description}");
+            }
+            if let Some(license) = &metadata.license {
+                println!("  license: {license}");
+            }
+            if let Some(repository) = &metadata.repository {
+                println!("  repository: {repository}");
+            }
+            if let Some(homepage) = &metadata.homepage {
+                println!("  homepage: {homepage}");
+            }
+            if !metadata.keywords.is_empty() {
+                println!(
+                    "  keywords: {keywords}",
+                    keywords = metadata.keywords.join(", ")
+                );
+            }
+        }
+        println!("  versions:");
+        for version in &package.versions {
+            if let Some(content) = &version.content {
+                let revoked = if version.revoked { " [REVOKED]" } else { "" };
+                println!(
+                    "    {version} ({content}){revoked}",
+                    version = version.version
+                );
+                for (name, content) in &version.artifacts {
+                    println!("      {name}: {content}");
+                }
+            }
+        }
     }
 
-    async fn print_namespace_map<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage>(
+    async fn load_namespace_map<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage>(
         client: &Client<R, C, N>,
-    ) -> Result<()> {
-        if let Some(map) = client.namespace_map().load_namespace_map().await? {
-            for (namespace, registry) in map {
-                println!("  {namespace}={registry}");
-            }
-        };
-
-        Ok(())
+    ) -> Result<IndexMap<String, String>> {
+        Ok(client
+            .namespace_map()
+            .load_namespace_map()
+            .await?
+            .map(|map| map.into_iter().collect())
+            .unwrap_or_default())
     }
 }
@@ -4,8 +4,10 @@ use dialoguer::{theme::ColorfulTheme, Confirm, Password};
 use p256::ecdsa::SigningKey;
 use rand_core::OsRng;
 use warg_client::Config;
-use warg_credentials::keyring::{delete_signing_key, get_signing_key, set_signing_key};
-use warg_crypto::signing::PrivateKey;
+use warg_credentials::keyring::{
+    delete_signing_key, get_signing_key, list_signing_keys, set_signing_key,
+};
+use warg_crypto::signing::{PrivateKey, PublicKey};
 
 use super::CommonOptions;
 
@@ -23,6 +25,8 @@ impl KeyCommand {
         match self.command {
             KeySubcommand::New(cmd) => cmd.exec().await,
             KeySubcommand::Info(cmd) => cmd.exec().await,
+            KeySubcommand::List(cmd) => cmd.exec().await,
+            KeySubcommand::Export(cmd) => cmd.exec().await,
             KeySubcommand::Set(cmd) => cmd.exec().await,
             KeySubcommand::Delete(cmd) => cmd.exec().await,
         }
@@ -36,6 +40,10 @@ pub enum KeySubcommand {
     New(KeyNewCommand),
     /// Shows information about the signing key for a registry in the local keyring.
     Info(KeyInfoCommand),
+    /// Lists the signing keys for each registry in the local keyring.
+    List(KeyListCommand),
+    /// Exports the public key of the signing key for a registry in the local keyring.
+    Export(KeyExportCommand),
     /// Sets the signing key for a registry in the local keyring.
     Set(KeySetCommand),
     /// Deletes the signing key for a registry from the local keyring.
@@ -54,14 +62,15 @@ impl KeyNewCommand {
     /// Executes the command.
     pub async fn exec(self) -> Result<()> {
         let config = &mut self.common.read_config()?;
+        let registry = self.common.registry_url(config);
         let key = SigningKey::random(&mut OsRng).into();
-        if let Some(ref reg) = self.common.registry {
+        if let Some(ref reg) = registry {
             config.keys.insert(reg.to_string());
         } else {
             config.keys.insert("default".to_string());
         }
         set_signing_key(
-            self.common.registry.as_deref(),
+            registry.as_deref(),
             &key,
             &mut config.keys,
             config.home_url.as_deref(),
@@ -86,8 +95,9 @@ impl KeyInfoCommand {
     /// Executes the command.
     pub async fn exec(self) -> Result<()> {
         let config = &self.common.read_config()?;
+        let registry = self.common.registry_url(config);
         let private_key = get_signing_key(
-            self.common.registry.as_deref(),
+            registry.as_deref(),
             &config.keys,
             config.home_url.as_deref(),
         )?;
@@ -98,6 +108,66 @@ impl KeyInfoCommand {
     }
 }
 
+/// Lists the signing keys for each registry in the local keyring.
+#[derive(Args)]
+pub struct KeyListCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+}
+
+impl KeyListCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = &self.common.read_config()?;
+        let keys = list_signing_keys(&config.keys)?;
+        if keys.is_empty() {
+            println!("no signing keys are stored in the local keyring");
+            return Ok(());
+        }
+
+        for (registry, public_key) in keys {
+            println!("{registry}: {key_id}", key_id = public_key.fingerprint());
+        }
+
+        Ok(())
+    }
+}
+
+/// Exports the public key of the signing key for a registry in the local keyring.
+#[derive(Args)]
+pub struct KeyExportCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+
+    /// Export the public key; this is the default, as private keys are never
+    /// exported, and is accepted for compatibility.
+    #[clap(long)]
+    pub public: bool,
+}
+
+impl KeyExportCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = &self.common.read_config()?;
+        println!("{}", self.public_key(config)?);
+        Ok(())
+    }
+
+    /// Gets the public key of the signing key to export; private keys are
+    /// never exported.
+    pub fn public_key(&self, config: &Config) -> Result<PublicKey> {
+        let registry = self.common.registry_url(config);
+        let private_key = get_signing_key(
+            registry.as_deref(),
+            &config.keys,
+            config.home_url.as_deref(),
+        )?;
+        Ok(private_key.public_key())
+    }
+}
+
 /// Sets the signing key for a registry in the local keyring.
 #[derive(Args)]
 pub struct KeySetCommand {
@@ -116,9 +186,10 @@ impl KeySetCommand {
         let key =
             PrivateKey::decode(key_str).context("signing key is not in the correct format")?;
         let config = &mut self.common.read_config()?;
+        let registry = self.common.registry_url(config);
 
         set_signing_key(
-            self.common.registry.as_deref(),
+            registry.as_deref(),
             &key,
             &mut config.keys,
             config.home_url.as_deref(),
@@ -143,25 +214,26 @@ impl KeyDeleteCommand {
     /// Executes the command.
     pub async fn exec(self) -> Result<()> {
         let config = &mut self.common.read_config()?;
+        let registry = self.common.registry_url(config);
 
         if Confirm::with_theme(&ColorfulTheme::default())
             .with_prompt("are you sure you want to delete your signing key")
             .interact()?
         {
             delete_signing_key(
-                self.common.registry.as_deref(),
+                registry.as_deref(),
                 &config.keys,
                 config.home_url.as_deref(),
             )?;
             let keys = &mut config.keys;
-            if let Some(registry_url) = self.common.registry {
-                keys.swap_remove(&registry_url);
+            if let Some(registry_url) = &registry {
+                keys.swap_remove(registry_url);
             } else {
                 keys.swap_remove("default");
             }
             config.write_to_file(&Config::default_config_path()?)?;
             println!("signing key was deleted successfully",);
-        } else if let Some(url) = self.common.registry {
+        } else if let Some(url) = registry {
             println!(
                 "skipping deletion of signing key for registry `{url}`",
                 url = url
@@ -3,6 +3,7 @@ use anyhow::Result;
 use clap::Args;
 use semver::VersionReq;
 use warg_client::{
+    lockfile::LOCK_FILE_NAME,
     storage::{PackageInfo, RegistryStorage},
     FileSystemClient,
 };
@@ -48,6 +49,10 @@ impl LockCommand {
 
     async fn lock(client: FileSystemClient, info: &PackageInfo) -> Result<()> {
         client.lock_component(info).await?;
+        client
+            .lock_file(info)
+            .await?
+            .write_to_file(LOCK_FILE_NAME)?;
         Ok(())
     }
 }
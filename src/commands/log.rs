@@ -0,0 +1,171 @@
+use super::CommonOptions;
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::Args;
+use itertools::Itertools;
+use std::{
+    fmt,
+    time::{Duration, UNIX_EPOCH},
+};
+use warg_api::v1::package::{PackageRecordEntry, PackageRecordState, PackageRecordSummary};
+use warg_protocol::registry::PackageName;
+
+/// The number of hex digits of record and key ids to print.
+const SHORT_ID_LEN: usize = 12;
+
+/// Print the record history of a package.
+///
+/// The history is summarized by the registry and is not verified by the client.
+#[derive(Args)]
+pub struct LogCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+
+    /// The package to print the record history of.
+    #[clap(value_name = "PACKAGE")]
+    pub package: PackageName,
+}
+
+impl LogCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = self.common.read_config()?;
+        let client = self.common.create_client(&config, None).await?;
+        let records = client.remote_record_history(&self.package).await?;
+
+        let output = self.common.output();
+        if output.is_json() {
+            return output.document(&records);
+        }
+
+        if records.is_empty() {
+            println!(
+                "package `{package}` has no published records",
+                package = self.package
+            );
+            return Ok(());
+        }
+
+        let rows = records.iter().map(Row::new).collect::<Vec<_>>();
+        let header = Row {
+            record_id: "RECORD".to_string(),
+            timestamp: "TIMESTAMP".to_string(),
+            key_id: "KEY ID".to_string(),
+            entries: "ENTRIES".to_string(),
+            state: "STATE".to_string(),
+        };
+
+        let width = |f: fn(&Row) -> &str| {
+            rows.iter()
+                .chain(std::iter::once(&header))
+                .map(|row| f(row).len())
+                .max()
+                .unwrap_or_default()
+        };
+        let widths = [
+            width(|r| &r.record_id),
+            width(|r| &r.timestamp),
+            width(|r| &r.key_id),
+            width(|r| &r.entries),
+        ];
+
+        for row in std::iter::once(&header).chain(&rows) {
+            println!(
+                "{record_id:<w0$}  {timestamp:<w1$}  {key_id:<w2$}  {entries:<w3$}  {state}",
+                record_id = row.record_id,
+                timestamp = row.timestamp,
+                key_id = row.key_id,
+                entries = row.entries,
+                state = row.state,
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+                w3 = widths[3],
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// A row of the record history table.
+struct Row {
+    record_id: String,
+    timestamp: String,
+    key_id: String,
+    entries: String,
+    state: String,
+}
+
+impl Row {
+    fn new(record: &PackageRecordSummary) -> Self {
+        Self {
+            record_id: short_id(&record.record_id),
+            timestamp: DateTime::<Utc>::from(UNIX_EPOCH + Duration::from_secs(record.timestamp))
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            key_id: short_id(&record.key_id),
+            entries: record.entries.iter().map(EntryDisplay).join(", "),
+            state: match &record.state {
+                PackageRecordState::Sourcing { .. } => "sourcing".to_string(),
+                PackageRecordState::Processing => "processing".to_string(),
+                PackageRecordState::Rejected { reason } => format!("rejected: {reason}"),
+                PackageRecordState::Published { .. } => match record.checkpoint_log_length {
+                    Some(log_length) => format!("published (checkpoint {log_length})"),
+                    None => "published".to_string(),
+                },
+            },
+        }
+    }
+}
+
+/// Shortens an id of the form `<algorithm>:<hex>` to a prefix of its hex digits.
+fn short_id(id: &impl fmt::Display) -> String {
+    let id = id.to_string();
+    let hex = id.split_once(':').map_or(id.as_str(), |(_, hex)| hex);
+    hex.chars().take(SHORT_ID_LEN).collect()
+}
+
+struct EntryDisplay<'a>(&'a PackageRecordEntry);
+
+impl fmt::Display for EntryDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            PackageRecordEntry::Init { key_id, .. } => {
+                write!(f, "init {key_id}", key_id = short_id(key_id))
+            }
+            PackageRecordEntry::Grant {
+                key_id,
+                permissions,
+            } => write!(
+                f,
+                "grant {permissions} to {key_id}",
+                permissions = permissions.iter().join("+"),
+                key_id = short_id(key_id)
+            ),
+            PackageRecordEntry::Revoke {
+                key_id,
+                permissions,
+            } => write!(
+                f,
+                "revoke {permissions} from {key_id}",
+                permissions = permissions.iter().join("+"),
+                key_id = short_id(key_id)
+            ),
+            PackageRecordEntry::RevokeKey { key_id, .. } => {
+                write!(f, "revoke key {key_id}", key_id = short_id(key_id))
+            }
+            PackageRecordEntry::Release {
+                version, yanked, ..
+            } => {
+                write!(f, "release {version}")?;
+                if *yanked {
+                    write!(f, " [YANKED]")?;
+                }
+                Ok(())
+            }
+            PackageRecordEntry::Yank { version } => write!(f, "yank {version}"),
+            PackageRecordEntry::Unknown => write!(f, "unknown entry"),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use super::CommonOptions;
+use anyhow::{bail, Result};
+use clap::{Args, Subcommand};
+use itertools::Itertools;
+use std::time::{Duration, SystemTime};
+use warg_api::v1::operator::OperatorError;
+use warg_client::{api, storage::RegistryStorage as _, ClientError, FileSystemClient};
+use warg_crypto::signing::{KeyID, PrivateKey, PublicKey};
+use warg_protocol::{
+    operator::{OperatorEntry, PackagePattern, Permission},
+    registry::RecordId,
+};
+
+/// Manage the operator log of a warg registry.
+#[derive(Subcommand)]
+pub enum OperatorCommand {
+    /// Grant operator permissions to a key.
+    Grant(OperatorGrantCommand),
+    /// Grant a key permission to publish to the packages matching a pattern.
+    GrantPublish(OperatorGrantPublishCommand),
+    /// Revoke operator permissions from a key.
+    Revoke(OperatorRevokeCommand),
+    /// Rotate the operator key to a new key.
+    RotateKey(OperatorRotateKeyCommand),
+    /// Reserve a namespace so that only the given keys may initialize
+    /// packages in it.
+    ReserveNamespace(OperatorReserveNamespaceCommand),
+}
+
+impl OperatorCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        match self {
+            Self::Grant(cmd) => cmd.exec().await,
+            Self::GrantPublish(cmd) => cmd.exec().await,
+            Self::Revoke(cmd) => cmd.exec().await,
+            Self::RotateKey(cmd) => cmd.exec().await,
+            Self::ReserveNamespace(cmd) => cmd.exec().await,
+        }
+    }
+}
+
+/// Grant operator permissions to a key.
+#[derive(Args)]
+pub struct OperatorGrantCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The public key to grant permissions to.
+    #[clap(long, value_name = "PUBLIC_KEY")]
+    pub key: PublicKey,
+    /// The permission(s) to grant.
+    #[clap(long = "permission", value_delimiter = ',', required = true)]
+    pub permissions: Vec<Permission>,
+}
+
+impl OperatorGrantCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = self.common.read_config()?;
+        let client = self.common.create_client(&config, None).await?;
+        let signing_key = self.common.signing_key(&client)?;
+
+        let record_id = publish(
+            &client,
+            &signing_key,
+            OperatorEntry::GrantFlat {
+                key: self.key.clone(),
+                permissions: self.permissions.clone(),
+            },
+        )
+        .await?;
+
+        println!(
+            "granted ({permissions_str}) to key ID `{key_id}` in operator record `{record_id}`",
+            permissions_str = self.permissions.iter().join(","),
+            key_id = self.key.fingerprint(),
+        );
+
+        Ok(())
+    }
+}
+
+/// Grant a key permission to publish to the packages matching a pattern.
+#[derive(Args)]
+pub struct OperatorGrantPublishCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The public key to grant permission to.
+    #[clap(long, value_name = "PUBLIC_KEY")]
+    pub key: PublicKey,
+    /// The pattern of packages the key may publish to: `*`, `<namespace>:*`,
+    /// or `<namespace>:<name>`.
+    #[clap(long = "package", value_name = "PATTERN")]
+    pub pattern: PackagePattern,
+}
+
+impl OperatorGrantPublishCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = self.common.read_config()?;
+        let client = self.common.create_client(&config, None).await?;
+        let signing_key = self.common.signing_key(&client)?;
+
+        let record_id = publish(
+            &client,
+            &signing_key,
+            OperatorEntry::GrantPackagePublish {
+                key: self.key.clone(),
+                pattern: self.pattern.clone(),
+            },
+        )
+        .await?;
+
+        println!(
+            "granted publish to `{pattern}` to key ID `{key_id}` in operator record `{record_id}`",
+            pattern = self.pattern,
+            key_id = self.key.fingerprint(),
+        );
+
+        Ok(())
+    }
+}
+
+/// Revoke operator permissions from a key.
+#[derive(Args)]
+pub struct OperatorRevokeCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The key ID to revoke permissions from.
+    #[clap(long, value_name = "KEY_ID")]
+    pub key: KeyID,
+    /// The permission(s) to revoke.
+    #[clap(long = "permission", value_delimiter = ',', required = true)]
+    pub permissions: Vec<Permission>,
+}
+
+impl OperatorRevokeCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = self.common.read_config()?;
+        let client = self.common.create_client(&config, None).await?;
+        let signing_key = self.common.signing_key(&client)?;
+
+        let record_id = publish(
+            &client,
+            &signing_key,
+            OperatorEntry::RevokeFlat {
+                key_id: self.key.clone(),
+                permissions: self.permissions.clone(),
+            },
+        )
+        .await?;
+
+        println!(
+            "revoked ({permissions_str}) from key ID `{key_id}` in operator record `{record_id}`",
+            permissions_str = self.permissions.iter().join(","),
+            key_id = self.key,
+        );
+
+        Ok(())
+    }
+}
+
+/// Rotate the operator key to a new key.
+///
+/// The rotation is published with the signing key, which is then kept valid
+/// for the overlap while the registry switches to signing checkpoints with
+/// the new key. Once the overlap ends, the signing key's permissions are
+/// revoked.
+///
+/// The registry must be configured with the new key as its next operator key.
+#[derive(Args)]
+pub struct OperatorRotateKeyCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The public key to rotate to.
+    #[clap(long, value_name = "PUBLIC_KEY")]
+    pub key: PublicKey,
+    /// The number of seconds the signing key may still sign checkpoints
+    /// after the rotation.
+    #[clap(long, value_name = "SECONDS", default_value = "300")]
+    pub overlap: u64,
+}
+
+impl OperatorRotateKeyCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = self.common.read_config()?;
+        let client = self.common.create_client(&config, None).await?;
+        let signing_key = self.common.signing_key(&client)?;
+        let old_key_id = signing_key.public_key().fingerprint();
+        let key_id = self.key.fingerprint();
+
+        let record_id = publish(
+            &client,
+            &signing_key,
+            OperatorEntry::RotateKey {
+                key: self.key.clone(),
+                overlap: Duration::from_secs(self.overlap),
+            },
+        )
+        .await?;
+        println!("published rotation to key ID `{key_id}` in operator record `{record_id}`");
+
+        println!("waiting for the registry to sign checkpoints with key ID `{key_id}`...");
+        client.wait_for_operator_key(&key_id).await?;
+
+        client.fetch_operator().await?;
+        let operator = client
+            .registry()
+            .load_operator(client.get_warg_registry())
+            .await?
+            .unwrap_or_default();
+        if let Some(end) = operator.state.key_rotation_overlap_end(&old_key_id) {
+            if let Ok(remaining) = end.duration_since(SystemTime::now()) {
+                println!(
+                    "waiting {secs}s for the overlap with key ID `{old_key_id}` to end...",
+                    secs = remaining.as_secs()
+                );
+                tokio::time::sleep(remaining).await;
+            }
+        }
+
+        let permissions = operator.state.key_permissions(&old_key_id).collect_vec();
+        if !permissions.is_empty() {
+            let record_id = publish(
+                &client,
+                &signing_key,
+                OperatorEntry::RevokeFlat {
+                    key_id: old_key_id.clone(),
+                    permissions: permissions.clone(),
+                },
+            )
+            .await?;
+            println!(
+                "revoked ({permissions_str}) from key ID `{old_key_id}` in operator record `{record_id}`",
+                permissions_str = permissions.iter().join(","),
+            );
+        }
+
+        println!("rotated the operator key from key ID `{old_key_id}` to `{key_id}`");
+        Ok(())
+    }
+}
+
+/// Reserve a namespace so that only the given keys may initialize packages
+/// in it.
+///
+/// Reserving a namespace again adds to the keys it is reserved for.
+#[derive(Args)]
+pub struct OperatorReserveNamespaceCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The namespace to reserve.
+    #[clap(value_name = "NAMESPACE")]
+    pub namespace: String,
+    /// The key ID(s) that may initialize packages in the namespace.
+    #[clap(
+        long = "key",
+        value_name = "KEY_ID",
+        value_delimiter = ',',
+        required = true
+    )]
+    pub key_ids: Vec<KeyID>,
+}
+
+impl OperatorReserveNamespaceCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = self.common.read_config()?;
+        let client = self.common.create_client(&config, None).await?;
+        let signing_key = self.common.signing_key(&client)?;
+
+        let record_id = publish(
+            &client,
+            &signing_key,
+            OperatorEntry::ReserveNamespace {
+                namespace: self.namespace.clone(),
+                key_ids: self.key_ids.clone(),
+            },
+        )
+        .await?;
+
+        println!(
+            "reserved namespace `{namespace}` for key ID(s) {key_ids} in operator record `{record_id}`",
+            namespace = self.namespace,
+            key_ids = self.key_ids.iter().map(|id| format!("`{id}`")).join(", "),
+        );
+
+        Ok(())
+    }
+}
+
+/// Publishes an operator record with the given entry.
+///
+/// A record that no longer follows the head of the operator log is reported
+/// with a suggestion to refetch the log.
+async fn publish(
+    client: &FileSystemClient,
+    signing_key: &PrivateKey,
+    entry: OperatorEntry,
+) -> Result<RecordId> {
+    match client
+        .publish_operator_record(signing_key, vec![entry])
+        .await
+    {
+        Ok(record_id) => Ok(record_id),
+        Err(ClientError::Api(api::ClientError::Operator(OperatorError::HeadMismatch))) => {
+            bail!("the operator log changed while the record was being published; run `warg fetch operator` and try again")
+        }
+        Err(e) => Err(e.into()),
+    }
+}
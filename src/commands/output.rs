@@ -0,0 +1,84 @@
+use anyhow::{bail, Error, Result};
+use serde::Serialize;
+use std::{fmt, str::FromStr};
+
+/// The format of a command's output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable messages.
+    #[default]
+    Human,
+    /// A single JSON document.
+    Json,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Human => write!(f, "human"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            _ => bail!("unsupported output format `{s}`: expected `human` or `json`"),
+        }
+    }
+}
+
+/// Writes the output of a command in the selected format.
+///
+/// Messages are printed as the command progresses in the human format and
+/// suppressed in the JSON format, where the command instead prints a single
+/// document once it completes.
+#[derive(Debug, Clone, Copy)]
+pub struct Output {
+    format: OutputFormat,
+}
+
+impl Output {
+    /// Creates a new output in the given format.
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format }
+    }
+
+    /// Determines if the output is a JSON document.
+    pub fn is_json(&self) -> bool {
+        self.format == OutputFormat::Json
+    }
+
+    /// Prints a message in the human format.
+    pub fn message(&self, message: impl fmt::Display) {
+        if !self.is_json() {
+            println!("{message}");
+        }
+    }
+
+    /// Prints a status message that is not part of the command's result.
+    ///
+    /// The message goes to stderr in the JSON format so that stdout only
+    /// contains the document.
+    pub fn status(&self, message: impl fmt::Display) {
+        if self.is_json() {
+            eprintln!("{message}");
+        } else {
+            println!("{message}");
+        }
+    }
+
+    /// Prints the document of a command in the JSON format.
+    pub fn document(&self, document: &impl Serialize) -> Result<()> {
+        if self.is_json() {
+            println!("{}", serde_json::to_string_pretty(document)?);
+        }
+
+        Ok(())
+    }
+}
@@ -1,49 +1,57 @@
-use super::{CommonOptions, Retry};
+use super::{CommonOptions, Output, Retry};
 use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
 use clap::{Args, Subcommand};
-use futures::TryStreamExt;
+use futures::{stream, StreamExt, TryStreamExt};
+use indexmap::IndexMap;
 use itertools::Itertools;
-use std::{future::Future, path::PathBuf, time::Duration};
+use serde::Serialize;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 use tokio::io::BufReader;
 use tokio_util::io::ReaderStream;
+use warg_api::v1::package::PackageLogStatus;
 use warg_client::{
+    signer::{CommandSigner, Signer},
     storage::{ContentStorage as _, PublishEntry, PublishInfo, RegistryStorage as _},
-    FileSystemClient,
+    ClientError, FileSystemClient, PackageFetch, PublishResult,
 };
 use warg_crypto::{
     hash::AnyHash,
-    signing::{KeyID, PublicKey},
+    signing::{KeyID, PrivateKey, PublicKey},
 };
+use warg_protobuf::protocol as protobuf;
 use warg_protocol::{
-    package::Permission,
-    registry::{PackageName, RecordId},
+    package::{Permission, ReleaseMetadata, COMPONENT_ARTIFACT, WIT_ARTIFACT},
+    registry::{PackageName, RecordId, RegistryIndex},
     Version,
 };
 
 const DEFAULT_WAIT_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_PARALLEL_SUBMISSIONS: usize = 4;
+const DEFAULT_SUBMIT_ALL_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const MAX_REBASE_ATTEMPTS: usize = 3;
 
-/// Used to enqueue a publish entry if there is a pending publish.
+/// Used to enqueue a publish entry if there is a pending publish for the package.
 /// Returns `Ok(None)` if the entry was enqueued or `Ok(Some(entry))` if there
-/// was no pending publish.
-async fn enqueue<'a, T>(
-    client: &'a FileSystemClient,
+/// was no pending publish for the package.
+async fn enqueue(
+    client: &FileSystemClient,
     name: &PackageName,
-    entry: impl FnOnce(&'a FileSystemClient) -> T,
-) -> Result<Option<PublishEntry>>
-where
-    T: Future<Output = Result<PublishEntry>> + 'a,
-{
-    match client.registry().load_publish().await? {
-        Some(mut info) => {
-            if &info.name != name {
-                bail!(
-                    "there is already publish in progress for package `{name}`",
-                    name = info.name
-                );
-            }
-
-            let entry = entry(client).await?;
+    entry: PublishEntry,
+) -> Result<Option<PublishEntry>> {
+    let pending = client
+        .registry()
+        .load_publishes()
+        .await?
+        .into_iter()
+        .find(|info| &info.name == name);
 
+    match pending {
+        Some(mut info) => {
             if matches!(entry, PublishEntry::Init) && info.initializing() {
                 bail!("there is already a pending initializing for package `{name}`");
             }
@@ -52,8 +60,276 @@ where
             client.registry().store_publish(Some(&info)).await?;
             Ok(None)
         }
-        None => Ok(Some(entry(client).await?)),
+        None => Ok(Some(entry)),
+    }
+}
+
+/// Publishes a single entry for a package.
+///
+/// The entry is added to the pending publish for the package if there is
+/// one; otherwise, it is submitted in a record of its own.
+async fn publish_entry(
+    client: &FileSystemClient,
+    signing_key: &PrivateKey,
+    entry: PublishEntry,
+    timestamp: SystemTime,
+    no_wait: bool,
+    output: Output,
+    report: &mut PublishReport,
+) -> Result<()> {
+    let name = report.package.clone();
+    match enqueue(client, &name, entry.clone()).await? {
+        Some(entry) => {
+            let record_id = client
+                .publish_with_info_at(
+                    signing_key,
+                    PublishInfo {
+                        name: name.clone(),
+                        head: None,
+                        entries: vec![entry.clone()],
+                    },
+                    timestamp,
+                )
+                .await?;
+            report.submitted(record_id.clone());
+
+            if no_wait {
+                output.message(format_args!(
+                    "submitted record `{record_id}` for publishing"
+                ));
+            } else {
+                wait_for_publish(client, &record_id, output, report).await?;
+                output.message(describe_published(&name, &entry));
+            }
+        }
+        None => {
+            report.state = PublishState::Pending;
+            output.message(describe_enqueued(&name, &entry));
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the record that would be submitted for a publish as JSON, along
+/// with the identifier it would have, without signing or submitting it.
+///
+/// The record is built from client storage only; publishing with the printed
+/// timestamp submits the same record if the package log has not changed.
+async fn print_dry_run(
+    client: &FileSystemClient,
+    key: &PublicKey,
+    info: &PublishInfo,
+    timestamp: SystemTime,
+    output: Output,
+    report: &mut PublishReport,
+) -> Result<()> {
+    let record = client.build_record(key, info, timestamp).await?;
+    // The record was built against a stored checkpoint, so its algorithm is
+    // known without contacting the registry
+    let record_id = RecordId::unsigned_package_record_for(client.hash_algorithm().await?, &record);
+    let record = protobuf::PackageRecord::from(&record);
+
+    output.message(serde_json::to_string_pretty(&record)?);
+    output.message(format_args!(
+        "record `{record_id}` for package `{name}` was not submitted (dry run); publish with `--timestamp {timestamp}` to submit this record",
+        name = info.name,
+        timestamp = DateTime::<Utc>::from(timestamp).to_rfc3339_opts(SecondsFormat::AutoSi, true)
+    ));
+
+    report.state = PublishState::DryRun;
+    report.record_id = Some(record_id);
+    report.record = Some(record);
+    Ok(())
+}
+
+/// The state of a publish reported with `--format json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum PublishState {
+    /// The entries are in a pending publish that was not submitted.
+    Pending,
+    /// The pending publish was aborted.
+    Aborted,
+    /// The record was built without being signed or submitted.
+    DryRun,
+    /// The version was already released with the same content.
+    AlreadyPublished,
+    /// The record was submitted and is waiting to be published.
+    Submitted,
+    /// The record was published.
+    Published,
+    /// The record was rejected by the registry.
+    Rejected,
+    /// The record was not published before the timeout elapsed.
+    TimedOut,
+    /// The publish failed.
+    Failed,
+}
+
+impl fmt::Display for PublishState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Aborted => write!(f, "aborted"),
+            Self::DryRun => write!(f, "dry run"),
+            Self::AlreadyPublished => write!(f, "already published"),
+            Self::Submitted => write!(f, "submitted"),
+            Self::Published => write!(f, "published"),
+            Self::Rejected => write!(f, "rejected"),
+            Self::TimedOut => write!(f, "timed out"),
+            Self::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// The outcome of a publish, printed by the publish subcommands with
+/// `--format json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PublishReport {
+    /// The package being published.
+    package: PackageName,
+    /// The identifier of the record, once it is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    record_id: Option<RecordId>,
+    /// The entries of the record.
+    entries: Vec<PublishEntry>,
+    /// The state of the publish.
+    state: PublishState,
+    /// The index of the published record in the registry log.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    registry_index: Option<RegistryIndex>,
+    /// The identifier of the checkpoint that includes the published record.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checkpoint: Option<AnyHash>,
+    /// The reason the registry gave for not publishing the record.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    /// The error the publish failed with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// The record built for a dry run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    record: Option<protobuf::PackageRecord>,
+    /// The time spent on the publish, in milliseconds.
+    elapsed_ms: u64,
+    #[serde(skip)]
+    started: Instant,
+}
+
+impl PublishReport {
+    /// Creates a new report for a publish of the given entries.
+    ///
+    /// The report starts out failed; commands update it as the publish
+    /// progresses.
+    fn new(package: PackageName, entries: Vec<PublishEntry>) -> Self {
+        Self {
+            package,
+            record_id: None,
+            entries,
+            state: PublishState::Failed,
+            registry_index: None,
+            checkpoint: None,
+            reason: None,
+            error: None,
+            record: None,
+            elapsed_ms: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// Creates a new report for the given publish in the given state.
+    fn for_publish(info: &PublishInfo, state: PublishState) -> Self {
+        let mut report = Self::new(info.name.clone(), info.entries.clone());
+        report.state = state;
+        report
+    }
+
+    /// Records that the publish was submitted as the given record.
+    fn submitted(&mut self, record_id: RecordId) {
+        self.record_id = Some(record_id);
+        self.state = PublishState::Submitted;
+    }
+
+    /// Records the outcome of a submitted record.
+    fn record_result(&mut self, result: &PublishResult) {
+        match result {
+            PublishResult::Published { registry_index } => {
+                self.state = PublishState::Published;
+                self.registry_index = Some(*registry_index);
+            }
+            PublishResult::Rejected { reason } => {
+                self.state = PublishState::Rejected;
+                self.reason = Some(reason.clone());
+            }
+            PublishResult::MissingContent { .. } => {
+                self.state = PublishState::Failed;
+                self.reason = result.reason();
+            }
+        }
+    }
+
+    /// Records an error submitting or waiting on the publish.
+    fn record_error(&mut self, e: ClientError) {
+        match e {
+            ClientError::PublishRejected { reason, .. } => {
+                self.state = PublishState::Rejected;
+                self.reason = Some(reason);
+            }
+            e => self.fail(e),
+        }
+    }
+
+    /// Marks the publish as failed with the given error.
+    fn fail(&mut self, e: impl fmt::Display) {
+        self.state = PublishState::Failed;
+        self.error = Some(e.to_string());
+    }
+
+    /// Gets the status of the publish as shown in the summary of
+    /// `publish submit --all`.
+    fn status(&self) -> String {
+        match (self.state, self.reason.as_ref().or(self.error.as_ref())) {
+            (PublishState::Rejected | PublishState::Failed, Some(reason)) => {
+                format!("{state} ({reason})", state = self.state)
+            }
+            (state, _) => state.to_string(),
+        }
+    }
+}
+
+/// Prints the reports of a publish subcommand as a JSON document.
+///
+/// If the command failed, the error is recorded in each report of a publish
+/// that did not complete; a command that failed before any publish was
+/// reported prints just the error.
+///
+/// A single report is printed as an object and several as an array.
+fn finish(output: Output, mut reports: Vec<PublishReport>, res: Result<()>) -> Result<()> {
+    if !output.is_json() {
+        return res;
+    }
+
+    for report in &mut reports {
+        report.elapsed_ms = report.started.elapsed().as_millis() as u64;
+        if let Err(e) = &res {
+            if report.state == PublishState::Failed && report.error.is_none() {
+                report.error = Some(format!("{e:#}"));
+            }
+        }
+    }
+
+    match (reports.as_slice(), &res) {
+        ([], Err(e)) => output.document(&serde_json::json!({
+            "state": PublishState::Failed,
+            "error": format!("{e:#}"),
+        }))?,
+        ([report], _) => output.document(report)?,
+        _ => output.document(&reports)?,
     }
+
+    res
 }
 
 /// Publish a package to a warg registry.
@@ -69,6 +345,8 @@ pub enum PublishCommand {
     Grant(PublishGrantCommand),
     /// Revoke permissions for the package.
     Revoke(PublishRevokeCommand),
+    /// Revoke a key, distrusting the releases it signed as of a time.
+    RevokeKey(PublishRevokeKeyCommand),
     /// Start a new pending publish.
     Start(PublishStartCommand),
     /// List the records in a pending publish.
@@ -79,6 +357,8 @@ pub enum PublishCommand {
     Submit(PublishSubmitCommand),
     /// Wait for a pending publish to complete.
     Wait(PublishWaitCommand),
+    /// Publish every package release found in a directory.
+    Dir(PublishDirCommand),
 }
 
 impl PublishCommand {
@@ -90,11 +370,13 @@ impl PublishCommand {
             Self::Yank(cmd) => cmd.exec(retry).await,
             Self::Grant(cmd) => cmd.exec(retry).await,
             Self::Revoke(cmd) => cmd.exec(retry).await,
+            Self::RevokeKey(cmd) => cmd.exec(retry).await,
             Self::Start(cmd) => cmd.exec().await,
             Self::List(cmd) => cmd.exec().await,
             Self::Abort(cmd) => cmd.exec().await,
             Self::Submit(cmd) => cmd.exec().await,
             Self::Wait(cmd) => cmd.exec().await,
+            Self::Dir(cmd) => cmd.exec().await,
         }
     }
 }
@@ -117,51 +399,35 @@ pub struct PublishInitCommand {
 impl PublishInitCommand {
     /// Executes the command.
     pub async fn exec(self, retry: Option<Retry>) -> Result<()> {
+        let output = self.common.output();
+        let mut report = PublishReport::new(self.name.clone(), vec![PublishEntry::Init]);
+        let res = self.publish(retry, output, &mut report).await;
+        finish(output, vec![report], res)
+    }
+
+    async fn publish(
+        &self,
+        retry: Option<Retry>,
+        output: Output,
+        report: &mut PublishReport,
+    ) -> Result<()> {
         let config = self.common.read_config()?;
         let mut client = self.common.create_client(&config, retry).await?;
 
         client.refresh_namespace(self.name.namespace()).await?;
+        self.common.report_registry(&client);
 
         let signing_key = self.common.signing_key(&client)?;
-        match enqueue(&client, &self.name, |_| {
-            std::future::ready(Ok(PublishEntry::Init))
-        })
-        .await?
-        {
-            Some(entry) => {
-                let record_id = client
-                    .publish_with_info(
-                        &signing_key,
-                        PublishInfo {
-                            name: self.name.clone(),
-                            head: None,
-                            entries: vec![entry],
-                        },
-                    )
-                    .await?;
-
-                if self.no_wait {
-                    println!("submitted record `{record_id}` for publishing");
-                } else {
-                    client
-                        .wait_for_publish(&self.name, &record_id, DEFAULT_WAIT_INTERVAL)
-                        .await?;
-
-                    println!(
-                        "published initialization of package `{name}`",
-                        name = self.name,
-                    );
-                }
-            }
-            None => {
-                println!(
-                    "added initialization of package `{name}` to pending publish",
-                    name = self.name
-                );
-            }
-        }
-
-        Ok(())
+        publish_entry(
+            &client,
+            &signing_key,
+            PublishEntry::Init,
+            SystemTime::now(),
+            self.no_wait,
+            output,
+            report,
+        )
+        .await
     }
 }
 
@@ -181,78 +447,185 @@ pub struct PublishReleaseCommand {
     /// The path to the package being published.
     #[clap(value_name = "PATH")]
     pub path: PathBuf,
+    /// The path to the WIT package of the release, published as its `wit` artifact.
+    #[clap(long, value_name = "PATH")]
+    pub wit: Option<PathBuf>,
+    /// Another artifact of the release, such as `debug=app.debug.wasm`; may be specified more than once.
+    #[clap(long = "artifact", value_name = "NAME=PATH", value_parser = parse_artifact)]
+    pub artifacts: Vec<(String, PathBuf)>,
     /// Whether to wait for the publish to complete.
     #[clap(long)]
     pub no_wait: bool,
+    /// Succeed without publishing if the version was already released with the same content.
+    #[clap(long)]
+    pub if_not_exists: bool,
+    /// Print the record that would be published without signing or submitting it.
+    #[clap(long, alias = "draft")]
+    pub dry_run: bool,
+    /// The timestamp of the record, in RFC 3339 format; defaults to now.
+    ///
+    /// Pass the timestamp printed by `--dry-run` to publish the previewed record.
+    #[clap(long, value_name = "TIME")]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// A short description of the package to include with the release.
+    #[clap(long, value_name = "DESCRIPTION")]
+    pub description: Option<String>,
+    /// The SPDX license expression of the release, such as `Apache-2.0 OR MIT`.
+    #[clap(long, value_name = "LICENSE")]
+    pub license: Option<String>,
+    /// The URL of the package's source repository.
+    #[clap(long, value_name = "URL")]
+    pub repository: Option<String>,
+    /// The URL of the package's homepage.
+    #[clap(long, value_name = "URL")]
+    pub homepage: Option<String>,
+    /// A keyword for finding the package; may be specified more than once.
+    #[clap(long = "keyword", value_name = "KEYWORD")]
+    pub keywords: Vec<String>,
 }
 
 impl PublishReleaseCommand {
     /// Executes the command.
     pub async fn exec(self, retry: Option<Retry>) -> Result<()> {
+        let output = self.common.output();
+        let mut report = PublishReport::new(self.name.clone(), Vec::new());
+        let res = self.publish(retry, output, &mut report).await;
+        finish(output, vec![report], res)
+    }
+
+    /// Gets the metadata to include with the release, if any was given.
+    fn metadata(&self) -> Option<ReleaseMetadata> {
+        let metadata = ReleaseMetadata {
+            description: self.description.clone(),
+            license: self.license.clone(),
+            repository: self.repository.clone(),
+            homepage: self.homepage.clone(),
+            keywords: self.keywords.clone(),
+        };
+
+        (!metadata.is_empty()).then_some(metadata)
+    }
+
+    async fn publish(
+        &self,
+        retry: Option<Retry>,
+        output: Output,
+        report: &mut PublishReport,
+    ) -> Result<()> {
         let config = self.common.read_config()?;
         let mut client = self.common.create_client(&config, retry).await?;
         client.refresh_namespace(self.name.namespace()).await?;
+        self.common.report_registry(&client);
         let signing_key = self.common.signing_key(&client)?;
 
-        let path = self.path.clone();
-        let version = self.version.clone();
-        match enqueue(&client, &self.name, move |c| async move {
-            let content = c
-                .content()
-                .store_content(
-                    Box::pin(
-                        ReaderStream::new(BufReader::new(
-                            tokio::fs::File::open(&path).await.with_context(|| {
-                                format!("failed to open `{path}`", path = path.display())
-                            })?,
-                        ))
-                        .map_err(|e| anyhow!(e)),
-                    ),
-                    None,
-                )
-                .await?;
+        let content = store_file(&client, &self.path).await?;
+        let mut artifacts = IndexMap::new();
+        for (name, path) in self.wit.iter().map(|path| (WIT_ARTIFACT, path)).chain(
+            self.artifacts
+                .iter()
+                .map(|(name, path)| (name.as_str(), path)),
+        ) {
+            if artifacts.contains_key(name) {
+                bail!("artifact `{name}` was specified more than once");
+            }
 
-            Ok(PublishEntry::Release { version, content })
-        })
-        .await?
+            artifacts.insert(name.to_string(), store_file(&client, path).await?);
+        }
+
+        let entry = PublishEntry::Release {
+            version: self.version.clone(),
+            content: content.clone(),
+            artifacts,
+            metadata: self.metadata(),
+        };
+        report.entries = vec![entry.clone()];
+
+        if self.if_not_exists
+            && client
+                .release_exists(&self.name, &self.version, &content)
+                .await?
         {
-            Some(entry) => {
-                let record_id = client
-                    .publish_with_info(
-                        &signing_key,
-                        PublishInfo {
-                            name: self.name.clone(),
-                            head: None,
-                            entries: vec![entry],
-                        },
-                    )
-                    .await?;
+            output.message(format_args!(
+                "version {version} of package `{name}` is already published with the same content",
+                version = self.version,
+                name = self.name
+            ));
+            report.state = PublishState::AlreadyPublished;
+            return Ok(());
+        }
 
-                if self.no_wait {
-                    println!("submitted record `{record_id}` for publishing");
-                } else {
-                    client
-                        .wait_for_publish(&self.name, &record_id, DEFAULT_WAIT_INTERVAL)
-                        .await?;
-
-                    println!(
-                        "published version {version} of package `{name}`",
-                        version = self.version,
-                        name = self.name
-                    );
-                }
-            }
-            None => {
-                println!(
-                    "added release of version {version} for package `{name}` to pending publish",
-                    version = self.version,
-                    name = self.name
-                );
-            }
+        let timestamp = self.timestamp.map_or_else(SystemTime::now, Into::into);
+        if self.dry_run {
+            // Preview the pending publish for the package with the release added
+            let mut info = client
+                .registry()
+                .load_publishes()
+                .await?
+                .into_iter()
+                .find(|info| info.name == self.name)
+                .unwrap_or_else(|| PublishInfo {
+                    name: self.name.clone(),
+                    head: None,
+                    entries: Vec::new(),
+                });
+            info.entries.push(entry);
+            report.entries = info.entries.clone();
+            return print_dry_run(
+                &client,
+                &signing_key.public_key(),
+                &info,
+                timestamp,
+                output,
+                report,
+            )
+            .await;
         }
 
-        Ok(())
+        publish_entry(
+            &client,
+            &signing_key,
+            entry,
+            timestamp,
+            self.no_wait,
+            output,
+            report,
+        )
+        .await
+    }
+}
+
+/// Parses an artifact given as `NAME=PATH`.
+fn parse_artifact(s: &str) -> Result<(String, PathBuf)> {
+    let (name, path) = s
+        .split_once('=')
+        .with_context(|| format!("artifact `{s}` is not in the form `NAME=PATH`"))?;
+    if name.is_empty() || path.is_empty() {
+        bail!("artifact `{s}` is not in the form `NAME=PATH`");
+    }
+
+    if name == COMPONENT_ARTIFACT {
+        bail!("the `{COMPONENT_ARTIFACT}` artifact is the package being published");
     }
+
+    Ok((name.to_string(), path.into()))
+}
+
+/// Stores the file at the given path in client content storage.
+async fn store_file(client: &FileSystemClient, path: &Path) -> Result<AnyHash> {
+    client
+        .content()
+        .store_content(
+            Box::pin(
+                ReaderStream::new(BufReader::new(
+                    tokio::fs::File::open(path).await.with_context(|| {
+                        format!("failed to open `{path}`", path = path.display())
+                    })?,
+                ))
+                .map_err(|e| anyhow!(e)),
+            ),
+            None,
+        )
+        .await
 }
 
 /// Yank a package release from a warg registry.
@@ -276,53 +649,38 @@ pub struct PublishYankCommand {
 impl PublishYankCommand {
     /// Executes the command.
     pub async fn exec(self, retry: Option<Retry>) -> Result<()> {
+        let output = self.common.output();
+        let entry = PublishEntry::Yank {
+            version: self.version.clone(),
+        };
+        let mut report = PublishReport::new(self.name.clone(), vec![entry.clone()]);
+        let res = self.publish(retry, entry, output, &mut report).await;
+        finish(output, vec![report], res)
+    }
+
+    async fn publish(
+        &self,
+        retry: Option<Retry>,
+        entry: PublishEntry,
+        output: Output,
+        report: &mut PublishReport,
+    ) -> Result<()> {
         let config = self.common.read_config()?;
         let mut client = self.common.create_client(&config, retry).await?;
         client.refresh_namespace(self.name.namespace()).await?;
+        self.common.report_registry(&client);
         let signing_key = self.common.signing_key(&client)?;
 
-        let version = self.version.clone();
-        match enqueue(&client, &self.name, move |_| async move {
-            Ok(PublishEntry::Yank { version })
-        })
-        .await?
-        {
-            Some(entry) => {
-                let record_id = client
-                    .publish_with_info(
-                        &signing_key,
-                        PublishInfo {
-                            name: self.name.clone(),
-                            head: None,
-                            entries: vec![entry],
-                        },
-                    )
-                    .await?;
-
-                if self.no_wait {
-                    println!("submitted record `{record_id}` for publishing");
-                } else {
-                    client
-                        .wait_for_publish(&self.name, &record_id, DEFAULT_WAIT_INTERVAL)
-                        .await?;
-
-                    println!(
-                        "yanked version {version} of package `{name}`",
-                        version = self.version,
-                        name = self.name
-                    );
-                }
-            }
-            None => {
-                println!(
-                    "added yank of version {version} for package `{name}` to pending publish",
-                    version = self.version,
-                    name = self.name
-                );
-            }
-        }
-
-        Ok(())
+        publish_entry(
+            &client,
+            &signing_key,
+            entry,
+            SystemTime::now(),
+            self.no_wait,
+            output,
+            report,
+        )
+        .await
     }
 }
 
@@ -354,57 +712,39 @@ pub struct PublishGrantCommand {
 impl PublishGrantCommand {
     /// Executes the command.
     pub async fn exec(self, retry: Option<Retry>) -> Result<()> {
+        let output = self.common.output();
+        let entry = PublishEntry::Grant {
+            key: self.public_key.clone(),
+            permissions: self.permissions.clone(),
+        };
+        let mut report = PublishReport::new(self.name.clone(), vec![entry.clone()]);
+        let res = self.publish(retry, entry, output, &mut report).await;
+        finish(output, vec![report], res)
+    }
+
+    async fn publish(
+        &self,
+        retry: Option<Retry>,
+        entry: PublishEntry,
+        output: Output,
+        report: &mut PublishReport,
+    ) -> Result<()> {
         let config = self.common.read_config()?;
         let mut client = self.common.create_client(&config, retry).await?;
         client.refresh_namespace(self.name.namespace()).await?;
+        self.common.report_registry(&client);
         let signing_key = self.common.signing_key(&client)?;
 
-        match enqueue(&client, &self.name, |_| async {
-            Ok(PublishEntry::Grant {
-                key: self.public_key.clone(),
-                permissions: self.permissions.clone(),
-            })
-        })
-        .await?
-        {
-            Some(entry) => {
-                let record_id = client
-                    .publish_with_info(
-                        &signing_key,
-                        PublishInfo {
-                            name: self.name.clone(),
-                            head: None,
-                            entries: vec![entry],
-                        },
-                    )
-                    .await?;
-
-                if self.no_wait {
-                    println!("submitted record `{record_id}` for publishing");
-                } else {
-                    client
-                        .wait_for_publish(&self.name, &record_id, DEFAULT_WAIT_INTERVAL)
-                        .await?;
-
-                    println!(
-                        "granted ({permissions_str}) to key ID `{key_id}` for package `{name}`",
-                        permissions_str = self.permissions.iter().join(","),
-                        key_id = self.public_key.fingerprint(),
-                        name = self.name
-                    );
-                }
-            }
-            None => {
-                println!(
-                    "added grant of ({permissions_str}) to key ID `{key_id}` for package `{name}` to pending publish",
-                    permissions_str = self.permissions.iter().join(","),
-                    key_id = self.public_key.fingerprint(),
-                    name = self.name
-                );
-            }
-        }
-
-        Ok(())
+        publish_entry(
+            &client,
+            &signing_key,
+            entry,
+            SystemTime::now(),
+            self.no_wait,
+            output,
+            report,
+        )
+        .await
     }
 }
 
@@ -436,57 +776,101 @@ pub struct PublishRevokeCommand {
 impl PublishRevokeCommand {
     /// Executes the command.
     pub async fn exec(self, retry: Option<Retry>) -> Result<()> {
+        let output = self.common.output();
+        let entry = PublishEntry::Revoke {
+            key_id: self.key.clone(),
+            permissions: self.permissions.clone(),
+        };
+        let mut report = PublishReport::new(self.name.clone(), vec![entry.clone()]);
+        let res = self.publish(retry, entry, output, &mut report).await;
+        finish(output, vec![report], res)
+    }
+
+    async fn publish(
+        &self,
+        retry: Option<Retry>,
+        entry: PublishEntry,
+        output: Output,
+        report: &mut PublishReport,
+    ) -> Result<()> {
         let config = self.common.read_config()?;
         let mut client = self.common.create_client(&config, retry).await?;
         client.refresh_namespace(self.name.namespace()).await?;
+        self.common.report_registry(&client);
         let signing_key = self.common.signing_key(&client)?;
 
-        match enqueue(&client, &self.name, |_| async {
-            Ok(PublishEntry::Revoke {
-                key_id: self.key.clone(),
-                permissions: self.permissions.clone(),
-            })
-        })
-        .await?
-        {
-            Some(entry) => {
-                let record_id = client
-                    .publish_with_info(
-                        &signing_key,
-                        PublishInfo {
-                            name: self.name.clone(),
-                            head: None,
-                            entries: vec![entry],
-                        },
-                    )
-                    .await?;
+        publish_entry(
+            &client,
+            &signing_key,
+            entry,
+            SystemTime::now(),
+            self.no_wait,
+            output,
+            report,
+        )
+        .await
+    }
+}
 
-                if self.no_wait {
-                    println!("submitted record `{record_id}` for publishing");
-                } else {
-                    client
-                        .wait_for_publish(&self.name, &record_id, DEFAULT_WAIT_INTERVAL)
-                        .await?;
-
-                    println!(
-                        "revoked ({permissions_str}) from key ID `{key_id}` for package `{name}`",
-                        permissions_str = self.permissions.iter().join(","),
-                        key_id = self.key,
-                        name = self.name
-                    );
-                }
-            }
-            None => {
-                println!(
-                    "added revoke of ({permissions_str}) from key ID `{key_id}` for package `{name}` to pending publish",
-                    permissions_str = self.permissions.iter().join(","),
-                    key_id = self.key,
-                    name = self.name
-                );
-            }
-        }
+/// Publish a package to a warg registry.
+#[derive(Args)]
+#[clap(disable_version_flag = true)]
+pub struct PublishRevokeKeyCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The package name.
+    #[clap(long, short, value_name = "PACKAGE")]
+    pub name: PackageName,
+    /// The ID of the key to revoke.
+    #[clap(value_name = "KEY_ID")]
+    pub key: KeyID,
+    /// The time the key is revoked as of, in RFC 3339 format; defaults to now.
+    ///
+    /// Releases signed by the key at or after this time are not trusted.
+    #[clap(long, value_name = "TIME")]
+    pub since: Option<DateTime<Utc>>,
+    /// Whether to wait for the publish to complete.
+    #[clap(long)]
+    pub no_wait: bool,
+}
 
-        Ok(())
+impl PublishRevokeKeyCommand {
+    /// Executes the command.
+    pub async fn exec(self, retry: Option<Retry>) -> Result<()> {
+        let output = self.common.output();
+        let entry = PublishEntry::RevokeKey {
+            key_id: self.key.clone(),
+            since: self.since.map_or_else(SystemTime::now, Into::into),
+        };
+        let mut report = PublishReport::new(self.name.clone(), vec![entry.clone()]);
+        let res = self.publish(retry, entry, output, &mut report).await;
+        finish(output, vec![report], res)
+    }
+
+    async fn publish(
+        &self,
+        retry: Option<Retry>,
+        entry: PublishEntry,
+        output: Output,
+        report: &mut PublishReport,
+    ) -> Result<()> {
+        let config = self.common.read_config()?;
+        let mut client = self.common.create_client(&config, retry).await?;
+        client.refresh_namespace(self.name.namespace()).await?;
+        self.common.report_registry(&client);
+        let signing_key = self.common.signing_key(&client)?;
+
+        publish_entry(
+            &client,
+            &signing_key,
+            entry,
+            SystemTime::now(),
+            self.no_wait,
+            output,
+            report,
+        )
+        .await
     }
 }
 
@@ -505,12 +889,20 @@ pub struct PublishStartCommand {
 impl PublishStartCommand {
     /// Executes the command.
     pub async fn exec(self) -> Result<()> {
+        let output = self.common.output();
+        let mut report = PublishReport::new(self.name.clone(), Vec::new());
+        let res = self.start(output, &mut report).await;
+        finish(output, vec![report], res)
+    }
+
+    async fn start(&self, output: Output, report: &mut PublishReport) -> Result<()> {
         let config = self.common.read_config()?;
         let mut client = self.common.create_client(&config, None).await?;
         client.refresh_namespace(self.name.namespace()).await?;
 
-        match client.registry().load_publish().await? {
-            Some(info) => bail!("a publish is already in progress for package `{name}`; use `publish abort` to abort the current publish", name = info.name),
+        let publishes = client.registry().load_publishes().await?;
+        match publishes.iter().find(|info| info.name == self.name) {
+            Some(info) => bail!("a publish is already in progress for package `{name}`; use `publish abort {name}` to abort the current publish", name = info.name),
             None => {
                 client.registry().store_publish(Some(&PublishInfo {
                     name: self.name.clone(),
@@ -519,10 +911,11 @@ impl PublishStartCommand {
                 }))
                 .await?;
 
-                println!(
+                report.state = PublishState::Pending;
+                output.message(format_args!(
                     "started new pending publish for package `{name}`",
                     name = self.name
-                );
+                ));
                 Ok(())
             },
         }
@@ -535,50 +928,89 @@ pub struct PublishListCommand {
     /// The common command options.
     #[clap(flatten)]
     pub common: CommonOptions,
+    /// The package whose pending publish to list; if not specified, all
+    /// pending publishes are listed.
+    #[clap(long = "package", value_name = "PACKAGE")]
+    pub name: Option<PackageName>,
 }
 
 impl PublishListCommand {
     /// Executes the command.
     pub async fn exec(self) -> Result<()> {
+        let output = self.common.output();
+        let mut reports = Vec::new();
+        let res = self.list(output, &mut reports).await;
+        finish(output, reports, res)
+    }
+
+    async fn list(&self, output: Output, reports: &mut Vec<PublishReport>) -> Result<()> {
         let config = self.common.read_config()?;
         let client = self.common.create_client(&config, None).await?;
 
-        match client.registry().load_publish().await? {
-            Some(info) => {
-                println!(
-                    "publishing package `{name}` with {count} record(s) to publish\n",
-                    name = info.name,
-                    count = info.entries.len()
-                );
-
-                for (i, entry) in info.entries.iter().enumerate() {
-                    print!("record {i}: ");
-                    match entry {
-                        PublishEntry::Init => {
-                            println!("initialize package");
-                        }
-                        PublishEntry::Release { version, content } => {
-                            println!("release {version} with content digest `{content}`")
-                        }
-                        PublishEntry::Yank { version } => {
-                            println!("yank {version}")
+        let mut publishes = client.registry().load_publishes().await?;
+        if let Some(name) = &self.name {
+            publishes.retain(|info| &info.name == name);
+            if publishes.is_empty() {
+                bail!("no pending publish for package `{name}` to list");
+            }
+        } else if publishes.is_empty() {
+            bail!("no pending publish to list");
+        }
+
+        for (index, info) in publishes.iter().enumerate() {
+            reports.push(PublishReport::for_publish(info, PublishState::Pending));
+            if output.is_json() {
+                continue;
+            }
+
+            if index > 0 {
+                println!();
+            }
+
+            println!(
+                "publishing package `{name}` with {count} record(s) to publish\n",
+                name = info.name,
+                count = info.entries.len()
+            );
+
+            for (i, entry) in info.entries.iter().enumerate() {
+                print!("record {i}: ");
+                match entry {
+                    PublishEntry::Init => {
+                        println!("initialize package");
+                    }
+                    PublishEntry::Release {
+                        version,
+                        content,
+                        artifacts,
+                        ..
+                    } => {
+                        println!("release {version} with content digest `{content}`");
+                        for (name, content) in artifacts {
+                            println!("  with `{name}` artifact `{content}`");
                         }
-                        PublishEntry::Grant { key, permissions } => println!(
-                            "grant ({permissions_str}) to `{key_id}`",
-                            permissions_str = permissions.iter().join(","),
-                            key_id = key.fingerprint(),
-                        ),
-                        PublishEntry::Revoke {
-                            key_id,
-                            permissions,
-                        } => println!(
-                            "revoke ({permissions_str}) from `{key_id}`",
-                            permissions_str = permissions.iter().join(","),
-                        ),
                     }
+                    PublishEntry::Yank { version } => {
+                        println!("yank {version}")
+                    }
+                    PublishEntry::Grant { key, permissions } => println!(
+                        "grant ({permissions_str}) to `{key_id}`",
+                        permissions_str = permissions.iter().join(","),
+                        key_id = key.fingerprint(),
+                    ),
+                    PublishEntry::Revoke {
+                        key_id,
+                        permissions,
+                    } => println!(
+                        "revoke ({permissions_str}) from `{key_id}`",
+                        permissions_str = permissions.iter().join(","),
+                    ),
+                    PublishEntry::RevokeKey { key_id, since } => println!(
+                        "revoke key `{key_id}` as of {since}",
+                        since = format_time(*since),
+                    ),
                 }
             }
-            None => bail!("no pending publish to list"),
         }
 
         Ok(())
@@ -591,23 +1023,51 @@ pub struct PublishAbortCommand {
     /// The common command options.
     #[clap(flatten)]
     pub common: CommonOptions,
+    /// The package whose pending publish to abort; if not specified, all
+    /// pending publishes are aborted.
+    #[clap(value_name = "PACKAGE")]
+    pub name: Option<PackageName>,
+    /// The package whose pending publish to abort.
+    #[clap(long = "package", value_name = "PACKAGE", conflicts_with = "name")]
+    pub package: Option<PackageName>,
 }
 
 impl PublishAbortCommand {
     /// Executes the command.
     pub async fn exec(self) -> Result<()> {
+        let output = self.common.output();
+        let mut reports = Vec::new();
+        let res = self.abort(output, &mut reports).await;
+        finish(output, reports, res)
+    }
+
+    async fn abort(&self, output: Output, reports: &mut Vec<PublishReport>) -> Result<()> {
         let config = self.common.read_config()?;
         let client = self.common.create_client(&config, None).await?;
 
-        match client.registry().load_publish().await? {
-            Some(info) => {
-                client.registry().store_publish(None).await?;
-                println!(
-                    "aborted the pending publish for package `{name}`",
-                    name = info.name
-                );
+        let mut publishes = client.registry().load_publishes().await?;
+        let aborted = match self.package.as_ref().or(self.name.as_ref()) {
+            Some(name) => {
+                let index = publishes
+                    .iter()
+                    .position(|info| &info.name == name)
+                    .ok_or_else(|| anyhow!("no pending publish for package `{name}` to abort"))?;
+                vec![publishes.remove(index)]
             }
-            None => bail!("no pending publish to abort"),
+            None => std::mem::take(&mut publishes),
+        };
+
+        if aborted.is_empty() {
+            bail!("no pending publish to abort");
+        }
+
+        client.registry().store_publishes(&publishes).await?;
+        for info in aborted {
+            output.message(format_args!(
+                "aborted the pending publish for package `{name}`",
+                name = info.name
+            ));
+            reports.push(PublishReport::for_publish(&info, PublishState::Aborted));
         }
 
         Ok(())
@@ -623,67 +1083,780 @@ pub struct PublishSubmitCommand {
     /// Whether to wait for the publish to complete.
     #[clap(long)]
     pub no_wait: bool,
+    /// The package whose pending publish to submit; if not specified, the
+    /// pending publishes of all packages are submitted one after another.
+    #[clap(value_name = "PACKAGE", conflicts_with = "all")]
+    pub name: Option<PackageName>,
+    /// The package whose pending publish to submit.
+    #[clap(
+        long = "package",
+        value_name = "PACKAGE",
+        conflicts_with_all = ["name", "all"]
+    )]
+    pub package: Option<PackageName>,
+    /// Submit the pending publishes of all packages concurrently.
+    #[clap(long)]
+    pub all: bool,
+    /// The maximum number of pending publishes to submit at once (defaults to 4).
+    #[clap(long, value_name = "N", requires = "all")]
+    pub parallel: Option<usize>,
+    /// The number of seconds to wait for all publishes to complete (defaults to 600).
+    #[clap(long, value_name = "SECONDS", requires = "all")]
+    pub timeout: Option<u64>,
+    /// Print the records that would be submitted without signing or submitting them.
+    #[clap(long, alias = "draft", conflicts_with = "all")]
+    pub dry_run: bool,
+    /// The timestamp of the records, in RFC 3339 format; defaults to now.
+    ///
+    /// Pass the timestamp printed by `--dry-run` to submit the previewed records.
+    #[clap(long, value_name = "TIME", conflicts_with = "all")]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Fail instead of rebasing a publish whose record was rejected because
+    /// another record was published to the package first.
+    ///
+    /// Publishes submitted with `--all` are never rebased.
+    #[clap(long)]
+    pub no_rebase: bool,
+    /// Sign records by running the given command instead of using a key from
+    /// the keyring; the record is written to the command's stdin and the
+    /// command prints the signature to stdout.
+    #[clap(long, value_name = "COMMAND", requires = "signer_public_key")]
+    pub sign_with_command: Option<String>,
+    /// The public key of the key used by the signing command.
+    #[clap(long, value_name = "KEY", requires = "sign_with_command")]
+    pub signer_public_key: Option<PublicKey>,
 }
 
 impl PublishSubmitCommand {
     /// Executes the command.
     pub async fn exec(self) -> Result<()> {
+        let output = self.common.output();
+        let mut reports = Vec::new();
+        let res = self.submit(output, &mut reports).await;
+        finish(output, reports, res)
+    }
+
+    /// Gets the signer to sign records with.
+    fn signer(&self, client: &FileSystemClient) -> Result<Box<dyn Signer>> {
+        signer(
+            &self.common,
+            client,
+            self.sign_with_command.as_deref(),
+            self.signer_public_key.as_ref(),
+        )
+    }
+
+    async fn submit(&self, output: Output, reports: &mut Vec<PublishReport>) -> Result<()> {
         let config = self.common.read_config()?;
         let client = self.common.create_client(&config, None).await?;
 
-        match client.registry().load_publish().await? {
-            Some(info) => {
-                println!(
-                    "submitting publish for package `{name}`...",
-                    name = info.name
-                );
-
-                let signing_key = self.common.signing_key(&client)?;
-                let record_id = client.publish_with_info(&signing_key, info.clone()).await?;
-
-                client.registry().store_publish(None).await?;
-
-                if self.no_wait {
-                    println!("submitted record `{record_id}` for publishing");
-                } else {
-                    client
-                        .wait_for_publish(&info.name, &record_id, DEFAULT_WAIT_INTERVAL)
-                        .await?;
-
-                    for entry in &info.entries {
-                        let name = &info.name;
-                        match entry {
-                            PublishEntry::Init => {
-                                println!("published initialization of package `{name}`");
-                            }
-                            PublishEntry::Release { version, .. } => {
-                                println!("published version {version} of package `{name}`");
-                            }
-                            PublishEntry::Yank { version } => {
-                                println!("yanked version {version} of package `{name}`")
-                            }
-                            PublishEntry::Grant { key, permissions } => {
-                                println!(
-                                    "granted ({permissions_str}) to `{key_id}`",
-                                    permissions_str = permissions.iter().join(","),
-                                    key_id = key.fingerprint(),
-                                )
-                            }
-                            PublishEntry::Revoke {
-                                key_id,
-                                permissions,
-                            } => println!(
-                                "revoked ({permissions_str}) from `{key_id}`",
-                                permissions_str = permissions.iter().join(","),
-                            ),
-                        }
+        let mut publishes = client.registry().load_publishes().await?;
+        if self.all {
+            if publishes.is_empty() {
+                bail!("no pending publish to submit");
+            }
+
+            return self.submit_all(&client, publishes, output, reports).await;
+        }
+
+        let selected = match self.package.as_ref().or(self.name.as_ref()) {
+            Some(name) => vec![publishes
+                .iter()
+                .find(|info| &info.name == name)
+                .cloned()
+                .ok_or_else(|| anyhow!("no pending publish for package `{name}` to submit"))?],
+            None if publishes.is_empty() => bail!("no pending publish to submit"),
+            None => publishes.clone(),
+        };
+
+        let signer = self.signer(&client)?;
+        let timestamp = self.timestamp.map_or_else(SystemTime::now, Into::into);
+        if self.dry_run {
+            for info in &selected {
+                reports.push(PublishReport::for_publish(info, PublishState::Failed));
+                let report = reports.last_mut().unwrap();
+                print_dry_run(
+                    &client,
+                    &signer.public_key(),
+                    info,
+                    timestamp,
+                    output,
+                    report,
+                )
+                .await?;
+            }
+
+            return Ok(());
+        }
+
+        // Publishes are submitted one after another; a publish that fails to
+        // submit stops the others, which remain pending
+        for info in selected {
+            reports.push(PublishReport::for_publish(&info, PublishState::Failed));
+            let report = reports.last_mut().unwrap();
+            let name = &info.name;
+            output.message(format_args!("submitting publish for package `{name}`..."));
+
+            let record_id = client
+                .publish_with_info_at(&*signer, info.clone(), timestamp)
+                .await?;
+            report.submitted(record_id.clone());
+
+            publishes.retain(|pending| &pending.name != name);
+            client.registry().store_publishes(&publishes).await?;
+
+            output.message(format_args!(
+                "submitted record `{record_id}` for package `{name}`"
+            ));
+            if !self.no_wait {
+                self.wait_for_rebased_publish(&client, &*signer, &info, record_id, output, report)
+                    .await?;
+                print_published(&info, output);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Waits for a submitted record to be published.
+    ///
+    /// If the record is rejected because another record was published to
+    /// the package first, the publish is rebased onto the new head of the
+    /// package log and submitted again, up to `MAX_REBASE_ATTEMPTS` times.
+    async fn wait_for_rebased_publish(
+        &self,
+        client: &FileSystemClient,
+        signer: &dyn Signer,
+        info: &PublishInfo,
+        mut record_id: RecordId,
+        output: Output,
+        report: &mut PublishReport,
+    ) -> Result<()> {
+        let name = &info.name;
+        let mut info = info.clone();
+        let mut attempts = 0;
+        loop {
+            let result = client
+                .wait_for_publish(name, &record_id, DEFAULT_WAIT_INTERVAL)
+                .await?;
+            if self.no_rebase || attempts == MAX_REBASE_ATTEMPTS || !result.is_stale_head() {
+                return record_outcome(client, &record_id, &result, output, report).await;
+            }
+
+            attempts += 1;
+            output.message(format_args!(
+                "package `{name}` changed before record `{record_id}` was published; rebasing (attempt {attempts} of {MAX_REBASE_ATTEMPTS})..."
+            ));
+            info = match client.rebase_publish(&info).await {
+                Ok(info) => info,
+                Err(e) => {
+                    report.fail(&e);
+                    return Err(e.into());
+                }
+            };
+
+            record_id = client.publish_with_info(signer, info.clone()).await?;
+            report.submitted(record_id.clone());
+            output.message(format_args!(
+                "submitted record `{record_id}` for package `{name}`"
+            ));
+        }
+    }
+
+    /// Submits the given pending publishes concurrently and waits for all of
+    /// them to complete together.
+    ///
+    /// A failure to publish one package does not stop the others; publishes
+    /// that could not be submitted remain pending.
+    async fn submit_all(
+        &self,
+        client: &FileSystemClient,
+        publishes: Vec<PublishInfo>,
+        output: Output,
+        reports: &mut Vec<PublishReport>,
+    ) -> Result<()> {
+        output.message(format_args!(
+            "submitting publishes for {count} package(s)...",
+            count = publishes.len()
+        ));
+
+        let submitted = submit_concurrently(
+            client,
+            publishes,
+            self.parallel.unwrap_or(DEFAULT_PARALLEL_SUBMISSIONS),
+            || self.signer(client),
+        )
+        .await;
+
+        let mut pending = Vec::new();
+        for (info, report) in submitted {
+            if report.state != PublishState::Submitted {
+                pending.push(info);
+            }
+            reports.push(report);
+        }
+
+        client.registry().store_publishes(&pending).await?;
+        finish_submissions(client, reports, self.no_wait, self.timeout, output).await
+    }
+}
+
+/// Gets the signer to sign records with: the given signing command if
+/// there is one, or otherwise the signing key from the keyring.
+fn signer(
+    common: &CommonOptions,
+    client: &FileSystemClient,
+    command: Option<&str>,
+    public_key: Option<&PublicKey>,
+) -> Result<Box<dyn Signer>> {
+    match (command, public_key) {
+        (Some(command), Some(key)) => Ok(Box::new(CommandSigner::new(command, key.clone()))),
+        _ => Ok(Box::new(common.signing_key(client)?)),
+    }
+}
+
+/// Submits the given publishes concurrently, at most `parallel` at a time.
+///
+/// Returns each publish along with the report of its submission.
+async fn submit_concurrently<F>(
+    client: &FileSystemClient,
+    publishes: Vec<PublishInfo>,
+    parallel: usize,
+    signer: F,
+) -> Vec<(PublishInfo, PublishReport)>
+where
+    F: Fn() -> Result<Box<dyn Signer>>,
+{
+    let signer = &signer;
+    stream::iter(publishes)
+        .map(|info| async move {
+            let mut report = PublishReport::for_publish(&info, PublishState::Failed);
+            match signer() {
+                Ok(signer) => match client.publish_with_info(&*signer, info.clone()).await {
+                    Ok(record_id) => report.submitted(record_id),
+                    Err(e) => report.record_error(e),
+                },
+                Err(e) => report.fail(format_args!("{e:#}")),
+            }
+            (info, report)
+        })
+        .buffered(parallel.max(1))
+        .collect()
+        .await
+}
+
+/// Waits for submitted publishes to complete, unless `no_wait` is set, and
+/// prints a summary of the outcome of each.
+///
+/// Fails if any of the publishes did not complete.
+async fn finish_submissions(
+    client: &FileSystemClient,
+    reports: &mut [PublishReport],
+    no_wait: bool,
+    timeout: Option<u64>,
+    output: Output,
+) -> Result<()> {
+    if !no_wait {
+        let deadline = Instant::now()
+            + timeout
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_SUBMIT_ALL_TIMEOUT);
+        wait_for_all(client, reports, deadline).await;
+
+        if output.is_json() {
+            let published: Vec<_> = reports
+                .iter()
+                .filter(|report| report.state == PublishState::Published)
+                .map(|report| report.package.clone())
+                .collect();
+            if !published.is_empty() {
+                let checkpoint = published_checkpoint(client, &published).await?;
+                for report in reports.iter_mut() {
+                    if report.state == PublishState::Published {
+                        report.checkpoint = Some(checkpoint.clone());
                     }
                 }
             }
-            None => bail!("no pending publish to submit"),
         }
+    }
 
-        Ok(())
+    if !output.is_json() {
+        print_summary(reports);
+    }
+
+    let failed = reports
+        .iter()
+        .filter(|report| {
+            !matches!(
+                report.state,
+                PublishState::Submitted | PublishState::Published | PublishState::AlreadyPublished
+            )
+        })
+        .count();
+    if failed > 0 {
+        bail!(
+            "{failed} of {count} package(s) failed to publish",
+            count = reports.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Publish every package release found in a directory.
+///
+/// The directory is laid out as `<namespace>/<name>/<version>.wasm`; each
+/// package is initialized if needed and its releases are published in
+/// version order as a single record. Releases already in the registry with
+/// the same content are skipped, so an interrupted import may be run again.
+#[derive(Args)]
+pub struct PublishDirCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The path to the directory of packages to publish.
+    #[clap(value_name = "PATH")]
+    pub path: PathBuf,
+    /// Whether to wait for the publishes to complete.
+    #[clap(long)]
+    pub no_wait: bool,
+    /// The maximum number of packages to submit at once (defaults to 4).
+    #[clap(long, value_name = "N")]
+    pub parallel: Option<usize>,
+    /// The number of seconds to wait for all publishes to complete (defaults to 600).
+    #[clap(long, value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+    /// Sign records by running the given command instead of using a key from
+    /// the keyring; the record is written to the command's stdin and the
+    /// command prints the signature to stdout.
+    #[clap(long, value_name = "COMMAND", requires = "signer_public_key")]
+    pub sign_with_command: Option<String>,
+    /// The public key of the key used by the signing command.
+    #[clap(long, value_name = "KEY", requires = "sign_with_command")]
+    pub signer_public_key: Option<PublicKey>,
+}
+
+impl PublishDirCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let output = self.common.output();
+        let mut reports = Vec::new();
+        let res = self.publish(output, &mut reports).await;
+        finish(output, reports, res)
+    }
+
+    async fn publish(&self, output: Output, reports: &mut Vec<PublishReport>) -> Result<()> {
+        let packages = find_releases(&self.path)?;
+        if packages.is_empty() {
+            bail!(
+                "no packages were found in `{path}`",
+                path = self.path.display()
+            );
+        }
+
+        let config = self.common.read_config()?;
+        let client = self.common.create_client(&config, None).await?;
+        self.common.report_registry(&client);
+
+        output.message(format_args!(
+            "fetching the logs of {count} package(s)...",
+            count = packages.len()
+        ));
+        let mut publishes = Vec::new();
+        for (name, releases) in packages {
+            // Each log is fetched on its own as packages yet to be imported
+            // do not exist in the registry
+            let fetch = client
+                .fetch_packages([&name])
+                .await?
+                .pop()
+                .context("expected the outcome of fetching the package log")?;
+            let mut report = PublishReport::new(name.clone(), Vec::new());
+            match unpublished_releases(&client, fetch, releases).await {
+                Ok(Some(info)) => {
+                    publishes.push(info);
+                    continue;
+                }
+                Ok(None) => report.state = PublishState::AlreadyPublished,
+                Err(e) => report.fail(format_args!("{e:#}")),
+            }
+            reports.push(report);
+        }
+
+        if publishes.is_empty() {
+            output.message("every release is already published");
+        } else {
+            output.message(format_args!(
+                "submitting publishes for {count} package(s)...",
+                count = publishes.len()
+            ));
+        }
+
+        let submitted = submit_concurrently(
+            &client,
+            publishes,
+            self.parallel.unwrap_or(DEFAULT_PARALLEL_SUBMISSIONS),
+            || {
+                signer(
+                    &self.common,
+                    &client,
+                    self.sign_with_command.as_deref(),
+                    self.signer_public_key.as_ref(),
+                )
+            },
+        )
+        .await;
+        reports.extend(submitted.into_iter().map(|(_, report)| report));
+
+        finish_submissions(&client, reports, self.no_wait, self.timeout, output).await
+    }
+}
+
+/// The releases of a package found in a directory, with the path of the
+/// content of each.
+type DirReleases = Vec<(Version, PathBuf)>;
+
+/// Finds the package releases in a directory laid out as
+/// `<namespace>/<name>/<version>.wasm`.
+///
+/// Files without a `.wasm` extension are ignored. The packages are ordered
+/// by name and the releases of each package by version.
+fn find_releases(root: &Path) -> Result<Vec<(PackageName, DirReleases)>> {
+    let mut packages = Vec::new();
+    for namespace in subdirectories(root)? {
+        for package in subdirectories(&namespace)? {
+            let name = PackageName::new(format!(
+                "{namespace}:{name}",
+                namespace = file_name(&namespace),
+                name = file_name(&package)
+            ))
+            .with_context(|| {
+                format!(
+                    "directory `{path}` is not named for a valid package",
+                    path = package.display()
+                )
+            })?;
+
+            let mut releases = Vec::new();
+            for entry in fs::read_dir(&package)? {
+                let path = entry?.path();
+                if !path.is_file() || path.extension().map_or(true, |ext| ext != "wasm") {
+                    continue;
+                }
+
+                let version = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse::<Version>().ok())
+                    .with_context(|| {
+                        format!(
+                            "file `{path}` is not named for a valid version",
+                            path = path.display()
+                        )
+                    })?;
+                releases.push((version, path));
+            }
+
+            if !releases.is_empty() {
+                releases.sort_by(|(a, _), (b, _)| a.cmp(b));
+                packages.push((name, releases));
+            }
+        }
+    }
+
+    packages.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(packages)
+}
+
+/// Gets the subdirectories of the given directory.
+fn subdirectories(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(path)
+        .with_context(|| format!("failed to read directory `{path}`", path = path.display()))?
+    {
+        let path = entry?.path();
+        if path.is_dir() {
+            dirs.push(path);
+        }
+    }
+
+    Ok(dirs)
+}
+
+/// Gets the last component of a path as a string.
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Builds the publish of the releases of a package that are not yet in the
+/// registry, initializing the package if it does not exist.
+///
+/// Returns `Ok(None)` if every release is already in the registry.
+async fn unpublished_releases(
+    client: &FileSystemClient,
+    fetch: PackageFetch,
+    releases: DirReleases,
+) -> Result<Option<PublishInfo>> {
+    let name = fetch.name;
+    let mut entries = Vec::new();
+    match fetch.new_records {
+        Ok(_) if fetch.head.is_some() => {}
+        Ok(_)
+        | Err(
+            ClientError::PackageDoesNotExist { .. }
+            | ClientError::PackageDoesNotExistWithHint { .. },
+        ) => entries.push(PublishEntry::Init),
+        Err(e) => return Err(e.into()),
+    }
+
+    let state = client
+        .registry()
+        .load_package(client.get_warg_registry(), &name)
+        .await?
+        .map(|info| info.state)
+        .unwrap_or_default();
+    for (version, path) in releases {
+        let content = store_file(client, &path).await?;
+        match state.release(&version).map(|release| release.content()) {
+            Some(Some(existing)) if existing == &content => continue,
+            Some(Some(_)) => bail!(
+                "version {version} of package `{name}` was already released with different content"
+            ),
+            Some(None) => bail!("version {version} of package `{name}` was yanked"),
+            None => entries.push(PublishEntry::Release {
+                version,
+                content,
+                artifacts: IndexMap::new(),
+                metadata: None,
+            }),
+        }
+    }
+
+    if entries.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(PublishInfo {
+        name,
+        head: None,
+        entries,
+    }))
+}
+
+/// Describes an entry added to the pending publish for a package.
+fn describe_enqueued(name: &PackageName, entry: &PublishEntry) -> String {
+    match entry {
+        PublishEntry::Init => {
+            format!("added initialization of package `{name}` to pending publish")
+        }
+        PublishEntry::Release { version, .. } => {
+            format!("added release of version {version} for package `{name}` to pending publish")
+        }
+        PublishEntry::Yank { version } => {
+            format!("added yank of version {version} for package `{name}` to pending publish")
+        }
+        PublishEntry::Grant { key, permissions } => format!(
+            "added grant of ({permissions_str}) to key ID `{key_id}` for package `{name}` to pending publish",
+            permissions_str = permissions.iter().join(","),
+            key_id = key.fingerprint(),
+        ),
+        PublishEntry::Revoke {
+            key_id,
+            permissions,
+        } => format!(
+            "added revoke of ({permissions_str}) from key ID `{key_id}` for package `{name}` to pending publish",
+            permissions_str = permissions.iter().join(","),
+        ),
+        PublishEntry::RevokeKey { key_id, since } => format!(
+            "added revocation of key ID `{key_id}` as of {since} for package `{name}` to pending publish",
+            since = format_time(*since),
+        ),
+    }
+}
+
+/// Describes a published entry of a package.
+fn describe_published(name: &PackageName, entry: &PublishEntry) -> String {
+    match entry {
+        PublishEntry::Init => format!("published initialization of package `{name}`"),
+        PublishEntry::Release { version, .. } => {
+            format!("published version {version} of package `{name}`")
+        }
+        PublishEntry::Yank { version } => format!("yanked version {version} of package `{name}`"),
+        PublishEntry::Grant { key, permissions } => format!(
+            "granted ({permissions_str}) to key ID `{key_id}` for package `{name}`",
+            permissions_str = permissions.iter().join(","),
+            key_id = key.fingerprint(),
+        ),
+        PublishEntry::Revoke {
+            key_id,
+            permissions,
+        } => format!(
+            "revoked ({permissions_str}) from key ID `{key_id}` for package `{name}`",
+            permissions_str = permissions.iter().join(","),
+        ),
+        PublishEntry::RevokeKey { key_id, since } => format!(
+            "revoked key ID `{key_id}` as of {since} for package `{name}`",
+            since = format_time(*since),
+        ),
+    }
+}
+
+/// Formats a time in RFC 3339 format.
+fn format_time(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Prints the entries of a publish that was published.
+fn print_published(info: &PublishInfo, output: Output) {
+    for entry in &info.entries {
+        output.message(describe_published(&info.name, entry));
+    }
+}
+
+/// Waits for a submitted record to be published, recording the outcome in
+/// the given report.
+///
+/// Fails with the registry's reason if the record was not published.
+async fn wait_for_publish(
+    client: &FileSystemClient,
+    record_id: &RecordId,
+    output: Output,
+    report: &mut PublishReport,
+) -> Result<()> {
+    let result = client
+        .wait_for_publish(&report.package, record_id, DEFAULT_WAIT_INTERVAL)
+        .await?;
+    record_outcome(client, record_id, &result, output, report).await
+}
+
+/// Records the outcome of a submitted record in the given report.
+///
+/// Fails with the registry's reason if the record was not published.
+async fn record_outcome(
+    client: &FileSystemClient,
+    record_id: &RecordId,
+    result: &PublishResult,
+    output: Output,
+    report: &mut PublishReport,
+) -> Result<()> {
+    let name = report.package.clone();
+    report.record_result(result);
+
+    if output.is_json() && result.is_published() {
+        report.checkpoint = Some(published_checkpoint(client, [&name]).await?);
+    }
+
+    ensure_published(&name, record_id, result)
+}
+
+/// Updates the given packages to the latest checkpoint, which includes
+/// their published records, and gets its identifier.
+async fn published_checkpoint<'a, I>(client: &FileSystemClient, packages: I) -> Result<AnyHash>
+where
+    I: IntoIterator<Item = &'a PackageName>,
+    I::IntoIter: ExactSizeIterator,
+{
+    client.upsert(packages).await?;
+
+    let checkpoint = client
+        .registry()
+        .load_checkpoint(client.get_warg_registry())
+        .await?
+        .context("client storage has no checkpoint after updating packages")?;
+    let checkpoint = &checkpoint.as_ref().checkpoint;
+    Ok(AnyHash::of(checkpoint.log_root.algorithm(), checkpoint))
+}
+
+/// Fails with the registry's reason if a record was not published.
+fn ensure_published(
+    name: &PackageName,
+    record_id: &RecordId,
+    result: &PublishResult,
+) -> Result<()> {
+    match result.reason() {
+        Some(reason) => {
+            bail!("record `{record_id}` of package `{name}` was not published: {reason}")
+        }
+        None => Ok(()),
+    }
+}
+
+/// Polls the status of every submitted record in a single loop until all of
+/// them complete or the deadline passes.
+async fn wait_for_all(client: &FileSystemClient, reports: &mut [PublishReport], deadline: Instant) {
+    loop {
+        for report in reports.iter_mut() {
+            let (PublishState::Submitted, Some(record_id)) = (report.state, &report.record_id)
+            else {
+                continue;
+            };
+
+            match client.check_publish(&report.package, record_id).await {
+                Ok(Some(result)) => report.record_result(&result),
+                Ok(None) => {}
+                Err(e) => report.record_error(e),
+            }
+        }
+
+        let waiting = reports
+            .iter()
+            .any(|report| report.state == PublishState::Submitted);
+        if !waiting {
+            return;
+        }
+
+        if Instant::now() >= deadline {
+            for report in reports.iter_mut() {
+                if report.state == PublishState::Submitted {
+                    report.state = PublishState::TimedOut;
+                }
+            }
+            return;
+        }
+
+        tokio::time::sleep(DEFAULT_WAIT_INTERVAL).await;
+    }
+}
+
+/// Prints a table of the outcome of each package submitted with `publish submit --all`.
+fn print_summary(reports: &[PublishReport]) {
+    let cells: Vec<_> = reports
+        .iter()
+        .map(|report| {
+            (
+                report.package.to_string(),
+                report
+                    .record_id
+                    .as_ref()
+                    .map(ToString::to_string)
+                    .unwrap_or_else(|| "-".to_string()),
+                report.status(),
+            )
+        })
+        .collect();
+
+    let name_width = cells
+        .iter()
+        .map(|(name, ..)| name.len())
+        .chain(["PACKAGE".len()])
+        .max()
+        .unwrap_or_default();
+    let record_width = cells
+        .iter()
+        .map(|(_, record, _)| record.len())
+        .chain(["RECORD".len()])
+        .max()
+        .unwrap_or_default();
+
+    println!();
+    println!(
+        "{:name_width$}  {:record_width$}  STATUS",
+        "PACKAGE", "RECORD"
+    );
+    for (name, record, status) in cells {
+        println!("{name:name_width$}  {record:record_width$}  {status}");
     }
 }
 
@@ -706,25 +1879,81 @@ pub struct PublishWaitCommand {
 impl PublishWaitCommand {
     /// Executes the command.
     pub async fn exec(self) -> Result<()> {
+        let output = self.common.output();
+        let mut report = PublishReport::new(self.name.clone(), Vec::new());
+        report.record_id = Some(RecordId::from(self.record_id.clone()));
+        let res = self.wait(output, &mut report).await;
+        finish(output, vec![report], res)
+    }
+
+    async fn wait(&self, output: Output, report: &mut PublishReport) -> Result<()> {
         let config = self.common.read_config()?;
         let mut client = self.common.create_client(&config, None).await?;
         client.refresh_namespace(self.name.namespace()).await?;
-        let record_id = RecordId::from(self.record_id);
+        let record_id = RecordId::from(self.record_id.clone());
 
-        println!(
+        output.message(format_args!(
             "waiting for record `{record_id} of package `{name}` to be published...",
             name = self.name
-        );
-
-        client
-            .wait_for_publish(&self.name, &record_id, Duration::from_secs(1))
+        ));
+
+        let mut last = None;
+        let result = client
+            .wait_for_publish_with_progress(
+                &self.name,
+                &record_id,
+                Duration::from_secs(1),
+                |status| {
+                    // Only report when the log has made progress
+                    let ahead = records_ahead(&record_id, status);
+                    if last != Some(ahead) {
+                        output.message(format_progress(ahead, status));
+                        last = Some(ahead);
+                    }
+                },
+            )
             .await?;
+        report.record_result(&result);
+        ensure_published(&self.name, &record_id, &result)?;
+
+        if output.is_json() {
+            report.checkpoint = Some(published_checkpoint(&client, [&self.name]).await?);
+        }
 
-        println!(
+        output.message(format_args!(
             "record `{record_id} of package `{name}` has been published",
             name = self.name
-        );
+        ));
 
         Ok(())
     }
 }
+
+/// Gets the number of validated records in the log that precede the given record
+/// and are not yet included in a checkpoint.
+fn records_ahead(record_id: &RecordId, status: &PackageLogStatus) -> u64 {
+    // A validated record is the head of the log until another record follows it
+    if status.head.as_ref() == Some(record_id) {
+        status.pending_records.saturating_sub(1)
+    } else {
+        status.pending_records
+    }
+}
+
+/// Formats the progress of a record waiting to be published.
+fn format_progress(ahead: u64, status: &PackageLogStatus) -> String {
+    let mut progress = format!(
+        "{ahead} record{s} ahead of yours",
+        s = if ahead == 1 { "" } else { "s" }
+    );
+
+    if let Some(timestamp) = status.checkpoint_timestamp {
+        let elapsed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs().saturating_sub(timestamp))
+            .unwrap_or_default();
+        progress.push_str(&format!(", last checkpoint {elapsed}s ago"));
+    }
+
+    progress
+}
@@ -1,8 +1,12 @@
 use super::CommonOptions;
 use anyhow::Result;
 use clap::Args;
+use dialoguer::{theme::ColorfulTheme, Confirm};
 
 /// Reset local data for registry.
+///
+/// Package logs, checkpoints, and pending publishes are removed; cached
+/// content is kept and may be pruned with `warg clean --content`.
 #[derive(Args)]
 pub struct ResetCommand {
     /// The common command options.
@@ -14,6 +18,12 @@ pub struct ResetCommand {
     /// Whether to reset namespace mappings
     #[clap(long)]
     pub namespaces: bool,
+    /// Reset without asking for confirmation.
+    #[clap(long, short)]
+    pub yes: bool,
+    /// List the local data that would be removed without removing it.
+    #[clap(long)]
+    pub dry_run: bool,
 }
 
 impl ResetCommand {
@@ -22,6 +32,41 @@ impl ResetCommand {
         let config = self.common.read_config()?;
         let client = self.common.create_client(&config, None).await?;
 
+        let entries = client.registry_state(self.all).await?;
+        for entry in &entries {
+            println!(
+                "{registry}: {item} ({size} bytes)",
+                registry = entry.registry,
+                item = entry.item,
+                size = entry.size
+            );
+        }
+        let total: u64 = entries.iter().map(|e| e.size).sum();
+
+        if self.dry_run {
+            println!(
+                "would remove {count} item(s) totaling {total} bytes",
+                count = entries.len()
+            );
+            return Ok(());
+        }
+
+        let prompt = if self.all {
+            "are you sure you want to reset local data for all registries".to_string()
+        } else {
+            format!(
+                "are you sure you want to reset local data for registry `{url}`",
+                url = client.url()
+            )
+        };
+        if !self.yes
+            && !Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(prompt)
+                .interact()?
+        {
+            return Ok(());
+        }
+
         if self.all {
             println!("resetting local data for all registries...");
             client.reset_registry(true).await?;
@@ -34,6 +79,10 @@ impl ResetCommand {
             client.reset_namespaces().await?;
         }
 
+        println!(
+            "removed {count} item(s) totaling {total} bytes",
+            count = entries.len()
+        );
         Ok(())
     }
 }
@@ -0,0 +1,57 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use std::{fs, path::PathBuf, time::SystemTime};
+use warg_client::{
+    lockfile::{LockFile, LOCK_FILE_NAME},
+    sbom::{self, SbomFormat},
+};
+
+/// Generate a software bill of materials from a lock file.
+#[derive(Args)]
+pub struct SbomCommand {
+    /// The path to the lock file to read.
+    #[clap(long, value_name = "LOCK", default_value = LOCK_FILE_NAME)]
+    pub lock: PathBuf,
+
+    /// The format of the bill of materials (`cyclonedx` or `spdx`).
+    #[clap(long, value_name = "FORMAT", default_value = "cyclonedx")]
+    pub format: SbomFormat,
+
+    /// The path to write the bill of materials to; defaults to stdout.
+    #[clap(long, short, value_name = "OUTPUT")]
+    pub output: Option<PathBuf>,
+}
+
+impl SbomCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let lock = LockFile::from_file(&self.lock)?;
+        let name = self
+            .lock
+            .canonicalize()
+            .ok()
+            .and_then(|p| p.parent()?.file_name()?.to_str().map(ToString::to_string))
+            .unwrap_or_else(|| "warg".to_string());
+        let document = sbom::generate(&lock, self.format, &name, SystemTime::now())?;
+
+        match &self.output {
+            Some(output) => {
+                fs::write(output, document).with_context(|| {
+                    format!(
+                        "failed to write bill of materials `{path}`",
+                        path = output.display()
+                    )
+                })?;
+                println!(
+                    "wrote {format} bill of materials for {count} package(s) to `{path}`",
+                    format = self.format,
+                    count = lock.packages.len(),
+                    path = output.display()
+                );
+            }
+            None => println!("{document}"),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use super::CommonOptions;
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use warg_client::trust::TrustPolicy;
+use warg_crypto::signing::KeyID;
+use warg_protocol::registry::PackageName;
+
+/// Manage the keys trusted to sign the records of packages.
+///
+/// Records of a package with trusted keys that are signed by any other key
+/// are reported with a warning when the package log is fetched, or fail the
+/// fetch with `--strict-trust`.
+#[derive(Subcommand)]
+pub enum TrustCommand {
+    /// Trusts a key to sign the records of a package or namespace.
+    Add(TrustAddCommand),
+    /// Lists the keys trusted for each package or namespace.
+    List(TrustListCommand),
+}
+
+impl TrustCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        match self {
+            Self::Add(cmd) => cmd.exec().await,
+            Self::List(cmd) => cmd.exec().await,
+        }
+    }
+}
+
+/// Trusts a key to sign the records of a package or namespace.
+#[derive(Args)]
+pub struct TrustAddCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The package name (e.g. `example:hello`) or namespace pattern (e.g.
+    /// `example` or `example-*`) to trust the key for.
+    #[clap(value_name = "PACKAGE")]
+    pub package: String,
+    /// The ID of the key to trust (e.g. `sha256:abc...`).
+    #[clap(value_name = "KEY_ID")]
+    pub key_id: KeyID,
+}
+
+impl TrustAddCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        // Reject invalid package names; namespaces and patterns are matched as given
+        if self.package.contains(':') && !self.package.ends_with('*') {
+            PackageName::new(self.package.as_str())?;
+        }
+
+        let path = self.common.read_config()?.trust_policy_path()?;
+        let mut policy = TrustPolicy::from_file(&path)?;
+        if policy.add(self.package.as_str(), self.key_id.clone()) {
+            policy.write_to_file(&path)?;
+            println!(
+                "trusted key `{key_id}` for `{package}`",
+                key_id = self.key_id,
+                package = self.package
+            );
+        } else {
+            println!(
+                "key `{key_id}` is already trusted for `{package}`",
+                key_id = self.key_id,
+                package = self.package
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Lists the keys trusted for each package or namespace.
+#[derive(Args)]
+pub struct TrustListCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+}
+
+impl TrustListCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let path = self.common.read_config()?.trust_policy_path()?;
+        let policy = TrustPolicy::from_file(path)?;
+        if policy.packages.is_empty() {
+            println!("no keys are trusted; any key authorized by a package log is accepted");
+        }
+
+        for (package, key_ids) in &policy.packages {
+            println!("{package}:");
+            for key_id in key_ids {
+                println!("  {key_id}");
+            }
+        }
+
+        Ok(())
+    }
+}
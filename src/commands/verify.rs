@@ -0,0 +1,152 @@
+use super::CommonOptions;
+use anyhow::{bail, Result};
+use clap::Args;
+use serde::Serialize;
+use std::path::PathBuf;
+use warg_client::{VerificationCheck, VerificationOutcome};
+use warg_crypto::hash::AnyHash;
+use warg_protocol::{
+    registry::{PackageName, RegistryLen},
+    Version,
+};
+
+/// Verify that a file is the content of a package release.
+///
+/// The package log is validated up to the registry's latest checkpoint, the
+/// checkpoint's signature is verified against the operator log, and the log
+/// heads are proven included in the checkpoint.
+#[derive(Args)]
+pub struct VerifyCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The package that released the file.
+    #[clap(value_name = "PACKAGE")]
+    pub package: PackageName,
+    /// The version of the release.
+    #[clap(id = "release_version", value_name = "VERSION")]
+    pub version: Version,
+    /// The path of the file to verify.
+    #[clap(value_name = "PATH")]
+    pub path: PathBuf,
+}
+
+impl VerifyCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = self.common.read_config()?;
+        let client = self.common.create_client(&config, None).await?;
+        let output = self.common.output();
+
+        output.message(format_args!(
+            "verifying `{path}` as version `{version}` of package `{package}`...",
+            path = self.path.display(),
+            version = self.version,
+            package = self.package
+        ));
+
+        let verification = client
+            .verify_release(&self.package, &self.version, &self.path)
+            .await?;
+
+        let report = VerifyReport {
+            package: &self.package,
+            version: &self.version,
+            path: &self.path,
+            digest: verification.digest.as_ref(),
+            log_length: verification.log_length,
+            passed: verification.passed(),
+            checks: verification
+                .checks
+                .iter()
+                .map(|(check, outcome)| CheckReport::new(*check, outcome))
+                .collect(),
+        };
+
+        output.document(&report)?;
+        for check in &report.checks {
+            let state = match check.state {
+                CheckState::Passed => "pass",
+                CheckState::Failed => "FAIL",
+                CheckState::Skipped => "skip",
+            };
+            match &check.error {
+                Some(error) => output.message(format_args!(
+                    "  {state}  {check}: {error}",
+                    check = check.display
+                )),
+                None => output.message(format_args!("  {state}  {check}", check = check.display)),
+            }
+        }
+
+        if !report.passed {
+            bail!(
+                "`{path}` could not be verified as version `{version}` of package `{package}`",
+                path = self.path.display(),
+                version = self.version,
+                package = self.package
+            );
+        }
+
+        output.message(format_args!(
+            "verified against the checkpoint with log length {log_length}",
+            log_length = report.log_length
+        ));
+        Ok(())
+    }
+}
+
+/// The result of a verification, as printed in the JSON format.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifyReport<'a> {
+    package: &'a PackageName,
+    version: &'a Version,
+    path: &'a PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    digest: Option<&'a AnyHash>,
+    log_length: RegistryLen,
+    passed: bool,
+    checks: Vec<CheckReport>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum CheckState {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckReport {
+    check: &'static str,
+    #[serde(skip)]
+    display: String,
+    state: CheckState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl CheckReport {
+    fn new(check: VerificationCheck, outcome: &VerificationOutcome) -> Self {
+        let (state, error) = match outcome {
+            VerificationOutcome::Passed => (CheckState::Passed, None),
+            VerificationOutcome::Failed(e) => (CheckState::Failed, Some(e.to_string())),
+            VerificationOutcome::Skipped => (CheckState::Skipped, None),
+        };
+
+        Self {
+            check: match check {
+                VerificationCheck::PackageLog => "packageLog",
+                VerificationCheck::ReleaseContent => "releaseContent",
+                VerificationCheck::CheckpointSignature => "checkpointSignature",
+                VerificationCheck::InclusionProof => "inclusionProof",
+            },
+            display: check.to_string(),
+            state,
+            error,
+        }
+    }
+}
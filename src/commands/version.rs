@@ -0,0 +1,52 @@
+use anyhow::Result;
+use clap::Args;
+use warg_api::v1::API_VERSION;
+
+/// The features the tool was built with.
+const FEATURES: &[(&str, bool)] = &[
+    ("postgres", cfg!(feature = "postgres")),
+    ("s3", cfg!(feature = "s3")),
+    ("grpc", cfg!(feature = "grpc")),
+];
+
+/// Gets the version of the tool, including the commit it was built from when
+/// known.
+pub fn version() -> &'static str {
+    option_env!("CARGO_VERSION_INFO").unwrap_or(env!("CARGO_PKG_VERSION"))
+}
+
+/// Display the version of the `warg` tool.
+#[derive(Args)]
+pub struct VersionCommand {
+    /// Also display the commit, features, and registry API version of the build.
+    #[clap(short, long)]
+    pub verbose: bool,
+}
+
+impl VersionCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        println!("warg {version}", version = version());
+        if !self.verbose {
+            return Ok(());
+        }
+
+        println!(
+            "commit: {commit}",
+            commit = option_env!("CARGO_GIT_HASH").unwrap_or("unknown")
+        );
+
+        let features = FEATURES
+            .iter()
+            .filter_map(|(name, enabled)| enabled.then_some(*name))
+            .collect::<Vec<_>>();
+        if features.is_empty() {
+            println!("features: none");
+        } else {
+            println!("features: {features}", features = features.join(", "));
+        }
+
+        println!("registry API: {API_VERSION}");
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use super::CommonOptions;
+use anyhow::Result;
+use clap::Args;
+use indexmap::IndexMap;
+use warg_client::{storage::RegistryStorage as _, FileSystemClient};
+use warg_crypto::hash::AnyHash;
+use warg_protocol::registry::{PackageName, RegistryIndex};
+
+/// Watch a registry for new checkpoints.
+///
+/// Prints a line for each new checkpoint followed by the packages in client
+/// storage it updated.
+#[derive(Args)]
+pub struct WatchCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The packages to add to client storage before watching.
+    #[clap(value_name = "PACKAGE")]
+    pub packages: Vec<PackageName>,
+}
+
+impl WatchCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = self.common.read_config()?;
+        let client = self.common.create_client(&config, None).await?;
+
+        if !self.packages.is_empty() {
+            client.upsert(self.packages.iter()).await?;
+        }
+
+        let algorithm = client.hash_algorithm().await?;
+        let mut checkpoint = client.wait_for_checkpoint(None).await?;
+        let mut heads = package_heads(&client).await?;
+        println!(
+            "watching for checkpoints after log length {len}...",
+            len = checkpoint.as_ref().checkpoint.log_length
+        );
+
+        loop {
+            let since = AnyHash::of(algorithm, &checkpoint.as_ref().checkpoint);
+            checkpoint = client.wait_for_checkpoint(Some(&since)).await?;
+
+            println!(
+                "checkpoint `{id}` (log length {len})",
+                id = AnyHash::of(algorithm, &checkpoint.as_ref().checkpoint),
+                len = checkpoint.as_ref().checkpoint.log_length
+            );
+
+            let updated = package_heads(&client).await?;
+            for (name, head) in &updated {
+                if heads.get(name) != Some(head) {
+                    println!("  updated package `{name}`");
+                }
+            }
+            heads = updated;
+        }
+    }
+}
+
+/// Gets the registry index of the head of each package in client storage.
+async fn package_heads(
+    client: &FileSystemClient,
+) -> Result<IndexMap<PackageName, Option<RegistryIndex>>> {
+    Ok(client
+        .registry()
+        .load_packages()
+        .await?
+        .into_iter()
+        .map(|package| (package.name, package.head_registry_index))
+        .collect())
+}
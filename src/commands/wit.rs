@@ -0,0 +1,45 @@
+use super::{CommonOptions, Retry};
+use anyhow::{anyhow, Result};
+use clap::Args;
+use warg_protocol::{registry::PackageName, VersionReq};
+
+/// Print the WIT of a warg registry package.
+///
+/// The package content is downloaded into client storage if it is not
+/// already present.
+#[derive(Args)]
+#[clap(disable_version_flag = true)]
+pub struct WitCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The name of the package.
+    #[clap(value_name = "PACKAGE")]
+    pub package: PackageName,
+    /// The version requirement of the package; defaults to `*`.
+    #[clap(long, short, value_name = "VERSION")]
+    pub version: Option<VersionReq>,
+}
+
+impl WitCommand {
+    /// Executes the command.
+    pub async fn exec(self, retry: Option<Retry>) -> Result<()> {
+        let config = self.common.read_config()?;
+        let mut client = self.common.create_client(&config, retry).await?;
+        client.refresh_namespace(self.package.namespace()).await?;
+
+        let requirement = self.version.unwrap_or(VersionReq::STAR);
+        let download = client
+            .download(&self.package, &requirement)
+            .await?
+            .ok_or_else(|| {
+                anyhow!(
+                    "a version of package `{name}` that satisfies `{requirement}` was not found",
+                    name = self.package
+                )
+            })?;
+
+        print!("{wit}", wit = client.extract_wit(&download.digest)?);
+        Ok(())
+    }
+}
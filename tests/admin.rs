@@ -0,0 +1,692 @@
+use anyhow::{Context, Result};
+use reqwest::StatusCode;
+use secrecy::SecretString;
+use std::{borrow::Cow, time::Duration, time::SystemTime};
+use warg_api::v1::{
+    admin::{
+        AuditEntriesResponse, AuditOperation, AuditOutcome, CompactRecordsResponse,
+        ContentGcResponse, PruneRecordsResponse, RejectRecordRequest, RejectRecordResponse,
+        SubmissionQueueResponse, SuppressPackageRequest, SuppressionResponse,
+    },
+    package::{
+        PackageError, PackageRecordState, PublishRecordRequest, UploadContentRange, UploadEndpoint,
+    },
+    paths,
+};
+use warg_client::{
+    api::{self, UploadProgress},
+    storage::{ContentStorage, PublishEntry, PublishInfo},
+    ClientError, PublishResult,
+};
+use warg_crypto::hash::{AnyHash, Hash, HashAlgorithm, Sha256};
+use warg_protocol::{
+    package::{self, PackageEntry, PACKAGE_RECORD_VERSION},
+    registry::{LogId, PackageName},
+    ProtoEnvelope, VersionReq,
+};
+use warg_server::audit::{verify_audit_files, AuditLogError, FileAuditLog};
+use warg_test_fixture::{TestRegistry, ADMIN_TOKEN};
+
+async fn audit_entries(
+    registry: &TestRegistry,
+    query: &[(&str, String)],
+) -> Result<AuditEntriesResponse> {
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{url}/{path}",
+            url = registry.url(),
+            path = paths::admin_audit()
+        ))
+        .bearer_auth(ADMIN_TOKEN)
+        .query(query)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    Ok(response.json().await?)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_audits_mutating_operations() -> Result<()> {
+    let registry = TestRegistry::start_with_config(|config| {
+        config
+            .with_data_store_audit_log()
+            .with_admin_token(SecretString::new(ADMIN_TOKEN.to_string()))
+    })
+    .await?;
+
+    let bytes = wat::parse_str("(component)")?;
+    let digest = registry
+        .publish_simple("test:audited", "1.0.0", bytes.clone())
+        .await?;
+    registry
+        .publish_simple("test:other", "1.0.0", bytes)
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    // The administration API requires the admin token
+    let url = format!(
+        "{url}/{path}",
+        url = registry.url(),
+        path = paths::admin_audit()
+    );
+    let client = reqwest::Client::new();
+    let response = client.get(&url).send().await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = client.get(&url).bearer_auth("wrong").send().await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let key_id = registry.publisher_key().public_key().fingerprint();
+    let entries = audit_entries(&registry, &[("package", "test:audited".into())])
+        .await?
+        .entries;
+
+    let submitted = entries
+        .iter()
+        .find(|e| e.operation == AuditOperation::RecordSubmitted)
+        .expect("expected a submitted record entry");
+    assert_eq!(submitted.outcome, AuditOutcome::Success);
+    assert_eq!(submitted.key_id.as_ref(), Some(&key_id));
+    assert!(submitted.record_id.is_some());
+    assert!(submitted.source.is_some());
+    assert!(submitted.request_id.is_some());
+
+    let uploaded = entries
+        .iter()
+        .find(|e| e.operation == AuditOperation::ContentUploaded)
+        .expect("expected a content uploaded entry");
+    assert_eq!(uploaded.content.as_ref(), Some(&digest));
+    assert_eq!(uploaded.key_id.as_ref(), Some(&key_id));
+
+    let processed = entries
+        .iter()
+        .find(|e| e.operation == AuditOperation::RecordProcessed)
+        .expect("expected a processed record entry");
+    assert_eq!(processed.outcome, AuditOutcome::Success);
+    assert_eq!(processed.record_id, submitted.record_id);
+
+    assert!(entries
+        .iter()
+        .all(|e| e.package.as_ref().map(|p| p.as_ref()) == Some("test:audited")));
+
+    // Checkpoints are not associated with a package or key
+    let all = audit_entries(&registry, &[]).await?.entries;
+    assert!(all
+        .iter()
+        .any(|e| e.operation == AuditOperation::CheckpointIssued && e.log_length.is_some()));
+    assert!(all.len() > entries.len());
+    assert!(all.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+
+    let by_key = audit_entries(&registry, &[("keyId", key_id.to_string())])
+        .await?
+        .entries;
+    assert!(!by_key.is_empty());
+    assert!(by_key.iter().all(|e| e.key_id.as_ref() == Some(&key_id)));
+
+    let limited = audit_entries(&registry, &[("limit", "2".into())])
+        .await?
+        .entries;
+    assert_eq!(limited.as_slice(), &all[all.len() - 2..]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_chains_file_audit_log() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("audit.log");
+    let audit_log = FileAuditLog::open(&path).await?.with_hash_chain(true);
+    let registry =
+        TestRegistry::start_with_config(|config| config.with_audit_log(audit_log)).await?;
+
+    registry
+        .publish_simple("test:chained", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+
+    // A record for an undefined namespace is rejected
+    let name = PackageName::new("undefined:chained")?;
+    let key = registry.publisher_key();
+    let record = ProtoEnvelope::signed_contents(
+        key,
+        package::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: key.public_key(),
+            }],
+        },
+    )?;
+    assert!(registry
+        .api_client()?
+        .publish_package_record(
+            &LogId::package_log::<Sha256>(&name),
+            PublishRecordRequest {
+                package_name: Cow::Borrowed(&name),
+                record: Cow::Owned(record.into()),
+                content_sources: Default::default(),
+            },
+        )
+        .await
+        .is_err());
+    registry.advance_checkpoint().await?;
+    registry.shutdown().await?;
+
+    let contents = std::fs::read_to_string(&path)?;
+    assert!(contents
+        .lines()
+        .any(|l| l.contains("\"outcome\":\"failure\"") && l.contains("undefined:chained")));
+    let count = verify_audit_files(&[&path]).await?;
+    assert_eq!(count, contents.lines().count());
+    assert!(count > 3);
+
+    // Tampering with an entry is detected at the entry that follows it
+    let tampered = contents.replacen("test:chained", "test:tampered", 1);
+    let line = contents
+        .lines()
+        .position(|l| l.contains("test:chained"))
+        .context("expected an entry for the package")?
+        + 2;
+    std::fs::write(&path, tampered)?;
+    match verify_audit_files(&[&path]).await {
+        Err(AuditLogError::BrokenChain { line: broken, .. }) => assert_eq!(broken, line),
+        res => panic!("unexpected result: {res:?}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_disables_admin_api_without_token() -> Result<()> {
+    let registry =
+        TestRegistry::start_with_config(|config| config.with_data_store_audit_log()).await?;
+
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{url}/{path}",
+            url = registry.url(),
+            path = paths::admin_audit()
+        ))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_reports_submission_queue() -> Result<()> {
+    let registry = TestRegistry::start_with_config(|config| {
+        config
+            .with_submission_queue_depth(8)
+            .with_admin_token(SecretString::new(ADMIN_TOKEN.to_string()))
+    })
+    .await?;
+
+    registry
+        .publish_simple("test:queued", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{url}/{path}",
+            url = registry.url(),
+            path = paths::admin_queue()
+        ))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let queue: SubmissionQueueResponse = response.json().await?;
+    assert_eq!(queue.capacity, 8);
+    assert_eq!(queue.depth, 0);
+    assert_eq!(queue.submitted, 1);
+    assert_eq!(queue.rejected, 0);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_suppresses_packages() -> Result<()> {
+    const REASON: &str = "contains malware";
+
+    let registry = TestRegistry::start_with_config(|config| {
+        config
+            .with_data_store_audit_log()
+            .with_admin_token(SecretString::new(ADMIN_TOKEN.to_string()))
+    })
+    .await?;
+
+    let bytes = wat::parse_str("(component)")?;
+    registry
+        .publish_simple("test:suppressed", "1.0.0", bytes.clone())
+        .await?;
+
+    let name = PackageName::new("test:suppressed")?;
+    let url = format!(
+        "{url}/{path}",
+        url = registry.url(),
+        path = paths::admin_suppression(&name)
+    );
+    let client = reqwest::Client::new();
+    let suppress = || {
+        client
+            .put(&url)
+            .bearer_auth(ADMIN_TOKEN)
+            .json(&SuppressPackageRequest {
+                reason: REASON.to_string(),
+            })
+            .send()
+    };
+
+    let response = suppress().await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let suppression: SuppressionResponse = response.json().await?;
+
+    // A package can only be suppressed once
+    assert_eq!(suppress().await?.status(), StatusCode::CONFLICT);
+
+    // The package can no longer be resolved or published to
+    let api = registry.api_client()?;
+    match api.resolve_package(&name, &VersionReq::STAR, false).await {
+        Err(api::ClientError::Package(PackageError::Suppressed(reason))) => {
+            assert_eq!(reason, REASON)
+        }
+        _ => panic!("expected the package to be suppressed"),
+    }
+
+    let err = registry
+        .publish_simple("test:suppressed", "2.0.0", bytes.clone())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains(REASON), "{err}");
+
+    // The log remains available, but clients refuse to use the package
+    registry.advance_checkpoint().await?;
+    let client = registry.client()?;
+    client.upsert([&name]).await?;
+    match client.download(&name, &VersionReq::STAR).await {
+        Err(ClientError::PackageSuppressed { reason, .. }) => assert_eq!(reason, REASON),
+        _ => panic!("expected the package to be suppressed"),
+    }
+
+    let response = reqwest::Client::new()
+        .delete(&url)
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let resolved = api.resolve_package(&name, &VersionReq::STAR, false).await?;
+    assert_eq!(resolved.version, "1.0.0".parse()?);
+    registry
+        .publish_simple("test:suppressed", "2.0.0", bytes)
+        .await?;
+
+    let entries = audit_entries(&registry, &[("package", name.to_string())])
+        .await?
+        .entries;
+    let suppressed = entries
+        .iter()
+        .find(|e| e.operation == AuditOperation::PackageSuppressed)
+        .expect("expected a package suppressed entry");
+    assert_eq!(suppressed.record_id.as_ref(), Some(&suppression.record_id));
+    assert!(entries.iter().any(|e| {
+        e.operation == AuditOperation::PackageSuppressed && e.outcome == AuditOutcome::Failure
+    }));
+    assert!(entries
+        .iter()
+        .any(|e| e.operation == AuditOperation::PackageUnsuppressed));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_collects_rejected_content() -> Result<()> {
+    let registry = TestRegistry::start_with_config(|config| {
+        config
+            .with_content_gc_grace_period(Duration::ZERO)
+            .with_admin_token(SecretString::new(ADMIN_TOKEN.to_string()))
+    })
+    .await?;
+
+    let published = registry
+        .publish_simple("test:collected", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+
+    // A client unaware of the package initializes it again, which the
+    // registry rejects only after the content was uploaded
+    let name = PackageName::new("test:collected")?;
+    let client = registry.client()?;
+    let bytes = wat::parse_str("(component (core module))")?;
+    let rejected = client
+        .content()
+        .store_content(
+            Box::pin(futures::stream::once(async move { Ok(bytes.into()) })),
+            None,
+        )
+        .await?;
+    let record_id = client
+        .publish_with_info(
+            registry.publisher_key(),
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![
+                    PublishEntry::Init,
+                    PublishEntry::Release {
+                        version: "2.0.0".parse()?,
+                        content: rejected.clone(),
+                        artifacts: Default::default(),
+                        metadata: None,
+                    },
+                ],
+            },
+        )
+        .await?;
+    assert!(matches!(
+        client
+            .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+            .await?,
+        PublishResult::Rejected { .. }
+    ));
+
+    let content_url = |digest: &AnyHash| {
+        format!(
+            "{url}/content/{file_name}",
+            url = registry.url(),
+            file_name = digest.to_string().replace(':', "-")
+        )
+    };
+    assert_eq!(
+        reqwest::get(content_url(&rejected)).await?.status(),
+        StatusCode::OK
+    );
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{url}/{path}",
+            url = registry.url(),
+            path = paths::admin_content_gc()
+        ))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<ContentGcResponse>().await?.removed, 1);
+
+    assert_eq!(
+        reqwest::get(content_url(&rejected)).await?.status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        reqwest::get(content_url(&published)).await?.status(),
+        StatusCode::OK
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_compacts_published_records() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:compacted";
+
+    let registry = TestRegistry::start_with_config(|config| {
+        config
+            .with_content_gc_grace_period(Duration::ZERO)
+            .with_admin_token(SecretString::new(ADMIN_TOKEN.to_string()))
+    })
+    .await?;
+
+    let name = PackageName::new(PACKAGE_NAME)?;
+    for (version, module) in [("1.0.0", ""), ("2.0.0", "(core module)")] {
+        registry
+            .publish_simple(
+                PACKAGE_NAME,
+                version,
+                wat::parse_str(format!("(component {module})"))?,
+            )
+            .await?;
+    }
+    registry.advance_checkpoint().await?;
+
+    let early = registry.client()?;
+    early.upsert([&name]).await?;
+
+    let admin = |path: &'static str, body: serde_json::Value| {
+        reqwest::Client::new()
+            .post(format!("{url}/{path}", url = registry.url()))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(&body)
+            .send()
+    };
+
+    let response = admin(
+        paths::admin_records_compact(),
+        serde_json::json!({ "keepLast": 1 }),
+    )
+    .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json::<CompactRecordsResponse>().await?.compacted,
+        1
+    );
+
+    // A client that already has the compacted records fetches across the
+    // compaction boundary
+    let bytes = wat::parse_str("(component)")?;
+    let content = early
+        .content()
+        .store_content(
+            Box::pin(futures::stream::once(async move { Ok(bytes.into()) })),
+            None,
+        )
+        .await?;
+    let record_id = early
+        .publish_with_info(
+            registry.publisher_key(),
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::Release {
+                    version: "3.0.0".parse()?,
+                    content,
+                    artifacts: Default::default(),
+                    metadata: None,
+                }],
+            },
+        )
+        .await?;
+    assert!(early
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await?
+        .is_published());
+    registry.advance_checkpoint().await?;
+    early.update().await?;
+    assert!(early.download_exact(&name, &"3.0.0".parse()?).await.is_ok());
+
+    // A client without them cannot fetch the compacted history
+    match registry.client()?.upsert([&name]).await {
+        Err(e) => assert!(e.to_string().contains("compacted"), "{e}"),
+        Ok(()) => panic!("expected the compacted records to be unavailable"),
+    }
+
+    // Content of compacted releases is still referenced
+    let response = admin(paths::admin_content_gc(), serde_json::json!({})).await?;
+    assert_eq!(response.json::<ContentGcResponse>().await?.removed, 0);
+    assert!(early.download_exact(&name, &"1.0.0".parse()?).await.is_ok());
+
+    // No records were rejected
+    let response = admin(
+        paths::admin_records_prune(),
+        serde_json::json!({ "before": u32::MAX }),
+    )
+    .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<PruneRecordsResponse>().await?.removed, 0);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_force_rejects_stuck_records() -> Result<()> {
+    const REASON: &str = "content upload never completed";
+
+    let registry = TestRegistry::start_with_config(|config| {
+        config
+            .with_data_store_audit_log()
+            .with_admin_token(SecretString::new(ADMIN_TOKEN.to_string()))
+    })
+    .await?;
+    let name = PackageName::new("test:stuck")?;
+    let log_id = LogId::package_log::<Sha256>(&name);
+    let bytes = wat::parse_str("(component (core module))")?;
+    let digest = AnyHash::from(Hash::<Sha256>::of(bytes.as_slice()));
+    let total = bytes.len() as u64;
+
+    // Submit a record whose content upload stalls halfway
+    let key = registry.publisher_key();
+    let record = ProtoEnvelope::signed_contents(
+        key,
+        package::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: key.public_key(),
+                },
+                PackageEntry::Release {
+                    version: "1.0.0".parse()?,
+                    content: digest.clone(),
+                    artifacts: Default::default(),
+                    metadata: None,
+                },
+            ],
+        },
+    )?;
+
+    let api = registry.api_client()?;
+    let record = api
+        .publish_package_record(
+            &log_id,
+            PublishRecordRequest {
+                package_name: Cow::Borrowed(&name),
+                record: Cow::Owned(record.into()),
+                content_sources: Default::default(),
+            },
+        )
+        .await?;
+    let UploadEndpoint::Http {
+        method,
+        url,
+        headers,
+    } = &record
+        .missing_content()
+        .find(|(d, _)| **d == digest)
+        .context("expected the content to be missing")?
+        .1
+        .upload[0];
+
+    let half = total / 2;
+    let range = |start: u64, end: u64| UploadContentRange::Bytes { start, end, total };
+    let progress = api
+        .upload_content_range(
+            method,
+            url,
+            headers,
+            range(0, total - 1),
+            bytes[..half as usize].to_vec(),
+        )
+        .await?;
+    assert_eq!(progress, UploadProgress::Received(half));
+
+    // Rejecting the record requires the admin token and a reason
+    let reject_url = format!(
+        "{url}/{path}",
+        url = registry.url(),
+        path = paths::admin_record_reject(&log_id, &record.record_id)
+    );
+    let client = reqwest::Client::new();
+    let reject = |token: &str, reason: &str| {
+        client
+            .post(&reject_url)
+            .bearer_auth(token)
+            .json(&RejectRecordRequest {
+                reason: reason.to_string(),
+            })
+            .send()
+    };
+    assert_eq!(
+        reject("wrong", REASON).await?.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        reject(ADMIN_TOKEN, " ").await?.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let response = reject(ADMIN_TOKEN, REASON).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let rejected: RejectRecordResponse = response.json().await?;
+    assert_eq!(rejected.record_id, record.record_id);
+
+    // A record can only be rejected while it is pending
+    assert_eq!(
+        reject(ADMIN_TOKEN, REASON).await?.status(),
+        StatusCode::CONFLICT
+    );
+
+    // The client waiting on the record is told the operator's reason
+    let result = registry
+        .client()?
+        .wait_for_publish(&name, &record.record_id, Duration::from_millis(25))
+        .await?;
+    assert_eq!(
+        result,
+        PublishResult::Rejected {
+            reason: REASON.to_string()
+        }
+    );
+
+    // Completing the stalled upload does not revive the record
+    assert!(api
+        .upload_content_range(
+            method,
+            url,
+            headers,
+            range(half, total - 1),
+            bytes[half as usize..].to_vec(),
+        )
+        .await
+        .is_err());
+    registry.advance_checkpoint().await?;
+    match api
+        .get_package_record(&log_id, &record.record_id)
+        .await?
+        .state
+    {
+        PackageRecordState::Rejected { reason } => assert_eq!(reason, REASON),
+        _ => panic!("expected the record to remain rejected"),
+    }
+
+    let entries = audit_entries(&registry, &[("package", name.to_string())])
+        .await?
+        .entries;
+    let audited = entries
+        .iter()
+        .find(|e| e.operation == AuditOperation::RecordRejected)
+        .expect("expected a record rejected entry");
+    assert_eq!(audited.outcome, AuditOutcome::Success);
+    assert_eq!(audited.reason.as_deref(), Some(REASON));
+    assert_eq!(audited.record_id.as_ref(), Some(&record.record_id));
+    assert_eq!(
+        audited.key_id.as_ref(),
+        Some(&key.public_key().fingerprint())
+    );
+
+    Ok(())
+}
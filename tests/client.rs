@@ -1,19 +1,193 @@
-use self::support::*;
-use anyhow::{bail, Context, Result};
-use std::{fs, time::Duration};
+use anyhow::{Context, Result};
+use futures::{StreamExt, TryStreamExt};
+use indexmap::IndexMap;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+    time::SystemTime,
+};
+use warg_api::v1::{
+    fetch::{FetchLogsRequest, FetchLogsResponse},
+    operator::{OperatorError, PublishOperatorRecordRequest},
+    package::{
+        ListPackagesQuery, PackageError, PackageRecordEntry, PackageRecordState,
+        PackageRecordsQuery, PublishRecordRequest, UploadContentRange, UploadEndpoint,
+    },
+    paths,
+    proof::{ConsistencyRequest, ConsistencyResponse, InclusionRequest, InclusionResponse},
+    API_VERSION,
+};
 use warg_client::{
-    storage::{ContentStorage, PublishEntry, PublishInfo, RegistryStorage},
-    Config, FileSystemClient, StorageLockResult,
+    api::{self, UploadProgress},
+    core,
+    retry::RetryPolicy,
+    storage::{
+        ContentStorage, PublishEntry, PublishInfo, RegistryStateItem, RegistryStorage,
+        RegistryStorageKind,
+    },
+    ClientError, Config, FileSystemClient, PublishResult, RegistryUrl, StaleCheckpointPolicy,
+};
+use warg_crypto::{
+    hash::{AnyHash, Hash, HashAlgorithm, Sha256},
+    signing::{generate_p256_pair, PrivateKey},
+};
+use warg_protocol::{
+    operator::{self, OperatorEntry, OperatorRecord},
+    package::{self, PackageEntry, PACKAGE_RECORD_VERSION},
+    registry::{LogId, PackageName, RecordId, TimestampedCheckpoint},
+    ProtoEnvelope, SerdeEnvelope, VersionReq,
 };
-use warg_protocol::registry::PackageName;
+use warg_test_fixture::{client_with_config, TestRegistry, TEST_NAMESPACE};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_publishes_and_downloads() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:component";
+
+    let registry = TestRegistry::start().await?;
+    let bytes = wat::parse_str("(component)")?;
+
+    let first = registry
+        .publish_simple(PACKAGE_NAME, "0.1.0", bytes.clone())
+        .await?;
+    let second = registry
+        .publish_simple(PACKAGE_NAME, "0.2.0", bytes)
+        .await?;
+    assert_eq!(first, second);
+
+    // Both releases and the initial checkpoint should be in the log
+    let checkpoint = registry.advance_checkpoint().await?;
+    assert_eq!(checkpoint.log_length, 3);
+
+    // A fresh client should be able to download both releases
+    let client = registry.client()?;
+    let name = PackageName::new(PACKAGE_NAME)?;
+    client.upsert([&name]).await?;
+
+    for version in ["0.1.0", "0.2.0"] {
+        let download = client
+            .download(&name, &version.parse()?)
+            .await?
+            .context("failed to resolve package")?;
+        assert_eq!(download.digest, first);
+        assert_eq!(download.version, version.parse()?);
+        assert_eq!(fs::read(&download.path)?, wat::parse_str("(component)")?);
+    }
+
+    assert!(client.download(&name, &"0.3.0".parse()?).await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_publishes_and_downloads_with_sha512() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:sha512";
+
+    let registry =
+        TestRegistry::start_with_config(|config| config.with_hash_algorithm(HashAlgorithm::Sha512))
+            .await?;
+    let bytes = wat::parse_str("(component)")?;
+    let digest = registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", bytes.clone())
+        .await?;
+
+    let checkpoint = registry.advance_checkpoint().await?;
+    assert_eq!(checkpoint.log_root.algorithm(), HashAlgorithm::Sha512);
+    assert_eq!(checkpoint.map_root.algorithm(), HashAlgorithm::Sha512);
+
+    let client = registry.client()?;
+    let name = PackageName::new(PACKAGE_NAME)?;
+    client.upsert([&name]).await?;
+    let download = client
+        .download(&name, &"1.0.0".parse()?)
+        .await?
+        .context("failed to resolve package")?;
+    assert_eq!(download.digest, digest);
+    assert_eq!(fs::read(&download.path)?, bytes);
+
+    // The package log head is identified by a SHA-512 record id
+    let package = client
+        .registry()
+        .load_package(client.get_warg_registry(), &name)
+        .await?
+        .context("expected the package in client storage")?;
+    let head = package.state.head().as_ref().context("expected a head")?;
+    assert_eq!(head.digest.algorithm(), HashAlgorithm::Sha512);
+    let record = registry
+        .api_client()?
+        .get_package_record(
+            &LogId::package_log_for(HashAlgorithm::Sha512, &name),
+            &head.digest,
+        )
+        .await?;
+    assert!(matches!(record.state, PackageRecordState::Published { .. }));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_checks_existing_releases() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:existing";
+
+    let registry = TestRegistry::start().await?;
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let client = registry.client()?;
+
+    let component = wat::parse_str("(component)")?;
+    let digest = HashAlgorithm::Sha256.digest(&component);
+    let other = HashAlgorithm::Sha256.digest(&wat::parse_str("(component (core module))")?);
+
+    // The package does not exist yet
+    assert!(
+        !client
+            .release_exists(&name, &"0.1.0".parse()?, &digest)
+            .await?
+    );
+
+    registry
+        .publish_simple(PACKAGE_NAME, "0.1.0", component.clone())
+        .await?;
+    assert!(
+        client
+            .release_exists(&name, &"0.1.0".parse()?, &digest)
+            .await?
+    );
+    assert!(
+        !client
+            .release_exists(&name, &"0.2.0".parse()?, &digest)
+            .await?
+    );
 
-pub mod support;
+    // The client's stored package log is now stale; the check must refresh it
+    registry
+        .publish_simple(PACKAGE_NAME, "0.2.0", component)
+        .await?;
+    assert!(
+        client
+            .release_exists(&name, &"0.2.0".parse()?, &digest)
+            .await?
+    );
 
-fn create_client(config: &Config) -> Result<FileSystemClient> {
-    match FileSystemClient::try_new_with_config(None, config, None)? {
-        StorageLockResult::Acquired(client) => Ok(client),
-        _ => bail!("failed to acquire storage lock"),
+    // Different content for an existing version is an error
+    match client
+        .release_exists(&name, &"0.1.0".parse()?, &other)
+        .await
+    {
+        Err(ClientError::ReleaseContentMismatch {
+            existing, content, ..
+        }) => {
+            assert_eq!(existing, digest);
+            assert_eq!(content, other);
+        }
+        res => panic!("expected a content mismatch, got {res:?}"),
     }
+
+    Ok(())
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
@@ -21,10 +195,10 @@ async fn client_incrementally_fetches() -> Result<()> {
     const RELEASE_COUNT: usize = 300;
     const PACKAGE_NAME: &str = "test:package";
 
-    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
-
-    let client = create_client(&config)?;
-    let signing_key = support::test_signing_key();
+    let registry = TestRegistry::start().await?;
+    let config = registry.client_config();
+    let client = client_with_config(&config)?;
+    let signing_key = registry.publisher_key();
 
     // Store a single component that will be used for every release
     let bytes =
@@ -42,7 +216,7 @@ async fn client_incrementally_fetches() -> Result<()> {
     let name = PackageName::new(PACKAGE_NAME)?;
     let mut head = client
         .publish_with_info(
-            &signing_key,
+            signing_key,
             PublishInfo {
                 name: name.clone(),
                 head: None,
@@ -54,22 +228,25 @@ async fn client_incrementally_fetches() -> Result<()> {
     for i in 1..=RELEASE_COUNT {
         head = client
             .publish_with_info(
-                &signing_key,
+                signing_key,
                 PublishInfo {
                     name: name.clone(),
                     head: Some(head),
                     entries: vec![PublishEntry::Release {
                         version: format!("0.{i}.0").parse().unwrap(),
                         content: digest.clone(),
+                        artifacts: Default::default(),
+                        metadata: None,
                     }],
                 },
             )
             .await?;
     }
 
-    client
+    assert!(client
         .wait_for_publish(&name, &head, Duration::from_millis(100))
-        .await?;
+        .await?
+        .is_published());
 
     drop(client);
 
@@ -78,7 +255,7 @@ async fn client_incrementally_fetches() -> Result<()> {
         .context("failed to remove registries directory")?;
 
     // Recreate the client with the same config
-    let client = create_client(&config)?;
+    let client = client_with_config(&config)?;
 
     // Regression test: update on empty registry storage
     client.update().await?;
@@ -107,3 +284,4109 @@ async fn client_incrementally_fetches() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_reports_publish_progress() -> Result<()> {
+    let registry = TestRegistry::start_with_config(|config| {
+        config.with_checkpoint_interval(Duration::from_secs(2))
+    })
+    .await?;
+
+    // Publishing returns just after a checkpoint, leaving most of an interval
+    // before the next one
+    registry
+        .publish_simple("test:first", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+
+    let name = PackageName::new("test:progress")?;
+    let client = registry.client()?;
+    let record_id = client
+        .publish_with_info(
+            registry.publisher_key(),
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::Init],
+            },
+        )
+        .await?;
+
+    let mut statuses = Vec::new();
+    let result = client
+        .wait_for_publish_with_progress(&name, &record_id, Duration::from_millis(25), |status| {
+            statuses.push(status.clone())
+        })
+        .await?;
+    assert!(result.is_published());
+
+    let status = statuses
+        .iter()
+        .find(|s| s.head.as_ref() == Some(&record_id))
+        .context("expected the record to be reported as the log head")?;
+    assert_eq!(status.pending_records, 1);
+    assert!(status.checkpoint_timestamp.is_some());
+
+    // The log status is only reported while the record is processing
+    let record = registry
+        .api_client()?
+        .get_package_record(&LogId::package_log::<Sha256>(&name), &record_id)
+        .await?;
+    assert!(record.log_status.is_none());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_resolves_versions_from_storage() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:resolved";
+
+    let registry = TestRegistry::start().await?;
+    let bytes = wat::parse_str("(component)")?;
+    for version in ["1.0.0", "1.1.0", "1.2.0", "1.3.0-alpha.1", "1.3.0-alpha.2"] {
+        registry
+            .publish_simple(PACKAGE_NAME, version, bytes.clone())
+            .await?;
+    }
+
+    let client = registry.client()?;
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let record_id = client
+        .publish_with_info(
+            registry.publisher_key(),
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::Yank {
+                    version: "1.1.0".parse()?,
+                }],
+            },
+        )
+        .await?;
+    assert!(client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(25))
+        .await?
+        .is_published());
+    registry.advance_checkpoint().await?;
+
+    // Nothing resolves until the package is in client storage
+    let client = registry.client()?;
+    assert!(client
+        .resolve_version(&name, &"^1".parse()?, false)
+        .await?
+        .is_none());
+    client.upsert([&name]).await?;
+
+    for (req, include_yanked, expected) in [
+        // Yanked releases are skipped unless included
+        ("^1", false, Some("1.2.0")),
+        ("=1.1.0", false, None),
+        ("=1.1.0", true, Some("1.1.0")),
+        ("=1.0.0", false, Some("1.0.0")),
+        // A matching stable version is preferred over a higher prerelease
+        (">=1.2.0, <=1.3.0-alpha.2", false, Some("1.2.0")),
+        // Prereleases are ordered when no stable version matches
+        (">=1.3.0-alpha.1", false, Some("1.3.0-alpha.2")),
+        ("=1.3.0-alpha.1", false, Some("1.3.0-alpha.1")),
+        ("^2", false, None),
+    ] {
+        let resolved = client
+            .resolve_version(&name, &req.parse()?, include_yanked)
+            .await?
+            .map(|r| r.version.to_string());
+        assert_eq!(resolved.as_deref(), expected, "{req}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_distrusts_releases_by_revoked_keys() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:revoked";
+
+    let registry = TestRegistry::start().await?;
+    let client = registry.client()?;
+    let (owner_public_key, owner_key) = generate_p256_pair();
+    client
+        .publish_operator_record(
+            registry.operator_key(),
+            vec![OperatorEntry::GrantPackagePublish {
+                key: owner_public_key.clone(),
+                pattern: operator::PackagePattern::Any,
+            }],
+        )
+        .await?;
+
+    let bytes = wat::parse_str("(component)")?;
+    for version in ["1.0.0", "2.0.0"] {
+        registry
+            .publish_simple(PACKAGE_NAME, version, bytes.clone())
+            .await?;
+    }
+
+    let name = PackageName::new(PACKAGE_NAME)?;
+    assert!(publish_entry(
+        &client,
+        registry.publisher_key(),
+        &name,
+        PublishEntry::Grant {
+            key: owner_public_key,
+            permissions: vec![package::Permission::Release],
+        },
+    )
+    .await?
+    .is_published());
+
+    // The publisher key is revoked as of the release of 2.0.0
+    registry.advance_checkpoint().await?;
+    client.upsert([&name]).await?;
+    let since = client
+        .resolve_version(&name, &"=2.0.0".parse()?, false)
+        .await?
+        .context("2.0.0 was not released")?
+        .timestamp;
+    let publisher_id = registry.publisher_key().public_key().fingerprint();
+    assert!(publish_entry(
+        &client,
+        &owner_key,
+        &name,
+        PublishEntry::RevokeKey {
+            key_id: publisher_id.clone(),
+            since,
+        },
+    )
+    .await?
+    .is_published());
+
+    // The revoked key may no longer publish
+    match registry
+        .publish_simple(PACKAGE_NAME, "3.0.0", bytes.clone())
+        .await
+    {
+        Err(e) => assert!(
+            format!("{e:#}").contains(&format!("the key with ID {publisher_id} has been revoked")),
+            "{e:#}"
+        ),
+        Ok(_) => panic!("expected the publish by a revoked key to be rejected"),
+    }
+
+    // The release before the revocation is trusted; the one after is not
+    registry.advance_checkpoint().await?;
+    let client = registry.client()?;
+    client.upsert([&name]).await?;
+    let resolved = client
+        .resolve_version(&name, &VersionReq::STAR, false)
+        .await?
+        .map(|r| r.version.to_string());
+    assert_eq!(resolved.as_deref(), Some("1.0.0"));
+    assert_eq!(
+        client
+            .resolve_download(&name, &VersionReq::STAR)
+            .await?
+            .map(|(version, _)| version.to_string())
+            .as_deref(),
+        Some("1.0.0")
+    );
+
+    let client = client.with_include_revoked(true);
+    let resolved = client
+        .resolve_version(&name, &VersionReq::STAR, false)
+        .await?
+        .map(|r| r.version.to_string());
+    assert_eq!(resolved.as_deref(), Some("2.0.0"));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_submits_the_record_previewed_by_a_dry_run() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:dry-run";
+
+    let registry = TestRegistry::start().await?;
+    let digest = registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    let config = registry.client_config();
+    let name = PackageName::new(PACKAGE_NAME)?;
+    {
+        let client = client_with_config(&config)?;
+        client.upsert([&name]).await?;
+        client
+            .registry()
+            .store_publish(Some(&PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::Release {
+                    version: "2.0.0".parse()?,
+                    content: digest,
+                    artifacts: Default::default(),
+                    metadata: None,
+                }],
+            }))
+            .await?;
+    }
+
+    let dir = tempfile::tempdir()?;
+    let key = registry.publisher_key();
+    let script = write_signer_script(dir.path())?;
+    let der = dir.path().join("publisher.der");
+    write_der_key(key, &der)?;
+    let command = format!("sh {} {}", script.display(), der.display());
+    let public_key = key.public_key().to_string();
+    let submit = |args: Vec<&str>| {
+        let config = config.clone();
+        let args = [
+            &["publish", "submit", PACKAGE_NAME, "--format", "json"],
+            args.as_slice(),
+            &[
+                "--sign-with-command",
+                &command,
+                "--signer-public-key",
+                &public_key,
+            ],
+        ]
+        .concat()
+        .into_iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+        async move {
+            let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+            let output = run_warg(&config, &args).await?;
+            anyhow::Ok(serde_json::from_slice::<serde_json::Value>(&output)?)
+        }
+    };
+
+    // The dry run builds the record from client storage without submitting it
+    let preview = submit(vec!["--dry-run"]).await?;
+    assert_eq!(preview["state"], "dryRun");
+    let client = client_with_config(&config)?;
+    let head = client
+        .registry()
+        .load_package(client.get_warg_registry(), &name)
+        .await?
+        .context("package should be stored")?
+        .state
+        .head()
+        .as_ref()
+        .context("package should have a head")?
+        .digest
+        .to_string();
+    drop(client);
+    assert_eq!(preview["record"]["prev"], head);
+    let timestamp = preview["record"]["time"]
+        .as_str()
+        .context("expected a record timestamp")?;
+
+    // Submitting with the previewed timestamp publishes the previewed record
+    let published = submit(vec!["--timestamp", timestamp]).await?;
+    assert_eq!(published["state"], "published");
+    assert_eq!(published["recordId"], preview["recordId"]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_prevalidates_records_before_signing() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:prevalidated";
+
+    let registry = TestRegistry::start().await?;
+    let digest = registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let client = registry.client()?;
+    client.upsert([&name]).await?;
+    let info = PublishInfo {
+        name: name.clone(),
+        head: None,
+        entries: vec![PublishEntry::Release {
+            version: "1.0.0".parse()?,
+            content: digest,
+            artifacts: Default::default(),
+            metadata: None,
+        }],
+    };
+
+    // Releasing a released version fails before the record is signed
+    match client
+        .publish_with_info(registry.publisher_key(), info.clone())
+        .await
+    {
+        Err(e @ ClientError::PrevalidationFailed { .. }) => {
+            assert!(
+                e.to_string().ends_with(
+                    "rejected by a local pre-check due to: the package record was invalid: \
+                     an entry attempted to release version 1.0.0 which is already released"
+                ),
+                "{e}"
+            );
+        }
+        res => panic!("expected the record to fail validation: {res:?}"),
+    }
+
+    // Skipping the check leaves the record to the registry to reject
+    let client = client.with_skip_prevalidation(true);
+    let record_id = client
+        .publish_with_info(registry.publisher_key(), info)
+        .await?;
+    let result = client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await?;
+    assert!(result.reason().is_some(), "{result:?}");
+
+    Ok(())
+}
+
+/// Submits a release record for a package whose timestamp is two hours ahead
+/// of the current time.
+async fn publish_skewed_release(registry: &TestRegistry) -> Result<Result<RecordId, ClientError>> {
+    let digest = registry
+        .publish_simple("test:skewed", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    let client = registry.client()?;
+    let name = PackageName::new("test:skewed")?;
+    let signing_key = registry.publisher_key();
+    let info = PublishInfo {
+        name: name.clone(),
+        head: None,
+        entries: vec![PublishEntry::Release {
+            version: "2.0.0".parse()?,
+            content: digest,
+            artifacts: Default::default(),
+            metadata: None,
+        }],
+    };
+
+    client.upsert([&name]).await?;
+    let record = client
+        .build_record(
+            &signing_key.public_key(),
+            &info,
+            SystemTime::now() + Duration::from_secs(2 * 60 * 60),
+        )
+        .await?;
+    Ok(client.publish_record(signing_key, &name, record).await)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_surfaces_record_timestamp_skew() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+
+    match publish_skewed_release(&registry).await? {
+        Err(ClientError::Api(api::ClientError::Package(PackageError::Rejection(reason)))) => {
+            assert!(
+                reason.contains("ahead of the registry clock"),
+                "unexpected reason: {reason}"
+            );
+        }
+        res => panic!("unexpected result: {res:?}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_accepts_skewed_timestamps_when_disabled() -> Result<()> {
+    let registry =
+        TestRegistry::start_with_config(|config| config.with_max_timestamp_skew(None)).await?;
+
+    let record_id = publish_skewed_release(&registry).await??;
+    let client = registry.client()?;
+    assert!(matches!(
+        client
+            .wait_for_publish(
+                &PackageName::new("test:skewed")?,
+                &record_id,
+                Duration::from_millis(100)
+            )
+            .await?,
+        PublishResult::Published { .. }
+    ));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_detects_stale_checkpoints() -> Result<()> {
+    // The registry declares checkpoints more often than it issues them
+    let registry = TestRegistry::start_with_config(|config| {
+        config
+            .with_checkpoint_interval(Duration::from_secs(3))
+            .with_max_checkpoint_interval(Duration::from_secs(1))
+    })
+    .await?;
+
+    // Publishing returns just after a checkpoint; wait until it is stale but
+    // before the next one is issued
+    registry
+        .publish_simple("test:stale", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    tokio::time::sleep(Duration::from_millis(1600)).await;
+
+    let name = PackageName::new("test:stale")?;
+    let mut config = registry.client_config();
+    config.stale_checkpoint = StaleCheckpointPolicy::Fail;
+    config.checkpoint_grace_period = Some(0);
+    let err = client_with_config(&config)?
+        .upsert([&name])
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, ClientError::StaleCheckpoint { age, max_interval: 1 } if age >= 1),
+        "{err:?}"
+    );
+    assert!(
+        err.to_string()
+            .contains("declares a checkpoint at least every 1 second(s)"),
+        "{err}"
+    );
+
+    // The grace period allows for delayed checkpoints
+    config.checkpoint_grace_period = Some(60);
+    client_with_config(&config)?.upsert([&name]).await?;
+
+    // By default, a stale checkpoint is only warned about
+    let client = registry.client()?;
+    client.upsert([&name]).await?;
+    assert!(client.download(&name, &"1.0.0".parse()?).await?.is_some());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_refuses_expired_checkpoints() -> Result<()> {
+    let registry = TestRegistry::start_with_config(|config| {
+        config.with_checkpoint_validity(Duration::from_secs(60))
+    })
+    .await?;
+    registry
+        .publish_simple("test:expiring", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+
+    // Checkpoints are signed with their expiry
+    let ts_checkpoint = registry.api_client()?.latest_checkpoint().await?;
+    let ts_checkpoint = ts_checkpoint.as_ref();
+    assert_eq!(ts_checkpoint.expires, Some(ts_checkpoint.timestamp + 60));
+
+    let name = PackageName::new("test:expiring")?;
+    let requirement = VersionReq::STAR;
+    let client = registry.client()?;
+    client.upsert([&name]).await?;
+    assert!(client
+        .resolve_version(&name, &requirement, false)
+        .await?
+        .is_some());
+
+    // Once the clock passes the expiry, neither the stored nor the latest
+    // checkpoint is trusted
+    let client = client.with_clock(|| SystemTime::now() + Duration::from_secs(120));
+    let err = client
+        .resolve_version(&name, &requirement, false)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, ClientError::StaleRegistry { expires } if expires == ts_checkpoint.expires.unwrap()),
+        "{err:?}"
+    );
+    let err = client.download(&name, &requirement).await.unwrap_err();
+    assert!(matches!(err, ClientError::StaleRegistry { .. }), "{err:?}");
+
+    // Stale state is used only when explicitly allowed
+    let client = client.with_allow_stale(true);
+    assert!(client
+        .resolve_version(&name, &requirement, false)
+        .await?
+        .is_some());
+    assert!(client.download(&name, &requirement).await?.is_some());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_rejects_checkpoint_regressions() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    registry
+        .publish_simple("test:rollback", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+
+    let client = registry.client()?;
+    let name = PackageName::new("test:rollback")?;
+    client.upsert([&name]).await?;
+    let current = client
+        .registry()
+        .load_checkpoint(&None)
+        .await?
+        .context("expected a stored checkpoint")?
+        .into_contents();
+
+    // Simulate the registry rolling back by having the client hold a newer
+    // checkpoint than the registry's latest
+    for (log_length, timestamp) in [
+        (current.checkpoint.log_length + 1, current.timestamp),
+        (current.checkpoint.log_length, current.timestamp + 3600),
+    ] {
+        let mut newer = current.clone();
+        newer.checkpoint.log_length = log_length;
+        newer.timestamp = timestamp;
+        let newer = SerdeEnvelope::signed_contents(registry.operator_key(), newer)?;
+        client.registry().store_checkpoint(&None, &newer).await?;
+
+        let err = client.update().await.unwrap_err();
+        assert!(
+            matches!(
+                err,
+                ClientError::CheckpointRegression { from_log_length, from_timestamp, .. }
+                    if from_log_length == log_length && from_timestamp == timestamp
+            ),
+            "{err:?}"
+        );
+
+        // The client keeps its head
+        let head = client.registry().load_checkpoint(&None).await?;
+        assert_eq!(head.as_ref().map(|c| c.as_ref()), Some(newer.as_ref()));
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_keeps_pending_publishes_per_package() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    let config = registry.client_config();
+    let client = client_with_config(&config)?;
+    let init = |name: &PackageName| PublishInfo {
+        name: name.clone(),
+        head: None,
+        entries: vec![PublishEntry::Init],
+    };
+
+    // Pending publishes stored by older clients are a single publish
+    let first = PackageName::new("test:first")?;
+    fs::write(
+        config
+            .registries_dir
+            .as_ref()
+            .unwrap()
+            .join(client.url().safe_label())
+            .join("pending-publish.json"),
+        serde_json::to_vec(&init(&first))?,
+    )?;
+
+    let second = PackageName::new("test:second")?;
+    client
+        .registry()
+        .store_publish(Some(&init(&second)))
+        .await?;
+    client.registry().store_publish(Some(&init(&first))).await?;
+
+    let publishes = client.registry().load_publishes().await?;
+    let names: Vec<_> = publishes.iter().map(|p| p.name.clone()).collect();
+    assert_eq!(names, [first.clone(), second.clone()]);
+    assert_eq!(
+        client.registry().load_publish().await?.map(|p| p.name),
+        Some(second.clone())
+    );
+
+    // Submit both publishes before waiting for either
+    let mut records = Vec::new();
+    for info in publishes {
+        let record_id = client
+            .publish_with_info(registry.publisher_key(), info.clone())
+            .await?;
+        records.push((info.name, record_id));
+    }
+
+    while !records.is_empty() {
+        let mut remaining = Vec::new();
+        for (name, record_id) in records {
+            match client.check_publish(&name, &record_id).await? {
+                Some(result) => assert!(result.is_published(), "{result:?}"),
+                None => remaining.push((name, record_id)),
+            }
+        }
+
+        records = remaining;
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+
+    // Removing the latest pending publish keeps the others
+    client.registry().store_publish(None).await?;
+    let publishes = client.registry().load_publishes().await?;
+    assert_eq!(publishes.len(), 1);
+    assert_eq!(publishes[0].name, first);
+
+    client.registry().store_publishes(&[]).await?;
+    assert!(client.registry().load_publish().await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_fetches_package_at_checkpoint() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    let name = PackageName::new("test:pinned")?;
+    let later = PackageName::new("test:later")?;
+
+    let first = registry
+        .publish_simple("test:pinned", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    let pinned: AnyHash = Hash::<Sha256>::of(&registry.advance_checkpoint().await?).into();
+
+    registry
+        .publish_simple(
+            "test:pinned",
+            "2.0.0",
+            wat::parse_str("(component (core module))")?,
+        )
+        .await?;
+    registry
+        .publish_simple("test:later", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    // Only the releases as of the checkpoint are known
+    let client = registry.client()?;
+    let info = client.package_at_checkpoint(&name, &pinned).await?;
+    let versions = info
+        .state
+        .releases()
+        .map(|r| r.version.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(versions, ["1.0.0"]);
+
+    let download = client
+        .download_at_checkpoint(&name, &VersionReq::STAR, &pinned)
+        .await?
+        .context("failed to resolve package")?;
+    assert_eq!(download.version, "1.0.0".parse()?);
+    assert_eq!(download.digest, first);
+
+    // Client storage is not modified by historical queries
+    assert!(client
+        .registry()
+        .load_package(client.get_warg_registry(), &name)
+        .await?
+        .is_none());
+
+    // The current state still resolves the latest release
+    client.upsert([&name]).await?;
+    let download = client
+        .download(&name, &VersionReq::STAR)
+        .await?
+        .context("failed to resolve package")?;
+    assert_eq!(download.version, "2.0.0".parse()?);
+
+    match client.package_at_checkpoint(&later, &pinned).await {
+        Err(ClientError::PackageNotInCheckpoint {
+            name,
+            checkpoint_id,
+        }) => {
+            assert_eq!(name, later);
+            assert_eq!(checkpoint_id, pinned);
+        }
+        res => panic!("expected the package to be missing at the checkpoint: {res:?}"),
+    }
+
+    let unknown: AnyHash = Hash::<Sha256>::of("unknown").into();
+    match client.package_at_checkpoint(&name, &unknown).await {
+        Err(ClientError::CheckpointNotFound { checkpoint_id }) => {
+            assert_eq!(checkpoint_id, unknown)
+        }
+        res => panic!("expected the checkpoint to be unknown: {res:?}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_grants_and_revokes_operator_publish_permission() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    let client = registry.client()?;
+    let (public_key, signing_key) = generate_p256_pair();
+
+    let init = |name: &str| {
+        Ok::<_, anyhow::Error>(PublishInfo {
+            name: PackageName::new(name)?,
+            head: None,
+            entries: vec![PublishEntry::Init],
+        })
+    };
+
+    // The key is not authorized to publish to the namespace by policy
+    assert!(client
+        .publish_with_info(&signing_key, init("test:before")?)
+        .await
+        .is_err());
+
+    client
+        .publish_operator_record(
+            registry.operator_key(),
+            vec![OperatorEntry::GrantFlat {
+                key: public_key.clone(),
+                permissions: vec![operator::Permission::Publish],
+            }],
+        )
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    let info = init("test:granted")?;
+    let name = info.name.clone();
+    let record_id = client.publish_with_info(&signing_key, info).await?;
+    assert!(client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await?
+        .is_published());
+
+    // A record that does not follow the head of the operator log conflicts
+    let stale = ProtoEnvelope::signed_contents(
+        registry.operator_key(),
+        OperatorRecord {
+            prev: None,
+            version: operator::OPERATOR_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![OperatorEntry::RevokeFlat {
+                key_id: public_key.fingerprint(),
+                permissions: vec![operator::Permission::Publish],
+            }],
+        },
+    )?;
+    match registry
+        .api_client()?
+        .publish_operator_record(PublishOperatorRecordRequest {
+            record: Cow::Owned(stale.into()),
+        })
+        .await
+    {
+        Err(api::ClientError::Operator(OperatorError::HeadMismatch)) => {}
+        res => panic!("expected a head mismatch: {res:?}"),
+    }
+
+    client
+        .publish_operator_record(
+            registry.operator_key(),
+            vec![OperatorEntry::RevokeFlat {
+                key_id: public_key.fingerprint(),
+                permissions: vec![operator::Permission::Publish],
+            }],
+        )
+        .await?;
+
+    match client
+        .publish_with_info(&signing_key, init("test:revoked")?)
+        .await
+    {
+        Err(ClientError::Api(api::ClientError::Package(PackageError::Unauthorized(message)))) => {
+            assert!(message.contains("does not have permission to publish"))
+        }
+        res => panic!("expected the publish to be unauthorized: {res:?}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_enforces_namespace_reservations() -> Result<()> {
+    let registry = TestRegistry::start_with_namespace("wasi").await?;
+    let client = registry.client()?;
+    let (owner_public_key, owner_key) = generate_p256_pair();
+
+    client
+        .publish_operator_record(
+            registry.operator_key(),
+            vec![
+                OperatorEntry::GrantPackagePublish {
+                    key: owner_public_key.clone(),
+                    pattern: "wasi:*".parse().map_err(anyhow::Error::msg)?,
+                },
+                OperatorEntry::ReserveNamespace {
+                    namespace: "wasi".to_string(),
+                    key_ids: vec![owner_public_key.fingerprint()],
+                },
+            ],
+        )
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    // The publisher key may publish to any package but the namespace is not
+    // reserved for it
+    match publish_init(&client, registry.publisher_key(), "wasi:clock").await {
+        Err(ClientError::Api(api::ClientError::Package(PackageError::Unauthorized(message)))) => {
+            assert!(
+                message.contains(&format!(
+                    "namespace `wasi` is reserved; key `{key_id}` may not initialize package `wasi:clock`",
+                    key_id = registry.publisher_key().public_key().fingerprint()
+                )),
+                "{message}"
+            )
+        }
+        res => panic!("expected the publish to be unauthorized: {res:?}"),
+    }
+
+    assert!(publish_init(&client, &owner_key, "wasi:clock")
+        .await?
+        .is_published());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_keeps_fetching_across_operator_key_rotation() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:rotated";
+
+    let (next_public_key, next_key) = generate_p256_pair();
+    let registry =
+        TestRegistry::start_with_config(|config| config.with_next_operator_key(next_key)).await?;
+    let old_key_id = registry.operator_key().public_key().fingerprint();
+    let next_key_id = next_public_key.fingerprint();
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let client = registry.client()?;
+
+    // Fetches and downloads the given version with the existing client
+    let fetch = |version: &'static str| {
+        let (client, name) = (&client, name.clone());
+        async move {
+            client.upsert([&name]).await?;
+            client
+                .download(&name, &version.parse()?)
+                .await?
+                .context("failed to resolve package")
+        }
+    };
+
+    registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    registry.advance_checkpoint().await?;
+    fetch("=1.0.0").await?;
+
+    // The registry switches keys once the rotation is published
+    client
+        .publish_operator_record(
+            registry.operator_key(),
+            vec![OperatorEntry::RotateKey {
+                key: next_public_key,
+                overlap: Duration::from_secs(1),
+            }],
+        )
+        .await?;
+    let checkpoint = client.wait_for_operator_key(&next_key_id).await?;
+    assert_eq!(checkpoint.key_id(), &next_key_id);
+
+    registry
+        .publish_simple(PACKAGE_NAME, "1.1.0", wat::parse_str("(component)")?)
+        .await?;
+    fetch("=1.1.0").await?;
+
+    // The old key is revoked once the overlap ends
+    client.fetch_operator().await?;
+    let operator = client
+        .registry()
+        .load_operator(client.get_warg_registry())
+        .await?
+        .context("expected operator log in client storage")?;
+    let end = operator
+        .state
+        .key_rotation_overlap_end(&old_key_id)
+        .context("expected the old key to be rotated")?;
+    if let Ok(remaining) = end.duration_since(SystemTime::now()) {
+        tokio::time::sleep(remaining + Duration::from_secs(1)).await;
+    }
+    client
+        .publish_operator_record(
+            registry.operator_key(),
+            vec![OperatorEntry::RevokeFlat {
+                key_id: old_key_id.clone(),
+                permissions: operator.state.key_permissions(&old_key_id).collect(),
+            }],
+        )
+        .await?;
+
+    registry
+        .publish_simple(PACKAGE_NAME, "1.2.0", wat::parse_str("(component)")?)
+        .await?;
+    fetch("=1.2.0").await?;
+
+    let checkpoint = registry.api_client()?.latest_checkpoint().await?;
+    assert_eq!(checkpoint.key_id(), &next_key_id);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_enforces_package_scoped_publish_grants() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    let client = registry.client()?;
+    let (scoped_public_key, scoped_key) = generate_p256_pair();
+    let (wildcard_public_key, wildcard_key) = generate_p256_pair();
+
+    client
+        .publish_operator_record(
+            registry.operator_key(),
+            vec![
+                OperatorEntry::DefineNamespace {
+                    namespace: "a".to_string(),
+                },
+                OperatorEntry::DefineNamespace {
+                    namespace: "b".to_string(),
+                },
+                OperatorEntry::GrantPackagePublish {
+                    key: scoped_public_key.clone(),
+                    pattern: "a:pkg".parse().map_err(anyhow::Error::msg)?,
+                },
+                OperatorEntry::GrantPackagePublish {
+                    key: wildcard_public_key,
+                    pattern: operator::PackagePattern::Any,
+                },
+            ],
+        )
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    // The scoped key may only publish to the granted package
+    assert!(publish_init(&client, &scoped_key, "a:pkg")
+        .await?
+        .is_published());
+    match publish_init(&client, &scoped_key, "b:pkg").await {
+        Err(ClientError::Api(api::ClientError::Package(PackageError::Unauthorized(message)))) => {
+            assert!(
+                message.contains(&format!(
+                    "key `{key_id}` does not have permission to publish package `b:pkg`",
+                    key_id = scoped_public_key.fingerprint()
+                )),
+                "{message}"
+            )
+        }
+        res => panic!("expected the publish to be unauthorized: {res:?}"),
+    }
+
+    // The wildcard key may publish to both
+    assert!(publish_init(&client, &wildcard_key, "a:other")
+        .await?
+        .is_published());
+    assert!(publish_init(&client, &wildcard_key, "b:pkg")
+        .await?
+        .is_published());
+
+    Ok(())
+}
+
+async fn publish_init(
+    client: &FileSystemClient,
+    signing_key: &PrivateKey,
+    name: &str,
+) -> Result<PublishResult, ClientError> {
+    let name = PackageName::new(name).unwrap();
+    let info = PublishInfo {
+        name: name.clone(),
+        head: None,
+        entries: vec![PublishEntry::Init],
+    };
+    let record_id = client.publish_with_info(signing_key, info).await?;
+    client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await
+}
+
+async fn publish_entry(
+    client: &FileSystemClient,
+    signing_key: &PrivateKey,
+    name: &PackageName,
+    entry: PublishEntry,
+) -> Result<PublishResult, ClientError> {
+    let info = PublishInfo {
+        name: name.clone(),
+        head: None,
+        entries: vec![entry],
+    };
+    let record_id = client.publish_with_info(signing_key, info).await?;
+    client
+        .wait_for_publish(name, &record_id, Duration::from_millis(100))
+        .await
+}
+
+/// Runs the `warg` CLI with the given client configuration and arguments.
+/// Writes the client configuration next to its storage directories,
+/// returning the path to the configuration file.
+fn write_config(config: &Config) -> Result<std::path::PathBuf> {
+    let config_path = config
+        .registries_dir
+        .as_ref()
+        .and_then(|dir| dir.parent())
+        .context("expected a client directory")?
+        .join("config.json");
+    config.write_to_file(&config_path)?;
+    Ok(config_path)
+}
+
+#[test]
+fn cli_prints_build_info_and_completions() -> Result<()> {
+    let warg = |args: &[&str]| -> Result<String> {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_warg"))
+            .args(args)
+            .output()?;
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    let version = warg(&["version", "--verbose"])?;
+    assert!(version.starts_with(&format!("warg {}", env!("CARGO_PKG_VERSION"))));
+    assert!(version.contains(&format!("registry API: {API_VERSION}\n")));
+    if let Some(commit) = option_env!("CARGO_GIT_HASH") {
+        assert!(
+            version.contains(&format!("commit: {commit}\n")),
+            "{version}"
+        );
+    }
+
+    // Completions are generated for the `warg` binary
+    assert!(warg(&["completions", "bash"])?.contains("complete -F _warg"));
+    for shell in ["zsh", "fish", "powershell"] {
+        assert!(warg(&["completions", shell])?.contains("warg"));
+    }
+
+    Ok(())
+}
+
+async fn warg(config: &Config, args: &[&str]) -> Result<std::process::Output> {
+    let config_path = write_config(config)?;
+
+    let args = args.iter().map(ToString::to_string).collect::<Vec<_>>();
+    let output = tokio::task::spawn_blocking(move || {
+        std::process::Command::new(env!("CARGO_BIN_EXE_warg"))
+            .args(args)
+            .arg("--config")
+            .arg(&config_path)
+            .output()
+    })
+    .await??;
+
+    Ok(output)
+}
+
+/// Runs the `warg` CLI with the given client configuration and arguments,
+/// returning its standard output.
+async fn run_warg(config: &Config, args: &[&str]) -> Result<Vec<u8>> {
+    let output = warg(config, args).await?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    Ok(output.stdout)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_imports_file_system_storage_into_sqlite() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:imported";
+
+    let registry = TestRegistry::start().await?;
+    registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    let mut config = registry.client_config();
+    let name = PackageName::new(PACKAGE_NAME)?;
+    {
+        let client = client_with_config(&config)?;
+        client.upsert([&name]).await?;
+    }
+
+    // The package stored as files is imported on first open
+    config.storage = RegistryStorageKind::Sqlite;
+    config.offline = true;
+    let client = client_with_config(&config)?;
+    let info = client
+        .registry()
+        .load_package(client.get_warg_registry(), &name)
+        .await?
+        .context("expected the package to be imported")?;
+    assert_eq!(info.state.releases().count(), 1);
+    assert!(client
+        .registry()
+        .load_checkpoint(client.get_warg_registry())
+        .await?
+        .is_some());
+
+    let state = client.registry_state(false).await?;
+    assert!(
+        state
+            .iter()
+            .any(|e| e.item == RegistryStateItem::Package { name: name.clone() } && e.size > 0),
+        "{state:?}"
+    );
+    assert!(
+        state
+            .iter()
+            .any(|e| e.item == RegistryStateItem::Checkpoint),
+        "{state:?}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_shares_sqlite_storage_between_processes() -> Result<()> {
+    const PACKAGES: usize = 4;
+
+    let registry = TestRegistry::start().await?;
+    let names = (0..PACKAGES)
+        .map(|i| PackageName::new(format!("test:shared{i}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    for name in &names {
+        registry
+            .publish_simple(name.as_ref(), "1.0.0", wat::parse_str("(component)")?)
+            .await?;
+    }
+    registry.advance_checkpoint().await?;
+
+    let mut config = registry.client_config();
+    config.storage = RegistryStorageKind::Sqlite;
+    let config_path = write_config(&config)?;
+
+    let run_concurrently = |args: Vec<Vec<String>>| {
+        let config_path = config_path.clone();
+        async move {
+            let mut children = Vec::new();
+            for args in args {
+                children.push(
+                    tokio::process::Command::new(env!("CARGO_BIN_EXE_warg"))
+                        .args(args)
+                        .arg("--config")
+                        .arg(&config_path)
+                        .output(),
+                );
+            }
+
+            for output in futures::future::join_all(children).await {
+                let output = output?;
+                assert!(
+                    output.status.success(),
+                    "{}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+
+            anyhow::Ok(())
+        }
+    };
+
+    // Concurrent processes store different packages in the same database
+    run_concurrently(
+        names
+            .iter()
+            .map(|name| vec!["download".to_string(), name.to_string()])
+            .collect(),
+    )
+    .await?;
+
+    for name in &names {
+        registry
+            .publish_simple(name.as_ref(), "2.0.0", wat::parse_str("(component)")?)
+            .await?;
+    }
+    registry.advance_checkpoint().await?;
+
+    // Concurrent processes update the same packages in the same database
+    run_concurrently((0..PACKAGES).map(|_| vec!["update".to_string()]).collect()).await?;
+
+    let client = client_with_config(&config)?;
+    let packages = client.registry().load_packages().await?;
+    assert_eq!(packages.len(), PACKAGES);
+    for package in packages {
+        assert_eq!(
+            package.state.releases().count(),
+            2,
+            "unexpected releases of `{name}`",
+            name = package.name
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_resolves_component_imports() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    for version in ["1.0.0", "1.2.0"] {
+        registry
+            .publish_simple("test:bar", version, wat::parse_str("(component)")?)
+            .await?;
+    }
+    registry.advance_checkpoint().await?;
+
+    let config = registry.client_config();
+    let dir = config
+        .content_dir
+        .as_ref()
+        .and_then(|dir| dir.parent())
+        .context("expected a client directory")?;
+    fs::create_dir_all(dir)?;
+    let path = dir.join("component.wasm");
+    fs::write(
+        &path,
+        wat::parse_str(
+            r#"(component
+                (import "test:bar/iface@1.0.0" (instance))
+                (import "test:bar/other@2.0.0" (instance))
+                (import "test:missing/iface@1.0.0" (instance))
+                (import "local" (func))
+            )"#,
+        )?,
+    )?;
+
+    let output = run_warg(&config, &["dependencies", path.to_str().unwrap(), "--json"]).await?;
+    let report: serde_json::Value = serde_json::from_slice(&output)?;
+
+    assert_eq!(
+        report["resolved"],
+        serde_json::json!([{
+            "import": "test:bar/iface@1.0.0",
+            "package": "test:bar",
+            "requirement": "^1.0.0",
+            "version": "1.2.0",
+            "recordId": report["resolved"][0]["recordId"],
+        }])
+    );
+    assert_eq!(
+        report["unresolved"],
+        serde_json::json!([
+            {
+                "import": "test:bar/other@2.0.0",
+                "package": "test:bar",
+                "requirement": "^2.0.0",
+                "reason": "noMatchingVersion",
+            },
+            {
+                "import": "test:missing/iface@1.0.0",
+                "package": "test:missing",
+                "requirement": "^1.0.0",
+                "reason": "unknownPackage",
+            },
+        ])
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_prints_package_wit() -> Result<()> {
+    const WIT: &str = r#"package test:greeter;
+
+interface greet {
+  greet: func(name: string) -> string;
+}
+
+world greeter {
+  export greet;
+}
+"#;
+
+    let mut resolve = wit_parser::Resolve::new();
+    let pkg = resolve.push(wit_parser::UnresolvedPackage::parse(
+        std::path::Path::new("foo.wit"),
+        WIT,
+    )?)?;
+
+    let registry = TestRegistry::start().await?;
+    registry
+        .publish_simple(
+            "test:greeter",
+            "1.0.0",
+            wit_component::encode(Some(true), &resolve, pkg)?,
+        )
+        .await?;
+    registry
+        .publish_simple("test:module", "1.0.0", wat::parse_str("(module)")?)
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    let config = registry.client_config();
+    let output = run_warg(&config, &["wit", "test:greeter", "--version", "^1"]).await?;
+    let wit = String::from_utf8(output)?;
+    assert!(wit.contains("package test:greeter"), "{wit}");
+    assert!(wit.contains("world greeter"), "{wit}");
+    assert!(
+        wit.contains("greet: func(name: string) -> string;"),
+        "{wit}"
+    );
+
+    let res = warg(&config, &["wit", "test:module"]).await?;
+    assert!(!res.status.success());
+    let stderr = String::from_utf8_lossy(&res.stderr);
+    assert!(stderr.contains("is not a component"), "{stderr}");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_watches_for_new_checkpoints() -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    const PACKAGE_NAME: &str = "test:watched";
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    let registry = TestRegistry::start().await?;
+    registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    let config_path = write_config(&registry.client_config())?;
+    let mut child = tokio::process::Command::new(env!("CARGO_BIN_EXE_warg"))
+        .args(["watch", PACKAGE_NAME, "--config"])
+        .arg(&config_path)
+        .stdout(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut lines = BufReader::new(child.stdout.take().context("expected stdout")?).lines();
+
+    let line = tokio::time::timeout(TIMEOUT, lines.next_line())
+        .await??
+        .context("expected output")?;
+    assert!(line.starts_with("watching for checkpoints"), "{line}");
+
+    registry
+        .publish_simple(PACKAGE_NAME, "2.0.0", wat::parse_str("(component)")?)
+        .await?;
+    let checkpoint = registry.advance_checkpoint().await?;
+
+    // The watcher prints the new checkpoint and the package it updated
+    let line = tokio::time::timeout(TIMEOUT, lines.next_line())
+        .await??
+        .context("expected output")?;
+    assert!(
+        line.ends_with(&format!("(log length {len})", len = checkpoint.log_length)),
+        "{line}"
+    );
+    let line = tokio::time::timeout(TIMEOUT, lines.next_line())
+        .await??
+        .context("expected output")?;
+    assert_eq!(line, format!("  updated package `{PACKAGE_NAME}`"));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_fetches_all_tracked_packages() -> Result<()> {
+    const PACKAGES: usize = 10;
+
+    let registry = TestRegistry::start().await?;
+    let names = (0..PACKAGES)
+        .map(|i| format!("test:tracked{i}"))
+        .collect::<Vec<_>>();
+    for name in &names {
+        registry
+            .publish_simple(name, "1.0.0", wat::parse_str("(component)")?)
+            .await?;
+    }
+    registry.advance_checkpoint().await?;
+
+    // Start tracking each package, one at a time
+    let config = registry.client_config();
+    for name in &names {
+        let output = String::from_utf8(run_warg(&config, &["fetch", name]).await?)?;
+        assert!(
+            output.starts_with(&format!("fetched 1 new record(s) for package `{name}`")),
+            "{output}"
+        );
+    }
+
+    for name in &names {
+        registry
+            .publish_simple(name, "2.0.0", wat::parse_str("(component)")?)
+            .await?;
+    }
+    registry.advance_checkpoint().await?;
+
+    // A single command brings every tracked package up to date
+    let output = String::from_utf8(run_warg(&config, &["fetch", "--all"]).await?)?;
+    let mut lines = output.lines();
+    assert_eq!(lines.next(), Some("fetched 0 new operator record(s)"));
+    let fetched = lines
+        .filter(|line| line.starts_with("fetched 1 new record(s) for package `test:tracked"))
+        .count();
+    assert_eq!(fetched, PACKAGES, "{output}");
+
+    // Failures are summarized with a non-zero exit code
+    let output = warg(&config, &["fetch", "test:missing"]).await?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(
+        stderr.contains("failed to fetch 1 package(s): `test:missing`"),
+        "{stderr}"
+    );
+
+    let client = client_with_config(&config)?;
+    for name in &names {
+        let info = client
+            .registry()
+            .load_package(
+                client.get_warg_registry(),
+                &PackageName::new(name.as_str())?,
+            )
+            .await?
+            .context("expected the package to be tracked")?;
+        assert_eq!(info.state.releases().count(), 2);
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_checks_records_against_trusted_keys() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:trusted";
+
+    let registry = TestRegistry::start().await?;
+    let published = registry
+        .publish_package(PACKAGE_NAME, "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    let config = registry.client_config();
+    let (other_key, _) = generate_p256_pair();
+    let other_id = other_key.fingerprint().to_string();
+    let output = String::from_utf8(
+        run_warg(&config, &["trust", "add", PACKAGE_NAME, other_id.as_str()]).await?,
+    )?;
+    assert!(
+        output.contains(&format!("trusted key `{other_id}` for `{PACKAGE_NAME}`")),
+        "{output}"
+    );
+
+    // A record signed by a key that is not pinned fails a strict fetch
+    let output = warg(&config, &["fetch", "--strict-trust", PACKAGE_NAME]).await?;
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(
+        stdout.contains(&format!(
+            "record `{record_id}` of package `{PACKAGE_NAME}` was signed by key",
+            record_id = published.record_id
+        )),
+        "{stdout}"
+    );
+
+    // Otherwise, the record is fetched with a warning
+    let output = String::from_utf8(run_warg(&config, &["fetch", PACKAGE_NAME]).await?)?;
+    assert!(output.contains("which is not trusted"), "{output}");
+
+    // Trusting the publisher's key accepts its new records
+    let publisher_id = registry.publisher_key().public_key().fingerprint();
+    run_warg(
+        &config,
+        &[
+            "trust",
+            "add",
+            PACKAGE_NAME,
+            publisher_id.to_string().as_str(),
+        ],
+    )
+    .await?;
+    registry
+        .publish_simple(PACKAGE_NAME, "2.0.0", wat::parse_str("(component)")?)
+        .await?;
+    registry.advance_checkpoint().await?;
+    run_warg(&config, &["fetch", "--strict-trust", PACKAGE_NAME]).await?;
+
+    let output = String::from_utf8(run_warg(&config, &["trust", "list"]).await?)?;
+    assert_eq!(
+        output,
+        format!("{PACKAGE_NAME}:\n  {other_id}\n  {publisher_id}\n")
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_prints_storage_info_as_json() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:info";
+
+    let registry = TestRegistry::start().await?;
+    let digest = registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    let checkpoint = registry.advance_checkpoint().await?;
+
+    // Populate the client storage, releasing its lock before running the CLI
+    let config = registry.client_config();
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let head = {
+        let client = client_with_config(&config)?;
+        client.upsert([&name]).await?;
+        client
+            .registry()
+            .load_package(client.get_warg_registry(), &name)
+            .await?
+            .context("package should be in client storage")?
+            .state
+            .head()
+            .as_ref()
+            .context("package should have a head")?
+            .digest
+            .clone()
+    };
+
+    let output = run_warg(&config, &["info", "--json"]).await?;
+    let info: serde_json::Value = serde_json::from_slice(&output)?;
+    assert_eq!(
+        info["registry"]
+            .as_str()
+            .map(|url| url.trim_end_matches('/')),
+        Some(registry.url())
+    );
+    assert_eq!(info["checkpoint"]["logLength"], checkpoint.log_length);
+    assert_eq!(
+        info["checkpoint"]["logRoot"],
+        checkpoint.log_root.to_string()
+    );
+
+    let packages = info["packages"].as_array().context("expected packages")?;
+    assert_eq!(packages.len(), 1);
+    assert_eq!(packages[0]["name"], PACKAGE_NAME);
+    assert_eq!(packages[0]["head"], head.to_string());
+    assert_eq!(packages[0]["versions"][0]["version"], "1.0.0");
+    assert_eq!(packages[0]["versions"][0]["content"], digest.to_string());
+    assert_eq!(packages[0]["versions"][0]["yanked"], false);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_keeps_pending_publishes_per_package() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    let config = registry.client_config();
+
+    for name in ["test:first", "test:second"] {
+        run_warg(&config, &["publish", "start", name]).await?;
+    }
+
+    let output = String::from_utf8(run_warg(&config, &["publish", "list"]).await?)?;
+    assert!(output.contains("`test:first`"), "{output}");
+    assert!(output.contains("`test:second`"), "{output}");
+
+    let output = String::from_utf8(
+        run_warg(&config, &["publish", "list", "--package", "test:second"]).await?,
+    )?;
+    assert!(!output.contains("`test:first`"), "{output}");
+    assert!(output.contains("`test:second`"), "{output}");
+
+    run_warg(&config, &["publish", "abort", "--package", "test:first"]).await?;
+
+    let client = client_with_config(&config)?;
+    let pending = client
+        .registry()
+        .load_publishes()
+        .await?
+        .into_iter()
+        .map(|info| info.name.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(pending, ["test:second"]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_prints_publish_results_as_json() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    let config = registry.client_config();
+    let name = PackageName::new("test:scripted")?;
+
+    // Pending publishes are reported with their state
+    let output = run_warg(
+        &config,
+        &["publish", "start", name.as_ref(), "--format", "json"],
+    )
+    .await?;
+    let started: serde_json::Value = serde_json::from_slice(&output)?;
+    assert_eq!(started["package"], name.as_ref());
+    assert_eq!(started["state"], "pending");
+
+    let output = run_warg(&config, &["publish", "abort", "--format", "json"]).await?;
+    let aborted: serde_json::Value = serde_json::from_slice(&output)?;
+    assert_eq!(aborted["state"], "aborted");
+
+    // A failed command still prints a single document
+    let res = warg(&config, &["publish", "submit", "--format", "json"]).await?;
+    assert!(!res.status.success());
+    let failed: serde_json::Value = serde_json::from_slice(&res.stdout)?;
+    assert_eq!(failed["state"], "failed");
+    assert!(failed["error"]
+        .as_str()
+        .unwrap()
+        .contains("no pending publish to submit"));
+
+    // A published record is reported with the checkpoint that includes it
+    let record_id = {
+        let client = client_with_config(&config)?;
+        client
+            .publish_with_info(
+                registry.publisher_key(),
+                PublishInfo {
+                    name: name.clone(),
+                    head: None,
+                    entries: vec![PublishEntry::Init],
+                },
+            )
+            .await?
+    };
+    let output = run_warg(
+        &config,
+        &[
+            "publish",
+            "wait",
+            name.as_ref(),
+            &record_id.to_string(),
+            "--format",
+            "json",
+        ],
+    )
+    .await?;
+    let published: serde_json::Value = serde_json::from_slice(&output)?;
+    assert_eq!(published["recordId"], record_id.to_string());
+    assert_eq!(published["state"], "published");
+    assert!(published["registryIndex"].is_u64());
+    assert!(published["elapsedMs"].is_u64());
+
+    let client = client_with_config(&config)?;
+    let checkpoint = client
+        .registry()
+        .load_checkpoint(client.get_warg_registry())
+        .await?
+        .context("expected a checkpoint")?;
+    assert_eq!(
+        published["checkpoint"],
+        AnyHash::of(HashAlgorithm::Sha256, &checkpoint.as_ref().checkpoint).to_string()
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_resumes_interrupted_content_upload() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:resumed";
+
+    let registry = TestRegistry::start().await?;
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let bytes = wat::parse_str("(component (core module))")?;
+    let digest = AnyHash::from(Hash::<Sha256>::of(bytes.as_slice()));
+    let total = bytes.len() as u64;
+
+    // Submit a record whose content upload is interrupted halfway
+    let key = registry.publisher_key();
+    let record = ProtoEnvelope::signed_contents(
+        key,
+        package::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: key.public_key(),
+                },
+                PackageEntry::Release {
+                    version: "1.0.0".parse()?,
+                    content: digest.clone(),
+                    artifacts: Default::default(),
+                    metadata: None,
+                },
+            ],
+        },
+    )?;
+
+    let api = registry.api_client()?;
+    let record = api
+        .publish_package_record(
+            &LogId::package_log::<Sha256>(&name),
+            PublishRecordRequest {
+                package_name: Cow::Borrowed(&name),
+                record: Cow::Owned(record.into()),
+                content_sources: Default::default(),
+            },
+        )
+        .await?;
+    let UploadEndpoint::Http {
+        method,
+        url,
+        headers,
+    } = &record
+        .missing_content()
+        .find(|(d, _)| **d == digest)
+        .context("expected the content to be missing")?
+        .1
+        .upload[0];
+
+    let half = total / 2;
+    let progress = api
+        .upload_content_range(
+            method,
+            url,
+            headers,
+            UploadContentRange::Bytes {
+                start: 0,
+                end: total - 1,
+                total,
+            },
+            bytes[..half as usize].to_vec(),
+        )
+        .await?;
+    assert_eq!(progress, UploadProgress::Received(half));
+
+    // The registry keeps the received content and rejects uploads that skip any
+    let progress = api
+        .upload_content_range(
+            method,
+            url,
+            headers,
+            UploadContentRange::Bytes {
+                start: half + 1,
+                end: total - 1,
+                total,
+            },
+            bytes[half as usize + 1..].to_vec(),
+        )
+        .await?;
+    assert_eq!(progress, UploadProgress::Received(half));
+
+    // Publishing the same content again resumes the upload
+    let published = registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", bytes.clone())
+        .await?;
+    assert_eq!(published, digest);
+    registry.advance_checkpoint().await?;
+
+    let client = registry.client()?;
+    client.upsert([&name]).await?;
+    let download = client
+        .download(&name, &VersionReq::STAR)
+        .await?
+        .context("failed to resolve package")?;
+    assert_eq!(fs::read(&download.path)?, bytes);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_fetches_operator_records() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    registry.advance_checkpoint().await?;
+
+    let config = registry.client_config();
+    let output = run_warg(&config, &["fetch", "operator", "--json"]).await?;
+    let records: Vec<serde_json::Value> = serde_json::from_slice(&output)?;
+    assert!(!records.is_empty());
+
+    let init = &records[0];
+    assert_eq!(init["registryIndex"], 0);
+    assert_eq!(
+        init["keyId"],
+        registry
+            .operator_key()
+            .public_key()
+            .fingerprint()
+            .to_string()
+    );
+    assert_eq!(init["entries"][0]["type"], "init");
+    assert!(records
+        .iter()
+        .flat_map(|r| r["entries"].as_array().unwrap())
+        .any(|e| e["type"] == "defineNamespace" && e["namespace"] == TEST_NAMESPACE));
+
+    // The fetched records are kept in client storage
+    let output = run_warg(&config, &["fetch", "operator"]).await?;
+    assert_eq!(
+        String::from_utf8(output)?.trim(),
+        "operator log is up to date"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_downloads_package_to_output() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:download";
+
+    let registry = TestRegistry::start().await?;
+    let v1 = wat::parse_str("(component)")?;
+    let v2 = wat::parse_str("(component (core module))")?;
+    registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", v1.clone())
+        .await?;
+    let v2_digest = registry.publish_simple(PACKAGE_NAME, "2.0.0", v2).await?;
+    registry.advance_checkpoint().await?;
+
+    let config = registry.client_config();
+    let dir = config
+        .registries_dir
+        .as_ref()
+        .and_then(|dir| dir.parent())
+        .context("expected a client directory")?
+        .to_path_buf();
+    let output = dir.join("download.wasm");
+    let output_arg = output.to_str().context("expected a UTF-8 path")?;
+    run_warg(
+        &config,
+        &[
+            "download",
+            PACKAGE_NAME,
+            "--version",
+            "^1",
+            "--output",
+            output_arg,
+        ],
+    )
+    .await?;
+    assert_eq!(std::fs::read(&output)?, v1);
+
+    // Content that does not match the release digest is rejected
+    std::fs::write(registry.content_path(&v2_digest), b"corrupted")?;
+    let output = dir.join("corrupted.wasm");
+    let output_arg = output.to_str().context("expected a UTF-8 path")?;
+    let res = warg(
+        &registry.client_config(),
+        &[
+            "download",
+            PACKAGE_NAME,
+            "--version",
+            "^2",
+            "--output",
+            output_arg,
+        ],
+    )
+    .await?;
+    assert!(!res.status.success());
+    let stderr = String::from_utf8_lossy(&res.stderr);
+    assert!(stderr.contains(&v2_digest.to_string()), "{stderr}");
+    assert!(!output.exists());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_downloads_several_packages() -> Result<()> {
+    const PACKAGE_NAMES: [&str; 2] = ["test:first-download", "test:second-download"];
+
+    let registry = TestRegistry::start().await?;
+    let mut digests = Vec::new();
+    for (name, wat) in PACKAGE_NAMES
+        .into_iter()
+        .zip(["(component)", "(component (core module))"])
+    {
+        digests.push(
+            registry
+                .publish_simple(name, "1.0.0", wat::parse_str(wat)?)
+                .await?,
+        );
+    }
+    registry.advance_checkpoint().await?;
+
+    let config = registry.client_config();
+    let stdout = run_warg(
+        &config,
+        &[
+            "download",
+            PACKAGE_NAMES[0],
+            PACKAGE_NAMES[1],
+            "--jobs",
+            "2",
+        ],
+    )
+    .await?;
+    let stdout = String::from_utf8_lossy(&stdout);
+    for (name, digest) in PACKAGE_NAMES.iter().zip(&digests) {
+        assert!(
+            stdout.contains(&format!(
+                "downloaded version 1.0.0 of package `{name}` ({digest})"
+            )),
+            "{stdout}"
+        );
+    }
+
+    // Content is written to an output path for a single package only
+    let res = warg(
+        &config,
+        &[
+            "download",
+            PACKAGE_NAMES[0],
+            PACKAGE_NAMES[1],
+            "--output",
+            "download.wasm",
+        ],
+    )
+    .await?;
+    assert!(!res.status.success());
+    let stderr = String::from_utf8_lossy(&res.stderr);
+    assert!(stderr.contains("single package"), "{stderr}");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_downloads_release_artifacts_by_name() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:artifacts";
+
+    let registry = TestRegistry::start().await?;
+    let config = registry.client_config();
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let component = wat::parse_str("(component)")?;
+    // WIT packages are encoded as components
+    let wit = wat::parse_str("(component (type (instance)))")?;
+
+    {
+        let client = client_with_config(&config)?;
+        let mut digests = Vec::new();
+        for bytes in [component.clone(), wit.clone()] {
+            digests.push(
+                client
+                    .content()
+                    .store_content(
+                        Box::pin(futures::stream::once(async move { Ok(bytes.into()) })),
+                        None,
+                    )
+                    .await?,
+            );
+        }
+        let (component_digest, wit_digest) = (digests[0].clone(), digests[1].clone());
+
+        let record_id = client
+            .publish_with_info(
+                registry.publisher_key(),
+                PublishInfo {
+                    name: name.clone(),
+                    head: None,
+                    entries: vec![
+                        PublishEntry::Init,
+                        PublishEntry::Release {
+                            version: "1.0.0".parse()?,
+                            content: component_digest.clone(),
+                            artifacts: IndexMap::from([(
+                                package::WIT_ARTIFACT.to_string(),
+                                wit_digest.clone(),
+                            )]),
+                            metadata: None,
+                        },
+                    ],
+                },
+            )
+            .await?;
+        let result = client
+            .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+            .await?;
+        assert!(result.reason().is_none(), "{result:?}");
+
+        // Both artifacts were uploaded to and verified by the registry
+        for digest in [&component_digest, &wit_digest] {
+            assert!(registry.content_path(digest).is_file(), "{digest}");
+        }
+
+        // Artifacts of types not registered with the registry are rejected
+        let res = client
+            .publish_with_info(
+                registry.publisher_key(),
+                PublishInfo {
+                    name: name.clone(),
+                    head: None,
+                    entries: vec![PublishEntry::Release {
+                        version: "2.0.0".parse()?,
+                        content: component_digest,
+                        artifacts: IndexMap::from([("docs".to_string(), wit_digest)]),
+                        metadata: None,
+                    }],
+                },
+            )
+            .await;
+        let error = res
+            .expect_err("expected the release to be rejected")
+            .to_string();
+        assert!(
+            error.contains("version 2.0.0 has an artifact of unregistered type `docs`"),
+            "{error}"
+        );
+    }
+    registry.advance_checkpoint().await?;
+
+    let dir = config
+        .registries_dir
+        .as_ref()
+        .and_then(|dir| dir.parent())
+        .context("expected a client directory")?
+        .to_path_buf();
+    for (artifact, expected) in [("component", &component), ("wit", &wit)] {
+        let output = dir.join(format!("{artifact}.wasm"));
+        let output_arg = output.to_str().context("expected a UTF-8 path")?;
+        run_warg(
+            &config,
+            &[
+                "download",
+                PACKAGE_NAME,
+                "--artifact",
+                artifact,
+                "--output",
+                output_arg,
+            ],
+        )
+        .await?;
+        assert_eq!(&std::fs::read(&output)?, expected, "{artifact}");
+    }
+
+    let res = warg(&config, &["download", PACKAGE_NAME, "--artifact", "debug"]).await?;
+    assert!(!res.status.success());
+    let stderr = String::from_utf8_lossy(&res.stderr);
+    assert!(
+        stderr.contains(
+            "version `1.0.0` of package `test:artifacts` does not have a `debug` artifact"
+        ),
+        "{stderr}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_selects_registries_by_alias() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:aliased";
+    const UNREACHABLE_URL: &str = "http://127.0.0.1:9";
+
+    let registry = TestRegistry::start().await?;
+    let digest = registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    // An unreachable home registry is only set without verification
+    let config = registry.client_config();
+    let config_path = write_config(&config)?;
+    let res = warg(&config, &["config", "set-registry", UNREACHABLE_URL]).await?;
+    assert!(!res.status.success());
+    let stderr = String::from_utf8_lossy(&res.stderr);
+    assert!(stderr.contains("failed to reach registry"), "{stderr}");
+    run_warg(
+        &config,
+        &["config", "set-registry", UNREACHABLE_URL, "--no-verify"],
+    )
+    .await?;
+    let config = Config::from_file(&config_path)?;
+
+    run_warg(
+        &config,
+        &["config", "add-registry", "staging", registry.url()],
+    )
+    .await?;
+    let config = Config::from_file(&config_path)?;
+    let url = RegistryUrl::new(registry.url())?.to_string();
+    assert_eq!(config.registries.get("staging"), Some(&url));
+    assert_eq!(
+        config.home_url,
+        Some(RegistryUrl::new(UNREACHABLE_URL)?.to_string())
+    );
+
+    let stdout = run_warg(&config, &["config", "get", "registries"]).await?;
+    assert!(String::from_utf8_lossy(&stdout).contains(&url));
+
+    // The alias selects the registry the package is downloaded from
+    let stdout = run_warg(
+        &config,
+        &["download", "--registry", "staging", PACKAGE_NAME],
+    )
+    .await?;
+    let stdout = String::from_utf8_lossy(&stdout);
+    assert!(
+        stdout.contains(&format!(
+            "downloaded version 1.0.0 of package `{PACKAGE_NAME}` ({digest})"
+        )),
+        "{stdout}"
+    );
+
+    // The default key must name a key in the keyring
+    let res = warg(&config, &["config", "set-default-key", "missing"]).await?;
+    assert!(!res.status.success());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_routes_namespaces_to_registries() -> Result<()> {
+    const INTERNAL_PACKAGE: &str = "internal:tool";
+    const HOME_PACKAGE: &str = "test:http";
+
+    let home = TestRegistry::start().await?;
+    let internal = TestRegistry::start_with_namespace("internal").await?;
+    let home_digest = home
+        .publish_simple(HOME_PACKAGE, "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    let internal_digest = internal
+        .publish_simple(
+            INTERNAL_PACKAGE,
+            "1.0.0",
+            wat::parse_str("(component (core module))")?,
+        )
+        .await?;
+    home.advance_checkpoint().await?;
+    internal.advance_checkpoint().await?;
+
+    // The home registry does not serve the internal namespace
+    let mut config = home.client_config();
+    let res = warg(&config, &["download", INTERNAL_PACKAGE]).await?;
+    assert!(!res.status.success());
+
+    config
+        .namespace_registries
+        .insert("internal:*".to_string(), internal.url().to_string());
+    let stdout = run_warg(&config, &["download", INTERNAL_PACKAGE, HOME_PACKAGE]).await?;
+    let stdout = String::from_utf8_lossy(&stdout);
+    for (name, registry, digest) in [
+        (INTERNAL_PACKAGE, internal.url(), &internal_digest),
+        (HOME_PACKAGE, home.url(), &home_digest),
+    ] {
+        let registry = RegistryUrl::new(registry)?;
+        assert!(
+            stdout.contains(&format!(
+                "downloading package `{name}` from registry `{registry}`"
+            )),
+            "{stdout}"
+        );
+        assert!(
+            stdout.contains(&format!(
+                "downloaded version 1.0.0 of package `{name}` ({digest})"
+            )),
+            "{stdout}"
+        );
+    }
+
+    // Each registry has its own client storage while content is shared
+    let mut client = client_with_config(&config)?;
+    let internal_name = PackageName::new(INTERNAL_PACKAGE)?;
+    client.refresh_namespace(internal_name.namespace()).await?;
+    assert_eq!(
+        client.routed_registry().map(ToString::to_string),
+        Some(RegistryUrl::new(internal.url())?.to_string())
+    );
+    assert!(client
+        .registry()
+        .load_package(client.get_warg_registry(), &internal_name)
+        .await?
+        .is_some());
+    assert!(client
+        .content()
+        .content_location(&internal_digest)
+        .is_some());
+
+    client.refresh_namespace(TEST_NAMESPACE).await?;
+    assert!(client.routed_registry().is_none());
+    assert!(client
+        .registry()
+        .load_package(client.get_warg_registry(), &internal_name)
+        .await?
+        .is_none());
+    assert!(client.content().content_location(&home_digest).is_some());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_manages_namespace_routes() -> Result<()> {
+    const INTERNAL_PACKAGE: &str = "internal:tool";
+    const APP_PACKAGE: &str = "test:app";
+
+    let home = TestRegistry::start().await?;
+    let internal = TestRegistry::start_with_namespace("internal").await?;
+    internal
+        .publish_simple(INTERNAL_PACKAGE, "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    home.publish_simple(
+        APP_PACKAGE,
+        "1.0.0",
+        wat::parse_str(
+            r#"(component (import "unlocked-dep=<internal:tool@{>=1.0.0}>" (instance)))"#,
+        )?,
+    )
+    .await?;
+    home.advance_checkpoint().await?;
+    internal.advance_checkpoint().await?;
+
+    let config = home.client_config();
+    let config_path = write_config(&config)?;
+    let stdout = run_warg(&config, &["config", "route", "list"]).await?;
+    let stdout = String::from_utf8_lossy(&stdout);
+    assert!(
+        stdout.contains("no namespace routes configured"),
+        "{stdout}"
+    );
+
+    // Routes are added to the configuration file
+    run_warg(
+        &config,
+        &["config", "route", "add", "corp:*", internal.url()],
+    )
+    .await?;
+    let config = Config::from_file(&config_path)?;
+    let internal_url = RegistryUrl::new(internal.url())?.to_string();
+    assert_eq!(
+        config.namespace_registries.get("corp:*"),
+        Some(&internal_url)
+    );
+    let stdout = run_warg(&config, &["config", "route", "list"]).await?;
+    let stdout = String::from_utf8_lossy(&stdout);
+    assert!(
+        stdout.contains(&format!("corp:* -> {internal_url}")),
+        "{stdout}"
+    );
+
+    // With routes configured, a namespace the home registry does not serve
+    // must be routed
+    let res = warg(&config, &["download", INTERNAL_PACKAGE]).await?;
+    assert!(!res.status.success());
+    let stderr = String::from_utf8_lossy(&res.stderr);
+    assert!(
+        stderr.contains(&format!(
+            "namespace `internal` is not served by registry `{home_url}` and is not routed to another registry",
+            home_url = RegistryUrl::new(home.url())?
+        )),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("warg config route add internal <REGISTRY>"),
+        "{stderr}"
+    );
+
+    // The lock file records the registry each package was resolved from
+    run_warg(
+        &config,
+        &["config", "route", "add", "internal", internal.url()],
+    )
+    .await?;
+    let config = Config::from_file(&config_path)?;
+    run_warg(&config, &["download", INTERNAL_PACKAGE, APP_PACKAGE]).await?;
+
+    let mut client = client_with_config(&config)?;
+    client.refresh_namespace(TEST_NAMESPACE).await?;
+    let info = client
+        .registry()
+        .load_package(client.get_warg_registry(), &PackageName::new(APP_PACKAGE)?)
+        .await?
+        .context("expected the package to be stored")?;
+    let lock = client.lock_file(&info).await?;
+    let mut registries = lock
+        .packages
+        .iter()
+        .map(|p| (p.name.to_string(), p.registry.clone()))
+        .collect::<Vec<_>>();
+    registries.sort();
+    assert_eq!(
+        registries,
+        [
+            (INTERNAL_PACKAGE.to_string(), internal_url),
+            (
+                APP_PACKAGE.to_string(),
+                RegistryUrl::new(home.url())?.to_string()
+            ),
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_prints_package_record_history() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:history";
+
+    let registry = TestRegistry::start().await?;
+    let bytes = wat::parse_str("(component)")?;
+    for version in ["1.0.0", "1.1.0"] {
+        registry
+            .publish_simple(PACKAGE_NAME, version, bytes.clone())
+            .await?;
+    }
+
+    let client = registry.client()?;
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let record_id = client
+        .publish_with_info(
+            registry.publisher_key(),
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::Yank {
+                    version: "1.0.0".parse()?,
+                }],
+            },
+        )
+        .await?;
+    assert!(client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(25))
+        .await?
+        .is_published());
+    registry.advance_checkpoint().await?;
+
+    // The history is paged by the registry
+    let api = registry.api_client()?;
+    let page = api
+        .package_records(
+            &name,
+            &PackageRecordsQuery {
+                since: None,
+                limit: Some(2),
+            },
+        )
+        .await?;
+    assert_eq!(page.records.len(), 2);
+    let page = api
+        .package_records(
+            &name,
+            &PackageRecordsQuery {
+                since: page.next,
+                limit: Some(2),
+            },
+        )
+        .await?;
+    assert_eq!(page.records.len(), 1);
+    assert_eq!(page.records[0].record_id, record_id);
+    assert!(page.next.is_none());
+
+    let history = client.remote_record_history(&name).await?;
+    assert_eq!(history.len(), 3);
+    let key_id = registry.publisher_key().public_key().fingerprint();
+    assert!(history.iter().all(|r| r.key_id == key_id));
+    assert!(history.iter().all(|r| r.checkpoint_log_length.is_some()));
+    assert!(matches!(
+        &history[0].entries[..],
+        [
+            PackageRecordEntry::Init { .. },
+            PackageRecordEntry::Release { yanked: true, .. }
+        ]
+    ));
+    assert!(matches!(
+        &history[1].entries[..],
+        [PackageRecordEntry::Release { yanked: false, .. }]
+    ));
+    assert!(matches!(
+        &history[2].entries[..],
+        [PackageRecordEntry::Yank { .. }]
+    ));
+
+    // The CLI prints the records in log order with the yanked release flagged
+    let config = registry.client_config();
+    let stdout = run_warg(&config, &["log", PACKAGE_NAME]).await?;
+    let stdout = String::from_utf8_lossy(&stdout);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 4, "{stdout}");
+    assert!(lines[0].starts_with("RECORD"), "{stdout}");
+    assert!(lines[1].contains("init"), "{stdout}");
+    assert!(lines[1].contains("release 1.0.0 [YANKED]"), "{stdout}");
+    assert!(lines[2].contains("release 1.1.0"), "{stdout}");
+    assert!(!lines[2].contains("YANKED"), "{stdout}");
+    assert!(lines[3].contains("yank 1.0.0"), "{stdout}");
+    assert!(
+        lines[1..].iter().all(|l| l.contains("published")),
+        "{stdout}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_downloads_cached_packages_offline() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:offline";
+
+    let registry = TestRegistry::start().await?;
+    let v1 = wat::parse_str("(component)")?;
+    registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", v1.clone())
+        .await?;
+    registry
+        .publish_simple(
+            PACKAGE_NAME,
+            "2.0.0",
+            wat::parse_str("(component (core module))")?,
+        )
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    // Only the content of the first release is downloaded
+    let config = registry.client_config();
+    run_warg(&config, &["download", PACKAGE_NAME, "--version", "^1"]).await?;
+
+    // A release published afterwards is not seen by an offline client
+    registry
+        .publish_simple(PACKAGE_NAME, "3.0.0", v1.clone())
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    let output = config
+        .registries_dir
+        .as_ref()
+        .and_then(|dir| dir.parent())
+        .context("expected a client directory")?
+        .join("offline.wasm");
+    let output_arg = output.to_str().context("expected a UTF-8 path")?;
+    run_warg(
+        &config,
+        &[
+            "download",
+            PACKAGE_NAME,
+            "--version",
+            "^1",
+            "--offline",
+            "--output",
+            output_arg,
+        ],
+    )
+    .await?;
+    assert_eq!(std::fs::read(&output)?, v1);
+
+    // The latest known release is 2.0.0, but its content was never downloaded
+    for name in [PACKAGE_NAME, "test:unknown"] {
+        let res = warg(&config, &["download", name, "--offline"]).await?;
+        assert!(!res.status.success());
+        let stderr = String::from_utf8_lossy(&res.stderr);
+        assert!(stderr.contains("offline mode"), "{stderr}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn offline_client_refuses_to_publish() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:offline-publish";
+
+    let registry = TestRegistry::start().await?;
+    let digest = registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    let mut config = registry.client_config();
+    config.offline = true;
+    let client = client_with_config(&config)?;
+    assert!(client.is_offline());
+
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let res = client
+        .publish_with_info(
+            registry.publisher_key(),
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::Release {
+                    version: "2.0.0".parse()?,
+                    content: digest,
+                    artifacts: Default::default(),
+                    metadata: None,
+                }],
+            },
+        )
+        .await;
+    assert!(matches!(res, Err(ClientError::Offline)), "{res:?}");
+
+    // Waiting on a record fails immediately rather than polling the registry
+    let record_id = RecordId::from(AnyHash::from(Hash::<Sha256>::of("unknown")));
+    let res = tokio::time::timeout(
+        Duration::from_secs(5),
+        client.wait_for_publish(&name, &record_id, Duration::from_secs(60)),
+    )
+    .await?;
+    assert!(matches!(res, Err(ClientError::Offline)), "{res:?}");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_downloads_from_fallback_content_dirs() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:shared";
+
+    let registry = TestRegistry::start().await?;
+    let bytes = wat::parse_str("(component)")?;
+    let digest = registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", bytes.clone())
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    // Another user's client populates the shared content directory
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let shared = registry.client_config();
+    client_with_config(&shared)?
+        .download(&name, &"1.0.0".parse()?)
+        .await?
+        .context("failed to resolve package")?;
+    let shared_dir = shared
+        .content_dir
+        .clone()
+        .context("expected a content dir")?;
+
+    // An offline client makes no requests, so the content must come from the
+    // fallback directory
+    let mut config = registry.client_config();
+    config.registries_dir = shared.registries_dir.clone();
+    config.content_fallback_dirs = vec![shared_dir.clone()];
+    config.offline = true;
+    let download = client_with_config(&config)?
+        .download(&name, &"1.0.0".parse()?)
+        .await?
+        .context("failed to resolve package")?;
+    assert_eq!(download.digest, digest);
+    assert!(download.path.starts_with(&shared_dir));
+    assert_eq!(fs::read(&download.path)?, bytes);
+
+    // Corrupted shared content is ignored and fetched into the client's own
+    // content directory instead
+    fs::write(&download.path, b"corrupted")?;
+    config.offline = false;
+    let download = client_with_config(&config)?
+        .download(&name, &"1.0.0".parse()?)
+        .await?
+        .context("failed to resolve package")?;
+    assert!(download
+        .path
+        .starts_with(config.content_dir.as_ref().unwrap()));
+    assert_eq!(fs::read(&download.path)?, bytes);
+
+    Ok(())
+}
+
+/// Gets the number of bytes of content the registry has served.
+async fn served_bytes(registry: &TestRegistry) -> Result<u64> {
+    let metrics = reqwest::get(format!("{url}/metrics", url = registry.url()))
+        .await?
+        .text()
+        .await?;
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix("warg_content_served_bytes_total "))
+        .context("missing served bytes metric")?
+        .parse()
+        .context("invalid served bytes metric")
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_resumes_interrupted_downloads() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:large";
+
+    let registry = TestRegistry::start().await?;
+    let bytes = wat::parse_str(format!(
+        r#"(component (core module (memory 1) (data (i32.const 0) "{data}")))"#,
+        data = "a".repeat(60_000)
+    ))?;
+    let len = bytes.len() as u64;
+    let digest = registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", bytes.clone())
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    // A range of the content can be downloaded on its own
+    let client = registry.client()?;
+    let range = client
+        .download_range(&digest, 8..16)
+        .await?
+        .map_ok(|bytes| bytes.to_vec())
+        .try_concat()
+        .await?;
+    assert_eq!(range, bytes[8..16]);
+
+    // Interrupt a download halfway through the content
+    let half = len / 2;
+    let interrupted = client
+        .download_range(&digest, ..half)
+        .await?
+        .chain(futures::stream::once(async {
+            Err(anyhow::anyhow!("connection reset"))
+        }));
+    assert!(client
+        .content()
+        .store_partial_content(Box::pin(interrupted), &digest, 0)
+        .await
+        .is_err());
+    assert_eq!(client.content().partial_content_len(&digest).await?, half);
+
+    // Rerunning the download only transfers the remaining bytes
+    let before = served_bytes(&registry).await?;
+    let path = client
+        .download_contents([digest.clone()], 1)
+        .await
+        .swap_remove(&digest)
+        .unwrap()?;
+    assert_eq!(served_bytes(&registry).await? - before, len - half);
+    assert_eq!(fs::read(&path)?, bytes);
+
+    // A resumed download not matching the digest is downloaded again
+    assert!(client.content().remove_content(&digest).await?);
+    client
+        .content()
+        .store_partial_content(
+            Box::pin(
+                futures::stream::once(async { Ok(bytes::Bytes::from_static(b"corrupted")) }).chain(
+                    futures::stream::once(async { Err(anyhow::anyhow!("connection reset")) }),
+                ),
+            ),
+            &digest,
+            0,
+        )
+        .await
+        .unwrap_err();
+    let path = client.download_content(&digest).await?;
+    assert_eq!(fs::read(&path)?, bytes);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_reports_content_that_was_never_uploaded() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:unsourced";
+
+    let registry = TestRegistry::start().await?;
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let digest = AnyHash::from(Hash::<Sha256>::of("never uploaded"));
+
+    // Submit a record releasing content without uploading the content
+    let key = registry.publisher_key();
+    let record = ProtoEnvelope::signed_contents(
+        key,
+        package::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: key.public_key(),
+                },
+                PackageEntry::Release {
+                    version: "1.0.0".parse()?,
+                    content: digest.clone(),
+                    artifacts: Default::default(),
+                    metadata: None,
+                },
+            ],
+        },
+    )?;
+    let record = registry
+        .api_client()?
+        .publish_package_record(
+            &LogId::package_log::<Sha256>(&name),
+            PublishRecordRequest {
+                package_name: Cow::Borrowed(&name),
+                record: Cow::Owned(record.into()),
+                content_sources: Default::default(),
+            },
+        )
+        .await?;
+
+    let result = registry
+        .client()?
+        .wait_for_publish(&name, &record.record_id, Duration::from_millis(25))
+        .await?;
+    assert_eq!(
+        result,
+        PublishResult::MissingContent {
+            digests: vec![digest.clone()]
+        }
+    );
+
+    // The CLI reports the missing content rather than waiting for it
+    let res = warg(
+        &registry.client_config(),
+        &[
+            "publish",
+            "wait",
+            PACKAGE_NAME,
+            &record.record_id.to_string(),
+        ],
+    )
+    .await?;
+    assert!(!res.status.success());
+    let stderr = String::from_utf8_lossy(&res.stderr);
+    assert!(
+        stderr.contains(&format!(
+            "needed content `{digest}` but it was not provided"
+        )),
+        "{stderr}"
+    );
+
+    Ok(())
+}
+
+/// Writes the given key as a DER-encoded SEC1 private key for `openssl`.
+#[cfg(unix)]
+fn write_der_key(key: &PrivateKey, path: &std::path::Path) -> Result<()> {
+    // ECPrivateKey { version 1, privateKey, parameters prime256v1 }
+    let mut der = vec![0x30, 0x31, 0x02, 0x01, 0x01, 0x04, 0x20];
+    der.extend(key.bytes());
+    der.extend([
+        0xa0, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07,
+    ]);
+    fs::write(path, der)?;
+    Ok(())
+}
+
+/// Writes a signing script to the given directory that signs its stdin with
+/// the DER-encoded key given as its argument, returning the script's path.
+#[cfg(unix)]
+fn write_signer_script(dir: &std::path::Path) -> Result<std::path::PathBuf> {
+    let script = dir.join("signer.sh");
+    fs::write(
+        &script,
+        "set -e\nprintf 'ecdsa-p256:'\nopenssl dgst -sha256 -keyform DER -sign \"$1\" | openssl base64 -A\necho\n",
+    )?;
+    Ok(script)
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_publishes_with_external_signer() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    let config = registry.client_config();
+    let key = registry.publisher_key();
+    let dir = tempfile::tempdir()?;
+
+    let script = write_signer_script(dir.path())?;
+    let publisher_der = dir.path().join("publisher.der");
+    write_der_key(key, &publisher_der)?;
+    let (_, other_key) = generate_p256_pair();
+    let other_der = dir.path().join("other.der");
+    write_der_key(&other_key, &other_der)?;
+
+    let store_publish = |name: &'static str| {
+        let config = config.clone();
+        async move {
+            let client = client_with_config(&config)?;
+            let bytes = wat::parse_str("(component)")?;
+            let digest = client
+                .content()
+                .store_content(
+                    Box::pin(futures::stream::once(async move { Ok(bytes.into()) })),
+                    None,
+                )
+                .await?;
+            client
+                .registry()
+                .store_publish(Some(&PublishInfo {
+                    name: PackageName::new(name)?,
+                    head: None,
+                    entries: vec![
+                        PublishEntry::Init,
+                        PublishEntry::Release {
+                            version: "1.0.0".parse()?,
+                            content: digest,
+                            artifacts: Default::default(),
+                            metadata: None,
+                        },
+                    ],
+                }))
+                .await?;
+            anyhow::Ok(())
+        }
+    };
+    let public_key = key.public_key().to_string();
+    let submit = |der: &std::path::Path| {
+        let command = format!("sh {} {}", script.display(), der.display());
+        let config = config.clone();
+        let public_key = public_key.clone();
+        async move {
+            warg(
+                &config,
+                &[
+                    "publish",
+                    "submit",
+                    "--sign-with-command",
+                    &command,
+                    "--signer-public-key",
+                    &public_key,
+                ],
+            )
+            .await
+        }
+    };
+
+    // A record signed by the command is published
+    store_publish("test:signed").await?;
+    let output = submit(&publisher_der).await?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "{stderr}{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let client = client_with_config(&config)?;
+    client.upsert([&PackageName::new("test:signed")?]).await?;
+    let download = client
+        .download_exact(&PackageName::new("test:signed")?, &"1.0.0".parse()?)
+        .await?;
+    assert_eq!(fs::read(download.path)?, wat::parse_str("(component)")?);
+    drop(client);
+
+    // A record signed with a key other than the given public key is rejected
+    store_publish("test:missigned").await?;
+    let output = submit(&other_der).await?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("verification failed"), "{stderr}");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_publishes_directory_of_packages() -> Result<()> {
+    const PACKAGES: [&str; 3] = ["alpha", "beta", "gamma"];
+    const VERSIONS: [&str; 4] = ["1.0.0", "1.1.0", "1.10.0", "2.0.0"];
+
+    let registry = TestRegistry::start().await?;
+    let config = registry.client_config();
+    let key = registry.publisher_key();
+    let dir = tempfile::tempdir()?;
+
+    let script = dir.path().join("signer.sh");
+    fs::write(
+        &script,
+        "set -e\nprintf 'ecdsa-p256:'\nopenssl dgst -sha256 -keyform DER -sign \"$1\" | openssl base64 -A\necho\n",
+    )?;
+    let publisher_der = dir.path().join("publisher.der");
+    write_der_key(key, &publisher_der)?;
+
+    let root = dir.path().join("packages");
+    for package in PACKAGES {
+        let package_dir = root.join(TEST_NAMESPACE).join(package);
+        fs::create_dir_all(&package_dir)?;
+        for version in VERSIONS {
+            fs::write(
+                package_dir.join(format!("{version}.wasm")),
+                wat::parse_str(format!(
+                    r#"(component (core module (func (export "{package}@{version}"))))"#
+                ))?,
+            )?;
+        }
+        fs::write(package_dir.join("README.md"), "not a release")?;
+    }
+
+    let command = format!("sh {} {}", script.display(), publisher_der.display());
+    let public_key = key.public_key().to_string();
+    let args = [
+        "publish",
+        "dir",
+        root.to_str().unwrap(),
+        "--sign-with-command",
+        &command,
+        "--signer-public-key",
+        &public_key,
+        "--format",
+        "json",
+    ];
+
+    let reports: Vec<serde_json::Value> = serde_json::from_slice(&run_warg(&config, &args).await?)?;
+    assert_eq!(reports.len(), PACKAGES.len());
+    assert!(
+        reports.iter().all(|report| report["state"] == "published"),
+        "{reports:?}"
+    );
+
+    let names = PACKAGES
+        .iter()
+        .map(|package| PackageName::new(format!("{TEST_NAMESPACE}:{package}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let client = client_with_config(&config)?;
+    client.upsert(&names).await?;
+    let (heads, versions) = package_releases(&client, &names).await?;
+    assert_eq!(versions.len(), PACKAGES.len() * VERSIONS.len());
+    assert_eq!(
+        versions[..VERSIONS.len()],
+        VERSIONS.map(|version| format!("{TEST_NAMESPACE}:alpha@{version}"))
+    );
+    drop(client);
+
+    // Running the import again publishes nothing
+    let reports: Vec<serde_json::Value> = serde_json::from_slice(&run_warg(&config, &args).await?)?;
+    assert!(
+        reports
+            .iter()
+            .all(|report| report["state"] == "alreadyPublished"),
+        "{reports:?}"
+    );
+
+    let client = client_with_config(&config)?;
+    client.upsert(&names).await?;
+    assert_eq!(package_releases(&client, &names).await?, (heads, versions));
+
+    Ok(())
+}
+
+/// Gets the heads of the given package logs in client storage along with
+/// their releases, as `<package>@<version>`.
+async fn package_releases(
+    client: &FileSystemClient,
+    names: &[PackageName],
+) -> Result<(Vec<Option<RecordId>>, Vec<String>)> {
+    let mut heads = Vec::new();
+    let mut versions = Vec::new();
+    for name in names {
+        let info = client
+            .registry()
+            .load_package(client.get_warg_registry(), name)
+            .await?
+            .context("expected the package in client storage")?;
+        heads.push(info.state.head().as_ref().map(|h| h.digest.clone()));
+        versions.extend(
+            info.state
+                .releases()
+                .map(|release| format!("{name}@{version}", version = release.version)),
+        );
+    }
+
+    Ok((heads, versions))
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_rebases_publish_onto_new_head() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:rebased";
+
+    let registry = TestRegistry::start().await?;
+    let config = registry.client_config();
+    let key = registry.publisher_key();
+    let dir = tempfile::tempdir()?;
+
+    let script = dir.path().join("signer.sh");
+    fs::write(
+        &script,
+        "set -e\nprintf 'ecdsa-p256:'\nopenssl dgst -sha256 -keyform DER -sign \"$1\" | openssl base64 -A\necho\n",
+    )?;
+    let der = dir.path().join("publisher.der");
+    write_der_key(key, &der)?;
+    let command = format!("sh {} {}", script.display(), der.display());
+    let public_key = key.public_key().to_string();
+
+    // Stores a pending publish started when the given record was the head,
+    // as if another client published to the package before it was submitted
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let store_publish = |head: RecordId, version: &'static str| {
+        let (config, name) = (config.clone(), name.clone());
+        async move {
+            let client = client_with_config(&config)?;
+            let bytes = wat::parse_str("(component)")?;
+            let digest = client
+                .content()
+                .store_content(
+                    Box::pin(futures::stream::once(async move { Ok(bytes.into()) })),
+                    None,
+                )
+                .await?;
+            client
+                .registry()
+                .store_publish(Some(&PublishInfo {
+                    name,
+                    head: Some(head),
+                    entries: vec![PublishEntry::Release {
+                        version: version.parse()?,
+                        content: digest,
+                        artifacts: Default::default(),
+                        metadata: None,
+                    }],
+                }))
+                .await?;
+            anyhow::Ok(())
+        }
+    };
+    let submit = |args: &'static [&'static str]| {
+        let (config, command, public_key) = (config.clone(), command.clone(), public_key.clone());
+        async move {
+            let mut all = vec![
+                "publish",
+                "submit",
+                "--sign-with-command",
+                &command,
+                "--signer-public-key",
+                &public_key,
+            ];
+            all.extend(args);
+            warg(&config, &all).await
+        }
+    };
+
+    // A release of a version not taken by the other client is rebased and published
+    let head = registry
+        .publish_package(PACKAGE_NAME, "1.0.0", wat::parse_str("(component)")?)
+        .await?
+        .record_id;
+    store_publish(head, "1.1.0").await?;
+    registry
+        .publish_simple(PACKAGE_NAME, "2.0.0", wat::parse_str("(component)")?)
+        .await?;
+    let output = submit(&[]).await?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "{stderr}{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let client = client_with_config(&config)?;
+    client.upsert([&name]).await?;
+    let package = client
+        .registry()
+        .load_package(client.get_warg_registry(), &name)
+        .await?
+        .context("expected the package in client storage")?;
+    let versions: Vec<_> = package
+        .state
+        .releases()
+        .map(|r| r.version.to_string())
+        .collect();
+    assert_eq!(versions, ["1.0.0", "2.0.0", "1.1.0"]);
+    drop(client);
+
+    // A release of a version taken by the other client fails instead
+    let head = registry
+        .publish_package(PACKAGE_NAME, "3.0.0", wat::parse_str("(component)")?)
+        .await?
+        .record_id;
+    store_publish(head, "4.0.0").await?;
+    registry
+        .publish_simple(PACKAGE_NAME, "4.0.0", wat::parse_str("(component)")?)
+        .await?;
+    let output = submit(&[]).await?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains(&format!(
+            "version `4.0.0` of package `{PACKAGE_NAME}` was released by another publish"
+        )),
+        "{stderr}"
+    );
+
+    // Rebasing may be disabled
+    let head = registry
+        .publish_package(PACKAGE_NAME, "5.0.0", wat::parse_str("(component)")?)
+        .await?
+        .record_id;
+    store_publish(head, "6.0.0").await?;
+    registry
+        .publish_simple(PACKAGE_NAME, "7.0.0", wat::parse_str("(component)")?)
+        .await?;
+    let output = submit(&["--no-rebase"]).await?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("previous record hash does not match"),
+        "{stderr}"
+    );
+
+    Ok(())
+}
+
+/// Rewrites the body of a request to, or a response from, the given path.
+type ProxyHook = Box<dyn Fn(&str, Vec<u8>) -> Vec<u8> + Send + Sync>;
+
+/// Starts a proxy to the given registry that passes request and response
+/// bodies through the given hooks.
+///
+/// Returns the URL of the proxy.
+async fn start_proxy(
+    upstream: &str,
+    request_hook: ProxyHook,
+    response_hook: ProxyHook,
+) -> Result<String> {
+    let upstream = upstream.to_string();
+    let hooks = Arc::new((request_hook, response_hook));
+    let client = reqwest::Client::new();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{addr}", addr = listener.local_addr()?);
+
+    let proxy = move |request: axum::extract::Request| {
+        let (upstream, hooks, client) = (upstream.clone(), hooks.clone(), client.clone());
+        async move {
+            let (request_hook, response_hook) = hooks.as_ref();
+            let (parts, body) = request.into_parts();
+            let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+            let body = request_hook(parts.uri.path(), body.to_vec());
+            let (builder, body) = forward(&client, &upstream, &parts, body).await;
+            let body = response_hook(parts.uri.path(), body);
+            builder.body(axum::body::Body::from(body)).unwrap()
+        }
+    };
+
+    tokio::spawn(async move { axum::serve(listener, axum::Router::new().fallback(proxy)).await });
+    Ok(url)
+}
+
+/// Forwards a request received by a proxy to the given registry.
+///
+/// Returns the start of the proxy's response and the registry's response body.
+async fn forward(
+    client: &reqwest::Client,
+    upstream: &str,
+    parts: &axum::http::request::Parts,
+    body: Vec<u8>,
+) -> (axum::http::response::Builder, Vec<u8>) {
+    let path = parts
+        .uri
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/");
+    let mut forward = client
+        .request(
+            parts.method.as_str().parse().unwrap(),
+            format!("{upstream}{path}"),
+        )
+        .body(body);
+    for (name, value) in &parts.headers {
+        if name != axum::http::header::HOST && name != axum::http::header::CONTENT_LENGTH {
+            forward = forward.header(name.as_str(), value.as_bytes());
+        }
+    }
+
+    // The body is buffered, so the framing of the registry's response is not
+    // forwarded
+    let response = forward.send().await.unwrap();
+    let mut builder = axum::http::Response::builder().status(response.status().as_u16());
+    for (name, value) in response.headers() {
+        if name != reqwest::header::CONTENT_LENGTH && name != reqwest::header::TRANSFER_ENCODING {
+            builder = builder.header(name.as_str(), value.as_bytes());
+        }
+    }
+
+    (builder, response.bytes().await.unwrap().to_vec())
+}
+
+/// Starts a proxy to the given registry that fails the first `failures`
+/// requests of each method and path with `502 Bad Gateway`, as a load
+/// balancer in front of a restarting registry might.
+///
+/// Returns the URL of the proxy and the number of requests it failed.
+async fn start_flaky_proxy(upstream: &str, failures: usize) -> Result<(String, Arc<AtomicUsize>)> {
+    let upstream = upstream.to_string();
+    let client = reqwest::Client::new();
+    let attempts = Arc::new(std::sync::Mutex::new(HashMap::<String, usize>::new()));
+    let failed = Arc::new(AtomicUsize::new(0));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{addr}", addr = listener.local_addr()?);
+
+    let proxy = {
+        let failed = failed.clone();
+        move |request: axum::extract::Request| {
+            let (upstream, client) = (upstream.clone(), client.clone());
+            let (attempts, failed) = (attempts.clone(), failed.clone());
+            async move {
+                let (parts, body) = request.into_parts();
+                let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+
+                let key = format!("{} {}", parts.method, parts.uri.path());
+                let attempt = {
+                    let mut attempts = attempts.lock().unwrap();
+                    let attempt = attempts.entry(key).or_default();
+                    *attempt += 1;
+                    *attempt
+                };
+                if attempt <= failures {
+                    failed.fetch_add(1, Ordering::SeqCst);
+                    return axum::http::Response::builder()
+                        .status(502)
+                        .header("content-type", "text/html")
+                        .body(axum::body::Body::from("<html>502 Bad Gateway</html>"))
+                        .unwrap();
+                }
+
+                let (builder, body) = forward(&client, &upstream, &parts, body.to_vec()).await;
+                builder.body(axum::body::Body::from(body)).unwrap()
+            }
+        }
+    };
+
+    tokio::spawn(async move { axum::serve(listener, axum::Router::new().fallback(proxy)).await });
+    Ok((url, failed))
+}
+
+/// Starts a proxy to the given registry that re-signs the registry's latest
+/// checkpoint with the given key once `rogue` is set.
+///
+/// Returns the URL of the proxy.
+async fn start_rogue_proxy(
+    upstream: &str,
+    key: PrivateKey,
+    rogue: Arc<AtomicBool>,
+) -> Result<String> {
+    start_proxy(
+        upstream,
+        Box::new(|_, body| body),
+        Box::new(move |path, body| {
+            if !rogue.load(Ordering::SeqCst) || path != "/v1/fetch/checkpoint" {
+                return body;
+            }
+
+            let checkpoint: SerdeEnvelope<TimestampedCheckpoint> =
+                serde_json::from_slice(&body).unwrap();
+            let forged = SerdeEnvelope::signed_contents(&key, checkpoint.into_contents());
+            serde_json::to_vec(&forged.unwrap()).unwrap()
+        }),
+    )
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_uploads_only_missing_content() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    let key = registry.publisher_key();
+
+    // Count the content uploads made through a proxy
+    let uploads = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let mut config = registry.client_config();
+    config.home_url = Some({
+        let uploads = uploads.clone();
+        start_proxy(
+            registry.url(),
+            Box::new(move |path, body| {
+                if path.starts_with("/v1/package/") && path.contains("/content/") {
+                    uploads.lock().unwrap().push(body.len());
+                }
+                body
+            }),
+            Box::new(|_, body| body),
+        )
+        .await?
+    });
+    let uploaded = || uploads.lock().unwrap().clone();
+
+    let client = client_with_config(&config)?;
+    let store = |wat: &'static str| {
+        let client = &client;
+        async move {
+            let bytes = wat::parse_str(wat)?;
+            let len = bytes.len();
+            let digest = client
+                .content()
+                .store_content(
+                    Box::pin(futures::stream::once(async move { Ok(bytes.into()) })),
+                    None,
+                )
+                .await?;
+            anyhow::Ok((digest, len))
+        }
+    };
+    let publish = |name: &'static str, digest: AnyHash| {
+        let client = &client;
+        async move {
+            let name = PackageName::new(name)?;
+            let record_id = client
+                .publish_with_info(
+                    key,
+                    PublishInfo {
+                        name: name.clone(),
+                        head: None,
+                        entries: vec![
+                            PublishEntry::Init,
+                            PublishEntry::Release {
+                                version: "1.0.0".parse()?,
+                                content: digest,
+                                artifacts: Default::default(),
+                                metadata: None,
+                            },
+                        ],
+                    },
+                )
+                .await?;
+            let result = client
+                .wait_for_publish(&name, &record_id, Duration::from_millis(25))
+                .await?;
+            assert!(
+                matches!(result, PublishResult::Published { .. }),
+                "{result:?}"
+            );
+            anyhow::Ok(())
+        }
+    };
+
+    // The first release of the content uploads it
+    let (digest, len) = store("(component)").await?;
+    publish("test:first", digest.clone()).await?;
+    let first = uploaded();
+    assert_eq!(first.iter().sum::<usize>(), len);
+
+    // Releasing identical content in another package uploads nothing
+    publish("test:second", digest).await?;
+    assert_eq!(uploaded(), first);
+
+    // Content uploaded for another package after a record was published is
+    // no longer reported as missing for that record
+    let (digest, other_len) = store("(component (core module))").await?;
+    let name = PackageName::new("test:third")?;
+    let log_id = LogId::package_log::<Sha256>(&name);
+    let record = ProtoEnvelope::signed_contents(
+        key,
+        package::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: key.public_key(),
+                },
+                PackageEntry::Release {
+                    version: "1.0.0".parse()?,
+                    content: digest.clone(),
+                    artifacts: Default::default(),
+                    metadata: None,
+                },
+            ],
+        },
+    )?;
+    let api = registry.api_client()?;
+    let record = api
+        .publish_package_record(
+            &log_id,
+            PublishRecordRequest {
+                package_name: Cow::Borrowed(&name),
+                record: Cow::Owned(record.into()),
+                content_sources: Default::default(),
+            },
+        )
+        .await?;
+    let missing = api.get_missing_content(&log_id, &record.record_id).await?;
+    assert_eq!(
+        missing.missing_content.keys().collect::<Vec<_>>(),
+        [&digest]
+    );
+
+    publish("test:fourth", digest).await?;
+    assert_eq!(uploaded()[first.len()..].iter().sum::<usize>(), other_len);
+
+    let missing = api.get_missing_content(&log_id, &record.record_id).await?;
+    assert!(missing.missing_content.is_empty());
+    let result = client
+        .wait_for_publish(&name, &record.record_id, Duration::from_millis(25))
+        .await?;
+    assert!(
+        matches!(result, PublishResult::Published { .. }),
+        "{result:?}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_verifies_package_releases() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    let content = wat::parse_str("(component)")?;
+    let published = registry
+        .publish_package("test:verified", "1.0.0", content.clone())
+        .await?;
+
+    // The proxy asks the registry to prove the log heads in the wrong order
+    let forge = Arc::new(AtomicBool::new(false));
+    let mut config = registry.client_config();
+    config.home_url = Some({
+        let forge = forge.clone();
+        start_proxy(
+            registry.url(),
+            Box::new(move |path, body| {
+                if path != "/v1/proof/inclusion" || !forge.load(Ordering::SeqCst) {
+                    return body;
+                }
+
+                let mut request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                request["leafs"].as_array_mut().unwrap().reverse();
+                serde_json::to_vec(&request).unwrap()
+            }),
+            Box::new(|_, body| body),
+        )
+        .await?
+    });
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("verified.wasm");
+    let verify = || {
+        let config = config.clone();
+        let path = path.to_str().unwrap().to_string();
+        async move {
+            let output = warg(
+                &config,
+                &[
+                    "verify",
+                    "test:verified",
+                    "1.0.0",
+                    &path,
+                    "--format",
+                    "json",
+                ],
+            )
+            .await?;
+            let report: serde_json::Value = serde_json::from_slice(&output.stdout)
+                .with_context(|| String::from_utf8_lossy(&output.stderr).to_string())?;
+            let checks = report["checks"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|check| {
+                    (
+                        check["check"].as_str().unwrap().to_string(),
+                        check["state"].as_str().unwrap().to_string(),
+                        check["error"].as_str().unwrap_or_default().to_string(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            anyhow::Ok((output.status.success(), report, checks))
+        }
+    };
+    let outcome = |checks: &[(String, String, String)], name: &str| {
+        checks
+            .iter()
+            .find(|(check, ..)| check == name)
+            .map(|(_, state, error)| (state.clone(), error.clone()))
+            .unwrap()
+    };
+
+    // The released content passes every check
+    fs::write(&path, &content)?;
+    let (success, report, checks) = verify().await?;
+    assert!(success, "{report}");
+    assert_eq!(report["passed"], true);
+    assert_eq!(report["digest"], published.digest.to_string());
+    assert_eq!(checks.len(), 4);
+    assert!(checks.iter().all(|(_, state, _)| state == "passed"));
+
+    // A single changed byte fails the release content check
+    let mut tampered = content.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    fs::write(&path, &tampered)?;
+    let (success, report, checks) = verify().await?;
+    assert!(!success);
+    assert_eq!(report["passed"], false);
+    let (state, error) = outcome(&checks, "releaseContent");
+    assert_eq!(state, "failed");
+    assert!(
+        error.contains(&format!(
+            "was released with content `{digest}`",
+            digest = published.digest
+        )),
+        "{error}"
+    );
+    assert_eq!(outcome(&checks, "inclusionProof").0, "passed");
+
+    // A forged proof fails the inclusion proof check
+    fs::write(&path, &content)?;
+    forge.store(true, Ordering::SeqCst);
+    let (success, _, checks) = verify().await?;
+    assert!(!success);
+    assert_eq!(outcome(&checks, "releaseContent").0, "passed");
+    let (state, error) = outcome(&checks, "inclusionProof");
+    assert_eq!(state, "failed");
+    assert!(error.contains("failed to verify a proof"), "{error}");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_refuses_updates_with_tampered_proofs() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:tampered";
+
+    let registry = TestRegistry::start().await?;
+    let bytes = wat::parse_str("(component)")?;
+    registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", bytes.clone())
+        .await?;
+
+    // The proxy asks the registry to prove something other than what the
+    // client requested, so the registry's proofs do not match the request
+    let tamper_inclusion = Arc::new(AtomicBool::new(false));
+    let tamper_consistency = Arc::new(AtomicBool::new(false));
+    let (inclusion, consistency) = (tamper_inclusion.clone(), tamper_consistency.clone());
+    let mut config = registry.client_config();
+    config.home_url = Some(
+        start_proxy(
+            registry.url(),
+            Box::new(move |path, body| {
+                let mut request: serde_json::Value = match path {
+                    "/v1/proof/inclusion" if inclusion.load(Ordering::SeqCst) => {
+                        serde_json::from_slice(&body).unwrap()
+                    }
+                    "/v1/proof/consistency" if consistency.load(Ordering::SeqCst) => {
+                        serde_json::from_slice(&body).unwrap()
+                    }
+                    _ => return body,
+                };
+
+                if let Some(leafs) = request.get_mut("leafs") {
+                    leafs.as_array_mut().unwrap().truncate(1);
+                }
+                if let Some(from) = request.get_mut("from") {
+                    *from = (from.as_u64().unwrap() - 1).into();
+                }
+                serde_json::to_vec(&request).unwrap()
+            }),
+            Box::new(|_, body| body),
+        )
+        .await?,
+    );
+
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let client = client_with_config(&config)?;
+    client.upsert([&name]).await?;
+    let head = client
+        .registry()
+        .load_checkpoint(client.get_warg_registry())
+        .await?
+        .context("expected a checkpoint in client storage")?;
+
+    registry
+        .publish_simple(PACKAGE_NAME, "2.0.0", bytes)
+        .await?;
+
+    for (tamper, expected) in [
+        (
+            &tamper_inclusion,
+            "expected 2 log inclusion proof(s) but found 1",
+        ),
+        (&tamper_consistency, "failed to prove inclusion: found root"),
+    ] {
+        tamper.store(true, Ordering::SeqCst);
+        match client.update().await {
+            Err(ClientError::ProofVerificationFailed(e)) => {
+                assert!(e.to_string().contains(expected), "{e}")
+            }
+            res => panic!("expected proof verification to fail: {res:?}"),
+        }
+        tamper.store(false, Ordering::SeqCst);
+
+        // Nothing was stored
+        let stored = client
+            .registry()
+            .load_checkpoint(client.get_warg_registry())
+            .await?
+            .context("expected a checkpoint in client storage")?;
+        assert_eq!(stored.as_ref(), head.as_ref());
+        let package = client
+            .registry()
+            .load_package(client.get_warg_registry(), &name)
+            .await?
+            .context("expected the package in client storage")?;
+        assert_eq!(package.checkpoint.as_ref(), Some(&head.as_ref().checkpoint));
+    }
+
+    // Once the proofs are genuine, the update succeeds
+    client.update().await?;
+    assert!(client.download(&name, &"2.0.0".parse()?).await?.is_some());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_rejects_checkpoints_signed_by_unknown_keys() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:untrusted";
+
+    let registry = TestRegistry::start().await?;
+    let bytes = wat::parse_str("(component)")?;
+    registry
+        .publish_simple(PACKAGE_NAME, "1.0.0", bytes.clone())
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    let (rogue_public_key, rogue_key) = generate_p256_pair();
+    let rogue = Arc::new(AtomicBool::new(false));
+    let mut config = registry.client_config();
+    config.home_url = Some(start_rogue_proxy(registry.url(), rogue_key, rogue.clone()).await?);
+
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let client = client_with_config(&config)?;
+    client.upsert([&name]).await?;
+    let checkpoint = client
+        .registry()
+        .load_checkpoint(client.get_warg_registry())
+        .await?
+        .context("expected a checkpoint in client storage")?;
+
+    registry
+        .publish_simple(PACKAGE_NAME, "2.0.0", bytes)
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    rogue.store(true, Ordering::SeqCst);
+    match client.update().await {
+        Err(ClientError::UntrustedCheckpoint { key_id }) => {
+            assert_eq!(key_id, rogue_public_key.fingerprint())
+        }
+        res => panic!("expected the checkpoint to be untrusted: {res:?}"),
+    }
+
+    // The client storage is left as it was before the update
+    let stored = client
+        .registry()
+        .load_checkpoint(client.get_warg_registry())
+        .await?
+        .context("expected a checkpoint in client storage")?;
+    assert_eq!(stored.as_ref(), checkpoint.as_ref());
+    let package = client
+        .registry()
+        .load_package(client.get_warg_registry(), &name)
+        .await?
+        .context("expected the package in client storage")?;
+    assert_eq!(
+        package
+            .state
+            .releases()
+            .map(|r| r.version.to_string())
+            .collect::<Vec<_>>(),
+        ["1.0.0"]
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn cli_resets_state_and_cleans_unreferenced_content() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    let kept_content = wat::parse_str("(component)")?;
+    let dropped_content = wat::parse_str("(component (core module))")?;
+    let kept = registry
+        .publish_simple("test:kept", "1.0.0", kept_content)
+        .await?;
+    let dropped = registry
+        .publish_simple("test:dropped", "1.0.0", dropped_content.clone())
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    let config = registry.client_config();
+    for name in ["test:kept", "test:dropped"] {
+        run_warg(&config, &["download", name]).await?;
+    }
+
+    // The client locks its storage, so each check opens its own client
+    let packages = || async {
+        let client = client_with_config(&config)?;
+        anyhow::Ok(client.registry().load_packages().await?.len())
+    };
+    let has_content = |digest: &AnyHash| {
+        let client = client_with_config(&config)?;
+        anyhow::Ok(client.content().content_location(digest).is_some())
+    };
+
+    // A dry run lists the state without removing it
+    let stdout = String::from_utf8(run_warg(&config, &["reset", "--dry-run"]).await?)?;
+    assert!(stdout.contains("package log `test:kept`"), "{stdout}");
+    assert!(stdout.contains("checkpoint"), "{stdout}");
+    assert_eq!(packages().await?, 2);
+
+    // Resetting keeps content, and fetching rebuilds the state
+    run_warg(&config, &["reset", "--yes"]).await?;
+    assert!(client_with_config(&config)?
+        .registry_state(false)
+        .await?
+        .is_empty());
+    assert!(has_content(&dropped)?);
+    run_warg(&config, &["download", "test:kept"]).await?;
+    assert_eq!(packages().await?, 1);
+
+    // Content of releases no longer in storage is pruned
+    let stdout = String::from_utf8(run_warg(&config, &["clean", "--content", "--dry-run"]).await?)?;
+    assert!(stdout.contains(&dropped.to_string()), "{stdout}");
+    assert!(!stdout.contains(&kept.to_string()), "{stdout}");
+    assert!(has_content(&dropped)?);
+
+    let stdout = String::from_utf8(run_warg(&config, &["clean", "--content", "--yes"]).await?)?;
+    assert!(
+        stdout.contains(&format!(
+            "reclaimed {len} bytes",
+            len = dropped_content.len()
+        )),
+        "{stdout}"
+    );
+    assert!(!has_content(&dropped)?);
+    assert!(has_content(&kept)?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_retries_transient_failures() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+
+    // Every request through the proxy fails twice before reaching the registry
+    let (url, failed) = start_flaky_proxy(registry.url(), 2).await?;
+    let mut config = registry.client_config();
+    config.home_url = Some(url);
+    config.retry = Some(RetryPolicy::default().with_base_delay(Duration::from_millis(10)));
+
+    let client = client_with_config(&config)?;
+    let bytes = wat::parse_str("(component)")?;
+    let digest = client
+        .content()
+        .store_content(
+            Box::pin(futures::stream::once(async move { Ok(bytes.into()) })),
+            None,
+        )
+        .await?;
+
+    let name = PackageName::new("test:retried")?;
+    let record_id = client
+        .publish_with_info(
+            registry.publisher_key(),
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![
+                    PublishEntry::Init,
+                    PublishEntry::Release {
+                        version: "1.0.0".parse()?,
+                        content: digest.clone(),
+                        artifacts: Default::default(),
+                        metadata: None,
+                    },
+                ],
+            },
+        )
+        .await?;
+    let result = client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(25))
+        .await?;
+    assert!(
+        matches!(result, PublishResult::Published { .. }),
+        "{result:?}"
+    );
+
+    let download = client.download_exact(&name, &"1.0.0".parse()?).await?;
+    assert_eq!(download.digest, digest);
+    assert!(failed.load(Ordering::SeqCst) >= 6);
+    drop(client);
+
+    // A registry that keeps failing is given up on
+    let (url, _) = start_flaky_proxy(registry.url(), usize::MAX).await?;
+    config.home_url = Some(url);
+    config.retry = Some(
+        RetryPolicy::default()
+            .with_max_attempts(3)
+            .with_base_delay(Duration::from_millis(10)),
+    );
+
+    let client = client_with_config(&config)?;
+    let err = client
+        .upsert([&name])
+        .await
+        .expect_err("the registry should be unavailable");
+    assert!(
+        err.to_string().contains("gave up after 3 attempts"),
+        "{err}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_polls_publishes_at_a_fixed_interval_unless_backing_off() -> Result<()> {
+    const INTERVAL: Duration = Duration::from_millis(50);
+    const PROCESSING_CHECKS: usize = 5;
+
+    let registry = TestRegistry::start().await?;
+
+    // The proxy reports records as processing for the first few checks
+    let processing = Arc::new(AtomicUsize::new(0));
+    let checks = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let mut config = registry.client_config();
+    config.home_url = Some({
+        let (processing, checks) = (processing.clone(), checks.clone());
+        start_proxy(
+            registry.url(),
+            Box::new(|_, body| body),
+            Box::new(move |path, body| {
+                let is_check = path
+                    .split_once("/record/")
+                    .is_some_and(|(_, rest)| !rest.is_empty() && !rest.contains('/'));
+                if !is_check {
+                    return body;
+                }
+
+                checks.lock().unwrap().push(std::time::Instant::now());
+                if processing
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                    .is_err()
+                {
+                    return body;
+                }
+
+                let mut record: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                record["state"] = "processing".into();
+                record.as_object_mut().unwrap().remove("registryIndex");
+                serde_json::to_vec(&record).unwrap()
+            }),
+        )
+        .await?
+    });
+    config.retry = Some(RetryPolicy::default().with_jitter(false));
+
+    let client = client_with_config(&config)?;
+    let name = PackageName::new("test:polled")?;
+    let record_id = client
+        .publish_with_info(
+            registry.publisher_key(),
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::Init],
+            },
+        )
+        .await?;
+
+    // Waits for the record while it is reported as processing, returning the
+    // delays between the checks
+    let wait = |client: FileSystemClient| {
+        let (name, record_id) = (name.clone(), record_id.clone());
+        let (processing, checks) = (processing.clone(), checks.clone());
+        async move {
+            checks.lock().unwrap().clear();
+            processing.store(PROCESSING_CHECKS, Ordering::SeqCst);
+            let result = client.wait_for_publish(&name, &record_id, INTERVAL).await?;
+            assert!(result.is_published(), "{result:?}");
+
+            let checks = checks.lock().unwrap();
+            assert_eq!(checks.len(), PROCESSING_CHECKS + 1);
+            anyhow::Ok(checks.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>())
+        }
+    };
+
+    // By default, the record is checked at the given interval
+    let delays = wait(client).await?;
+    assert!(delays.iter().all(|d| *d >= INTERVAL), "{delays:?}");
+    assert!(
+        delays.iter().sum::<Duration>() < INTERVAL * 16,
+        "{delays:?}"
+    );
+
+    // With backoff, the delays double from the given interval
+    let client = client_with_config(&config)?.with_backoff_polling(true);
+    let delays = wait(client).await?;
+    for (i, delay) in delays.iter().enumerate() {
+        assert!(*delay >= INTERVAL * 2u32.pow(i as u32), "{delays:?}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_publishes_release_metadata() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    let config = registry.client_config();
+    let name = PackageName::new("test:described")?;
+    let metadata = package::ReleaseMetadata {
+        description: Some("A package with metadata".to_string()),
+        license: Some("Apache-2.0 WITH LLVM-exception".to_string()),
+        repository: Some("https://github.com/example/described".to_string()),
+        homepage: None,
+        keywords: vec!["example".to_string(), "metadata".to_string()],
+    };
+
+    let client = client_with_config(&config)?;
+    let bytes = wat::parse_str("(component)")?;
+    let digest = client
+        .content()
+        .store_content(
+            Box::pin(futures::stream::once(async move { Ok(bytes.into()) })),
+            None,
+        )
+        .await?;
+    let release = |version: &str, metadata: package::ReleaseMetadata| -> Result<PublishEntry> {
+        Ok(PublishEntry::Release {
+            version: version.parse()?,
+            content: digest.clone(),
+            artifacts: Default::default(),
+            metadata: Some(metadata),
+        })
+    };
+
+    let record_id = client
+        .publish_with_info(
+            registry.publisher_key(),
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::Init, release("1.0.0", metadata.clone())?],
+            },
+        )
+        .await?;
+    assert!(client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(25))
+        .await?
+        .is_published());
+
+    // An invalid license is rejected with the reason
+    let err = client
+        .publish_with_info(
+            registry.publisher_key(),
+            PublishInfo {
+                name: name.clone(),
+                head: Some(record_id),
+                entries: vec![release(
+                    "1.1.0",
+                    package::ReleaseMetadata {
+                        license: Some("Apache 2".to_string()),
+                        ..Default::default()
+                    },
+                )?],
+            },
+        )
+        .await
+        .expect_err("the license should be rejected");
+    assert!(
+        err.to_string()
+            .contains("license `Apache 2` is not a valid SPDX expression"),
+        "{err}"
+    );
+    registry.advance_checkpoint().await?;
+
+    // The metadata is returned by the record history and package listing
+    let api = registry.api_client()?;
+    let history = api.package_records(&name, &Default::default()).await?;
+    assert!(matches!(
+        &history.records[0].entries[..],
+        [
+            PackageRecordEntry::Init { .. },
+            PackageRecordEntry::Release { metadata: Some(m), .. }
+        ] if m == &metadata
+    ));
+
+    let packages = api.list_packages(&ListPackagesQuery::default()).await?;
+    let summary = packages
+        .packages
+        .iter()
+        .find(|p| p.name == name)
+        .context("package was not listed")?;
+    assert_eq!(summary.metadata.as_ref(), Some(&metadata));
+
+    // The metadata is displayed with the package
+    client.upsert([&name]).await?;
+    let info = client
+        .registry()
+        .load_package(client.get_warg_registry(), &name)
+        .await?
+        .context("package should be in client storage")?;
+    assert_eq!(
+        info.state.releases().next().unwrap().metadata,
+        Some(metadata)
+    );
+    drop(client);
+
+    let output = String::from_utf8(run_warg(&config, &["info", name.as_ref()]).await?)?;
+    assert!(
+        output.contains("description: A package with metadata"),
+        "{output}"
+    );
+    assert!(
+        output.contains("license: Apache-2.0 WITH LLVM-exception"),
+        "{output}"
+    );
+    assert!(output.contains("keywords: example, metadata"), "{output}");
+
+    Ok(())
+}
+
+/// A transport for the client core that sends requests with `reqwest`.
+struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpTransport {
+    async fn post<T: serde::Serialize + ?Sized, R: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &T,
+    ) -> Result<R> {
+        Ok(self
+            .client
+            .post(format!("{url}/{path}", url = self.url))
+            .json(body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+}
+
+#[async_trait::async_trait]
+impl core::Transport for HttpTransport {
+    async fn latest_checkpoint(&self) -> Result<SerdeEnvelope<TimestampedCheckpoint>> {
+        Ok(self
+            .client
+            .get(format!(
+                "{url}/{path}",
+                url = self.url,
+                path = paths::fetch_checkpoint()
+            ))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    async fn fetch_logs(&self, request: FetchLogsRequest<'_>) -> Result<FetchLogsResponse> {
+        self.post(paths::fetch_logs(), &request).await
+    }
+
+    async fn prove_inclusion(&self, request: InclusionRequest) -> Result<InclusionResponse> {
+        self.post(paths::prove_inclusion(), &request).await
+    }
+
+    async fn prove_consistency(&self, request: ConsistencyRequest) -> Result<ConsistencyResponse> {
+        self.post(paths::prove_consistency(), &request).await
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn core_verifier_verifies_packages() -> Result<()> {
+    const PACKAGE_NAME: &str = "test:core";
+
+    let registry = TestRegistry::start().await?;
+    registry
+        .publish_simple(PACKAGE_NAME, "0.1.0", wat::parse_str("(component)")?)
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    let verifier = core::Verifier::new(
+        HttpTransport {
+            client: reqwest::Client::new(),
+            url: registry.url().trim_end_matches('/').to_string(),
+        },
+        core::MemoryStateStorage::default(),
+    );
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let info = verifier
+        .update(std::slice::from_ref(&name))
+        .await?
+        .remove(0);
+    let release = core::resolve_release(&info.state, &VersionReq::STAR, false, false)
+        .context("expected a release")?;
+    assert_eq!(release.version, "0.1.0".parse()?);
+
+    // Updating to a later checkpoint proves its consistency with the first
+    registry
+        .publish_simple(PACKAGE_NAME, "0.2.0", wat::parse_str("(component)")?)
+        .await?;
+    let checkpoint = registry.advance_checkpoint().await?;
+    let info = verifier
+        .update(std::slice::from_ref(&name))
+        .await?
+        .remove(0);
+    assert_eq!(info.checkpoint, Some(checkpoint));
+    let release = core::resolve_release(&info.state, &VersionReq::STAR, false, false)
+        .context("expected a release")?;
+    assert_eq!(release.version, "0.2.0".parse()?);
+
+    // Nothing is stored for a package that does not exist
+    let missing = PackageName::new("test:missing")?;
+    assert!(matches!(
+        verifier.update(std::slice::from_ref(&missing)).await,
+        Err(core::CoreError::Transport(_))
+    ));
+    assert!(
+        core::StateStorage::load_package(verifier.storage(), &missing)
+            .await?
+            .is_none()
+    );
+
+    Ok(())
+}
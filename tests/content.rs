@@ -0,0 +1,175 @@
+use anyhow::{Context, Result};
+use std::{borrow::Cow, time::SystemTime};
+use warg_api::v1::{
+    content::ContentSource,
+    package::{
+        PackageError, PackageRecord as PackageRecordResponse, PublishRecordRequest, UploadEndpoint,
+    },
+};
+use warg_client::api;
+use warg_crypto::hash::{AnyHash, Hash, HashAlgorithm, Sha256};
+use warg_protocol::{
+    package::{self, PackageEntry, PACKAGE_RECORD_VERSION},
+    registry::{LogId, PackageName},
+    ProtoEnvelope,
+};
+use warg_test_fixture::TestRegistry;
+
+async fn publish_with_source(
+    registry: &TestRegistry,
+    digest: &AnyHash,
+    url: &str,
+) -> Result<PackageRecordResponse, api::ClientError> {
+    let name = PackageName::new("test:sources").unwrap();
+    let key = registry.publisher_key();
+    let record = ProtoEnvelope::signed_contents(
+        key,
+        package::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: key.public_key(),
+                },
+                PackageEntry::Release {
+                    version: "1.0.0".parse().unwrap(),
+                    content: digest.clone(),
+                    artifacts: Default::default(),
+                    metadata: None,
+                },
+            ],
+        },
+    )
+    .unwrap();
+
+    registry
+        .api_client()
+        .unwrap()
+        .publish_package_record(
+            &LogId::package_log::<Sha256>(&name),
+            PublishRecordRequest {
+                package_name: Cow::Borrowed(&name),
+                record: Cow::Owned(record.into()),
+                content_sources: [(
+                    digest.clone(),
+                    vec![ContentSource::HttpGet {
+                        url: url.to_string(),
+                        accept_ranges: false,
+                        size: Some(7),
+                    }],
+                )]
+                .into_iter()
+                .collect(),
+            },
+        )
+        .await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_validates_content_sources() -> Result<()> {
+    let registry = TestRegistry::start_with_config(|config| {
+        config.with_content_source_host("cdn.example.com")
+    })
+    .await?;
+    let digest = AnyHash::from(Hash::<Sha256>::of("sources"));
+
+    match publish_with_source(&registry, &digest, "https://elsewhere.example.com/sources").await {
+        Err(api::ClientError::Package(PackageError::Rejection(reason))) => {
+            assert!(
+                reason.contains("is not served from an allowed host"),
+                "unexpected reason: {reason}"
+            );
+        }
+        Err(e) => panic!("unexpected error: {e}"),
+        Ok(_) => panic!("expected the content source to be rejected"),
+    }
+
+    // Sources from an allowed host are accepted, but the content must still be uploaded
+    let record = publish_with_source(&registry, &digest, "https://cdn.example.com/sources").await?;
+    assert!(record.missing_content().any(|(d, _)| *d == digest));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_rejects_content_not_matching_its_digest() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    let api = registry.api_client()?;
+    let bytes = wat::parse_str("(component)")?;
+    let tampered = wat::parse_str("(component (core module))")?;
+
+    for algorithm in [HashAlgorithm::Sha256, HashAlgorithm::Sha512] {
+        let name = PackageName::new(format!("test:tampered-{algorithm}"))?;
+        let digest = algorithm.digest(&bytes);
+        let key = registry.publisher_key();
+        let record = ProtoEnvelope::signed_contents(
+            key,
+            package::PackageRecord {
+                prev: None,
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: vec![
+                    PackageEntry::Init {
+                        hash_algorithm: HashAlgorithm::Sha256,
+                        key: key.public_key(),
+                    },
+                    PackageEntry::Release {
+                        version: "1.0.0".parse()?,
+                        content: digest.clone(),
+                        artifacts: Default::default(),
+                        metadata: None,
+                    },
+                ],
+            },
+        )?;
+
+        let log_id = LogId::package_log::<Sha256>(&name);
+        let record = api
+            .publish_package_record(
+                &log_id,
+                PublishRecordRequest {
+                    package_name: Cow::Borrowed(&name),
+                    record: Cow::Owned(record.into()),
+                    content_sources: Default::default(),
+                },
+            )
+            .await?;
+        let UploadEndpoint::Http {
+            method,
+            url,
+            headers,
+        } = &record
+            .missing_content()
+            .find(|(d, _)| **d == digest)
+            .context("expected the content to be missing")?
+            .1
+            .upload[0];
+
+        match api
+            .upload_content(method, url, headers, tampered.clone())
+            .await
+        {
+            Err(api::ClientError::Package(e)) => {
+                assert_eq!(e.status(), 422);
+                assert!(
+                    e.to_string()
+                        .contains(&format!("does not match expected digest `{digest}`")),
+                    "{e}"
+                );
+            }
+            res => panic!("expected the content to be unprocessable, got {res:?}"),
+        }
+
+        // The record still needs its content, which may then be uploaded
+        let record = api.get_package_record(&log_id, &record.record_id).await?;
+        assert!(record.missing_content().any(|(d, _)| *d == digest));
+        api.upload_content(method, url, headers, bytes.clone())
+            .await?;
+        let record = api.get_package_record(&log_id, &record.record_id).await?;
+        assert!(record.missing_content().next().is_none());
+    }
+
+    Ok(())
+}
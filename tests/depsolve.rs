@@ -1,18 +1,31 @@
 use self::support::*;
 use anyhow::{Context, Result};
-use std::time::Duration;
+use jsonschema::Resource;
+use serde_json::{json, Value};
+use std::{
+    path::Path,
+    time::{Duration, SystemTime},
+};
 use warg_client::{
+    lockfile::LockFile,
+    sbom::{self, SbomFormat},
     storage::{
         ContentStorage, FileSystemContentStorage, FileSystemNamespaceMapStorage,
-        FileSystemRegistryStorage, PublishEntry, PublishInfo, RegistryStorage,
+        LocalRegistryStorage, PublishEntry, PublishInfo, RegistryStorage,
     },
     Client,
 };
 use warg_crypto::signing::PrivateKey;
-use warg_protocol::registry::{PackageName, RecordId};
+use warg_protocol::{
+    package::ReleaseMetadata,
+    registry::{PackageName, RecordId},
+};
 
 pub mod support;
 
+/// The license the `test:meet` release is published with.
+const LICENSE: &str = "Apache-2.0 OR MIT";
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
 async fn depsolve() -> Result<()> {
     let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
@@ -25,57 +38,65 @@ async fn depsolve() -> Result<()> {
         &signing_key,
         "test:add",
         "tests/components/add.wat",
+        None,
     )
     .await?;
-    client
+    assert!(client
         .wait_for_publish(
             &PackageName::new("test:add")?,
             &head,
             Duration::from_millis(100),
         )
-        .await?;
+        .await?
+        .is_published());
     head = publish_package(
         &client,
         &signing_key,
         "test:five",
         "tests/components/five.wat",
+        None,
     )
     .await?;
-    client
+    assert!(client
         .wait_for_publish(
             &PackageName::new("test:five")?,
             &head,
             Duration::from_millis(100),
         )
-        .await?;
+        .await?
+        .is_published());
     head = publish_package(
         &client,
         &signing_key,
         "test:inc",
         "tests/components/inc.wat",
+        None,
     )
     .await?;
-    client
+    assert!(client
         .wait_for_publish(
             &PackageName::new("test:inc")?,
             &head,
             Duration::from_millis(100),
         )
-        .await?;
+        .await?
+        .is_published());
     head = publish_package(
         &client,
         &signing_key,
         "test:meet",
         "tests/components/meet.wat",
+        Some(LICENSE),
     )
     .await?;
-    client
+    assert!(client
         .wait_for_publish(
             &PackageName::new("test:meet")?,
             &head,
             Duration::from_millis(100),
         )
-        .await?;
+        .await?
+        .is_published());
 
     client.update().await?;
     client
@@ -93,14 +114,38 @@ async fn depsolve() -> Result<()> {
         .await?
         .context("package does not exist in client storage")?;
 
+    // Locking and bundling write their output to the current directory
+    let components = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/components");
+    let output = tempfile::tempdir()?;
+    std::env::set_current_dir(output.path())?;
+
     let locked_bytes = client.lock_component(&info).await?;
-    let expected_locked = wat::parse_file("tests/components/meet_locked.wat")?;
+    let expected_locked = wat::parse_file(components.join("meet_locked.wat"))?;
     assert_eq!(
         wasmprinter::print_bytes(&locked_bytes)?,
         wasmprinter::print_bytes(expected_locked)?
     );
+
+    let lock = client.lock_file(&info).await?;
+    let locked: Vec<_> = lock
+        .packages
+        .iter()
+        .map(|p| (p.name.to_string(), p.version.to_string()))
+        .collect();
+    assert_eq!(locked.len(), 4);
+    for name in ["test:add", "test:five", "test:inc", "test:meet"] {
+        assert!(locked.contains(&(name.to_string(), "1.0.0".to_string())));
+    }
+    assert!(lock.packages.iter().all(|p| p.checkpoint.is_some()));
+    for package in &lock.packages {
+        let expected = (package.name.as_ref() == "test:meet").then_some(LICENSE);
+        assert_eq!(package.license.as_deref(), expected, "{}", package.name);
+    }
+    validate_cyclonedx(&lock)?;
+    validate_spdx(&lock)?;
+
     let bundled_bytes = client.bundle_component(&info).await?;
-    let expected_bundled = wat::parse_file("tests/components/meet_bundled.wat")?;
+    let expected_bundled = wat::parse_file(components.join("meet_bundled.wat"))?;
     assert_eq!(
         wasmprinter::print_bytes(bundled_bytes)?,
         wasmprinter::print_bytes(expected_bundled)?
@@ -108,15 +153,92 @@ async fn depsolve() -> Result<()> {
     Ok(())
 }
 
+/// Validates a document against one of the JSON schemas in `tests/sbom`.
+///
+/// The CycloneDX schema refers to separate schemas for SPDX license
+/// identifiers and JSON signatures; SBOMs name their licenses and are not
+/// signed, so those are accepted as is rather than fetched.
+fn validate_schema(schema: &str, document: &Value) -> Result<()> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/sbom")
+        .join(schema);
+    let schema: Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    let validator = jsonschema::options()
+        .with_resource(
+            "http://cyclonedx.org/schema/spdx.schema.json",
+            Resource::from_contents(json!({}))?,
+        )
+        .with_resource(
+            "http://cyclonedx.org/schema/jsf-0.82.schema.json",
+            Resource::from_contents(json!({ "definitions": { "signature": {} } }))?,
+        )
+        .build(&schema)
+        .map_err(|e| anyhow::anyhow!("invalid schema `{path}`: {e}", path = path.display()))?;
+    let errors: Vec<_> = validator
+        .iter_errors(document)
+        .map(|e| format!("{path}: {e}", path = e.instance_path))
+        .collect();
+    assert!(errors.is_empty(), "{errors:#?}");
+    Ok(())
+}
+
+/// Validates a CycloneDX SBOM of the lock file against the CycloneDX 1.5 JSON schema.
+fn validate_cyclonedx(lock: &LockFile) -> Result<()> {
+    let bom: Value = serde_json::from_str(&sbom::generate(
+        lock,
+        SbomFormat::CycloneDx,
+        "meet",
+        SystemTime::UNIX_EPOCH,
+    )?)?;
+    validate_schema("bom-1.5.schema.json", &bom)?;
+
+    let components = bom["components"]
+        .as_array()
+        .context("expected components")?;
+    assert_eq!(components.len(), lock.packages.len());
+    for (component, package) in components.iter().zip(&lock.packages) {
+        assert_eq!(component["name"], package.name.to_string());
+        assert_eq!(component["version"], package.version.to_string());
+        assert_eq!(component["externalReferences"][0]["url"], package.registry);
+        match &package.license {
+            Some(license) => assert_eq!(component["licenses"][0]["license"]["name"], *license),
+            None => assert!(component.get("licenses").is_none()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates an SPDX SBOM of the lock file against the SPDX 2.3 JSON schema.
+fn validate_spdx(lock: &LockFile) -> Result<()> {
+    let doc: Value = serde_json::from_str(&sbom::generate(
+        lock,
+        SbomFormat::Spdx,
+        "meet",
+        SystemTime::UNIX_EPOCH,
+    )?)?;
+    validate_schema("spdx-schema-2.3.json", &doc)?;
+
+    let packages = doc["packages"].as_array().context("expected packages")?;
+    assert_eq!(packages.len(), lock.packages.len());
+    for (package, locked) in packages.iter().zip(&lock.packages) {
+        assert_eq!(package["name"], locked.name.to_string());
+        assert_eq!(package["versionInfo"], locked.version.to_string());
+        assert_eq!(package["downloadLocation"], locked.registry);
+        let license = locked.license.as_deref().unwrap_or("NOASSERTION");
+        assert_eq!(package["licenseConcluded"], license);
+        assert_eq!(package["licenseDeclared"], license);
+    }
+
+    Ok(())
+}
+
 async fn publish_package(
-    client: &Client<
-        FileSystemRegistryStorage,
-        FileSystemContentStorage,
-        FileSystemNamespaceMapStorage,
-    >,
+    client: &Client<LocalRegistryStorage, FileSystemContentStorage, FileSystemNamespaceMapStorage>,
     signing_key: &PrivateKey,
     name: &str,
     path: &str,
+    license: Option<&str>,
 ) -> Result<RecordId> {
     let comp = wat::parse_file(path)?;
     let name = PackageName::new(name)?;
@@ -137,9 +259,10 @@ async fn publish_package(
             },
         )
         .await?;
-    client
+    assert!(client
         .wait_for_publish(&name.clone(), &head, Duration::from_millis(100))
-        .await?;
+        .await?
+        .is_published());
     head = client
         .publish_with_info(
             signing_key,
@@ -147,8 +270,13 @@ async fn publish_package(
                 name: name.clone(),
                 head: Some(head),
                 entries: vec![PublishEntry::Release {
-                    version: format!("1.0.0").parse().unwrap(),
+                    version: "1.0.0".to_string().parse().unwrap(),
                     content: add_digest.clone(),
+                    artifacts: Default::default(),
+                    metadata: license.map(|license| ReleaseMetadata {
+                        license: Some(license.to_string()),
+                        ..Default::default()
+                    }),
                 }],
             },
         )
@@ -0,0 +1,188 @@
+use anyhow::Result;
+use axum::response::IntoResponse;
+use reqwest::{header, StatusCode};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use url::Url;
+use warg_api::v1::package::PackageError;
+use warg_client::api;
+use warg_crypto::hash::AnyHash;
+use warg_protocol::{registry::PackageName, Version, VersionReq};
+use warg_test_fixture::TestRegistry;
+
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+fn content_url(registry: &TestRegistry, digest: &AnyHash) -> String {
+    format!(
+        "{url}/content/{file_name}",
+        url = registry.url(),
+        file_name = digest.to_string().replace(':', "-")
+    )
+}
+
+/// The content whose first download fails with a transient error.
+#[derive(Default)]
+struct FlakyContent {
+    path: Mutex<Option<String>>,
+    requests: AtomicUsize,
+}
+
+/// Starts a server serving content from the registry, failing the first
+/// request for the flaky content with a `503 Service Unavailable` response.
+fn start_flaky_content_server(
+    listener: tokio::net::TcpListener,
+    registry_url: &str,
+    flaky: Arc<FlakyContent>,
+) {
+    let upstream = registry_url.to_string();
+    let serve = move |uri: axum::http::Uri| {
+        let (upstream, flaky) = (upstream.clone(), flaky.clone());
+        async move {
+            let path = uri.path().to_string();
+            if flaky.path.lock().unwrap().as_deref() == Some(path.as_str())
+                && flaky.requests.fetch_add(1, Ordering::SeqCst) == 0
+            {
+                return axum::http::StatusCode::SERVICE_UNAVAILABLE.into_response();
+            }
+
+            let response = reqwest::get(format!("{upstream}{path}")).await.unwrap();
+            (
+                axum::http::StatusCode::from_u16(response.status().as_u16()).unwrap(),
+                response.bytes().await.unwrap(),
+            )
+                .into_response()
+        }
+    };
+
+    tokio::spawn(async move { axum::serve(listener, axum::Router::new().fallback(serve)).await });
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_downloads_contents_concurrently() -> Result<()> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let content_base_url = Url::parse(&format!("http://{addr}", addr = listener.local_addr()?))?;
+    let registry =
+        TestRegistry::start_with_config(|config| config.with_content_base_url(content_base_url))
+            .await?;
+    let flaky = Arc::new(FlakyContent::default());
+    start_flaky_content_server(listener, registry.url(), flaky.clone());
+
+    let mut contents = Vec::new();
+    let mut digests = Vec::new();
+    for (i, wat) in [
+        "(component)",
+        "(component (core module))",
+        "(component (core module) (core module))",
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let content = wat::parse_str(wat)?;
+        digests.push(
+            registry
+                .publish_simple(&format!("test:concurrent{i}"), "1.0.0", content.clone())
+                .await?,
+        );
+        contents.push(content);
+    }
+
+    *flaky.path.lock().unwrap() = Some(format!(
+        "/content/{file_name}",
+        file_name = digests[1].to_string().replace(':', "-")
+    ));
+
+    // The first attempt to download the flaky content is retried
+    let client = registry.client()?;
+    let results = client.download_contents(digests.clone(), 2).await;
+    assert_eq!(flaky.requests.load(Ordering::SeqCst), 2);
+    assert_eq!(
+        results.keys().collect::<Vec<_>>(),
+        digests.iter().collect::<Vec<_>>()
+    );
+    for ((_, res), content) in results.into_iter().zip(contents) {
+        assert_eq!(std::fs::read(res?)?, content);
+    }
+
+    registry.shutdown().await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_counts_package_downloads() -> Result<()> {
+    let registry =
+        TestRegistry::start_with_config(|config| config.with_download_stats(FLUSH_INTERVAL))
+            .await?;
+
+    let name = PackageName::new("test:counted")?;
+    registry
+        .publish_simple("test:counted", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    let digest = registry
+        .publish_simple(
+            "test:counted",
+            "2.0.0",
+            wat::parse_str("(component (core module))")?,
+        )
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    // Downloading the latest release fetches its content
+    let client = registry.client()?;
+    client.upsert([&name]).await?;
+    let download = client
+        .download(&name, &VersionReq::STAR)
+        .await?
+        .expect("expected a download");
+    assert_eq!(download.version, "2.0.0".parse()?);
+
+    // Resolving a release counts as a download of it
+    let api = registry.api_client()?;
+    api.resolve_package(&name, &"=1.0.0".parse()?, false)
+        .await?;
+
+    // Repeated downloads and range fragments are not counted again
+    let http = reqwest::Client::new();
+    let response = http.get(content_url(&registry, &digest)).send().await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = http
+        .get(content_url(&registry, &digest))
+        .header(header::RANGE, "bytes=4-")
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+
+    tokio::time::sleep(FLUSH_INTERVAL * 5).await;
+
+    let stats = api.package_stats(&name).await?;
+    assert_eq!(stats.name, name);
+    assert_eq!(stats.downloads.total, 2);
+    assert_eq!(stats.downloads.last_day, 2);
+    assert_eq!(stats.downloads.last_month, 2);
+    for version in ["1.0.0", "2.0.0"] {
+        let counts = stats.versions[&version.parse::<Version>()?];
+        assert_eq!(counts.total, 1, "{version}");
+        assert_eq!(counts.last_week, 1, "{version}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_disables_download_stats_by_default() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+    registry
+        .publish_simple("test:uncounted", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+
+    let name = PackageName::new("test:uncounted")?;
+    match registry.api_client()?.package_stats(&name).await {
+        Err(api::ClientError::Package(PackageError::NotSupported(_))) => {}
+        res => panic!("expected download statistics to be unsupported: {res:?}"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,360 @@
+use anyhow::{Context, Result};
+use futures::{future, TryStreamExt};
+use indexmap::IndexMap;
+use std::{
+    borrow::Cow,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use warg_api::v1::{
+    fetch::{
+        FetchError, FetchLogsRequest, FetchLogsResponse, FetchLogsStreamItem,
+        FETCH_LOGS_STREAM_MEDIA_TYPE,
+    },
+    paths,
+    proof::ProofError,
+};
+use warg_client::api;
+use warg_crypto::hash::{AnyHash, Hash, Sha256};
+use warg_protocol::registry::{Checkpoint, LogId, PackageName};
+use warg_test_fixture::TestRegistry;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_fetches_logs_within_total_limit() -> Result<()> {
+    const PACKAGES: usize = 5;
+
+    let registry = TestRegistry::start().await?;
+    let mut packages = IndexMap::new();
+    for i in 0..PACKAGES {
+        let name = PackageName::new(format!("test:batched{i}"))?;
+        registry
+            .publish_simple(name.as_ref(), "1.0.0", wat::parse_str("(component)")?)
+            .await?;
+        packages.insert(LogId::package_log::<Sha256>(&name), None);
+    }
+    let checkpoint = registry.advance_checkpoint().await?;
+
+    let client = registry.api_client()?;
+    let mut operator = None;
+    let mut fetched = IndexMap::<LogId, usize>::new();
+    let mut requests = 0;
+    loop {
+        let response = client
+            .fetch_logs(FetchLogsRequest {
+                log_length: checkpoint.log_length,
+                limit: None,
+                total_limit: Some(2),
+                operator: operator.as_deref().map(Cow::Borrowed),
+                packages: Cow::Borrowed(&packages),
+            })
+            .await?;
+        requests += 1;
+
+        let total =
+            response.operator.len() + response.packages.values().map(Vec::len).sum::<usize>();
+        assert!(total <= 2, "fetched {total} records");
+
+        if let Some(record) = response.operator.last() {
+            operator = Some(record.fetch_token.clone());
+        }
+        for (id, records) in response.packages {
+            if let Some(record) = records.last() {
+                packages[&id] = Some(record.fetch_token.clone());
+            }
+            *fetched.entry(id).or_default() += records.len();
+        }
+
+        if !response.more {
+            break;
+        }
+    }
+
+    // Every package log was fetched, using continuations past the total limit
+    assert!(requests > 1);
+    assert_eq!(fetched.len(), PACKAGES);
+    assert!(fetched.values().all(|count| *count == 1), "{fetched:?}");
+
+    // The total limit is bounded
+    let res = client
+        .fetch_logs(FetchLogsRequest {
+            log_length: checkpoint.log_length,
+            limit: None,
+            total_limit: Some(0),
+            operator: None,
+            packages: Cow::Owned(IndexMap::new()),
+        })
+        .await;
+    assert!(
+        matches!(
+            res,
+            Err(api::ClientError::Fetch(FetchError::Message {
+                status: 400,
+                ..
+            }))
+        ),
+        "{res:?}"
+    );
+
+    Ok(())
+}
+
+/// Fetches the logs of the given packages as a stream, collecting the items.
+async fn stream_logs(
+    client: &api::Client,
+    checkpoint: &Checkpoint,
+    operator: Option<&str>,
+    limit: Option<u16>,
+    packages: &IndexMap<LogId, Option<String>>,
+) -> Result<Vec<FetchLogsStreamItem>, api::ClientError> {
+    client
+        .fetch_logs_stream(FetchLogsRequest {
+            log_length: checkpoint.log_length,
+            limit,
+            total_limit: None,
+            operator: operator.map(Cow::Borrowed),
+            packages: Cow::Borrowed(packages),
+        })
+        .await?
+        .try_collect()
+        .await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_streams_logs_to_clients_accepting_ndjson() -> Result<()> {
+    const RELEASES: usize = 3;
+
+    let registry = TestRegistry::start().await?;
+    let name = PackageName::new("test:streamed")?;
+    for i in 0..RELEASES {
+        registry
+            .publish_simple(
+                name.as_ref(),
+                &format!("1.{i}.0"),
+                wat::parse_str("(component)")?,
+            )
+            .await?;
+    }
+    let checkpoint = registry.advance_checkpoint().await?;
+    let log_id = LogId::package_log::<Sha256>(&name);
+    let packages = IndexMap::from([(log_id.clone(), None)]);
+
+    // Every record is streamed without a limit, followed by the end line
+    let client = registry.api_client()?;
+    let items = stream_logs(&client, &checkpoint, None, None, &packages).await?;
+    let operator = items
+        .iter()
+        .rev()
+        .find_map(|item| match item {
+            FetchLogsStreamItem::Operator { record } => Some(record.fetch_token.clone()),
+            _ => None,
+        })
+        .context("expected an operator record")?;
+    let streamed = items
+        .iter()
+        .filter(
+            |item| matches!(item, FetchLogsStreamItem::Package { log_id: id, .. } if id == &log_id),
+        )
+        .count();
+    assert_eq!(streamed, RELEASES);
+    assert!(
+        matches!(items.last(), Some(FetchLogsStreamItem::End { more: false })),
+        "{items:?}"
+    );
+
+    // A limit is applied to each streamed log
+    let items = stream_logs(&client, &checkpoint, Some(&operator), Some(1), &packages).await?;
+    assert!(
+        matches!(
+            items.as_slice(),
+            [
+                FetchLogsStreamItem::Package { .. },
+                FetchLogsStreamItem::End { more: true }
+            ]
+        ),
+        "{items:?}"
+    );
+
+    // The response is newline-delimited JSON only if the client accepts it
+    let request = FetchLogsRequest {
+        log_length: checkpoint.log_length,
+        limit: None,
+        total_limit: None,
+        operator: None,
+        packages: Cow::Borrowed(&packages),
+    };
+    let url = format!(
+        "{url}/{path}",
+        url = registry.url(),
+        path = paths::fetch_logs()
+    );
+    let response = reqwest::Client::new()
+        .post(&url)
+        .header(reqwest::header::ACCEPT, FETCH_LOGS_STREAM_MEDIA_TYPE)
+        .json(&request)
+        .send()
+        .await?;
+    assert_eq!(
+        response.headers()[reqwest::header::CONTENT_TYPE],
+        FETCH_LOGS_STREAM_MEDIA_TYPE
+    );
+    let lines = response
+        .text()
+        .await?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<FetchLogsStreamItem>, _>>()?;
+    assert_eq!(
+        lines
+            .iter()
+            .filter(|item| matches!(item, FetchLogsStreamItem::Package { .. }))
+            .count(),
+        RELEASES
+    );
+    assert!(
+        matches!(lines.last(), Some(FetchLogsStreamItem::End { more: false })),
+        "{lines:?}"
+    );
+    let response = reqwest::Client::new()
+        .post(&url)
+        .json(&request)
+        .send()
+        .await?;
+    assert_eq!(
+        response.headers()[reqwest::header::CONTENT_TYPE],
+        "application/json"
+    );
+    let response: FetchLogsResponse = response.json().await?;
+    assert_eq!(response.packages[&log_id].len(), RELEASES);
+
+    // An unknown log is not found, before or after records were streamed
+    let unknown = IndexMap::from([(
+        LogId::package_log::<Sha256>(&PackageName::new("test:unknown")?),
+        None,
+    )]);
+    for operator in [Some(operator.as_str()), None] {
+        let res = stream_logs(&client, &checkpoint, operator, None, &unknown).await;
+        assert!(
+            matches!(
+                res,
+                Err(api::ClientError::Fetch(FetchError::LogNotFound(_)))
+            ),
+            "{res:?}"
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn server_proves_consistency_between_checkpoints() -> Result<()> {
+    const PACKAGES: usize = 10;
+
+    let registry = TestRegistry::start().await?;
+    let client = registry.api_client()?;
+    let done = AtomicBool::new(false);
+
+    // Publish packages concurrently so checkpoints are issued while monitoring
+    let publish = async {
+        future::try_join_all((0..PACKAGES).map(|i| {
+            // Each package has distinct content so uploads do not collide
+            let name = format!("test:busy{i}");
+            let wat = format!("(component (import \"f{i}\" (func)))");
+            let registry = &registry;
+            async move {
+                registry
+                    .publish_simple(&name, "1.0.0", wat::parse_str(wat)?)
+                    .await
+            }
+        }))
+        .await?;
+        registry.advance_checkpoint().await?;
+        done.store(true, Ordering::SeqCst);
+        anyhow::Ok(())
+    };
+
+    // Tail the checkpoints, proving each is consistent with the one before it
+    let monitor = async {
+        let mut since = None;
+        let mut previous: Option<Checkpoint> = None;
+        let mut verified = 0;
+        loop {
+            let finished = done.load(Ordering::SeqCst);
+            let response = client.list_checkpoints(since.as_ref(), Some(2)).await?;
+            assert!(response.checkpoints.len() <= 2);
+            for ts_checkpoint in &response.checkpoints {
+                let checkpoint = &ts_checkpoint.as_ref().checkpoint;
+                if let Some(previous) = &previous {
+                    assert!(previous.log_length < checkpoint.log_length);
+                    client
+                        .prove_checkpoint_consistency(&previous.log_root, &checkpoint.log_root)
+                        .await?;
+                    verified += 1;
+                }
+
+                since = Some(AnyHash::of(checkpoint.log_root.algorithm(), checkpoint));
+                previous = Some(checkpoint.clone());
+            }
+
+            if finished && !response.more {
+                return anyhow::Ok((verified, previous));
+            }
+
+            if !response.more {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }
+    };
+
+    let ((), (verified, last)) = tokio::try_join!(publish, monitor)?;
+    assert!(verified > 0);
+
+    // Consistency is proven between any two checkpoints, not only consecutive ones
+    let first = client.list_checkpoints(None, Some(1)).await?;
+    assert!(first.more);
+    let first = &first.checkpoints[0].as_ref().checkpoint;
+    let last = last.context("expected a checkpoint")?;
+    client
+        .prove_checkpoint_consistency(&first.log_root, &last.log_root)
+        .await?;
+
+    // A later checkpoint is not proven consistent with an earlier one
+    let res = client
+        .prove_checkpoint_consistency(&last.log_root, &first.log_root)
+        .await;
+    assert!(
+        matches!(
+            res,
+            Err(api::ClientError::Proof(ProofError::Message {
+                status: 422,
+                ..
+            }))
+        ),
+        "{res:?}"
+    );
+
+    // Unknown checkpoints are not found
+    let unknown = AnyHash::from(Hash::<Sha256>::of("unknown"));
+    let res = client
+        .prove_checkpoint_consistency(&unknown, &last.log_root)
+        .await;
+    assert!(
+        matches!(
+            res,
+            Err(api::ClientError::Proof(ProofError::Message {
+                status: 404,
+                ..
+            }))
+        ),
+        "{res:?}"
+    );
+    let res = client.list_checkpoints(Some(&unknown), None).await;
+    assert!(
+        matches!(
+            res,
+            Err(api::ClientError::Fetch(FetchError::CheckpointIdNotFound(_)))
+        ),
+        "{res:?}"
+    );
+
+    Ok(())
+}
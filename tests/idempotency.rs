@@ -0,0 +1,169 @@
+use anyhow::Result;
+use reqwest::StatusCode;
+use secrecy::SecretString;
+use std::time::Duration;
+use warg_api::v1::{
+    admin::{AuditEntriesResponse, AuditOperation},
+    package::{PackageRecord as PackageRecordResponse, PackageRecordState, PackageRecordsQuery},
+    paths, IDEMPOTENCY_KEY_HEADER_NAME,
+};
+use warg_crypto::signing::generate_p256_pair;
+use warg_protocol::{package::PackageRecord, registry::PackageName, ProtoEnvelope};
+use warg_test_fixture::{init_record, TestRegistry, ADMIN_TOKEN};
+
+async fn start(ttl: Duration) -> Result<TestRegistry> {
+    TestRegistry::start_with_config(|config| {
+        config
+            .with_idempotency_key_ttl(ttl)
+            .with_data_store_audit_log()
+            .with_admin_token(SecretString::new(ADMIN_TOKEN.to_string()))
+    })
+    .await
+}
+
+async fn submit(
+    registry: &TestRegistry,
+    name: &PackageName,
+    record: &ProtoEnvelope<PackageRecord>,
+    key: &str,
+) -> Result<(StatusCode, serde_json::Value)> {
+    let response = registry
+        .submit_record(name, record.clone())
+        .header(IDEMPOTENCY_KEY_HEADER_NAME, key)
+        .send()
+        .await?;
+
+    Ok((response.status(), response.json().await?))
+}
+
+/// Counts the submissions of a package actually performed by the registry.
+async fn submissions(registry: &TestRegistry, name: &PackageName) -> Result<usize> {
+    let response: AuditEntriesResponse = reqwest::Client::new()
+        .get(format!(
+            "{url}/{path}",
+            url = registry.url(),
+            path = paths::admin_audit()
+        ))
+        .bearer_auth(ADMIN_TOKEN)
+        .query(&[("package", name.to_string())])
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    Ok(response
+        .entries
+        .iter()
+        .filter(|e| e.operation == AuditOperation::RecordSubmitted)
+        .count())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_replays_accepted_submissions() -> Result<()> {
+    let registry = start(Duration::from_secs(60)).await?;
+    let name = PackageName::new("test:replayed")?;
+    let body = init_record(registry.publisher_key())?;
+
+    let (status, first) = submit(&registry, &name, &body, "replay-accepted").await?;
+    assert_eq!(status, StatusCode::ACCEPTED, "{first}");
+    let first: PackageRecordResponse = serde_json::from_value(first)?;
+
+    let (status, second) = submit(&registry, &name, &body, "replay-accepted").await?;
+    assert_eq!(status, StatusCode::ACCEPTED, "{second}");
+    let second: PackageRecordResponse = serde_json::from_value(second)?;
+    assert_eq!(first.record_id, second.record_id);
+    assert_eq!(submissions(&registry, &name).await?, 1);
+
+    // Reusing the key for a different request is an error
+    let other = init_record(registry.publisher_key())?;
+    let (status, _) = submit(&registry, &name, &other, "replay-accepted").await?;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(submissions(&registry, &name).await?, 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_replays_rejected_submissions() -> Result<()> {
+    let registry = start(Duration::from_secs(60)).await?;
+    let name = PackageName::new("test:rejected")?;
+
+    // Only the publisher key is authorized to publish to the namespace
+    let (_, unauthorized_key) = generate_p256_pair();
+    let body = init_record(&unauthorized_key)?;
+
+    let (status, first) = submit(&registry, &name, &body, "replay-rejected").await?;
+    assert_eq!(status, StatusCode::FORBIDDEN, "{first}");
+
+    let (status, second) = submit(&registry, &name, &body, "replay-rejected").await?;
+    assert_eq!(status, StatusCode::FORBIDDEN, "{second}");
+    assert_eq!(first, second);
+    assert_eq!(submissions(&registry, &name).await?, 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_expires_idempotency_keys() -> Result<()> {
+    let registry = start(Duration::from_millis(200)).await?;
+    let name = PackageName::new("test:expired")?;
+
+    let (_, unauthorized_key) = generate_p256_pair();
+    let body = init_record(&unauthorized_key)?;
+
+    let (status, _) = submit(&registry, &name, &body, "expiring").await?;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(submissions(&registry, &name).await?, 1);
+
+    // Once the key expires, the submission is performed again
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let (status, _) = submit(&registry, &name, &body, "expiring").await?;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(submissions(&registry, &name).await?, 2);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_accepts_resubmitted_records() -> Result<()> {
+    let registry = start(Duration::from_secs(60)).await?;
+    let name = PackageName::new("test:resubmitted")?;
+    let body = init_record(registry.publisher_key())?;
+
+    let (status, first) = submit(&registry, &name, &body, "resubmit-first").await?;
+    assert_eq!(status, StatusCode::ACCEPTED, "{first}");
+    let first: PackageRecordResponse = serde_json::from_value(first)?;
+    assert!(registry
+        .client()?
+        .wait_for_publish(&name, &first.record_id, Duration::from_millis(25))
+        .await?
+        .is_published());
+
+    // Resubmitting the same record under a new key, as a client would after
+    // its key expired, returns the existing record
+    let (status, second) = submit(&registry, &name, &body, "resubmit-second").await?;
+    assert_eq!(status, StatusCode::OK, "{second}");
+    let (status, third) = submit(&registry, &name, &body, "resubmit-third").await?;
+    assert_eq!(status, StatusCode::OK, "{third}");
+    assert_eq!(second, third);
+
+    let second: PackageRecordResponse = serde_json::from_value(second)?;
+    assert_eq!(second.record_id, first.record_id);
+    assert!(matches!(second.state, PackageRecordState::Published { .. }));
+
+    // The record was only appended to the log once
+    registry.advance_checkpoint().await?;
+    let page = registry
+        .api_client()?
+        .package_records(
+            &name,
+            &PackageRecordsQuery {
+                since: None,
+                limit: None,
+            },
+        )
+        .await?;
+    assert_eq!(page.records.len(), 1);
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use anyhow::Result;
+use clap::Parser;
+use indexmap::IndexSet;
+use keyring::{
+    credential::{Credential, CredentialApi, CredentialBuilderApi},
+    Error,
+};
+use p256::ecdsa::SigningKey;
+use rand_core::OsRng;
+use std::{
+    any::Any,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use warg_cli::commands::KeyExportCommand;
+use warg_client::Config;
+use warg_credentials::keyring::set_signing_key;
+use warg_crypto::signing::{PrivateKey, PublicKey};
+
+type Passwords = Arc<Mutex<HashMap<(String, String), String>>>;
+
+/// A keyring that keeps passwords in memory, shared by all of its entries.
+#[derive(Default)]
+struct MemoryKeyring(Passwords);
+
+struct MemoryCredential {
+    passwords: Passwords,
+    key: (String, String),
+}
+
+impl CredentialApi for MemoryCredential {
+    fn set_password(&self, password: &str) -> keyring::Result<()> {
+        self.passwords
+            .lock()
+            .unwrap()
+            .insert(self.key.clone(), password.to_string());
+        Ok(())
+    }
+
+    fn get_password(&self) -> keyring::Result<String> {
+        self.passwords
+            .lock()
+            .unwrap()
+            .get(&self.key)
+            .cloned()
+            .ok_or(Error::NoEntry)
+    }
+
+    fn delete_password(&self) -> keyring::Result<()> {
+        self.passwords
+            .lock()
+            .unwrap()
+            .remove(&self.key)
+            .map(drop)
+            .ok_or(Error::NoEntry)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl CredentialBuilderApi for MemoryKeyring {
+    fn build(
+        &self,
+        _target: Option<&str>,
+        service: &str,
+        user: &str,
+    ) -> keyring::Result<Box<Credential>> {
+        Ok(Box::new(MemoryCredential {
+            passwords: self.0.clone(),
+            key: (service.to_string(), user.to_string()),
+        }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[derive(Parser)]
+struct ExportArgs {
+    #[clap(flatten)]
+    command: KeyExportCommand,
+}
+
+#[test]
+fn cli_exports_public_key_of_signing_key() -> Result<()> {
+    keyring::set_default_credential_builder(Box::<MemoryKeyring>::default());
+
+    let key: PrivateKey = SigningKey::random(&mut OsRng).into();
+    let mut keys = IndexSet::new();
+    keys.insert("default".to_string());
+    set_signing_key(None, &key, &mut keys, None)?;
+    let config = Config {
+        keys,
+        ..Default::default()
+    };
+
+    // The public key is exported in the registry's text encoding
+    let command = ExportArgs::try_parse_from(["export"])?.command;
+    let exported = command.public_key(&config)?;
+    assert_eq!(exported, key.public_key());
+    let encoded = exported.to_string();
+    assert!(encoded.starts_with("ecdsa-p256:"), "{encoded}");
+    assert_eq!(encoded.parse::<PublicKey>()?, key.public_key());
+    assert_ne!(encoded, *key.encode());
+
+    // `--public` is still accepted and exports the same key
+    let command = ExportArgs::try_parse_from(["export", "--public"])?.command;
+    assert_eq!(command.public_key(&config)?, key.public_key());
+
+    Ok(())
+}
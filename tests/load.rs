@@ -0,0 +1,181 @@
+use anyhow::Result;
+use futures::future::try_join_all;
+use reqwest::{header, StatusCode};
+use secrecy::SecretString;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use warg_api::v1::{admin::SubmissionQueueResponse, package::PackageError, paths};
+use warg_crypto::signing::PrivateKey;
+use warg_protocol::registry::PackageName;
+use warg_test_fixture::{init_record, TestRegistry, ADMIN_TOKEN};
+
+/// The number of submissions sent at once.
+const FLOOD: usize = 100;
+
+/// The number of floods sent one after another to sustain the load.
+const WAVES: usize = 5;
+
+async fn start() -> Result<TestRegistry> {
+    // A single queued record and no waiting for room make the queue
+    // overflow as soon as submissions arrive faster than they are processed
+    TestRegistry::start_with_config(|config| {
+        config
+            .with_submission_queue_depth(1)
+            .with_submission_timeout(Duration::ZERO)
+            .with_admin_token(SecretString::new(ADMIN_TOKEN.to_string()))
+    })
+    .await
+}
+
+/// Gets the state of the server's submission queue.
+async fn queue(
+    client: &reqwest::Client,
+    registry: &TestRegistry,
+) -> Result<SubmissionQueueResponse> {
+    Ok(client
+        .get(format!(
+            "{url}/{path}",
+            url = registry.url(),
+            path = paths::admin_queue()
+        ))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+/// Submits an init record for the package, returning the response.
+async fn submit(
+    registry: &TestRegistry,
+    name: &PackageName,
+    signing_key: &PrivateKey,
+) -> Result<reqwest::Response> {
+    Ok(registry
+        .submit_record(name, init_record(signing_key)?)
+        .send()
+        .await?)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn server_sheds_load_when_flooded_with_submissions() -> Result<()> {
+    let registry = start().await?;
+
+    let names = (0..FLOOD)
+        .map(|i| PackageName::new(format!("test:flood{i}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let responses = tokio::time::timeout(
+        Duration::from_secs(60),
+        try_join_all(
+            names
+                .iter()
+                .map(|name| submit(&registry, name, registry.publisher_key())),
+        ),
+    )
+    .await
+    .expect("the server should respond to every submission")?;
+
+    // Every submission is either accepted or shed with a retryable 503
+    let mut shed = 0;
+    for response in responses {
+        match response.status() {
+            StatusCode::ACCEPTED => {}
+            StatusCode::SERVICE_UNAVAILABLE => {
+                shed += 1;
+                let retry_after: u64 = response
+                    .headers()
+                    .get(header::RETRY_AFTER)
+                    .expect("response should have a retry-after header")
+                    .to_str()?
+                    .parse()?;
+                assert!(retry_after > 0);
+                let error = response.json::<PackageError>().await?;
+                assert_eq!(error.status(), StatusCode::SERVICE_UNAVAILABLE.as_u16());
+            }
+            status => panic!("unexpected status {status}: {}", response.text().await?),
+        }
+    }
+    assert!(shed > 0, "expected some submissions to be shed");
+    assert!(shed < FLOOD, "expected some submissions to be accepted");
+
+    // The server recovers once the flood passes
+    registry.advance_checkpoint().await?;
+    let name = PackageName::new("test:after")?;
+    let response = submit(&registry, &name, registry.publisher_key()).await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn server_queue_stays_bounded_under_sustained_flood() -> Result<()> {
+    let registry = start().await?;
+    let client = reqwest::Client::new();
+    let done = AtomicBool::new(false);
+
+    // Flood the server wave after wave, faster than records are processed
+    let flood = async {
+        let mut statuses = Vec::new();
+        for wave in 0..WAVES {
+            let names = (0..FLOOD)
+                .map(|i| PackageName::new(format!("test:wave{wave}flood{i}")))
+                .collect::<Result<Vec<_>, _>>()?;
+            for response in try_join_all(
+                names
+                    .iter()
+                    .map(|name| submit(&registry, name, registry.publisher_key())),
+            )
+            .await?
+            {
+                statuses.push(response.status());
+            }
+        }
+        done.store(true, Ordering::SeqCst);
+        anyhow::Ok(statuses)
+    };
+
+    // Sample the queue while the flood lasts
+    let sample = async {
+        let mut samples = Vec::new();
+        while !done.load(Ordering::SeqCst) {
+            samples.push(queue(&client, &registry).await?);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        anyhow::Ok(samples)
+    };
+
+    let (statuses, samples) = tokio::time::timeout(Duration::from_secs(120), async {
+        tokio::try_join!(flood, sample)
+    })
+    .await
+    .expect("the server should respond to every submission")?;
+
+    // The records buffered by the server never exceed the queue's capacity,
+    // however long the flood lasts; the excess is shed instead
+    assert!(!samples.is_empty());
+    for sample in &samples {
+        assert_eq!(sample.capacity, 1);
+        assert!(sample.depth <= sample.capacity, "{sample:?}");
+    }
+
+    let accepted = statuses
+        .iter()
+        .filter(|status| **status == StatusCode::ACCEPTED)
+        .count();
+    let shed = statuses
+        .iter()
+        .filter(|status| **status == StatusCode::SERVICE_UNAVAILABLE)
+        .count();
+    assert_eq!(accepted + shed, WAVES * FLOOD, "{statuses:?}");
+    assert!(shed > 0, "expected some submissions to be shed");
+
+    // Every submission was either queued for processing or rejected
+    let queue = queue(&client, &registry).await?;
+    assert_eq!(queue.submitted, accepted as u64);
+    assert_eq!(queue.rejected, shed as u64);
+
+    Ok(())
+}
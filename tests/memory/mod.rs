@@ -1,8 +1,16 @@
 //! Tests for the in-memory storage backend.
 
 use super::{support::*, *};
-use anyhow::Result;
+use anyhow::{Context, Result};
+use reqwest::{header, StatusCode};
+use std::path::Path;
 use warg_client::api;
+use warg_crypto::hash::{AnyHash, HashAlgorithm};
+use warg_server::{
+    content::{ContentEncryption, MasterKey},
+    datastore::MemoryDataStore,
+};
+use warg_test_fixture::TestRegistry;
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
 async fn it_starts_with_initial_checkpoint() -> Result<()> {
@@ -12,21 +20,45 @@ async fn it_starts_with_initial_checkpoint() -> Result<()> {
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
 async fn it_publishes_a_component() -> Result<()> {
-    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
-    test_component_publishing(&config).await?;
+    let registry = TestRegistry::start().await?;
+    let published = registry
+        .publish_package("test:component", "0.1.0", wat::parse_str("(component)")?)
+        .await?;
+
+    // Assert that the package can be downloaded
+    let client = registry.client()?;
+    client.upsert([&published.name]).await?;
+    let download = client
+        .download(&published.name, &"0.1.0".parse()?)
+        .await?
+        .context("failed to resolve package")?;
+
+    assert_eq!(download.digest, published.digest);
+    assert_eq!(download.version, published.version);
+
+    // Assert that it is a valid component
+    match wit_component::decode(&fs::read(download.path).context("failed to read component")?)? {
+        DecodedWasm::Component(..) => {}
+        _ => panic!("expected component"),
+    }
+
+    // Assert that a different version can't be downloaded
+    assert!(client
+        .download(&published.name, &"0.2.0".parse()?)
+        .await?
+        .is_none());
 
     // There should be two log entries in the registry
-    let client = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
-    let ts_checkpoint = client.latest_checkpoint().await?;
+    let ts_checkpoint = registry.api_client()?.latest_checkpoint().await?;
     assert_eq!(
         ts_checkpoint.as_ref().checkpoint.log_length,
         2,
         "expected two log entries (initial + component)"
     );
 
-    test_fetch_package_names(&config).await?;
+    test_fetch_package_names(&registry.client_config()).await?;
 
-    Ok(())
+    registry.shutdown().await
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
@@ -46,6 +78,174 @@ async fn it_yanks_a_package() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_reports_package_dependencies() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    test_package_dependencies(&config).await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_resolves_package_releases() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    test_package_resolution(&config).await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_keeps_published_records_across_restarts() -> Result<()> {
+    let root = root().await?;
+    let store = MemoryDataStore::default();
+    let name = PackageName::new("test:component")?;
+    let log_id = LogId::package_log::<Sha256>(&name);
+
+    let (record, checkpoint) = {
+        let (_server, config) =
+            spawn_server(&root, None, Some(Box::new(store.clone())), None).await?;
+        test_component_publishing(&config).await?;
+
+        let client = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+        let checkpoint = client.latest_checkpoint().await?.into_contents().checkpoint;
+        let record_id = create_client(&config)?
+            .registry()
+            .load_package(&None, &name)
+            .await?
+            .context("package should be stored by the client")?
+            .state
+            .head()
+            .as_ref()
+            .context("package should have a head")?
+            .digest
+            .clone();
+        (
+            client.get_package_record(&log_id, &record_id).await?,
+            checkpoint,
+        )
+    };
+
+    let (_server, config) = spawn_server(&root, None, Some(Box::new(store)), None).await?;
+    let client = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+
+    let restarted = client
+        .get_package_record(&log_id, &record.record_id)
+        .await?;
+    assert!(matches!(
+        (&record.state, &restarted.state),
+        (
+            PackageRecordState::Published { registry_index: before },
+            PackageRecordState::Published { registry_index: after },
+        ) if before == after
+    ));
+
+    // The checkpoint issued before the restart is still the latest
+    let latest = client.latest_checkpoint().await?.into_contents().checkpoint;
+    assert_eq!(latest, checkpoint);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_lists_packages() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    test_package_listing(&config).await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_encrypts_content_at_rest() -> Result<()> {
+    let root = root().await?;
+    let encryption = ContentEncryption::new(MasterKey::generate());
+    let (_server, config) =
+        spawn_server_with_encryption(&root, None, None, None, Some(encryption)).await?;
+
+    // Publishing and downloading decrypts the content
+    test_component_publishing(&config).await?;
+    test_encrypted_content(&config, &root.join("server/files")).await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_migrates_plaintext_content_to_encryption() -> Result<()> {
+    let root = root().await?;
+    {
+        let (_server, config) = spawn_server(&root, None, None, None).await?;
+        test_component_publishing(&config).await?;
+    }
+
+    let encryption = ContentEncryption::new(MasterKey::generate());
+    let (_server, config) =
+        spawn_server_with_encryption(&root, None, None, None, Some(encryption)).await?;
+    test_encrypted_content(&config, &root.join("server/files")).await
+}
+
+/// Checks that the stored content is encrypted and served decrypted, including ranges.
+async fn test_encrypted_content(config: &warg_client::Config, files_dir: &Path) -> Result<()> {
+    let client = reqwest::Client::new();
+    let mut checked = 0;
+    for entry in std::fs::read_dir(files_dir)? {
+        let path = entry?.path();
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let Ok(digest) = file_name.replacen('-', ":", 1).parse::<AnyHash>() else {
+            continue;
+        };
+
+        let stored = std::fs::read(&path)?;
+        assert!(stored.starts_with(b"WARGENC"), "content is not encrypted");
+
+        let url = format!(
+            "{home}/content/{file_name}",
+            home = config.home_url.as_ref().unwrap()
+        );
+        let response = client.get(&url).send().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let content = response.bytes().await?;
+        assert_eq!(HashAlgorithm::Sha256.digest(&content), digest);
+        assert!(content.len() >= 8);
+
+        let len = content.len();
+        for (range, expected, content_range) in [
+            (
+                "bytes=2-5".to_string(),
+                &content[2..6],
+                format!("bytes 2-5/{len}"),
+            ),
+            (
+                "bytes=-3".to_string(),
+                &content[len - 3..],
+                format!("bytes {start}-{end}/{len}", start = len - 3, end = len - 1),
+            ),
+            (
+                "bytes=1-".to_string(),
+                &content[1..],
+                format!("bytes 1-{end}/{len}", end = len - 1),
+            ),
+        ] {
+            let response = client
+                .get(&url)
+                .header(header::RANGE, &range)
+                .send()
+                .await?;
+            assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT, "{range}");
+            assert_eq!(
+                response
+                    .headers()
+                    .get(header::CONTENT_RANGE)
+                    .context("expected content range")?,
+                content_range.as_str()
+            );
+            assert_eq!(response.bytes().await?.as_ref(), expected, "{range}");
+        }
+
+        let response = client
+            .get(&url)
+            .header(header::RANGE, format!("bytes={len}-"))
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        checked += 1;
+    }
+
+    assert!(checked > 0, "expected content to be stored");
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
 async fn it_publishes_a_wit_package() -> Result<()> {
     let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
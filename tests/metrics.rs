@@ -0,0 +1,56 @@
+use anyhow::Result;
+use reqwest::{header, StatusCode};
+use warg_test_fixture::TestRegistry;
+
+async fn scrape(registry: &TestRegistry) -> Result<String> {
+    let response = reqwest::get(format!("{url}/metrics", url = registry.url())).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map_or(false, |v| v.starts_with("text/plain")));
+    Ok(response.text().await?)
+}
+
+fn sample(metrics: &str, name: &str) -> u64 {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+        .unwrap_or_else(|| panic!("missing metric `{name}`"))
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_reports_published_records_in_metrics() -> Result<()> {
+    let registry = TestRegistry::start().await?;
+
+    let before = scrape(&registry).await?;
+    assert_eq!(sample(&before, "warg_packages"), 0);
+    assert_eq!(sample(&before, "warg_records{status=\"published\"}"), 0);
+
+    let content = wat::parse_str("(component)")?;
+    let len = content.len() as u64;
+    registry
+        .publish_simple("test:metrics", "1.0.0", content)
+        .await?;
+    registry.advance_checkpoint().await?;
+
+    let after = scrape(&registry).await?;
+    assert_eq!(sample(&after, "warg_packages"), 1);
+    assert_eq!(sample(&after, "warg_records{status=\"pending\"}"), 0);
+    assert_eq!(sample(&after, "warg_records{status=\"validated\"}"), 0);
+    assert_eq!(sample(&after, "warg_records{status=\"published\"}"), 1);
+    assert_eq!(sample(&after, "warg_content_stored_bytes_total"), len);
+    assert!(
+        sample(&after, "warg_checkpoints_issued_total")
+            > sample(&before, "warg_checkpoints_issued_total")
+    );
+    assert_eq!(
+        sample(&after, "warg_record_submission_duration_seconds_count"),
+        1
+    );
+
+    Ok(())
+}
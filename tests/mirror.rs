@@ -0,0 +1,198 @@
+use anyhow::{bail, Result};
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::time::Duration;
+use url::Url;
+use warg_api::v1::paths;
+use warg_test_fixture::TestRegistry;
+
+/// The interval at which the mirror syncs with its upstream registry.
+const SYNC_INTERVAL: Duration = Duration::from_millis(500);
+
+async fn get_health(registry: &TestRegistry) -> Result<Value> {
+    Ok(reqwest::get(format!("{url}/health", url = registry.url()))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+/// Waits for the mirror to replicate the upstream's latest checkpoint,
+/// returning the mirror's health.
+async fn wait_for_sync(
+    upstream: &TestRegistry,
+    mirror: &TestRegistry,
+    timeout: Duration,
+) -> Result<Value> {
+    let log_length = upstream
+        .api_client()?
+        .latest_checkpoint()
+        .await?
+        .as_ref()
+        .checkpoint
+        .log_length;
+
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let health = get_health(mirror).await?;
+        if health["mirror"]["logLength"] == log_length as u64 {
+            return Ok(health);
+        }
+
+        if tokio::time::Instant::now() >= deadline {
+            bail!("mirror did not replicate log length {log_length}: {health}");
+        }
+
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_mirrors_upstream_registry() -> Result<()> {
+    let upstream = TestRegistry::start().await?;
+    let first = upstream
+        .publish_package("test:mirrored", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+
+    let upstream_url: Url = upstream.url().parse()?;
+    let mirror = TestRegistry::start_with_config(|config| {
+        config
+            .with_mirror(upstream_url)
+            .with_mirror_sync_interval(SYNC_INTERVAL)
+    })
+    .await?;
+
+    let health = wait_for_sync(&upstream, &mirror, SYNC_INTERVAL * 4).await?;
+    assert_eq!(health["mirror"]["recordsBehind"], 0, "{health}");
+    assert!(health["mirror"]["lastSuccessfulSync"].is_u64(), "{health}");
+    assert!(get_health(&upstream).await?.get("mirror").is_none());
+
+    // The mirror serves the upstream's checkpoint, records, and content
+    let client = mirror.client()?;
+    let download = client.download_exact(&first.name, &first.version).await?;
+    assert_eq!(download.digest, first.digest);
+
+    // A release published to the upstream appears within one sync interval
+    let second = upstream
+        .publish_package("test:mirrored", "1.1.0", wat::parse_str("(component)")?)
+        .await?;
+    wait_for_sync(&upstream, &mirror, SYNC_INTERVAL * 2).await?;
+
+    let client = mirror.client()?;
+    let download = client.download_exact(&second.name, &second.version).await?;
+    assert_eq!(download.digest, second.digest);
+
+    // Publishing directly to the mirror is refused
+    let err = mirror
+        .publish_package("test:direct", "1.0.0", wat::parse_str("(component)")?)
+        .await
+        .expect_err("publishing to a mirror should fail");
+    let message = format!("{err:?}");
+    assert!(message.contains("read-only mirror"), "{message}");
+    assert!(message.contains(upstream.url()), "{message}");
+
+    mirror.shutdown().await?;
+    upstream.shutdown().await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_is_unavailable_before_its_first_checkpoint() -> Result<()> {
+    // A mirror of an unreachable registry never stores a checkpoint
+    let mirror = TestRegistry::start_with_config(|config| {
+        config
+            .with_mirror("http://127.0.0.1:1".parse().unwrap())
+            .with_mirror_sync_interval(SYNC_INTERVAL)
+    })
+    .await?;
+
+    for path in [paths::fetch_checkpoint(), paths::ledger_sources()] {
+        let response = reqwest::get(format!("{url}/{path}", url = mirror.url())).await?;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE, "{path}");
+    }
+
+    // The server keeps serving, reporting why the mirror has not synced
+    let deadline = tokio::time::Instant::now() + SYNC_INTERVAL * 4;
+    loop {
+        let health = get_health(&mirror).await?;
+        assert_eq!(health["mirror"]["logLength"], 0, "{health}");
+        if health["mirror"]["lastError"].is_string() {
+            break;
+        }
+
+        if tokio::time::Instant::now() >= deadline {
+            bail!("mirror did not report a sync error: {health}");
+        }
+
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+
+    mirror.shutdown().await
+}
+
+#[cfg(feature = "grpc")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_mirrors_upstream_registry_over_grpc() -> Result<()> {
+    use indexmap::IndexMap;
+    use std::borrow::Cow;
+    use warg_api::v1::fetch::FetchLogsRequest;
+    use warg_crypto::hash::Sha256;
+    use warg_protocol::registry::LogId;
+
+    // Reserve a port for the upstream's replication service
+    let grpc_addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+    let upstream =
+        TestRegistry::start_with_config(|config| config.with_grpc_addr(grpc_addr)).await?;
+    let first = upstream
+        .publish_package("test:replicated", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+
+    // The mirror never reconnects during the test, so every checkpoint it
+    // replicates after the first is pushed by the upstream
+    let upstream_url: Url = upstream.url().parse()?;
+    let mirror = TestRegistry::start_with_config(|config| {
+        config
+            .with_mirror(upstream_url)
+            .with_mirror_grpc_endpoint(format!("http://{grpc_addr}").parse().unwrap())
+            .with_mirror_sync_interval(Duration::from_secs(3600))
+    })
+    .await?;
+
+    let health = wait_for_sync(&upstream, &mirror, SYNC_INTERVAL * 4).await?;
+    assert_eq!(health["mirror"]["recordsBehind"], 0, "{health}");
+    assert!(health["mirror"].get("lastError").is_none(), "{health}");
+
+    let client = mirror.client()?;
+    let download = client.download_exact(&first.name, &first.version).await?;
+    assert_eq!(download.digest, first.digest);
+
+    let second = upstream
+        .publish_package("test:replicated", "1.1.0", wat::parse_str("(component)")?)
+        .await?;
+    wait_for_sync(&upstream, &mirror, SYNC_INTERVAL * 4).await?;
+
+    let client = mirror.client()?;
+    let download = client.download_exact(&second.name, &second.version).await?;
+    assert_eq!(download.digest, second.digest);
+
+    // The mirror serves the same checkpoint and logs as the upstream
+    let upstream_client = upstream.api_client()?;
+    let mirror_client = mirror.api_client()?;
+    let checkpoint = upstream_client.latest_checkpoint().await?;
+    assert_eq!(mirror_client.latest_checkpoint().await?, checkpoint);
+
+    let packages = IndexMap::from([(LogId::package_log::<Sha256>(&second.name), None)]);
+    let request = || FetchLogsRequest {
+        log_length: checkpoint.as_ref().checkpoint.log_length,
+        operator: None,
+        limit: None,
+        total_limit: None,
+        packages: Cow::Borrowed(&packages),
+    };
+    let expected = serde_json::to_value(upstream_client.fetch_logs(request()).await?)?;
+    let actual = serde_json::to_value(mirror_client.fetch_logs(request()).await?)?;
+    assert_eq!(actual, expected);
+    assert_eq!(actual["packages"].as_object().unwrap().len(), 1, "{actual}");
+
+    mirror.shutdown().await?;
+    upstream.shutdown().await
+}
@@ -4,7 +4,7 @@ use super::{support::*, *};
 use anyhow::{Context, Result};
 use testresult::TestResult;
 use warg_client::api;
-use warg_protocol::registry::RegistryLen;
+use warg_protocol::registry::{RecordId, RegistryLen};
 use warg_server::datastore::{DataStore, PostgresDataStore};
 
 fn data_store() -> Result<Box<dyn DataStore>> {
@@ -47,6 +47,10 @@ async fn it_works_with_postgres() -> TestResult {
     test_component_publishing(&config).await?;
     test_package_yanking(&config).await?;
     test_wit_publishing(&config).await?;
+    test_package_dependencies(&config).await?;
+    test_restored_package_dependencies(&config).await?;
+    test_package_resolution(&config).await?;
+    test_package_listing(&config).await?;
     test_wasm_content_policy(&config).await?;
     test_unauthorized_signing_key(&config).await?;
     test_publishing_name_conflict(&config).await?;
@@ -63,15 +67,22 @@ async fn it_works_with_postgres() -> TestResult {
         PackageName::new("test:wit-package")?,
         PackageName::new("test:unauthorized-key")?,
         PackageName::new("test:name")?,
+        PackageName::new("test:dependent")?,
     ];
 
-    // There should be two log entries in the registry
+    // The resolution and listing tests publish several releases of packages
+    // that are not downloaded below
+    const RESOLUTION_RECORDS: RegistryLen = 5;
+    const LISTING_RECORDS: RegistryLen = 5;
+
+    // There should be a log entry for each package, the initial checkpoint,
+    // the yank, and the resolution and listing tests' releases
     let client = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
     let ts_checkpoint = client.latest_checkpoint().await?;
     assert_eq!(
         ts_checkpoint.as_ref().checkpoint.log_length,
-        packages.len() as RegistryLen + 2, /* publishes + initial checkpoint + yank */
-        "expected {len} packages plus the initial checkpoint and yank",
+        packages.len() as RegistryLen + 2 + RESOLUTION_RECORDS + LISTING_RECORDS,
+        "expected {len} packages plus the initial checkpoint, yank, and other releases",
         len = packages.len()
     );
 
@@ -88,8 +99,8 @@ async fn it_works_with_postgres() -> TestResult {
     let ts_checkpoint = client.latest_checkpoint().await?;
     assert_eq!(
         ts_checkpoint.as_ref().checkpoint.log_length,
-        packages.len() as RegistryLen + 2, /* publishes + initial checkpoint + yank*/
-        "expected {len} packages plus the initial checkpoint and yank",
+        packages.len() as RegistryLen + 2 + RESOLUTION_RECORDS + LISTING_RECORDS,
+        "expected {len} packages plus the initial checkpoint, yank, and other releases",
         len = packages.len()
     );
 
@@ -126,3 +137,37 @@ async fn it_works_with_postgres() -> TestResult {
 
     Ok(())
 }
+
+/// Stores the dependencies of the `test:dependent` release again, as when its
+/// record is resubmitted, and ensures they are not duplicated.
+async fn test_restored_package_dependencies(config: &Config) -> Result<()> {
+    let store = data_store()?;
+    let name = PackageName::new("test:dependent")?;
+    let log_id = LogId::package_log::<Sha256>(&name);
+    let version: Version = "0.1.0".parse()?;
+
+    let client = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+    let log_length = client
+        .latest_checkpoint()
+        .await?
+        .as_ref()
+        .checkpoint
+        .log_length;
+    let page = store
+        .get_package_records(&log_id, log_length, None, 1)
+        .await?;
+    let record = &page.records.first().context("expected a record")?.envelope;
+    let record_id = RecordId::package_record::<Sha256>(record);
+
+    let dependencies = store.get_package_dependencies(&log_id, &version).await?;
+    assert_eq!(dependencies.len(), 2);
+    store
+        .store_package_dependencies(&log_id, &record_id, &version, &dependencies)
+        .await?;
+    assert_eq!(
+        store.get_package_dependencies(&log_id, &version).await?,
+        dependencies
+    );
+
+    Ok(())
+}
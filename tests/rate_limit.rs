@@ -0,0 +1,109 @@
+use anyhow::Result;
+use reqwest::{header, StatusCode};
+use std::num::NonZeroU32;
+use warg_api::v1::{package::PackageError, paths};
+use warg_crypto::{
+    hash::Sha256,
+    signing::{generate_p256_pair, PrivateKey},
+};
+use warg_protocol::{
+    registry::{LogId, PackageName, RecordId},
+    ProtoEnvelopeBody,
+};
+use warg_test_fixture::{init_record, TestRegistry};
+
+const BURST: u32 = 2;
+
+async fn start() -> Result<TestRegistry> {
+    // One record per minute so the bucket does not refill during the test
+    TestRegistry::start_with_config(|config| {
+        config.with_submission_rate_limit(
+            NonZeroU32::new(1).unwrap(),
+            NonZeroU32::new(BURST).unwrap(),
+        )
+    })
+    .await
+}
+
+/// Submits an init record for the package, returning the response and the record's id.
+async fn submit(
+    registry: &TestRegistry,
+    name: &PackageName,
+    signing_key: &PrivateKey,
+) -> Result<(reqwest::Response, RecordId)> {
+    let record = init_record(signing_key)?;
+    let record_id = RecordId::package_record::<Sha256>(&record);
+    let response = registry.submit_record(name, record).send().await?;
+    Ok((response, record_id))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_rate_limits_submissions_per_key() -> Result<()> {
+    let registry = start().await?;
+
+    for i in 0..BURST {
+        let name = PackageName::new(format!("test:allowed{i}"))?;
+        let (response, _) = submit(&registry, &name, registry.publisher_key()).await?;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+    }
+
+    let name = PackageName::new("test:excess")?;
+    let (response, record_id) = submit(&registry, &name, registry.publisher_key()).await?;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .expect("response should have a retry-after header")
+        .to_str()?
+        .parse()?;
+    assert!(retry_after > 0);
+    match response.json::<PackageError>().await? {
+        PackageError::TooManyRequests { retry_after: secs } => assert_eq!(secs, retry_after),
+        e => panic!("unexpected error: {e}"),
+    }
+
+    // The excess record was never stored
+    let response = reqwest::get(format!(
+        "{url}/{path}",
+        url = registry.url(),
+        path = paths::package_record(&LogId::package_log::<Sha256>(&name), &record_id)
+    ))
+    .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Other keys have their own limit
+    let (_, other_key) = generate_p256_pair();
+    let name = PackageName::new("test:other")?;
+    let (response, _) = submit(&registry, &name, &other_key).await?;
+    assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_rate_limits_only_verified_submissions() -> Result<()> {
+    let registry = start().await?;
+    let publisher_key = registry.publisher_key();
+    let (_, other_key) = generate_p256_pair();
+
+    // Records signed by another key but claiming to be signed by the
+    // publisher's key are rejected without using up the publisher's limit
+    for i in 0..=BURST {
+        let name = PackageName::new(format!("test:forged{i}"))?;
+        let mut record = serde_json::to_value(ProtoEnvelopeBody::from(init_record(&other_key)?))?;
+        record["keyId"] = publisher_key.public_key().fingerprint().to_string().into();
+        let record: ProtoEnvelopeBody = serde_json::from_value(record)?;
+        let response = registry.submit_record(&name, record).send().await?;
+        let status = response.status();
+        assert!(status.is_client_error(), "{status}");
+        assert_ne!(status, StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    for i in 0..BURST {
+        let name = PackageName::new(format!("test:genuine{i}"))?;
+        let (response, _) = submit(&registry, &name, publisher_key).await?;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,217 @@
+use anyhow::{Context, Result};
+use reqwest::StatusCode;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use warg_api::v1::{package::PackageError, registry::RecordLimits};
+use warg_client::storage::{ContentStorage, PublishEntry, PublishInfo, RegistryStorage};
+use warg_crypto::{
+    hash::HashAlgorithm,
+    signing::{generate_p256_pair, PrivateKey},
+};
+use warg_protocol::{
+    package::{PackageEntry, PackageRecord, PACKAGE_RECORD_VERSION},
+    registry::PackageName,
+    ProtoEnvelope,
+};
+use warg_server::limits::Limits;
+use warg_test_fixture::TestRegistry;
+
+const MAX_ENTRIES: u32 = 3;
+
+/// Builds an init record with a release for each of the given versions.
+///
+/// The timestamp has no fractional seconds so that records with the same
+/// entries always have the same size.
+fn record(signing_key: &PrivateKey, versions: &[&str]) -> Result<ProtoEnvelope<PackageRecord>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    let content = HashAlgorithm::Sha256.digest(b"content");
+
+    let mut entries = vec![PackageEntry::Init {
+        hash_algorithm: HashAlgorithm::Sha256,
+        key: signing_key.public_key(),
+    }];
+    entries.extend(versions.iter().map(|version| PackageEntry::Release {
+        version: version.parse().unwrap(),
+        content: content.clone(),
+        artifacts: Default::default(),
+        metadata: None,
+    }));
+
+    Ok(ProtoEnvelope::signed_contents(
+        signing_key,
+        PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: UNIX_EPOCH + Duration::from_secs(now.as_secs()),
+            entries,
+        },
+    )?)
+}
+
+async fn submit(
+    registry: &TestRegistry,
+    name: &str,
+    record: ProtoEnvelope<PackageRecord>,
+) -> Result<reqwest::Response> {
+    let name = PackageName::new(name)?;
+    Ok(registry.submit_record(&name, record).send().await?)
+}
+
+async fn rejection(response: reqwest::Response) -> Result<String> {
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    match response.json::<PackageError>().await? {
+        PackageError::Rejection(message) => Ok(message),
+        e => panic!("unexpected error: {e}"),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_enforces_record_limits() -> Result<()> {
+    // Envelopes of the same shape have the same size regardless of the key
+    let (_, key) = generate_p256_pair();
+    let max_size = record(&key, &["1.0.0", "1.0.1"])?.to_protobuf().len() as u64;
+
+    let registry = TestRegistry::start_with_config(|config| {
+        config
+            .with_max_record_size(max_size)
+            .with_max_record_entries(MAX_ENTRIES)
+    })
+    .await?;
+
+    let metadata = registry.api_client()?.registry_metadata().await?;
+    assert_eq!(metadata.record_limits.max_record_size, Some(max_size));
+    assert_eq!(metadata.record_limits.max_record_entries, Some(MAX_ENTRIES));
+
+    // A record right at both limits is accepted
+    let key = registry.publisher_key();
+    let response = submit(
+        &registry,
+        "test:boundary",
+        record(key, &["1.0.0", "1.0.1"])?,
+    )
+    .await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    // A record one byte over the size limit is rejected
+    let response = submit(&registry, "test:large", record(key, &["1.0.0", "1.0.10"])?).await?;
+    let message = rejection(response).await?;
+    assert!(
+        message.contains(&format!(
+            "is {size} bytes, exceeding the maximum record size of {max_size} bytes",
+            size = max_size + 1
+        )),
+        "{message}"
+    );
+
+    // A record with one entry too many is rejected
+    let response = submit(
+        &registry,
+        "test:many",
+        record(key, &["1.0.0", "1.0.1", "1.0.2"])?,
+    )
+    .await?;
+    let message = rejection(response).await?;
+    assert!(
+        message.contains("has 4 entries, exceeding the maximum of 3 entries per record"),
+        "{message}"
+    );
+
+    registry.shutdown().await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_applies_reloaded_limits() -> Result<()> {
+    let (_, key) = generate_p256_pair();
+    let max_size = record(&key, &["1.0.0", "1.0.1"])?.to_protobuf().len() as u64;
+
+    let registry =
+        TestRegistry::start_with_config(|config| config.with_max_record_size(max_size)).await?;
+
+    let key = registry.publisher_key();
+    let response = submit(&registry, "test:before", record(key, &["1.0.0", "1.0.1"])?).await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    // Lowering the limit applies to the next submission of the same size
+    let reloaded = max_size - 1;
+    registry.limits().store(Limits {
+        record: RecordLimits {
+            max_record_size: Some(reloaded),
+            max_record_entries: None,
+        },
+        submission_rate: None,
+    });
+
+    let metadata = registry.api_client()?.registry_metadata().await?;
+    assert_eq!(metadata.record_limits.max_record_size, Some(reloaded));
+
+    let response = submit(&registry, "test:after", record(key, &["1.0.0", "1.0.1"])?).await?;
+    let message = rejection(response).await?;
+    assert!(
+        message.contains(&format!(
+            "is {max_size} bytes, exceeding the maximum record size of {reloaded} bytes"
+        )),
+        "{message}"
+    );
+
+    registry.shutdown().await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_splits_publishes_exceeding_record_limits() -> Result<()> {
+    const RELEASE_COUNT: usize = 4;
+
+    let registry =
+        TestRegistry::start_with_config(|config| config.with_max_record_entries(2)).await?;
+
+    let name = PackageName::new("test:split")?;
+    let client = registry.client()?;
+    let content = wat::parse_str("(component)")?;
+    let digest = client
+        .content()
+        .store_content(
+            Box::pin(futures::stream::once(async move { Ok(content.into()) })),
+            None,
+        )
+        .await?;
+
+    let mut entries = vec![PublishEntry::Init];
+    entries.extend((0..RELEASE_COUNT).map(|i| PublishEntry::Release {
+        version: format!("1.0.{i}").parse().unwrap(),
+        content: digest.clone(),
+        artifacts: Default::default(),
+        metadata: None,
+    }));
+
+    let record_id = client
+        .publish_with_info(
+            registry.publisher_key(),
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries,
+            },
+        )
+        .await?;
+
+    let result = client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(25))
+        .await?;
+    assert!(result.is_published(), "{result:?}");
+
+    // The five entries were published as three records after the initial operator record
+    let checkpoint = registry.api_client()?.latest_checkpoint().await?;
+    assert_eq!(checkpoint.as_ref().checkpoint.log_length, 4);
+
+    client.upsert([&name]).await?;
+    let package = client
+        .registry()
+        .load_package(client.get_warg_registry(), &name)
+        .await?
+        .context("package does not exist in client storage")?;
+    assert_eq!(package.state.releases().count(), RELEASE_COUNT);
+    assert_eq!(
+        package.state.head().as_ref().map(|head| &head.digest),
+        Some(&record_id)
+    );
+
+    registry.shutdown().await
+}
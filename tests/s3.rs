@@ -0,0 +1,72 @@
+//! Tests for the S3-compatible content store.
+//!
+//! Use the `ci/run-s3-tests.sh` script to run these tests against MinIO.
+#![cfg(feature = "s3")]
+
+use anyhow::{Context, Result};
+use reqwest::{header, StatusCode};
+use std::time::{SystemTime, UNIX_EPOCH};
+use warg_server::content::{ContentStore, S3ContentStore, S3Credentials};
+use warg_test_fixture::TestRegistry;
+
+/// Creates a content store for the bucket given by the environment.
+///
+/// Each store uses its own key prefix so tests do not share content.
+fn content_store() -> Result<S3ContentStore> {
+    let endpoint = std::env::var("WARG_S3_ENDPOINT")
+        .context("failed to get `WARG_S3_ENDPOINT` environment variable")?
+        .parse()?;
+    let bucket = std::env::var("WARG_S3_BUCKET")
+        .context("failed to get `WARG_S3_BUCKET` environment variable")?;
+    let prefix = format!(
+        "test-{nanos}",
+        nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos()
+    );
+
+    Ok(S3ContentStore::new(endpoint, bucket, S3Credentials::from_env()?)?.with_prefix(prefix))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_publishes_and_downloads_with_s3() -> Result<()> {
+    let store = content_store()?;
+    let registry = {
+        let store = store.clone();
+        TestRegistry::start_with_config(move |config| config.with_content_store(store)).await?
+    };
+
+    let content = wat::parse_str("(component)")?;
+    let published = registry
+        .publish_package("test:s3", "1.0.0", content.clone())
+        .await?;
+    assert!(store.exists(&published.digest).await?);
+    assert_eq!(store.size(&published.digest).await?, content.len() as u64);
+    let listed = store.list().await?;
+    assert_eq!(listed.len(), 1, "{listed:?}");
+    assert_eq!(listed[0].0, published.digest);
+
+    let client = registry.client()?;
+    let download = client
+        .download_exact(&published.name, &published.version)
+        .await?;
+    assert_eq!(download.digest, published.digest);
+    assert_eq!(std::fs::read(&download.path)?, content);
+
+    // Ranges are served from the object store
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{url}/content/{file_name}",
+            url = registry.url(),
+            file_name = published.digest.to_string().replace(':', "-")
+        ))
+        .header(header::RANGE, "bytes=1-3")
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.bytes().await?.as_ref(), &content[1..4]);
+
+    registry.shutdown().await?;
+
+    store.delete(&published.digest).await?;
+    assert!(!store.exists(&published.digest).await?);
+    Ok(())
+}
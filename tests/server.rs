@@ -12,13 +12,13 @@ use warg_api::v1::{
     content::{ContentSource, ContentSourcesResponse},
     fetch::{FetchPackageNamesRequest, FetchPackageNamesResponse},
     ledger::{LedgerSource, LedgerSourceContentType, LedgerSourcesResponse},
-    package::PublishRecordRequest,
+    package::{ListPackagesQuery, PackageError, PackageRecordState, PublishRecordRequest},
     paths,
 };
 use warg_client::{
     api,
     storage::{PublishEntry, PublishInfo, RegistryStorage},
-    ClientError, Config,
+    ClientError, Config, PublishResult,
 };
 use warg_crypto::{
     hash::{HashAlgorithm, Sha256},
@@ -145,14 +145,249 @@ async fn test_package_yanking(config: &Config) -> Result<()> {
             },
         )
         .await?;
-    client
+    assert!(client
         .wait_for_publish(&name, &record_id, Duration::from_millis(100))
-        .await?;
+        .await?
+        .is_published());
 
     // Assert that the package is yanked
     client.upsert([&name]).await?;
     let opt = client.download(&name, &PACKAGE_VERSION.parse()?).await?;
     assert!(opt.is_none(), "expected no download, got {opt:?}");
+
+    // Assert that the release can't be yanked again
+    // The local pre-check is skipped so that the registry rejects the record
+    let client = client.with_skip_prevalidation(true);
+    let version: Version = PACKAGE_VERSION.parse()?;
+    let res = async {
+        let record_id = client
+            .publish_with_info(
+                &signing_key,
+                PublishInfo {
+                    name: name.clone(),
+                    head: None,
+                    entries: vec![PublishEntry::Yank { version }],
+                },
+            )
+            .await?;
+        client
+            .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+            .await
+    }
+    .await;
+    match res {
+        Ok(PublishResult::Rejected { reason }) => assert_eq!(
+            reason,
+            format!("the package record was invalid: an entry attempted to yank version {PACKAGE_VERSION} which is already yanked")
+        ),
+        res => panic!("expected the second yank to be rejected: {res:?}"),
+    }
+
+    Ok(())
+}
+
+async fn test_package_dependencies(config: &Config) -> Result<()> {
+    const PACKAGE_NAME: &str = "test:dependent";
+    const PACKAGE_VERSION: &str = "0.1.0";
+
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let client = create_client(config)?;
+    let signing_key = test_signing_key();
+    publish_component(
+        &client,
+        &name,
+        PACKAGE_VERSION,
+        r#"(component
+            (import "unlocked-dep=<test:dependency@{>=1.0.0}>" (instance))
+            (import "wasi:cli/environment@0.2.0" (instance))
+        )"#,
+        true,
+        &signing_key,
+    )
+    .await?;
+
+    // Assert the dependencies were derived from the component imports
+    let dependencies = client
+        .remote_dependencies(&name, &PACKAGE_VERSION.parse()?)
+        .await?;
+    assert_eq!(
+        dependencies
+            .iter()
+            .map(|d| d.name.as_ref())
+            .collect::<Vec<_>>(),
+        ["test:dependency", "wasi:cli"]
+    );
+
+    // Assert the reverse dependencies include the release
+    let dependents = client
+        .remote_dependents(&PackageName::new("test:dependency")?)
+        .await?;
+    assert_eq!(dependents.len(), 1);
+    assert_eq!(dependents[0].name, name);
+    assert_eq!(dependents[0].version, PACKAGE_VERSION.parse()?);
+
+    // Assert an unknown version has no dependencies
+    assert!(client
+        .remote_dependencies(&name, &"1.0.0".parse()?)
+        .await?
+        .is_empty());
+
+    Ok(())
+}
+
+async fn test_package_resolution(config: &Config) -> Result<()> {
+    const PACKAGE_NAME: &str = "test:resolved";
+
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let client = create_client(config)?;
+    let signing_key = test_signing_key();
+    let content = wat::parse_str("(component)")?;
+    let digest = publish(&client, &name, "1.0.0", content.clone(), true, &signing_key).await?;
+    for version in ["1.2.0", "1.3.0-beta.1", "2.0.0"] {
+        publish(
+            &client,
+            &name,
+            version,
+            content.clone(),
+            false,
+            &signing_key,
+        )
+        .await?;
+    }
+
+    let api = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+    let req = "^1.0".parse()?;
+
+    // Assert the latest matching release is selected
+    let resolved = api.resolve_package(&name, &req, false).await?;
+    assert_eq!(resolved.version, "1.2.0".parse()?);
+    assert_eq!(resolved.content, digest);
+    if let Some(checkpoint) = &resolved.checkpoint {
+        assert!(checkpoint.as_ref().checkpoint.log_length > resolved.registry_index);
+    }
+
+    let record = api
+        .get_package_record(&LogId::package_log::<Sha256>(&name), &resolved.record_id)
+        .await?;
+    assert!(matches!(
+        record.state,
+        PackageRecordState::Published { registry_index } if registry_index == resolved.registry_index
+    ));
+
+    // Assert prereleases are only selected when requested
+    let resolved = api.resolve_package(&name, &req, true).await?;
+    assert_eq!(resolved.version, "1.3.0-beta.1".parse()?);
+
+    // Assert yanked releases are excluded
+    let record_id = client
+        .publish_with_info(
+            &signing_key,
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::Yank {
+                    version: "1.2.0".parse()?,
+                }],
+            },
+        )
+        .await?;
+    assert!(client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await?
+        .is_published());
+
+    let resolved = api.resolve_package(&name, &req, false).await?;
+    assert_eq!(resolved.version, "1.0.0".parse()?);
+
+    // Assert unmatched requirements and unknown packages are not found
+    match api.resolve_package(&name, &"^3".parse()?, true).await {
+        Err(api::ClientError::Package(PackageError::Message { status: 404, .. })) => {}
+        res => panic!("expected no matching release, got {res:?}"),
+    }
+
+    match api
+        .resolve_package(&PackageName::new("test:unknown")?, &req, false)
+        .await
+    {
+        Err(api::ClientError::Package(PackageError::LogNotFound(_))) => {}
+        res => panic!("expected an unknown package, got {res:?}"),
+    }
+
+    Ok(())
+}
+
+async fn test_package_listing(config: &Config) -> Result<()> {
+    let client = create_client(config)?;
+    let signing_key = test_signing_key();
+    let content = wat::parse_str("(component)")?;
+    // Each package is published with its versions in order and the expected latest version
+    let expected = [
+        ("test:listed-one", &["1.0.0"][..], "1.0.0"),
+        ("test:listed-two", &["1.0.0", "2.0.0", "1.5.0"], "2.0.0"),
+        ("test:listed-three", &["0.1.0"], "0.1.0"),
+    ];
+
+    for (name, versions, _) in &expected {
+        let name = PackageName::new(*name)?;
+        for (i, version) in versions.iter().enumerate() {
+            publish(
+                &client,
+                &name,
+                version,
+                content.clone(),
+                i == 0,
+                &signing_key,
+            )
+            .await?;
+        }
+    }
+
+    let api = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+    let packages = api
+        .list_packages(&ListPackagesQuery::default())
+        .await?
+        .packages;
+
+    for (name, versions, latest) in &expected {
+        let name = PackageName::new(*name)?;
+        let summary = packages
+            .iter()
+            .find(|p| p.name == name)
+            .with_context(|| format!("package `{name}` was not listed"))?;
+        assert_eq!(summary.log_id, LogId::package_log::<Sha256>(&name));
+        assert_eq!(summary.record_count, versions.len() as u64);
+        assert_eq!(summary.latest_version, Some(latest.parse()?));
+    }
+
+    // Assert pages partition the full listing
+    let mut paged = Vec::new();
+    loop {
+        let page = api
+            .list_packages(&ListPackagesQuery {
+                offset: Some(paged.len() as u64),
+                limit: Some(2),
+            })
+            .await?
+            .packages;
+        assert!(page.len() <= 2);
+        if page.is_empty() {
+            break;
+        }
+        paged.extend(page);
+    }
+    assert_eq!(paged, packages);
+
+    match api
+        .list_packages(&ListPackagesQuery {
+            offset: None,
+            limit: Some(0),
+        })
+        .await
+    {
+        Err(api::ClientError::Package(PackageError::Message { status: 400, .. })) => {}
+        res => panic!("expected an invalid limit, got {res:?}"),
+    }
+
     Ok(())
 }
 
@@ -236,19 +471,12 @@ async fn test_wasm_content_policy(config: &Config) -> Result<()> {
                 "content is not valid WebAssembly: unexpected end-of-file (at offset 0x0)"
             );
 
-            // Waiting on the publish should fail with a rejection as well
+            // Waiting on the publish should report the rejection as well
             match client
                 .wait_for_publish(&name, &record_id, Duration::from_millis(100))
-                .await
-                .expect_err("expected wait for publish to fail")
+                .await?
             {
-                ClientError::PublishRejected {
-                    name: rejected_name,
-                    record_id: other,
-                    reason,
-                } => {
-                    assert_eq!(name, rejected_name);
-                    assert_eq!(record_id, other);
+                PublishResult::Rejected { reason } => {
                     assert_eq!(
                         reason,
                         "content with digest `sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855` was rejected by policy: content is not valid WebAssembly: unexpected end-of-file (at offset 0x0)"
@@ -281,6 +509,9 @@ async fn test_unauthorized_signing_key(config: &Config) -> Result<()> {
     )
     .await?;
 
+    // The local pre-check is skipped so that the registry rejects the record
+    let client = client.with_skip_prevalidation(true);
+
     // Next, we're going to publish a new record signed by a different key
     let signing_key = PrivateKey::from(p256::ecdsa::SigningKey::random(&mut OsRng));
 
@@ -317,6 +548,9 @@ async fn test_unknown_signing_key(config: &Config) -> Result<()> {
     )
     .await?;
 
+    // The local pre-check is skipped so that the registry rejects the record
+    let client = client.with_skip_prevalidation(true);
+
     // Next, we're going to publish a new record signed by a different key
     // The new key is not currently known to the package log.
     let signing_key = PrivateKey::from(p256::ecdsa::SigningKey::random(&mut OsRng));
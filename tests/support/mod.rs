@@ -20,6 +20,7 @@ use warg_crypto::{
 };
 use warg_protocol::{operator, registry::PackageName};
 use warg_server::{
+    content::ContentEncryption,
     datastore::DataStore,
     policy::{content::WasmContentPolicy, record::AuthorizedKeyPolicy},
     Config, Server,
@@ -117,6 +118,17 @@ pub async fn spawn_server(
     content_base_url: Option<Url>,
     data_store: Option<Box<dyn DataStore>>,
     authorized_keys: Option<Vec<(String, KeyID)>>,
+) -> Result<(ServerInstance, warg_client::Config)> {
+    spawn_server_with_encryption(root, content_base_url, data_store, authorized_keys, None).await
+}
+
+/// Spawns a server as a background task, optionally encrypting its content at rest.
+pub async fn spawn_server_with_encryption(
+    root: &Path,
+    content_base_url: Option<Url>,
+    data_store: Option<Box<dyn DataStore>>,
+    authorized_keys: Option<Vec<(String, KeyID)>>,
+    content_encryption: Option<ContentEncryption>,
 ) -> Result<(ServerInstance, warg_client::Config)> {
     let _subscriber_guard = thread_test_logging();
 
@@ -127,6 +139,10 @@ pub async fn spawn_server(
         .with_checkpoint_interval(Duration::from_millis(100))
         .with_content_policy(WasmContentPolicy::default()); // For the tests, we assume only wasm content is allowed.
 
+    if let Some(encryption) = content_encryption {
+        config = config.with_content_encryption(encryption);
+    }
+
     if let Some(content_url) = content_base_url {
         config = config.with_content_base_url(content_url);
     }
@@ -162,11 +178,21 @@ pub async fn spawn_server(
 
     let config = warg_client::Config {
         home_url: Some(format!("http://{addr}")),
+        registries: Default::default(),
+        namespace_registries: Default::default(),
         registries_dir: Some(root.join("registries")),
         content_dir: Some(root.join("content")),
+        content_fallback_dirs: Vec::new(),
         namespace_map_path: Some(root.join("namespaces")),
+        trust_policy_path: Some(root.join("trust.json")),
         keys: IndexSet::new(),
+        default_key: None,
         keyring_auth: false,
+        stale_checkpoint: Default::default(),
+        checkpoint_grace_period: None,
+        offline: false,
+        retry: None,
+        storage: Default::default(),
     };
 
     Ok((instance, config))
@@ -195,6 +221,8 @@ pub async fn publish(
     entries.push(PublishEntry::Release {
         version: version.parse().unwrap(),
         content: digest.clone(),
+        artifacts: Default::default(),
+        metadata: None,
     });
 
     let record_id = client
@@ -208,9 +236,10 @@ pub async fn publish(
         )
         .await?;
 
-    client
+    assert!(client
         .wait_for_publish(name, &record_id, Duration::from_millis(100))
-        .await?;
+        .await?
+        .is_published());
 
     Ok(digest)
 }
@@ -0,0 +1,113 @@
+//! Tests for correlating the events of a request.
+
+use anyhow::{Context, Result};
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+use tracing_subscriber::fmt::MakeWriter;
+use warg_api::v1::{paths, REQUEST_ID_HEADER_NAME};
+use warg_test_fixture::TestRegistry;
+
+/// Captures the output of the tracing subscriber.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8_lossy(&self.0.lock().unwrap())
+            .lines()
+            .map(ToString::to_string)
+            .collect()
+    }
+}
+
+impl io::Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Output {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// Gets the value of the given field in a line of output.
+fn field<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let start = line.find(&format!("{name}="))? + name.len() + 1;
+    let value = &line[start..];
+    let end = value.find([' ', '}']).unwrap_or(value.len());
+    Some(value[..end].trim_matches('"'))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_correlates_publish_events() -> Result<()> {
+    let output = Output::default();
+    tracing_subscriber::fmt()
+        .with_env_filter("warg_server=info,tower_http=info")
+        .with_writer(output.clone())
+        .with_ansi(false)
+        .init();
+
+    let registry = TestRegistry::start().await?;
+    let published = registry
+        .publish_package("test:traced", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+    let record_id = published.record_id.to_string();
+
+    let lines = output.lines();
+    let states = |state: &str| {
+        lines
+            .iter()
+            .filter(|line| line.contains("package record state changed"))
+            .filter(|line| field(line, "record_id") == Some(record_id.as_str()))
+            .filter(|line| line.ends_with(&format!("state=\"{state}\"")))
+            .collect::<Vec<_>>()
+    };
+
+    // The record is published in the span of the request that submitted it
+    // for processing, which follows the record's submission
+    for state in ["sourcing", "processing", "validated", "published"] {
+        assert_eq!(states(state).len(), 1, "{state}: {lines:#?}");
+    }
+
+    let published = states("published")[0];
+    let request_id = field(published, "request_id").context("expected a request ID")?;
+    assert!(!request_id.is_empty(), "{published}");
+    assert!(published.contains("process_record{"), "{published}");
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.contains("starting") && line.contains(request_id)),
+        "{lines:#?}"
+    );
+    assert!(states("processing")[0].contains(request_id), "{lines:#?}");
+
+    // Requests are assigned an ID unless the client provides one
+    let url = format!(
+        "{url}/{path}",
+        url = registry.url(),
+        path = paths::fetch_checkpoint()
+    );
+    let response = reqwest::get(&url).await?.error_for_status()?;
+    assert!(response.headers().contains_key(REQUEST_ID_HEADER_NAME));
+
+    let response = reqwest::Client::new()
+        .get(&url)
+        .header(REQUEST_ID_HEADER_NAME, "my-request")
+        .send()
+        .await?
+        .error_for_status()?;
+    assert_eq!(response.headers()[REQUEST_ID_HEADER_NAME], "my-request");
+
+    registry.shutdown().await
+}
@@ -0,0 +1,309 @@
+use anyhow::{Context, Result};
+use axum::{
+    body::Bytes,
+    http::{HeaderMap, StatusCode},
+};
+use hmac::{Hmac, Mac};
+use secrecy::SecretString;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+use url::Url;
+use warg_api::v1::{
+    admin::{WebhookDeliveriesResponse, WebhookDelivery, WebhookDeliveryStatus},
+    paths,
+    webhook::{
+        WebhookEntry, WebhookPayload, WEBHOOK_DELIVERY_HEADER_NAME, WEBHOOK_SIGNATURE_HEADER_NAME,
+    },
+};
+use warg_crypto::hash::{AnyHash, Hash, Sha256};
+use warg_server::services::Webhook;
+use warg_test_fixture::{TestRegistry, ADMIN_TOKEN};
+
+const WEBHOOK_SECRET: &str = "webhook-secret";
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A request received by the webhook sink.
+struct Received {
+    at: Instant,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl Received {
+    /// Verifies the signature of the request and returns its payload.
+    fn verify(&self) -> Result<WebhookPayload> {
+        let signature = self
+            .headers
+            .get(WEBHOOK_SIGNATURE_HEADER_NAME)
+            .context("missing signature header")?
+            .to_str()?
+            .strip_prefix("sha256=")
+            .context("signature should be prefixed with the algorithm")?;
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(WEBHOOK_SECRET.as_bytes())?;
+        mac.update(&self.body);
+        mac.verify_slice(&hex::decode(signature)?)
+            .context("signature does not match")?;
+
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+
+    fn delivery_id(&self) -> &str {
+        self.headers[WEBHOOK_DELIVERY_HEADER_NAME].to_str().unwrap()
+    }
+}
+
+/// Starts a webhook sink that forwards requests to the returned channel,
+/// responding with `500 Internal Server Error` while `failing` is set.
+async fn start_sink(failing: Arc<AtomicBool>) -> Result<(Url, mpsc::UnboundedReceiver<Received>)> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = Url::parse(&format!(
+        "http://{addr}/hook",
+        addr = listener.local_addr()?
+    ))?;
+    let (tx, rx) = mpsc::unbounded_channel();
+    let receive = move |headers: HeaderMap, body: Bytes| {
+        let (tx, failing) = (tx.clone(), failing.clone());
+        async move {
+            tx.send(Received {
+                at: Instant::now(),
+                headers,
+                body,
+            })
+            .ok();
+            if failing.load(Ordering::SeqCst) {
+                StatusCode::INTERNAL_SERVER_ERROR
+            } else {
+                StatusCode::NO_CONTENT
+            }
+        }
+    };
+
+    tokio::spawn(async move { axum::serve(listener, axum::Router::new().fallback(receive)).await });
+    Ok((url, rx))
+}
+
+async fn receive(rx: &mut mpsc::UnboundedReceiver<Received>) -> Result<Received> {
+    tokio::time::timeout(RECEIVE_TIMEOUT, rx.recv())
+        .await
+        .context("timed out waiting for a webhook delivery")?
+        .context("the webhook sink stopped")
+}
+
+async fn deliveries(registry: &TestRegistry) -> Result<Vec<WebhookDelivery>> {
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{url}/{path}",
+            url = registry.url(),
+            path = paths::admin_webhook_deliveries()
+        ))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await?;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    Ok(response
+        .json::<WebhookDeliveriesResponse>()
+        .await?
+        .deliveries)
+}
+
+/// Waits until the only delivery has the given status.
+async fn wait_for_delivery(
+    registry: &TestRegistry,
+    status: WebhookDeliveryStatus,
+) -> Result<WebhookDelivery> {
+    let deadline = Instant::now() + RECEIVE_TIMEOUT;
+    loop {
+        let mut deliveries = deliveries(registry).await?;
+        assert_eq!(deliveries.len(), 1);
+        if deliveries[0].status == status {
+            return Ok(deliveries.remove(0));
+        }
+
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting for the delivery to be {status:?}"
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+async fn retry(registry: &TestRegistry, id: &str) -> Result<reqwest::Response> {
+    Ok(reqwest::Client::new()
+        .post(format!(
+            "{url}/{path}",
+            url = registry.url(),
+            path = paths::admin_webhook_delivery_retry(id)
+        ))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await?)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_delivers_signed_webhooks_for_matching_packages() -> Result<()> {
+    let (url, mut rx) = start_sink(Default::default()).await?;
+    let registry = TestRegistry::start_with_config(|config| {
+        config
+            .with_admin_token(SecretString::new(ADMIN_TOKEN.to_string()))
+            .with_webhook(
+                Webhook::new(url, SecretString::new(WEBHOOK_SECRET.to_string()))
+                    .with_package("test:hooked"),
+            )
+    })
+    .await?;
+
+    let bytes = wat::parse_str("(component)")?;
+    let digest = registry
+        .publish_simple("test:hooked", "1.0.0", bytes.clone())
+        .await?;
+    let checkpoint = registry
+        .api_client()?
+        .latest_checkpoint()
+        .await?
+        .as_ref()
+        .checkpoint
+        .clone();
+
+    let received = receive(&mut rx).await?;
+    let payload = received.verify()?;
+    assert_eq!(payload.package.as_ref(), "test:hooked");
+    assert_eq!(
+        payload.checkpoint,
+        AnyHash::from(Hash::<Sha256>::of(&checkpoint))
+    );
+    assert!(payload.registry_index < checkpoint.log_length);
+    assert_eq!(
+        payload.entries,
+        [WebhookEntry::Release {
+            version: "1.0.0".parse()?,
+            content: digest,
+        }]
+    );
+
+    // A tampered payload does not verify
+    let tampered = Received {
+        body: Bytes::from(received.body.iter().copied().rev().collect::<Vec<_>>()),
+        ..received
+    };
+    assert!(tampered.verify().is_err());
+
+    // Packages not matching the webhook's filter are not delivered
+    registry
+        .publish_simple("test:other", "1.0.0", bytes)
+        .await?;
+    assert!(
+        tokio::time::timeout(Duration::from_millis(500), rx.recv())
+            .await
+            .is_err(),
+        "unexpected delivery for an unmatched package"
+    );
+
+    let delivery = wait_for_delivery(&registry, WebhookDeliveryStatus::Delivered).await?;
+    assert_eq!(delivery.id, tampered.delivery_id());
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.payload, payload);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_retries_failed_webhook_deliveries() -> Result<()> {
+    const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+    let failing = Arc::new(AtomicBool::new(true));
+    let (url, mut rx) = start_sink(failing.clone()).await?;
+    let dir = tempfile::tempdir()?;
+    let dead_letter_file = dir.path().join("dead-letter.jsonl");
+    let registry = TestRegistry::start_with_config(|config| {
+        config
+            .with_admin_token(SecretString::new(ADMIN_TOKEN.to_string()))
+            .with_webhook(Webhook::new(
+                url,
+                SecretString::new(WEBHOOK_SECRET.to_string()),
+            ))
+            .with_webhook_max_attempts(3)
+            .with_webhook_retry_delay(RETRY_DELAY)
+            .with_webhook_dead_letter_file(&dead_letter_file)
+    })
+    .await?;
+
+    registry
+        .publish_simple("test:flaky", "1.0.0", wat::parse_str("(component)")?)
+        .await?;
+
+    // Each attempt is delayed twice as long as the previous one
+    let attempts = [
+        receive(&mut rx).await?,
+        receive(&mut rx).await?,
+        receive(&mut rx).await?,
+    ];
+    for attempt in &attempts {
+        attempt.verify()?;
+        assert_eq!(attempt.delivery_id(), attempts[0].delivery_id());
+    }
+    assert!(attempts[1].at - attempts[0].at >= RETRY_DELAY);
+    assert!(attempts[2].at - attempts[1].at >= RETRY_DELAY * 2);
+
+    let failed = wait_for_delivery(&registry, WebhookDeliveryStatus::Failed).await?;
+    assert_eq!(failed.id, attempts[0].delivery_id());
+    assert_eq!(failed.attempts, 3);
+    assert!(failed.last_error.unwrap().contains("500"));
+
+    // The failed delivery is recorded in the dead-letter file
+    let deadline = Instant::now() + RECEIVE_TIMEOUT;
+    let dead_letter = loop {
+        let contents = tokio::fs::read_to_string(&dead_letter_file)
+            .await
+            .unwrap_or_default();
+        if !contents.is_empty() || Instant::now() >= deadline {
+            break contents;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    };
+    let lines = dead_letter.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 1);
+    let dead: WebhookDelivery = serde_json::from_str(lines[0])?;
+    assert_eq!(dead.id, failed.id);
+    assert_eq!(dead.status, WebhookDeliveryStatus::Failed);
+
+    // Retrying the failed delivery through the administration API delivers it
+    failing.store(false, Ordering::SeqCst);
+    let response = retry(&registry, &failed.id).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let retried: WebhookDelivery = response.json().await?;
+    assert_eq!(retried.status, WebhookDeliveryStatus::Pending);
+
+    let attempt = receive(&mut rx).await?;
+    assert_eq!(attempt.delivery_id(), failed.id);
+    let delivered = wait_for_delivery(&registry, WebhookDeliveryStatus::Delivered).await?;
+    assert_eq!(delivered.attempts, 1);
+    assert_eq!(delivered.last_error, None);
+
+    // Only failed deliveries may be retried
+    let response = retry(&registry, &failed.id).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::CONFLICT);
+    let response = retry(&registry, "unknown").await?;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn server_without_webhooks_rejects_delivery_requests() -> Result<()> {
+    let registry = TestRegistry::start_with_config(|config| {
+        config.with_admin_token(SecretString::new(ADMIN_TOKEN.to_string()))
+    })
+    .await?;
+
+    let response = retry(&registry, "unknown").await?;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_IMPLEMENTED);
+
+    Ok(())
+}